@@ -0,0 +1,43 @@
+//! Benchmark of the proof generation.
+//!
+//! Parallel parts of the proof generation are run by the `bellman` worker, so the amount of threads
+//! is set with the `BELLMAN_NUM_CPUS` environment variable (all the CPU cores by default);
+//! compare the runs with its different values.
+//!
+//! Requires the keys for the smallest supported block size to be present in `KEY_DIR`.
+
+// External deps
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+// Workspace deps
+use models::{
+    config_options::ConfigurationOptions,
+    prover_utils::{PlonkVerificationKey, SetupForStepByStepProver},
+};
+use prover::testing;
+
+fn bench_proof_gen(c: &mut Criterion) {
+    let block_size_chunks = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let circuit = testing::deposit_block_prover_data(block_size_chunks).into_circuit(1);
+    let setup =
+        SetupForStepByStepProver::prepare_setup_for_step_by_step_prover(circuit.clone(), false)
+            .expect("failed to prepare setup");
+    let vk = PlonkVerificationKey::read_verification_key_for_main_circuit(block_size_chunks)
+        .expect("failed to read verification key");
+
+    let mut group = c.benchmark_group("proof_gen");
+    // Every iteration takes seconds, so the default amount of samples is too large.
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::from_parameter(block_size_chunks), |b| {
+        b.iter(|| {
+            setup
+                .gen_step_by_step_proof_using_prepared_setup(circuit.clone(), &vk)
+                .expect("failed to generate proof")
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(proof_gen_benches, bench_proof_gen);
+criterion_main!(proof_gen_benches);
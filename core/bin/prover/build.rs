@@ -0,0 +1,4 @@
+fn main() {
+    #[cfg(feature = "grpc-client")]
+    tonic_build::compile_protos("proto/prover.proto").expect("failed to compile prover protos");
+}
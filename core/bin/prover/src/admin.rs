@@ -0,0 +1,94 @@
+//! Admin endpoint of the prover, allowing to tune the prover options without restart.
+//!
+//! `GET /config` returns the current values of the reconfigurable options,
+//! `POST /config` updates the options present in the request body, e.g.
+//! `{"heartbeat_interval_ms": 1000, "cycle_wait_ms": 500}`.
+
+// Built-in deps
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+// External deps
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde::{Deserialize, Serialize};
+// Workspace deps
+use models::config_options::ProverOptions;
+// Local deps
+use crate::SharedProverOptions;
+
+/// Update of the prover options, absent fields are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigUpdate {
+    pub heartbeat_interval_ms: Option<u64>,
+    pub cycle_wait_ms: Option<u64>,
+}
+
+impl ConfigUpdate {
+    /// Returns the options with the update applied.
+    pub fn apply(&self, options: &ProverOptions) -> ProverOptions {
+        let mut options = options.clone();
+        if let Some(heartbeat_interval_ms) = self.heartbeat_interval_ms {
+            options.heartbeat_interval = Duration::from_millis(heartbeat_interval_ms);
+        }
+        if let Some(cycle_wait_ms) = self.cycle_wait_ms {
+            options.cycle_wait = Duration::from_millis(cycle_wait_ms);
+        }
+        options
+    }
+}
+
+/// Current values of the reconfigurable options.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrentConfig {
+    pub heartbeat_interval_ms: u64,
+    pub cycle_wait_ms: u64,
+}
+
+impl From<&ProverOptions> for CurrentConfig {
+    fn from(options: &ProverOptions) -> Self {
+        Self {
+            heartbeat_interval_ms: options.heartbeat_interval.as_millis() as u64,
+            cycle_wait_ms: options.cycle_wait.as_millis() as u64,
+        }
+    }
+}
+
+fn get_config(options: web::Data<SharedProverOptions>) -> HttpResponse {
+    HttpResponse::Ok().json(CurrentConfig::from(options.load().as_ref()))
+}
+
+fn update_config(
+    options: web::Data<SharedProverOptions>,
+    update: web::Json<ConfigUpdate>,
+) -> HttpResponse {
+    options.rcu(|current| update.apply(current));
+    log::info!(
+        "prover options updated through admin endpoint: {:?}",
+        update.into_inner()
+    );
+    HttpResponse::Ok().json(CurrentConfig::from(options.load().as_ref()))
+}
+
+/// Starts the admin server on a separate thread.
+pub fn start_admin_server(
+    options: SharedProverOptions,
+    addr: SocketAddr,
+) -> thread::JoinHandle<()> {
+    thread::Builder::new()
+        .name("prover_admin".to_string())
+        .spawn(move || {
+            HttpServer::new(move || {
+                App::new()
+                    .data(Arc::clone(&options))
+                    .route("/config", web::get().to(get_config))
+                    .route("/config", web::post().to(update_config))
+            })
+            .workers(1)
+            .bind(addr)
+            .expect("failed to bind admin server")
+            .run()
+            .expect("admin server failed");
+        })
+        .expect("failed to start admin server thread")
+}
@@ -1,13 +1,92 @@
-use models::config_options::get_env;
-use models::prover_utils::EncodedProofPlonk;
-use prover::cli_utils::main_for_prover_impl;
-use prover::{ApiClient, BabyProverError, ProverConfig, ProverImpl};
-use std::sync::mpsc;
-use std::time::Duration;
+use clap::{Arg, ArgMatches};
+use models::config_options::{get_env, ProverOptions};
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
+use prover::cli_utils::{main_for_prover_impl, ProverApiClient};
+use prover::metrics::ProverMetrics;
+use prover::{
+    logging, ApiClient, BabyProverError, HeartbeatRequest, ProverConfig, ProverImpl, ProvingStage,
+    RetryPolicy, RoundOutcome, ShutdownRequest, PROVER_VERSION,
+};
+use rand::Rng;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Failures injected by the dummy prover, to exercise the retry and re-assignment logic
+/// of the provers and the server without a real circuit.
+#[derive(Debug, Default)]
+pub struct FailureInjection {
+    /// Probability of dropping the proof instead of publishing it.
+    pub failure_rate: f64,
+    /// Range of the simulated proof time, in milliseconds.
+    pub delay_ms: Option<RangeInclusive<u64>>,
+    /// Blocks the proofs are always dropped for.
+    pub fail_blocks: HashSet<i64>,
+}
+
+impl FailureInjection {
+    fn should_fail(&self, block: i64) -> bool {
+        self.fail_blocks.contains(&block) || rand::thread_rng().gen_bool(self.failure_rate)
+    }
+
+    fn proof_delay(&self) -> Duration {
+        match &self.delay_ms {
+            Some(delay_ms) => Duration::from_millis(
+                rand::thread_rng().gen_range(delay_ms.start(), delay_ms.end() + 1),
+            ),
+            None => Duration::from_millis(0),
+        }
+    }
+}
+
+fn parse_failure_rate(rate: &str) -> Result<f64, String> {
+    let rate: f64 = rate
+        .parse()
+        .map_err(|e| format!("invalid failure rate {}: {}", rate, e))?;
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(format!("failure rate must be in [0, 1], got {}", rate));
+    }
+    Ok(rate)
+}
+
+/// Parses the delay range in the `MIN:MAX` format, both ends included.
+fn parse_delay_ms(delay: &str) -> Result<RangeInclusive<u64>, String> {
+    let mut bounds = delay.splitn(2, ':');
+    let mut parse_bound = || -> Result<u64, String> {
+        let bound = bounds
+            .next()
+            .ok_or_else(|| format!("delay must be in the MIN:MAX format, got {}", delay))?;
+        bound
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid delay bound {}: {}", bound, e))
+    };
+    let (min, max) = (parse_bound()?, parse_bound()?);
+    if min > max {
+        return Err(format!("delay min {} exceeds max {}", min, max));
+    }
+    Ok(min..=max)
+}
+
+fn parse_fail_blocks(blocks: &str) -> Result<HashSet<i64>, String> {
+    blocks
+        .split(',')
+        .map(|block| {
+            block
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid block number {}: {}", block, e))
+        })
+        .collect()
+}
 
 #[derive(Debug)]
 pub struct DummyProverConfig {
     pub block_sizes: Vec<usize>,
+    pub failure_injection: FailureInjection,
+    pub retry_policy: RetryPolicy,
 }
 
 impl ProverConfig for DummyProverConfig {
@@ -17,6 +96,39 @@ impl ProverConfig for DummyProverConfig {
                 .split(',')
                 .map(|p| p.parse().unwrap())
                 .collect(),
+            failure_injection: FailureInjection::default(),
+            retry_policy: RetryPolicy::from_options(&ProverOptions::from_env()),
+        }
+    }
+
+    fn cli_args() -> Vec<Arg<'static, 'static>> {
+        vec![
+            Arg::with_name("failure_rate")
+                .long("failure-rate")
+                .takes_value(true)
+                .help("Probability of dropping the proof instead of publishing it, e.g. 0.3"),
+            Arg::with_name("delay_ms")
+                .long("delay-ms")
+                .takes_value(true)
+                .value_name("MIN:MAX")
+                .help("Range of the simulated proof time, in milliseconds"),
+            Arg::with_name("fail_blocks")
+                .long("fail-blocks")
+                .takes_value(true)
+                .help("Comma-separated blocks the proofs are always dropped for"),
+        ]
+    }
+
+    fn apply_cli_args(&mut self, matches: &ArgMatches<'_>) {
+        let failure_injection = &mut self.failure_injection;
+        if let Some(rate) = matches.value_of("failure_rate") {
+            failure_injection.failure_rate = parse_failure_rate(rate).expect("--failure-rate");
+        }
+        if let Some(delay) = matches.value_of("delay_ms") {
+            failure_injection.delay_ms = Some(parse_delay_ms(delay).expect("--delay-ms"));
+        }
+        if let Some(blocks) = matches.value_of("fail_blocks") {
+            failure_injection.fail_blocks = parse_fail_blocks(blocks).expect("--fail-blocks");
         }
     }
 }
@@ -26,6 +138,17 @@ struct DummyProver<C> {
     api_client: C,
     heartbeat_interval: Duration,
     config: DummyProverConfig,
+    metrics: Option<Arc<ProverMetrics>>,
+}
+
+impl<C> DummyProver<C> {
+    /// Records the failed request to the prover server and converts it into the prover error.
+    fn api_error(&self, err: failure::Error) -> BabyProverError {
+        if let Some(metrics) = &self.metrics {
+            metrics.api_error();
+        }
+        BabyProverError::from_api(err)
+    }
 }
 
 impl<C: ApiClient> ProverImpl<C> for DummyProver<C> {
@@ -35,71 +158,103 @@ impl<C: ApiClient> ProverImpl<C> for DummyProver<C> {
         config: DummyProverConfig,
         api_client: C,
         heartbeat_interval: Duration,
+        metrics: Option<Arc<ProverMetrics>>,
     ) -> Self {
         DummyProver {
             api_client,
             heartbeat_interval,
             config,
+            metrics,
         }
     }
 
     fn next_round(
         &self,
-        start_heartbeats_tx: mpsc::Sender<(i32, bool)>,
-    ) -> Result<(), BabyProverError> {
-        let mut block = 0;
-        let mut job_id = 0;
+        start_heartbeats_tx: mpsc::Sender<HeartbeatRequest>,
+        _shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError> {
+        let mut job = None;
 
         for block_size in &self.config.block_sizes {
-            let block_to_prove = self.api_client.block_to_prove(*block_size).map_err(|e| {
-                let e = format!("failed to get block to prove {}", e);
-                BabyProverError::Api(e)
-            })?;
-
-            let (current_request_block, current_request_job_id) =
-                block_to_prove.unwrap_or_else(|| {
-                    log::trace!("no block to prove from the server for size: {}", block_size);
-                    (0, 0)
-                });
-
-            if current_request_job_id != 0 {
-                block = current_request_block;
-                job_id = current_request_job_id;
+            let block_to_prove = self
+                .api_client
+                .block_to_prove(*block_size)
+                .map_err(|e| self.api_error(e))?;
+
+            if block_to_prove.is_some() {
+                job = block_to_prove;
                 break;
             }
+            log::trace!("no block to prove from the server for size: {}", block_size);
         }
 
         // Notify heartbeat routine on new proving block job or None.
-        start_heartbeats_tx
-            .send((job_id, false))
-            .expect("failed to send new job to heartbeat routine");
-        if job_id == 0 {
-            return Ok(());
-        }
+        start_heartbeats_tx.send(HeartbeatRequest::Job(job.map(|(_, job_id)| job_id)))?;
+        let (block, job_id) = match job {
+            Some(job) => job,
+            None => return Ok(RoundOutcome::NoJob),
+        };
+        logging::set_round_job(block, job_id);
 
         log::info!("got job id: {}, block {}", job_id, block);
-        let _instance = self.api_client.prover_data(block).map_err(|err| {
-            BabyProverError::Api(format!(
-                "could not get prover data for block {}: {}",
-                block, err
-            ))
-        })?;
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::FetchingData))?;
+        let _instance = self
+            .api_client
+            .prover_data(block)
+            .map_err(|e| self.api_error(e))?;
 
         log::info!("starting to compute proof for block {}", block,);
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::ProofGeneration))?;
+        if let Some(metrics) = &self.metrics {
+            metrics.proof_started();
+        }
+        let proof_started_at = Instant::now();
+        thread::sleep(self.config.failure_injection.proof_delay());
+        let proof = EncodedProofPlonk::default();
+        let proving_time = proof_started_at.elapsed();
+        let failed = self.config.failure_injection.should_fail(block);
+        if let Some(metrics) = &self.metrics {
+            metrics.proof_finished(proving_time, !failed);
+        }
+        if failed {
+            // Proof is dropped, so the job is re-assigned once its heartbeats time out.
+            log::error!(
+                "injected failure: proof for block {} is not published",
+                block
+            );
+            return Ok(RoundOutcome::JobAbandoned);
+        }
 
-        self.api_client
-            .publish(block, EncodedProofPlonk::default())
-            .map_err(|e| BabyProverError::Api(format!("failed to publish proof: {}", e)))?;
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::Publishing))?;
+        let publish_started_at = Instant::now();
+        let stats = ProvingStats {
+            proving_time_secs: Some(proving_time.as_secs_f64()),
+            prover_version: Some(PROVER_VERSION.to_string()),
+            ..Default::default()
+        };
+        let published = self.api_client.publish_with_stats(block, proof, stats);
+        if let Some(metrics) = &self.metrics {
+            metrics.publish_finished(publish_started_at.elapsed(), published.is_ok());
+        }
+        published.map_err(|e| BabyProverError::from_publish(block, e))?;
 
         log::info!("finished and published proof for block {}", block);
-        Ok(())
+        Ok(RoundOutcome::JobDone)
     }
 
     fn get_heartbeat_options(&self) -> (&C, Duration) {
         (&self.api_client, self.heartbeat_interval)
     }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.config.retry_policy
+    }
+
+    fn supported_block_sizes(&self) -> Vec<usize> {
+        self.config.block_sizes.clone()
+    }
 }
 
 fn main() {
-    main_for_prover_impl::<DummyProver<prover::client::ApiClient>>();
+    main_for_prover_impl::<DummyProver<ProverApiClient>>();
 }
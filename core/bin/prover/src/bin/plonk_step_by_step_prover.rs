@@ -1,6 +1,7 @@
-use prover::cli_utils::main_for_prover_impl;
+use prover::cli_utils::{main_for_prover_impl, ProverApiClient};
+use prover::parallel_prover::ParallelProver;
 use prover::plonk_step_by_step_prover::PlonkStepByStepProver;
 
 fn main() {
-    main_for_prover_impl::<PlonkStepByStepProver<prover::client::ApiClient>>();
+    main_for_prover_impl::<ParallelProver<PlonkStepByStepProver<ProverApiClient>>>();
 }
@@ -0,0 +1,102 @@
+//! Measures the proving performance of the machine on the synthetic blocks, without the server.
+//!
+//! For every block size, proves the block with a single deposit `--iterations` times
+//! and prints the timings as JSON (one line per block size).
+//! Requires the keys for the benchmarked block sizes to be present in `KEY_DIR`.
+
+// Built-in deps
+use std::time::{Duration, Instant};
+// External deps
+use clap::{App, Arg};
+use serde_json::json;
+// Workspace deps
+use models::{
+    config_options::get_env,
+    node::operations::DepositOp,
+    prover_utils::{PlonkVerificationKey, SetupForStepByStepProver},
+};
+use prover::testing;
+
+fn parse_block_sizes(block_sizes: &str) -> Vec<usize> {
+    block_sizes
+        .split(',')
+        .map(|size| size.trim().parse().expect("invalid block size"))
+        .collect()
+}
+
+/// Proves the synthetic block `iterations` times, returning the duration of every run.
+/// Proofs are verified right after the generation, verification time is included.
+fn bench_block_size(block_size_chunks: usize, iterations: usize) -> Vec<Duration> {
+    let circuit = testing::deposit_block_prover_data(block_size_chunks).into_circuit(1);
+    let setup =
+        SetupForStepByStepProver::prepare_setup_for_step_by_step_prover(circuit.clone(), false)
+            .expect("failed to prepare setup");
+    let vk = PlonkVerificationKey::read_verification_key_for_main_circuit(block_size_chunks)
+        .expect("failed to read verification key");
+
+    (0..iterations)
+        .map(|_| {
+            let started_at = Instant::now();
+            setup
+                .gen_step_by_step_proof_using_prepared_setup(circuit.clone(), &vk)
+                .expect("failed to generate verified proof");
+            started_at.elapsed()
+        })
+        .collect()
+}
+
+fn main() {
+    env_logger::init();
+
+    let cli = App::new("Prover benchmark")
+        .author("Matter Labs")
+        .arg(
+            Arg::with_name("iterations")
+                .long("iterations")
+                .takes_value(true)
+                .default_value("1")
+                .help("Amount of proofs generated for every block size"),
+        )
+        .arg(
+            Arg::with_name("chunks")
+                .long("chunks")
+                .takes_value(true)
+                .help("Comma-separated block sizes in chunks, `BLOCK_CHUNK_SIZES` by default"),
+        )
+        .get_matches();
+
+    let iterations: usize = cli
+        .value_of("iterations")
+        .unwrap()
+        .parse()
+        .expect("invalid amount of iterations");
+    assert!(iterations > 0, "amount of iterations must be positive");
+    let block_sizes = match cli.value_of("chunks") {
+        Some(block_sizes) => parse_block_sizes(block_sizes),
+        None => parse_block_sizes(&get_env("BLOCK_CHUNK_SIZES")),
+    };
+    for &block_size_chunks in &block_sizes {
+        assert!(
+            block_size_chunks >= DepositOp::CHUNKS,
+            "block of {} chunks doesn't fit the deposit",
+            block_size_chunks
+        );
+    }
+
+    for block_size_chunks in block_sizes {
+        log::info!("benchmarking block of {} chunks", block_size_chunks);
+        let timings: Vec<u64> = bench_block_size(block_size_chunks, iterations)
+            .into_iter()
+            .map(|duration| duration.as_millis() as u64)
+            .collect();
+
+        let report = json!({
+            "chunks": block_size_chunks,
+            "iterations": iterations,
+            "min_ms": timings.iter().min(),
+            "avg_ms": timings.iter().sum::<u64>() / timings.len() as u64,
+            "max_ms": timings.iter().max(),
+        });
+        println!("{}", report);
+    }
+}
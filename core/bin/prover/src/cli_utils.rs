@@ -1,26 +1,47 @@
 // Built-in deps
 use std::{
-    sync::{
-        atomic::{AtomicI32, Ordering},
-        mpsc, Arc,
-    },
-    thread,
-    time::Duration,
+    net::SocketAddr,
+    sync::{mpsc, Arc},
 };
 // External deps
+use arc_swap::ArcSwap;
 use clap::{App, Arg};
 // Workspace deps
-use models::config_options::{parse_env, ProverOptions};
+use models::config_options::{get_env, parse_env_opt, ProverOptions};
 // Local deps
-use crate::{client, start, ApiClient, ProverConfig, ProverImpl, ShutdownRequest};
+use crate::{
+    admin, check_server_health, check_verification_keys, client, logging,
+    metrics::{start_metrics_exporter, PoolMetrics, ProverMetrics},
+    params,
+    pool::{PooledApiClient, ProverPool, RoundRobin},
+    proof_spool::{ProofSpool, SpoolingApiClient},
+    telemetry, ApiClient, ProverConfig, ProverImpl, ShutdownRequest,
+};
+
+/// Amount of attempts to register the prover on startup, see `client::ApiClient::register_with_retry`.
+const REGISTER_MAX_ATTEMPTS: u32 = 10;
 
-fn api_client_from_env(worker_name: &str) -> client::ApiClient {
-    let server_api_url = parse_env("PROVER_SERVER_URL");
-    let request_timout = Duration::from_secs(parse_env::<u64>("REQ_SERVER_TIMEOUT"));
-    client::ApiClient::new(&server_api_url, worker_name, request_timout)
+/// API client shared by the provers of the pool run by the prover binaries.
+pub type ServerApiClient = SpoolingApiClient<client::ApiClient>;
+/// API client used by the prover binaries.
+pub type ProverApiClient = PooledApiClient<ServerApiClient>;
+
+fn api_client_from_env(worker_name: &str, prover_options: &ProverOptions) -> ServerApiClient {
+    let server_api_url = get_env("PROVER_SERVER_URL");
+    let client_options = client::ClientOptions::from_prover_options(prover_options);
+    let api_client =
+        match client::ApiClient::new_with_options(&server_api_url, worker_name, client_options) {
+            Ok(api_client) => api_client,
+            Err(e) => {
+                eprintln!("Failed to create the prover server client: {}", e);
+                std::process::exit(1);
+            }
+        };
+    let spool = ProofSpool::new(get_env("PROVER_SPOOL_DIR")).expect("failed to open proof spool");
+    SpoolingApiClient::new(api_client, spool)
 }
 
-pub fn main_for_prover_impl<P: ProverImpl<client::ApiClient> + 'static + Send + Sync>() {
+pub fn main_for_prover_impl<P: ProverImpl<ProverApiClient> + 'static + Send + Sync>() {
     let cli = App::new("Plonk step by step prover")
         .author("Matter Labs")
         .arg(
@@ -29,29 +50,61 @@ pub fn main_for_prover_impl<P: ProverImpl<client::ApiClient> + 'static + Send +
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("params_digest")
+                .long("params-digest")
+                .takes_value(true)
+                .help("Expected SHA-256 digest (hex) of the verification keys of the supported block sizes"),
+        );
+    let cli = <P as ProverImpl<ProverApiClient>>::Config::cli_args()
+        .into_iter()
+        .fold(cli, |cli, arg| cli.arg(arg))
         .get_matches();
-    let worker_name = cli.value_of("worker_name").unwrap();
+    let worker_name = cli.value_of("worker_name").unwrap().to_string();
+    let expected_params_digest = cli
+        .value_of("params_digest")
+        .map(|digest| params::parse_params_digest(digest).expect("invalid params digest"));
 
     // used env
-    let heartbeat_interval = ProverOptions::from_env().heartbeat_interval;
-    let prover_config = <P as ProverImpl<client::ApiClient>>::Config::from_env();
-    let api_client = api_client_from_env(&worker_name);
-    let prover = P::create_from_config(prover_config, api_client.clone(), heartbeat_interval);
+    let prover_options = ProverOptions::from_env();
+    let heartbeat_interval = prover_options.heartbeat_interval;
+    let api_client = api_client_from_env(&worker_name, &prover_options);
+    let metrics = Arc::new(ProverMetrics::new());
+    let pool_metrics = PoolMetrics::new(&metrics);
+    // Every prover of the pool (including the restarted ones) is created with the same config.
+    let create_prover = {
+        let metrics = metrics.clone();
+        move |api_client: ProverApiClient| {
+            let mut prover_config = <P as ProverImpl<ProverApiClient>>::Config::from_env();
+            prover_config.apply_cli_args(&cli);
+            P::create_from_config(
+                prover_config,
+                api_client,
+                heartbeat_interval,
+                Some(metrics.clone()),
+            )
+        }
+    };
 
-    env_logger::init();
+    logging::init_logger(&worker_name);
     const ABSENT_PROVER_ID: i32 = -1;
 
+    // Export the tracing spans, if the collector is configured.
+    let _telemetry_guard = telemetry::tracer_from_env("prover").map(|(tracer, guard)| {
+        telemetry::start_tracing(tracer);
+        guard
+    });
+
     log::info!("creating prover, worker name: {}", worker_name);
 
     // Create client
 
-    let prover_id_arc = Arc::new(AtomicI32::new(ABSENT_PROVER_ID));
-
     let shutdown_request = ShutdownRequest::new();
 
     // Handle termination requests.
     {
         let shutdown_request = shutdown_request.clone();
+        let api_client = api_client.clone();
         ctrlc::set_handler(move || {
             log::info!(
                 "Termination signal received. It will be handled after the currently working round"
@@ -64,6 +117,10 @@ pub fn main_for_prover_impl<P: ProverImpl<client::ApiClient> + 'static + Send +
 
             if shutdown_request.get() {
                 log::warn!("Second shutdown request received, shutting down without waiting for round to be completed");
+                // Otherwise the server reassigns the job only after the prover timeout.
+                if let Err(e) = api_client.prover_stopped(shutdown_request.prover_id()) {
+                    log::error!("failed to send prover stop request: {}", e);
+                }
                 std::process::exit(0);
             }
 
@@ -72,29 +129,88 @@ pub fn main_for_prover_impl<P: ProverImpl<client::ApiClient> + 'static + Send +
         .expect("Failed to register ctrlc handler");
     }
 
+    // Expose prover metrics, if the port is configured.
+    match parse_env_opt::<u16>("PROMETHEUS_EXPORT_PORT") {
+        Some(port) => start_metrics_exporter(metrics, SocketAddr::from(([0, 0, 0, 0], port))),
+        None => log::info!("PROMETHEUS_EXPORT_PORT is not set, prover metrics are not exported"),
+    }
+
+    let prover_options = Arc::new(ArcSwap::from_pointee(prover_options));
+    // Admin endpoint is optional, as it allows to change the prover behavior remotely.
+    if let Some(admin_addr) = parse_env_opt::<SocketAddr>("PROVER_ADMIN_BIND") {
+        admin::start_admin_server(Arc::clone(&prover_options), admin_addr);
+    }
+
+    // Start provers, they don't take the jobs until the prover is registered.
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let pool = ProverPool::start(
+        api_client.clone(),
+        create_prover,
+        Box::new(RoundRobin::default()),
+        exit_err_tx,
+        shutdown_request.clone(),
+        Arc::clone(&prover_options),
+        Some(pool_metrics),
+    );
+
     // Register prover
+    let block_sizes = pool.supported_block_sizes();
+    assert!(
+        !block_sizes.is_empty(),
+        "prover doesn't support any block size, check the keys in KEY_DIR"
+    );
+    log::info!("supported block sizes: {:?}", block_sizes);
+    if let Some(expected_params_digest) = expected_params_digest {
+        params::check_params_digest(&block_sizes, &expected_params_digest)
+            .expect("circuit params check failed");
+        log::info!("circuit params digest matches the expected one");
+    }
+    if prover_options.load().health_check_on_start {
+        match check_server_health(api_client.inner()) {
+            Ok(health) => log::info!(
+                "prover server {} is healthy, pending jobs: {}",
+                health.version,
+                health.pending_jobs
+            ),
+            Err(e) => {
+                log::error!("prover server health check failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // Prover with the keys other than the server expects would publish the invalid proofs only.
+    let vk_hashes = params::vk_hashes(&block_sizes).expect("failed to hash verification keys");
+    if let Err(e) = check_verification_keys(api_client.inner(), &vk_hashes) {
+        log::error!("verification keys check failed: {}", e);
+        std::process::exit(1);
+    }
+    log::info!("verification keys match the ones expected by the prover server");
+    // Server may still be starting, so registration is retried.
     let prover_id = api_client
-        .register_prover(0)
+        .inner()
+        .register_with_retry(
+            &block_sizes,
+            REGISTER_MAX_ATTEMPTS,
+            prover_options.load().retry_initial_delay,
+        )
         .expect("failed to register prover");
     shutdown_request.set_prover_id(prover_id);
 
-    // Start prover
-    let (exit_err_tx, exit_err_rx) = mpsc::channel();
-    let jh = thread::spawn(move || {
-        start(prover, exit_err_tx, shutdown_request);
-    });
-
     // Handle prover exit errors.
+    // Channel is closed without any message if prover was stopped gracefully.
     let err = exit_err_rx.recv();
-    jh.join().expect("failed to join on worker thread");
-    log::error!("prover exited with error: {:?}", err);
-    {
-        let prover_id = prover_id_arc.load(Ordering::SeqCst);
-        if prover_id != ABSENT_PROVER_ID {
-            match api_client.prover_stopped(prover_id) {
-                Ok(_) => {}
-                Err(e) => log::error!("failed to send prover stop request: {}", e),
+    pool.join();
+    match err {
+        Ok(err) => {
+            log::error!("prover exited with error: {:?}", err);
+            let prover_id = shutdown_request.prover_id();
+            if prover_id != ABSENT_PROVER_ID {
+                match api_client.prover_stopped(prover_id) {
+                    Ok(_) => {}
+                    Err(e) => log::error!("failed to send prover stop request: {}", e),
+                }
             }
         }
+        Err(_) => log::info!("prover stopped"),
     }
 }
@@ -1,19 +1,444 @@
 // Built-in deps
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::time::{self, Duration};
+use std::sync::{Arc, Mutex};
+use std::time::{self, Duration, Instant};
 // External deps
-use backoff::Operation;
+use async_trait::async_trait;
+use backoff::{backoff::Backoff, Operation};
+use chrono::{DateTime, Utc};
 use failure::bail;
 use failure::format_err;
+use failure::Fail;
+use flate2::read::GzDecoder;
 use log::*;
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, RETRY_AFTER,
+};
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 // Workspace deps
 use crate::client;
-use crate::prover_data::ProverData;
+use crate::params;
+use crate::prover_data::{self, ProverData};
+use crate::telemetry;
 use circuit::circuit::FranklinCircuit;
+use models::config_options::{ProverOptions, TlsConfig};
 use models::node::Engine;
-use models::prover_utils::EncodedProofPlonk;
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
+// Local deps
+use crate::JobProgress;
+
+mod async_http;
+#[cfg(feature = "grpc-client")]
+mod grpc;
+mod middleware;
+
+pub use self::async_http::AsyncApiClient;
+#[cfg(feature = "grpc-client")]
+pub use self::grpc::{proto, GrpcApiClient};
+pub use self::middleware::{ApiClientMiddleware, LoggingMiddleware, NoopMiddleware};
+
+/// Amount of attempts to publish the proof before giving up.
+const PUBLISH_ATTEMPTS: u32 = 3;
+/// Delay before the second attempt to publish the proof, doubled after every failed attempt.
+const PUBLISH_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Amount of attempts to download a single part of the prover data before giving up.
+const PROVER_DATA_PART_ATTEMPTS: u32 = 5;
+/// Delay before the second attempt to download the part of the prover data,
+/// doubled after every failed attempt.
+const PROVER_DATA_PART_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Prover data is read in chunks of this size, the progress is reported after every chunk.
+const PROVER_DATA_CHUNK_SIZE: usize = 64 * 1024;
+/// Progress of the prover data download is logged at most this often.
+const PROVER_DATA_PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Timeouts and retry policy of the requests to the prover server.
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// Max time to establish the connection to the server.
+    pub connect_timeout: Duration,
+    /// Max time of a single request, including the connection and reading of the response.
+    pub request_timeout: Duration,
+    /// Amount of extra attempts of the idempotent requests which failed to connect to the server.
+    pub retries: u32,
+    /// Delay before the first retry, doubled after every failed attempt.
+    pub backoff: Duration,
+    /// Shared secret sent as a bearer token with every request.
+    pub secret_auth: Option<String>,
+    /// PEM certificate trusted in addition to the system ones, for the `https://` servers
+    /// with the self-signed certificates.
+    pub ca_cert: Option<PathBuf>,
+    /// Idle connections to the server are kept open for this long to be reused by the next
+    /// requests. Must be shorter than the keep-alive of the server, so the client doesn't reuse
+    /// the connection being closed by the server.
+    pub idle_connection_timeout: Duration,
+    /// Interval of the TCP keep-alive probes of the connections, `None` disables the probes.
+    /// Keeps the idle connections from being dropped by the load balancers in between.
+    pub tcp_keepalive: Option<Duration>,
+    /// Max amount of the idle connections to the server kept in the pool.
+    pub max_idle_connections: usize,
+    /// Log the bytes read and written by the connections at the `trace` level.
+    pub connection_verbose: bool,
+    /// Mutual TLS with the server: the client presents its certificate and trusts only
+    /// the server certificates signed by the CA. Server URL must have the `https://` scheme.
+    pub tls: Option<TlsConfig>,
+    /// Max size of the prover data of a block in bytes, the larger responses are rejected
+    /// before they are parsed.
+    pub max_prover_data_size: usize,
+    /// Server responding that there is no job of the block size is not asked again for this
+    /// long, the cached empty response is returned instead. Zero disables the caching.
+    pub none_cache_ttl: Duration,
+}
+
+impl ClientOptions {
+    pub fn from_prover_options(options: &ProverOptions) -> Self {
+        Self {
+            connect_timeout: options.connect_timeout,
+            request_timeout: options.request_timeout,
+            retries: options.request_retries,
+            backoff: options.request_retry_backoff,
+            secret_auth: options.secret_auth.clone(),
+            ca_cert: options.server_ca_cert.clone(),
+            tls: options.tls.clone(),
+            max_prover_data_size: options.max_prover_data_size,
+            none_cache_ttl: options.none_cache_ttl,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(10),
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            secret_auth: None,
+            ca_cert: None,
+            idle_connection_timeout: Duration::from_secs(60),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            max_idle_connections: 4,
+            connection_verbose: true,
+            tls: None,
+            max_prover_data_size: 512 * 1024 * 1024,
+            none_cache_ttl: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Progress of the prover data download, reported after every received chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DownloadProgress {
+    pub block: i64,
+    /// Part being downloaded, starting from zero.
+    pub part: usize,
+    /// Amount of the parts of the prover data.
+    pub parts: usize,
+    /// Bytes of the prover data received so far, including the previous parts.
+    pub received: u64,
+    /// Expected size of the prover data: the size of the previous parts plus the `Content-Length`
+    /// of the current one. Equals to the whole size on the last part, `None` if the server
+    /// doesn't send the length.
+    pub total: Option<u64>,
+}
+
+/// Loads the client certificate and the only trusted CA of the mutual TLS.
+fn load_tls(tls: &TlsConfig) -> Result<(reqwest::Identity, reqwest::Certificate), ClientError> {
+    let read = |path: &PathBuf| {
+        fs::read(path)
+            .map_err(|e| ClientError::config(format!("failed to read {}: {}", path.display(), e)))
+    };
+    // Identity of the rustls backend is loaded from the single PEM with both key and certificate.
+    let mut identity = read(&tls.key_pem)?;
+    identity.push(b'\n');
+    identity.extend(read(&tls.cert_pem)?);
+    let identity = reqwest::Identity::from_pem(&identity).map_err(|e| {
+        ClientError::config(format!(
+            "invalid TLS certificate {} or key {}: {}",
+            tls.cert_pem.display(),
+            tls.key_pem.display(),
+            e
+        ))
+    })?;
+    let ca = reqwest::Certificate::from_pem(&read(&tls.ca_pem)?).map_err(|e| {
+        ClientError::config(format!(
+            "invalid CA certificate {}: {}",
+            tls.ca_pem.display(),
+            e
+        ))
+    })?;
+    Ok((identity, ca))
+}
+
+/// Loads the additional root certificate trusted by the client, see `ClientOptions::ca_cert`.
+fn load_ca_cert(ca_cert: &PathBuf) -> Result<reqwest::Certificate, ClientError> {
+    fs::read(ca_cert)
+        .map_err(|e| e.to_string())
+        .and_then(|pem| reqwest::Certificate::from_pem(&pem).map_err(|e| e.to_string()))
+        .map_err(|e| {
+            ClientError::config(format!(
+                "invalid CA certificate {}: {}",
+                ca_cert.display(),
+                e
+            ))
+        })
+}
+
+/// Creates the HTTP client applying the timeouts, the authentication and the TLS of the options.
+/// Blocking and asynchronous reqwest builders have the same methods, but no common trait,
+/// so both clients are configured by this macro.
+macro_rules! build_http_client {
+    ($builder:expr, $options:expr) => {{
+        let options: &ClientOptions = $options;
+        let mut builder = $builder
+            .connect_timeout(options.connect_timeout)
+            .timeout(options.request_timeout)
+            .pool_idle_timeout(options.idle_connection_timeout)
+            .pool_max_idle_per_host(options.max_idle_connections)
+            .tcp_keepalive(options.tcp_keepalive)
+            .connection_verbose(options.connection_verbose);
+        if let Some(secret_auth) = &options.secret_auth {
+            let mut auth = HeaderValue::from_str(&format!("Bearer {}", secret_auth))
+                .map_err(|_| ClientError::config("prover secret auth is not a valid header"))?;
+            auth.set_sensitive(true);
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, auth);
+            builder = builder.default_headers(headers);
+        }
+        if let Some(ca_cert) = &options.ca_cert {
+            builder = builder.add_root_certificate(load_ca_cert(ca_cert)?);
+        }
+        if let Some(tls) = &options.tls {
+            let (identity, ca) = load_tls(tls)?;
+            builder = builder
+                .use_rustls_tls()
+                .tls_built_in_root_certs(false)
+                .identity(identity)
+                .add_root_certificate(ca);
+        }
+        builder
+            .build()
+            .map_err(|e| ClientError::config(format!("failed to create HTTP client: {}", e)))?
+    }};
+}
+
+/// Adds the W3C `traceparent` header of the current span to the request.
+/// Nothing is added unless the spans are exported, see `telemetry::start_tracing`.
+fn with_trace_context(
+    request: reqwest::blocking::RequestBuilder,
+) -> reqwest::blocking::RequestBuilder {
+    telemetry::trace_context_headers()
+        .into_iter()
+        .fold(request, |request, (name, value)| {
+            request.header(&name, value)
+        })
+}
+
+/// Same as `with_trace_context`, but for the request of the asynchronous client.
+fn with_trace_context_async(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    telemetry::trace_context_headers()
+        .into_iter()
+        .fold(request, |request, (name, value)| {
+            request.header(&name, value)
+        })
+}
+
+/// Max length of the worker name the prover is registered with.
+pub const MAX_WORKER_NAME_LEN: usize = 64;
+
+/// Checks the worker name the prover is registered with, see `ApiClient::new_with_options`.
+fn validate_worker_name(worker: &str) -> Result<(), ClientError> {
+    if worker.is_empty() {
+        return Err(ClientError::config("worker name cannot be empty"));
+    }
+    if worker.len() > MAX_WORKER_NAME_LEN {
+        return Err(ClientError::config(format!(
+            "worker name {} is longer than {} characters",
+            worker, MAX_WORKER_NAME_LEN
+        )));
+    }
+    if let Some(c) = worker
+        .chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || "-_.:".contains(c)))
+    {
+        return Err(ClientError::config(format!(
+            "worker name {:?} contains invalid character {:?}",
+            worker, c
+        )));
+    }
+    Ok(())
+}
+
+/// Header of the `/prover_data/{block}/part/{n}` response with the total amount of parts.
+/// Parts are the consecutive slices of the encoded `Option<ProverData>`, either JSON or binary
+/// (see `prover_data::encode_binary`) if the request accepts it.
+pub const PROVER_DATA_PARTS_HEADER: &str = "x-prover-data-parts";
+
+/// Failed request to the prover server. Lets the callers tell the transient failures
+/// (see `is_retryable`) from the ones which won't go away by repeating the request.
+#[derive(Debug, Fail)]
+pub enum ClientError {
+    /// Request didn't reach the server or the connection was broken, e.g. connection refused.
+    #[fail(display = "{} request failed: {}", request, message)]
+    Transport {
+        request: &'static str,
+        message: String,
+    },
+    /// Server didn't respond in time.
+    #[fail(display = "{} request failed: {}", request, message)]
+    Timeout {
+        request: &'static str,
+        message: String,
+    },
+    /// Server responded with the unsuccessful status.
+    #[fail(
+        display = "{} request failed with status: {} and message: {}",
+        request, status, message
+    )]
+    Http {
+        request: &'static str,
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    /// Server responded with the malformed data.
+    #[fail(display = "failed to parse {} response: {}", request, message)]
+    Decode {
+        request: &'static str,
+        message: String,
+    },
+    /// Server rejected the shared secret of the prover.
+    #[fail(display = "{} request is not authorized by the server", request)]
+    Auth { request: &'static str },
+    /// Client can't be created with the given configuration, e.g. the invalid worker name.
+    #[fail(display = "invalid prover client configuration: {}", message)]
+    Config { message: String },
+}
+
+impl ClientError {
+    fn config(message: impl Into<String>) -> Self {
+        ClientError::Config {
+            message: message.into(),
+        }
+    }
+
+    fn send_failed(request: &'static str, err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            ClientError::Timeout {
+                request,
+                message: err.to_string(),
+            }
+        } else {
+            ClientError::Transport {
+                request,
+                message: err.to_string(),
+            }
+        }
+    }
+
+    fn read_failed(request: &'static str, err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::TimedOut {
+            ClientError::Timeout {
+                request,
+                message: err.to_string(),
+            }
+        } else {
+            ClientError::Transport {
+                request,
+                message: err.to_string(),
+            }
+        }
+    }
+
+    fn decode_failed(request: &'static str, err: impl std::fmt::Display) -> Self {
+        ClientError::Decode {
+            request,
+            message: err.to_string(),
+        }
+    }
+
+    /// Returns the response if its status is successful, otherwise the error with the response text.
+    fn check_status(
+        request: &'static str,
+        res: reqwest::blocking::Response,
+    ) -> Result<reqwest::blocking::Response, Self> {
+        let status = res.status();
+        if status.is_success() {
+            Ok(res)
+        } else if status == reqwest::StatusCode::UNAUTHORIZED {
+            Err(ClientError::Auth { request })
+        } else {
+            Err(ClientError::Http {
+                request,
+                status,
+                message: res.text().unwrap_or_default(),
+            })
+        }
+    }
+
+    /// Checks the status of the response which carries no data. Response is read to the end,
+    /// so its connection is returned to the pool to be reused by the next requests.
+    fn check_response(request: &'static str, res: reqwest::blocking::Response) -> Result<(), Self> {
+        Self::check_status(request, res)?
+            .bytes()
+            .map_err(|e| Self::send_failed(request, e))?;
+        Ok(())
+    }
+
+    /// Same as `check_response`, but for the response of the asynchronous client.
+    async fn check_response_async(
+        request: &'static str,
+        res: reqwest::Response,
+    ) -> Result<(), Self> {
+        let status = res.status();
+        if status.is_success() {
+            res.bytes()
+                .await
+                .map_err(|e| Self::send_failed(request, e))?;
+            Ok(())
+        } else if status == reqwest::StatusCode::UNAUTHORIZED {
+            Err(ClientError::Auth { request })
+        } else {
+            Err(ClientError::Http {
+                request,
+                status,
+                message: res.text().await.unwrap_or_default(),
+            })
+        }
+    }
+
+    /// Returns `true` if the request may succeed when repeated later: the server was unreachable,
+    /// overloaded or failed internally. Rejected requests and malformed responses are not retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Transport { .. } | ClientError::Timeout { .. } => true,
+            ClientError::Http { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            ClientError::Decode { .. } | ClientError::Auth { .. } | ClientError::Config { .. } => {
+                false
+            }
+        }
+    }
+}
+
+/// Returns `true` if the error is the `ClientError` which is not going to go away by retrying.
+pub fn is_fatal(err: &failure::Error) -> bool {
+    err.downcast_ref::<ClientError>()
+        .map_or(false, |err| !err.is_retryable())
+}
+
+/// Server already has a different proof for the block, so it will never accept this one.
+#[derive(Debug, Fail)]
+#[fail(display = "server already has a different proof for block {}", block)]
+pub struct ProofConflict {
+    pub block: i64,
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct ProverReq {
@@ -21,6 +446,107 @@ pub struct ProverReq {
     pub block_size: usize,
 }
 
+/// Request of the `/block_to_prove_wait` long-poll, see `ApiClient::block_to_prove_wait`.
+#[derive(Serialize, Deserialize)]
+pub struct ProverWaitReq {
+    pub name: String,
+    pub block_size: usize,
+    /// Server holds the request for this long at most (capped by the server) if there is no job.
+    pub max_wait_ms: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RegisterReq {
+    pub name: String,
+    /// Block sizes the prover is able to prove, the server assigns it the blocks of these sizes only.
+    pub block_sizes: Vec<usize>,
+    /// Host the prover runs on. Metadata fields are not sent by the provers of the older versions.
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// Version of the prover crate.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Amount of CPU cores of the prover machine.
+    #[serde(default)]
+    pub cpu_cores: Option<u32>,
+    /// Hex-encoded SHA-256 digest of the verification keys of the block sizes, see
+    /// `params::params_digest`. `None` if the keys can't be read.
+    #[serde(default)]
+    pub vk_hash: Option<String>,
+}
+
+impl RegisterReq {
+    /// Registration request of the prover running on this machine, with the metadata of the machine.
+    pub fn new(name: &str, block_sizes: &[usize]) -> Self {
+        Self {
+            name: name.to_string(),
+            block_sizes: block_sizes.to_vec(),
+            hostname: hostname::get()
+                .ok()
+                .and_then(|hostname| hostname.into_string().ok()),
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            cpu_cores: Some(num_cpus::get() as u32),
+            vk_hash: params::params_digest(block_sizes).ok().map(hex::encode),
+        }
+    }
+}
+
+/// Hash of the verification key of the block size, see `params::vk_hashes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VkHash {
+    pub block_size: usize,
+    /// Hex-encoded SHA-256 digest of the verification key, without the `0x` prefix.
+    pub hash: String,
+}
+
+/// Request of the `/check_vk` handshake, see `check_verification_keys`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckVkReq {
+    pub name: String,
+    pub vk_hashes: Vec<VkHash>,
+}
+
+/// Response of the `/check_vk` handshake.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckVkRes {
+    /// Block sizes whose verification keys differ from the ones expected by the server.
+    /// Block sizes the server has no expected key for are not reported.
+    pub mismatched_block_sizes: Vec<usize>,
+}
+
+/// Response of the `/health` endpoint of the prover server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerHealth {
+    /// Version of the server build.
+    pub version: String,
+    /// Whether the server is able to access its database. Server can't give out the jobs otherwise.
+    pub db_connected: bool,
+    /// Amount of the jobs waiting for the provers, `0` if the database is not connected.
+    pub pending_jobs: usize,
+}
+
+/// Stage of the proof of the block, see `ProofStatus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofState {
+    /// Block is committed, but its proof is not stored yet.
+    Pending,
+    /// Proof of the block is stored.
+    Proved,
+    /// Block is not committed yet.
+    NotFound,
+}
+
+/// Response of the `/block/{number}/proof` endpoint of the prover server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofStatus {
+    pub block: i64,
+    pub status: ProofState,
+    /// Time the proof was stored at, set for the proved blocks only.
+    #[serde(default)]
+    pub proof_created_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BlockToProveRes {
     pub prover_run_id: i32,
@@ -30,218 +556,1152 @@ pub struct BlockToProveRes {
 #[derive(Serialize, Deserialize)]
 pub struct WorkingOnReq {
     pub prover_run_id: i32,
+    /// Not sent by the provers which don't report their progress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<JobProgress>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExtendLeaseReq {
+    pub prover_run_id: i32,
+    /// Job is not reassigned to another prover for this amount of seconds.
+    pub extra_seconds: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecordFailureReq {
+    pub prover_run_id: i32,
+    /// Description of the error the job failed with.
+    pub reason: String,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PublishReq {
     pub block: u32,
     pub proof: EncodedProofPlonk,
+    /// Omitted by the older provers, so every field of the statistics is optional.
+    #[serde(flatten)]
+    pub stats: ProvingStats,
+}
+
+/// Body of the `409 Conflict` response to the publish request: another proof for the block
+/// is already published, see `ProofConflict`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishConflictRes {
+    pub already_published: bool,
+}
+
+/// Outcome of publishing a single proof of the batch, see `ApiClient::publish_batch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishResult {
+    /// Proof is stored (or the same proof was already stored).
+    Stored,
+    /// Server already has a different proof for the block, see `ProofConflict`.
+    Conflict,
+    /// Proof is valid, but not stored because another proof of the batch was rejected.
+    NotCommitted,
+}
+
+/// `ApiClientBuilder` configures the `ApiClient` option by option, starting with the default
+/// `ClientOptions`.
+#[derive(Debug)]
+pub struct ApiClientBuilder {
+    base_url: String,
+    worker: String,
+    options: ClientOptions,
+    middleware: Arc<dyn ApiClientMiddleware>,
+}
+
+impl ApiClientBuilder {
+    /// Starts building the client of the prover server at `base_url` for the `worker`.
+    pub fn new(base_url: &str, worker: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            worker: worker.to_string(),
+            options: ClientOptions::default(),
+            middleware: Arc::new(NoopMiddleware),
+        }
+    }
+
+    /// Replaces all the options configured so far.
+    pub fn with_options(self, options: ClientOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    /// Sets the max time to establish the connection to the server.
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.options.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// Sets the max time of a single request.
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.options.request_timeout = request_timeout;
+        self
+    }
+
+    /// Sets the amount of extra attempts and the initial delay between them for the requests
+    /// which failed to connect to the server.
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.options.retries = retries;
+        self.options.backoff = backoff;
+        self
+    }
+
+    /// Sets the shared secret sent as a bearer token with every request.
+    pub fn with_secret_auth(mut self, secret_auth: impl Into<String>) -> Self {
+        self.options.secret_auth = Some(secret_auth.into());
+        self
+    }
+
+    /// Sets the PEM certificate trusted in addition to the system ones.
+    pub fn with_ca_cert(mut self, ca_cert: impl Into<PathBuf>) -> Self {
+        self.options.ca_cert = Some(ca_cert.into());
+        self
+    }
+
+    /// Sets the time the idle connections are kept in the pool.
+    pub fn with_idle_connection_timeout(mut self, idle_connection_timeout: Duration) -> Self {
+        self.options.idle_connection_timeout = idle_connection_timeout;
+        self
+    }
+
+    /// Sets the interval of the TCP keep-alive probes, `None` disables them.
+    pub fn with_tcp_keepalive(mut self, tcp_keepalive: Option<Duration>) -> Self {
+        self.options.tcp_keepalive = tcp_keepalive;
+        self
+    }
+
+    /// Sets the max amount of the idle connections kept in the pool.
+    pub fn with_max_idle_connections(mut self, max_idle_connections: usize) -> Self {
+        self.options.max_idle_connections = max_idle_connections;
+        self
+    }
+
+    /// Enables or disables the `trace` logs of the connection reads and writes.
+    pub fn with_connection_verbose(mut self, connection_verbose: bool) -> Self {
+        self.options.connection_verbose = connection_verbose;
+        self
+    }
+
+    /// Sets the max size of the prover data of a block in bytes.
+    pub fn with_max_prover_data_size(mut self, max_prover_data_size: usize) -> Self {
+        self.options.max_prover_data_size = max_prover_data_size;
+        self
+    }
+
+    /// Sets how long the empty `block_to_prove` response is reused, see `ClientOptions::none_cache_ttl`.
+    pub fn with_none_cache_ttl(mut self, none_cache_ttl: Duration) -> Self {
+        self.options.none_cache_ttl = none_cache_ttl;
+        self
+    }
+
+    /// Enables the mutual TLS with the server, see `ClientOptions::tls`.
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.options.tls = Some(tls);
+        self
+    }
+
+    /// Sets the hooks invoked around every request of the client.
+    pub fn with_middleware(self, middleware: impl ApiClientMiddleware + 'static) -> Self {
+        Self {
+            middleware: Arc::new(middleware),
+            ..self
+        }
+    }
+
+    /// Finishes the building, see `ApiClient::new_with_options` for the validation of the parameters.
+    pub fn build(self) -> Result<ApiClient, ClientError> {
+        let client = ApiClient::new_with_options(&self.base_url, &self.worker, self.options)?;
+        Ok(ApiClient {
+            middleware: self.middleware,
+            ..client
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ApiClient {
+    health_url: Url,
+    block_url: Url,
     register_url: Url,
     block_to_prove_url: Url,
+    block_to_prove_wait_url: Url,
     working_on_url: Url,
-    prover_data_url: Url,
+    extend_lease_url: Url,
+    prover_data_parts_url: Url,
+    verified_block_prover_data_url: Url,
     publish_url: Url,
+    publish_batch_url: Url,
     stopped_url: Url,
+    record_failure_url: Url,
+    check_vk_url: Url,
     worker: String,
+    options: ClientOptions,
     // client keeps connection pool inside, so it is recommended to reuse it (see docstring for reqwest::Client)
+    // Every request (including the prover data download) goes through it, so the connection
+    // is kept alive across the rounds instead of being established for every request.
     http_client: reqwest::blocking::Client,
+    /// Client of the requests sent from the asynchronous tasks (e.g. the heartbeats),
+    /// so they don't block the runtime threads, see `crate::ApiClient::working_on_async`.
+    async_http_client: reqwest::Client,
+    middleware: Arc<dyn ApiClientMiddleware>,
+    /// Time of the latest empty `block_to_prove` response by the block size, shared by the clones.
+    no_job_since: Arc<Mutex<HashMap<usize, Instant>>>,
+    /// Server rate limiting the client asked not to send the requests until this moment,
+    /// see `send`. Shared by the clones.
+    retry_after: Arc<Mutex<Option<Instant>>>,
 }
 
 impl ApiClient {
-    pub fn new(base_url: &Url, worker: &str, req_server_timeout: time::Duration) -> Self {
-        if worker == "" {
-            panic!("worker name cannot be empty")
+    /// Creates the client with the default `ClientOptions` and the given request timeout.
+    /// Fails if the base URL of the server or the worker name is invalid, see `new_with_options`.
+    pub fn new(
+        base_url: &str,
+        worker: &str,
+        req_server_timeout: time::Duration,
+    ) -> Result<Self, ClientError> {
+        let options = ClientOptions {
+            request_timeout: req_server_timeout,
+            ..Default::default()
+        };
+        Self::new_with_options(base_url, worker, options)
+    }
+
+    /// Same as `new`, but panics if the base URL or the worker name is invalid.
+    #[deprecated(note = "use the fallible `ApiClient::new` instead")]
+    pub fn new_unchecked(base_url: &Url, worker: &str, req_server_timeout: time::Duration) -> Self {
+        Self::new(base_url.as_str(), worker, req_server_timeout).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Creates the client, applying the timeouts and the authentication of `options` to every request.
+    /// Base URL must have either the `http://` or the `https://` scheme. Worker name must be
+    /// non-empty, at most `MAX_WORKER_NAME_LEN` characters long and consist of the ASCII letters,
+    /// digits and `-_.:` characters only.
+    pub fn new_with_options(
+        base_url: &str,
+        worker: &str,
+        options: ClientOptions,
+    ) -> Result<Self, ClientError> {
+        validate_worker_name(worker)?;
+        let base_url = Url::parse(base_url).map_err(|e| {
+            ClientError::config(format!("invalid prover server URL {}: {}", base_url, e))
+        })?;
+        if !matches!(base_url.scheme(), "http" | "https") {
+            return Err(ClientError::config(format!(
+                "prover server URL {} must have the http or https scheme",
+                base_url
+            )));
         }
-        let http_client = reqwest::blocking::ClientBuilder::new()
-            .timeout(req_server_timeout)
-            .build()
-            .expect("Failed to create request client");
-        Self {
+
+        if options.tls.is_some() && base_url.scheme() != "https" {
+            return Err(ClientError::config(format!(
+                "prover server URL {} must have the https scheme to use TLS",
+                base_url
+            )));
+        }
+        let http_client = build_http_client!(reqwest::blocking::ClientBuilder::new(), &options);
+        let async_http_client = build_http_client!(reqwest::ClientBuilder::new(), &options);
+        Ok(Self {
+            health_url: base_url.join("/health").unwrap(),
+            block_url: base_url.join("/block/").unwrap(),
             register_url: base_url.join("/register").unwrap(),
             block_to_prove_url: base_url.join("/block_to_prove").unwrap(),
+            block_to_prove_wait_url: base_url.join("/block_to_prove_wait").unwrap(),
             working_on_url: base_url.join("/working_on").unwrap(),
-            prover_data_url: base_url.join("/prover_data").unwrap(),
+            extend_lease_url: base_url.join("/extend_lease").unwrap(),
+            prover_data_parts_url: base_url.join("/prover_data/").unwrap(),
+            verified_block_prover_data_url: base_url.join("/verified_block_prover_data").unwrap(),
             publish_url: base_url.join("/publish").unwrap(),
+            publish_batch_url: base_url.join("/publish_batch").unwrap(),
             stopped_url: base_url.join("/stopped").unwrap(),
+            record_failure_url: base_url.join("/record_failure").unwrap(),
+            check_vk_url: base_url.join("/check_vk").unwrap(),
             worker: worker.to_string(),
+            options,
             http_client,
+            async_http_client,
+            middleware: Arc::new(NoopMiddleware),
+            no_job_since: Default::default(),
+            retry_after: Default::default(),
+        })
+    }
+
+    /// Starts the GET request, propagating the trace context of the current span to the server.
+    fn get(&self, url: &Url) -> reqwest::blocking::RequestBuilder {
+        with_trace_context(self.http_client.get(url.as_str()))
+    }
+
+    /// Starts the POST request, propagating the trace context of the current span to the server.
+    fn post(&self, url: &Url) -> reqwest::blocking::RequestBuilder {
+        with_trace_context(self.http_client.post(url.as_str()))
+    }
+
+    /// Starts the POST request of the asynchronous client, see `post`.
+    fn post_async(&self, url: &Url) -> reqwest::RequestBuilder {
+        with_trace_context_async(self.async_http_client.post(url.as_str()))
+    }
+
+    /// Returns the time left until the moment the server rate limiting the client
+    /// asked not to send the requests before, if it's not passed yet.
+    fn rate_limit_wait(&self, request_name: &'static str) -> Option<Duration> {
+        let retry_after = *self.retry_after.lock().unwrap();
+        let wait = retry_after.and_then(|until| until.checked_duration_since(Instant::now()));
+        if let Some(wait) = wait {
+            debug!(
+                "Server is rate limiting the requests, waiting {:.1}s before the {} request",
+                wait.as_millis() as f32 / 1000.0f32,
+                request_name
+            );
+        }
+        wait
+    }
+
+    /// Remembers the `Retry-After` of the `429 Too Many Requests` response, see `send`.
+    fn record_retry_after(&self, status: reqwest::StatusCode, headers: &HeaderMap) {
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let wait = headers
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Duration::from_secs);
+            *self.retry_after.lock().unwrap() = wait.map(|wait| Instant::now() + wait);
         }
     }
 
-    fn with_retries<T>(
+    /// Sends the request named `request_name`, reporting it to the middleware.
+    /// If the server responded with `429 Too Many Requests` and the `Retry-After` header before,
+    /// waits for the given time to pass before sending the request.
+    fn send(
         &self,
-        op: &dyn Fn() -> Result<T, failure::Error>,
-    ) -> Result<T, failure::Error> {
-        let mut wrap_to_backoff_operation = || -> Result<T, backoff::Error<failure::Error>> {
-            op().map_err(backoff::Error::Transient)
-        };
+        request_name: &'static str,
+        request: reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::Response, reqwest::Error> {
+        if let Some(wait) = self.rate_limit_wait(request_name) {
+            std::thread::sleep(wait);
+        }
 
-        wrap_to_backoff_operation
-            .retry_notify(&mut Self::get_backoff(), |err, next_after: Duration| {
-                let duration_secs = next_after.as_millis() as f32 / 1000.0f32;
+        self.middleware.on_request(request_name);
+        let started = time::Instant::now();
+        let res = request.send();
+        self.middleware.on_response(
+            request_name,
+            started.elapsed(),
+            res.as_ref().ok().map(|res| res.status()),
+        );
+        if let Ok(res) = &res {
+            self.record_retry_after(res.status(), res.headers());
+        }
+        res
+    }
 
-                warn!(
-                    "Failed to reach server err: <{}>, retrying after: {:.1}s",
-                    err, duration_secs,
-                )
-            })
-            .map_err(|e| {
-                panic!(
-                    "Prover can't reach server, for the max elapsed time of the backoff: {}",
-                    e
-                )
+    /// Same as `send`, but sends the request of the asynchronous client without blocking the thread.
+    async fn send_async(
+        &self,
+        request_name: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        if let Some(wait) = self.rate_limit_wait(request_name) {
+            tokio::time::delay_for(wait).await;
+        }
+
+        self.middleware.on_request(request_name);
+        let started = time::Instant::now();
+        let res = request.send().await;
+        self.middleware.on_response(
+            request_name,
+            started.elapsed(),
+            res.as_ref().ok().map(|res| res.status()),
+        );
+        if let Ok(res) = &res {
+            self.record_retry_after(res.status(), res.headers());
+        }
+        res
+    }
+
+    /// Sends the idempotent request, built anew for every attempt. Requests failed to connect
+    /// to the server are retried up to `ClientOptions::retries` times, other errors
+    /// (including the timeouts of the connected requests) are returned right away.
+    fn send_idempotent(
+        &self,
+        request_name: &'static str,
+        request: &dyn Fn() -> reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::Response, reqwest::Error> {
+        let mut delay = self.options.backoff;
+        let mut attempt = 0;
+        loop {
+            let err = match self.send(request_name, request()) {
+                Ok(res) => return Ok(res),
+                Err(err) => err,
+            };
+            if !err.is_connect() || attempt >= self.options.retries {
+                return Err(err);
+            }
+            attempt += 1;
+            warn!(
+                "Failed to connect to server (retry {}/{}) err: <{}>, retrying after: {:.1}s",
+                attempt,
+                self.options.retries,
+                err,
+                delay.as_millis() as f32 / 1000.0f32,
+            );
+            std::thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+
+    /// Requests the part of the prover data, returned along with the total amount of parts.
+    /// Every part is requested with its own timeout. `received` bytes of the previous parts
+    /// count towards `ClientOptions::max_prover_data_size`.
+    fn prover_data_part(
+        &self,
+        block: i64,
+        part: usize,
+        received: usize,
+        on_progress: &dyn Fn(&DownloadProgress),
+    ) -> Result<(Vec<u8>, usize), failure::Error> {
+        trace!("sending prover_data part {} of block {}", part, block);
+        let url = self
+            .prover_data_parts_url
+            .join(&format!("{}/part/{}", block, part))?;
+        let res = self
+            .send_idempotent("prover data part", &|| self.get_prover_data(&url))
+            .map_err(|e| ClientError::send_failed("prover data part", e))?;
+        let res = ClientError::check_status("prover data part", res)?;
+        let parts = res
+            .headers()
+            .get(PROVER_DATA_PARTS_HEADER)
+            .and_then(|parts| parts.to_str().ok())
+            .and_then(|parts| parts.parse().ok())
+            .ok_or_else(|| ClientError::decode_failed("prover data part", "no amount of parts"))?;
+        let data = self.read_limited("prover data part", res, received, &|read, total| {
+            on_progress(&DownloadProgress {
+                block,
+                part,
+                parts,
+                received: read,
+                total,
             })
+        })?;
+        Ok((data, parts))
     }
 
-    fn get_backoff() -> backoff::ExponentialBackoff {
-        let mut backoff = backoff::ExponentialBackoff::default();
-        backoff.current_interval = Duration::from_secs(1);
-        backoff.initial_interval = Duration::from_secs(1);
-        backoff.multiplier = 1.5f64;
-        backoff.max_interval = Duration::from_secs(10);
-        backoff.max_elapsed_time = Some(Duration::from_secs(2 * 60));
-        backoff
+    /// Starts the GET request of the prover data, asking the server to compress the response
+    /// and to use the binary encoding. Servers unaware of the binary encoding respond with JSON.
+    fn get_prover_data(&self, url: &Url) -> reqwest::blocking::RequestBuilder {
+        self.get(url)
+            .header(ACCEPT_ENCODING, "gzip")
+            .header(ACCEPT, prover_data::BINARY_CONTENT_TYPE)
     }
 
-    pub fn register_prover(&self, block_size: usize) -> Result<i32, failure::Error> {
-        let op = || -> Result<i32, failure::Error> {
-            info!("Registering prover...");
-            let res = self
-                .http_client
-                .post(self.register_url.as_str())
-                .json(&client::ProverReq {
-                    name: self.worker.clone(),
-                    block_size,
-                })
-                .send();
+    /// Reads the body of the response in chunks, reporting the bytes read so far (starting from
+    /// `received`) and the expected total. Fails without reading the rest of the body once
+    /// `received` plus the body exceeds `ClientOptions::max_prover_data_size`.
+    /// Gzip-compressed body is decompressed, its size and progress are those of the decompressed data.
+    fn read_limited(
+        &self,
+        request: &'static str,
+        res: reqwest::blocking::Response,
+        received: usize,
+        on_chunk: &dyn Fn(u64, Option<u64>),
+    ) -> Result<Vec<u8>, ClientError> {
+        let max_size = self.options.max_prover_data_size as u64;
+        let too_large = || {
+            ClientError::decode_failed(
+                request,
+                format!("prover data exceeds the max size of {} bytes", max_size),
+            )
+        };
+        let compressed = res
+            .headers()
+            .get(CONTENT_ENCODING)
+            .map_or(false, |encoding| encoding.as_bytes() == b"gzip");
+        // Length of the compressed body says nothing about the length of the data.
+        let content_length = if compressed {
+            None
+        } else {
+            res.content_length()
+        };
+        let total = content_length.map(|len| received as u64 + len);
+        if total.map_or(false, |total| total > max_size) {
+            return Err(too_large());
+        }
 
-            let res = res.map_err(|e| format_err!("register request failed: {}", e))?;
-            let text = res
-                .text()
-                .map_err(|e| format_err!("failed to read register response: {}", e))?;
+        let mut body: Box<dyn Read> = if compressed {
+            Box::new(GzDecoder::new(res))
+        } else {
+            Box::new(res)
+        };
+        let mut data = Vec::with_capacity(content_length.unwrap_or(0) as usize);
+        let mut chunk = vec![0u8; PROVER_DATA_CHUNK_SIZE];
+        loop {
+            let read = body
+                .read(&mut chunk)
+                .map_err(|e| ClientError::read_failed(request, e))?;
+            if read == 0 {
+                return Ok(data);
+            }
+            data.extend_from_slice(&chunk[..read]);
+            let read_total = (received + data.len()) as u64;
+            if read_total > max_size {
+                return Err(too_large());
+            }
+            on_chunk(read_total, total);
+        }
+    }
 
-            Ok(i32::from_str(&text)
-                .map_err(|e| format_err!("failed to parse register prover id: {}", e))?)
+    /// Downloads the prover data part by part. Failed part is requested again on its own,
+    /// so a transient failure (e.g. dropped connection) doesn't restart the whole download.
+    fn download_prover_data(
+        &self,
+        block: i64,
+        on_progress: &dyn Fn(&DownloadProgress),
+    ) -> Result<Option<ProverData>, failure::Error> {
+        let (mut data, parts) = with_prover_data_part_retries(block, 0, &|| {
+            self.prover_data_part(block, 0, 0, on_progress)
+        })?;
+        for part in 1..parts {
+            let (part_data, part_parts) = with_prover_data_part_retries(block, part, &|| {
+                self.prover_data_part(block, part, data.len(), on_progress)
+            })?;
+            if part_parts != parts {
+                bail!("prover data of block {} changed during the download", block);
+            }
+            data.extend_from_slice(&part_data);
+        }
+        Ok(prover_data::decode(&data).map_err(|e| ClientError::decode_failed("prover data", e))?)
+    }
+
+    fn try_register_prover(&self, block_sizes: &[usize]) -> Result<i32, failure::Error> {
+        info!("Registering prover...");
+        let res = self
+            .send(
+                "register",
+                self.post(&self.register_url)
+                    .json(&client::RegisterReq::new(&self.worker, block_sizes)),
+            )
+            .map_err(|e| ClientError::send_failed("register", e))?;
+        let res = ClientError::check_status("register", res)?;
+        let text = res
+            .text()
+            .map_err(|e| ClientError::send_failed("register", e))?;
+
+        Ok(i32::from_str(&text).map_err(|e| ClientError::decode_failed("register", e))?)
+    }
+
+    /// Same as `ApiClient::prover_data`, but reports the progress of the download
+    /// to `on_progress` after every received chunk instead of logging it.
+    pub fn prover_data_with_progress(
+        &self,
+        block: i64,
+        on_progress: &dyn Fn(&DownloadProgress),
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        let op = || -> Result<ProverData, failure::Error> {
+            trace!("sending prover_data");
+            let res = self.download_prover_data(block, on_progress)?;
+            Ok(res.ok_or_else(|| format_err!("ProverData for block {} is not ready yet", block))?)
         };
 
-        Ok(self.with_retries(&op)?)
+        let prover_data = with_retries(&op)?;
+        Ok(prover_data.into_circuit(block))
+    }
+
+    /// Registers the prover able to prove the blocks of the given sizes.
+    pub fn register_prover(&self, block_sizes: &[usize]) -> Result<i32, failure::Error> {
+        Ok(with_retries(&|| self.try_register_prover(block_sizes))?)
+    }
+
+    /// Registers the prover, making up to `max_attempts` attempts with the linear backoff:
+    /// the wait after the `n`-th failed attempt is `n * delay`.
+    /// Unlike `register_prover`, returns the last error instead of panicking, so the caller
+    /// decides what to do with the server which is not ready yet.
+    /// Fatal client errors (e.g. the rejected secret auth) are returned right away.
+    pub fn register_with_retry(
+        &self,
+        block_sizes: &[usize],
+        max_attempts: u32,
+        delay: Duration,
+    ) -> Result<i32, failure::Error> {
+        let mut attempt = 1;
+        loop {
+            let err = match self.try_register_prover(block_sizes) {
+                Ok(prover_id) => return Ok(prover_id),
+                Err(err) => err,
+            };
+            if is_fatal(&err) {
+                return Err(err);
+            }
+            if attempt >= max_attempts {
+                return Err(format_err!(
+                    "failed to register prover after {} attempts: {}",
+                    attempt,
+                    err
+                ));
+            }
+
+            let next_after = delay * attempt;
+            warn!(
+                "Failed to register prover (attempt {}/{}) err: <{}>, retrying after: {:.1}s",
+                attempt,
+                max_attempts,
+                err,
+                next_after.as_millis() as f32 / 1000.0f32,
+            );
+            std::thread::sleep(next_after);
+            attempt += 1;
+        }
+    }
+
+    /// Same as `block_to_prove`, but if there is no job the server holds the request until
+    /// the job is available (e.g. the witness of the new block is generated) or `max_wait` expires,
+    /// so the prover doesn't have to poll the server every `PROVER_CYCLE_WAIT`.
+    pub fn block_to_prove_wait(
+        &self,
+        block_size: usize,
+        max_wait: Duration,
+    ) -> Result<Option<(i64, i32)>, failure::Error> {
+        trace!("sending block_to_prove_wait for {:?}", max_wait);
+        let res = self
+            .send_idempotent("block to prove wait", &|| {
+                self.get(&self.block_to_prove_wait_url)
+                    .json(&client::ProverWaitReq {
+                        name: self.worker.clone(),
+                        block_size,
+                        max_wait_ms: max_wait.as_millis() as u64,
+                    })
+                    // Request is held by the server for up to `max_wait` on top of the usual response time.
+                    .timeout(self.options.request_timeout + max_wait)
+            })
+            .map_err(|e| ClientError::send_failed("block to prove wait", e))?;
+        let res = ClientError::check_status("block to prove wait", res)?;
+        let text = res
+            .text()
+            .map_err(|e| ClientError::send_failed("block to prove wait", e))?;
+        let res: Option<client::BlockToProveRes> = serde_json::from_str(&text)
+            .map_err(|e| ClientError::decode_failed("block to prove wait", e))?;
+        Ok(res.map(|res| (res.block, res.prover_run_id)))
     }
 }
 
+#[async_trait]
 impl crate::ApiClient for ApiClient {
+    /// Failed request is retried on connection errors only, the other errors are returned
+    /// to the caller, so a hung server doesn't stall the prover round loop.
+    ///
+    /// Empty response is reused for `ClientOptions::none_cache_ttl` without asking the server,
+    /// so the idle provers polling in a tight loop don't flood it with the requests.
     fn block_to_prove(&self, block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
-        let op = || -> Result<Option<(i64, i32)>, failure::Error> {
-            trace!("sending block_to_prove");
-            let res = self
-                .http_client
-                .get(self.block_to_prove_url.as_str())
-                .json(&client::ProverReq {
+        let ttl = self.options.none_cache_ttl;
+        if ttl > Duration::from_secs(0) {
+            let no_job_since = self.no_job_since.lock().unwrap();
+            if let Some(since) = no_job_since.get(&block_size) {
+                if since.elapsed() < ttl {
+                    trace!("no block to prove of size {} (cached)", block_size);
+                    return Ok(None);
+                }
+            }
+        }
+
+        trace!("sending block_to_prove");
+        let res = self
+            .send_idempotent("block to prove", &|| {
+                self.get(&self.block_to_prove_url).json(&client::ProverReq {
                     name: self.worker.clone(),
                     block_size,
                 })
-                .send()
-                .map_err(|e| format_err!("block to prove request failed: {}", e))?;
-            let text = res
-                .text()
-                .map_err(|e| format_err!("failed to read block to prove response: {}", e))?;
-            let res: client::BlockToProveRes = serde_json::from_str(&text)
-                .map_err(|e| format_err!("failed to parse block to prove response: {}", e))?;
-            if res.block != 0 {
-                return Ok(Some((res.block, res.prover_run_id)));
-            }
-            Ok(None)
-        };
+            })
+            .map_err(|e| ClientError::send_failed("block to prove", e))?;
+        let res = ClientError::check_status("block to prove", res)?;
+        let text = res
+            .text()
+            .map_err(|e| ClientError::send_failed("block to prove", e))?;
+        let res: Option<client::BlockToProveRes> = serde_json::from_str(&text)
+            .map_err(|e| ClientError::decode_failed("block to prove", e))?;
+        let mut no_job_since = self.no_job_since.lock().unwrap();
+        match res {
+            Some(res) => {
+                no_job_since.remove(&block_size);
+                Ok(Some((res.block, res.prover_run_id)))
+            }
+            None => {
+                no_job_since.insert(block_size, Instant::now());
+                Ok(None)
+            }
+        }
+    }
 
-        Ok(self.with_retries(&op)?)
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error> {
+        trace!("sending working_on {}, progress: {:?}", job_id, progress);
+        let res = self
+            .send(
+                "working on",
+                self.post(&self.working_on_url).json(&client::WorkingOnReq {
+                    prover_run_id: job_id,
+                    progress,
+                }),
+            )
+            .map_err(|e| ClientError::send_failed("working on", e))?;
+        ClientError::check_response("working on", res)?;
+        Ok(())
     }
 
-    fn working_on(&self, job_id: i32) -> Result<(), failure::Error> {
-        trace!("sending working_on {}", job_id);
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        trace!("sending extend_lease {} by {}s", job_id, extra_seconds);
         let res = self
-            .http_client
-            .post(self.working_on_url.as_str())
-            .json(&client::WorkingOnReq {
-                prover_run_id: job_id,
-            })
-            .send()
-            .map_err(|e| format_err!("failed to send working on request: {}", e))?;
-        if res.status() != reqwest::StatusCode::OK {
-            bail!("working on request failed with status: {}", res.status())
-        } else {
-            Ok(())
-        }
+            .send(
+                "extend lease",
+                self.post(&self.extend_lease_url)
+                    .json(&client::ExtendLeaseReq {
+                        prover_run_id: job_id,
+                        extra_seconds,
+                    }),
+            )
+            .map_err(|e| ClientError::send_failed("extend lease", e))?;
+        ClientError::check_response("extend lease", res)?;
+        Ok(())
     }
 
-    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'_, Engine>, failure::Error> {
-        let op = || -> Result<ProverData, failure::Error> {
-            trace!("sending prover_data");
-            let res = self
-                .http_client
-                .get(self.prover_data_url.as_str())
-                .json(&block)
-                .send()
-                .map_err(|e| format_err!("failed to request prover data: {}", e))?;
-            let text = res
-                .text()
-                .map_err(|e| format_err!("failed to read prover data response: {}", e))?;
-            let res: Option<ProverData> = serde_json::from_str(&text)
-                .map_err(|e| format_err!("failed to parse prover data response: {}", e))?;
-            Ok(res.ok_or_else(|| format_err!("ProverData for block {} is not ready yet", block))?)
-        };
+    /// Prover data of the large blocks takes tens of megabytes, so it's downloaded in parts.
+    /// Progress of the download is logged every few seconds.
+    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        let last_logged = Cell::new(time::Instant::now());
+        self.prover_data_with_progress(block, &|progress| {
+            if last_logged.get().elapsed() >= PROVER_DATA_PROGRESS_LOG_INTERVAL {
+                last_logged.set(time::Instant::now());
+                log_download_progress(progress);
+            }
+        })
+    }
 
-        let prover_data = self.with_retries(&op)?;
+    /// Unlike `prover_data`, is not retried: data of the verified block is either stored or not.
+    fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        trace!("sending verified_block_prover_data {}", block);
+        let res = self
+            .send(
+                "verified block prover data",
+                self.get_prover_data(&self.verified_block_prover_data_url)
+                    .json(&block),
+            )
+            .map_err(|e| ClientError::send_failed("verified block prover data", e))?;
+        let res = ClientError::check_status("verified block prover data", res)?;
+        let data = self.read_limited("verified block prover data", res, 0, &|_, _| {})?;
+        let res = prover_data::decode(&data)
+            .map_err(|e| ClientError::decode_failed("verified block prover data", e))?;
+        let prover_data =
+            res.ok_or_else(|| format_err!("no ProverData for verified block {}", block))?;
         Ok(prover_data.into_circuit(block))
     }
 
+    /// Publishing the same proof again is accepted by the server, so the proof is re-sent
+    /// if the previous attempt failed, e.g. timed out after the server stored it.
     fn publish(&self, block: i64, proof: EncodedProofPlonk) -> Result<(), failure::Error> {
-        let op = move || -> Result<(), failure::Error> {
+        self.publish_with_stats(block, proof, ProvingStats::default())
+    }
+
+    fn publish_with_stats(
+        &self,
+        block: i64,
+        proof: EncodedProofPlonk,
+        mut stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        stats.worker_name.get_or_insert_with(|| self.worker.clone());
+        let op = || -> Result<(), failure::Error> {
             trace!("Trying publish proof {}", block);
-            let proof = proof.clone();
             let res = self
-                .http_client
-                .post(self.publish_url.as_str())
-                .json(&client::PublishReq {
-                    block: block as u32,
-                    proof,
-                })
-                .send()
-                .map_err(|e| format_err!("failed to send publish request: {}", e))?;
-            let status = res.status();
-            if status != reqwest::StatusCode::OK {
-                match res.text() {
-                    Ok(message) => {
-                        if message == "duplicate key" {
-                            warn!("proof for block {} already exists", block);
-                        } else {
-                            bail!(
-                                "publish request failed with status: {} and message: {}",
-                                status,
-                                message
-                            );
-                        }
-                    }
-                    Err(_) => {
-                        bail!("publish request failed with status: {}", status);
-                    }
-                };
+                .send(
+                    "publish",
+                    self.post(&self.publish_url).json(&client::PublishReq {
+                        block: block as u32,
+                        proof: proof.clone(),
+                        stats: stats.clone(),
+                    }),
+                )
+                .map_err(|e| ClientError::send_failed("publish", e))?;
+            if res.status() == reqwest::StatusCode::CONFLICT {
+                return Err(ProofConflict { block }.into());
             }
+            ClientError::check_response("publish", res)?;
 
             Ok(())
         };
 
-        Ok(self.with_retries(&op)?)
+        with_publish_retries(block, &op)
+    }
+
+    /// Not retried: the batch is either stored as a whole or not at all, so the caller may
+    /// re-send it the same way as a single proof.
+    fn publish_batch(
+        &self,
+        items: Vec<(i64, EncodedProofPlonk)>,
+    ) -> Result<Vec<PublishResult>, failure::Error> {
+        trace!("sending publish_batch of {} proofs", items.len());
+        let items_count = items.len();
+        let batch: Vec<_> = items
+            .into_iter()
+            .map(|(block, proof)| client::PublishReq {
+                block: block as u32,
+                proof,
+                stats: ProvingStats {
+                    worker_name: Some(self.worker.clone()),
+                    ..Default::default()
+                },
+            })
+            .collect();
+        let res = self
+            .send(
+                "publish batch",
+                self.post(&self.publish_batch_url).json(&batch),
+            )
+            .map_err(|e| ClientError::send_failed("publish batch", e))?;
+        let res = ClientError::check_status("publish batch", res)?;
+        let results: Vec<PublishResult> = res
+            .json()
+            .map_err(|e| ClientError::decode_failed("publish batch", e))?;
+        if results.len() != items_count {
+            return Err(ClientError::decode_failed(
+                "publish batch",
+                format!("{} results for {} proofs", results.len(), items_count),
+            )
+            .into());
+        }
+        Ok(results)
     }
 
     fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
-        self.http_client
-            .post(self.stopped_url.as_str())
-            .json(&prover_run_id)
-            .send()
-            .map_err(|e| format_err!("prover stopped request failed: {}", e))?;
+        let res = self
+            .send(
+                "prover stopped",
+                self.post(&self.stopped_url).json(&prover_run_id),
+            )
+            .map_err(|e| ClientError::send_failed("prover stopped", e))?;
+        ClientError::check_response("prover stopped", res)?;
+        Ok(())
+    }
+
+    fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        trace!("sending record_failure {}: {}", job_id, reason);
+        let res = self
+            .send(
+                "record failure",
+                self.post(&self.record_failure_url)
+                    .json(&client::RecordFailureReq {
+                        prover_run_id: job_id,
+                        reason: reason.to_string(),
+                    }),
+            )
+            .map_err(|e| ClientError::send_failed("record failure", e))?;
+        ClientError::check_response("record failure", res)?;
+        Ok(())
+    }
+
+    fn health_check(&self) -> Result<client::ServerHealth, failure::Error> {
+        trace!("sending health check");
+        let res = self
+            .send_idempotent("health check", &|| self.get(&self.health_url))
+            .map_err(|e| ClientError::send_failed("health check", e))?;
+        let res = ClientError::check_status("health check", res)?;
+        let text = res
+            .text()
+            .map_err(|e| ClientError::send_failed("health check", e))?;
+        Ok(serde_json::from_str(&text)
+            .map_err(|e| ClientError::decode_failed("health check", e))?)
+    }
+
+    fn proof_status(&self, block: i64) -> Result<client::ProofStatus, failure::Error> {
+        trace!("sending proof status request for block {}", block);
+        let url = self.block_url.join(&format!("{}/proof", block))?;
+        let res = self
+            .send_idempotent("proof status", &|| self.get(&url))
+            .map_err(|e| ClientError::send_failed("proof status", e))?;
+        let res = ClientError::check_status("proof status", res)?;
+        let text = res
+            .text()
+            .map_err(|e| ClientError::send_failed("proof status", e))?;
+        Ok(serde_json::from_str(&text)
+            .map_err(|e| ClientError::decode_failed("proof status", e))?)
+    }
+
+    fn check_vk(&self, vk_hashes: &[client::VkHash]) -> Result<client::CheckVkRes, failure::Error> {
+        trace!("sending verification keys check");
+        let req = client::CheckVkReq {
+            name: self.worker.clone(),
+            vk_hashes: vk_hashes.to_vec(),
+        };
+        let res = self
+            .send_idempotent("check vk", &|| self.post(&self.check_vk_url).json(&req))
+            .map_err(|e| ClientError::send_failed("check vk", e))?;
+        let res = ClientError::check_status("check vk", res)?;
+        let text = res
+            .text()
+            .map_err(|e| ClientError::send_failed("check vk", e))?;
+        Ok(serde_json::from_str(&text).map_err(|e| ClientError::decode_failed("check vk", e))?)
+    }
+
+    // Requests of the heartbeat routine and of the stopping prover are sent from the runtime
+    // tasks, so they don't block the runtime threads. Rounds call the blocking methods,
+    // since they run on the blocking threads anyway.
+
+    async fn working_on_async(
+        &self,
+        job_id: i32,
+        progress: Option<JobProgress>,
+    ) -> Result<(), failure::Error> {
+        trace!("sending working_on {}, progress: {:?}", job_id, progress);
+        let res = self
+            .send_async(
+                "working on",
+                self.post_async(&self.working_on_url)
+                    .json(&client::WorkingOnReq {
+                        prover_run_id: job_id,
+                        progress,
+                    }),
+            )
+            .await
+            .map_err(|e| ClientError::send_failed("working on", e))?;
+        ClientError::check_response_async("working on", res).await?;
+        Ok(())
+    }
+
+    async fn extend_lease_async(
+        &self,
+        job_id: i32,
+        extra_seconds: u32,
+    ) -> Result<(), failure::Error> {
+        trace!("sending extend_lease {} by {}s", job_id, extra_seconds);
+        let res = self
+            .send_async(
+                "extend lease",
+                self.post_async(&self.extend_lease_url)
+                    .json(&client::ExtendLeaseReq {
+                        prover_run_id: job_id,
+                        extra_seconds,
+                    }),
+            )
+            .await
+            .map_err(|e| ClientError::send_failed("extend lease", e))?;
+        ClientError::check_response_async("extend lease", res).await?;
+        Ok(())
+    }
+
+    async fn prover_stopped_async(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        let res = self
+            .send_async(
+                "prover stopped",
+                self.post_async(&self.stopped_url).json(&prover_run_id),
+            )
+            .await
+            .map_err(|e| ClientError::send_failed("prover stopped", e))?;
+        ClientError::check_response_async("prover stopped", res).await?;
         Ok(())
     }
+
+    async fn record_failure_async(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        trace!("sending record_failure {}: {}", job_id, reason);
+        let res = self
+            .send_async(
+                "record failure",
+                self.post_async(&self.record_failure_url)
+                    .json(&client::RecordFailureReq {
+                        prover_run_id: job_id,
+                        reason: reason.to_string(),
+                    }),
+            )
+            .await
+            .map_err(|e| ClientError::send_failed("record failure", e))?;
+        ClientError::check_response_async("record failure", res).await?;
+        Ok(())
+    }
+}
+
+/// Runs the request operation, retrying it with the exponential backoff until it succeeds.
+/// Fatal client errors (see `is_fatal`) are returned right away.
+/// Panics if the server can't be reached for the max elapsed time of the backoff.
+fn with_retries<T>(op: &dyn Fn() -> Result<T, failure::Error>) -> Result<T, failure::Error> {
+    let mut wrap_to_backoff_operation = || -> Result<T, backoff::Error<failure::Error>> {
+        op().map_err(|err| {
+            if is_fatal(&err) {
+                backoff::Error::Permanent(err)
+            } else {
+                backoff::Error::Transient(err)
+            }
+        })
+    };
+
+    wrap_to_backoff_operation
+        .retry_notify(&mut get_backoff(), |err, next_after: Duration| {
+            let duration_secs = next_after.as_millis() as f32 / 1000.0f32;
+
+            warn!(
+                "Failed to reach server err: <{}>, retrying after: {:.1}s",
+                err, duration_secs,
+            )
+        })
+        .map_err(|e| match e {
+            backoff::Error::Permanent(err) => err,
+            backoff::Error::Transient(err) => panic!(
+                "Prover can't reach server, for the max elapsed time of the backoff: {}",
+                err
+            ),
+        })
+}
+
+/// Runs the publish operation, making up to `PUBLISH_ATTEMPTS` attempts with the exponential backoff.
+/// `ProofConflict` and fatal client errors are returned right away, since retrying them is pointless.
+fn with_publish_retries(
+    block: i64,
+    op: &dyn Fn() -> Result<(), failure::Error>,
+) -> Result<(), failure::Error> {
+    let mut delay = PUBLISH_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let err = match op() {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        if err.downcast_ref::<ProofConflict>().is_some()
+            || is_fatal(&err)
+            || attempt >= PUBLISH_ATTEMPTS
+        {
+            return Err(err);
+        }
+        warn!(
+            "Failed to publish proof for block {} (attempt {}/{}) err: <{}>, retrying after: {:.1}s",
+            block,
+            attempt,
+            PUBLISH_ATTEMPTS,
+            err,
+            delay.as_millis() as f32 / 1000.0f32,
+        );
+        std::thread::sleep(delay);
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+fn log_download_progress(progress: &DownloadProgress) {
+    const MIB: f64 = 1024.0 * 1024.0;
+    let received = progress.received as f64 / MIB;
+    match progress.total {
+        Some(total) => info!(
+            "Downloading prover data for block {}: part {}/{}, {:.1}/{:.1} MiB",
+            progress.block,
+            progress.part + 1,
+            progress.parts,
+            received,
+            total as f64 / MIB,
+        ),
+        None => info!(
+            "Downloading prover data for block {}: part {}/{}, {:.1} MiB",
+            progress.block,
+            progress.part + 1,
+            progress.parts,
+            received,
+        ),
+    }
+}
+
+/// Runs the download of the prover data part, making up to `PROVER_DATA_PART_ATTEMPTS` attempts
+/// with the exponential backoff. Fatal client errors are returned right away.
+fn with_prover_data_part_retries<T>(
+    block: i64,
+    part: usize,
+    op: &dyn Fn() -> Result<T, failure::Error>,
+) -> Result<T, failure::Error> {
+    let mut delay = PROVER_DATA_PART_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let err = match op() {
+            Ok(res) => return Ok(res),
+            Err(err) => err,
+        };
+        if is_fatal(&err) || attempt >= PROVER_DATA_PART_ATTEMPTS {
+            return Err(err);
+        }
+        warn!(
+            "Failed to download part {} of prover data for block {} (attempt {}/{}) err: <{}>, retrying after: {:.1}s",
+            part,
+            block,
+            attempt,
+            PROVER_DATA_PART_ATTEMPTS,
+            err,
+            delay.as_millis() as f32 / 1000.0f32,
+        );
+        std::thread::sleep(delay);
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Asynchronous version of `with_publish_retries`.
+async fn with_publish_retries_async<F, Fut>(block: i64, op: F) -> Result<(), failure::Error>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<(), failure::Error>>,
+{
+    let mut delay = PUBLISH_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let err = match op().await {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        if err.downcast_ref::<ProofConflict>().is_some() || attempt >= PUBLISH_ATTEMPTS {
+            return Err(err);
+        }
+        warn!(
+            "Failed to publish proof for block {} (attempt {}/{}) err: <{}>, retrying after: {:.1}s",
+            block,
+            attempt,
+            PUBLISH_ATTEMPTS,
+            err,
+            delay.as_millis() as f32 / 1000.0f32,
+        );
+        tokio::time::delay_for(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Asynchronous version of `with_retries`, which doesn't block the runtime while waiting
+/// for the next attempt.
+async fn with_retries_async<T, F, Fut>(op: F) -> Result<T, failure::Error>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T, failure::Error>>,
+{
+    let mut backoff = get_backoff();
+    loop {
+        let err = match op().await {
+            Ok(res) => return Ok(res),
+            Err(err) => err,
+        };
+        match backoff.next_backoff() {
+            Some(next_after) => {
+                let duration_secs = next_after.as_millis() as f32 / 1000.0f32;
+                warn!(
+                    "Failed to reach server err: <{}>, retrying after: {:.1}s",
+                    err, duration_secs,
+                );
+                tokio::time::delay_for(next_after).await;
+            }
+            None => panic!(
+                "Prover can't reach server, for the max elapsed time of the backoff: {}",
+                err
+            ),
+        }
+    }
+}
+
+fn get_backoff() -> backoff::ExponentialBackoff {
+    let mut backoff = backoff::ExponentialBackoff::default();
+    backoff.current_interval = Duration::from_secs(1);
+    backoff.initial_interval = Duration::from_secs(1);
+    backoff.multiplier = 1.5f64;
+    backoff.max_interval = Duration::from_secs(10);
+    backoff.max_elapsed_time = Some(Duration::from_secs(2 * 60));
+    backoff
 }
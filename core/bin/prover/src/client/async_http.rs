@@ -0,0 +1,306 @@
+//! Asynchronous implementation of the HTTP prover server API client.
+
+// Built-in deps
+use std::str::FromStr;
+use std::time::Duration;
+// External deps
+use async_trait::async_trait;
+use failure::{bail, format_err};
+use log::*;
+use reqwest::Url;
+// Workspace deps
+use crate::prover_data::ProverData;
+use circuit::circuit::FranklinCircuit;
+use models::node::Engine;
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
+// Local deps
+use super::{
+    with_publish_retries_async, with_retries_async, BlockToProveRes, ExtendLeaseReq, ProofConflict,
+    ProverReq, PublishReq, RecordFailureReq, RegisterReq, WorkingOnReq,
+};
+use crate::JobProgress;
+
+/// API client communicating with the prover server over HTTP without blocking the runtime.
+/// Same as `client::ApiClient`, but implements the `AsyncApiClient`.
+#[derive(Debug, Clone)]
+pub struct AsyncApiClient {
+    register_url: Url,
+    block_to_prove_url: Url,
+    working_on_url: Url,
+    extend_lease_url: Url,
+    prover_data_url: Url,
+    verified_block_prover_data_url: Url,
+    publish_url: Url,
+    stopped_url: Url,
+    record_failure_url: Url,
+    worker: String,
+    // client keeps connection pool inside, so it is recommended to reuse it (see docstring for reqwest::Client)
+    http_client: reqwest::Client,
+}
+
+impl AsyncApiClient {
+    pub fn new(base_url: &Url, worker: &str, req_server_timeout: Duration) -> Self {
+        if worker == "" {
+            panic!("worker name cannot be empty")
+        }
+        let http_client = reqwest::ClientBuilder::new()
+            .timeout(req_server_timeout)
+            .build()
+            .expect("Failed to create request client");
+        Self {
+            register_url: base_url.join("/register").unwrap(),
+            block_to_prove_url: base_url.join("/block_to_prove").unwrap(),
+            working_on_url: base_url.join("/working_on").unwrap(),
+            extend_lease_url: base_url.join("/extend_lease").unwrap(),
+            prover_data_url: base_url.join("/prover_data").unwrap(),
+            verified_block_prover_data_url: base_url.join("/verified_block_prover_data").unwrap(),
+            publish_url: base_url.join("/publish").unwrap(),
+            stopped_url: base_url.join("/stopped").unwrap(),
+            record_failure_url: base_url.join("/record_failure").unwrap(),
+            worker: worker.to_string(),
+            http_client,
+        }
+    }
+
+    /// Registers the prover able to prove the blocks of the given sizes.
+    pub async fn register_prover(&self, block_sizes: &[usize]) -> Result<i32, failure::Error> {
+        with_retries_async(|| self.try_register_prover(block_sizes)).await
+    }
+
+    async fn try_register_prover(&self, block_sizes: &[usize]) -> Result<i32, failure::Error> {
+        info!("Registering prover...");
+        let res = self
+            .http_client
+            .post(self.register_url.as_str())
+            .json(&RegisterReq::new(&self.worker, block_sizes))
+            .send()
+            .await
+            .map_err(|e| format_err!("register request failed: {}", e))?;
+        if !res.status().is_success() {
+            bail!("register request failed with status: {}", res.status());
+        }
+        let text = res
+            .text()
+            .await
+            .map_err(|e| format_err!("failed to read register response: {}", e))?;
+
+        Ok(i32::from_str(&text)
+            .map_err(|e| format_err!("failed to parse register prover id: {}", e))?)
+    }
+
+    async fn try_block_to_prove(
+        &self,
+        block_size: usize,
+    ) -> Result<Option<(i64, i32)>, failure::Error> {
+        trace!("sending block_to_prove");
+        let res = self
+            .http_client
+            .get(self.block_to_prove_url.as_str())
+            .json(&ProverReq {
+                name: self.worker.clone(),
+                block_size,
+            })
+            .send()
+            .await
+            .map_err(|e| format_err!("block to prove request failed: {}", e))?;
+        let text = res
+            .text()
+            .await
+            .map_err(|e| format_err!("failed to read block to prove response: {}", e))?;
+        let res: Option<BlockToProveRes> = serde_json::from_str(&text)
+            .map_err(|e| format_err!("failed to parse block to prove response: {}", e))?;
+        Ok(res.map(|res| (res.block, res.prover_run_id)))
+    }
+
+    async fn try_prover_data(&self, block: i64) -> Result<ProverData, failure::Error> {
+        trace!("sending prover_data");
+        let res = self
+            .http_client
+            .get(self.prover_data_url.as_str())
+            .json(&block)
+            .send()
+            .await
+            .map_err(|e| format_err!("failed to request prover data: {}", e))?;
+        let text = res
+            .text()
+            .await
+            .map_err(|e| format_err!("failed to read prover data response: {}", e))?;
+        let res: Option<ProverData> = serde_json::from_str(&text)
+            .map_err(|e| format_err!("failed to parse prover data response: {}", e))?;
+        Ok(res.ok_or_else(|| format_err!("ProverData for block {} is not ready yet", block))?)
+    }
+
+    async fn try_publish(
+        &self,
+        block: i64,
+        proof: &EncodedProofPlonk,
+        stats: &ProvingStats,
+    ) -> Result<(), failure::Error> {
+        trace!("Trying publish proof {}", block);
+        let res = self
+            .http_client
+            .post(self.publish_url.as_str())
+            .json(&PublishReq {
+                block: block as u32,
+                proof: proof.clone(),
+                stats: stats.clone(),
+            })
+            .send()
+            .await
+            .map_err(|e| format_err!("failed to send publish request: {}", e))?;
+        let status = res.status();
+        if status == reqwest::StatusCode::CONFLICT {
+            return Err(ProofConflict { block }.into());
+        }
+        if status != reqwest::StatusCode::OK {
+            match res.text().await {
+                Ok(message) => bail!(
+                    "publish request failed with status: {} and message: {}",
+                    status,
+                    message
+                ),
+                Err(_) => bail!("publish request failed with status: {}", status),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl crate::AsyncApiClient for AsyncApiClient {
+    async fn block_to_prove(
+        &self,
+        block_size: usize,
+    ) -> Result<Option<(i64, i32)>, failure::Error> {
+        with_retries_async(|| self.try_block_to_prove(block_size)).await
+    }
+
+    async fn working_on(
+        &self,
+        job_id: i32,
+        progress: Option<JobProgress>,
+    ) -> Result<(), failure::Error> {
+        trace!("sending working_on {}, progress: {:?}", job_id, progress);
+        let res = self
+            .http_client
+            .post(self.working_on_url.as_str())
+            .json(&WorkingOnReq {
+                prover_run_id: job_id,
+                progress,
+            })
+            .send()
+            .await
+            .map_err(|e| format_err!("failed to send working on request: {}", e))?;
+        if res.status() != reqwest::StatusCode::OK {
+            bail!("working on request failed with status: {}", res.status())
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        trace!("sending extend_lease {} by {}s", job_id, extra_seconds);
+        let res = self
+            .http_client
+            .post(self.extend_lease_url.as_str())
+            .json(&ExtendLeaseReq {
+                prover_run_id: job_id,
+                extra_seconds,
+            })
+            .send()
+            .await
+            .map_err(|e| format_err!("failed to send extend lease request: {}", e))?;
+        if res.status() != reqwest::StatusCode::OK {
+            bail!("extend lease request failed with status: {}", res.status())
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn prover_data(
+        &self,
+        block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        let prover_data = with_retries_async(|| self.try_prover_data(block)).await?;
+        Ok(prover_data.into_circuit(block))
+    }
+
+    async fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        trace!("sending verified_block_prover_data {}", block);
+        let res = self
+            .http_client
+            .get(self.verified_block_prover_data_url.as_str())
+            .json(&block)
+            .send()
+            .await
+            .map_err(|e| format_err!("failed to request verified block prover data: {}", e))?;
+        let status = res.status();
+        let text = res.text().await.map_err(|e| {
+            format_err!("failed to read verified block prover data response: {}", e)
+        })?;
+        if status != reqwest::StatusCode::OK {
+            bail!(
+                "verified block prover data request failed with status: {} and message: {}",
+                status,
+                text
+            );
+        }
+        let res: Option<ProverData> = serde_json::from_str(&text).map_err(|e| {
+            format_err!("failed to parse verified block prover data response: {}", e)
+        })?;
+        let prover_data =
+            res.ok_or_else(|| format_err!("no ProverData for verified block {}", block))?;
+        Ok(prover_data.into_circuit(block))
+    }
+
+    async fn publish(&self, block: i64, proof: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.publish_with_stats(block, proof, ProvingStats::default())
+            .await
+    }
+
+    async fn publish_with_stats(
+        &self,
+        block: i64,
+        proof: EncodedProofPlonk,
+        mut stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        stats.worker_name.get_or_insert_with(|| self.worker.clone());
+        with_publish_retries_async(block, || self.try_publish(block, &proof, &stats)).await
+    }
+
+    async fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        self.http_client
+            .post(self.stopped_url.as_str())
+            .json(&prover_run_id)
+            .send()
+            .await
+            .map_err(|e| format_err!("prover stopped request failed: {}", e))?;
+        Ok(())
+    }
+
+    async fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        trace!("sending record_failure {}: {}", job_id, reason);
+        let res = self
+            .http_client
+            .post(self.record_failure_url.as_str())
+            .json(&RecordFailureReq {
+                prover_run_id: job_id,
+                reason: reason.to_string(),
+            })
+            .send()
+            .await
+            .map_err(|e| format_err!("failed to send record failure request: {}", e))?;
+        if res.status() != reqwest::StatusCode::OK {
+            bail!(
+                "record failure request failed with status: {}",
+                res.status()
+            )
+        } else {
+            Ok(())
+        }
+    }
+}
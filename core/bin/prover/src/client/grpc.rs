@@ -0,0 +1,338 @@
+//! gRPC implementation of the prover server API client.
+
+// Built-in deps
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+// External deps
+use failure::format_err;
+use log::*;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+// Workspace deps
+use crate::prover_data::ProverData;
+use crate::tls::TlsConfig;
+use circuit::circuit::FranklinCircuit;
+use models::node::Engine;
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
+// Local deps
+use self::proto::prover_service_client::ProverServiceClient;
+use super::{with_publish_retries, with_retries, ProofConflict, RegisterReq};
+use crate::{JobProgress, ProvingStage};
+
+pub mod proto {
+    tonic::include_proto!("prover");
+}
+
+impl From<ProvingStage> for proto::ProvingStage {
+    fn from(stage: ProvingStage) -> Self {
+        match stage {
+            ProvingStage::FetchingData => Self::FetchingData,
+            ProvingStage::Synthesis => Self::Synthesis,
+            ProvingStage::ProofGeneration => Self::ProofGeneration,
+            ProvingStage::Verification => Self::Verification,
+            ProvingStage::Publishing => Self::Publishing,
+        }
+    }
+}
+
+impl From<proto::ProvingStage> for ProvingStage {
+    fn from(stage: proto::ProvingStage) -> Self {
+        match stage {
+            proto::ProvingStage::FetchingData => Self::FetchingData,
+            proto::ProvingStage::Synthesis => Self::Synthesis,
+            proto::ProvingStage::ProofGeneration => Self::ProofGeneration,
+            proto::ProvingStage::Verification => Self::Verification,
+            proto::ProvingStage::Publishing => Self::Publishing,
+        }
+    }
+}
+
+impl From<JobProgress> for proto::JobProgress {
+    fn from(progress: JobProgress) -> Self {
+        Self {
+            stage: proto::ProvingStage::from(progress.stage) as i32,
+            elapsed_secs: progress.elapsed_secs,
+        }
+    }
+}
+
+impl From<RegisterReq> for proto::RegisterRequest {
+    fn from(req: RegisterReq) -> Self {
+        Self {
+            name: req.name,
+            block_sizes: req.block_sizes.iter().map(|size| *size as u64).collect(),
+            hostname: req.hostname.unwrap_or_default(),
+            version: req.version.unwrap_or_default(),
+            cpu_cores: req.cpu_cores.unwrap_or_default(),
+            vk_hash: req.vk_hash.unwrap_or_default(),
+        }
+    }
+}
+
+impl proto::JobProgress {
+    /// Converts the progress received over the wire, returns `None` if the stage is unknown.
+    pub fn decode(&self) -> Option<JobProgress> {
+        let stage = proto::ProvingStage::from_i32(self.stage)?;
+        Some(JobProgress {
+            stage: stage.into(),
+            elapsed_secs: self.elapsed_secs,
+        })
+    }
+}
+
+/// API client communicating with the prover server over gRPC.
+///
+/// `ApiClient` methods are blocking, so the client runs requests on its own runtime.
+#[derive(Debug, Clone)]
+pub struct GrpcApiClient {
+    client: ProverServiceClient<Channel>,
+    endpoint: Endpoint,
+    /// `Bearer` authorization sent with every request, see `with_secret_auth`.
+    secret_auth: Option<MetadataValue<Ascii>>,
+    runtime: Arc<Mutex<tokio::runtime::Runtime>>,
+    worker: String,
+}
+
+impl GrpcApiClient {
+    pub fn new(server_url: &str, worker: &str, req_server_timeout: Duration) -> Self {
+        if worker == "" {
+            panic!("worker name cannot be empty")
+        }
+        let runtime = tokio::runtime::Builder::new()
+            .threaded_scheduler()
+            .enable_all()
+            .build()
+            .expect("failed to create gRPC client runtime");
+        let endpoint = Endpoint::from_shared(server_url.to_string())
+            .expect("invalid prover server url")
+            .timeout(req_server_timeout);
+        Self {
+            client: connect(&runtime, &endpoint, None),
+            endpoint,
+            secret_auth: None,
+            runtime: Arc::new(Mutex::new(runtime)),
+            worker: worker.to_string(),
+        }
+    }
+
+    /// Sends the shared secret as a bearer token with every request.
+    pub fn with_secret_auth(mut self, secret_auth: &str) -> Self {
+        let auth = MetadataValue::from_str(&format!("Bearer {}", secret_auth))
+            .expect("prover secret auth is not a valid metadata value");
+        self.secret_auth = Some(auth);
+        self.reconnect();
+        self
+    }
+
+    /// Connects to the server over the mutual TLS, presenting the certificate signed by the CA
+    /// of the server. Server URL must have the `https` scheme.
+    pub fn with_tls(mut self, tls_config: &TlsConfig) -> Result<Self, failure::Error> {
+        let read = |path: &Path| {
+            fs::read(path).map_err(|e| format_err!("failed to read {}: {}", path.display(), e))
+        };
+        let tls = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(read(&tls_config.ca_pem)?))
+            .identity(Identity::from_pem(
+                read(&tls_config.cert_pem)?,
+                read(&tls_config.key_pem)?,
+            ));
+        self.endpoint = self.endpoint.tls_config(tls);
+        self.reconnect();
+        Ok(self)
+    }
+
+    /// Recreates the client with the current endpoint and authorization.
+    fn reconnect(&mut self) {
+        let runtime = self
+            .runtime
+            .lock()
+            .expect("gRPC client runtime lock is poisoned");
+        self.client = connect(&runtime, &self.endpoint, self.secret_auth.as_ref());
+    }
+
+    /// Performs the request to the server, blocking until the response is received.
+    fn request<T, F, Fut>(&self, op: F) -> Result<T, tonic::Status>
+    where
+        F: FnOnce(ProverServiceClient<Channel>) -> Fut,
+        Fut: std::future::Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+    {
+        let future = op(self.client.clone());
+        let response = self
+            .runtime
+            .lock()
+            .expect("gRPC client runtime lock is poisoned")
+            .block_on(future)?;
+        Ok(response.into_inner())
+    }
+
+    fn prover_request(&self, block_size: usize) -> proto::ProverRequest {
+        proto::ProverRequest {
+            name: self.worker.clone(),
+            block_size: block_size as u64,
+        }
+    }
+
+    /// Registers the prover able to prove the blocks of the given sizes,
+    /// along with the metadata of the machine.
+    pub fn register_prover(&self, block_sizes: &[usize]) -> Result<i32, failure::Error> {
+        let request = proto::RegisterRequest::from(RegisterReq::new(&self.worker, block_sizes));
+        let op = || -> Result<i32, failure::Error> {
+            info!("Registering prover...");
+            let request = request.clone();
+            let res = self
+                .request(|mut client| async move { client.register(request).await })
+                .map_err(|e| format_err!("register request failed: {}", e))?;
+            Ok(res.prover_id)
+        };
+
+        Ok(with_retries(&op)?)
+    }
+}
+
+/// Creates the client of the server at `endpoint`, sending `secret_auth` with every request.
+/// Connection is established lazily, so client can be created before the server is started.
+fn connect(
+    runtime: &tokio::runtime::Runtime,
+    endpoint: &Endpoint,
+    secret_auth: Option<&MetadataValue<Ascii>>,
+) -> ProverServiceClient<Channel> {
+    let channel = runtime
+        .enter(|| endpoint.connect_lazy())
+        .expect("failed to create gRPC channel");
+    match secret_auth.cloned() {
+        Some(auth) => {
+            ProverServiceClient::with_interceptor(channel, move |mut request: tonic::Request<()>| {
+                request.metadata_mut().insert("authorization", auth.clone());
+                Ok(request)
+            })
+        }
+        None => ProverServiceClient::new(channel),
+    }
+}
+
+impl crate::ApiClient for GrpcApiClient {
+    fn block_to_prove(&self, block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        let op = || -> Result<Option<(i64, i32)>, failure::Error> {
+            trace!("sending block_to_prove");
+            let request = self.prover_request(block_size);
+            let res = self
+                .request(|mut client| async move { client.block_to_prove(request).await })
+                .map_err(|e| format_err!("block to prove request failed: {}", e))?;
+            Ok(res.job.map(|job| (job.block, job.prover_run_id)))
+        };
+
+        Ok(with_retries(&op)?)
+    }
+
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error> {
+        trace!("sending working_on {}, progress: {:?}", job_id, progress);
+        let request = proto::WorkingOnRequest {
+            prover_run_id: job_id,
+            progress: progress.map(Into::into),
+        };
+        self.request(|mut client| async move { client.working_on(request).await })
+            .map_err(|e| format_err!("failed to send working on request: {}", e))?;
+        Ok(())
+    }
+
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        trace!("sending extend_lease {} by {}s", job_id, extra_seconds);
+        let request = proto::ExtendLeaseRequest {
+            prover_run_id: job_id,
+            extra_seconds,
+        };
+        self.request(|mut client| async move { client.extend_lease(request).await })
+            .map_err(|e| format_err!("failed to send extend lease request: {}", e))?;
+        Ok(())
+    }
+
+    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        let op = || -> Result<ProverData, failure::Error> {
+            trace!("sending prover_data");
+            let request = proto::ProverDataRequest { block };
+            let res = self
+                .request(|mut client| async move { client.prover_data(request).await })
+                .map_err(|e| format_err!("failed to request prover data: {}", e))?;
+            let res: Option<ProverData> = serde_json::from_slice(&res.prover_data)
+                .map_err(|e| format_err!("failed to parse prover data response: {}", e))?;
+            Ok(res.ok_or_else(|| format_err!("ProverData for block {} is not ready yet", block))?)
+        };
+
+        let prover_data = with_retries(&op)?;
+        Ok(prover_data.into_circuit(block))
+    }
+
+    fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        trace!("sending verified_block_prover_data {}", block);
+        let request = proto::ProverDataRequest { block };
+        let res = self
+            .request(|mut client| async move { client.verified_block_prover_data(request).await })
+            .map_err(|e| format_err!("failed to request verified block prover data: {}", e))?;
+        let res: Option<ProverData> = serde_json::from_slice(&res.prover_data).map_err(|e| {
+            format_err!("failed to parse verified block prover data response: {}", e)
+        })?;
+        let prover_data =
+            res.ok_or_else(|| format_err!("no ProverData for verified block {}", block))?;
+        Ok(prover_data.into_circuit(block))
+    }
+
+    fn publish(&self, block: i64, proof: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.publish_with_stats(block, proof, ProvingStats::default())
+    }
+
+    fn publish_with_stats(
+        &self,
+        block: i64,
+        proof: EncodedProofPlonk,
+        mut stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        stats.worker_name.get_or_insert_with(|| self.worker.clone());
+        let proof = serde_json::to_vec(&proof)
+            .map_err(|e| format_err!("failed to serialize proof: {}", e))?;
+        let stats = serde_json::to_vec(&stats)
+            .map_err(|e| format_err!("failed to serialize proving stats: {}", e))?;
+        let op = move || -> Result<(), failure::Error> {
+            trace!("Trying publish proof {}", block);
+            let request = proto::PublishRequest {
+                block,
+                proof: proof.clone(),
+                stats: stats.clone(),
+            };
+            let res = self.request(|mut client| async move { client.publish(request).await });
+            match res {
+                Ok(_) => Ok(()),
+                Err(status) if status.code() == tonic::Code::AlreadyExists => {
+                    Err(ProofConflict { block }.into())
+                }
+                Err(status) => Err(format_err!("publish request failed: {}", status)),
+            }
+        };
+
+        with_publish_retries(block, &op)
+    }
+
+    fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        let request = proto::StoppedRequest {
+            prover_id: prover_run_id,
+        };
+        self.request(|mut client| async move { client.stopped(request).await })
+            .map_err(|e| format_err!("prover stopped request failed: {}", e))?;
+        Ok(())
+    }
+
+    fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        trace!("sending record_failure {}: {}", job_id, reason);
+        let request = proto::RecordFailureRequest {
+            prover_run_id: job_id,
+            reason: reason.to_string(),
+        };
+        self.request(|mut client| async move { client.record_failure(request).await })
+            .map_err(|e| format_err!("failed to send record failure request: {}", e))?;
+        Ok(())
+    }
+}
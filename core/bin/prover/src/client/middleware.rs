@@ -0,0 +1,60 @@
+//! Hooks invoked by the HTTP prover server API client around its requests.
+
+// Built-in deps
+use std::fmt;
+use std::time::Duration;
+// External deps
+use log::*;
+use reqwest::StatusCode;
+
+/// Hooks invoked by `ApiClient` around every HTTP request to the prover server,
+/// e.g. to record the requests as the tracing spans or the metrics.
+/// Every attempt of the retried request is reported separately.
+///
+/// `request` is the name of the API call, e.g. `"block to prove"`, the same as in `ClientError`.
+pub trait ApiClientMiddleware: fmt::Debug + Send + Sync {
+    /// Called right before the request is sent.
+    fn on_request(&self, _request: &'static str) {}
+
+    /// Called once the response headers are received, or the request has failed.
+    /// `status` is `None` if no response was received, e.g. the server was not reachable.
+    fn on_response(
+        &self,
+        _request: &'static str,
+        _duration: Duration,
+        _status: Option<StatusCode>,
+    ) {
+    }
+}
+
+/// Middleware doing nothing, used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMiddleware;
+
+impl ApiClientMiddleware for NoopMiddleware {}
+
+/// Middleware logging every request with its duration and the response status.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LoggingMiddleware;
+
+impl ApiClientMiddleware for LoggingMiddleware {
+    fn on_request(&self, request: &'static str) {
+        trace!("sending {} request", request);
+    }
+
+    fn on_response(&self, request: &'static str, duration: Duration, status: Option<StatusCode>) {
+        match status {
+            Some(status) => debug!(
+                "{} request completed in {}ms with status {}",
+                request,
+                duration.as_millis(),
+                status
+            ),
+            None => debug!(
+                "{} request failed after {}ms",
+                request,
+                duration.as_millis()
+            ),
+        }
+    }
+}
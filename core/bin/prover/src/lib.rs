@@ -1,31 +1,88 @@
+pub mod admin;
 pub mod cli_utils;
 pub mod client;
 pub mod exit_proof;
+pub mod logging;
+pub mod metrics;
+pub mod parallel_prover;
+pub mod params;
+pub mod pinned_blocks;
 pub mod plonk_step_by_step_prover;
+pub mod pool;
+pub mod proof_cache;
+pub mod proof_spool;
 pub mod prover_data;
 pub mod serialization;
+pub mod telemetry;
+pub mod testing;
+pub mod tls;
 
 // Built-in deps
+use std::collections::HashMap;
 use std::sync::{
-    atomic::{AtomicBool, AtomicI32, Ordering},
-    mpsc, Arc,
+    atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering},
+    mpsc, Arc, Mutex,
 };
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
     fmt::{self, Debug},
-    thread,
+    panic, thread,
 };
 // External deps
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use clap::{Arg, ArgMatches};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::task;
 // Workspace deps
-use models::{config_options::ProverOptions, node::Engine, prover_utils::EncodedProofPlonk};
+use models::{
+    config_options::ProverOptions,
+    node::Engine,
+    prover_utils::{EncodedProofPlonk, ProvingStats},
+};
+// Local deps
+use crate::metrics::ProverMetrics;
 
 const ABSENT_PROVER_ID: i32 = -1;
 
+/// Version of the prover, reported to the server along with the proofs.
+pub const PROVER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Prover options which can be replaced while the prover is running, e.g. through the admin endpoint.
+/// Rounds and heartbeats load the latest options on every iteration.
+pub type SharedProverOptions = Arc<ArcSwap<ProverOptions>>;
+/// Interval of checking whether the prover should be stopped without finishing the current round.
+const ABORT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+/// Rounds don't take a new job while this many computations abandoned by `run_with_deadline`
+/// are still running: they occupy the CPU cores the next proof needs.
+const MAX_ABANDONED_COMPUTATIONS: usize = 1;
+/// Fraction of the prover timeout after which the job lease is extended, counting from
+/// the job start or the last extension.
+const LEASE_EXTENSION_THRESHOLD: f64 = 0.75;
+
+/// Defines what prover does with the proof being computed when shutdown is requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownBehavior {
+    /// Stop right away, abandoning the proof being computed.
+    AbortImmediate,
+    /// Finish and publish the proof being computed, and only then stop.
+    FinishCurrentProof,
+}
+
+impl Default for ShutdownBehavior {
+    fn default() -> Self {
+        Self::FinishCurrentProof
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ShutdownRequest {
     shutdown_requested: Arc<AtomicBool>,
     prover_id: Arc<AtomicI32>,
+    behavior: ShutdownBehavior,
+    /// Computations abandoned by the rounds which are still running, see `run_with_deadline`.
+    abandoned_computations: Arc<AtomicUsize>,
 }
 
 impl Default for ShutdownRequest {
@@ -35,6 +92,8 @@ impl Default for ShutdownRequest {
         Self {
             shutdown_requested: Default::default(),
             prover_id,
+            behavior: Default::default(),
+            abandoned_computations: Default::default(),
         }
     }
 }
@@ -44,6 +103,29 @@ impl ShutdownRequest {
         Self::default()
     }
 
+    pub fn with_behavior(behavior: ShutdownBehavior) -> Self {
+        Self {
+            behavior,
+            ..Self::default()
+        }
+    }
+
+    pub fn behavior(&self) -> ShutdownBehavior {
+        self.behavior
+    }
+
+    /// Returns the request with the same prover ID and behavior, which is set independently
+    /// of this one. Used to stop a single prover of the pool, see `pool::ProverPool`.
+    /// Abandoned computations are shared, since the provers of the pool share the CPU cores.
+    pub fn detached(&self) -> Self {
+        Self {
+            shutdown_requested: Default::default(),
+            prover_id: Arc::clone(&self.prover_id),
+            behavior: self.behavior,
+            abandoned_computations: Arc::clone(&self.abandoned_computations),
+        }
+    }
+
     pub fn set_prover_id(&self, id: i32) {
         self.prover_id.store(id, Ordering::SeqCst);
     }
@@ -59,11 +141,29 @@ impl ShutdownRequest {
     pub fn get(&self) -> bool {
         self.shutdown_requested.load(Ordering::SeqCst)
     }
+
+    /// Returns `true` if shutdown was requested and the current round must not be finished.
+    pub fn abort_requested(&self) -> bool {
+        self.get() && self.behavior == ShutdownBehavior::AbortImmediate
+    }
+
+    /// Returns the amount of computations abandoned by `run_with_deadline` which are still running.
+    pub fn abandoned_computations(&self) -> usize {
+        self.abandoned_computations.load(Ordering::SeqCst)
+    }
 }
 
 /// Trait that provides type needed by prover to initialize.
 pub trait ProverConfig {
     fn from_env() -> Self;
+
+    /// Command line arguments specific to the prover, accepted along with the common ones.
+    fn cli_args() -> Vec<Arg<'static, 'static>> {
+        Vec::new()
+    }
+
+    /// Overrides the config loaded from the environment with the prover specific arguments.
+    fn apply_cli_args(&mut self, _matches: &ArgMatches<'_>) {}
 }
 
 /// Trait that tries to separate prover from networking (API)
@@ -73,168 +173,1360 @@ pub trait ProverImpl<C: ApiClient> {
     /// Config concrete type used by current prover
     type Config: ProverConfig;
     /// Creates prover from config and API client.
-    fn create_from_config(config: Self::Config, client: C, heartbeat: Duration) -> Self;
-    /// Fetches job from the server and creates proof for it
+    /// If metrics are provided, prover records its observations there.
+    fn create_from_config(
+        config: Self::Config,
+        client: C,
+        heartbeat: Duration,
+        metrics: Option<Arc<ProverMetrics>>,
+    ) -> Self;
+    /// Fetches job from the server and creates proof for it.
+    /// ID of the obtained job (or `None` if there is no job) must be sent to the heartbeat routine,
+    /// followed by the stages of the job processing as they start.
+    /// If the round fails, the job is considered abandoned and heartbeats for it are stopped
+    /// by the caller.
+    /// Jobs may come in any block order: prover data of the block starts from its own old root,
+    /// so the blocks are proved independently, e.g. by several provers at once.
     fn next_round(
         &self,
-        start_heartbeats_tx: mpsc::Sender<(i32, bool)>,
-    ) -> Result<(), BabyProverError>;
+        start_heartbeats_tx: mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError>;
     /// Returns client reference and config needed for heartbeat.
     fn get_heartbeat_options(&self) -> (&C, Duration);
+    /// Returns the policy of delays between the rounds failed with retryable API errors.
+    fn retry_policy(&self) -> &RetryPolicy;
+    /// Returns the block sizes the prover is able to prove, reported to the server
+    /// on registration, so the prover isn't assigned the blocks of other sizes.
+    fn supported_block_sizes(&self) -> Vec<usize>;
+}
+
+/// Result of the successfully completed prover round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundOutcome {
+    /// Job was obtained from the server and its proof was published.
+    JobDone,
+    /// Server had no job for the prover.
+    NoJob,
+    /// Job was abandoned without the proof, e.g. since its generation exceeded the time limit.
+    JobAbandoned,
+}
+
+/// Stage of the job processing, reported to the server along with the heartbeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProvingStage {
+    /// Prover data of the block is being downloaded.
+    FetchingData,
+    /// Circuit is being synthesized, e.g. to prepare the setup for the block size.
+    Synthesis,
+    /// Proof is being computed.
+    ProofGeneration,
+    /// Computed proof is being verified.
+    Verification,
+    /// Proof is being sent to the server.
+    Publishing,
 }
 
-pub trait ApiClient: Debug {
+/// Progress of the job, sent with the `working_on` heartbeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub stage: ProvingStage,
+    /// Seconds passed since the stage was started.
+    pub elapsed_secs: u64,
+}
+
+/// Request sent by the prover worker to the heartbeat routine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatRequest {
+    /// Worker started working on the job with the given ID, or has no job if `None`.
+    Job(Option<i32>),
+    /// Current job of the worker reached the given stage.
+    Stage(ProvingStage),
+    /// Heartbeat routine must be stopped.
+    Exit,
+}
+
+#[async_trait]
+pub trait ApiClient: Debug + Send + Sync {
     fn block_to_prove(&self, block_size: usize) -> Result<Option<(i64, i32)>, failure::Error>;
-    fn working_on(&self, job_id: i32) -> Result<(), failure::Error>;
+    /// Notifies the server that the job is still being processed.
+    /// Progress is `None` until the first stage of the job is reported.
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error>;
+    /// Asks the server not to reassign the job to another prover for `extra_seconds`,
+    /// even if its proof generation exceeds the prover timeout.
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error>;
     fn prover_data(
         &self,
         block: i64,
-    ) -> Result<circuit::circuit::FranklinCircuit<'_, Engine>, failure::Error>;
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error>;
+    /// Requests the prover data of the already verified block, bypassing the job queue.
+    /// Used to re-prove the historical blocks, see `ProverOptions::block_range`.
+    fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error>;
     fn publish(&self, block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error>;
+    /// Publishes the proof along with the statistics of its generation.
+    /// Clients which don't report the statistics to the server publish the proof only.
+    fn publish_with_stats(
+        &self,
+        block: i64,
+        p: EncodedProofPlonk,
+        _stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        self.publish(block, p)
+    }
+    /// Publishes the proofs of several blocks at once, returning the outcome for every proof.
+    /// Blocks must be strictly increasing. Server stores either all the proofs of the batch or
+    /// none of them. By default the proofs are published one by one, so the proofs preceding
+    /// the conflicting one stay published, and the following ones are not sent.
+    fn publish_batch(
+        &self,
+        items: Vec<(i64, EncodedProofPlonk)>,
+    ) -> Result<Vec<client::PublishResult>, failure::Error> {
+        let mut results = Vec::with_capacity(items.len());
+        for (block, proof) in items {
+            if results.contains(&client::PublishResult::Conflict) {
+                results.push(client::PublishResult::NotCommitted);
+                continue;
+            }
+            match self.publish(block, proof) {
+                Ok(()) => results.push(client::PublishResult::Stored),
+                Err(e) if e.downcast_ref::<client::ProofConflict>().is_some() => {
+                    results.push(client::PublishResult::Conflict)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(results)
+    }
     fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error>;
+    /// Reports the failure of the job to the server, so its block is reassigned right away
+    /// instead of after the prover timeout.
+    fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error>;
+    /// Requests the health of the server, see `check_server_health`.
+    /// Clients which don't support the health check report the error.
+    fn health_check(&self) -> Result<client::ServerHealth, failure::Error> {
+        failure::bail!("health check is not supported by the client")
+    }
+    /// Requests the status of the proof of the block, e.g. to wait until it's stored.
+    /// Clients which don't support the request report the error.
+    fn proof_status(&self, _block: i64) -> Result<client::ProofStatus, failure::Error> {
+        failure::bail!("proof status is not supported by the client")
+    }
+    /// Sends the hashes of the verification keys of the prover to be compared with the ones
+    /// expected by the server, see `check_verification_keys`.
+    /// Clients which don't support the check report the error.
+    fn check_vk(
+        &self,
+        _vk_hashes: &[client::VkHash],
+    ) -> Result<client::CheckVkRes, failure::Error> {
+        failure::bail!("verification keys check is not supported by the client")
+    }
+
+    // Asynchronous versions of the methods above.
+    // By default they run the blocking implementation without stalling other tasks of the runtime,
+    // so they must be called within the multi-threaded tokio runtime. Clients able to send
+    // the requests without blocking override them, e.g. `client::ApiClient` does so for
+    // the requests of the heartbeat routine.
+
+    async fn block_to_prove_async(
+        &self,
+        block_size: usize,
+    ) -> Result<Option<(i64, i32)>, failure::Error> {
+        task::block_in_place(|| self.block_to_prove(block_size))
+    }
+
+    async fn working_on_async(
+        &self,
+        job_id: i32,
+        progress: Option<JobProgress>,
+    ) -> Result<(), failure::Error> {
+        task::block_in_place(|| self.working_on(job_id, progress))
+    }
+
+    async fn extend_lease_async(
+        &self,
+        job_id: i32,
+        extra_seconds: u32,
+    ) -> Result<(), failure::Error> {
+        task::block_in_place(|| self.extend_lease(job_id, extra_seconds))
+    }
+
+    async fn prover_data_async(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        task::block_in_place(|| self.prover_data(block))
+    }
+
+    async fn prover_data_for_block_async(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        task::block_in_place(|| self.prover_data_for_block(block))
+    }
+
+    async fn publish_async(&self, block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        task::block_in_place(|| self.publish(block, p))
+    }
+
+    async fn publish_with_stats_async(
+        &self,
+        block: i64,
+        p: EncodedProofPlonk,
+        stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        task::block_in_place(|| self.publish_with_stats(block, p, stats))
+    }
+
+    async fn prover_stopped_async(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        task::block_in_place(|| self.prover_stopped(prover_run_id))
+    }
+
+    async fn record_failure_async(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        task::block_in_place(|| self.record_failure(job_id, reason))
+    }
+}
+
+/// Asynchronous counterpart of the `ApiClient`, implemented by the clients which don't block
+/// the runtime threads while waiting for the server.
+/// Every `ApiClient` is an `AsyncApiClient` as well, through its `*_async` methods.
+#[async_trait]
+pub trait AsyncApiClient: Debug + Send + Sync {
+    async fn block_to_prove(&self, block_size: usize)
+        -> Result<Option<(i64, i32)>, failure::Error>;
+    async fn working_on(
+        &self,
+        job_id: i32,
+        progress: Option<JobProgress>,
+    ) -> Result<(), failure::Error>;
+    async fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error>;
+    async fn prover_data(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error>;
+    async fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error>;
+    async fn publish(&self, block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error>;
+    /// See `ApiClient::publish_with_stats`.
+    async fn publish_with_stats(
+        &self,
+        block: i64,
+        p: EncodedProofPlonk,
+        _stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        self.publish(block, p).await
+    }
+    async fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error>;
+    async fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error>;
+}
+
+#[async_trait]
+impl<C: ApiClient> AsyncApiClient for C {
+    async fn block_to_prove(
+        &self,
+        block_size: usize,
+    ) -> Result<Option<(i64, i32)>, failure::Error> {
+        self.block_to_prove_async(block_size).await
+    }
+
+    async fn working_on(
+        &self,
+        job_id: i32,
+        progress: Option<JobProgress>,
+    ) -> Result<(), failure::Error> {
+        self.working_on_async(job_id, progress).await
+    }
+
+    async fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        self.extend_lease_async(job_id, extra_seconds).await
+    }
+
+    async fn prover_data(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        self.prover_data_async(block).await
+    }
+
+    async fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        self.prover_data_for_block_async(block).await
+    }
+
+    async fn publish(&self, block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.publish_async(block, p).await
+    }
+
+    async fn publish_with_stats(
+        &self,
+        block: i64,
+        p: EncodedProofPlonk,
+        stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        self.publish_with_stats_async(block, p, stats).await
+    }
+
+    async fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        self.prover_stopped_async(prover_run_id).await
+    }
+
+    async fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        self.record_failure_async(job_id, reason).await
+    }
+}
+
+/// Adapter which allows to use the `AsyncApiClient` with the provers requiring the `ApiClient`.
+///
+/// Blocking methods (used by the prover rounds) run the requests on the adapter's own runtime,
+/// while the asynchronous ones (used by the heartbeat routine) are forwarded to the inner client.
+#[derive(Debug, Clone)]
+pub struct BlockingApiClient<C> {
+    inner: C,
+    runtime: Arc<Mutex<tokio::runtime::Runtime>>,
+}
+
+impl<C: AsyncApiClient> BlockingApiClient<C> {
+    pub fn new(inner: C) -> Self {
+        let runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .expect("failed to create blocking api client runtime");
+        Self {
+            inner,
+            runtime: Arc::new(Mutex::new(runtime)),
+        }
+    }
+
+    /// Returns the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.runtime
+            .lock()
+            .expect("blocking api client runtime lock is poisoned")
+            .block_on(future)
+    }
+}
+
+#[async_trait]
+impl<C: AsyncApiClient> ApiClient for BlockingApiClient<C> {
+    fn block_to_prove(&self, block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        self.block_on(self.inner.block_to_prove(block_size))
+    }
+
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error> {
+        self.block_on(self.inner.working_on(job_id, progress))
+    }
+
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        self.block_on(self.inner.extend_lease(job_id, extra_seconds))
+    }
+
+    fn prover_data(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        self.block_on(self.inner.prover_data(block))
+    }
+
+    fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        self.block_on(self.inner.prover_data_for_block(block))
+    }
+
+    fn publish(&self, block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.block_on(self.inner.publish(block, p))
+    }
+
+    fn publish_with_stats(
+        &self,
+        block: i64,
+        p: EncodedProofPlonk,
+        stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        self.block_on(self.inner.publish_with_stats(block, p, stats))
+    }
+
+    fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        self.block_on(self.inner.prover_stopped(prover_run_id))
+    }
+
+    fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        self.block_on(self.inner.record_failure(job_id, reason))
+    }
+
+    async fn block_to_prove_async(
+        &self,
+        block_size: usize,
+    ) -> Result<Option<(i64, i32)>, failure::Error> {
+        self.inner.block_to_prove(block_size).await
+    }
+
+    async fn working_on_async(
+        &self,
+        job_id: i32,
+        progress: Option<JobProgress>,
+    ) -> Result<(), failure::Error> {
+        self.inner.working_on(job_id, progress).await
+    }
+
+    async fn extend_lease_async(
+        &self,
+        job_id: i32,
+        extra_seconds: u32,
+    ) -> Result<(), failure::Error> {
+        self.inner.extend_lease(job_id, extra_seconds).await
+    }
+
+    async fn prover_data_async(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        self.inner.prover_data(block).await
+    }
+
+    async fn prover_data_for_block_async(
+        &self,
+        block: i64,
+    ) -> Result<circuit::circuit::FranklinCircuit<'static, Engine>, failure::Error> {
+        self.inner.prover_data_for_block(block).await
+    }
+
+    async fn publish_async(&self, block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.inner.publish(block, p).await
+    }
+
+    async fn publish_with_stats_async(
+        &self,
+        block: i64,
+        p: EncodedProofPlonk,
+        stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        self.inner.publish_with_stats(block, p, stats).await
+    }
+
+    async fn prover_stopped_async(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        self.inner.prover_stopped(prover_run_id).await
+    }
+
+    async fn record_failure_async(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        self.inner.record_failure(job_id, reason).await
+    }
+}
+
+/// Policy of delays between prover rounds after consecutive API errors or rounds without a job.
+/// Delay grows exponentially from `initial_delay` up to `max_delay` and is randomly
+/// shifted by `jitter_fraction` of its value, so restarted provers don't hit the server at once.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    pub jitter_fraction: f64,
+}
+
+impl RetryPolicy {
+    const DEFAULT_MULTIPLIER: f64 = 2.0;
+    const DEFAULT_JITTER_FRACTION: f64 = 0.1;
+
+    /// Creates the policy with the default multiplier and jitter.
+    pub fn new(initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            max_delay,
+            multiplier: Self::DEFAULT_MULTIPLIER,
+            jitter_fraction: Self::DEFAULT_JITTER_FRACTION,
+        }
+    }
+
+    pub fn from_options(options: &ProverOptions) -> Self {
+        Self::new(options.retry_initial_delay, options.retry_max_delay)
+    }
+
+    /// Policy of waits between the rounds without a job. Wait starts at `cycle_wait` and
+    /// grows up to `idle_backoff_max`, jitter is not applied.
+    pub fn idle_from_options(options: &ProverOptions) -> Self {
+        Self {
+            initial_delay: options.cycle_wait,
+            max_delay: options.idle_backoff_max.max(options.cycle_wait),
+            multiplier: Self::DEFAULT_MULTIPLIER,
+            jitter_fraction: 0.0,
+        }
+    }
+
+    /// Returns the delay without jitter after the given number of consecutive errors.
+    pub fn base_delay(&self, consecutive_errors: u32) -> Duration {
+        let exponent = consecutive_errors.saturating_sub(1) as i32;
+        let delay_secs = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        if delay_secs >= self.max_delay.as_secs_f64() {
+            self.max_delay
+        } else {
+            Duration::from_secs_f64(delay_secs)
+        }
+    }
+
+    /// Returns the delay with random jitter after the given number of consecutive errors.
+    pub fn delay(&self, consecutive_errors: u32) -> Duration {
+        let base_delay = self.base_delay(consecutive_errors);
+        if self.jitter_fraction <= 0.0 {
+            return base_delay;
+        }
+
+        let jitter = rand::thread_rng().gen_range(-self.jitter_fraction, self.jitter_fraction);
+        base_delay.mul_f64(1.0 + jitter)
+    }
 }
 
 #[derive(Debug)]
 pub enum BabyProverError {
-    Api(String),
-    Internal(String),
+    /// Request to the prover server failed.
+    /// Retryable errors (e.g. timeouts or unavailable server) are followed by another round,
+    /// while non-retryable ones stop the prover.
+    Api {
+        message: String,
+        retryable: bool,
+        source: Option<failure::Compat<failure::Error>>,
+    },
+    /// Prover failed to create the proof (e.g. proof verification failed). Always stops the prover.
+    Internal {
+        message: String,
+        /// Block the prover was working on, if any.
+        block: Option<i64>,
+        /// Job the prover was working on, if any. Its failure is reported to the server.
+        job_id: Option<i32>,
+    },
+    /// Another prover has already published the proof for the block, so the server won't
+    /// accept this one. Publishing is not retried, but the prover keeps running.
+    AlreadyPublished { block: i64 },
+    /// Round was cancelled because of the immediate shutdown request.
+    Stop,
+}
+
+impl BabyProverError {
+    /// Creates the API error. Most of the failed requests (including timeouts) are caused by
+    /// the temporarily unavailable server, so they are retried by default, unless the error
+    /// is the fatal `client::ClientError`, e.g. the rejected secret auth.
+    pub fn from_api(err: failure::Error) -> Self {
+        let retryable = !client::is_fatal(&err);
+        Self::api(err, retryable)
+    }
+
+    pub fn api(err: failure::Error, retryable: bool) -> Self {
+        BabyProverError::Api {
+            message: err.to_string(),
+            retryable,
+            source: Some(err.compat()),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        BabyProverError::Internal {
+            message: message.into(),
+            block: None,
+            job_id: None,
+        }
+    }
+
+    pub fn internal_for_block(block: i64, message: impl Into<String>) -> Self {
+        BabyProverError::Internal {
+            message: message.into(),
+            block: Some(block),
+            job_id: None,
+        }
+    }
+
+    /// Converts the failed publish of the proof for the block. Server having a different proof
+    /// for the block means that the block is already proven by another prover.
+    pub fn from_publish(block: i64, err: failure::Error) -> Self {
+        if err.downcast_ref::<client::ProofConflict>().is_some() {
+            BabyProverError::AlreadyPublished { block }
+        } else {
+            Self::from_api(err)
+        }
+    }
+
+    /// Returns `true` if the prover may keep running after this error.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            BabyProverError::Api { retryable, .. } => *retryable,
+            BabyProverError::AlreadyPublished { .. } => true,
+            BabyProverError::Internal { .. } | BabyProverError::Stop => false,
+        }
+    }
+
+    /// Attaches the job the error occurred in. Only internal errors are reported to the server
+    /// as the job failures, so the other errors are left as is.
+    pub fn with_job(mut self, job_id: i32) -> Self {
+        if let BabyProverError::Internal { job_id: job, .. } = &mut self {
+            *job = Some(job_id);
+        }
+        self
+    }
+
+    /// Returns the block the error is related to, if known.
+    pub fn block(&self) -> Option<i64> {
+        match self {
+            BabyProverError::Internal { block, .. } => *block,
+            BabyProverError::AlreadyPublished { block } => Some(*block),
+            BabyProverError::Api { .. } | BabyProverError::Stop => None,
+        }
+    }
+
+    /// Returns the job the error is related to, if known.
+    pub fn job_id(&self) -> Option<i32> {
+        match self {
+            BabyProverError::Internal { job_id, .. } => *job_id,
+            BabyProverError::Api { .. }
+            | BabyProverError::AlreadyPublished { .. }
+            | BabyProverError::Stop => None,
+        }
+    }
 }
 
 impl fmt::Display for BabyProverError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        let desc = match self {
-            BabyProverError::Api(s) => s,
-            BabyProverError::Internal(s) => s,
-        };
-        write!(f, "{}", desc)
+        match self {
+            BabyProverError::Api { message, .. } => write!(f, "{}", message),
+            BabyProverError::Internal { message, .. } => write!(f, "{}", message),
+            BabyProverError::AlreadyPublished { block } => {
+                write!(f, "proof for block {} is already published", block)
+            }
+            BabyProverError::Stop => write!(f, "round was cancelled by the shutdown request"),
+        }
     }
 }
 
+/// Failing to notify the heartbeat routine means that it is not running anymore, so heartbeats
+/// for the job are not sent and the job would be reassigned to another prover.
+impl<T> From<mpsc::SendError<T>> for BabyProverError {
+    fn from(_: mpsc::SendError<T>) -> Self {
+        BabyProverError::internal("heartbeat routine is not running")
+    }
+}
+
+impl std::error::Error for BabyProverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BabyProverError::Api { source, .. } => source
+                .as_ref()
+                .map(|err| err as &(dyn std::error::Error + 'static)),
+            BabyProverError::Internal { .. }
+            | BabyProverError::AlreadyPublished { .. }
+            | BabyProverError::Stop => None,
+        }
+    }
+}
+
+/// Checks that the server is able to give out the jobs before the prover starts taking them,
+/// so the prover fails fast instead of retrying the requests to the degraded server.
+/// Fails if the server is not reachable or reports that its database is not connected.
+pub fn check_server_health<C: ApiClient>(
+    api_client: &C,
+) -> Result<client::ServerHealth, BabyProverError> {
+    let health = api_client
+        .health_check()
+        .map_err(|e| BabyProverError::api(e, false))?;
+    if !health.db_connected {
+        return Err(BabyProverError::internal(format!(
+            "prover server {} is not connected to the database",
+            health.version
+        )));
+    }
+    Ok(health)
+}
+
+/// Checks that the verification keys of the prover are the ones expected by the server before
+/// the prover starts taking the jobs, so the prover with the stale keys (e.g. after the trusted
+/// setup refresh) doesn't publish the proofs failing the verification.
+/// Fails if the server is not reachable or reports a mismatched key.
+pub fn check_verification_keys<C: ApiClient>(
+    api_client: &C,
+    vk_hashes: &[client::VkHash],
+) -> Result<(), BabyProverError> {
+    let res = api_client
+        .check_vk(vk_hashes)
+        .map_err(|e| BabyProverError::api(e, false))?;
+    if !res.mismatched_block_sizes.is_empty() {
+        return Err(BabyProverError::internal(format!(
+            "verification keys of block sizes {:?} don't match the ones expected by the prover server",
+            res.mismatched_block_sizes
+        )));
+    }
+    Ok(())
+}
+
+/// Runs the long computation (e.g. proof generation) in a separate thread, checking
+/// whether the immediate shutdown was requested before starting it and while waiting for it
+/// to finish.
+///
+/// Returns `BabyProverError::Stop` as soon as the shutdown is requested, the result of the
+/// computation is discarded in this case. Computation can't be interrupted, so its thread keeps
+/// running until the computation finishes and is counted as abandoned meanwhile: the rounds
+/// sharing the `shutdown_request` don't take a new job while there are
+/// `MAX_ABANDONED_COMPUTATIONS` of them.
+pub fn run_cancellable<T, F>(
+    shutdown_request: &ShutdownRequest,
+    computation: F,
+) -> Result<T, BabyProverError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let result = run_with_deadline(shutdown_request, None, computation)?;
+    Ok(result.expect("computation without deadline can't time out"))
+}
+
+/// Same as `run_cancellable`, but stops waiting for the computation after `max_time`
+/// returning `Ok(None)`, the computation is abandoned in this case as well.
+pub fn run_with_deadline<T, F>(
+    shutdown_request: &ShutdownRequest,
+    max_time: Option<Duration>,
+    computation: F,
+) -> Result<Option<T>, BabyProverError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    if shutdown_request.abort_requested() {
+        return Err(BabyProverError::Stop);
+    }
+
+    let started_at = Instant::now();
+    let (result_tx, result_rx) = mpsc::channel();
+    // Set by either the computation thread once it's finished or the caller once it stops waiting,
+    // whichever is the second one is responsible for the counter of the abandoned computations.
+    let settled = Arc::new(AtomicBool::new(false));
+    let computation_settled = Arc::clone(&settled);
+    let abandoned_computations = Arc::clone(&shutdown_request.abandoned_computations);
+    thread::Builder::new()
+        .name("prover_computation".to_string())
+        .spawn(move || {
+            let result = panic::catch_unwind(panic::AssertUnwindSafe(computation));
+            if computation_settled.swap(true, Ordering::SeqCst) {
+                abandoned_computations.fetch_sub(1, Ordering::SeqCst);
+            }
+            match result {
+                // Receiver is dropped if computation was cancelled.
+                Ok(result) => {
+                    let _ = result_tx.send(result);
+                }
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        })
+        .map_err(|e| {
+            BabyProverError::internal(format!("failed to start computation thread: {}", e))
+        })?;
+    let abandon = || {
+        shutdown_request
+            .abandoned_computations
+            .fetch_add(1, Ordering::SeqCst);
+        if settled.swap(true, Ordering::SeqCst) {
+            // Computation has just finished.
+            shutdown_request
+                .abandoned_computations
+                .fetch_sub(1, Ordering::SeqCst);
+        }
+    };
+
+    loop {
+        if shutdown_request.abort_requested() {
+            abandon();
+            return Err(BabyProverError::Stop);
+        }
+        if let Some(max_time) = max_time {
+            if started_at.elapsed() >= max_time {
+                abandon();
+                return Ok(None);
+            }
+        }
+        match result_rx.recv_timeout(ABORT_CHECK_INTERVAL) {
+            Ok(result) => return Ok(Some(result)),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(BabyProverError::internal("computation thread panicked"));
+            }
+        }
+    }
+}
+
+/// Handle to the running prover, returned by `start`.
+/// Can be used to wait for the prover to finish or to stop it gracefully.
+///
+/// Prover is requested to stop once the handle is dropped, so the prover threads
+/// don't outlive their owner.
+#[derive(Debug)]
+pub struct ProverHandle {
+    shutdown_request: ShutdownRequest,
+    rounds_done_rx: mpsc::Receiver<()>,
+    /// `None` once the thread is joined.
+    runtime_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ProverHandle {
+    /// Requests the prover to stop according to the shutdown behavior, without waiting for it.
+    pub fn stop(&self) {
+        self.shutdown_request.set();
+    }
+
+    /// Waits for both the rounds and the heartbeat routines to finish.
+    pub fn join(mut self) {
+        if let Some(runtime_thread) = self.runtime_thread.take() {
+            runtime_thread
+                .join()
+                .expect("failed to join on prover runtime thread");
+        }
+    }
+
+    /// Requests the prover to stop and waits for it according to the shutdown behavior.
+    /// With `ShutdownBehavior::FinishCurrentProof` the round being currently processed (if any)
+    /// is finished and its proof is published, and heartbeats for the current job are being sent
+    /// until the round is completed.
+    ///
+    /// Returns an error if the prover was not stopped within the provided timeout,
+    /// in that case prover runtime is left detached.
+    pub fn stop_gracefully(self, timeout: Duration) -> Result<(), failure::Error> {
+        self.stop();
+
+        match self.rounds_done_rx.recv_timeout(timeout) {
+            Ok(()) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                self.join();
+                Ok(())
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(failure::format_err!(
+                "prover did not finish the current round within {:?}",
+                timeout
+            )),
+        }
+    }
+}
+
+impl Drop for ProverHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Starts the prover runtime, which runs the prover rounds and the heartbeat routine.
+/// Fatal prover errors are sent to the `exit_err_tx` channel.
+/// Prover options are loaded from the environment.
 pub fn start<CLIENT, PROVER>(
     prover: PROVER,
     exit_err_tx: mpsc::Sender<BabyProverError>,
-    shutdown_requested: ShutdownRequest,
+    shutdown_request: ShutdownRequest,
+) -> ProverHandle
+where
+    CLIENT: 'static + Sync + Send + ApiClient,
+    PROVER: ProverImpl<CLIENT> + Send + Sync + 'static,
+{
+    start_with_options(
+        prover,
+        exit_err_tx,
+        shutdown_request,
+        ProverOptions::from_env(),
+    )
+}
+
+/// Same as `start`, but uses the provided prover options instead of loading them from the environment.
+pub fn start_with_options<CLIENT, PROVER>(
+    prover: PROVER,
+    exit_err_tx: mpsc::Sender<BabyProverError>,
+    shutdown_request: ShutdownRequest,
+    prover_options: ProverOptions,
+) -> ProverHandle
+where
+    CLIENT: 'static + Sync + Send + ApiClient,
+    PROVER: ProverImpl<CLIENT> + Send + Sync + 'static,
+{
+    start_with_shared_options(
+        prover,
+        exit_err_tx,
+        shutdown_request,
+        Arc::new(ArcSwap::from_pointee(prover_options)),
+    )
+}
+
+/// Same as `start_with_options`, but the options may be updated while the prover is running,
+/// see `SharedProverOptions`.
+pub fn start_with_shared_options<CLIENT, PROVER>(
+    prover: PROVER,
+    exit_err_tx: mpsc::Sender<BabyProverError>,
+    shutdown_request: ShutdownRequest,
+    prover_options: SharedProverOptions,
+) -> ProverHandle
+where
+    CLIENT: 'static + Sync + Send + ApiClient,
+    PROVER: ProverImpl<CLIENT> + Send + Sync + 'static,
+{
+    let (rounds_done_tx, rounds_done_rx) = mpsc::channel();
+    let rounds_shutdown_request = shutdown_request.clone();
+    let runtime_thread = thread::Builder::new()
+        .name("prover_runtime".to_string())
+        .spawn(move || {
+            let mut runtime = tokio::runtime::Builder::new()
+                .threaded_scheduler()
+                .enable_all()
+                .build()
+                .expect("failed to create prover runtime");
+
+            runtime.block_on(run_async_with_shared_options(
+                prover,
+                exit_err_tx,
+                rounds_shutdown_request,
+                prover_options,
+            ));
+            // Receiver may be already dropped if nobody waits for the prover to stop.
+            let _ = rounds_done_tx.send(());
+            // Don't wait for the abandoned round (if any) to be completed.
+            runtime.shutdown_timeout(Duration::from_secs(0));
+        })
+        .expect("failed to start prover runtime thread");
+
+    ProverHandle {
+        shutdown_request,
+        rounds_done_rx,
+        runtime_thread: Some(runtime_thread),
+    }
+}
+
+/// Runs the prover on the current runtime until it's stopped. `start_with_options` is a thin
+/// wrapper which runs this on a dedicated runtime thread.
+///
+/// Spawns the rounds routines of every prover worker and the heartbeat routine as the runtime
+/// tasks and waits for all of them. Workers prove different jobs concurrently sharing the same prover.
+/// Proofs are computed on the blocking threads of the runtime, so the heartbeats keep flowing.
+///
+/// Fatal error of any worker stops the whole prover and is sent to the `exit_err_tx` channel.
+/// If the immediate shutdown is requested, the current rounds are abandoned.
+///
+/// Runtime must be multi-threaded, since the default asynchronous methods of `ApiClient`
+/// block in place.
+pub async fn run_async<CLIENT, PROVER>(
+    prover: PROVER,
+    exit_err_tx: mpsc::Sender<BabyProverError>,
+    shutdown_request: ShutdownRequest,
+    prover_options: ProverOptions,
+) where
+    CLIENT: 'static + Sync + Send + ApiClient,
+    PROVER: ProverImpl<CLIENT> + Send + Sync + 'static,
+{
+    run_async_with_shared_options(
+        prover,
+        exit_err_tx,
+        shutdown_request,
+        Arc::new(ArcSwap::from_pointee(prover_options)),
+    )
+    .await
+}
+
+/// Same as `run_async`, but the options may be updated while the prover is running,
+/// see `SharedProverOptions`.
+pub async fn run_async_with_shared_options<CLIENT, PROVER>(
+    prover: PROVER,
+    exit_err_tx: mpsc::Sender<BabyProverError>,
+    shutdown_request: ShutdownRequest,
+    prover_options: SharedProverOptions,
 ) where
     CLIENT: 'static + Sync + Send + ApiClient,
     PROVER: ProverImpl<CLIENT> + Send + Sync + 'static,
 {
-    let (tx_block_start, rx_block_start) = mpsc::channel();
     let prover = Arc::new(prover);
-    let prover_rc = Arc::clone(&prover);
-    let join_handle = thread::spawn(move || {
-        let tx_block_start2 = tx_block_start.clone();
-        exit_err_tx
-            .send(run_rounds(
-                prover.as_ref(),
-                tx_block_start,
-                shutdown_requested,
-            ))
-            .expect("failed to send exit error");
-        tx_block_start2
-            .send((0, true))
-            .expect("failed to send heartbeat exit request"); // exit heartbeat routine request.
+    // Amount of workers is fixed once the prover is started.
+    let workers = prover_options.load().workers.max(1);
+    log::info!("Starting prover with {} worker(s)", workers);
+
+    let (heartbeat_exit_tx, heartbeat_exit_rx) = mpsc::channel();
+    let mut heartbeat_rxs = vec![heartbeat_exit_rx];
+    let (worker_done_tx, mut worker_done_rx) = tokio::sync::mpsc::unbounded_channel();
+    for worker_id in 0..workers {
+        let (tx_block_start, rx_block_start) = mpsc::channel();
+        heartbeat_rxs.push(rx_block_start);
+
+        let rounds_prover = Arc::clone(&prover);
+        let rounds_shutdown_request = shutdown_request.clone();
+        let rounds_prover_options = Arc::clone(&prover_options);
+        let worker_done_tx = worker_done_tx.clone();
+        tokio::spawn(async move {
+            // Rounds are run in a separate task, so the panic of the worker is reported
+            // as an error instead of silently leaving the prover without this worker.
+            let rounds = tokio::spawn(async move {
+                run_rounds(
+                    rounds_prover,
+                    tx_block_start,
+                    rounds_shutdown_request,
+                    rounds_prover_options,
+                )
+                .await
+            });
+            let result = rounds.await.unwrap_or_else(|e| {
+                Err(BabyProverError::internal(format!(
+                    "prover worker {} panicked: {}",
+                    worker_id, e
+                )))
+            });
+            log::info!("Prover worker {} finished", worker_id);
+            // Receiver is dropped if the prover is already stopping.
+            let _ = worker_done_tx.send(result);
+        });
+    }
+    // Channel is closed once all the workers are finished.
+    drop(worker_done_tx);
+
+    let heartbeat_prover = Arc::clone(&prover);
+    let heartbeat_prover_options = Arc::clone(&prover_options);
+    let mut heartbeat_task = tokio::spawn(async move {
+        let (client, heartbeat_interval) = heartbeat_prover.get_heartbeat_options();
+        keep_sending_work_heartbeats(
+            client,
+            heartbeat_interval,
+            heartbeat_prover_options,
+            heartbeat_rxs,
+        )
+        .await
     });
-    let (client, heartbeat_interval) = prover_rc.get_heartbeat_options();
-    keep_sending_work_heartbeats(client, heartbeat_interval, rx_block_start);
-    join_handle
-        .join()
-        .expect("failed to join on running rounds thread");
+    let mut heartbeat_finished = false;
+
+    let (api_client, _) = prover.get_heartbeat_options();
+    let workers_done = async {
+        while let Some(result) = worker_done_rx.recv().await {
+            if let Err(err) = result {
+                // Prover is going to exit, so the server is notified before that instead
+                // of waiting for the prover timeout to reassign the job.
+                report_fatal_error(api_client, &shutdown_request, &err).await;
+                exit_err_tx.send(err).expect("failed to send exit error");
+                return false;
+            }
+        }
+        true
+    };
+    let stopped_gracefully = tokio::select! {
+        stopped_gracefully = workers_done => stopped_gracefully,
+        _ = wait_for_abort_request(&shutdown_request) => {
+            log::warn!("Immediate shutdown requested, abandoning the current rounds");
+            true
+        }
+        heartbeat_result = &mut heartbeat_task => {
+            // Heartbeat routine runs until the exit request, so the jobs of the workers
+            // would be reassigned to other provers without it.
+            heartbeat_finished = true;
+            let err = match heartbeat_result {
+                Ok(Err(err)) => err,
+                Ok(Ok(())) => BabyProverError::internal("heartbeat routine exited unexpectedly"),
+                Err(e) => BabyProverError::internal(format!("heartbeat routine panicked: {}", e)),
+            };
+            exit_err_tx.send(err).expect("failed to send exit error");
+            false
+        }
+    };
+    if stopped_gracefully {
+        notify_prover_stopped(api_client, &shutdown_request).await;
+    }
+
+    if !heartbeat_finished {
+        // Routine is stopped only after the exit request is sent, so it can't fail to receive it.
+        let _ = heartbeat_exit_tx.send(HeartbeatRequest::Exit);
+        match heartbeat_task.await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => log::error!("heartbeat routine failed: {}", err),
+            Err(e) => log::error!("heartbeat routine panicked: {}", e),
+        }
+    }
+}
+
+/// Resolves once the shutdown without finishing the current round is requested.
+async fn wait_for_abort_request(shutdown_request: &ShutdownRequest) {
+    while !shutdown_request.abort_requested() {
+        tokio::time::delay_for(ABORT_CHECK_INTERVAL).await;
+    }
+}
+
+/// Notifies server that prover is stopped, if prover was registered.
+async fn notify_prover_stopped<C: ApiClient>(api_client: &C, shutdown_request: &ShutdownRequest) {
+    let prover_id = shutdown_request.prover_id();
+    if prover_id != ABSENT_PROVER_ID {
+        match api_client.prover_stopped_async(prover_id).await {
+            Ok(_) => {}
+            Err(e) => log::error!("failed to send prover stop request: {}", e),
+        }
+    }
 }
 
-fn run_rounds<PROVER: ProverImpl<CLIENT>, CLIENT: ApiClient>(
-    prover: &PROVER,
-    start_heartbeats_tx: mpsc::Sender<(i32, bool)>,
+/// Reports the failure of the job the fatal error occurred in (if any) and notifies server
+/// that prover is stopped.
+async fn report_fatal_error<C: ApiClient>(
+    api_client: &C,
+    shutdown_request: &ShutdownRequest,
+    err: &BabyProverError,
+) {
+    if let Some(job_id) = err.job_id() {
+        if let Err(e) = api_client
+            .record_failure_async(job_id, &err.to_string())
+            .await
+        {
+            logging::with_job(err.block(), job_id, || {
+                log::error!("failed to send job failure request: {}", e)
+            });
+        }
+    }
+    notify_prover_stopped(api_client, shutdown_request).await;
+}
+
+/// Runs prover rounds until either the shutdown is requested or a fatal error occurs.
+/// Shutdown request is checked only between rounds, abandoning of the current round
+/// on immediate shutdown is handled by the caller.
+async fn run_rounds<CLIENT, PROVER>(
+    prover: Arc<PROVER>,
+    start_heartbeats_tx: mpsc::Sender<HeartbeatRequest>,
     shutdown_request: ShutdownRequest,
-) -> BabyProverError {
+    prover_options: SharedProverOptions,
+) -> Result<(), BabyProverError>
+where
+    CLIENT: 'static + ApiClient,
+    PROVER: ProverImpl<CLIENT> + Send + Sync + 'static,
+{
     log::info!("Running worker rounds");
-    let cycle_wait_interval = ProverOptions::from_env().cycle_wait;
+    let mut consecutive_api_errors = 0;
+    let mut consecutive_idle_rounds = 0;
 
     loop {
         if shutdown_request.get() {
             log::info!("Shutdown requested, ignoring the next round and finishing the job");
+            return Ok(());
+        }
 
-            let prover_id = shutdown_request.prover_id();
-            if prover_id != ABSENT_PROVER_ID {
-                let (api_client, _) = prover.get_heartbeat_options();
-                match api_client.prover_stopped(prover_id) {
-                    Ok(_) => {}
-                    Err(e) => log::error!("failed to send prover stop request: {}", e),
-                }
-            }
-
-            std::process::exit(0);
+        if shutdown_request.abandoned_computations() >= MAX_ABANDONED_COMPUTATIONS {
+            // Abandoned computation keeps the CPU busy, so the next job would take too long as well.
+            log::trace!("Waiting for the abandoned computations to finish");
+            tokio::time::delay_for(ABORT_CHECK_INTERVAL).await;
+            continue;
         }
 
         log::trace!("Starting a next round");
-        let ret = prover.next_round(start_heartbeats_tx.clone());
-        if let Err(err) = ret {
-            match err {
-                BabyProverError::Api(text) => {
-                    log::error!("could not reach api server: {}", text);
-                }
-                BabyProverError::Internal(_) => {
-                    return err;
-                }
-            };
+        // Round is mostly a CPU-bound proof computation, so it's run without blocking other tasks.
+        let round_prover = Arc::clone(&prover);
+        let round_heartbeats_tx = start_heartbeats_tx.clone();
+        let round_shutdown_request = shutdown_request.clone();
+        let ret = task::spawn_blocking(move || {
+            logging::in_round(|| {
+                round_prover.next_round(round_heartbeats_tx, &round_shutdown_request)
+            })
+        })
+        .await
+        .unwrap_or_else(|e| {
+            Err(BabyProverError::internal(format!(
+                "prover round panicked: {}",
+                e
+            )))
+        });
+        if matches!(ret, Err(_) | Ok(RoundOutcome::JobAbandoned)) {
+            // Job of the failed round is abandoned, so heartbeats for it must be stopped.
+            // Otherwise the job stays locked on the server until the prover is considered gone.
+            start_heartbeats_tx.send(HeartbeatRequest::Job(None))?;
+        }
+        match ret {
+            Ok(RoundOutcome::JobDone) | Ok(RoundOutcome::JobAbandoned) => {
+                consecutive_api_errors = 0;
+                consecutive_idle_rounds = 0;
+            }
+            Ok(RoundOutcome::NoJob) => {
+                consecutive_api_errors = 0;
+                consecutive_idle_rounds += 1;
+            }
+            Err(BabyProverError::Stop) => {
+                log::warn!("Round was cancelled by the shutdown request");
+            }
+            Err(BabyProverError::AlreadyPublished { block }) => {
+                // Block is proven anyway, so the prover proceeds with the next job right away.
+                log::warn!(
+                    "Proof for block {} is already published by another prover",
+                    block
+                );
+                consecutive_api_errors = 0;
+                consecutive_idle_rounds = 0;
+            }
+            Err(err) if err.is_retryable() => {
+                consecutive_api_errors += 1;
+                log::error!(
+                    "could not reach api server (attempt {}): {}",
+                    consecutive_api_errors,
+                    err
+                );
+            }
+            Err(err) => return Err(err),
         }
         log::trace!("round completed.");
 
-        // Randomly generated shift to desynchronize multiple provers started at the same time.
-        let mut rng = rand::thread_rng();
-        let sleep_shift_ms = rng.gen_range(0, 300);
-        let sleep_duration = cycle_wait_interval + Duration::from_millis(sleep_shift_ms);
-        thread::sleep(sleep_duration);
+        if shutdown_request.get() {
+            // No need to wait before the next round, we're going to stop.
+            continue;
+        }
+
+        // Options are reloaded every round, so the updates take effect without restart.
+        let prover_options = prover_options.load_full();
+        let idle_policy = RetryPolicy::idle_from_options(&prover_options);
+        let sleep_duration = if consecutive_api_errors > 0 {
+            prover.retry_policy().delay(consecutive_api_errors)
+        } else {
+            // Wait grows only after the configured amount of rounds without a job,
+            // and equals to the `cycle_wait` until then.
+            let idle_rounds_to_back_off =
+                consecutive_idle_rounds.saturating_sub(prover_options.idle_backoff_after);
+            let wait = idle_policy.base_delay(idle_rounds_to_back_off + 1);
+            // Randomly generated shift to desynchronize multiple provers started at the same time.
+            let sleep_shift_ms = rand::thread_rng().gen_range(0, 300);
+            wait + Duration::from_millis(sleep_shift_ms)
+        };
+        tokio::time::delay_for(sleep_duration).await;
+    }
+}
+
+/// Job of the prover worker, for which the heartbeats are sent.
+struct WorkerJob {
+    job_id: i32,
+    /// Current stage of the job and the moment it was started.
+    stage: Option<(ProvingStage, Instant)>,
+    /// Moment the current lease of the job was started, i.e. the job start or the last extension.
+    lease_started_at: Instant,
+}
+
+impl WorkerJob {
+    fn progress(&self) -> Option<JobProgress> {
+        self.stage.map(|(stage, started_at)| JobProgress {
+            stage,
+            elapsed_secs: started_at.elapsed().as_secs(),
+        })
     }
 }
 
-fn keep_sending_work_heartbeats<C: ApiClient>(
+/// Sends heartbeats for the jobs of all the prover workers.
+/// Every receiver corresponds to a single worker, which reports the job it's currently working on
+/// and the stages of this job. Reports are processed right before sending the heartbeats,
+/// so a heartbeat is sent only for the latest job of the worker with its latest stage,
+/// and only if the worker didn't abandon the job.
+/// Jobs running longer than `LEASE_EXTENSION_THRESHOLD` of the prover timeout get their lease
+/// extended by the timeout, so the server doesn't reassign them to another prover.
+///
+/// Returns once the exit request is received, or with an error if all the senders are dropped
+/// without sending it.
+async fn keep_sending_work_heartbeats<C: ApiClient>(
     client: &C,
     heartbeat_interval: Duration,
-    start_heartbeats_rx: mpsc::Receiver<(i32, bool)>,
-) {
-    let mut job_id = 0;
+    prover_options: SharedProverOptions,
+    start_heartbeats_rxs: Vec<mpsc::Receiver<HeartbeatRequest>>,
+) -> Result<(), BabyProverError> {
+    let initial_options = prover_options.load_full();
+    let mut start_heartbeats_rxs: HashMap<usize, _> =
+        start_heartbeats_rxs.into_iter().enumerate().collect();
+    // Current job of each worker, keyed by the index of worker's receiver.
+    let mut jobs: HashMap<usize, WorkerJob> = HashMap::new();
     loop {
-        let mut rng = rand::thread_rng();
-
         // Randomly generated shift, so multiple provers won't spam the server at the same time.
-        let sleep_shift_ms = rng.gen_range(0, 500);
+        let sleep_shift_ms = rand::thread_rng().gen_range(0, 500);
+        // Interval of the prover is used until the options are updated at runtime.
+        let current_options = prover_options.load_full();
+        let heartbeat_interval = if Arc::ptr_eq(&current_options, &initial_options) {
+            heartbeat_interval
+        } else {
+            current_options.heartbeat_interval
+        };
         let sleep_duration = heartbeat_interval + Duration::from_millis(sleep_shift_ms);
-        thread::sleep(sleep_duration);
-
-        // Loop is required to empty queue: prover may send multiple messages while heartbeat
-        // thread was asleep, and we must process only the last one.
-        // This loop exists as soon as message queue is empty.
-        loop {
-            match start_heartbeats_rx.try_recv() {
-                Ok((new_job_id, quit_now)) => {
-                    // Check if we should stop this thread immediately.
-                    if quit_now {
-                        return;
+        tokio::time::delay_for(sleep_duration).await;
+
+        let mut finished_workers = Vec::new();
+        for (&worker_idx, start_heartbeats_rx) in &start_heartbeats_rxs {
+            // Loop is required to empty queue: prover may send multiple messages while heartbeat
+            // thread was asleep, and we must process only the last one.
+            // This loop exists as soon as message queue is empty.
+            loop {
+                match start_heartbeats_rx.try_recv() {
+                    Ok(HeartbeatRequest::Exit) => {
+                        return Ok(());
                     }
-                    // Update the current job ID.
-                    if new_job_id != 0 {
-                        // Message with non-zero job ID is sent once per job, so it won't be spammed all over the log.
-                        log::info!(
-                            "Starting sending heartbeats for job with ID: {}",
-                            new_job_id
+                    Ok(HeartbeatRequest::Job(Some(new_job_id))) => {
+                        // Message with job ID is sent once per job, so it won't be spammed all over the log.
+                        logging::with_job(None, new_job_id, || {
+                            log::info!(
+                                "Starting sending heartbeats for job with ID: {}",
+                                new_job_id
+                            )
+                        });
+                        jobs.insert(
+                            worker_idx,
+                            WorkerJob {
+                                job_id: new_job_id,
+                                stage: None,
+                                lease_started_at: Instant::now(),
+                            },
                         );
                     }
-                    job_id = new_job_id;
-                }
-                Err(mpsc::TryRecvError::Empty) => {
-                    // No messages in queue, use the last received value.
-                    break;
-                }
-                Err(e) => {
-                    panic!("error receiving from heartbeat channel: {}", e);
-                }
-            };
+                    Ok(HeartbeatRequest::Job(None)) => {
+                        jobs.remove(&worker_idx);
+                    }
+                    Ok(HeartbeatRequest::Stage(stage)) => {
+                        if let Some(job) = jobs.get_mut(&worker_idx) {
+                            logging::with_job(None, job.job_id, || {
+                                log::debug!("Job with ID {} reached stage {:?}", job.job_id, stage)
+                            });
+                            job.stage = Some((stage, Instant::now()));
+                        }
+                    }
+                    Err(mpsc::TryRecvError::Empty) => {
+                        // No messages in queue, use the last received value.
+                        break;
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        // Worker is finished, so it doesn't work on any job anymore.
+                        finished_workers.push(worker_idx);
+                        break;
+                    }
+                };
+            }
         }
-        if job_id != 0 {
-            log::trace!("sending working_on request for job_id: {}", job_id);
-            let ret = client.working_on(job_id);
+        for worker_idx in finished_workers {
+            start_heartbeats_rxs.remove(&worker_idx);
+            jobs.remove(&worker_idx);
+        }
+        if start_heartbeats_rxs.is_empty() {
+            return Err(BabyProverError::internal(
+                "heartbeat channels are disconnected without the exit request",
+            ));
+        }
+
+        let lease_timeout = current_options.gone_timeout;
+        for job in jobs.values_mut() {
+            log::trace!("sending working_on request for job_id: {}", job.job_id);
+            let ret = client.working_on_async(job.job_id, job.progress()).await;
             if let Err(e) = ret {
-                log::error!("working_on request erred: {}", e);
+                logging::with_job(None, job.job_id, || {
+                    log::error!("working_on request erred: {}", e)
+                });
+            }
+
+            if job.lease_started_at.elapsed() > lease_timeout.mul_f64(LEASE_EXTENSION_THRESHOLD) {
+                let extra_seconds = lease_timeout.as_secs().max(1) as u32;
+                logging::with_job(None, job.job_id, || {
+                    log::info!(
+                        "extending lease of job with ID {} by {} seconds",
+                        job.job_id,
+                        extra_seconds
+                    )
+                });
+                match client.extend_lease_async(job.job_id, extra_seconds).await {
+                    Ok(()) => job.lease_started_at = Instant::now(),
+                    Err(e) => logging::with_job(None, job.job_id, || {
+                        log::error!("extend_lease request erred: {}", e)
+                    }),
+                }
             }
         }
     }
@@ -0,0 +1,175 @@
+//! Logger of the prover, either human-readable (default) or structured JSON for the log aggregation.
+//!
+//! In the JSON mode every event is a single line object, carrying the block and the job
+//! of the current round, so the events of the same proof can be correlated.
+
+// Built-in deps
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+// External deps
+use env_logger::filter::{self, Filter};
+use log::{Log, Metadata, Record};
+use serde_json::{Map, Value};
+// Workspace deps
+use models::config_options::parse_env_or;
+
+/// Format of the prover log lines, set with `PROVER_LOG_FORMAT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format: {}, expected text or json", s)),
+        }
+    }
+}
+
+/// Context of the round the current thread works on, attached to the JSON log lines.
+#[derive(Debug, Default, Clone)]
+struct RoundContext {
+    block: Option<i64>,
+    job_id: Option<i32>,
+    duration: Option<Duration>,
+}
+
+thread_local! {
+    static ROUND_CONTEXT: RefCell<RoundContext> = RefCell::new(RoundContext::default());
+}
+
+/// Runs the round, so its log lines don't carry the context of the previous round run on the same thread.
+pub fn in_round<T>(round: impl FnOnce() -> T) -> T {
+    ROUND_CONTEXT.with(|context| *context.borrow_mut() = RoundContext::default());
+    let result = round();
+    ROUND_CONTEXT.with(|context| *context.borrow_mut() = RoundContext::default());
+    result
+}
+
+/// Attaches the job to the subsequent log lines of the current round.
+pub fn set_round_job(block: i64, job_id: i32) {
+    ROUND_CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        context.block = Some(block);
+        context.job_id = Some(job_id);
+    });
+}
+
+/// Attaches the duration to the log lines emitted by `log`.
+pub fn with_duration(duration: Duration, log: impl FnOnce()) {
+    ROUND_CONTEXT.with(|context| context.borrow_mut().duration = Some(duration));
+    log();
+    ROUND_CONTEXT.with(|context| context.borrow_mut().duration = None);
+}
+
+/// Attaches the job to the log lines emitted by `log`. Used for the events of the job outside
+/// of its round, e.g. ones of the heartbeat routine, where the round context is not set.
+pub fn with_job(block: Option<i64>, job_id: i32, log: impl FnOnce()) {
+    let job_context = RoundContext {
+        block,
+        job_id: Some(job_id),
+        duration: None,
+    };
+    let previous = ROUND_CONTEXT.with(|context| context.replace(job_context));
+    log();
+    ROUND_CONTEXT.with(|context| *context.borrow_mut() = previous);
+}
+
+/// Logger writing every event as a single line JSON object.
+pub struct JsonLogger {
+    worker_name: String,
+    filter: Filter,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonLogger {
+    pub fn new(worker_name: &str, filter: Filter, writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            worker_name: worker_name.to_string(),
+            filter,
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Installs the logger as the global one.
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        let max_level = self.filter.filter();
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    fn format(&self, record: &Record) -> String {
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now().to_rfc3339().into(),
+        );
+        line.insert("level".to_string(), record.level().to_string().into());
+        line.insert("target".to_string(), record.target().into());
+        line.insert("event".to_string(), record.args().to_string().into());
+        line.insert("worker_name".to_string(), self.worker_name.clone().into());
+        ROUND_CONTEXT.with(|context| {
+            let context = context.borrow();
+            if let Some(block) = context.block {
+                line.insert("block".to_string(), block.into());
+            }
+            if let Some(job_id) = context.job_id {
+                line.insert("job_id".to_string(), job_id.into());
+            }
+            if let Some(duration) = context.duration {
+                line.insert(
+                    "duration_ms".to_string(),
+                    (duration.as_millis() as u64).into(),
+                );
+            }
+        });
+        Value::Object(line).to_string()
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+        let line = self.format(record);
+        let mut writer = self.writer.lock().unwrap();
+        // Logging must not take the prover down, so the write errors are ignored.
+        let _ = writeln!(writer, "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = self.writer.lock().unwrap().flush();
+    }
+}
+
+/// Initializes the logger in the format set by `PROVER_LOG_FORMAT`, filtered with `RUST_LOG`
+/// in both formats.
+pub fn init_logger(worker_name: &str) {
+    match parse_env_or("PROVER_LOG_FORMAT", LogFormat::Text) {
+        LogFormat::Text => env_logger::init(),
+        LogFormat::Json => {
+            let mut filter = filter::Builder::new();
+            if let Ok(filters) = std::env::var("RUST_LOG") {
+                filter.parse(&filters);
+            }
+            JsonLogger::new(worker_name, filter.build(), Box::new(io::stderr()))
+                .init()
+                .expect("failed to set logger");
+        }
+    }
+}
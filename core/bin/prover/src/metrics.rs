@@ -0,0 +1,303 @@
+//! Prometheus metrics of the prover.
+
+// Built-in deps
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+// External deps
+use prometheus::{
+    Counter, Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, Opts, Registry, TextEncoder,
+};
+use prometheus_exporter_base::render_prometheus;
+
+/// Metrics collected by the prover while processing the rounds.
+///
+/// Metrics are atomic and shared with the exporter, so they can be scraped while a proof is in flight.
+#[derive(Debug, Clone)]
+pub struct ProverMetrics {
+    registry: Registry,
+    /// Time spent on the proof generation, in seconds.
+    pub proof_duration: Histogram,
+    /// Amount of successfully generated proofs.
+    pub proofs_succeeded: Counter,
+    /// Amount of proof attempts that failed.
+    pub proofs_failed: Counter,
+    /// Time spent on publishing the proof to the server, in seconds.
+    pub publish_duration: Histogram,
+    /// Amount of proofs accepted by the server.
+    pub proofs_published: Counter,
+    /// Amount of proofs that the prover failed to publish.
+    pub publish_failures: Counter,
+    /// Amount of failed requests to the prover server.
+    pub api_errors: Counter,
+    /// Set to `1` while prover works on a proof and to `0` otherwise.
+    pub busy: Gauge,
+    /// Time spent on loading the circuit parameters, in seconds, per block size.
+    pub params_load_duration: GaugeVec,
+    /// Change of the resident memory during the circuit parameters loading, in bytes, per block size.
+    pub params_memory_delta: GaugeVec,
+}
+
+impl ProverMetrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        // Proofs take from seconds to tens of minutes, so default buckets are not suitable.
+        let buckets =
+            prometheus::exponential_buckets(1.0, 2.0, 12).expect("invalid histogram buckets");
+        let proof_duration = Histogram::with_opts(
+            HistogramOpts::new(
+                "prover_proof_duration_seconds",
+                "Time spent on the proof generation",
+            )
+            .buckets(buckets),
+        )
+        .expect("failed to create proof duration metric");
+        let proofs_succeeded = Counter::new(
+            "prover_proofs_succeeded_total",
+            "Amount of successfully generated proofs",
+        )
+        .expect("failed to create proofs succeeded metric");
+        let proofs_failed = Counter::new(
+            "prover_proofs_failed_total",
+            "Amount of proof attempts that failed",
+        )
+        .expect("failed to create proofs failed metric");
+        let publish_duration = Histogram::with_opts(HistogramOpts::new(
+            "prover_publish_duration_seconds",
+            "Time spent on publishing the proof to the server",
+        ))
+        .expect("failed to create publish duration metric");
+        let proofs_published = Counter::new(
+            "prover_proofs_published_total",
+            "Amount of proofs accepted by the server",
+        )
+        .expect("failed to create proofs published metric");
+        let publish_failures = Counter::new(
+            "prover_publish_failures_total",
+            "Amount of proofs that the prover failed to publish",
+        )
+        .expect("failed to create publish failures metric");
+        let api_errors = Counter::new(
+            "prover_api_errors_total",
+            "Amount of failed requests to the prover server",
+        )
+        .expect("failed to create api errors metric");
+        let busy = Gauge::new(
+            "prover_busy",
+            "Whether prover is currently working on a proof",
+        )
+        .expect("failed to create prover busy metric");
+        let params_load_duration = GaugeVec::new(
+            Opts::new(
+                "prover_params_load_duration_seconds",
+                "Time spent on loading the circuit parameters",
+            ),
+            &["block_size"],
+        )
+        .expect("failed to create params load duration metric");
+        let params_memory_delta = GaugeVec::new(
+            Opts::new(
+                "prover_params_memory_delta_bytes",
+                "Change of the resident memory during the circuit parameters loading",
+            ),
+            &["block_size"],
+        )
+        .expect("failed to create params memory delta metric");
+
+        registry
+            .register(Box::new(proof_duration.clone()))
+            .expect("failed to register proof duration metric");
+        registry
+            .register(Box::new(proofs_succeeded.clone()))
+            .expect("failed to register proofs succeeded metric");
+        registry
+            .register(Box::new(proofs_failed.clone()))
+            .expect("failed to register proofs failed metric");
+        registry
+            .register(Box::new(publish_duration.clone()))
+            .expect("failed to register publish duration metric");
+        registry
+            .register(Box::new(proofs_published.clone()))
+            .expect("failed to register proofs published metric");
+        registry
+            .register(Box::new(publish_failures.clone()))
+            .expect("failed to register publish failures metric");
+        registry
+            .register(Box::new(api_errors.clone()))
+            .expect("failed to register api errors metric");
+        registry
+            .register(Box::new(busy.clone()))
+            .expect("failed to register prover busy metric");
+        registry
+            .register(Box::new(params_load_duration.clone()))
+            .expect("failed to register params load duration metric");
+        registry
+            .register(Box::new(params_memory_delta.clone()))
+            .expect("failed to register params memory delta metric");
+
+        Self {
+            registry,
+            proof_duration,
+            proofs_succeeded,
+            proofs_failed,
+            publish_duration,
+            proofs_published,
+            publish_failures,
+            api_errors,
+            busy,
+            params_load_duration,
+            params_memory_delta,
+        }
+    }
+
+    /// Marks the prover as working on a proof.
+    pub fn proof_started(&self) {
+        self.busy.set(1.0);
+    }
+
+    /// Records the outcome of the proof attempt and marks the prover as idle.
+    pub fn proof_finished(&self, duration: Duration, succeeded: bool) {
+        self.busy.set(0.0);
+        if succeeded {
+            self.proof_duration.observe(duration.as_secs_f64());
+            self.proofs_succeeded.inc();
+        } else {
+            self.proofs_failed.inc();
+        }
+    }
+
+    /// Records the outcome of the proof publishing. Failed publish is also counted as an API error.
+    pub fn publish_finished(&self, duration: Duration, succeeded: bool) {
+        self.publish_duration.observe(duration.as_secs_f64());
+        if succeeded {
+            self.proofs_published.inc();
+        } else {
+            self.publish_failures.inc();
+            self.api_errors.inc();
+        }
+    }
+
+    /// Records the failed request to the prover server.
+    pub fn api_error(&self) {
+        self.api_errors.inc();
+    }
+
+    /// Records the cost of loading the circuit parameters for the block size.
+    pub fn params_loaded(&self, block_size: usize, duration: Duration, memory_delta: Option<i64>) {
+        let block_size = block_size.to_string();
+        self.params_load_duration
+            .with_label_values(&[&block_size])
+            .set(duration.as_secs_f64());
+        if let Some(memory_delta) = memory_delta {
+            self.params_memory_delta
+                .with_label_values(&[&block_size])
+                .set(memory_delta as f64);
+        }
+    }
+
+    /// Renders all the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("failed to encode metrics");
+        String::from_utf8(buffer).expect("metrics are not valid utf-8")
+    }
+}
+
+impl Default for ProverMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Aggregate metrics of the provers run by a single process, see `pool::ProverPool`.
+#[derive(Debug, Clone)]
+pub struct PoolMetrics {
+    /// Amount of proofs accepted by the server from all the provers of the pool.
+    pub proofs_published: Counter,
+    /// Amount of proofs accepted by the server per second, averaged over the last minutes.
+    pub proof_rate: Gauge,
+    /// Amount of provers of the pool working on a job. Prover is considered busy from obtaining
+    /// the job until asking for the next one.
+    pub jobs_in_flight: Gauge,
+    /// Amount of provers of the pool which are running.
+    pub instances_alive: Gauge,
+    /// Amount of restarts of the failed provers.
+    pub restarts: Counter,
+}
+
+impl PoolMetrics {
+    /// Creates the metrics, registered along with the prover ones, so they are exported together.
+    pub fn new(prover_metrics: &ProverMetrics) -> Self {
+        let proofs_published = Counter::new(
+            "prover_pool_proofs_published_total",
+            "Amount of proofs accepted by the server from all the provers of the pool",
+        )
+        .expect("failed to create pool proofs published metric");
+        let proof_rate = Gauge::new(
+            "prover_pool_proofs_per_second",
+            "Amount of proofs accepted by the server per second",
+        )
+        .expect("failed to create pool proof rate metric");
+        let jobs_in_flight = Gauge::new(
+            "prover_pool_jobs_in_flight",
+            "Amount of provers of the pool working on a job",
+        )
+        .expect("failed to create pool jobs in flight metric");
+        let instances_alive = Gauge::new(
+            "prover_pool_instances_alive",
+            "Amount of provers of the pool which are running",
+        )
+        .expect("failed to create pool instances alive metric");
+        let restarts = Counter::new(
+            "prover_pool_restarts_total",
+            "Amount of restarts of the failed provers",
+        )
+        .expect("failed to create pool restarts metric");
+
+        let registry = &prover_metrics.registry;
+        registry
+            .register(Box::new(proofs_published.clone()))
+            .expect("failed to register pool proofs published metric");
+        registry
+            .register(Box::new(proof_rate.clone()))
+            .expect("failed to register pool proof rate metric");
+        registry
+            .register(Box::new(jobs_in_flight.clone()))
+            .expect("failed to register pool jobs in flight metric");
+        registry
+            .register(Box::new(instances_alive.clone()))
+            .expect("failed to register pool instances alive metric");
+        registry
+            .register(Box::new(restarts.clone()))
+            .expect("failed to register pool restarts metric");
+
+        Self {
+            proofs_published,
+            proof_rate,
+            jobs_in_flight,
+            instances_alive,
+            restarts,
+        }
+    }
+}
+
+/// Starts the HTTP server exposing the prover metrics on the `/metrics` endpoint.
+pub fn start_metrics_exporter(
+    metrics: Arc<ProverMetrics>,
+    addr: SocketAddr,
+) -> thread::JoinHandle<()> {
+    thread::Builder::new()
+        .name("prover_metrics".to_string())
+        .spawn(move || {
+            let mut runtime =
+                tokio::runtime::Runtime::new().expect("failed to create metrics runtime");
+            runtime.block_on(render_prometheus(addr, (), |_, _| async move {
+                Ok(metrics.render())
+            }));
+        })
+        .expect("failed to start metrics exporter thread")
+}
@@ -0,0 +1,95 @@
+// Built-in deps
+use std::env;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+// Workspace deps
+use models::config_options::parse_env_or;
+// Local deps
+use crate::metrics::ProverMetrics;
+use crate::{
+    ApiClient, BabyProverError, HeartbeatRequest, ProverConfig, ProverImpl, RetryPolicy,
+    RoundOutcome, ShutdownRequest,
+};
+
+pub struct ParallelProverConfig<PC> {
+    pub inner: PC,
+    /// Amount of threads used for the parallel computations, `0` means the amount of CPU cores.
+    pub threads: usize,
+}
+
+impl<PC: ProverConfig> ProverConfig for ParallelProverConfig<PC> {
+    fn from_env() -> Self {
+        Self {
+            inner: PC::from_env(),
+            threads: parse_env_or("PROVER_THREADS", 0),
+        }
+    }
+}
+
+/// Wrapper for a prover that configures the amount of threads used for the parallel parts
+/// of the proof generation (such as MSM and FFT).
+///
+/// These parts are run by the `bellman` worker, which is sized by the `BELLMAN_NUM_CPUS`
+/// environment variable rather than a thread pool of the prover, so the variable is set
+/// before the first proof is started.
+pub struct ParallelProver<P> {
+    inner: P,
+}
+
+impl<P> ParallelProver<P> {
+    const BELLMAN_THREADS_VAR: &'static str = "BELLMAN_NUM_CPUS";
+
+    fn init_worker_threads(threads: usize) {
+        if threads == 0 {
+            log::info!(
+                "Parallel prover uses {}",
+                env::var(Self::BELLMAN_THREADS_VAR)
+                    .map(|threads| format!("{} threads", threads))
+                    .unwrap_or_else(|_| "all the CPU cores".to_string())
+            );
+            return;
+        }
+        env::set_var(Self::BELLMAN_THREADS_VAR, threads.to_string());
+        log::info!("Parallel prover uses {} threads", threads);
+    }
+
+    /// Returns the inner prover.
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<C: ApiClient, P: ProverImpl<C>> ProverImpl<C> for ParallelProver<P> {
+    type Config = ParallelProverConfig<P::Config>;
+
+    fn create_from_config(
+        config: Self::Config,
+        api_client: C,
+        heartbeat_interval: Duration,
+        metrics: Option<Arc<ProverMetrics>>,
+    ) -> Self {
+        Self::init_worker_threads(config.threads);
+        let inner = P::create_from_config(config.inner, api_client, heartbeat_interval, metrics);
+        ParallelProver { inner }
+    }
+
+    fn next_round(
+        &self,
+        start_heartbeats_tx: mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError> {
+        self.inner.next_round(start_heartbeats_tx, shutdown_request)
+    }
+
+    fn get_heartbeat_options(&self) -> (&C, Duration) {
+        self.inner.get_heartbeat_options()
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        self.inner.retry_policy()
+    }
+
+    fn supported_block_sizes(&self) -> Vec<usize> {
+        self.inner.supported_block_sizes()
+    }
+}
@@ -0,0 +1,185 @@
+//! Loading of the circuit parameters (setup and verification key) with the load cost measurement.
+//!
+//! Parameters are loaded from disk once per block size, which takes seconds and gigabytes
+//! of memory, so the cost is logged and recorded to the metrics to tune the storage hardware.
+
+// Built-in deps
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+// External deps
+use crypto::{digest::Digest, sha2::Sha256};
+// Workspace deps
+use models::prover_utils::fs_utils::get_block_verification_key_path;
+// Local deps
+use crate::client::VkHash;
+use crate::metrics::ProverMetrics;
+
+/// Circuit parameters along with the cost of their loading.
+#[derive(Debug)]
+pub struct LoadedParams<T> {
+    params: T,
+    /// Wall-clock time spent on the loading.
+    pub load_duration: Duration,
+    /// Change of the process resident set size during the loading, in bytes.
+    /// `None` if the resident set size is unavailable on the platform.
+    pub rss_delta: Option<i64>,
+}
+
+impl<T> LoadedParams<T> {
+    /// Loads the parameters for the block size with `load` and measures the cost of the loading.
+    /// Warns if the loading took longer than `warn_threshold`.
+    pub fn load<E>(
+        block_size: usize,
+        warn_threshold: Option<Duration>,
+        metrics: Option<&ProverMetrics>,
+        load: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Self, E> {
+        let rss_before = resident_set_size();
+        let started_at = Instant::now();
+        let params = load()?;
+        let load_duration = started_at.elapsed();
+        let rss_delta = match (rss_before, resident_set_size()) {
+            (Some(before), Some(after)) => Some(after as i64 - before as i64),
+            _ => None,
+        };
+
+        log::info!(
+            "loaded params for block size: {} in {:?}, resident memory delta: {}",
+            block_size,
+            load_duration,
+            rss_delta.map_or_else(|| "unknown".to_string(), |delta| format!("{} bytes", delta))
+        );
+        if let Some(threshold) = warn_threshold {
+            if load_duration > threshold {
+                log::warn!(
+                    "loading params for block size: {} took {:?}, more than {:?}",
+                    block_size,
+                    load_duration,
+                    threshold
+                );
+            }
+        }
+        if let Some(metrics) = metrics {
+            metrics.params_loaded(block_size, load_duration, rss_delta);
+        }
+
+        Ok(Self {
+            params,
+            load_duration,
+            rss_delta,
+        })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.params
+    }
+}
+
+impl<T> Deref for LoadedParams<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.params
+    }
+}
+
+/// Returns the resident set size of the process in bytes, or `None` if it's unavailable.
+fn resident_set_size() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    parse_status_kilobytes(&status, "VmRSS:")
+}
+
+/// Returns the peak resident set size of the process in bytes, or `None` if it's unavailable.
+pub fn peak_resident_set_size() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    parse_status_kilobytes(&status, "VmHWM:")
+}
+
+/// Parses the line of the `/proc/<pid>/status` file given in kilobytes, e.g. `VmRSS`.
+fn parse_status_kilobytes(status: &str, field: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with(field))?;
+    let kilobytes: u64 = line
+        .trim_start_matches(field)
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Computes SHA-256 digest of the raw parameters bytes.
+pub fn sha256_of_params(mut params: impl Read) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = params.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.input(&buffer[..read]);
+    }
+    let mut digest = [0u8; 32];
+    hasher.result(&mut digest);
+    Ok(digest)
+}
+
+/// Computes SHA-256 digest of the circuit parameters (verification keys) of the block sizes,
+/// concatenated in the given order.
+pub fn params_digest(block_sizes: &[usize]) -> Result<[u8; 32], failure::Error> {
+    let mut params: Box<dyn Read> = Box::new(io::empty());
+    for &block_size in block_sizes {
+        let path = get_block_verification_key_path(block_size);
+        let file = File::open(&path).map_err(|e| {
+            failure::format_err!("Failed to open params file {}: {}", path.display(), e)
+        })?;
+        params = Box::new(params.chain(file));
+    }
+    Ok(sha256_of_params(params)?)
+}
+
+/// Computes the hashes of the verification keys of the block sizes one by one,
+/// for the verification keys check with the server, see `check_verification_keys`.
+pub fn vk_hashes(block_sizes: &[usize]) -> Result<Vec<VkHash>, failure::Error> {
+    block_sizes
+        .iter()
+        .map(|&block_size| {
+            Ok(VkHash {
+                block_size,
+                hash: hex::encode(params_digest(&[block_size])?),
+            })
+        })
+        .collect()
+}
+
+/// Parses the hex-encoded SHA-256 digest, with or without the `0x` prefix.
+pub fn parse_params_digest(digest: &str) -> Result<[u8; 32], failure::Error> {
+    let bytes = hex::decode(digest.trim_start_matches("0x"))?;
+    failure::ensure!(
+        bytes.len() == 32,
+        "digest must be 32 bytes long, got {} bytes",
+        bytes.len()
+    );
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&bytes);
+    Ok(digest)
+}
+
+/// Checks that the circuit parameters of the block sizes have the expected digest,
+/// so the corrupted or swapped parameters are detected before proving begins.
+pub fn check_params_digest(
+    block_sizes: &[usize],
+    expected: &[u8; 32],
+) -> Result<(), failure::Error> {
+    let actual = params_digest(block_sizes)?;
+    failure::ensure!(
+        &actual == expected,
+        "params digest mismatch for block sizes {:?}: expected 0x{}, got 0x{}",
+        block_sizes,
+        hex::encode(expected),
+        hex::encode(actual)
+    );
+    Ok(())
+}
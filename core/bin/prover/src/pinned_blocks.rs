@@ -0,0 +1,40 @@
+//! Re-proving of the pinned block range, used for debugging.
+//!
+//! Pinned prover doesn't take the jobs from the server: it requests the prover data of
+//! the already verified blocks directly, proves them one by one and writes the proofs
+//! to a local directory instead of publishing them.
+
+// Built-in deps
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+// Workspace deps
+use models::prover_utils::EncodedProofPlonk;
+
+/// Blocks the prover is pinned to, see `ProverOptions::block_range`.
+#[derive(Debug, Clone)]
+pub struct PinnedBlocks {
+    pub range: Range<i64>,
+    /// Directory the proofs are written to, one file per block.
+    pub proofs_dir: PathBuf,
+}
+
+impl PinnedBlocks {
+    pub fn proof_path(&self, block: i64) -> PathBuf {
+        self.proofs_dir.join(format!("proof_{:010}.json", block))
+    }
+
+    /// Writes the proof for the block, replacing the previously written one.
+    /// Returns the path of the written proof.
+    pub fn write_proof(&self, block: i64, proof: &EncodedProofPlonk) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.proofs_dir)?;
+        let bytes = serde_json::to_vec_pretty(proof)?;
+        // File is written under the temporary name first, so partially written proof is never read.
+        let path = self.proof_path(block);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bytes)?;
+        fs::rename(tmp_path, &path)?;
+        Ok(path)
+    }
+}
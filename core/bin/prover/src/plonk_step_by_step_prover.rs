@@ -1,114 +1,275 @@
-use crate::{ApiClient, BabyProverError, ProverConfig, ProverImpl};
-use models::config_options::{get_env, parse_env};
-use models::prover_utils::{PlonkVerificationKey, SetupForStepByStepProver};
-use std::sync::{mpsc, Mutex};
-use std::time::Duration;
-
-/// We prepare some data before making proof for each block size, so we cache it in case next block
-/// would be of our size
-struct PreparedComputations {
-    block_size: usize,
-    setup: SetupForStepByStepProver,
-}
+use crate::client::ProofConflict;
+use crate::logging;
+use crate::metrics::ProverMetrics;
+use crate::params::{self, LoadedParams};
+use crate::pinned_blocks::PinnedBlocks;
+use crate::proof_cache::ProofCache;
+use crate::prover_data;
+use crate::{
+    run_with_deadline, ApiClient, BabyProverError, HeartbeatRequest, ProverConfig, ProverImpl,
+    ProvingStage, RetryPolicy, RoundOutcome, ShutdownRequest, PROVER_VERSION,
+};
+use circuit::circuit::FranklinCircuit;
+use clap::{Arg, ArgMatches};
+use models::config_options::{get_env, parse_env, parse_env_or, ProverOptions};
+use models::node::Engine;
+use models::prover_utils::{
+    fs_utils::get_block_verification_key_path, EncodedProofPlonk, PlonkVerificationKey,
+    ProvingStats, SetupForStepByStepProver,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 
 pub struct PlonkStepByStepProver<C: ApiClient> {
     config: PlonkStepByStepProverConfig,
-    prepared_computations: Mutex<Option<PreparedComputations>>,
+    /// We prepare some data before making proof for each block size, so we cache it in case
+    /// the next block would be of the same size. Setups are loaded lazily on the first block
+    /// of the corresponding size and are shared by the concurrently running prover workers.
+    prepared_setups: Mutex<HashMap<usize, Arc<LoadedParams<PreparedSetup>>>>,
+    /// Pinned blocks which are not proved yet, empty if the prover is not pinned.
+    remaining_pinned_blocks: Mutex<Range<i64>>,
+    /// Proofs which failed to be published, so they are not re-created when the job is taken again.
+    proof_cache: ProofCache,
     api_client: C,
     heartbeat_interval: Duration,
+    metrics: Option<Arc<ProverMetrics>>,
+}
+
+/// Setup and verification key for the single block size.
+struct PreparedSetup {
+    setup: SetupForStepByStepProver,
+    /// `None` if the proofs are not verified by the prover.
+    vk: Option<PlonkVerificationKey>,
 }
 
 pub struct PlonkStepByStepProverConfig {
     pub block_sizes: Vec<usize>,
     pub download_setup_from_network: bool,
+    /// Publish the proofs without verifying them, see `ProverOptions::skip_self_verify`.
+    pub skip_self_verify: bool,
+    /// See `ProverOptions::params_load_warn_threshold`.
+    pub params_load_warn_threshold: Option<Duration>,
+    /// See `ProverOptions::max_proof_time`.
+    pub max_proof_time: Option<Duration>,
+    /// If set, the prover proves these blocks only and writes the proofs to disk,
+    /// see `ProverOptions::block_range`.
+    pub pinned_blocks: Option<PinnedBlocks>,
+    /// Proof which failed to be published is kept for this time, see `ProofCache`.
+    pub proof_cache_ttl: Duration,
+    /// If set, the prover data of every block is written to this directory before proving,
+    /// see `ProverData::save_to_file`.
+    pub dump_prover_data: Option<PathBuf>,
+    /// Delays between the rounds failed with API errors, see `ProverOptions::retry_initial_delay`.
+    pub retry_policy: RetryPolicy,
 }
 
 impl ProverConfig for PlonkStepByStepProverConfig {
     fn from_env() -> Self {
+        let prover_options = ProverOptions::from_env();
+        let pinned_blocks = prover_options.block_range.map(|range| PinnedBlocks {
+            range,
+            proofs_dir: parse_env_or("PROVER_PINNED_PROOFS_DIR", PathBuf::from("./pinned_proofs")),
+        });
         Self {
             block_sizes: get_env("BLOCK_CHUNK_SIZES")
                 .split(',')
                 .map(|p| p.parse().unwrap())
                 .collect(),
             download_setup_from_network: parse_env("PROVER_DOWNLOAD_SETUP"),
+            // Pinned blocks are re-proved for debugging, so their proofs are always verified.
+            skip_self_verify: prover_options.skip_self_verify && pinned_blocks.is_none(),
+            params_load_warn_threshold: prover_options.params_load_warn_threshold,
+            max_proof_time: prover_options.max_proof_time,
+            pinned_blocks,
+            proof_cache_ttl: Duration::from_secs(parse_env_or("PROVER_PROOF_CACHE_TTL_SECS", 3600)),
+            dump_prover_data: None,
+            retry_policy: RetryPolicy::from_options(&prover_options),
+        }
+    }
+
+    fn cli_args() -> Vec<Arg<'static, 'static>> {
+        vec![Arg::with_name("dump_prover_data")
+            .long("dump-prover-data")
+            .takes_value(true)
+            .value_name("DIR")
+            .help("Directory the prover data of every block is written to before proving")]
+    }
+
+    fn apply_cli_args(&mut self, matches: &ArgMatches<'_>) {
+        if let Some(dir) = matches.value_of("dump_prover_data") {
+            self.dump_prover_data = Some(PathBuf::from(dir));
         }
     }
 }
 
-impl<C: ApiClient> ProverImpl<C> for PlonkStepByStepProver<C> {
-    type Config = PlonkStepByStepProverConfig;
+impl<C: ApiClient> PlonkStepByStepProver<C> {
+    /// Records the failed request to the prover server and converts it into the prover error.
+    fn api_error(&self, err: failure::Error) -> BabyProverError {
+        if let Some(metrics) = &self.metrics {
+            metrics.api_error();
+        }
+        BabyProverError::from_api(err)
+    }
 
-    fn create_from_config(
-        config: PlonkStepByStepProverConfig,
-        api_client: C,
-        heartbeat_interval: Duration,
-    ) -> Self {
-        assert!(!config.block_sizes.is_empty());
-        PlonkStepByStepProver {
-            config,
-            prepared_computations: Mutex::new(None),
-            api_client,
-            heartbeat_interval,
+    /// Writes the prover data of the block to the dump directory, if it's configured.
+    /// Dump is a debugging aid, so its failure doesn't stop the proving.
+    fn dump_prover_data(&self, file_name: &str, instance: &FranklinCircuit<'static, Engine>) {
+        let dir = match &self.config.dump_prover_data {
+            Some(dir) => dir,
+            None => return,
+        };
+        let path = dir.join(file_name);
+        let saved = fs::create_dir_all(dir)
+            .map_err(failure::Error::from)
+            .and_then(|_| prover_data::ProverData::from_circuit(instance).save_to_file(&path));
+        match saved {
+            Ok(()) => log::info!("prover data is written to {}", path.display()),
+            Err(e) => log::warn!("failed to dump prover data: {}", e),
         }
     }
 
-    fn next_round(
+    /// Prepares the setup and reads the verification key for the block size.
+    fn prepare_setup(
         &self,
-        start_heartbeats_tx: mpsc::Sender<(i32, bool)>,
-    ) -> Result<(), BabyProverError> {
-        // first we try last proved block, since we have precomputations for it
-        let block_size_idx_to_try_first =
-            if let Some(precomp) = self.prepared_computations.lock().unwrap().as_ref() {
-                self.config
-                    .block_sizes
-                    .iter()
-                    .position(|size| *size == precomp.block_size)
-                    .unwrap()
+        block: i64,
+        block_size: usize,
+        instance: FranklinCircuit<'static, Engine>,
+    ) -> Result<PreparedSetup, BabyProverError> {
+        let vk = if self.config.skip_self_verify {
+            None
+        } else {
+            let vk = PlonkVerificationKey::read_verification_key_for_main_circuit(block_size)
+                .map_err(|e| {
+                    BabyProverError::internal_for_block(
+                        block,
+                        format!(
+                            "Failed to read vk for block: {}, size: {}, err: {}",
+                            block, block_size, e
+                        ),
+                    )
+                })?;
+            Some(vk)
+        };
+        // Verification key is read first, so the block without the keys fails before
+        // the expensive setup preparation.
+        let setup = SetupForStepByStepProver::prepare_setup_for_step_by_step_prover(
+            instance,
+            self.config.download_setup_from_network,
+        )
+        .map_err(|e| {
+            BabyProverError::internal_for_block(
+                block,
+                format!(
+                    "Failed to prepare setup for block_size: {}, err: {}",
+                    block_size, e
+                ),
+            )
+        })?;
+
+        Ok(PreparedSetup { setup, vk })
+    }
+
+    /// Creates a proof for the block (verified unless `skip_self_verify` is set), reusing
+    /// the prepared setup for the block size if it was already loaded.
+    /// Proof generation is cancelled on the immediate shutdown request.
+    /// Returns `None` if the proof generation exceeded `max_proof_time`.
+    fn create_proof(
+        &self,
+        block: i64,
+        block_size: usize,
+        instance: FranklinCircuit<'static, Engine>,
+        start_heartbeats_tx: &mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<Option<EncodedProofPlonk>, BabyProverError> {
+        if !self.config.block_sizes.contains(&block_size) {
+            return Err(BabyProverError::internal_for_block(
+                block,
+                format!(
+                    "Unsupported size of block: {}, size: {}, supported sizes: {:?}",
+                    block, block_size, self.config.block_sizes
+                ),
+            ));
+        }
+
+        let setup = {
+            // Lock is held during the preparation, so the same setup won't be prepared twice.
+            let mut prepared_setups = self.prepared_setups.lock().unwrap();
+            if let Some(setup) = prepared_setups.get(&block_size) {
+                Arc::clone(setup)
             } else {
-                0
-            };
-
-        let (mut block, mut job_id, mut block_size) = (0, 0, 0);
-        for offset_idx in 0..self.config.block_sizes.len() {
-            let idx = (block_size_idx_to_try_first + offset_idx) % self.config.block_sizes.len();
-            let current_block_size = self.config.block_sizes[idx];
-
-            let block_to_prove =
-                self.api_client
-                    .block_to_prove(current_block_size)
-                    .map_err(|e| {
-                        let e = format!("failed to get block to prove {}", e);
-                        BabyProverError::Api(e)
-                    })?;
-
-            let (current_request_block, current_request_job_id) =
-                block_to_prove.unwrap_or_else(|| {
-                    log::trace!(
-                        "no block to prove from the server for size: {}",
-                        current_block_size
-                    );
-                    (0, 0)
-                });
-
-            if current_request_job_id != 0 {
-                block = current_request_block;
-                job_id = current_request_job_id;
-                block_size = current_block_size;
-                break;
+                log::info!("preparing setup for block size: {}", block_size);
+                start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::Synthesis))?;
+                let setup = LoadedParams::load(
+                    block_size,
+                    self.config.params_load_warn_threshold,
+                    self.metrics.as_deref(),
+                    || self.prepare_setup(block, block_size, instance.clone()),
+                )?;
+                let setup = Arc::new(setup);
+                prepared_setups.insert(block_size, Arc::clone(&setup));
+                setup
             }
-        }
+        };
 
-        // Notify heartbeat routine on new proving block job or None.
-        start_heartbeats_tx
-            .send((job_id, false))
-            .expect("failed to send new job to heartbeat routine");
-        if job_id == 0 {
-            return Ok(());
-        }
-        let instance = self.api_client.prover_data(block).map_err(|err| {
-            BabyProverError::Api(format!(
-                "could not get prover data for block {}: {}",
-                block, err
+        // Proof is verified as a part of its generation, so there is no separate verification stage.
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::ProofGeneration))?;
+        let proof =
+            run_with_deadline(
+                shutdown_request,
+                self.config.max_proof_time,
+                move || match &setup.vk {
+                    Some(vk) => setup
+                        .setup
+                        .gen_step_by_step_proof_using_prepared_setup(instance, vk),
+                    None => setup
+                        .setup
+                        .gen_unverified_step_by_step_proof_using_prepared_setup(instance),
+                },
+            )?;
+        let proof = match proof {
+            Some(proof) => proof,
+            None => return Ok(None),
+        };
+
+        // Proof is verified right after its generation, so failure here is fatal.
+        proof.map(Some).map_err(|e| {
+            BabyProverError::internal_for_block(
+                block,
+                format!(
+                    "Failed to create verified proof for block: {}, size: {}, err: {}",
+                    block, block_size, e
+                ),
+            )
+        })
+    }
+
+    /// Fetches the prover data of the block of the requested size and creates its proof,
+    /// returned along with the proving time.
+    /// Returns `None` if the proof generation exceeded `max_proof_time`.
+    fn fetch_data_and_prove(
+        &self,
+        block: i64,
+        job_id: i32,
+        block_size: usize,
+        start_heartbeats_tx: &mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<Option<(EncodedProofPlonk, Duration)>, BabyProverError> {
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::FetchingData))?;
+        let instance = tracing::info_span!("prover_data", block_number = block)
+            .in_scope(|| self.api_client.prover_data(block))
+            .map_err(|e| self.api_error(e))?;
+        self.dump_prover_data(&format!("block_{}_job_{}.json", block, job_id), &instance);
+        // Witness is checked against the requested block size before the setup is chosen for it.
+        // Malformed witness is reported as a retryable API error, so the server can regenerate it.
+        prover_data::validate_circuit(&instance, block_size).map_err(|e| {
+            self.api_error(failure::format_err!(
+                "invalid prover data for block {}: {}",
+                block,
+                e
             ))
         })?;
 
@@ -118,59 +279,276 @@ impl<C: ApiClient> ProverImpl<C> for PlonkStepByStepProver<C> {
             block_size
         );
 
-        // we do this way here so old precomp is dropped
-        let valid_cached_precomp = {
-            self.prepared_computations
-                .lock()
-                .unwrap()
-                .take()
-                .filter(|p| p.block_size == block_size)
-        };
-        let precomp = if let Some(precomp) = valid_cached_precomp {
-            precomp
-        } else {
-            let setup = SetupForStepByStepProver::prepare_setup_for_step_by_step_prover(
-                instance.clone(),
-                self.config.download_setup_from_network,
+        if let Some(metrics) = &self.metrics {
+            metrics.proof_started();
+        }
+        let proof_span = tracing::info_span!(
+            "create_proof",
+            block_number = block,
+            job_id,
+            block_size,
+            proof_duration_ms = tracing::field::Empty,
+        );
+        let proof_started_at = Instant::now();
+        let proof = proof_span.in_scope(|| {
+            self.create_proof(
+                block,
+                block_size,
+                instance,
+                start_heartbeats_tx,
+                shutdown_request,
             )
-            .map_err(|e| {
-                BabyProverError::Internal(format!(
-                    "Failed to prepare setup for block_size: {}, err: {}",
-                    block_size, e
-                ))
-            })?;
-            PreparedComputations { block_size, setup }
-        };
+        });
+        let proving_time = proof_started_at.elapsed();
+        proof_span.record("proof_duration_ms", &(proving_time.as_millis() as u64));
+        if let Some(metrics) = &self.metrics {
+            metrics.proof_finished(proving_time, matches!(proof, Ok(Some(_))));
+        }
+        let proof = proof?;
+        if proof.is_none() {
+            // Generation may be wedged, so the job is left for other provers.
+            let err = BabyProverError::internal_for_block(
+                block,
+                format!(
+                    "Proof generation for block: {}, size: {} exceeded {:?}, abandoning the job",
+                    block, block_size, self.config.max_proof_time
+                ),
+            );
+            log::error!("{}", err);
+        }
+        Ok(proof.map(|proof| (proof, proving_time)))
+    }
 
-        let vk = PlonkVerificationKey::read_verification_key_for_main_circuit(block_size).map_err(
-            |e| {
-                BabyProverError::Internal(format!(
-                    "Failed to read vk for block: {}, size: {}, err: {}",
-                    block, block_size, e
-                ))
+    /// Proves the block of the job obtained from the server (or publishes its cached proof)
+    /// and publishes the proof.
+    fn prove_job(
+        &self,
+        block: i64,
+        job_id: i32,
+        block_size: usize,
+        start_heartbeats_tx: &mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError> {
+        let round_started_at = Instant::now();
+        let (verified_proof, proving_time) = match self.proof_cache.get(block) {
+            Some(proof) => {
+                // Previous attempt to publish the proof failed, so there is no need to prove again.
+                log::info!("publishing cached proof for block {}", block);
+                (proof, None)
+            }
+            None => match self.fetch_data_and_prove(
+                block,
+                job_id,
+                block_size,
+                start_heartbeats_tx,
+                shutdown_request,
+            )? {
+                Some((proof, proving_time)) => {
+                    self.proof_cache.insert(block, proof.clone());
+                    (proof, Some(proving_time))
+                }
+                None => return Ok(RoundOutcome::JobAbandoned),
             },
+        };
+        let stats = ProvingStats {
+            proving_time_secs: proving_time.map(|time| time.as_secs_f64()),
+            peak_memory_bytes: params::peak_resident_set_size(),
+            // Filled by the API client, which knows the name the prover is registered with.
+            worker_name: None,
+            prover_version: Some(PROVER_VERSION.to_string()),
+        };
+
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::Publishing))?;
+        let publish_started_at = Instant::now();
+        let published = tracing::info_span!("publish", block_number = block).in_scope(|| {
+            self.api_client
+                .publish_with_stats(block, verified_proof, stats)
+        });
+        if let Some(metrics) = &self.metrics {
+            metrics.publish_finished(publish_started_at.elapsed(), published.is_ok());
+        }
+        let publish_finished = match &published {
+            Ok(()) => true,
+            // Conflicting proof is never accepted, so there is no point in keeping it.
+            Err(e) => e.downcast_ref::<ProofConflict>().is_some(),
+        };
+        if publish_finished {
+            self.proof_cache.remove(block);
+        }
+        published.map_err(|e| BabyProverError::from_publish(block, e))?;
+
+        logging::with_duration(round_started_at.elapsed(), || {
+            log::info!("finished and published proof for block {}", block)
+        });
+        Ok(RoundOutcome::JobDone)
+    }
+
+    /// Proves the next pinned block and writes its proof to disk. The job queue of the server
+    /// is bypassed, so no heartbeats are sent for the block.
+    ///
+    /// Lock of the remaining blocks is held for the whole round, so the blocks are proved
+    /// sequentially even with multiple workers, and the block which failed to be proved
+    /// is retried in the next round.
+    fn prove_pinned_block(
+        &self,
+        pinned_blocks: &PinnedBlocks,
+        start_heartbeats_tx: &mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError> {
+        start_heartbeats_tx.send(HeartbeatRequest::Job(None))?;
+        let mut remaining_blocks = self.remaining_pinned_blocks.lock().unwrap();
+        let block = match remaining_blocks.clone().next() {
+            Some(block) => block,
+            None => return Ok(RoundOutcome::NoJob),
+        };
+
+        let instance = tracing::info_span!("prover_data", block_number = block)
+            .in_scope(|| self.api_client.prover_data_for_block(block))
+            .map_err(|e| self.api_error(e))?;
+        self.dump_prover_data(&format!("block_{}.json", block), &instance);
+        // Size of the verified block is not reported by the server, so its witness has to fit
+        // one of the supported sizes. Pinned block is not regenerated by the server,
+        // so its malformed witness is fatal.
+        let block_size =
+            prover_data::validate_circuit_of_any_size(&instance, &self.config.block_sizes)
+                .map_err(|e| {
+                    BabyProverError::internal_for_block(
+                        block,
+                        format!("invalid prover data for pinned block {}: {}", block, e),
+                    )
+                })?;
+        log::info!(
+            "starting to compute proof for pinned block {}, size: {}",
+            block,
+            block_size
+        );
+
+        let proof = self.create_proof(
+            block,
+            block_size,
+            instance,
+            start_heartbeats_tx,
+            shutdown_request,
         )?;
-        let verified_proof = precomp
-            .setup
-            .gen_step_by_step_proof_using_prepared_setup(instance, &vk)
-            .map_err(|e| {
-                BabyProverError::Internal(format!(
-                    "Failed to create verified proof for block: {}, size: {}, err: {}",
-                    block, block_size, e
-                ))
-            })?;
+        // Block which can't be proved in time won't be proved on retry either, so it's skipped.
+        remaining_blocks.start += 1;
+        let proof = match proof {
+            Some(proof) => proof,
+            None => {
+                log::error!(
+                    "Proof generation for pinned block: {}, size: {} exceeded {:?}, skipping the block",
+                    block,
+                    block_size,
+                    self.config.max_proof_time
+                );
+                return Ok(RoundOutcome::JobAbandoned);
+            }
+        };
 
-        *self.prepared_computations.lock().unwrap() = Some(precomp);
+        let path = pinned_blocks.write_proof(block, &proof).map_err(|e| {
+            BabyProverError::internal_for_block(
+                block,
+                format!("Failed to write proof for block: {}, err: {}", block, e),
+            )
+        })?;
+        log::info!(
+            "finished proof for pinned block {}, written to {}",
+            block,
+            path.display()
+        );
+        if remaining_blocks.start == remaining_blocks.end {
+            log::info!("all pinned blocks {:?} are proved", pinned_blocks.range);
+        }
+        Ok(RoundOutcome::JobDone)
+    }
+}
 
-        self.api_client
-            .publish(block, verified_proof)
-            .map_err(|e| BabyProverError::Api(format!("failed to publish proof: {}", e)))?;
+impl<C: ApiClient> ProverImpl<C> for PlonkStepByStepProver<C> {
+    type Config = PlonkStepByStepProverConfig;
 
-        log::info!("finished and published proof for block {}", block);
-        Ok(())
+    fn create_from_config(
+        config: PlonkStepByStepProverConfig,
+        api_client: C,
+        heartbeat_interval: Duration,
+        metrics: Option<Arc<ProverMetrics>>,
+    ) -> Self {
+        assert!(!config.block_sizes.is_empty());
+        let remaining_pinned_blocks = match &config.pinned_blocks {
+            Some(pinned_blocks) => pinned_blocks.range.clone(),
+            None => 0..0,
+        };
+        let proof_cache = ProofCache::new(config.proof_cache_ttl);
+        PlonkStepByStepProver {
+            config,
+            prepared_setups: Mutex::new(HashMap::new()),
+            remaining_pinned_blocks: Mutex::new(remaining_pinned_blocks),
+            proof_cache,
+            api_client,
+            heartbeat_interval,
+            metrics,
+        }
+    }
+
+    #[tracing::instrument(skip(self, start_heartbeats_tx, shutdown_request))]
+    fn next_round(
+        &self,
+        start_heartbeats_tx: mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError> {
+        if let Some(pinned_blocks) = &self.config.pinned_blocks {
+            return self.prove_pinned_block(pinned_blocks, &start_heartbeats_tx, shutdown_request);
+        }
+
+        let mut job = None;
+        for &current_block_size in &self.config.block_sizes {
+            let block_to_prove = self
+                .api_client
+                .block_to_prove(current_block_size)
+                .map_err(|e| self.api_error(e))?;
+
+            if let Some((block, job_id)) = block_to_prove {
+                job = Some((block, job_id, current_block_size));
+                break;
+            }
+            log::trace!(
+                "no block to prove from the server for size: {}",
+                current_block_size
+            );
+        }
+
+        // Notify heartbeat routine on new proving block job or None.
+        start_heartbeats_tx.send(HeartbeatRequest::Job(job.map(|(_, job_id, _)| job_id)))?;
+        let (block, job_id, block_size) = match job {
+            Some(job) => job,
+            None => return Ok(RoundOutcome::NoJob),
+        };
+        logging::set_round_job(block, job_id);
+        // Failure is attributed to the job, so the server can reassign it right away.
+        self.prove_job(
+            block,
+            job_id,
+            block_size,
+            &start_heartbeats_tx,
+            shutdown_request,
+        )
+        .map_err(|e| e.with_job(job_id))
     }
 
     fn get_heartbeat_options(&self) -> (&C, Duration) {
         (&self.api_client, self.heartbeat_interval)
     }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.config.retry_policy
+    }
+
+    /// Configured block sizes, for which the verification keys are present.
+    fn supported_block_sizes(&self) -> Vec<usize> {
+        self.config
+            .block_sizes
+            .iter()
+            .copied()
+            .filter(|size| get_block_verification_key_path(*size).exists())
+            .collect()
+    }
 }
@@ -0,0 +1,556 @@
+//! Pool of the provers run by a single process.
+//!
+//! Every prover of the pool runs its own rounds (see `start_with_shared_options`), while the pool
+//! decides which of them takes the next job from the server, see `JobScheduler`. Provers share
+//! the registration on the server, and the failed ones are restarted by the pool, so a single
+//! process can replace several prover processes.
+
+// Built-in deps
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc, Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+// Workspace deps
+use circuit::circuit::FranklinCircuit;
+use models::node::Engine;
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
+// Local deps
+use crate::client::{CheckVkRes, ProofStatus, PublishResult, ServerHealth, VkHash};
+use crate::metrics::PoolMetrics;
+use crate::{
+    start_with_shared_options, ApiClient, BabyProverError, JobProgress, ProverHandle, ProverImpl,
+    SharedProverOptions, ShutdownRequest, ABSENT_PROVER_ID,
+};
+
+/// Interval of checking the liveness of the provers and updating the pool metrics.
+const MONITOR_INTERVAL: Duration = Duration::from_millis(100);
+/// Window of the restarts limited by `ProverOptions::max_restarts_per_hour`.
+const RESTARTS_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Window the proof rate of the pool is averaged over, see `PoolMetrics::proof_rate`.
+const PROOF_RATE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Decides which prover of the pool takes the next job.
+pub trait JobScheduler: Debug + Send {
+    /// Picks the prover allowed to take the next job out of the idle ones.
+    /// Indices of the idle provers are sorted, and there is at least one of them.
+    fn next_instance(&self, idle: &[usize]) -> usize;
+    /// Called once the picked prover obtained the job from the server.
+    fn job_assigned(&mut self, instance: usize);
+}
+
+/// Gives the jobs to the idle provers in turn.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    last_assigned: Option<usize>,
+}
+
+impl JobScheduler for RoundRobin {
+    fn next_instance(&self, idle: &[usize]) -> usize {
+        idle.iter()
+            .copied()
+            .find(|&instance| self.last_assigned.map_or(true, |last| instance > last))
+            .unwrap_or(idle[0])
+    }
+
+    fn job_assigned(&mut self, instance: usize) {
+        self.last_assigned = Some(instance);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InstanceState {
+    /// Prover hasn't asked for a job yet, e.g. is still loading the circuit parameters.
+    Starting,
+    /// Prover waits for a job.
+    Idle,
+    /// Prover obtained a job and didn't ask for the next one yet.
+    Busy,
+    /// Prover is stopped and is not going to be restarted.
+    Stopped,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    scheduler: Box<dyn JobScheduler>,
+    instances: Vec<InstanceState>,
+    /// Times of the published proofs within the `PROOF_RATE_WINDOW`, recorded if the metrics are set.
+    published: VecDeque<Instant>,
+}
+
+impl PoolState {
+    fn count(&self, state: InstanceState) -> usize {
+        self.instances
+            .iter()
+            .filter(|&&instance| instance == state)
+            .count()
+    }
+}
+
+/// State shared by the API clients of the provers and the pool itself.
+#[derive(Debug)]
+struct PoolShared {
+    state: Mutex<PoolState>,
+    /// Shutdown request of the whole pool.
+    shutdown_request: ShutdownRequest,
+    /// Set once the server is notified that the prover is stopped.
+    stop_reported: AtomicBool,
+    metrics: Option<PoolMetrics>,
+}
+
+impl PoolShared {
+    fn set_state(&self, instance: usize, state: InstanceState) {
+        self.state.lock().unwrap().instances[instance] = state;
+    }
+}
+
+/// API client of a prover of the pool. Prover is given a job only when it's its turn
+/// according to the `JobScheduler` of the pool.
+#[derive(Debug)]
+pub struct PooledApiClient<C> {
+    inner: Arc<C>,
+    instance: usize,
+    pool: Arc<PoolShared>,
+}
+
+impl<C> Clone for PooledApiClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+            instance: self.instance,
+            pool: Arc::clone(&self.pool),
+        }
+    }
+}
+
+impl<C> PooledApiClient<C> {
+    /// Index of the prover in the pool.
+    pub fn instance(&self) -> usize {
+        self.instance
+    }
+
+    /// Returns the client shared by all the provers of the pool.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    fn proof_published(&self) {
+        if let Some(metrics) = &self.pool.metrics {
+            metrics.proofs_published.inc();
+            let mut state = self.pool.state.lock().unwrap();
+            state.published.push_back(Instant::now());
+        }
+    }
+}
+
+impl<C: ApiClient> ApiClient for PooledApiClient<C> {
+    /// Prover asks for the next job only once it's done with the previous one, so it's considered
+    /// idle from now on. No jobs are given until the prover is registered.
+    fn block_to_prove(&self, block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        let mut state = self.pool.state.lock().unwrap();
+        state.instances[self.instance] = InstanceState::Idle;
+        if self.pool.shutdown_request.prover_id() == ABSENT_PROVER_ID {
+            return Ok(None);
+        }
+        let idle: Vec<usize> = state
+            .instances
+            .iter()
+            .enumerate()
+            .filter(|(_, instance)| **instance == InstanceState::Idle)
+            .map(|(instance, _)| instance)
+            .collect();
+        if state.scheduler.next_instance(&idle) != self.instance {
+            return Ok(None);
+        }
+
+        // Lock is held during the request, so the provers take the jobs one at a time.
+        let job = self.inner.block_to_prove(block_size)?;
+        if job.is_some() {
+            state.scheduler.job_assigned(self.instance);
+            state.instances[self.instance] = InstanceState::Busy;
+        }
+        Ok(job)
+    }
+
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error> {
+        self.inner.working_on(job_id, progress)
+    }
+
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        self.inner.extend_lease(job_id, extra_seconds)
+    }
+
+    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        self.inner.prover_data(block)
+    }
+
+    fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        self.inner.prover_data_for_block(block)
+    }
+
+    fn publish(&self, block: i64, proof: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.inner.publish(block, proof)?;
+        self.proof_published();
+        Ok(())
+    }
+
+    fn publish_with_stats(
+        &self,
+        block: i64,
+        proof: EncodedProofPlonk,
+        stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        self.inner.publish_with_stats(block, proof, stats)?;
+        self.proof_published();
+        Ok(())
+    }
+
+    fn publish_batch(
+        &self,
+        items: Vec<(i64, EncodedProofPlonk)>,
+    ) -> Result<Vec<PublishResult>, failure::Error> {
+        let results = self.inner.publish_batch(items)?;
+        for result in &results {
+            if *result == PublishResult::Stored {
+                self.proof_published();
+            }
+        }
+        Ok(results)
+    }
+
+    /// Provers of the pool share the registration, so the server is notified only once
+    /// the whole pool is stopping, rather than when a single prover fails and is restarted.
+    fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        if !self.pool.shutdown_request.get() || self.pool.stop_reported.swap(true, Ordering::SeqCst)
+        {
+            return Ok(());
+        }
+        self.inner.prover_stopped(prover_run_id)
+    }
+
+    fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        self.inner.record_failure(job_id, reason)
+    }
+
+    fn health_check(&self) -> Result<ServerHealth, failure::Error> {
+        self.inner.health_check()
+    }
+
+    fn proof_status(&self, block: i64) -> Result<ProofStatus, failure::Error> {
+        self.inner.proof_status(block)
+    }
+
+    fn check_vk(&self, vk_hashes: &[VkHash]) -> Result<CheckVkRes, failure::Error> {
+        self.inner.check_vk(vk_hashes)
+    }
+}
+
+/// Pool of the provers run by a single process, see the module docs.
+///
+/// Provers are requested to stop once the pool is dropped.
+#[derive(Debug)]
+pub struct ProverPool {
+    handles: Arc<Mutex<Vec<ProverHandle>>>,
+    shutdown_request: ShutdownRequest,
+    supported_block_sizes: Vec<usize>,
+    /// `None` once the thread is joined.
+    monitor_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ProverPool {
+    /// Starts `ProverOptions::pool_size` provers created by `create_prover`, along with the routine
+    /// monitoring them. Failed prover is restarted up to `ProverOptions::max_restarts_per_hour`
+    /// times per hour, after that the whole pool is stopped and the error is sent to `exit_err_tx`.
+    ///
+    /// Provers don't take the jobs until the prover ID is set in the `shutdown_request`,
+    /// so the pool may be started before the registration.
+    pub fn start<C, P, F>(
+        api_client: C,
+        create_prover: F,
+        scheduler: Box<dyn JobScheduler>,
+        exit_err_tx: mpsc::Sender<BabyProverError>,
+        shutdown_request: ShutdownRequest,
+        prover_options: SharedProverOptions,
+        metrics: Option<PoolMetrics>,
+    ) -> Self
+    where
+        C: ApiClient + 'static,
+        P: ProverImpl<PooledApiClient<C>> + Send + Sync + 'static,
+        F: Fn(PooledApiClient<C>) -> P + Send + 'static,
+    {
+        let options = prover_options.load_full();
+        let size = options.pool_size.max(1);
+        log::info!("Starting pool of {} prover(s)", size);
+
+        let pool = Arc::new(PoolShared {
+            state: Mutex::new(PoolState {
+                scheduler,
+                instances: vec![InstanceState::Starting; size],
+                published: VecDeque::new(),
+            }),
+            shutdown_request: shutdown_request.clone(),
+            stop_reported: AtomicBool::new(false),
+            metrics,
+        });
+        let mut monitor = PoolMonitor {
+            api_client: Arc::new(api_client),
+            create_prover,
+            pool,
+            handles: Default::default(),
+            instances: Vec::with_capacity(size),
+            exit_err_tx,
+            exit_err_sent: false,
+            prover_options,
+            max_restarts_per_hour: options.max_restarts_per_hour,
+        };
+        let mut supported_block_sizes = Vec::new();
+        for instance in 0..size {
+            let prover = monitor.create_prover(instance);
+            if instance == 0 {
+                supported_block_sizes = prover.supported_block_sizes();
+            }
+            let (handle, monitored) = monitor.start_instance(prover);
+            monitor.handles.lock().unwrap().push(handle);
+            monitor.instances.push(monitored);
+        }
+        monitor.update_metrics();
+
+        let handles = Arc::clone(&monitor.handles);
+        let monitor_thread = thread::Builder::new()
+            .name("prover_pool_monitor".to_string())
+            .spawn(move || monitor.run())
+            .expect("failed to start prover pool monitor thread");
+
+        Self {
+            handles,
+            shutdown_request,
+            supported_block_sizes,
+            monitor_thread: Some(monitor_thread),
+        }
+    }
+
+    /// Amount of provers in the pool.
+    pub fn size(&self) -> usize {
+        self.handles.lock().unwrap().len()
+    }
+
+    /// Block sizes the provers of the pool are able to prove, reported to the server on registration.
+    pub fn supported_block_sizes(&self) -> Vec<usize> {
+        self.supported_block_sizes.clone()
+    }
+
+    /// Requests all the provers to stop according to the shutdown behavior, without waiting for them.
+    pub fn stop(&self) {
+        self.shutdown_request.set();
+    }
+
+    /// Waits for all the provers of the pool to finish.
+    pub fn join(mut self) {
+        if let Some(monitor_thread) = self.monitor_thread.take() {
+            monitor_thread
+                .join()
+                .expect("failed to join on prover pool monitor thread");
+        }
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+        for handle in handles {
+            handle.join();
+        }
+    }
+}
+
+impl Drop for ProverPool {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Prover of the pool, as seen by the monitor.
+struct MonitoredInstance {
+    /// Stops this prover only, set by the monitor once the pool is stopping.
+    shutdown_request: ShutdownRequest,
+    /// `None` once the prover is finished.
+    exit_err_rx: Option<mpsc::Receiver<BabyProverError>>,
+    /// Error the prover exited with, if any.
+    error: Option<BabyProverError>,
+    /// Times of the restarts within the `RESTARTS_WINDOW`.
+    restarts: VecDeque<Instant>,
+}
+
+/// Routine restarting the failed provers of the pool and updating the pool metrics.
+struct PoolMonitor<C, F> {
+    api_client: Arc<C>,
+    create_prover: F,
+    pool: Arc<PoolShared>,
+    handles: Arc<Mutex<Vec<ProverHandle>>>,
+    instances: Vec<MonitoredInstance>,
+    exit_err_tx: mpsc::Sender<BabyProverError>,
+    exit_err_sent: bool,
+    prover_options: SharedProverOptions,
+    max_restarts_per_hour: u32,
+}
+
+impl<C, P, F> PoolMonitor<C, F>
+where
+    C: ApiClient + 'static,
+    P: ProverImpl<PooledApiClient<C>> + Send + Sync + 'static,
+    F: Fn(PooledApiClient<C>) -> P,
+{
+    fn create_prover(&self, instance: usize) -> P {
+        (self.create_prover)(PooledApiClient {
+            inner: Arc::clone(&self.api_client),
+            instance,
+            pool: Arc::clone(&self.pool),
+        })
+    }
+
+    fn start_instance(&self, prover: P) -> (ProverHandle, MonitoredInstance) {
+        let (exit_err_tx, exit_err_rx) = mpsc::channel();
+        // Prover ID is shared with the pool, but every prover is stopped separately,
+        // so the handle of the failed prover can be dropped without stopping the others.
+        let shutdown_request = self.pool.shutdown_request.detached();
+        let handle = start_with_shared_options::<PooledApiClient<C>, P>(
+            prover,
+            exit_err_tx,
+            shutdown_request.clone(),
+            Arc::clone(&self.prover_options),
+        );
+        let monitored = MonitoredInstance {
+            shutdown_request,
+            exit_err_rx: Some(exit_err_rx),
+            error: None,
+            restarts: VecDeque::new(),
+        };
+        (handle, monitored)
+    }
+
+    /// Runs until all the provers are finished.
+    fn run(mut self) {
+        while self
+            .instances
+            .iter()
+            .any(|instance| instance.exit_err_rx.is_some())
+        {
+            if self.pool.shutdown_request.get() {
+                for instance in &self.instances {
+                    instance.shutdown_request.set();
+                }
+            }
+            for instance in 0..self.instances.len() {
+                self.check_instance(instance);
+            }
+            self.update_metrics();
+            thread::sleep(MONITOR_INTERVAL);
+        }
+        self.update_metrics();
+    }
+
+    /// Restarts the prover if it's finished while the pool is running.
+    fn check_instance(&mut self, instance: usize) {
+        let monitored = &mut self.instances[instance];
+        let exit_err_rx = match &monitored.exit_err_rx {
+            Some(exit_err_rx) => exit_err_rx,
+            None => return,
+        };
+        match exit_err_rx.try_recv() {
+            Ok(err) => {
+                // Prover runtime is finished right after the error is sent.
+                monitored.error = Some(err);
+                return;
+            }
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {}
+        }
+        monitored.exit_err_rx = None;
+        self.pool.set_state(instance, InstanceState::Stopped);
+        let err = monitored.error.take();
+        if self.pool.shutdown_request.get() {
+            if let Some(err) = err {
+                log::error!(
+                    "prover {} of the pool failed while stopping: {}",
+                    instance,
+                    err
+                );
+                self.send_exit_error(err);
+            }
+            return;
+        }
+
+        let err = err.unwrap_or_else(|| BabyProverError::internal("prover stopped unexpectedly"));
+        let now = Instant::now();
+        while monitored.restarts.front().map_or(false, |&restart| {
+            now.duration_since(restart) >= RESTARTS_WINDOW
+        }) {
+            monitored.restarts.pop_front();
+        }
+        if monitored.restarts.len() >= self.max_restarts_per_hour as usize {
+            log::error!(
+                "prover {} of the pool failed with no restarts left, stopping the pool: {}",
+                instance,
+                err
+            );
+            self.pool.shutdown_request.set();
+            self.send_exit_error(err);
+            return;
+        }
+        monitored.restarts.push_back(now);
+        log::error!(
+            "prover {} of the pool failed, restarting it (restart {}/{} within an hour): {}",
+            instance,
+            monitored.restarts.len(),
+            self.max_restarts_per_hour,
+            err
+        );
+        self.restart_instance(instance);
+    }
+
+    fn restart_instance(&mut self, instance: usize) {
+        let prover = self.create_prover(instance);
+        self.pool.set_state(instance, InstanceState::Starting);
+        let (handle, monitored) = self.start_instance(prover);
+        self.instances[instance].shutdown_request = monitored.shutdown_request;
+        self.instances[instance].exit_err_rx = monitored.exit_err_rx;
+        // Previous prover is already finished, so dropping its handle doesn't affect anything.
+        self.handles.lock().unwrap()[instance] = handle;
+        if let Some(metrics) = &self.pool.metrics {
+            metrics.restarts.inc();
+        }
+    }
+
+    /// Only the first error is sent, since the pool exits once it's received.
+    fn send_exit_error(&mut self, err: BabyProverError) {
+        if !self.exit_err_sent {
+            self.exit_err_sent = true;
+            // Receiver may be already dropped if nobody waits for the pool to stop.
+            let _ = self.exit_err_tx.send(err);
+        }
+    }
+
+    fn update_metrics(&self) {
+        let metrics = match &self.pool.metrics {
+            Some(metrics) => metrics,
+            None => return,
+        };
+        let mut state = self.pool.state.lock().unwrap();
+        let now = Instant::now();
+        while state.published.front().map_or(false, |&published| {
+            now.duration_since(published) >= PROOF_RATE_WINDOW
+        }) {
+            state.published.pop_front();
+        }
+        metrics
+            .proof_rate
+            .set(state.published.len() as f64 / PROOF_RATE_WINDOW.as_secs_f64());
+        metrics
+            .jobs_in_flight
+            .set(state.count(InstanceState::Busy) as f64);
+        metrics
+            .instances_alive
+            .set((state.instances.len() - state.count(InstanceState::Stopped)) as f64);
+    }
+}
@@ -0,0 +1,81 @@
+//! In-memory cache of the created proofs which were not published yet.
+//!
+//! If publishing fails after the proof is created, the job is taken again on the next rounds.
+//! Proof generation is expensive, so the cached proof is published instead of re-proving the block.
+
+// Built-in deps
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+// Workspace deps
+use models::prover_utils::EncodedProofPlonk;
+
+#[derive(Debug)]
+struct CachedProof {
+    proof: EncodedProofPlonk,
+    created_at: Instant,
+}
+
+/// Proofs waiting to be published, keyed by block number.
+/// Entries are evicted once published, or once they are older than `ttl`.
+///
+/// Clones share the cached proofs.
+#[derive(Debug, Clone)]
+pub struct ProofCache {
+    ttl: Duration,
+    proofs: Arc<Mutex<HashMap<i64, CachedProof>>>,
+}
+
+impl ProofCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            proofs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Caches the proof for the block, replacing the previously cached one.
+    pub fn insert(&self, block: i64, proof: EncodedProofPlonk) {
+        let mut proofs = self.proofs.lock().unwrap();
+        self.evict_expired(&mut proofs);
+        proofs.insert(
+            block,
+            CachedProof {
+                proof,
+                created_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Returns the cached proof for the block, unless it's expired.
+    pub fn get(&self, block: i64) -> Option<EncodedProofPlonk> {
+        let mut proofs = self.proofs.lock().unwrap();
+        self.evict_expired(&mut proofs);
+        proofs.get(&block).map(|cached| cached.proof.clone())
+    }
+
+    /// Evicts the proof for the block, e.g. once it's published.
+    pub fn remove(&self, block: i64) {
+        self.proofs.lock().unwrap().remove(&block);
+    }
+
+    /// Amount of cached proofs, including the expired ones which are not evicted yet.
+    pub fn len(&self) -> usize {
+        self.proofs.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn evict_expired(&self, proofs: &mut HashMap<i64, CachedProof>) {
+        let ttl = self.ttl;
+        proofs.retain(|block, cached| {
+            let expired = cached.created_at.elapsed() > ttl;
+            if expired {
+                log::warn!("cached proof for block {} expired unpublished", block);
+            }
+            !expired
+        });
+    }
+}
@@ -0,0 +1,257 @@
+//! On-disk spool of the generated proofs that were not yet accepted by the server.
+//!
+//! Proof generation is expensive, so the proof is written to the spool before publishing
+//! and removed only after the server acknowledges it. Proofs left in the spool (e.g. because the
+//! server was unavailable or the prover was restarted) are re-published later.
+
+// Built-in deps
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+// External deps
+use serde::{Deserialize, Serialize};
+// Workspace deps
+use circuit::circuit::FranklinCircuit;
+use models::node::Engine;
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
+// Local deps
+use crate::client::{CheckVkRes, ProofConflict, ProofStatus, PublishResult, ServerHealth, VkHash};
+use crate::{ApiClient, JobProgress};
+
+const SPOOLED_PROOF_EXTENSION: &str = "json";
+
+/// Proof stored in the spool.
+///
+/// Format is versioned, so proofs written by one prover version can be replayed by the next one.
+/// Public data commitment of the block is stored within the proof inputs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpooledProof {
+    pub version: u32,
+    pub block: i64,
+    pub proof: EncodedProofPlonk,
+}
+
+impl SpooledProof {
+    pub const CURRENT_VERSION: u32 = 1;
+
+    pub fn new(block: i64, proof: EncodedProofPlonk) -> Self {
+        Self {
+            version: Self::CURRENT_VERSION,
+            block,
+            proof,
+        }
+    }
+}
+
+/// Directory with the proofs waiting to be published, one file per block.
+#[derive(Debug, Clone)]
+pub struct ProofSpool {
+    dir: PathBuf,
+}
+
+impl ProofSpool {
+    /// Opens the spool in the given directory, creating the directory if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn proof_path(&self, block: i64) -> PathBuf {
+        self.dir
+            .join(format!("block_{:010}.{}", block, SPOOLED_PROOF_EXTENSION))
+    }
+
+    /// Stores the proof for the block, replacing the previously stored one.
+    pub fn store(&self, block: i64, proof: &EncodedProofPlonk) -> io::Result<()> {
+        let spooled = SpooledProof::new(block, proof.clone());
+        let bytes = serde_json::to_vec_pretty(&spooled)?;
+        // File is written under the temporary name first, so partially written proof is never read.
+        let path = self.proof_path(block);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bytes)?;
+        fs::rename(tmp_path, path)
+    }
+
+    /// Removes the proof for the block. Does nothing if there is no such proof.
+    pub fn remove(&self, block: i64) -> io::Result<()> {
+        match fs::remove_file(self.proof_path(block)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Loads all the stored proofs, ordered by block number.
+    /// Files that can't be read are left in place and skipped.
+    pub fn pending(&self) -> io::Result<Vec<SpooledProof>> {
+        let mut proofs = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(SPOOLED_PROOF_EXTENSION) {
+                continue;
+            }
+            let spooled = fs::read(&path).and_then(|bytes| {
+                serde_json::from_slice::<SpooledProof>(&bytes).map_err(io::Error::from)
+            });
+            match spooled {
+                Ok(spooled) if spooled.version <= SpooledProof::CURRENT_VERSION => {
+                    proofs.push(spooled)
+                }
+                Ok(spooled) => log::warn!(
+                    "skipping spooled proof {} of unsupported version {}",
+                    path.display(),
+                    spooled.version
+                ),
+                Err(e) => log::warn!(
+                    "skipping unreadable spooled proof {}: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        proofs.sort_by_key(|spooled| spooled.block);
+        Ok(proofs)
+    }
+}
+
+/// API client wrapper that keeps every published proof in the spool until the server accepts it.
+///
+/// Proofs left in the spool are re-published before requesting a new job, i.e. on startup
+/// and at the start of every round.
+#[derive(Debug, Clone)]
+pub struct SpoolingApiClient<C> {
+    inner: C,
+    spool: ProofSpool,
+    /// Prevents concurrently running workers from re-publishing the same proofs.
+    republish_lock: Arc<Mutex<()>>,
+}
+
+impl<C: ApiClient> SpoolingApiClient<C> {
+    pub fn new(inner: C, spool: ProofSpool) -> Self {
+        Self {
+            inner,
+            spool,
+            republish_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Returns the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Publishes all the proofs from the spool, removing the accepted ones.
+    pub fn republish_pending(&self) -> Result<(), failure::Error> {
+        let _guard = self.republish_lock.lock().unwrap();
+        for spooled in self.spool.pending()? {
+            log::info!("re-publishing spooled proof for block {}", spooled.block);
+            match self.inner.publish(spooled.block, spooled.proof) {
+                Ok(()) => {}
+                Err(e) if e.downcast_ref::<ProofConflict>().is_some() => {
+                    log::error!("dropping spooled proof: {}", e);
+                }
+                Err(e) => return Err(e),
+            }
+            self.spool.remove(spooled.block)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: ApiClient> ApiClient for SpoolingApiClient<C> {
+    fn block_to_prove(&self, block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        self.republish_pending()?;
+        self.inner.block_to_prove(block_size)
+    }
+
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error> {
+        self.inner.working_on(job_id, progress)
+    }
+
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        self.inner.extend_lease(job_id, extra_seconds)
+    }
+
+    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        self.inner.prover_data(block)
+    }
+
+    fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        self.inner.prover_data_for_block(block)
+    }
+
+    fn publish(&self, block: i64, proof: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.publish_with_stats(block, proof, ProvingStats::default())
+    }
+
+    /// Statistics are not spooled, so the re-published proofs are sent without them.
+    fn publish_with_stats(
+        &self,
+        block: i64,
+        proof: EncodedProofPlonk,
+        stats: ProvingStats,
+    ) -> Result<(), failure::Error> {
+        // Failing to spool the proof is not a reason to throw it away, so it is published anyway.
+        if let Err(e) = self.spool.store(block, &proof) {
+            log::error!("failed to spool proof for block {}: {}", block, e);
+        }
+        let published = self.inner.publish_with_stats(block, proof, stats);
+        // Conflicting proof is never accepted, so there is no point in keeping it.
+        if let Err(e) = &published {
+            if e.downcast_ref::<ProofConflict>().is_none() {
+                return published;
+            }
+        }
+        self.spool.remove(block)?;
+        published
+    }
+
+    /// Proofs are spooled until the server either stores or rejects the whole batch.
+    fn publish_batch(
+        &self,
+        items: Vec<(i64, EncodedProofPlonk)>,
+    ) -> Result<Vec<PublishResult>, failure::Error> {
+        for (block, proof) in &items {
+            if let Err(e) = self.spool.store(*block, proof) {
+                log::error!("failed to spool proof for block {}: {}", block, e);
+            }
+        }
+        let blocks: Vec<i64> = items.iter().map(|(block, _)| *block).collect();
+        let results = self.inner.publish_batch(items)?;
+        for (block, result) in blocks.into_iter().zip(&results) {
+            // Not committed proofs are valid, so they are kept to be published again.
+            if *result != PublishResult::NotCommitted {
+                self.spool.remove(block)?;
+            }
+        }
+        Ok(results)
+    }
+
+    fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        self.inner.prover_stopped(prover_run_id)
+    }
+
+    fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        self.inner.record_failure(job_id, reason)
+    }
+
+    fn health_check(&self) -> Result<ServerHealth, failure::Error> {
+        self.inner.health_check()
+    }
+
+    fn proof_status(&self, block: i64) -> Result<ProofStatus, failure::Error> {
+        self.inner.proof_status(block)
+    }
+
+    fn check_vk(&self, vk_hashes: &[VkHash]) -> Result<CheckVkRes, failure::Error> {
+        self.inner.check_vk(vk_hashes)
+    }
+}
@@ -1,19 +1,27 @@
 // Built-in
+use std::fs;
+use std::path::Path;
 // External
+use failure::{format_err, Fail};
 use serde::{Deserialize, Serialize};
 // Workspace
-use circuit::account::AccountWitness;
 use circuit::circuit::FranklinCircuit;
-use circuit::operation::{
-    OperationArguments, OperationBranch, OperationBranchWitness, SignatureData,
-};
-use crypto_exports::ff::PrimeField;
+use circuit::operation::{OperationArguments, OperationBranch, SignatureData};
+use circuit::serialization::{AccountWitnessDef, OperationArgumentsDef, OperationBranchDef};
+use crypto_exports::ff::{Field, PrimeField};
 use crypto_exports::franklin_crypto::alt_babyjubjub::AltJubjubBn256;
 use crypto_exports::franklin_crypto::rescue::bn256::Bn256RescueParams;
 use models::node::{Engine, Fr};
+use models::params;
 // Local
 use crate::serialization::*;
 
+/// First byte of the binary encoding of the prover data, see `encode_binary`.
+/// JSON encoding never starts with it, so the encodings are told apart by the first byte.
+pub const BINARY_ENCODING_VERSION: u8 = 1;
+/// Media type of the binary encoded prover data, accepted by the provers able to decode it.
+pub const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+
 /// ProverData is data prover needs to calculate proof of the given block.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProverData {
@@ -37,7 +45,105 @@ pub struct ProverData {
     pub validator_account: circuit::account::AccountWitness<Engine>,
 }
 
+/// Malformed witness, detected before the circuit synthesis.
+#[derive(Debug, Clone, PartialEq, Eq, Fail)]
+pub enum ProverDataError {
+    #[fail(
+        display = "operations: expected {} operations, got {}",
+        expected, actual
+    )]
+    OperationsCount { expected: usize, actual: usize },
+    #[fail(
+        display = "operations: expected one of {:?} operations, got {}",
+        supported, actual
+    )]
+    UnsupportedOperationsCount {
+        supported: Vec<usize>,
+        actual: usize,
+    },
+    #[fail(
+        display = "validator_balances: expected length {}, got {}",
+        expected, actual
+    )]
+    ValidatorBalancesLength { expected: usize, actual: usize },
+    #[fail(
+        display = "validator_audit_path: expected length {}, got {}",
+        expected, actual
+    )]
+    ValidatorAuditPathLength { expected: usize, actual: usize },
+    /// `branch` is either `lhs` or `rhs`.
+    #[fail(
+        display = "operations[{}].{}.witness.account_path: expected length {}, got {}",
+        operation, branch, expected, actual
+    )]
+    AccountPathLength {
+        operation: usize,
+        branch: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// `branch` is either `lhs` or `rhs`.
+    #[fail(
+        display = "operations[{}].{}.witness.balance_subtree_path: expected length {}, got {}",
+        operation, branch, expected, actual
+    )]
+    BalanceSubtreePathLength {
+        operation: usize,
+        branch: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+}
+
 impl ProverData {
+    /// Checks that the witness fits the circuit for the blocks of `chunk_size` chunks,
+    /// since otherwise the synthesis fails with an opaque error.
+    pub fn validate(&self, chunk_size: usize) -> Result<(), ProverDataError> {
+        validate_witness(
+            &self.operations,
+            &self.validator_balances,
+            &self.validator_audit_path,
+            chunk_size,
+        )
+    }
+
+    /// Writes the prover data to the JSON file, so the inputs of the failing proof
+    /// can be inspected or re-proved later.
+    pub fn save_to_file(&self, path: &Path) -> Result<(), failure::Error> {
+        let contents = serde_json::to_vec_pretty(self)?;
+        fs::write(path, contents)
+            .map_err(|e| format_err!("failed to write prover data to {}: {}", path.display(), e))
+    }
+
+    /// Reads the prover data written by `save_to_file`.
+    pub fn load_from_file(path: &Path) -> Result<Self, failure::Error> {
+        let contents = fs::read(path).map_err(|e| {
+            format_err!("failed to read prover data from {}: {}", path.display(), e)
+        })?;
+        serde_json::from_slice(&contents)
+            .map_err(|e| format_err!("invalid prover data in {}: {}", path.display(), e))
+    }
+
+    /// Reverse of `into_circuit`. Absent values of the circuit are replaced with zeroes,
+    /// the circuits created by `into_circuit` have all of them.
+    pub fn from_circuit(circuit: &FranklinCircuit<'_, Engine>) -> Self {
+        Self {
+            public_data_commitment: circuit.pub_data_commitment.unwrap_or_else(Fr::zero),
+            old_root: circuit.old_root.unwrap_or_else(Fr::zero),
+            initial_used_subtree_root: circuit.initial_used_subtree_root.unwrap_or_else(Fr::zero),
+            new_root: circuit
+                .operations
+                .last()
+                .and_then(|operation| operation.new_root)
+                .unwrap_or_else(Fr::zero),
+            validator_address: circuit.validator_address.unwrap_or_else(Fr::zero),
+            validator_balances: circuit.validator_balances.clone(),
+            validator_audit_path: circuit.validator_audit_path.clone(),
+            operations: circuit.operations.clone(),
+            validator_account: circuit.validator_account.clone(),
+        }
+    }
+
     pub fn into_circuit(self, block: i64) -> FranklinCircuit<'static, Engine> {
         FranklinCircuit {
             rescue_params: &models::params::RESCUE_PARAMS as &Bn256RescueParams,
@@ -55,15 +161,106 @@ impl ProverData {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "circuit::account::AccountWitness::<Engine>")]
-struct AccountWitnessDef {
-    #[serde(with = "OptionalFrSerde")]
-    pub nonce: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub pub_key_hash: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub address: Option<Fr>,
+/// Encodes the prover data (`None` if the witness is absent) in the compact binary format:
+/// `BINARY_ENCODING_VERSION` followed by the CBOR encoding, with the field elements as raw bytes.
+pub fn encode_binary(prover_data: Option<&ProverData>) -> Result<Vec<u8>, failure::Error> {
+    let mut encoded = vec![BINARY_ENCODING_VERSION];
+    serde_cbor::to_writer(&mut encoded, &prover_data)?;
+    Ok(encoded)
+}
+
+/// Decodes the prover data encoded either in JSON or by `encode_binary`.
+pub fn decode(data: &[u8]) -> Result<Option<ProverData>, failure::Error> {
+    match data.first() {
+        Some(&BINARY_ENCODING_VERSION) => Ok(serde_cbor::from_slice(&data[1..])?),
+        // Encoding of the future version, JSON starts with the printable character.
+        Some(version) if version.is_ascii_control() && !version.is_ascii_whitespace() => Err(
+            format_err!("unsupported prover data encoding version: {}", version),
+        ),
+        _ => Ok(serde_json::from_slice(data)?),
+    }
+}
+
+/// Same checks as `ProverData::validate`, for the witness already converted to the circuit.
+pub fn validate_circuit(
+    circuit: &FranklinCircuit<'_, Engine>,
+    chunk_size: usize,
+) -> Result<(), ProverDataError> {
+    validate_witness(
+        &circuit.operations,
+        &circuit.validator_balances,
+        &circuit.validator_audit_path,
+        chunk_size,
+    )
+}
+
+/// Same as `validate_circuit`, for the block of unknown size, which has to be one of
+/// `chunk_sizes`. Returns the size of the block.
+pub fn validate_circuit_of_any_size(
+    circuit: &FranklinCircuit<'_, Engine>,
+    chunk_sizes: &[usize],
+) -> Result<usize, ProverDataError> {
+    let chunk_size = circuit.operations.len();
+    if !chunk_sizes.contains(&chunk_size) {
+        return Err(ProverDataError::UnsupportedOperationsCount {
+            supported: chunk_sizes.to_vec(),
+            actual: chunk_size,
+        });
+    }
+    validate_circuit(circuit, chunk_size)?;
+    Ok(chunk_size)
+}
+
+fn validate_witness(
+    operations: &[circuit::operation::Operation<Engine>],
+    validator_balances: &[Option<Fr>],
+    validator_audit_path: &[Option<Fr>],
+    chunk_size: usize,
+) -> Result<(), ProverDataError> {
+    // Every chunk of the block is a separate operation of the circuit.
+    if operations.len() != chunk_size {
+        return Err(ProverDataError::OperationsCount {
+            expected: chunk_size,
+            actual: operations.len(),
+        });
+    }
+    // Validator account holds a balance for every token of the balance tree.
+    if validator_balances.len() != params::total_tokens() {
+        return Err(ProverDataError::ValidatorBalancesLength {
+            expected: params::total_tokens(),
+            actual: validator_balances.len(),
+        });
+    }
+    if validator_audit_path.len() != params::account_tree_depth() {
+        return Err(ProverDataError::ValidatorAuditPathLength {
+            expected: params::account_tree_depth(),
+            actual: validator_audit_path.len(),
+        });
+    }
+    for (operation_idx, operation) in operations.iter().enumerate() {
+        for &(branch, witness) in &[
+            ("lhs", &operation.lhs.witness),
+            ("rhs", &operation.rhs.witness),
+        ] {
+            if witness.account_path.len() != params::account_tree_depth() {
+                return Err(ProverDataError::AccountPathLength {
+                    operation: operation_idx,
+                    branch,
+                    expected: params::account_tree_depth(),
+                    actual: witness.account_path.len(),
+                });
+            }
+            if witness.balance_subtree_path.len() != params::balance_tree_depth() {
+                return Err(ProverDataError::BalanceSubtreePathLength {
+                    operation: operation_idx,
+                    branch,
+                    expected: params::balance_tree_depth(),
+                    actual: witness.balance_subtree_path.len(),
+                });
+            }
+        }
+    }
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize)]
@@ -93,48 +290,3 @@ pub struct OperationDef {
     #[serde(with = "OperationBranchDef")]
     pub rhs: OperationBranch<Engine>,
 }
-
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "circuit::operation::OperationArguments::<Engine>")]
-pub struct OperationArgumentsDef {
-    #[serde(with = "OptionalFrSerde")]
-    pub a: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub b: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub amount_packed: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub full_amount: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub fee: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub new_pub_key_hash: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub eth_address: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub pub_nonce: Option<Fr>,
-}
-
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "circuit::operation::OperationBranch::<Engine>")]
-pub struct OperationBranchDef {
-    #[serde(with = "OptionalFrSerde")]
-    pub address: Option<Fr>,
-    #[serde(with = "OptionalFrSerde")]
-    pub token: Option<Fr>,
-    #[serde(with = "OperationBranchWitnessDef")]
-    pub witness: OperationBranchWitness<Engine>,
-}
-
-#[derive(Serialize, Deserialize)]
-#[serde(remote = "circuit::operation::OperationBranchWitness::<Engine>")]
-pub struct OperationBranchWitnessDef {
-    #[serde(with = "AccountWitnessDef")]
-    pub account_witness: AccountWitness<Engine>,
-    #[serde(with = "VecOptionalFrSerde")]
-    pub account_path: Vec<Option<Fr>>,
-    #[serde(with = "OptionalFrSerde")]
-    pub balance_value: Option<Fr>,
-    #[serde(with = "VecOptionalFrSerde")]
-    pub balance_subtree_path: Vec<Option<Fr>>,
-}
@@ -0,0 +1,107 @@
+//! Export of the prover tracing spans to the Jaeger agent or the OpenTelemetry collector.
+//!
+//! Spans are recorded with the `tracing` crate and are no-ops unless the exporter is started.
+//! Trace context is passed between the prover and the prover server in the W3C `traceparent` header.
+
+// Built-in deps
+use std::collections::HashMap;
+// External deps
+use opentelemetry::sdk::{propagation::TraceContextPropagator, trace::Tracer};
+use opentelemetry::trace::TraceContextExt;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+// Workspace deps
+use models::config_options::parse_env_opt;
+
+/// Port of the Jaeger agent, used if `JAEGER_AGENT_PORT` is not set.
+const DEFAULT_JAEGER_AGENT_PORT: u16 = 6831;
+
+enum Uninstall {
+    Jaeger(opentelemetry_jaeger::Uninstall),
+    Otlp(opentelemetry_otlp::Uninstall),
+}
+
+/// Keeps the spans exporter running, pending spans are flushed when it's dropped.
+pub struct TelemetryGuard {
+    _uninstall: Uninstall,
+}
+
+/// Creates the tracer exporting the spans to the Jaeger agent at `JAEGER_AGENT_HOST`
+/// (with the port `JAEGER_AGENT_PORT`), or to the OTLP collector at `OTEL_EXPORTER_OTLP_ENDPOINT`.
+/// Returns `None` if neither is configured.
+pub fn tracer_from_env(service_name: &str) -> Option<(Tracer, TelemetryGuard)> {
+    if let Some(host) = parse_env_opt::<String>("JAEGER_AGENT_HOST") {
+        let port = parse_env_opt("JAEGER_AGENT_PORT").unwrap_or(DEFAULT_JAEGER_AGENT_PORT);
+        let endpoint = format!("{}:{}", host, port);
+        let (tracer, uninstall) = opentelemetry_jaeger::new_pipeline()
+            .with_agent_endpoint(endpoint.as_str())
+            .with_service_name(service_name)
+            .install()
+            .expect("failed to install Jaeger exporter");
+        log::info!("exporting tracing spans to Jaeger agent {}", endpoint);
+        return Some((
+            tracer,
+            TelemetryGuard {
+                _uninstall: Uninstall::Jaeger(uninstall),
+            },
+        ));
+    }
+
+    let endpoint: String = parse_env_opt("OTEL_EXPORTER_OTLP_ENDPOINT")?;
+    let (tracer, uninstall) = opentelemetry_otlp::new_pipeline()
+        .with_endpoint(&endpoint)
+        .with_trace_config(opentelemetry::sdk::trace::config().with_resource(
+            opentelemetry::sdk::Resource::new(vec![opentelemetry::KeyValue::new(
+                "service.name",
+                service_name.to_string(),
+            )]),
+        ))
+        .install()
+        .expect("failed to install OpenTelemetry exporter");
+    log::info!("exporting tracing spans to {}", endpoint);
+    Some((
+        tracer,
+        TelemetryGuard {
+            _uninstall: Uninstall::Otlp(uninstall),
+        },
+    ))
+}
+
+/// Sets the subscriber sending the spans of the whole process to the `tracer`,
+/// and the W3C Trace Context propagation of the spans between the processes.
+pub fn start_tracing(tracer: Tracer) {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let subscriber =
+        tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    tracing::subscriber::set_global_default(subscriber)
+        .expect("failed to set global tracing subscriber");
+}
+
+/// Headers carrying the trace context of the current span to the other process.
+/// Empty unless the tracing is started.
+pub fn trace_context_headers() -> HashMap<String, String> {
+    let context = tracing::Span::current().context();
+    let mut headers = HashMap::new();
+    opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut headers)
+    });
+    headers
+}
+
+/// Makes the trace context carried by the request `headers` the parent of the `span`.
+pub fn set_parent_from_headers(span: &tracing::Span, headers: &HashMap<String, String>) {
+    let parent =
+        opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(headers));
+    span.set_parent(&parent);
+}
+
+/// Hex-encoded ID of the trace the `span` belongs to, `None` if the span isn't exported.
+pub fn trace_id(span: &tracing::Span) -> Option<String> {
+    let context = span.context();
+    let span_context = context.span().span_context();
+    if span_context.is_valid() {
+        Some(span_context.trace_id().to_hex())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,109 @@
+//! Synthetic prover data, used to run the prover without the server (tests, benchmarks).
+//!
+//! With the `testing` feature also provides `MockApiClient`, so the prover can be run
+//! against the scripted server responses.
+
+// Built-in deps
+use std::time::Duration;
+// External deps
+use crypto_exports::pairing::ff::PrimeField;
+use num::BigUint;
+// Workspace deps
+use circuit::witness::{deposit::DepositWitness, utils::WitnessBuilder, Witness};
+use models::{
+    circuit::{account::CircuitAccount, CircuitAccountTree},
+    node::{
+        block::smallest_block_size_for_chunks, operations::DepositOp, Account, Address, Deposit, Fr,
+    },
+    prover_utils::fs_utils::get_block_verification_key_path,
+};
+// Local deps
+use crate::plonk_step_by_step_prover::PlonkStepByStepProverConfig;
+use crate::prover_data::ProverData;
+use crate::RetryPolicy;
+
+#[cfg(feature = "testing")]
+mod mock_api_client;
+
+#[cfg(feature = "testing")]
+pub use self::mock_api_client::{MockApiClient, WorkingOnCall};
+
+/// Creates the data for proving the block with a single deposit, padded with noops
+/// up to `block_size_chunks`.
+pub fn deposit_block_prover_data(block_size_chunks: usize) -> ProverData {
+    let mut circuit_account_tree = CircuitAccountTree::new(models::params::account_tree_depth());
+    let fee_account_id = 0;
+
+    // Init the fee account.
+    let fee_account = Account::default_with_address(&Address::default());
+    circuit_account_tree.insert(fee_account_id, CircuitAccount::from(fee_account));
+
+    let mut witness_accum = WitnessBuilder::new(&mut circuit_account_tree, fee_account_id, 1);
+
+    let empty_account_id = 1;
+    let empty_account_address = [7u8; 20].into();
+    let deposit_op = DepositOp {
+        priority_op: Deposit {
+            from: empty_account_address,
+            token: 0,
+            amount: BigUint::from(1u32),
+            to: empty_account_address,
+        },
+        account_id: empty_account_id,
+    };
+
+    let deposit_witness = DepositWitness::apply_tx(&mut witness_accum.account_tree, &deposit_op);
+    let deposit_operations = deposit_witness.calculate_operations(());
+    let pub_data_from_witness = deposit_witness.get_pubdata();
+
+    witness_accum.add_operation_with_pubdata(deposit_operations, pub_data_from_witness);
+    witness_accum.extend_pubdata_with_noops(block_size_chunks);
+    witness_accum.collect_fees(&Vec::new());
+    witness_accum.calculate_pubdata_commitment();
+
+    ProverData {
+        public_data_commitment: witness_accum.pubdata_commitment.unwrap(),
+        old_root: witness_accum.initial_root_hash,
+        initial_used_subtree_root: witness_accum.initial_used_subtree_root_hash,
+        new_root: witness_accum.root_after_fees.unwrap(),
+        validator_address: Fr::from_str(&witness_accum.fee_account_id.to_string())
+            .expect("failed to parse"),
+        operations: witness_accum.operations,
+        validator_balances: witness_accum.fee_account_balances.unwrap(),
+        validator_audit_path: witness_accum.fee_account_audit_path.unwrap(),
+        validator_account: witness_accum.fee_account_witness.unwrap(),
+    }
+}
+
+/// Returns the smallest of the `available_block_sizes` fitting the block with a single deposit.
+pub fn smallest_deposit_block_size(available_block_sizes: &[usize]) -> usize {
+    smallest_block_size_for_chunks(DepositOp::CHUNKS, available_block_sizes)
+}
+
+/// Returns the size of the block fitting a single deposit, which is none of the
+/// `available_block_sizes` and has no verification key, so the prover configured
+/// for it fails the job before loading the setup.
+pub fn deposit_block_size_without_keys(available_block_sizes: &[usize]) -> usize {
+    (DepositOp::CHUNKS..)
+        .find(|size| {
+            !available_block_sizes.contains(size)
+                && !get_block_verification_key_path(*size).exists()
+        })
+        .expect("every block size has the keys")
+}
+
+/// Config of the prover of `block_sizes` which doesn't touch the network or the disk,
+/// tests override the fields they exercise.
+pub fn prover_config(block_sizes: Vec<usize>) -> PlonkStepByStepProverConfig {
+    PlonkStepByStepProverConfig {
+        block_sizes,
+        download_setup_from_network: false,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        pinned_blocks: None,
+        proof_cache_ttl: Duration::from_secs(3600),
+        dump_prover_data: None,
+        retry_policy: RetryPolicy::new(Duration::from_millis(100), Duration::from_millis(400)),
+    }
+}
@@ -0,0 +1,182 @@
+//! In-memory `ApiClient`, used to run the prover against the scripted server responses.
+
+// Built-in deps
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+// External deps
+use circuit::circuit::FranklinCircuit;
+// Workspace deps
+use models::{node::Engine, prover_utils::EncodedProofPlonk};
+// Local deps
+use crate::{prover_data::ProverData, ApiClient, JobProgress};
+
+/// Recorded `working_on` request.
+#[derive(Debug, Clone, Copy)]
+pub struct WorkingOnCall {
+    pub job_id: i32,
+    pub progress: Option<JobProgress>,
+    pub at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    block_to_prove_responses: Mutex<VecDeque<Option<(i64, i32)>>>,
+    prover_data: Mutex<HashMap<i64, ProverData>>,
+    prover_data_requests: Mutex<Vec<i64>>,
+    working_on_calls: Mutex<Vec<WorkingOnCall>>,
+    extended_leases: Mutex<Vec<(i32, u32)>>,
+    failing_publishes: Mutex<usize>,
+    published: Mutex<Vec<(i64, EncodedProofPlonk)>>,
+    stopped_provers: Mutex<Vec<i32>>,
+    failures: Mutex<Vec<(i32, String)>>,
+}
+
+/// `ApiClient` answering the prover requests with the scripted responses and recording
+/// the requests made, for the assertions in tests.
+///
+/// Clones share the state, so a clone can be kept by the test while the prover owns the client.
+#[derive(Debug, Clone, Default)]
+pub struct MockApiClient {
+    state: Arc<MockState>,
+}
+
+impl MockApiClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scripts the responses to the `block_to_prove` requests, returned in order regardless
+    /// of the requested block size. There is no job once the script is over.
+    pub fn with_block_to_prove_responses(
+        self,
+        responses: impl IntoIterator<Item = Option<(i64, i32)>>,
+    ) -> Self {
+        self.state
+            .block_to_prove_responses
+            .lock()
+            .unwrap()
+            .extend(responses);
+        self
+    }
+
+    /// Serves the data for the block. Requests for the blocks without the data fail.
+    pub fn with_prover_data(self, block: i64, prover_data: ProverData) -> Self {
+        self.state
+            .prover_data
+            .lock()
+            .unwrap()
+            .insert(block, prover_data);
+        self
+    }
+
+    /// Fails the first `count` `publish` requests, the failed proofs are not recorded.
+    pub fn with_failing_publishes(self, count: usize) -> Self {
+        *self.state.failing_publishes.lock().unwrap() = count;
+        self
+    }
+
+    /// Returns the blocks the prover data was requested for so far.
+    pub fn prover_data_requests(&self) -> Vec<i64> {
+        self.state.prover_data_requests.lock().unwrap().clone()
+    }
+
+    /// Returns the `working_on` requests made so far.
+    pub fn working_on_calls(&self) -> Vec<WorkingOnCall> {
+        self.state.working_on_calls.lock().unwrap().clone()
+    }
+
+    /// Returns the `extend_lease` requests made so far, as job IDs along with the extra seconds.
+    pub fn extended_leases(&self) -> Vec<(i32, u32)> {
+        self.state.extended_leases.lock().unwrap().clone()
+    }
+
+    /// Returns the proofs published so far, along with their blocks.
+    pub fn published_proofs(&self) -> Vec<(i64, EncodedProofPlonk)> {
+        self.state.published.lock().unwrap().clone()
+    }
+
+    /// Returns the IDs of the provers reported as stopped.
+    pub fn stopped_provers(&self) -> Vec<i32> {
+        self.state.stopped_provers.lock().unwrap().clone()
+    }
+
+    /// Returns the job failures reported so far, as job IDs along with the reasons.
+    pub fn recorded_failures(&self) -> Vec<(i32, String)> {
+        self.state.failures.lock().unwrap().clone()
+    }
+}
+
+impl ApiClient for MockApiClient {
+    fn block_to_prove(&self, _block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        let mut responses = self.state.block_to_prove_responses.lock().unwrap();
+        Ok(responses.pop_front().flatten())
+    }
+
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error> {
+        self.state
+            .working_on_calls
+            .lock()
+            .unwrap()
+            .push(WorkingOnCall {
+                job_id,
+                progress,
+                at: Instant::now(),
+            });
+        Ok(())
+    }
+
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        self.state
+            .extended_leases
+            .lock()
+            .unwrap()
+            .push((job_id, extra_seconds));
+        Ok(())
+    }
+
+    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        self.state.prover_data_requests.lock().unwrap().push(block);
+        let prover_data = self.state.prover_data.lock().unwrap();
+        match prover_data.get(&block) {
+            Some(prover_data) => Ok(prover_data.clone().into_circuit(block)),
+            None => Err(failure::format_err!("no prover data for block {}", block)),
+        }
+    }
+
+    /// Served from the same prover data as `prover_data`.
+    fn prover_data_for_block(
+        &self,
+        block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        self.prover_data(block)
+    }
+
+    fn publish(&self, block: i64, proof: EncodedProofPlonk) -> Result<(), failure::Error> {
+        let mut failing_publishes = self.state.failing_publishes.lock().unwrap();
+        if *failing_publishes > 0 {
+            *failing_publishes -= 1;
+            failure::bail!("scripted publish failure for block {}", block);
+        }
+        self.state.published.lock().unwrap().push((block, proof));
+        Ok(())
+    }
+
+    fn prover_stopped(&self, prover_run_id: i32) -> Result<(), failure::Error> {
+        self.state
+            .stopped_provers
+            .lock()
+            .unwrap()
+            .push(prover_run_id);
+        Ok(())
+    }
+
+    fn record_failure(&self, job_id: i32, reason: &str) -> Result<(), failure::Error> {
+        self.state
+            .failures
+            .lock()
+            .unwrap()
+            .push((job_id, reason.to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! Mutual TLS between the provers and the prover server.
+//!
+//! Both sides present the certificate signed by the shared CA and reject the peers
+//! presenting no certificate or the certificate signed by another CA.
+
+// Built-in deps
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+// External deps
+use failure::{bail, format_err};
+use rustls::internal::pemfile;
+use rustls::{AllowAnyAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig};
+// Workspace deps
+pub use models::config_options::TlsConfig;
+
+/// Loads the TLS config of the prover server: `cert` and `key` identify the server,
+/// the provers must present the certificate signed by `ca`.
+pub fn load_tls_config(cert: &Path, key: &Path, ca: &Path) -> Result<ServerConfig, failure::Error> {
+    let mut client_roots = RootCertStore::empty();
+    let (added, _) = client_roots
+        .add_pem_file(&mut open(ca)?)
+        .map_err(|_| format_err!("invalid CA certificate {}", ca.display()))?;
+    if added == 0 {
+        bail!("no CA certificates in {}", ca.display());
+    }
+
+    let mut config = ServerConfig::new(AllowAnyAuthenticatedClient::new(client_roots));
+    config
+        .set_single_cert(load_certs(cert)?, load_private_key(key)?)
+        .map_err(|e| format_err!("invalid TLS certificate {}: {}", cert.display(), e))?;
+    Ok(config)
+}
+
+/// Same as `load_tls_config`, with the files of `config`.
+pub fn load_server_config(config: &TlsConfig) -> Result<ServerConfig, failure::Error> {
+    load_tls_config(&config.cert_pem, &config.key_pem, &config.ca_pem)
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, failure::Error> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| format_err!("failed to open {}: {}", path.display(), e))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Certificate>, failure::Error> {
+    let certs = pemfile::certs(&mut open(path)?)
+        .map_err(|_| format_err!("invalid TLS certificate {}", path.display()))?;
+    if certs.is_empty() {
+        bail!("no TLS certificates in {}", path.display());
+    }
+    Ok(certs)
+}
+
+/// Loads the first PKCS#8 or RSA private key of the file.
+fn load_private_key(path: &Path) -> Result<PrivateKey, failure::Error> {
+    let invalid = |_| format_err!("invalid TLS private key {}", path.display());
+    let mut keys = pemfile::pkcs8_private_keys(&mut open(path)?).map_err(invalid)?;
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut open(path)?).map_err(invalid)?;
+    }
+    keys.into_iter()
+        .next()
+        .ok_or_else(|| format_err!("no TLS private keys in {}", path.display()))
+}
@@ -0,0 +1,696 @@
+//! Tests of the prover running against `prover::testing::MockApiClient`.
+
+// Built-in deps
+use std::io::Write;
+use std::sync::{mpsc, Arc, Mutex};
+use std::{fs, net, thread, time};
+// Workspace deps
+use crypto_exports::pairing::ff::PrimeField;
+use models::{config_options::ConfigurationOptions, node::Fr, prover_utils::EncodedProofPlonk};
+// Local deps
+use prover::{
+    logging::{self, JsonLogger},
+    metrics::ProverMetrics,
+    pinned_blocks::PinnedBlocks,
+    plonk_step_by_step_prover::{PlonkStepByStepProver, PlonkStepByStepProverConfig},
+    testing::{self, MockApiClient},
+    ApiClient, BabyProverError, ProverImpl, ShutdownRequest,
+};
+
+#[test]
+fn mock_api_client_replays_script_and_records_requests() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![None, Some((1, 10))])
+        .with_prover_data(1, testing::deposit_block_prover_data(block_size));
+
+    assert_eq!(client.block_to_prove(block_size).unwrap(), None);
+    assert_eq!(client.block_to_prove(block_size).unwrap(), Some((1, 10)));
+    // Script is over.
+    assert_eq!(client.block_to_prove(block_size).unwrap(), None);
+
+    assert!(client.prover_data(1).is_ok());
+    assert!(client.prover_data(2).is_err());
+
+    // State is shared between the clones.
+    let prover_client = client.clone();
+    prover_client.working_on(10, None).unwrap();
+    prover_client
+        .publish(1, EncodedProofPlonk::default())
+        .unwrap();
+    prover_client.prover_stopped(3).unwrap();
+
+    let working_on_calls = client.working_on_calls();
+    assert_eq!(working_on_calls.len(), 1);
+    assert_eq!(working_on_calls[0].job_id, 10);
+    assert_eq!(
+        client.published_proofs(),
+        vec![(1, EncodedProofPlonk::default())]
+    );
+    assert_eq!(client.stopped_provers(), vec![3]);
+}
+
+#[test]
+fn stopped_prover_deregisters_before_exit() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let client = MockApiClient::new();
+    let config = testing::prover_config(vec![block_size]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let shutdown_request = ShutdownRequest::new();
+    shutdown_request.set_prover_id(7);
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, shutdown_request.clone());
+
+    // Same as the first termination signal received by the prover binary.
+    shutdown_request.set();
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+
+    assert!(exit_err_rx.try_recv().is_err(), "prover exited with error");
+    assert_eq!(client.stopped_provers(), vec![7]);
+}
+
+#[test]
+fn failed_job_is_reported_and_prover_deregisters_before_exit() {
+    let block_size = testing::deposit_block_size_without_keys(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 10))])
+        .with_prover_data(1, testing::deposit_block_prover_data(block_size));
+    // Block without the keys fails the job the same way as the failed proof verification,
+    // but without loading the setup.
+    let config = testing::prover_config(vec![block_size]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let shutdown_request = ShutdownRequest::new();
+    shutdown_request.set_prover_id(7);
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, shutdown_request);
+
+    let err = exit_err_rx
+        .recv_timeout(time::Duration::from_secs(60))
+        .expect("prover didn't fail on block without keys");
+    assert!(
+        matches!(err, BabyProverError::Internal { block: Some(1), .. }),
+        "unexpected error: {}",
+        err
+    );
+    assert_eq!(err.job_id(), Some(10));
+    handle.join();
+
+    let failures = client.recorded_failures();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0], (10, err.to_string()));
+    assert_eq!(client.stopped_provers(), vec![7]);
+}
+
+#[test]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+fn prover_proves_synthetic_block_against_mock_api_client() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 10))])
+        .with_prover_data(1, testing::deposit_block_prover_data(block_size));
+
+    let config = testing::prover_config(vec![block_size]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(60 * 10);
+    while client.published_proofs().is_empty() {
+        assert!(time::Instant::now() < deadline, "proof was not published");
+        assert!(exit_err_rx.try_recv().is_err(), "prover exited with error");
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+
+    let published = client.published_proofs();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].0, 1);
+    assert!(
+        client
+            .working_on_calls()
+            .iter()
+            .any(|call| call.job_id == 10),
+        "no heartbeats were sent for the job"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+fn pinned_prover_writes_proofs_to_disk_bypassing_job_queue() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((2, 20))])
+        .with_prover_data(1, testing::deposit_block_prover_data(block_size));
+    let pinned_blocks = PinnedBlocks {
+        range: 1..2,
+        proofs_dir: std::env::temp_dir().join(format!("pinned_proofs_test_{}", std::process::id())),
+    };
+
+    let config = PlonkStepByStepProverConfig {
+        pinned_blocks: Some(pinned_blocks.clone()),
+        ..testing::prover_config(vec![block_size])
+    };
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    let proof_path = pinned_blocks.proof_path(1);
+    let deadline = time::Instant::now() + time::Duration::from_secs(60 * 10);
+    while !proof_path.exists() {
+        assert!(time::Instant::now() < deadline, "proof was not written");
+        assert!(exit_err_rx.try_recv().is_err(), "prover exited with error");
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+
+    let written = fs::read(&proof_path).expect("failed to read written proof");
+    serde_json::from_slice::<EncodedProofPlonk>(&written).expect("written proof is invalid");
+    assert!(client.published_proofs().is_empty());
+    assert!(client.working_on_calls().is_empty());
+    // Job queue is not touched.
+    assert_eq!(client.block_to_prove(block_size).unwrap(), Some((2, 20)));
+
+    fs::remove_dir_all(&pinned_blocks.proofs_dir).unwrap();
+}
+
+#[test]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+fn prover_republishes_cached_proof_without_reproving() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    // Publishing fails once, so the block is assigned again.
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 10)), Some((1, 11))])
+        .with_prover_data(1, testing::deposit_block_prover_data(block_size))
+        .with_failing_publishes(1);
+
+    let config = testing::prover_config(vec![block_size]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(60 * 10);
+    while client.published_proofs().is_empty() {
+        assert!(time::Instant::now() < deadline, "proof was not published");
+        assert!(exit_err_rx.try_recv().is_err(), "prover exited with error");
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+
+    let published = client.published_proofs();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].0, 1);
+    assert_eq!(client.prover_data_requests(), vec![1]);
+}
+
+#[test]
+fn pinned_prover_fails_with_internal_error_on_malformed_prover_data() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let mut prover_data = testing::deposit_block_prover_data(block_size);
+    prover_data.validator_audit_path.pop();
+    let client = MockApiClient::new().with_prover_data(1, prover_data);
+    let pinned_blocks = PinnedBlocks {
+        range: 1..2,
+        proofs_dir: std::env::temp_dir()
+            .join(format!("pinned_malformed_test_{}", std::process::id())),
+    };
+
+    let config = PlonkStepByStepProverConfig {
+        pinned_blocks: Some(pinned_blocks.clone()),
+        ..testing::prover_config(vec![block_size])
+    };
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client,
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (tx, _rx) = mpsc::channel();
+    let err = p
+        .next_round(tx, &ShutdownRequest::new())
+        .expect_err("round with malformed prover data succeeded");
+    match &err {
+        BabyProverError::Internal { block, .. } => assert_eq!(*block, Some(1)),
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert!(
+        err.to_string().contains("validator_audit_path"),
+        "error doesn't name the malformed field: {}",
+        err
+    );
+    assert!(!pinned_blocks.proof_path(1).exists());
+}
+
+#[test]
+fn prover_rejects_prover_data_with_too_few_operations_as_retryable() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let mut prover_data = testing::deposit_block_prover_data(block_size);
+    prover_data.operations.pop();
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 10))])
+        .with_prover_data(1, prover_data);
+
+    let config = testing::prover_config(vec![block_size]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (tx, _rx) = mpsc::channel();
+    let err = p
+        .next_round(tx, &ShutdownRequest::new())
+        .expect_err("round with too few operations succeeded");
+    match &err {
+        BabyProverError::Api { .. } => assert!(err.is_retryable(), "error is not retryable"),
+        _ => panic!("unexpected error: {}", err),
+    }
+    let expected_msg = format!("expected {} operations, got {}", block_size, block_size - 1);
+    assert!(
+        err.to_string().contains(&expected_msg),
+        "error doesn't report the operations count: {}",
+        err
+    );
+    assert_eq!(client.prover_data_requests(), vec![1]);
+    assert!(client.published_proofs().is_empty());
+}
+
+#[test]
+fn pinned_prover_fails_with_internal_error_on_wrong_operations_count() {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let mut prover_data = testing::deposit_block_prover_data(block_size);
+    prover_data.operations.pop();
+    let client = MockApiClient::new().with_prover_data(1, prover_data);
+    let pinned_blocks = PinnedBlocks {
+        range: 1..2,
+        proofs_dir: std::env::temp_dir()
+            .join(format!("pinned_wrong_size_test_{}", std::process::id())),
+    };
+
+    let config = PlonkStepByStepProverConfig {
+        pinned_blocks: Some(pinned_blocks.clone()),
+        ..testing::prover_config(vec![block_size])
+    };
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client,
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (tx, _rx) = mpsc::channel();
+    let err = p
+        .next_round(tx, &ShutdownRequest::new())
+        .expect_err("round with wrong operations count succeeded");
+    match &err {
+        BabyProverError::Internal { block, .. } => assert_eq!(*block, Some(1)),
+        _ => panic!("unexpected error: {}", err),
+    }
+    let expected_msg = format!(
+        "expected one of {:?} operations, got {}",
+        vec![block_size],
+        block_size - 1
+    );
+    assert!(
+        err.to_string().contains(&expected_msg),
+        "error doesn't report the operations count: {}",
+        err
+    );
+    assert!(!pinned_blocks.proof_path(1).exists());
+}
+
+#[test]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+fn prover_sends_heartbeat_requests_and_exits_on_stop_signal() {
+    // Testing [black box] that:
+    // - BabyProver sends `working_on` requests (heartbeat) over api client
+    // - BabyProver stops running upon the stop request
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    // Heartbeats are sent only while the job is being proved.
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 1))])
+        .with_prover_data(1, testing::deposit_block_prover_data(block_size));
+
+    let config = testing::prover_config(vec![block_size]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    // Must receive heartbeat requests.
+    let deadline = time::Instant::now() + time::Duration::from_secs(10);
+    while client.working_on_calls().len() < 2 {
+        assert!(
+            time::Instant::now() < deadline,
+            "heartbeat request is not received"
+        );
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    assert!(client
+        .working_on_calls()
+        .iter()
+        .all(|call| call.job_id == 1));
+    handle
+        .stop_gracefully(time::Duration::from_secs(60 * 10))
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn prover_stops_heartbeats_for_abandoned_job() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    // Job is obtained, but there is no prover data for it, so the job is abandoned every round.
+    let client = MockApiClient::new().with_block_to_prove_responses(vec![Some((1, 1)); 100]);
+
+    let config = testing::prover_config(vec![block_size_chunks]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    thread::sleep(time::Duration::from_secs(3));
+    assert!(
+        !client.prover_data_requests().is_empty(),
+        "job was not obtained"
+    );
+    assert!(
+        client.working_on_calls().is_empty(),
+        "heartbeat was sent for the abandoned job"
+    );
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+}
+
+/// Runs the prover on the block with the corrupted public data commitment, so the proof
+/// created for it is invalid.
+fn prove_block_with_corrupted_commitment(
+    skip_self_verify: bool,
+) -> (
+    prover::ProverHandle,
+    MockApiClient,
+    mpsc::Receiver<BabyProverError>,
+) {
+    let block_size = testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let mut prover_data = testing::deposit_block_prover_data(block_size);
+    prover_data.public_data_commitment = Fr::from_str("1").unwrap();
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 1))])
+        .with_prover_data(1, prover_data);
+
+    let config = PlonkStepByStepProverConfig {
+        skip_self_verify,
+        ..testing::prover_config(vec![block_size])
+    };
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client.clone(),
+        time::Duration::from_secs(1),
+        None,
+    );
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+    (handle, client, exit_err_rx)
+}
+
+#[test]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+fn prover_fails_on_invalid_proof_with_self_verification() {
+    let (_handle, client, exit_err_rx) = prove_block_with_corrupted_commitment(false);
+
+    let err = exit_err_rx
+        .recv_timeout(time::Duration::from_secs(60 * 10))
+        .expect("prover didn't fail on invalid proof");
+    assert!(
+        matches!(err, BabyProverError::Internal { block: Some(1), .. }),
+        "unexpected error: {}",
+        err
+    );
+    assert!(
+        client.published_proofs().is_empty(),
+        "invalid proof was published"
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+fn prover_publishes_unverified_proof_without_self_verification() {
+    let (_handle, client, _exit_err_rx) = prove_block_with_corrupted_commitment(true);
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(60 * 10);
+    while client.published_proofs().is_empty() {
+        assert!(time::Instant::now() < deadline, "didn't receive proof");
+        thread::sleep(time::Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn prover_round_fails_with_retryable_error_on_malformed_prover_data() {
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let mut prover_data = testing::deposit_block_prover_data(block_size);
+    prover_data.validator_balances.clear();
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 1))])
+        .with_prover_data(1, prover_data);
+
+    let config = testing::prover_config(vec![block_size]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client,
+        time::Duration::from_secs(1),
+        None,
+    );
+    let (tx, _rx) = mpsc::channel();
+    let err = p
+        .next_round(tx, &ShutdownRequest::new())
+        .expect_err("round with malformed prover data succeeded");
+    assert!(err.is_retryable(), "unexpected error: {}", err);
+    assert!(
+        err.to_string().contains("validator_balances"),
+        "error doesn't name the malformed field: {}",
+        err
+    );
+}
+
+#[test]
+fn prover_metrics_are_served_on_prometheus_endpoint() {
+    let block_sizes = ConfigurationOptions::from_env().available_block_chunk_sizes;
+
+    // Round fails on the proof generation, so it can be run without the keys.
+    let size_without_keys = testing::deposit_block_size_without_keys(&block_sizes);
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, 1))])
+        .with_prover_data(1, testing::deposit_block_prover_data(size_without_keys));
+    let config = testing::prover_config(vec![size_without_keys]);
+    let metrics = Arc::new(ProverMetrics::new());
+    let metrics_addr = net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to pick metrics port");
+    prover::metrics::start_metrics_exporter(metrics.clone(), metrics_addr);
+
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client,
+        time::Duration::from_secs(1),
+        Some(metrics),
+    );
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let _handle = prover::start(p, exit_err_tx, Default::default());
+    exit_err_rx
+        .recv_timeout(time::Duration::from_secs(60))
+        .expect("prover didn't finish the round");
+
+    let scraped = reqwest::blocking::get(&format!("http://{}/metrics", metrics_addr))
+        .expect("failed to scrape metrics")
+        .text()
+        .expect("failed to read metrics");
+    for expected in &[
+        "prover_proofs_failed_total 1",
+        "prover_proofs_succeeded_total 0",
+        "prover_proofs_published_total 0",
+        "prover_api_errors_total 0",
+        "prover_busy 0",
+    ] {
+        assert!(
+            scraped.contains(expected),
+            "{} not in:\n{}",
+            expected,
+            scraped
+        );
+    }
+}
+
+/// Log writer shared with the test.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn json_logger_attaches_round_job_to_log_lines() {
+    let block_sizes = ConfigurationOptions::from_env().available_block_chunk_sizes;
+    let size_without_keys = testing::deposit_block_size_without_keys(&block_sizes);
+
+    // Logger is global, so lines of the other tests are told apart by the job ID.
+    let job_id = 4242;
+    let buffer = SharedBuffer::default();
+    let filter = env_logger::filter::Builder::new()
+        .filter_level(log::LevelFilter::Info)
+        .build();
+    JsonLogger::new("json_test_worker", filter, Box::new(buffer.clone()))
+        .init()
+        .expect("failed to set logger");
+
+    // Round fails on the proof generation, so it can be run without the keys.
+    let client = MockApiClient::new()
+        .with_block_to_prove_responses(vec![Some((1, job_id))])
+        .with_prover_data(1, testing::deposit_block_prover_data(size_without_keys));
+    let config = testing::prover_config(vec![size_without_keys]);
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        client,
+        time::Duration::from_secs(1),
+        None,
+    );
+    let (tx, _rx) = mpsc::channel();
+    let round = logging::in_round(|| p.next_round(tx, &ShutdownRequest::new()));
+    assert!(round.is_err(), "round with block without keys succeeded");
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("log line is not valid JSON"))
+        .filter(|line: &serde_json::Value| line["job_id"] == job_id)
+        .collect();
+    assert!(
+        !lines.is_empty(),
+        "no log lines of the round in:\n{}",
+        output
+    );
+    for line in &lines {
+        for key in &[
+            "timestamp",
+            "level",
+            "event",
+            "worker_name",
+            "block",
+            "job_id",
+        ] {
+            assert!(line.get(key).is_some(), "{} not in {}", key, line);
+        }
+        assert_eq!(line["block"], 1);
+        assert_eq!(line["worker_name"], "json_test_worker");
+    }
+    assert!(lines.iter().any(|line| line["event"]
+        .as_str()
+        .unwrap()
+        .starts_with("starting to compute proof for block 1")));
+}
+
+#[test]
+fn json_logger_attaches_job_to_log_lines_outside_of_round() {
+    let buffer = SharedBuffer::default();
+    let filter = env_logger::filter::Builder::new()
+        .filter_level(log::LevelFilter::Info)
+        .build();
+    // Logger is used directly instead of being installed, since the global one may be
+    // already set by the other test.
+    let logger = JsonLogger::new("json_test_worker", filter, Box::new(buffer.clone()));
+    let log_event = |event: &str| {
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .args(format_args!("{}", event))
+                .level(log::Level::Info)
+                .target("prover")
+                .build(),
+        )
+    };
+
+    logging::in_round(|| {
+        logging::set_round_job(1, 10);
+        logging::with_job(Some(2), 20, || log_event("heartbeat of job 20"));
+        // Context of the current round is restored afterwards.
+        log_event("round of job 10");
+    });
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("log line is not valid JSON"))
+        .collect();
+    assert_eq!(lines.len(), 2, "{}", output);
+    assert_eq!(lines[0]["event"], "heartbeat of job 20");
+    assert_eq!(lines[0]["block"], 2);
+    assert_eq!(lines[0]["job_id"], 20);
+    assert_eq!(lines[1]["block"], 1);
+    assert_eq!(lines[1]["job_id"], 10);
+}
@@ -1,222 +1,3051 @@
 // Built-in deps
+use std::collections::VecDeque;
 use std::fmt;
-use std::sync::{mpsc, Arc, Mutex};
-use std::{thread, time};
+use std::io::{Read, Write};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc, Arc, Mutex,
+};
+use std::{net, thread, time};
 // External deps
-use crypto_exports::pairing::ff::PrimeField;
-use num::BigUint;
+use arc_swap::ArcSwap;
+use flate2::{write::GzEncoder, Compression};
 // Workspace deps
-use circuit::{
-    circuit::FranklinCircuit,
-    witness::{deposit::DepositWitness, utils::WitnessBuilder, Witness},
-};
+use circuit::circuit::FranklinCircuit;
+use crypto_exports::pairing::ff::PrimeField;
 use models::{
-    circuit::{account::CircuitAccount, CircuitAccountTree},
-    config_options::ConfigurationOptions,
-    node::{
-        block::smallest_block_size_for_chunks, operations::DepositOp, Account, Address, Deposit,
-        Engine, Fr,
-    },
+    config_options::{ConfigurationOptions, ProverOptions},
+    node::{Engine, Fr},
     prover_utils::EncodedProofPlonk,
 };
 // Local deps
 use prover::{
-    plonk_step_by_step_prover::{PlonkStepByStepProver, PlonkStepByStepProverConfig},
-    prover_data::ProverData,
-    ProverImpl,
+    admin::{ConfigUpdate, CurrentConfig},
+    client::ClientError,
+    metrics::{PoolMetrics, ProverMetrics},
+    params::{self, LoadedParams},
+    plonk_step_by_step_prover::PlonkStepByStepProver,
+    pool::{JobScheduler, ProverPool, RoundRobin},
+    proof_cache::ProofCache,
+    proof_spool::{ProofSpool, SpoolingApiClient},
+    prover_data::{self, ProverData, ProverDataError},
+    testing, ApiClient, BabyProverError, BlockingApiClient, HeartbeatRequest, JobProgress,
+    ProverConfig, ProverImpl, ProvingStage, RetryPolicy, RoundOutcome, ShutdownBehavior,
+    ShutdownRequest,
 };
 
+#[test]
+fn prover_reports_error_when_heartbeat_routine_is_gone() {
+    let (round_started_tx, round_started_rx) = mpsc::channel();
+    // Heartbeat routine panics on the first heartbeat, dropping the receivers of the workers.
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(3),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        PanickingHeartbeatApiClient,
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    round_started_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("round wasn't started");
+    let err = exit_err_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("error wasn't sent to the exit channel");
+    match err {
+        BabyProverError::Internal { message, .. } => assert!(
+            message.contains("heartbeat routine"),
+            "unexpected error: {}",
+            message
+        ),
+        err => panic!("unexpected error: {}", err),
+    }
+    handle.join();
+}
+
 #[test]
 #[cfg_attr(not(feature = "keys-required"), ignore)]
-fn prover_sends_heartbeat_requests_and_exits_on_stop_signal() {
-    // Testing [black box] that:
-    // - BabyProver sends `working_on` requests (heartbeat) over api client
-    // - BabyProver stops running upon receiving data over stop channel
+fn prover_proves_consecutive_blocks_of_different_sizes() {
+    // Testing that the single prover is able to prove blocks of any supported size.
+    let block_sizes = ConfigurationOptions::from_env().available_block_chunk_sizes;
+    assert!(
+        block_sizes.len() >= 2,
+        "at least two block sizes are required for the test"
+    );
+    let (proof_tx, proof_rx) = mpsc::channel();
 
-    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let config = testing::prover_config(block_sizes[..2].to_vec());
+    let p = PlonkStepByStepProver::create_from_config(
+        config,
+        SequentialApiClient {
+            blocks: Mutex::new(
+                vec![
+                    (1, new_test_data_for_prover_with_size(block_sizes[1])),
+                    (2, new_test_data_for_prover_with_size(block_sizes[0])),
+                ]
+                .into(),
+            ),
+            publishes_tx: Mutex::new(proof_tx),
+        },
+        time::Duration::from_secs(1),
+        None,
+    );
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let _handle = prover::start(p, exit_err_tx, Default::default());
 
-    // Create a channel to notify on provers exit.
-    let (done_tx, _done_rx) = mpsc::channel();
-    // Create channel to notify test about heartbeat requests.
-    let (heartbeat_tx, heartbeat_rx) = mpsc::channel();
+    let timeout = time::Duration::from_secs(60 * 10);
+    for expected_block in 1..=2 {
+        let (block, _) = proof_rx
+            .recv_timeout(timeout)
+            .expect("didn't receive proof"); // if proof is received - then proof is verified
+        assert_eq!(block, expected_block);
+    }
+}
+
+#[test]
+fn admin_endpoint_updates_shared_prover_options() {
+    let prover_options = Arc::new(ArcSwap::from_pointee(ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(1000),
+        cycle_wait: time::Duration::from_millis(500),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(100),
+        retry_max_delay: time::Duration::from_millis(400),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    }));
+    let admin_addr = net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to pick admin port");
+    prover::admin::start_admin_server(Arc::clone(&prover_options), admin_addr);
+
+    let client = reqwest::blocking::Client::new();
+    let config_url = format!("http://{}/config", admin_addr);
+    // Server is started on a separate thread, so it may be not ready yet.
+    let mut current = None;
+    for _ in 0..50 {
+        if let Ok(response) = client.get(&config_url).send() {
+            current = Some(response.json::<CurrentConfig>().expect("invalid config"));
+            break;
+        }
+        thread::sleep(time::Duration::from_millis(100));
+    }
+    assert_eq!(
+        current.expect("admin server didn't start"),
+        CurrentConfig {
+            heartbeat_interval_ms: 1000,
+            cycle_wait_ms: 500,
+        }
+    );
+
+    // Absent fields are left unchanged.
+    let updated: CurrentConfig = client
+        .post(&config_url)
+        .json(&ConfigUpdate {
+            heartbeat_interval_ms: None,
+            cycle_wait_ms: Some(50),
+        })
+        .send()
+        .and_then(|response| response.json())
+        .expect("failed to update config");
+    assert_eq!(
+        updated,
+        CurrentConfig {
+            heartbeat_interval_ms: 1000,
+            cycle_wait_ms: 50,
+        }
+    );
+    assert_eq!(
+        prover_options.load().cycle_wait,
+        time::Duration::from_millis(50)
+    );
+    assert_eq!(
+        prover_options.load().heartbeat_interval,
+        time::Duration::from_millis(1000)
+    );
+
+    let response = client
+        .post(&config_url)
+        .body("not a config")
+        .header("content-type", "application/json")
+        .send()
+        .expect("failed to send request");
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn loaded_params_record_load_duration_and_metrics() {
+    let metrics = ProverMetrics::new();
+    let loaded = LoadedParams::load(
+        8,
+        Some(time::Duration::from_secs(0)),
+        Some(&metrics),
+        || {
+            thread::sleep(time::Duration::from_millis(10));
+            Ok::<_, ()>(vec![1u8; 1024 * 1024])
+        },
+    )
+    .expect("failed to load params");
+    assert!(loaded.load_duration >= time::Duration::from_millis(10));
+    assert_eq!(loaded.len(), 1024 * 1024);
+
+    let rendered = metrics.render();
+    assert!(
+        rendered.contains("prover_params_load_duration_seconds{block_size=\"8\"}"),
+        "load duration not in:\n{}",
+        rendered
+    );
+
+    // Failed loading is not recorded.
+    let failed = LoadedParams::<()>::load(16, None, Some(&metrics), || Err("no params"));
+    assert_eq!(failed.unwrap_err(), "no params");
+    assert!(!metrics.render().contains("block_size=\"16\""));
+}
 
-    // Run prover in a separate thread.
+#[test]
+fn params_digest_is_sha256_of_raw_bytes() {
+    let digest = params::sha256_of_params(&b"abc"[..]).unwrap();
+    assert_eq!(
+        hex::encode(digest),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    // Truncated params have the different digest.
+    assert_ne!(params::sha256_of_params(&b"ab"[..]).unwrap(), digest);
+
+    assert_eq!(
+        params::parse_params_digest(&format!("0x{}", hex::encode(digest))).unwrap(),
+        digest
+    );
+    assert!(params::parse_params_digest("abcd").is_err());
+    assert!(params::parse_params_digest("not hex").is_err());
+}
+
+#[test]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+fn params_digest_check_fails_on_mismatch() {
+    let block_sizes = ConfigurationOptions::from_env().available_block_chunk_sizes;
+    let digest = params::params_digest(&block_sizes).expect("failed to compute params digest");
+    params::check_params_digest(&block_sizes, &digest).expect("params digest doesn't match");
+
+    let mut other_digest = digest;
+    other_digest[0] ^= 1;
+    assert!(params::check_params_digest(&block_sizes, &other_digest).is_err());
+    // Params of the different block sizes are swapped.
+    let mut swapped_sizes = block_sizes.clone();
+    swapped_sizes.reverse();
+    if swapped_sizes != block_sizes {
+        assert!(params::check_params_digest(&swapped_sizes, &digest).is_err());
+    }
+}
+
+#[test]
+fn prover_registers_once_server_comes_up() {
+    // Reserve the free port and release it, so the server is unreachable until it's bound again.
+    let server_addr = net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to reserve port");
+    let client = prover::client::ApiClient::new(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+
+    // Server comes up after the first attempts fail, and responds with the prover ID.
     thread::spawn(move || {
-        // Create channel for proofs, not using in this test.
-        let (tx, _) = mpsc::channel();
-        let config = PlonkStepByStepProverConfig {
-            block_sizes: vec![block_size_chunks],
-            download_setup_from_network: false,
-        };
-        let p = PlonkStepByStepProver::create_from_config(
-            config,
-            MockApiClient {
-                block_to_prove: Mutex::new(Some((1, 1))),
-                heartbeats_tx: Arc::new(Mutex::new(heartbeat_tx)),
-                publishes_tx: Arc::new(Mutex::new(tx)),
-                prover_data_fn: || None,
-            },
-            time::Duration::from_millis(100),
-        );
-        let (tx, rx) = mpsc::channel();
-        let jh = thread::spawn(move || {
-            rx.recv().expect("on receive from exit error channel"); // mock receive exit error.
-        });
-        prover::start(p, tx, Default::default());
-        jh.join().expect("failed to join recv");
-        done_tx.send(()).expect("unexpected failure");
+        thread::sleep(time::Duration::from_millis(300));
+        let listener = net::TcpListener::bind(server_addr).expect("failed to start server");
+        let (mut stream, _) = listener.accept().expect("failed to accept connection");
+        let mut request = [0u8; 4096];
+        let _ = stream.read(&mut request);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n42")
+            .expect("failed to respond");
     });
 
-    let timeout = time::Duration::from_secs(10);
+    let prover_id = client
+        .register_with_retry(&[10], 10, time::Duration::from_millis(100))
+        .expect("prover didn't register");
+    assert_eq!(prover_id, 42);
+}
 
-    // Must receive heartbeat requests.
-    heartbeat_rx
-        .recv_timeout(timeout)
-        .expect("heartbeat request is not received");
-    heartbeat_rx
-        .recv_timeout(timeout)
-        .expect("heartbeat request is not received");
+/// Starts the HTTP server responding to the subsequent requests with the provided
+/// status lines (e.g. `200 OK`). Returns its address and the counter of the served requests.
+fn serve_scripted_responses(statuses: Vec<&'static str>) -> (net::SocketAddr, Arc<AtomicUsize>) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to start server");
+    let server_addr = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let served_requests = requests.clone();
+    thread::spawn(move || {
+        for status in statuses {
+            let (mut stream, _) = listener.accept().expect("failed to accept connection");
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            served_requests.fetch_add(1, Ordering::SeqCst);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            stream
+                .write_all(response.as_bytes())
+                .expect("failed to respond");
+        }
+    });
+    (server_addr, requests)
+}
+
+/// Returns the length of the HTTP request at the start of the `buffer`, if it's received completely.
+fn http_request_len(buffer: &[u8]) -> Option<usize> {
+    let headers_end = buffer.windows(4).position(|window| window == b"\r\n\r\n")? + 4;
+    let headers = String::from_utf8_lossy(&buffer[..headers_end]);
+    let body_len = headers
+        .lines()
+        .filter_map(|line| {
+            let mut header = line.splitn(2, ':');
+            let name = header.next()?;
+            let value = header.next()?;
+            if name.eq_ignore_ascii_case("content-length") {
+                value.trim().parse::<usize>().ok()
+            } else {
+                None
+            }
+        })
+        .next()
+        .unwrap_or(0);
+    Some(headers_end + body_len).filter(|&request_len| buffer.len() >= request_len)
+}
+
+/// Starts the HTTP server responding `200 OK` with the `body` to every request and keeping
+/// the connections alive. Returns its address and the amount of the accepted connections.
+fn serve_keep_alive(body: &'static str) -> (net::SocketAddr, Arc<AtomicUsize>) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to start server");
+    let server_addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted_connections = connections.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("failed to accept connection");
+            accepted_connections.fetch_add(1, Ordering::SeqCst);
+            thread::spawn(move || {
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    // Requests are read as a whole (headers and body) before responding.
+                    let request_len = loop {
+                        if let Some(request_len) = http_request_len(&buffer) {
+                            break request_len;
+                        }
+                        match stream.read(&mut chunk) {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+                        }
+                    };
+                    buffer.drain(..request_len);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    if stream.write_all(response.as_bytes()).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    (server_addr, connections)
+}
+
+/// Starts the HTTP server serving the JSON-encoded `data` in parts of `part_size` bytes
+/// like the prover server does. The connection is dropped in the middle of the first response
+/// for the part 1. Returns its address and the paths of the served requests.
+fn serve_prover_data_parts(
+    data: Vec<u8>,
+    part_size: usize,
+) -> (net::SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to start server");
+    let server_addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let served_requests = requests.clone();
+    thread::spawn(move || {
+        let parts: Vec<&[u8]> = data.chunks(part_size).collect();
+        let mut connection_dropped = false;
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("failed to accept connection");
+            let mut request = [0u8; 4096];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]);
+            let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+            served_requests.lock().unwrap().push(path.clone());
+
+            let part: usize = path.rsplit('/').next().unwrap().parse().unwrap();
+            let header = format!(
+                "HTTP/1.1 200 OK\r\n{}: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                prover::client::PROVER_DATA_PARTS_HEADER,
+                parts.len(),
+                parts[part].len()
+            );
+            stream
+                .write_all(header.as_bytes())
+                .expect("failed to respond");
+            if part == 1 && !connection_dropped {
+                connection_dropped = true;
+                let _ = stream.write_all(&parts[part][..parts[part].len() / 2]);
+                continue;
+            }
+            stream.write_all(parts[part]).expect("failed to respond");
+        }
+    });
+    (server_addr, requests)
 }
 
 #[test]
-#[cfg_attr(not(feature = "keys-required"), ignore)]
-fn prover_proves_a_block_and_publishes_result() {
-    // Testing [black box] the actual proof calculation by mocking genesis and +1 block.
-    let (proof_tx, proof_rx) = mpsc::channel();
+fn prover_data_download_resumes_after_dropped_connection() {
     let prover_data = new_test_data_for_prover();
-    let block_size_chunks = prover_data.operations.len();
+    let data = serde_json::to_vec(&Some(prover_data.clone())).unwrap();
+    let part_size = data.len() / 3 + 1;
+    let (server_addr, requests) = serve_prover_data_parts(data, part_size);
+    let client = prover::client::ApiClient::new(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+
+    let circuit = client
+        .prover_data(1)
+        .expect("prover data was not downloaded");
+    assert_eq!(circuit.operations.len(), prover_data.operations.len());
+    // Only the interrupted part is requested again.
+    assert_eq!(
+        *requests.lock().unwrap(),
+        vec![
+            "/prover_data/1/part/0",
+            "/prover_data/1/part/1",
+            "/prover_data/1/part/1",
+            "/prover_data/1/part/2",
+        ]
+    );
+}
 
-    // Run prover in separate thread.
+/// Starts the HTTP server serving the JSON-encoded `data` as the single part of the prover data,
+/// gzip-compressed if the request accepts it. Returns its address and the headers of the served requests.
+fn serve_compressed_prover_data(data: Vec<u8>) -> (net::SocketAddr, Arc<Mutex<Vec<String>>>) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to start server");
+    let server_addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let served_requests = requests.clone();
     thread::spawn(move || {
-        // Work heartbeat channel, not used in this test.
-        let (tx, _) = mpsc::channel();
-        let config = PlonkStepByStepProverConfig {
-            block_sizes: vec![block_size_chunks],
-            download_setup_from_network: false,
-        };
-        let p = PlonkStepByStepProver::create_from_config(
-            config,
-            MockApiClient {
-                block_to_prove: Mutex::new(Some((1, 1))),
-                heartbeats_tx: Arc::new(Mutex::new(tx)),
-                publishes_tx: Arc::new(Mutex::new(proof_tx)),
-                prover_data_fn: move || Some(prover_data.clone()),
-            },
-            time::Duration::from_secs(1),
-        );
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("failed to accept connection");
+            let mut request = [0u8; 4096];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]).to_lowercase();
+            served_requests.lock().unwrap().push(request.clone());
 
-        let (tx, rx) = mpsc::channel();
-        thread::spawn(move || {
-            rx.recv().unwrap();
-        });
-        prover::start(p, tx, Default::default());
+            let (encoding, body) = if request.contains("accept-encoding: gzip") {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&data).unwrap();
+                ("Content-Encoding: gzip\r\n", encoder.finish().unwrap())
+            } else {
+                ("", data.clone())
+            };
+            let header = format!(
+                "HTTP/1.1 200 OK\r\n{}: 1\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                prover::client::PROVER_DATA_PARTS_HEADER,
+                encoding,
+                body.len()
+            );
+            stream
+                .write_all(header.as_bytes())
+                .and_then(|_| stream.write_all(&body))
+                .expect("failed to respond");
+        }
     });
+    (server_addr, requests)
+}
 
-    let timeout = time::Duration::from_secs(60 * 10);
-    proof_rx
-        .recv_timeout(timeout)
-        .expect("didn't receive proof"); // if proof is received - then proof is verified
+#[test]
+fn compressed_prover_data_is_decompressed() {
+    let prover_data = new_test_data_for_prover();
+    let data = serde_json::to_vec(&Some(prover_data.clone())).unwrap();
+    let (server_addr, requests) = serve_compressed_prover_data(data);
+    let client = prover::client::ApiClient::new(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        time::Duration::from_secs(5),
+    )
+    .expect("failed to create client");
+
+    let circuit = client
+        .prover_data(1)
+        .expect("prover data was not downloaded");
+    assert!(requests.lock().unwrap()[0].contains("accept-encoding: gzip"));
+    // Circuit doesn't keep the new root, see `ProverData::from_circuit`.
+    let mut wanted = serde_json::to_value(&prover_data).unwrap();
+    let mut downloaded = serde_json::to_value(&ProverData::from_circuit(&circuit)).unwrap();
+    wanted.as_object_mut().unwrap().remove("new_root");
+    downloaded.as_object_mut().unwrap().remove("new_root");
+    assert_eq!(downloaded, wanted);
 }
 
-fn new_test_data_for_prover() -> ProverData {
-    let mut circuit_account_tree = CircuitAccountTree::new(models::params::account_tree_depth());
-    let fee_account_id = 0;
+/// Starts the HTTP server serving the JSON-encoded `data` as the single part of the prover data,
+/// writing the body in `chunks` pieces with the `delay` in between. Returns its address and the
+/// amount of the served requests.
+fn serve_prover_data_slowly(
+    data: Vec<u8>,
+    chunks: usize,
+    delay: time::Duration,
+) -> (net::SocketAddr, Arc<AtomicUsize>) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to start server");
+    let server_addr = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let served_requests = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.expect("failed to accept connection");
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            served_requests.fetch_add(1, Ordering::SeqCst);
+
+            let header = format!(
+                "HTTP/1.1 200 OK\r\n{}: 1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                prover::client::PROVER_DATA_PARTS_HEADER,
+                data.len()
+            );
+            if stream.write_all(header.as_bytes()).is_err() {
+                continue;
+            }
+            for chunk in data.chunks(data.len() / chunks + 1) {
+                thread::sleep(delay);
+                // Client drops the connection once the data exceeds its max size.
+                if stream
+                    .write_all(chunk)
+                    .and_then(|_| stream.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    });
+    (server_addr, requests)
+}
+
+#[test]
+fn prover_data_download_reports_progress() {
+    let prover_data = new_test_data_for_prover();
+    let data = serde_json::to_vec(&Some(prover_data.clone())).unwrap();
+    let data_len = data.len() as u64;
+    let (server_addr, _) = serve_prover_data_slowly(data, 4, time::Duration::from_millis(50));
+    let client = prover::client::ApiClient::new(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        time::Duration::from_secs(5),
+    )
+    .expect("failed to create client");
 
-    // Init the fee account.
-    let fee_account = Account::default_with_address(&Address::default());
-    circuit_account_tree.insert(fee_account_id, CircuitAccount::from(fee_account));
+    let progress = Mutex::new(Vec::new());
+    let circuit = client
+        .prover_data_with_progress(1, &|report| progress.lock().unwrap().push(*report))
+        .expect("prover data was not downloaded");
+    assert_eq!(circuit.operations.len(), prover_data.operations.len());
 
-    let mut witness_accum = WitnessBuilder::new(&mut circuit_account_tree, fee_account_id, 1);
+    let progress = progress.into_inner().unwrap();
+    assert!(progress.len() > 1, "{:?}", progress);
+    assert!(
+        progress
+            .windows(2)
+            .all(|reports| reports[0].received < reports[1].received),
+        "{:?}",
+        progress
+    );
+    assert!(progress
+        .iter()
+        .all(|report| report.block == 1 && report.parts == 1 && report.total == Some(data_len)));
+    assert_eq!(progress.last().unwrap().received, data_len);
+}
 
-    let empty_account_id = 1;
-    let empty_account_address = [7u8; 20].into();
-    let deposit_op = DepositOp {
-        priority_op: Deposit {
-            from: empty_account_address,
-            token: 0,
-            amount: BigUint::from(1u32),
-            to: empty_account_address,
-        },
-        account_id: empty_account_id,
-    };
+#[test]
+fn prover_data_download_fails_fast_on_oversized_response() {
+    let data = serde_json::to_vec(&Some(new_test_data_for_prover())).unwrap();
+    let max_size = data.len() / 2;
+    let (server_addr, requests) =
+        serve_prover_data_slowly(data, 4, time::Duration::from_millis(10));
+    let client =
+        prover::client::ApiClientBuilder::new(&format!("http://{}", server_addr), "test_worker")
+            .with_max_prover_data_size(max_size)
+            .build()
+            .expect("failed to create client");
 
-    let deposit_witness = DepositWitness::apply_tx(&mut witness_accum.account_tree, &deposit_op);
-    let deposit_operations = deposit_witness.calculate_operations(());
-    let pub_data_from_witness = deposit_witness.get_pubdata();
+    let err = client
+        .prover_data(1)
+        .err()
+        .expect("oversized prover data was downloaded");
+    assert!(prover::client::is_fatal(&err), "{}", err);
+    assert!(err.to_string().contains("max size"), "{}", err);
+    // Oversized response is not requested again.
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
 
-    witness_accum.add_operation_with_pubdata(deposit_operations, pub_data_from_witness);
-    witness_accum.extend_pubdata_with_noops(smallest_block_size_for_chunks(
-        DepositOp::CHUNKS,
-        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+#[test]
+fn publish_is_retried_on_transient_failures() {
+    let (server_addr, requests) =
+        serve_scripted_responses(vec!["500 Internal Server Error", "200 OK"]);
+    let client = prover::client::ApiClient::new(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+
+    client
+        .publish(1, EncodedProofPlonk::default())
+        .expect("proof was not published");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn publish_of_conflicting_proof_is_already_published_error() {
+    let (server_addr, requests) = serve_scripted_responses(vec!["409 Conflict", "200 OK"]);
+    let client = prover::client::ApiClient::new(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+
+    let err = client
+        .publish(1, EncodedProofPlonk::default())
+        .expect_err("conflicting proof was published");
+    // Conflict is not retried.
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    assert!(err
+        .downcast_ref::<prover::client::ProofConflict>()
+        .is_some());
+    let err = BabyProverError::from_publish(1, err);
+    assert!(matches!(
+        err,
+        BabyProverError::AlreadyPublished { block: 1 }
     ));
-    witness_accum.collect_fees(&Vec::new());
-    witness_accum.calculate_pubdata_commitment();
+    assert_eq!(err.block(), Some(1));
+    // Already proven block is not a failure of the job.
+    assert_eq!(err.job_id(), None);
+}
 
-    ProverData {
-        public_data_commitment: witness_accum.pubdata_commitment.unwrap(),
-        old_root: witness_accum.initial_root_hash,
-        initial_used_subtree_root: witness_accum.initial_used_subtree_root_hash,
-        new_root: witness_accum.root_after_fees.unwrap(),
-        validator_address: Fr::from_str(&witness_accum.fee_account_id.to_string())
-            .expect("failed to parse"),
-        operations: witness_accum.operations,
-        validator_balances: witness_accum.fee_account_balances.unwrap(),
-        validator_audit_path: witness_accum.fee_account_audit_path.unwrap(),
-        validator_account: witness_accum.fee_account_witness.unwrap(),
-    }
+#[test]
+fn prover_registration_fails_after_max_attempts() {
+    let server_addr = net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to reserve port");
+    let client = prover::client::ApiClient::new(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+
+    let err = client
+        .register_with_retry(&[10], 3, time::Duration::from_millis(10))
+        .expect_err("prover registered without server");
+    assert!(err.to_string().contains("after 3 attempts"), "{}", err);
 }
 
-struct MockApiClient<F> {
-    block_to_prove: Mutex<Option<(i64, i32)>>,
-    heartbeats_tx: Arc<Mutex<mpsc::Sender<()>>>,
-    publishes_tx: Arc<Mutex<mpsc::Sender<EncodedProofPlonk>>>,
-    prover_data_fn: F,
+#[test]
+fn block_to_prove_times_out_on_unresponsive_server() {
+    // Server accepts the connections, but never responds.
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to start server");
+    let server_addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut streams = Vec::new();
+        for stream in listener.incoming() {
+            streams.push(stream);
+        }
+    });
+    let client = prover::client::ApiClient::new_with_options(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        prover::client::ClientOptions {
+            connect_timeout: time::Duration::from_secs(1),
+            request_timeout: time::Duration::from_millis(200),
+            retries: 3,
+            backoff: time::Duration::from_millis(100),
+            ..Default::default()
+        },
+    )
+    .expect("failed to create client");
+
+    let started = time::Instant::now();
+    let err = client
+        .block_to_prove(10)
+        .expect_err("unresponsive server returned a job");
+    // Timed out request is not retried, since the server may still be processing it.
+    assert!(started.elapsed() < time::Duration::from_secs(5), "{}", err);
+    assert!(
+        err.to_string().contains("block to prove request failed"),
+        "{}",
+        err
+    );
 }
 
-impl<F> fmt::Debug for MockApiClient<F> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("MockApiClient").finish()
+#[test]
+fn block_to_prove_is_retried_on_connect_errors() {
+    // Reserve the free port and release it, so the server is unreachable until it's bound again.
+    let server_addr = net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to reserve port");
+    let client = prover::client::ApiClient::new_with_options(
+        &format!("http://{}", server_addr),
+        "test_worker",
+        prover::client::ClientOptions {
+            connect_timeout: time::Duration::from_secs(1),
+            request_timeout: time::Duration::from_secs(1),
+            retries: 5,
+            backoff: time::Duration::from_millis(100),
+            ..Default::default()
+        },
+    )
+    .expect("failed to create client");
+
+    thread::spawn(move || {
+        thread::sleep(time::Duration::from_millis(300));
+        let listener = net::TcpListener::bind(server_addr).expect("failed to start server");
+        let (mut stream, _) = listener.accept().expect("failed to accept connection");
+        let mut request = [0u8; 4096];
+        let _ = stream.read(&mut request);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\nnull")
+            .expect("failed to respond");
+    });
+
+    let job = client
+        .block_to_prove(10)
+        .expect("request was not retried until the server came up");
+    assert_eq!(job, None);
+}
+
+/// Creates the client of the server at `server_addr` which doesn't retry the failed requests.
+fn client_without_retries(server_addr: net::SocketAddr) -> prover::client::ApiClient {
+    prover::client::ApiClientBuilder::new(&format!("http://{}", server_addr), "test_worker")
+        .with_request_timeout(time::Duration::from_millis(200))
+        .with_retries(0, time::Duration::from_secs(1))
+        .build()
+        .expect("failed to create client")
+}
+
+#[test]
+fn client_error_transport_on_refused_connection() {
+    let server_addr = net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to reserve port");
+
+    let err = client_without_retries(server_addr)
+        .working_on(1, None)
+        .expect_err("request to the absent server succeeded");
+    match err.downcast_ref::<ClientError>() {
+        Some(err @ ClientError::Transport { .. }) => assert!(err.is_retryable()),
+        _ => panic!("unexpected error: {:?}", err),
     }
+    assert!(BabyProverError::from_api(err).is_retryable());
 }
 
-impl<F: Fn() -> Option<ProverData>> prover::ApiClient for MockApiClient<F> {
-    fn block_to_prove(&self, _block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
-        let block_to_prove = self.block_to_prove.lock().unwrap();
-        Ok(*block_to_prove)
+#[test]
+fn client_error_timeout_on_unresponsive_server() {
+    // Server accepts the connections, but never responds.
+    let listener = net::TcpListener::bind("127.0.0.1:0").expect("failed to start server");
+    let server_addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut streams = Vec::new();
+        for stream in listener.incoming() {
+            streams.push(stream);
+        }
+    });
+
+    let err = client_without_retries(server_addr)
+        .extend_lease(1, 60)
+        .expect_err("unresponsive server extended the lease");
+    match err.downcast_ref::<ClientError>() {
+        Some(err @ ClientError::Timeout { .. }) => assert!(err.is_retryable()),
+        _ => panic!("unexpected error: {:?}", err),
     }
+    assert!(BabyProverError::from_api(err).is_retryable());
+}
 
-    fn working_on(&self, job: i32) -> Result<(), failure::Error> {
-        let stored = self.block_to_prove.lock().unwrap();
-        if let Some((_, stored)) = *stored {
-            if stored != job {
-                return Err(failure::format_err!("unexpected job id"));
-            }
-            let _ = self.heartbeats_tx.lock().unwrap().send(());
+#[test]
+fn client_error_http_carries_response_status() {
+    let (server_addr, _) =
+        serve_scripted_responses(vec!["404 Not Found", "503 Service Unavailable"]);
+    let client = client_without_retries(server_addr);
+
+    let err = client
+        .working_on(1, None)
+        .expect_err("heartbeat of the unknown job was accepted");
+    match err.downcast_ref::<ClientError>() {
+        Some(err @ ClientError::Http { .. }) => {
+            assert!(!err.is_retryable());
+            assert!(matches!(err, ClientError::Http { status, .. } if status.as_u16() == 404));
         }
-        Ok(())
+        _ => panic!("unexpected error: {:?}", err),
     }
+    assert!(!BabyProverError::from_api(err).is_retryable());
 
-    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'_, Engine>, failure::Error> {
-        let block_to_prove = self.block_to_prove.lock().unwrap();
-        if (*block_to_prove).is_some() {
-            let v = (self.prover_data_fn)();
-            if let Some(pd) = v {
-                return Ok(pd.into_circuit(block));
-            }
+    // Unavailable server may recover.
+    let err = client
+        .working_on(1, None)
+        .expect_err("heartbeat was accepted by the unavailable server");
+    match err.downcast_ref::<ClientError>() {
+        Some(err @ ClientError::Http { .. }) => assert!(err.is_retryable()),
+        _ => panic!("unexpected error: {:?}", err),
+    }
+    assert!(BabyProverError::from_api(err).is_retryable());
+}
+
+#[test]
+fn client_error_decode_on_malformed_response() {
+    // Empty body is not a valid JSON.
+    let (server_addr, _) = serve_scripted_responses(vec!["200 OK"]);
+
+    let err = client_without_retries(server_addr)
+        .block_to_prove(10)
+        .expect_err("malformed response was parsed");
+    match err.downcast_ref::<ClientError>() {
+        Some(err @ ClientError::Decode { .. }) => assert!(!err.is_retryable()),
+        _ => panic!("unexpected error: {:?}", err),
+    }
+    assert!(!BabyProverError::from_api(err).is_retryable());
+}
+
+#[test]
+fn client_error_auth_is_not_retried() {
+    let (server_addr, requests) =
+        serve_scripted_responses(vec!["401 Unauthorized", "401 Unauthorized"]);
+    let client = client_without_retries(server_addr);
+
+    let err = client
+        .register_with_retry(&[10], 3, time::Duration::from_millis(10))
+        .expect_err("prover registered with the rejected secret");
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    match err.downcast_ref::<ClientError>() {
+        Some(err @ ClientError::Auth { .. }) => assert!(!err.is_retryable()),
+        _ => panic!("unexpected error: {:?}", err),
+    }
+    assert!(!BabyProverError::from_api(err).is_retryable());
+}
+
+fn client_config_error(base_url: &str, worker: &str) -> String {
+    match prover::client::ApiClient::new(base_url, worker, time::Duration::from_secs(1)) {
+        Err(ClientError::Config { message }) => message,
+        Err(err) => panic!("unexpected error: {:?}", err),
+        Ok(_) => panic!(
+            "client is created for {} with worker {:?}",
+            base_url, worker
+        ),
+    }
+}
+
+#[test]
+fn client_rejects_bad_server_url() {
+    assert!(client_config_error("not a url", "test_worker").contains("invalid prover server URL"));
+    assert!(client_config_error("ftp://127.0.0.1:8088", "test_worker").contains("scheme"));
+    assert!(prover::client::ApiClient::new(
+        "https://127.0.0.1:8088",
+        "test_worker",
+        time::Duration::from_secs(1)
+    )
+    .is_ok());
+}
+
+#[test]
+fn client_rejects_empty_worker_name() {
+    assert!(client_config_error("http://127.0.0.1:8088", "").contains("empty"));
+}
+
+#[test]
+fn client_builder_validates_worker_name() {
+    let err = prover::client::ApiClientBuilder::new("http://127.0.0.1:8088", "")
+        .with_max_idle_connections(1)
+        .build()
+        .expect_err("client with empty worker name is built");
+    assert!(matches!(err, ClientError::Config { .. }));
+}
+
+#[test]
+fn client_rejects_invalid_worker_name() {
+    assert!(client_config_error("http://127.0.0.1:8088", "worker name").contains("' '"));
+    assert!(client_config_error("http://127.0.0.1:8088", "worker/1").contains("'/'"));
+    let too_long = "w".repeat(prover::client::MAX_WORKER_NAME_LEN + 1);
+    assert!(client_config_error("http://127.0.0.1:8088", &too_long).contains("longer"));
+    assert!(prover::client::ApiClient::new(
+        "http://127.0.0.1:8088",
+        "prover-1.eu_west:2",
+        time::Duration::from_secs(1)
+    )
+    .is_ok());
+}
+
+#[test]
+fn client_reuses_connection_across_requests() {
+    let (server_addr, connections) = serve_keep_alive("null");
+    let client =
+        prover::client::ApiClientBuilder::new(&format!("http://{}", server_addr), "test_worker")
+            .with_request_timeout(time::Duration::from_secs(1))
+            .with_tcp_keepalive(Some(time::Duration::from_secs(60)))
+            .with_max_idle_connections(1)
+            .with_none_cache_ttl(time::Duration::from_secs(0))
+            .build()
+            .expect("failed to create client");
+
+    // Requests of the consecutive rounds go over the same connection.
+    for job_id in 0..5 {
+        assert_eq!(client.block_to_prove(10).expect("failed to get job"), None);
+        client
+            .working_on(job_id, None)
+            .expect("failed to send heartbeat");
+        client
+            .extend_lease(job_id, 60)
+            .expect("failed to extend lease");
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn server_health_check_fails_fast_on_disconnected_database() {
+    let (healthy_addr, _) =
+        serve_keep_alive(r#"{"version":"0.0.1","db_connected":true,"pending_jobs":3}"#);
+    let health = prover::check_server_health(&client_without_retries(healthy_addr))
+        .expect("healthy server is reported as degraded");
+    assert_eq!(
+        health,
+        prover::client::ServerHealth {
+            version: "0.0.1".to_string(),
+            db_connected: true,
+            pending_jobs: 3,
         }
-        Err(failure::format_err!("mock not configured"))
+    );
+
+    let (degraded_addr, _) =
+        serve_keep_alive(r#"{"version":"0.0.1","db_connected":false,"pending_jobs":0}"#);
+    let err = prover::check_server_health(&client_without_retries(degraded_addr))
+        .expect_err("degraded server is reported as healthy");
+    assert!(err.to_string().contains("not connected to the database"));
+    assert!(!err.is_retryable());
+
+    // Clients without the health check can't be used with the check enabled.
+    let client = IdleApiClient {
+        jobs: Mutex::new(VecDeque::new()),
+        requested_at: Arc::new(Mutex::new(Vec::new())),
+    };
+    assert!(prover::check_server_health(&client).is_err());
+}
+
+#[test]
+fn verification_keys_check_refuses_mismatched_keys() {
+    let vk_hashes = vec![prover::client::VkHash {
+        block_size: 10,
+        hash: "ab".repeat(32),
+    }];
+    let (matching_addr, _) = serve_keep_alive(r#"{"mismatched_block_sizes":[]}"#);
+    prover::check_verification_keys(&client_without_retries(matching_addr), &vk_hashes)
+        .expect("matching verification keys are refused");
+
+    let (mismatching_addr, _) = serve_keep_alive(r#"{"mismatched_block_sizes":[10]}"#);
+    let err =
+        prover::check_verification_keys(&client_without_retries(mismatching_addr), &vk_hashes)
+            .expect_err("mismatching verification keys are accepted");
+    assert!(matches!(err, BabyProverError::Internal { .. }), "{}", err);
+    assert!(err.to_string().contains("[10]"), "{}", err);
+
+    // Clients without the check can't be used to start the prover.
+    let client = IdleApiClient {
+        jobs: Mutex::new(VecDeque::new()),
+        requested_at: Arc::new(Mutex::new(Vec::new())),
+    };
+    assert!(prover::check_verification_keys(&client, &vk_hashes).is_err());
+}
+
+/// Middleware recording the requests of the client and their response statuses.
+#[derive(Debug, Default, Clone)]
+struct RecordingMiddleware(Arc<Mutex<Vec<String>>>);
+
+impl prover::client::ApiClientMiddleware for RecordingMiddleware {
+    fn on_request(&self, request: &'static str) {
+        self.0.lock().unwrap().push(format!("request {}", request));
     }
 
-    fn publish(&self, _block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error> {
-        // No more blocks to prove. We're only testing single rounds.
-        let mut block_to_prove = self.block_to_prove.lock().unwrap();
-        *block_to_prove = None;
+    fn on_response(
+        &self,
+        request: &'static str,
+        _duration: time::Duration,
+        status: Option<reqwest::StatusCode>,
+    ) {
+        let status = status.map_or("none".to_string(), |status| status.as_u16().to_string());
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("response {} {}", request, status));
+    }
+}
 
-        let _ = self.publishes_tx.lock().unwrap().send(p);
-        Ok(())
+#[test]
+fn client_middleware_sees_requests_of_round() {
+    let (server_addr, _) = serve_keep_alive("null");
+    let middleware = RecordingMiddleware::default();
+    let client =
+        prover::client::ApiClientBuilder::new(&format!("http://{}", server_addr), "test_worker")
+            .with_middleware(middleware.clone())
+            .build()
+            .expect("failed to create client");
+
+    // Round taking the job, sending the heartbeat and publishing the proof.
+    assert_eq!(client.block_to_prove(10).expect("failed to get job"), None);
+    client
+        .working_on(1, None)
+        .expect("failed to send heartbeat");
+    client
+        .publish(1, EncodedProofPlonk::default())
+        .expect("failed to publish proof");
+    assert_eq!(
+        *middleware.0.lock().unwrap(),
+        vec![
+            "request block to prove",
+            "response block to prove 200",
+            "request working on",
+            "response working on 200",
+            "request publish",
+            "response publish 200",
+        ]
+    );
+
+    // Failed request is reported without the status.
+    let refused_addr = net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("failed to reserve port");
+    let middleware = RecordingMiddleware::default();
+    let client =
+        prover::client::ApiClientBuilder::new(&format!("http://{}", refused_addr), "test_worker")
+            .with_retries(0, time::Duration::from_millis(10))
+            .with_middleware(middleware.clone())
+            .build()
+            .expect("failed to create client");
+    client
+        .working_on(1, None)
+        .expect_err("heartbeat is sent to the stopped server");
+    assert_eq!(
+        *middleware.0.lock().unwrap(),
+        vec!["request working on", "response working on none"]
+    );
+}
+
+#[test]
+fn client_reuses_empty_block_to_prove_response() {
+    let (server_addr, _) = serve_keep_alive("null");
+    let middleware = RecordingMiddleware::default();
+    let client =
+        prover::client::ApiClientBuilder::new(&format!("http://{}", server_addr), "test_worker")
+            .with_none_cache_ttl(time::Duration::from_millis(300))
+            .with_middleware(middleware.clone())
+            .build()
+            .expect("failed to create client");
+    let requests = || {
+        middleware
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.starts_with("request"))
+            .count()
+    };
+
+    // Server is asked once, the clones share the cached response.
+    for _ in 0..3 {
+        assert_eq!(client.block_to_prove(10).expect("failed to get job"), None);
     }
+    assert_eq!(client.clone().block_to_prove(10).unwrap(), None);
+    assert_eq!(requests(), 1);
 
-    fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+    // Jobs of the other block size are asked for separately.
+    assert_eq!(client.block_to_prove(20).expect("failed to get job"), None);
+    assert_eq!(requests(), 2);
+
+    thread::sleep(time::Duration::from_millis(400));
+    assert_eq!(client.block_to_prove(10).expect("failed to get job"), None);
+    assert_eq!(requests(), 3);
+
+    // Zero TTL disables the caching.
+    let middleware = RecordingMiddleware::default();
+    let client =
+        prover::client::ApiClientBuilder::new(&format!("http://{}", server_addr), "test_worker")
+            .with_none_cache_ttl(time::Duration::from_secs(0))
+            .with_middleware(middleware.clone())
+            .build()
+            .expect("failed to create client");
+    for _ in 0..3 {
+        assert_eq!(client.block_to_prove(10).expect("failed to get job"), None);
+    }
+    assert_eq!(middleware.0.lock().unwrap().len(), 6);
+}
+
+#[test]
+fn prover_finishes_in_flight_proof_on_graceful_stop() {
+    // Testing that the stop request received in the middle of the round doesn't
+    // interrupt it, and the proof is still published.
+    let (heartbeat_tx, _heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(2),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 1)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: Default::default(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start(
+        p,
+        exit_err_tx,
+        ShutdownRequest::with_behavior(ShutdownBehavior::FinishCurrentProof),
+    );
+
+    round_started_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("prover didn't start the round");
+    handle
+        .stop_gracefully(time::Duration::from_secs(30))
+        .expect("prover didn't stop in time");
+
+    proof_rx
+        .try_recv()
+        .expect("in-flight proof was not published");
+}
+
+#[test]
+fn prover_abandons_in_flight_proof_on_immediate_stop() {
+    // Testing that the stop request with `AbortImmediate` behavior doesn't wait
+    // for the current round to be finished.
+    let (heartbeat_tx, _heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(20),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 1)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: Default::default(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start(
+        p,
+        exit_err_tx,
+        ShutdownRequest::with_behavior(ShutdownBehavior::AbortImmediate),
+    );
+
+    round_started_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("prover didn't start the round");
+    handle
+        .stop_gracefully(time::Duration::from_secs(5))
+        .expect("prover didn't stop in time");
+
+    assert!(
+        proof_rx.try_recv().is_err(),
+        "abandoned proof must not be published"
+    );
+}
+
+#[test]
+fn prover_cancels_round_on_immediate_stop() {
+    // Testing that the proof computation is interrupted by the immediate stop request
+    // instead of burning the whole proving time.
+    let (heartbeat_tx, _heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, round_started_rx) = mpsc::channel();
+
+    // "Proving" outlasts the test, so the round can only end by being cancelled.
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(600),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 1)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: Default::default(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let shutdown_request = ShutdownRequest::with_behavior(ShutdownBehavior::AbortImmediate);
+
+    let round_shutdown_request = shutdown_request.clone();
+    let (round_result_tx, round_result_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (start_heartbeats_tx, _start_heartbeats_rx) = mpsc::channel();
+        let result = p.next_round(start_heartbeats_tx, &round_shutdown_request);
+        let _ = round_result_tx.send(result);
+    });
+
+    round_started_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("prover didn't start the round");
+    shutdown_request.set();
+
+    let result = round_result_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("round was not cancelled");
+    assert!(
+        matches!(result, Err(BabyProverError::Stop)),
+        "unexpected round result: {:?}",
+        result
+    );
+    assert!(
+        proof_rx.try_recv().is_err(),
+        "cancelled proof must not be published"
+    );
+}
+
+#[test]
+fn prover_treats_zero_job_id_as_a_real_job() {
+    // Job with ID 0 is a legitimate job (e.g. the first job in a fresh database),
+    // so heartbeats must be sent for it and the proof must be published.
+    let (heartbeat_tx, heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, _round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(2),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 0)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: Default::default(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    let timeout = time::Duration::from_secs(10);
+    heartbeat_rx
+        .recv_timeout(timeout)
+        .expect("heartbeat for the job with ID 0 is not sent");
+    proof_rx
+        .recv_timeout(timeout)
+        .expect("proof for the job with ID 0 is not published");
+    handle
+        .stop_gracefully(time::Duration::from_secs(30))
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn prover_workers_prove_jobs_concurrently() {
+    // Testing that with multiple workers the jobs are proved at the same time,
+    // and heartbeats are sent for every job being proved.
+    let (heartbeat_tx, heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(3),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 10), (2, 20)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: Default::default(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(0),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(100),
+        retry_max_delay: time::Duration::from_millis(400),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 2,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start_with_options(p, exit_err_tx, Default::default(), prover_options);
+
+    let timeout = time::Duration::from_secs(10);
+    // Both jobs are taken before the first one is proved.
+    for _ in 0..2 {
+        round_started_rx
+            .recv_timeout(timeout)
+            .expect("round didn't start");
+    }
+    assert!(
+        proof_rx.try_recv().is_err(),
+        "jobs were not proved concurrently"
+    );
+    let mut proved_blocks = vec![
+        proof_rx
+            .recv_timeout(timeout)
+            .expect("didn't receive proof"),
+        proof_rx
+            .recv_timeout(timeout)
+            .expect("didn't receive proof"),
+    ];
+    proved_blocks.sort();
+    assert_eq!(proved_blocks, vec![1, 2]);
+
+    let heartbeat_jobs: Vec<_> = heartbeat_rx.try_iter().map(|(job_id, _)| job_id).collect();
+    for job_id in &[10, 20] {
+        assert!(
+            heartbeat_jobs.contains(job_id),
+            "no heartbeats were sent for job {}",
+            job_id
+        );
+    }
+
+    handle
+        .stop_gracefully(timeout)
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn cancelled_computation_is_abandoned_until_it_finishes() {
+    let shutdown_request = ShutdownRequest::with_behavior(ShutdownBehavior::AbortImmediate);
+    let (finish_tx, finish_rx) = mpsc::channel::<()>();
+
+    // Shutdown is requested while the computation is running.
+    let computation_request = shutdown_request.clone();
+    let result = prover::run_cancellable(&shutdown_request, move || {
+        computation_request.set();
+        let _ = finish_rx.recv();
+    });
+    assert!(matches!(result, Err(BabyProverError::Stop)));
+    assert_eq!(shutdown_request.abandoned_computations(), 1);
+
+    finish_tx.send(()).unwrap();
+    let deadline = time::Instant::now() + time::Duration::from_secs(10);
+    while shutdown_request.abandoned_computations() > 0 {
+        assert!(
+            time::Instant::now() < deadline,
+            "finished computation is still counted as abandoned"
+        );
+        thread::sleep(time::Duration::from_millis(10));
+    }
+
+    // Computation isn't started at all once the shutdown is requested.
+    let result = prover::run_cancellable(&shutdown_request, || {
+        panic!("computation started after the shutdown request")
+    });
+    assert!(matches!(result, Err(BabyProverError::Stop)));
+}
+
+#[test]
+fn run_with_deadline_stops_waiting_for_slow_computation() {
+    let shutdown_request = ShutdownRequest::new();
+    let started_at = time::Instant::now();
+    let result = prover::run_with_deadline(
+        &shutdown_request,
+        Some(time::Duration::from_millis(200)),
+        || thread::sleep(time::Duration::from_secs(10)),
+    )
+    .expect("computation failed");
+    assert!(result.is_none());
+    assert!(started_at.elapsed() < time::Duration::from_secs(5));
+    // Computation keeps running in the background.
+    assert_eq!(shutdown_request.abandoned_computations(), 1);
+
+    let result = prover::run_with_deadline(
+        &shutdown_request,
+        Some(time::Duration::from_secs(10)),
+        || 42,
+    )
+    .expect("computation failed");
+    assert_eq!(result, Some(42));
+}
+
+#[test]
+fn prover_abandons_job_exceeding_max_proof_time_and_keeps_running() {
+    let (heartbeat_tx, heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, round_started_rx) = mpsc::channel();
+
+    // "Proving" takes longer than allowed for every job.
+    let proving_time = time::Duration::from_secs(2);
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time,
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: Some(time::Duration::from_millis(500)),
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 10), (2, 20)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: Default::default(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    let timeout = time::Duration::from_secs(10);
+    round_started_rx
+        .recv_timeout(timeout)
+        .expect("first round didn't start");
+    let first_started_at = time::Instant::now();
+    // Loop survives the abandoned job and takes the next one, but only once the abandoned
+    // computation is finished.
+    round_started_rx
+        .recv_timeout(timeout)
+        .expect("prover didn't take the next job");
+    // Half of the proving time is far beyond `max_proving_time`, and leaves the slack for the delay
+    // of the first round notification.
+    assert!(
+        first_started_at.elapsed() >= proving_time / 2,
+        "next job was taken while the abandoned computation was running"
+    );
+    // Heartbeats for the abandoned job are stopped.
+    thread::sleep(time::Duration::from_millis(300));
+    let _ = heartbeat_rx.try_iter().count();
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(
+        heartbeat_rx.try_iter().all(|(job_id, _)| job_id != 10),
+        "heartbeats are still sent for the abandoned job"
+    );
+
+    assert!(proof_rx.try_recv().is_err(), "abandoned job was published");
+    assert!(exit_err_rx.try_recv().is_err(), "prover exited with error");
+    handle
+        .stop_gracefully(timeout)
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn prover_extends_lease_of_job_exceeding_prover_timeout() {
+    let (heartbeat_tx, _heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, _round_started_rx) = mpsc::channel();
+    let extended_leases = Arc::new(Mutex::new(Vec::new()));
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(5),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 10)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: extended_leases.clone(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(100),
+        gone_timeout: time::Duration::from_secs(1),
+        retry_initial_delay: time::Duration::from_millis(100),
+        retry_max_delay: time::Duration::from_millis(400),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start_with_options(p, exit_err_tx, Default::default(), prover_options);
+
+    proof_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("didn't receive proof");
+    // Proving takes 5 timeouts, so the lease must be extended at least twice.
+    let extended_leases = extended_leases.lock().unwrap().clone();
+    assert!(
+        extended_leases.len() >= 2,
+        "lease was not extended: {:?}",
+        extended_leases
+    );
+    assert!(extended_leases.iter().all(|lease| *lease == (10, 1)));
+
+    assert!(exit_err_rx.try_recv().is_err(), "prover exited with error");
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn prover_reports_job_stages_with_heartbeats() {
+    let (heartbeat_tx, heartbeat_rx) = mpsc::channel();
+    let (proof_tx, _proof_rx) = mpsc::channel();
+    let (round_started_tx, _round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(2),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        JobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 10)].into()),
+            heartbeats_tx: Mutex::new(heartbeat_tx),
+            publishes_tx: Mutex::new(proof_tx),
+            extended_leases: Default::default(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start(p, exit_err_tx, Default::default());
+
+    let timeout = time::Duration::from_secs(10);
+    let (job_id, progress) = loop {
+        let (job_id, progress) = heartbeat_rx
+            .recv_timeout(timeout)
+            .expect("heartbeat request is not received");
+        if progress.is_some() {
+            break (job_id, progress.unwrap());
+        }
+    };
+    assert_eq!(job_id, 10);
+    assert_eq!(progress.stage, ProvingStage::ProofGeneration);
+
+    handle
+        .stop_gracefully(timeout)
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn spooled_proof_survives_restart_and_gets_published() {
+    let spool_dir = std::env::temp_dir().join(format!("prover_spool_test_{}", std::process::id()));
+    let spool = ProofSpool::new(&spool_dir).expect("failed to create spool");
+    let run_prover = |publish_succeeds: bool, jobs: Vec<(i64, i32)>| {
+        let (publishes_tx, publishes_rx) = mpsc::channel();
+        let (round_started_tx, _round_started_rx) = mpsc::channel();
+        let api_client = SpoolingApiClient::new(
+            UnreliablePublishApiClient {
+                jobs: Mutex::new(jobs.into_iter().collect()),
+                publish_succeeds,
+                publishes_tx: Mutex::new(publishes_tx),
+            },
+            spool.clone(),
+        );
+        let p = SlowProver::create_from_config(
+            SlowProverConfig {
+                proving_time: time::Duration::from_millis(0),
+                round_started_tx: Some(round_started_tx),
+                max_proving_time: None,
+                retry_policy: test_retry_policy(),
+            },
+            api_client,
+            time::Duration::from_millis(100),
+            None,
+        );
+        let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+        let handle = prover::start(p, exit_err_tx, Default::default());
+        (handle, publishes_rx)
+    };
+
+    // Server fails to accept the proof, so it's kept in the spool.
+    let (handle, publishes_rx) = run_prover(false, vec![(1, 1)]);
+    let (block, accepted) = publishes_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("proof wasn't published");
+    assert_eq!((block, accepted), (1, false));
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+    let pending = spool.pending().expect("failed to read spool");
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].block, 1);
+
+    // Restarted prover publishes the spooled proof even without getting any job.
+    let (handle, publishes_rx) = run_prover(true, Vec::new());
+    let (block, accepted) = publishes_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("spooled proof wasn't published");
+    assert_eq!((block, accepted), (1, true));
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+    assert!(spool.pending().expect("failed to read spool").is_empty());
+
+    std::fs::remove_dir_all(spool_dir).expect("failed to remove spool");
+}
+
+#[test]
+fn proof_cache_evicts_removed_and_expired_proofs() {
+    let cache = ProofCache::new(time::Duration::from_millis(200));
+    assert!(cache.get(1).is_none());
+
+    cache.insert(1, EncodedProofPlonk::default());
+    cache.insert(2, EncodedProofPlonk::default());
+    assert_eq!(cache.get(1), Some(EncodedProofPlonk::default()));
+    assert_eq!(cache.len(), 2);
+
+    cache.remove(1);
+    assert!(cache.get(1).is_none());
+    assert_eq!(cache.len(), 1);
+
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(cache.get(2).is_none());
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn prover_data_validation_checks_operations_count() {
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let mut prover_data = new_test_data_for_prover_with_size(block_size);
+    assert_eq!(prover_data.validate(block_size), Ok(()));
+
+    prover_data.operations.pop();
+    assert_eq!(
+        prover_data.validate(block_size),
+        Err(ProverDataError::OperationsCount {
+            expected: block_size,
+            actual: block_size - 1,
+        })
+    );
+}
+
+#[test]
+fn prover_data_validation_checks_validator_witness() {
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_data = new_test_data_for_prover_with_size(block_size);
+
+    let mut malformed = prover_data.clone();
+    malformed.validator_balances.pop();
+    assert_eq!(
+        malformed.validate(block_size),
+        Err(ProverDataError::ValidatorBalancesLength {
+            expected: models::params::total_tokens(),
+            actual: models::params::total_tokens() - 1,
+        })
+    );
+
+    let mut malformed = prover_data;
+    malformed.validator_audit_path.push(None);
+    let err = malformed.validate(block_size).unwrap_err();
+    assert_eq!(
+        err,
+        ProverDataError::ValidatorAuditPathLength {
+            expected: models::params::account_tree_depth(),
+            actual: models::params::account_tree_depth() + 1,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "validator_audit_path: expected length {}, got {}",
+            models::params::account_tree_depth(),
+            models::params::account_tree_depth() + 1
+        )
+    );
+}
+
+#[test]
+fn prover_data_is_saved_to_and_loaded_from_file() {
+    let prover_data = new_test_data_for_prover();
+    let path = std::env::temp_dir().join(format!("prover_data_test_{}.json", std::process::id()));
+
+    prover_data
+        .save_to_file(&path)
+        .expect("failed to save prover data");
+    let loaded = ProverData::load_from_file(&path).expect("failed to load prover data");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        serde_json::to_value(&loaded).unwrap(),
+        serde_json::to_value(&prover_data).unwrap()
+    );
+
+    // Prover dumps the data converted to the circuit, which doesn't keep the new root.
+    let from_circuit = ProverData::from_circuit(&prover_data.clone().into_circuit(1));
+    let without_new_root = |prover_data: &ProverData| {
+        let mut value = serde_json::to_value(prover_data).unwrap();
+        value.as_object_mut().unwrap().remove("new_root");
+        value
+    };
+    assert_eq!(
+        without_new_root(&from_circuit),
+        without_new_root(&prover_data)
+    );
+
+    assert!(ProverData::load_from_file(&path).is_err());
+}
+
+#[test]
+fn prover_data_binary_encoding_round_trips() {
+    let random_fr = || Fr::from_str(&rand::random::<u64>().to_string()).unwrap();
+    for _ in 0..10 {
+        let mut prover_data = new_test_data_for_prover();
+        prover_data.public_data_commitment = random_fr();
+        prover_data.old_root = random_fr();
+        prover_data.validator_balances[0] = Some(random_fr());
+        prover_data.validator_audit_path[1] = None;
+        prover_data.operations[0].lhs.witness.account_path[0] = Some(random_fr());
+        prover_data.operations[0].args.a = Some(random_fr());
+
+        let binary = prover_data::encode_binary(Some(&prover_data)).unwrap();
+        let json = serde_json::to_vec(&Some(&prover_data)).unwrap();
+        assert!(
+            binary.len() * 3 < json.len() * 2,
+            "binary encoding takes {} bytes, JSON {}",
+            binary.len(),
+            json.len()
+        );
+        for encoded in &[binary, json] {
+            let decoded = prover_data::decode(encoded)
+                .unwrap()
+                .expect("prover data is lost");
+            assert_eq!(
+                serde_json::to_value(&decoded).unwrap(),
+                serde_json::to_value(&prover_data).unwrap()
+            );
+        }
+    }
+
+    let absent = prover_data::encode_binary(None).unwrap();
+    assert!(prover_data::decode(&absent).unwrap().is_none());
+    assert!(prover_data::decode(b"null").unwrap().is_none());
+
+    let mut future_version = absent;
+    future_version[0] = prover_data::BINARY_ENCODING_VERSION + 1;
+    assert!(prover_data::decode(&future_version).is_err());
+}
+
+#[test]
+fn prover_data_validation_checks_operation_audit_paths() {
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_data = new_test_data_for_prover_with_size(block_size);
+
+    let mut malformed = prover_data.clone();
+    malformed.operations[1].rhs.witness.account_path.pop();
+    assert_eq!(
+        malformed.validate(block_size),
+        Err(ProverDataError::AccountPathLength {
+            operation: 1,
+            branch: "rhs",
+            expected: models::params::account_tree_depth(),
+            actual: models::params::account_tree_depth() - 1,
+        })
+    );
+
+    let mut malformed = prover_data;
+    malformed.operations[0]
+        .lhs
+        .witness
+        .balance_subtree_path
+        .clear();
+    assert_eq!(
+        malformed.validate(block_size),
+        Err(ProverDataError::BalanceSubtreePathLength {
+            operation: 0,
+            branch: "lhs",
+            expected: models::params::balance_tree_depth(),
+            actual: 0,
+        })
+    );
+}
+
+#[test]
+fn api_error_exposes_its_source() {
+    let err = BabyProverError::from_api(failure::format_err!("server is unavailable"));
+    assert_eq!(err.to_string(), "server is unavailable");
+
+    let source = std::error::Error::source(&err).expect("api error must have a source");
+    assert_eq!(source.to_string(), "server is unavailable");
+    assert!(std::error::Error::source(&BabyProverError::Stop).is_none());
+}
+
+#[test]
+fn prover_errors_are_classified_as_retryable_or_fatal() {
+    let err = BabyProverError::from_api(failure::format_err!("prover data request timed out"));
+    assert!(err.is_retryable());
+    assert_eq!(err.block(), None);
+
+    let err = BabyProverError::api(failure::format_err!("prover is unknown"), false);
+    assert!(!err.is_retryable());
+
+    let err = BabyProverError::internal_for_block(5, "proof verification failed");
+    assert!(!err.is_retryable());
+    assert_eq!(err.block(), Some(5));
+    assert!(std::error::Error::source(&err).is_none());
+
+    assert!(!BabyProverError::internal("computation thread panicked").is_retryable());
+    assert!(!BabyProverError::Stop.is_retryable());
+}
+
+#[test]
+fn prover_keeps_running_after_retryable_error() {
+    let (handle, rounds, exit_err_rx) = start_failing_prover(|| {
+        BabyProverError::from_api(failure::format_err!("server is unavailable"))
+    });
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(10);
+    while rounds.load(Ordering::SeqCst) < 3 {
+        assert!(
+            time::Instant::now() < deadline,
+            "prover didn't retry the failed rounds"
+        );
+        thread::sleep(time::Duration::from_millis(50));
+    }
+    assert!(exit_err_rx.try_recv().is_err());
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn prover_keeps_running_after_proof_is_already_published() {
+    let (handle, rounds, exit_err_rx) =
+        start_failing_prover(|| BabyProverError::AlreadyPublished { block: 1 });
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(10);
+    while rounds.load(Ordering::SeqCst) < 3 {
+        assert!(
+            time::Instant::now() < deadline,
+            "prover didn't proceed to the next rounds"
+        );
+        thread::sleep(time::Duration::from_millis(50));
+    }
+    assert!(exit_err_rx.try_recv().is_err());
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+}
+
+#[test]
+fn prover_stops_when_handle_is_dropped() {
+    let (handle, rounds, exit_err_rx) = start_failing_prover(|| {
+        BabyProverError::from_api(failure::format_err!("server is unavailable"))
+    });
+    while rounds.load(Ordering::SeqCst) < 1 {
+        thread::sleep(time::Duration::from_millis(50));
+    }
+
+    drop(handle);
+    // Round in progress (if any) is finished, but no new rounds are started.
+    thread::sleep(time::Duration::from_secs(1));
+    let rounds_after_drop = rounds.load(Ordering::SeqCst);
+    thread::sleep(time::Duration::from_secs(1));
+    assert_eq!(rounds.load(Ordering::SeqCst), rounds_after_drop);
+    assert!(exit_err_rx.try_recv().is_err());
+}
+
+#[test]
+fn prover_handle_stop_finishes_rounds() {
+    let (handle, _rounds, exit_err_rx) = start_failing_prover(|| {
+        BabyProverError::from_api(failure::format_err!("server is unavailable"))
+    });
+
+    handle.stop();
+    handle.join();
+    assert!(exit_err_rx.try_recv().is_err());
+}
+
+#[test]
+fn prover_exits_on_fatal_error() {
+    let (_handle, rounds, exit_err_rx) = start_failing_prover(|| {
+        BabyProverError::api(failure::format_err!("prover is unknown"), false)
+    });
+
+    let err = exit_err_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("prover didn't exit on fatal error");
+    assert!(!err.is_retryable(), "unexpected error: {}", err);
+    assert_eq!(rounds.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn retry_policy_delays_grow_exponentially_up_to_max() {
+    let policy = RetryPolicy {
+        initial_delay: time::Duration::from_secs(1),
+        max_delay: time::Duration::from_secs(10),
+        multiplier: 2.0,
+        jitter_fraction: 0.1,
+    };
+
+    let expected_secs = [1, 2, 4, 8, 10, 10];
+    for (attempt, expected) in (1..).zip(expected_secs.iter()) {
+        assert_eq!(
+            policy.base_delay(attempt),
+            time::Duration::from_secs(*expected)
+        );
+
+        let delay = policy.delay(attempt).as_secs_f64();
+        let expected = *expected as f64;
+        assert!(delay >= expected * 0.9 && delay <= expected * 1.1);
+    }
+}
+
+#[test]
+fn prover_backs_off_exponentially_on_consecutive_api_errors() {
+    // Server fails three times, then responds successfully and then fails twice again.
+    let responses = vec![false, false, false, true, false, false, true];
+    let requests_count = responses.len();
+    let requested_at = Arc::new(Mutex::new(Vec::new()));
+    let (round_started_tx, _round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_millis(0),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            // Delays are taken from the policy the prover is created with.
+            retry_policy: RetryPolicy::new(
+                time::Duration::from_millis(100),
+                time::Duration::from_millis(400),
+            ),
+        },
+        FlakyApiClient {
+            responses: Mutex::new(responses.into_iter().collect()),
+            requested_at: requested_at.clone(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(0),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(100),
+        retry_max_delay: time::Duration::from_millis(400),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start_with_options(p, exit_err_tx, Default::default(), prover_options);
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(10);
+    while requested_at.lock().unwrap().len() < requests_count {
+        assert!(
+            time::Instant::now() < deadline,
+            "prover didn't make the expected requests"
+        );
+        thread::sleep(time::Duration::from_millis(50));
+    }
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+
+    let requested_at = requested_at.lock().unwrap();
+    let delays: Vec<_> = requested_at
+        .windows(2)
+        .map(|pair| pair[1].duration_since(pair[0]))
+        .collect();
+    // Delay after the successful round is the regular cycle wait, so it isn't checked.
+    let expected_delays_ms = [(0, 100), (1, 200), (2, 400), (4, 100), (5, 200)];
+    for (idx, expected_ms) in expected_delays_ms.iter() {
+        let expected = *expected_ms as f64 / 1000.0;
+        let delay = delays[*idx].as_secs_f64();
+        // Scheduling on a loaded machine can only prolong the delays, so just the lower bound
+        // (with 10% jitter) is checked, while the upper one is covered by the `RetryPolicy` test.
+        assert!(
+            delay >= expected * 0.9,
+            "unexpected delay after request {}: {}s, expected ~{}s",
+            idx,
+            delay,
+            expected
+        );
+    }
+}
+
+#[test]
+fn prover_backs_off_when_idle_and_resets_on_job() {
+    // No job for five rounds, then a job, then no job again.
+    let jobs = vec![None, None, None, None, None, Some((1, 1)), None, None];
+    let requests_count = jobs.len();
+    let requested_at = Arc::new(Mutex::new(Vec::new()));
+    let (round_started_tx, _round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_millis(0),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        IdleApiClient {
+            jobs: Mutex::new(jobs.into_iter().collect()),
+            requested_at: requested_at.clone(),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(100),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(100),
+        retry_max_delay: time::Duration::from_millis(400),
+        idle_backoff_after: 2,
+        idle_backoff_max: time::Duration::from_millis(600),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let (exit_err_tx, _exit_err_rx) = mpsc::channel();
+    let handle = prover::start_with_options(p, exit_err_tx, Default::default(), prover_options);
+
+    let deadline = time::Instant::now() + time::Duration::from_secs(10);
+    while requested_at.lock().unwrap().len() < requests_count {
+        assert!(
+            time::Instant::now() < deadline,
+            "prover didn't make the expected requests"
+        );
+        thread::sleep(time::Duration::from_millis(50));
+    }
+    handle
+        .stop_gracefully(time::Duration::from_secs(10))
+        .expect("prover didn't stop in time");
+
+    let requested_at = requested_at.lock().unwrap();
+    let delays: Vec<_> = requested_at
+        .windows(2)
+        .map(|pair| pair[1].duration_since(pair[0]))
+        .collect();
+    // Wait stays at `cycle_wait` for the first two idle rounds, then doubles up to the maximum,
+    // and gets back to `cycle_wait` once the job is done.
+    let expected_delays_ms = [100, 100, 200, 400, 600, 100, 100];
+    for (idx, expected_ms) in expected_delays_ms.iter().enumerate() {
+        let expected = *expected_ms as f64 / 1000.0;
+        let delay = delays[idx].as_secs_f64();
+        // Up to 300ms of random shift plus some slack for the scheduling overhead.
+        assert!(
+            delay >= expected * 0.95 && delay <= expected + 0.3 + 0.1,
+            "unexpected delay after request {}: {}s, expected ~{}s",
+            idx,
+            delay,
+            expected
+        );
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn prover_runs_on_provided_runtime_with_async_client() {
+    let (heartbeats_tx, heartbeats_rx) = mpsc::channel();
+    let (publishes_tx, publishes_rx) = mpsc::channel();
+    let (round_started_tx, _round_started_rx) = mpsc::channel();
+
+    let p = SlowProver::create_from_config(
+        SlowProverConfig {
+            proving_time: time::Duration::from_secs(1),
+            round_started_tx: Some(round_started_tx),
+            max_proving_time: None,
+            retry_policy: test_retry_policy(),
+        },
+        BlockingApiClient::new(AsyncJobQueueApiClient {
+            jobs: Mutex::new(vec![(1, 1), (2, 2)].into_iter().collect()),
+            heartbeats_tx: Mutex::new(heartbeats_tx),
+            publishes_tx: Mutex::new(publishes_tx),
+        }),
+        time::Duration::from_millis(100),
+        None,
+    );
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(0),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(100),
+        retry_max_delay: time::Duration::from_millis(400),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let shutdown_request = ShutdownRequest::new();
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let prover_task = tokio::spawn(prover::run_async(
+        p,
+        exit_err_tx,
+        shutdown_request.clone(),
+        prover_options,
+    ));
+
+    let timeout = time::Duration::from_secs(10);
+    // Test body runs outside of the runtime workers, so waiting for the channels doesn't stall the prover.
+    // Heartbeats are sent by the asynchronous client while the proof is being computed.
+    heartbeats_rx
+        .recv_timeout(timeout)
+        .expect("heartbeat wasn't sent");
+    let published: Vec<_> = (0..2)
+        .map(|_| {
+            publishes_rx
+                .recv_timeout(timeout)
+                .expect("proof wasn't published")
+        })
+        .collect();
+    assert_eq!(published, vec![1, 2]);
+
+    shutdown_request.set();
+    prover_task.await.expect("prover task panicked");
+    assert!(exit_err_rx.try_recv().is_err());
+}
+
+/// Requests of the heartbeat routine are sent by the asynchronous HTTP client, so they don't
+/// require the multi-threaded runtime to block in place.
+#[tokio::test]
+async fn client_sends_heartbeat_routine_requests_asynchronously() {
+    let (server_addr, _) = serve_keep_alive("");
+    let middleware = RecordingMiddleware::default();
+    let client =
+        prover::client::ApiClientBuilder::new(&format!("http://{}", server_addr), "test_worker")
+            .with_middleware(middleware.clone())
+            .build()
+            .expect("failed to create client");
+
+    client
+        .working_on_async(1, None)
+        .await
+        .expect("failed to send heartbeat");
+    client
+        .extend_lease_async(1, 60)
+        .await
+        .expect("failed to extend lease");
+    client
+        .record_failure_async(1, "broken setup")
+        .await
+        .expect("failed to record failure");
+    client
+        .prover_stopped_async(1)
+        .await
+        .expect("failed to report stopped prover");
+    assert_eq!(
+        *middleware.0.lock().unwrap(),
+        vec![
+            "request working on",
+            "response working on 200",
+            "request extend lease",
+            "response extend lease 200",
+            "request record failure",
+            "response record failure 200",
+            "request prover stopped",
+            "response prover stopped 200",
+        ]
+    );
+}
+
+#[test]
+fn round_robin_gives_jobs_to_idle_provers_in_turn() {
+    let mut scheduler = RoundRobin::default();
+    assert_eq!(scheduler.next_instance(&[0, 1, 2]), 0);
+    scheduler.job_assigned(0);
+    assert_eq!(scheduler.next_instance(&[1, 2]), 1);
+    scheduler.job_assigned(1);
+    // Busy prover is skipped.
+    assert_eq!(scheduler.next_instance(&[0, 2]), 2);
+    scheduler.job_assigned(2);
+    // Turn wraps around.
+    assert_eq!(scheduler.next_instance(&[0, 1]), 0);
+}
+
+#[test]
+fn prover_pool_distributes_jobs_among_provers() {
+    let (heartbeat_tx, _heartbeat_rx) = mpsc::channel();
+    let (proof_tx, proof_rx) = mpsc::channel();
+    let (round_started_tx, round_started_rx) = mpsc::channel();
+    let api_client = JobQueueApiClient {
+        jobs: Mutex::new(vec![(1, 10), (2, 20)].into()),
+        heartbeats_tx: Mutex::new(heartbeat_tx),
+        publishes_tx: Mutex::new(proof_tx),
+        extended_leases: Default::default(),
+    };
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(0),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(100),
+        retry_max_delay: time::Duration::from_millis(400),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 2,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let metrics = PoolMetrics::new(&ProverMetrics::new());
+    let shutdown_request = ShutdownRequest::new();
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let pool = ProverPool::start(
+        api_client,
+        move |api_client| {
+            SlowProver::create_from_config(
+                SlowProverConfig {
+                    proving_time: time::Duration::from_secs(3),
+                    round_started_tx: Some(round_started_tx.clone()),
+                    max_proving_time: None,
+                    retry_policy: test_retry_policy(),
+                },
+                api_client,
+                time::Duration::from_millis(100),
+                None,
+            )
+        },
+        Box::new(RoundRobin::default()),
+        exit_err_tx,
+        shutdown_request.clone(),
+        Arc::new(ArcSwap::from_pointee(prover_options)),
+        Some(metrics.clone()),
+    );
+    assert_eq!(pool.size(), 2);
+
+    // No jobs are taken until the prover is registered.
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(round_started_rx.try_recv().is_err());
+    shutdown_request.set_prover_id(1);
+
+    // Every prover proves one job at a time, so both jobs are in flight only if
+    // they are given to the different provers.
+    let timeout = time::Duration::from_secs(2);
+    for _ in 0..2 {
+        round_started_rx
+            .recv_timeout(timeout)
+            .expect("jobs are not distributed among provers");
+    }
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(metrics.jobs_in_flight.get(), 2.0);
+    assert_eq!(metrics.instances_alive.get(), 2.0);
+
+    let mut published: Vec<_> = (0..2)
+        .map(|_| {
+            proof_rx
+                .recv_timeout(time::Duration::from_secs(10))
+                .expect("proof wasn't published")
+        })
+        .collect();
+    published.sort();
+    assert_eq!(published, vec![1, 2]);
+
+    pool.stop();
+    pool.join();
+    assert!(exit_err_rx.try_recv().is_err());
+    assert_eq!(metrics.proofs_published.get(), 2.0);
+    assert_eq!(metrics.instances_alive.get(), 0.0);
+}
+
+#[test]
+fn prover_pool_restarts_failed_prover_up_to_limit() {
+    let rounds = Arc::new(AtomicUsize::new(0));
+    let prover_rounds = rounds.clone();
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(0),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(10),
+        retry_max_delay: time::Duration::from_millis(10),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 2,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let metrics = PoolMetrics::new(&ProverMetrics::new());
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let pool = ProverPool::start(
+        FlakyApiClient {
+            responses: Mutex::new(VecDeque::new()),
+            requested_at: Arc::new(Mutex::new(Vec::new())),
+        },
+        move |api_client| {
+            FailingProver::create_from_config(
+                FailingProverConfig {
+                    error_fn: || BabyProverError::internal("broken setup"),
+                    rounds: prover_rounds.clone(),
+                    retry_policy: RetryPolicy::new(
+                        time::Duration::from_millis(10),
+                        time::Duration::from_millis(10),
+                    ),
+                },
+                api_client,
+                time::Duration::from_millis(100),
+                None,
+            )
+        },
+        Box::new(RoundRobin::default()),
+        exit_err_tx,
+        ShutdownRequest::new(),
+        Arc::new(ArcSwap::from_pointee(prover_options)),
+        Some(metrics.clone()),
+    );
+
+    // Pool gives up once the restarts are exhausted.
+    let err = exit_err_rx
+        .recv_timeout(time::Duration::from_secs(10))
+        .expect("pool didn't stop");
+    assert!(err.to_string().contains("broken setup"), "{}", err);
+    pool.join();
+    assert_eq!(rounds.load(Ordering::SeqCst), 3);
+    assert_eq!(metrics.restarts.get(), 2.0);
+    assert_eq!(metrics.instances_alive.get(), 0.0);
+}
+
+/// Starts the prover which fails every round with the error created by `error_fn`.
+/// Returns the prover handle, counter of the started rounds and the receiver of the exit error.
+fn start_failing_prover(
+    error_fn: fn() -> BabyProverError,
+) -> (
+    prover::ProverHandle,
+    Arc<AtomicUsize>,
+    mpsc::Receiver<BabyProverError>,
+) {
+    let rounds = Arc::new(AtomicUsize::new(0));
+    let p = FailingProver::create_from_config(
+        FailingProverConfig {
+            error_fn,
+            rounds: rounds.clone(),
+            retry_policy: RetryPolicy::new(
+                time::Duration::from_millis(10),
+                time::Duration::from_millis(10),
+            ),
+        },
+        FlakyApiClient {
+            responses: Mutex::new(VecDeque::new()),
+            requested_at: Arc::new(Mutex::new(Vec::new())),
+        },
+        time::Duration::from_millis(100),
+        None,
+    );
+    let prover_options = ProverOptions {
+        prepare_data_interval: time::Duration::from_millis(100),
+        prepare_data_max_interval: time::Duration::from_millis(100),
+        heartbeat_interval: time::Duration::from_millis(100),
+        cycle_wait: time::Duration::from_millis(0),
+        gone_timeout: time::Duration::from_secs(60),
+        retry_initial_delay: time::Duration::from_millis(10),
+        retry_max_delay: time::Duration::from_millis(10),
+        idle_backoff_after: 0,
+        idle_backoff_max: time::Duration::from_millis(0),
+        workers: 1,
+        skip_self_verify: false,
+        params_load_warn_threshold: None,
+        max_proof_time: None,
+        block_range: None,
+        connect_timeout: time::Duration::from_secs(5),
+        request_timeout: time::Duration::from_secs(10),
+        request_retries: 0,
+        request_retry_backoff: time::Duration::from_millis(100),
+        pool_size: 1,
+        max_restarts_per_hour: 0,
+        secret_auth: None,
+        server_ca_cert: None,
+        health_check_on_start: false,
+        tls: None,
+        max_prover_data_size: 512 * 1024 * 1024,
+        none_cache_ttl: time::Duration::from_millis(500),
+    };
+    let (exit_err_tx, exit_err_rx) = mpsc::channel();
+    let handle = prover::start_with_options(p, exit_err_tx, Default::default(), prover_options);
+    (handle, rounds, exit_err_rx)
+}
+
+/// Retry policy of the test provers, which don't expect the API errors to be retried for long.
+fn test_retry_policy() -> RetryPolicy {
+    RetryPolicy::new(
+        time::Duration::from_millis(100),
+        time::Duration::from_millis(400),
+    )
+}
+
+fn new_test_data_for_prover() -> ProverData {
+    new_test_data_for_prover_with_size(testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    ))
+}
+
+fn new_test_data_for_prover_with_size(block_size_chunks: usize) -> ProverData {
+    testing::deposit_block_prover_data(block_size_chunks)
+}
+
+/// API client which serves the provided blocks one by one, each of them only for
+/// `block_to_prove` requests with the matching block size.
+struct SequentialApiClient {
+    blocks: Mutex<VecDeque<(i64, ProverData)>>,
+    publishes_tx: Mutex<mpsc::Sender<(i64, EncodedProofPlonk)>>,
+}
+
+impl fmt::Debug for SequentialApiClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SequentialApiClient").finish()
+    }
+}
+
+impl prover::ApiClient for SequentialApiClient {
+    fn block_to_prove(&self, block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        let blocks = self.blocks.lock().unwrap();
+        Ok(blocks
+            .front()
+            .filter(|(_, data)| data.operations.len() == block_size)
+            .map(|(block, _)| (*block, *block as i32)))
+    }
+
+    fn working_on(&self, _job_id: i32, _: Option<JobProgress>) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn extend_lease(&self, _job_id: i32, _: u32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_data(&self, block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        let blocks = self.blocks.lock().unwrap();
+        match blocks.front() {
+            Some((current, data)) if *current == block => Ok(data.clone().into_circuit(block)),
+            _ => Err(failure::format_err!("unexpected block {}", block)),
+        }
+    }
+
+    fn prover_data_for_block(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn publish(&self, block: i64, p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        self.blocks.lock().unwrap().pop_front();
+        let _ = self.publishes_tx.lock().unwrap().send((block, p));
+        Ok(())
+    }
+
+    fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn record_failure(&self, _: i32, _: &str) -> Result<(), failure::Error> {
+        Ok(())
+    }
+}
+
+/// API client which hands out the provided jobs one per `block_to_prove` request.
+/// Reports every heartbeat along with the job progress, and records the lease extensions.
+#[derive(Debug)]
+struct JobQueueApiClient {
+    jobs: Mutex<VecDeque<(i64, i32)>>,
+    heartbeats_tx: Mutex<mpsc::Sender<(i32, Option<JobProgress>)>>,
+    publishes_tx: Mutex<mpsc::Sender<i64>>,
+    /// Jobs with the extended lease, along with the extra seconds.
+    extended_leases: Arc<Mutex<Vec<(i32, u32)>>>,
+}
+
+impl prover::ApiClient for JobQueueApiClient {
+    fn block_to_prove(&self, _block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        Ok(self.jobs.lock().unwrap().pop_front())
+    }
+
+    fn working_on(&self, job_id: i32, progress: Option<JobProgress>) -> Result<(), failure::Error> {
+        let _ = self.heartbeats_tx.lock().unwrap().send((job_id, progress));
+        Ok(())
+    }
+
+    fn extend_lease(&self, job_id: i32, extra_seconds: u32) -> Result<(), failure::Error> {
+        self.extended_leases
+            .lock()
+            .unwrap()
+            .push((job_id, extra_seconds));
+        Ok(())
+    }
+
+    fn prover_data(&self, _block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn prover_data_for_block(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn publish(&self, block: i64, _p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        let _ = self.publishes_tx.lock().unwrap().send(block);
+        Ok(())
+    }
+
+    fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn record_failure(&self, _: i32, _: &str) -> Result<(), failure::Error> {
+        Ok(())
+    }
+}
+
+/// Asynchronous version of the `JobQueueApiClient`.
+#[derive(Debug)]
+struct AsyncJobQueueApiClient {
+    jobs: Mutex<VecDeque<(i64, i32)>>,
+    heartbeats_tx: Mutex<mpsc::Sender<i32>>,
+    publishes_tx: Mutex<mpsc::Sender<i64>>,
+}
+
+#[async_trait::async_trait]
+impl prover::AsyncApiClient for AsyncJobQueueApiClient {
+    async fn block_to_prove(
+        &self,
+        _block_size: usize,
+    ) -> Result<Option<(i64, i32)>, failure::Error> {
+        Ok(self.jobs.lock().unwrap().pop_front())
+    }
+
+    async fn working_on(&self, job_id: i32, _: Option<JobProgress>) -> Result<(), failure::Error> {
+        let _ = self.heartbeats_tx.lock().unwrap().send(job_id);
+        Ok(())
+    }
+
+    async fn extend_lease(&self, _job_id: i32, _: u32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    async fn prover_data(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    async fn prover_data_for_block(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    async fn publish(&self, block: i64, _p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        let _ = self.publishes_tx.lock().unwrap().send(block);
+        Ok(())
+    }
+
+    async fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    async fn record_failure(&self, _: i32, _: &str) -> Result<(), failure::Error> {
+        Ok(())
+    }
+}
+
+/// API client which hands out the provided jobs and either accepts or rejects every published proof.
+/// Reports every publish attempt along with its outcome.
+#[derive(Debug)]
+struct UnreliablePublishApiClient {
+    jobs: Mutex<VecDeque<(i64, i32)>>,
+    publish_succeeds: bool,
+    publishes_tx: Mutex<mpsc::Sender<(i64, bool)>>,
+}
+
+impl prover::ApiClient for UnreliablePublishApiClient {
+    fn block_to_prove(&self, _block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        Ok(self.jobs.lock().unwrap().pop_front())
+    }
+
+    fn working_on(&self, _job_id: i32, _: Option<JobProgress>) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn extend_lease(&self, _job_id: i32, _: u32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_data(&self, _block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn prover_data_for_block(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn publish(&self, block: i64, _p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        let _ = self
+            .publishes_tx
+            .lock()
+            .unwrap()
+            .send((block, self.publish_succeeds));
+        if self.publish_succeeds {
+            Ok(())
+        } else {
+            Err(failure::format_err!("server is unavailable"))
+        }
+    }
+
+    fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn record_failure(&self, _: i32, _: &str) -> Result<(), failure::Error> {
+        Ok(())
+    }
+}
+
+/// Prover that doesn't compute anything, but takes the given time to "prove" a block.
+struct SlowProver<C> {
+    api_client: C,
+    heartbeat_interval: time::Duration,
+    proving_time: time::Duration,
+    round_started_tx: Mutex<Option<mpsc::Sender<()>>>,
+    max_proving_time: Option<time::Duration>,
+    retry_policy: RetryPolicy,
+}
+
+struct SlowProverConfig {
+    proving_time: time::Duration,
+    /// Notified once the round obtains a job.
+    round_started_tx: Option<mpsc::Sender<()>>,
+    /// Job is abandoned if "proving" takes longer than this.
+    max_proving_time: Option<time::Duration>,
+    retry_policy: RetryPolicy,
+}
+
+impl ProverConfig for SlowProverConfig {
+    fn from_env() -> Self {
+        Self {
+            proving_time: time::Duration::from_millis(0),
+            round_started_tx: None,
+            max_proving_time: None,
+            retry_policy: RetryPolicy::from_options(&ProverOptions::from_env()),
+        }
+    }
+}
+
+impl<C: ApiClient> ProverImpl<C> for SlowProver<C> {
+    type Config = SlowProverConfig;
+
+    fn create_from_config(
+        config: SlowProverConfig,
+        api_client: C,
+        heartbeat_interval: time::Duration,
+        _: Option<Arc<ProverMetrics>>,
+    ) -> Self {
+        Self {
+            api_client,
+            heartbeat_interval,
+            proving_time: config.proving_time,
+            round_started_tx: Mutex::new(config.round_started_tx),
+            max_proving_time: config.max_proving_time,
+            retry_policy: config.retry_policy,
+        }
+    }
+
+    fn next_round(
+        &self,
+        start_heartbeats_tx: mpsc::Sender<HeartbeatRequest>,
+        shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError> {
+        let (block, job_id) = match self.api_client.block_to_prove(0) {
+            Ok(Some(job)) => job,
+            Ok(None) => return Ok(RoundOutcome::NoJob),
+            Err(e) => return Err(BabyProverError::from_api(e)),
+        };
+        start_heartbeats_tx.send(HeartbeatRequest::Job(Some(job_id)))?;
+        if let Some(round_started_tx) = self.round_started_tx.lock().unwrap().as_ref() {
+            let _ = round_started_tx.send(());
+        }
+
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::ProofGeneration))?;
+        let proving_time = self.proving_time;
+        let proved =
+            prover::run_with_deadline(shutdown_request, self.max_proving_time, move || {
+                thread::sleep(proving_time)
+            })?;
+        if proved.is_none() {
+            return Ok(RoundOutcome::JobAbandoned);
+        }
+
+        start_heartbeats_tx.send(HeartbeatRequest::Stage(ProvingStage::Publishing))?;
+        self.api_client
+            .publish(block, EncodedProofPlonk::default())
+            .map(|_| RoundOutcome::JobDone)
+            .map_err(BabyProverError::from_api)
+    }
+
+    fn get_heartbeat_options(&self) -> (&C, time::Duration) {
+        (&self.api_client, self.heartbeat_interval)
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn supported_block_sizes(&self) -> Vec<usize> {
+        Vec::new()
+    }
+}
+
+/// Prover which fails every round with the error created by the provided function.
+struct FailingProver<C> {
+    api_client: C,
+    error_fn: fn() -> BabyProverError,
+    rounds: Arc<AtomicUsize>,
+    retry_policy: RetryPolicy,
+}
+
+struct FailingProverConfig {
+    error_fn: fn() -> BabyProverError,
+    /// Incremented on every round.
+    rounds: Arc<AtomicUsize>,
+    retry_policy: RetryPolicy,
+}
+
+impl ProverConfig for FailingProverConfig {
+    fn from_env() -> Self {
+        Self {
+            error_fn: || BabyProverError::internal("prover is failing"),
+            rounds: Default::default(),
+            retry_policy: RetryPolicy::from_options(&ProverOptions::from_env()),
+        }
+    }
+}
+
+impl<C: ApiClient> ProverImpl<C> for FailingProver<C> {
+    type Config = FailingProverConfig;
+
+    fn create_from_config(
+        config: FailingProverConfig,
+        api_client: C,
+        _: time::Duration,
+        _: Option<Arc<ProverMetrics>>,
+    ) -> Self {
+        Self {
+            api_client,
+            error_fn: config.error_fn,
+            rounds: config.rounds,
+            retry_policy: config.retry_policy,
+        }
+    }
+
+    fn next_round(
+        &self,
+        start_heartbeats_tx: mpsc::Sender<HeartbeatRequest>,
+        _shutdown_request: &ShutdownRequest,
+    ) -> Result<RoundOutcome, BabyProverError> {
+        self.rounds.fetch_add(1, Ordering::SeqCst);
+        start_heartbeats_tx.send(HeartbeatRequest::Job(None))?;
+        Err((self.error_fn)())
+    }
+
+    fn get_heartbeat_options(&self) -> (&C, time::Duration) {
+        (&self.api_client, time::Duration::from_millis(100))
+    }
+
+    fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    fn supported_block_sizes(&self) -> Vec<usize> {
+        Vec::new()
+    }
+}
+
+/// API client which responds to `block_to_prove` requests according to the provided script
+/// (`true` means successful response) and records the time of each request.
+/// Responds successfully once the script is over.
+#[derive(Debug)]
+struct FlakyApiClient {
+    responses: Mutex<VecDeque<bool>>,
+    requested_at: Arc<Mutex<Vec<time::Instant>>>,
+}
+
+impl prover::ApiClient for FlakyApiClient {
+    fn block_to_prove(&self, _block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        self.requested_at.lock().unwrap().push(time::Instant::now());
+        match self.responses.lock().unwrap().pop_front() {
+            Some(false) => Err(failure::format_err!("server is unavailable")),
+            _ => Ok(None),
+        }
+    }
+
+    fn working_on(&self, _job_id: i32, _: Option<JobProgress>) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn extend_lease(&self, _job_id: i32, _: u32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_data(&self, _block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn prover_data_for_block(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn publish(&self, _block: i64, _p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn record_failure(&self, _: i32, _: &str) -> Result<(), failure::Error> {
+        Ok(())
+    }
+}
+
+/// Api client which serves the configured jobs and records the times of the job requests.
+#[derive(Debug)]
+struct IdleApiClient {
+    jobs: Mutex<VecDeque<Option<(i64, i32)>>>,
+    requested_at: Arc<Mutex<Vec<time::Instant>>>,
+}
+
+impl prover::ApiClient for IdleApiClient {
+    fn block_to_prove(&self, _block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        self.requested_at.lock().unwrap().push(time::Instant::now());
+        Ok(self.jobs.lock().unwrap().pop_front().flatten())
+    }
+
+    fn working_on(&self, _job_id: i32, _: Option<JobProgress>) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn extend_lease(&self, _job_id: i32, _: u32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_data(&self, _block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn prover_data_for_block(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn publish(&self, _block: i64, _p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn record_failure(&self, _: i32, _: &str) -> Result<(), failure::Error> {
+        Ok(())
+    }
+}
+
+/// Api client which always has a job, but panics on the heartbeat request.
+#[derive(Debug)]
+struct PanickingHeartbeatApiClient;
+
+impl prover::ApiClient for PanickingHeartbeatApiClient {
+    fn block_to_prove(&self, _block_size: usize) -> Result<Option<(i64, i32)>, failure::Error> {
+        Ok(Some((1, 1)))
+    }
+
+    fn working_on(&self, _job_id: i32, _: Option<JobProgress>) -> Result<(), failure::Error> {
+        panic!("heartbeat request failed")
+    }
+
+    fn extend_lease(&self, _job_id: i32, _: u32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_data(&self, _block: i64) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn prover_data_for_block(
+        &self,
+        _block: i64,
+    ) -> Result<FranklinCircuit<'static, Engine>, failure::Error> {
+        Err(failure::format_err!("mock not configured"))
+    }
+
+    fn publish(&self, _block: i64, _p: EncodedProofPlonk) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn prover_stopped(&self, _: i32) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    fn record_failure(&self, _: i32, _: &str) -> Result<(), failure::Error> {
         Ok(())
     }
 }
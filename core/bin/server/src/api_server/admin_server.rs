@@ -151,7 +151,7 @@ pub fn start_admin_server(
     bind_to: SocketAddr,
     secret_auth: String,
     connection_pool: storage::ConnectionPool,
-    panic_notify: mpsc::Sender<bool>,
+    panic_notify: mpsc::Sender<Option<String>>,
 ) {
     thread::Builder::new()
         .name("admin_server".to_string())
@@ -0,0 +1,173 @@
+//! Subscription limits of the WebSocket connections.
+//!
+//! Subscriptions are requested through the RPC handlers, but their IDs are assigned by the
+//! event notifier, which also ends the one-shot subscriptions once they are notified. Both sides
+//! share this state, so the connection is only charged for the subscriptions it actually has.
+
+// Built-in deps
+use std::collections::HashSet;
+use std::num::NonZeroU32;
+// External uses
+use dashmap::DashMap;
+use governor::{
+    clock::DefaultClock,
+    state::{InMemoryState, NotKeyed},
+    Quota, RateLimiter,
+};
+use jsonrpc_pubsub::SubscriptionId;
+
+/// Subscriptions of a single WebSocket connection.
+struct ConnectionLimits {
+    rate_limiter: RateLimiter<NotKeyed, InMemoryState, DefaultClock>,
+    /// Amount of requested subscriptions the notifier has not handled yet.
+    pending: usize,
+    /// Subscriptions kept by the notifier until they are notified or unsubscribed.
+    active: HashSet<SubscriptionId>,
+}
+
+impl ConnectionLimits {
+    fn subscriptions(&self) -> usize {
+        self.pending + self.active.len()
+    }
+}
+
+pub struct ConnectionSubscriptions {
+    connections: DashMap<u64, ConnectionLimits>,
+    subscriptions_per_sec: NonZeroU32,
+    max_subscriptions: usize,
+}
+
+impl ConnectionSubscriptions {
+    pub fn new(subscriptions_per_sec: NonZeroU32, max_subscriptions: usize) -> Self {
+        Self {
+            connections: DashMap::new(),
+            subscriptions_per_sec,
+            max_subscriptions,
+        }
+    }
+
+    /// Checks whether the connection may make one more subscription and accounts it as pending
+    /// if so. Every accounted subscription has to be settled once the notifier handles it.
+    pub fn acquire(&self, conn_id: u64) -> Result<(), String> {
+        let mut limits = self
+            .connections
+            .entry(conn_id)
+            .or_insert_with(|| ConnectionLimits {
+                rate_limiter: RateLimiter::direct(Quota::per_second(self.subscriptions_per_sec)),
+                pending: 0,
+                active: HashSet::new(),
+            });
+
+        if limits.subscriptions() >= self.max_subscriptions {
+            return Err(format!(
+                "Too many subscriptions for the connection, max allowed: {}",
+                self.max_subscriptions
+            ));
+        }
+        if limits.rate_limiter.check().is_err() {
+            return Err(format!(
+                "Too many subscription requests, max allowed per second: {}",
+                self.subscriptions_per_sec
+            ));
+        }
+        limits.pending += 1;
+        Ok(())
+    }
+
+    /// Remembers the ID of the subscription the notifier keeps for the connection.
+    /// Has to be called before the ID is sent to the client, so it can unsubscribe right away.
+    pub fn keep(&self, conn_id: u64, id: SubscriptionId) {
+        if let Some(mut limits) = self.connections.get_mut(&conn_id) {
+            limits.active.insert(id);
+        }
+    }
+
+    /// Settles the pending subscription, whether it was kept, notified right away or rejected.
+    pub fn settle(&self, conn_id: u64) {
+        if let Some(mut limits) = self.connections.get_mut(&conn_id) {
+            limits.pending = limits.pending.saturating_sub(1);
+        }
+    }
+
+    /// Releases the subscription of the connection, returns `false` if it has no such subscription.
+    pub fn release(&self, conn_id: u64, id: &SubscriptionId) -> bool {
+        self.connections
+            .get_mut(&conn_id)
+            .map(|mut limits| limits.active.remove(id))
+            .unwrap_or(false)
+    }
+
+    pub fn remove_connection(&self, conn_id: u64) {
+        self.connections.remove(&conn_id);
+    }
+
+    #[cfg(test)]
+    pub fn subscriptions(&self, conn_id: u64) -> usize {
+        self.connections
+            .get(&conn_id)
+            .map(|limits| limits.subscriptions())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription_id(id: &str) -> SubscriptionId {
+        SubscriptionId::String(id.to_string())
+    }
+
+    fn connection_subscriptions(max_subscriptions: usize) -> ConnectionSubscriptions {
+        ConnectionSubscriptions::new(NonZeroU32::new(100).unwrap(), max_subscriptions)
+    }
+
+    #[test]
+    fn limits_pending_and_kept_subscriptions() {
+        let subscriptions = connection_subscriptions(2);
+        subscriptions.acquire(1).unwrap();
+        subscriptions.keep(1, subscription_id("a"));
+        subscriptions.settle(1);
+        subscriptions.acquire(1).unwrap();
+
+        assert!(subscriptions.acquire(1).is_err());
+        assert_eq!(subscriptions.subscriptions(1), 2);
+        // Limits are per connection.
+        subscriptions.acquire(2).unwrap();
+    }
+
+    #[test]
+    fn settled_subscriptions_that_were_not_kept_are_released() {
+        let subscriptions = connection_subscriptions(1);
+        subscriptions.acquire(1).unwrap();
+        subscriptions.settle(1);
+
+        assert_eq!(subscriptions.subscriptions(1), 0);
+        subscriptions.acquire(1).unwrap();
+    }
+
+    #[test]
+    fn releases_only_known_subscriptions() {
+        let subscriptions = connection_subscriptions(1);
+        subscriptions.acquire(1).unwrap();
+        subscriptions.keep(1, subscription_id("a"));
+        subscriptions.settle(1);
+
+        assert!(!subscriptions.release(1, &subscription_id("b")));
+        assert!(!subscriptions.release(2, &subscription_id("a")));
+        assert!(subscriptions.acquire(1).is_err());
+
+        assert!(subscriptions.release(1, &subscription_id("a")));
+        assert!(!subscriptions.release(1, &subscription_id("a")));
+        assert_eq!(subscriptions.subscriptions(1), 0);
+    }
+
+    #[test]
+    fn limits_subscription_rate() {
+        let subscriptions = ConnectionSubscriptions::new(NonZeroU32::new(1).unwrap(), 10);
+        subscriptions.acquire(1).unwrap();
+        subscriptions.settle(1);
+
+        assert!(subscriptions.acquire(1).is_err());
+    }
+}
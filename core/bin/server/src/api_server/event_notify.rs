@@ -1,8 +1,12 @@
+use super::connection_subscriptions::ConnectionSubscriptions;
+use super::replay_buffer::{ReplayBuffer, SubscriptionCheckpoint};
 use super::rpc_server::types::{
-    BlockInfo, ETHOpInfoResp, ResponseAccountState, TransactionInfoResp,
+    AccountTokenBalanceResp, BlockInfo, BlockInfoResp, ETHOpInfoResp, ResponseAccountState,
+    TransactionInfoResp,
 };
 use crate::state_keeper::{ExecutedOpId, ExecutedOpsNotify, StateKeeperRequest};
 use crate::utils::token_db_cache::TokenDBCache;
+use chrono::Utc;
 use failure::{bail, format_err};
 use futures::{
     channel::{mpsc, oneshot},
@@ -17,10 +21,15 @@ use jsonrpc_pubsub::{
 };
 use lru_cache::LruCache;
 use models::node::tx::TxHash;
-use models::node::BlockNumber;
-use models::{node::block::ExecutedOperations, node::AccountId, ActionType, Operation};
+use models::node::{AccountUpdate, AccountUpdates, BlockNumber, TokenId};
+use models::{
+    fe_from_bytes, node::block::ExecutedOperations, node::AccountId, ActionType, Operation,
+};
+use num::BigUint;
 use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::sync::Arc;
+use storage::chain::block::records::BlockDetails;
 use storage::chain::operations::records::StoredExecutedPriorityOperation;
 use storage::chain::operations_ext::records::TxReceiptResponse;
 use storage::ConnectionPool;
@@ -30,6 +39,8 @@ const MAX_LISTENERS_PER_ENTITY: usize = 2048;
 const TX_SUB_PREFIX: &str = "txsub";
 const ETHOP_SUB_PREFIX: &str = "eosub";
 const ACCOUNT_SUB_PREFIX: &str = "acsub";
+const BLOCK_SUB_PREFIX: &str = "blsub";
+const ACCOUNT_TOKEN_SUB_PREFIX: &str = "atsub";
 
 pub enum EventSubscribeRequest {
     Transaction {
@@ -42,20 +53,40 @@ pub enum EventSubscribeRequest {
         action: ActionType,
         subscriber: Subscriber<ETHOpInfoResp>,
     },
+    /// Events buffered after the `replay_from` block are replayed to the new subscriber.
     Account {
         address: Address,
         action: ActionType,
+        replay_from: Option<u64>,
         subscriber: Subscriber<ResponseAccountState>,
     },
+    Block {
+        block_number: u64,
+        action: ActionType,
+        subscriber: Subscriber<BlockInfoResp>,
+    },
+    /// Events buffered after the `replay_from` block are replayed to the new subscriber.
+    AccountToken {
+        address: Address,
+        token_id: TokenId,
+        action: ActionType,
+        replay_from: Option<u64>,
+        subscriber: Subscriber<AccountTokenBalanceResp>,
+    },
 }
 
 pub enum EventNotifierRequest {
-    Sub(EventSubscribeRequest),
+    Sub {
+        conn_id: u64,
+        request: EventSubscribeRequest,
+    },
     Unsub(SubscriptionId),
 }
 
 struct SubscriptionSender<T> {
     id: SubscriptionId,
+    /// WebSocket connection the subscription belongs to.
+    conn_id: u64,
     sink: Sink<T>,
 }
 
@@ -67,9 +98,19 @@ struct OperationNotifier {
 
     db_pool: ConnectionPool,
     state_keeper_requests: mpsc::Sender<StateKeeperRequest>,
+    connection_subs: Arc<ConnectionSubscriptions>,
     tx_subs: BTreeMap<(TxHash, ActionType), Vec<SubscriptionSender<TransactionInfoResp>>>,
     prior_op_subs: BTreeMap<(u64, ActionType), Vec<SubscriptionSender<ETHOpInfoResp>>>,
     account_subs: BTreeMap<(AccountId, ActionType), Vec<SubscriptionSender<ResponseAccountState>>>,
+    block_subs: BTreeMap<(u64, ActionType), Vec<SubscriptionSender<BlockInfoResp>>>,
+    account_token_subs: BTreeMap<
+        (AccountId, TokenId, ActionType),
+        Vec<SubscriptionSender<AccountTokenBalanceResp>>,
+    >,
+    // Transaction, priority operation and block subscriptions are resolved from the storage
+    // if the event has already happened, so only the account streams are replayed.
+    account_events: ReplayBuffer<(AccountId, ActionType), ResponseAccountState>,
+    account_token_events: ReplayBuffer<(AccountId, TokenId, ActionType), AccountTokenBalanceResp>,
 }
 
 impl OperationNotifier {
@@ -77,6 +118,43 @@ impl OperationNotifier {
         tokio::spawn(sink.notify(Ok(val)).compat().map(drop));
     }
 
+    /// Sends the values one after another, so the subscriber receives them in the same order.
+    fn send_in_order<T: serde::Serialize + Send + 'static>(&self, sink: &Sink<T>, vals: Vec<T>) {
+        if vals.is_empty() {
+            return;
+        }
+        let sink = sink.clone();
+        tokio::spawn(async move {
+            for val in vals {
+                if sink.notify(Ok(val)).compat().await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Assigns the ID to the subscription that is kept until its event happens,
+    /// so the connection is charged for it until then.
+    fn assign_kept_id<T>(
+        &self,
+        conn_id: u64,
+        sub: Subscriber<T>,
+        id: SubscriptionId,
+    ) -> Result<Sink<T>, failure::Error> {
+        self.connection_subs.keep(conn_id, id.clone());
+        sub.assign_id(id.clone()).map_err(|_| {
+            self.connection_subs.release(conn_id, &id);
+            format_err!("SubIdAssign")
+        })
+    }
+
+    /// Releases the subscriptions that are over once notified.
+    fn release_notified<T>(&self, subs: &[SubscriptionSender<T>]) {
+        for sub in subs {
+            self.connection_subs.release(sub.conn_id, &sub.id);
+        }
+    }
+
     async fn check_op_executed_current_block(
         &self,
         op_id: ExecutedOpId,
@@ -133,6 +211,33 @@ impl OperationNotifier {
                     }
                 }
             }
+            BLOCK_SUB_PREFIX => {
+                let block_number: u64 = sub_unique_id.parse()?;
+                if let Some(mut subs) = self.block_subs.remove(&(block_number, sub_action)) {
+                    subs.retain(|sub| sub.id != sub_id);
+                    if !subs.is_empty() {
+                        self.block_subs.insert((block_number, sub_action), subs);
+                    }
+                }
+            }
+            ACCOUNT_TOKEN_SUB_PREFIX => {
+                let mut unique_id_split = sub_unique_id.splitn(2, ':');
+                let account_id: AccountId = unique_id_split
+                    .next()
+                    .ok_or_else(incorrect_id_err)?
+                    .parse()?;
+                let token_id: TokenId = unique_id_split
+                    .next()
+                    .ok_or_else(incorrect_id_err)?
+                    .parse()?;
+                let key = (account_id, token_id, sub_action);
+                if let Some(mut subs) = self.account_token_subs.remove(&key) {
+                    subs.retain(|sub| sub.id != sub_id);
+                    if !subs.is_empty() {
+                        self.account_token_subs.insert(key, subs);
+                    }
+                }
+            }
             _ => return Err(incorrect_id_err()),
         }
         Ok(())
@@ -143,36 +248,76 @@ impl OperationNotifier {
         new_sub: EventNotifierRequest,
     ) -> Result<(), failure::Error> {
         match new_sub {
-            EventNotifierRequest::Sub(event_sub) => match event_sub {
-                EventSubscribeRequest::Transaction {
-                    hash,
-                    action,
-                    subscriber,
-                } => self.handle_transaction_sub(hash, action, subscriber).await,
-                EventSubscribeRequest::PriorityOp {
-                    serial_id,
-                    action,
-                    subscriber,
-                } => {
-                    self.handle_priority_op_sub(serial_id, action, subscriber)
-                        .await
-                }
-                EventSubscribeRequest::Account {
-                    address,
-                    action,
-                    subscriber,
-                } => {
-                    self.handle_account_update_sub(address, action, subscriber)
-                        .await
-                }
+            EventNotifierRequest::Sub { conn_id, request } => {
+                let result = self.handle_sub(conn_id, request).await;
+                self.connection_subs.settle(conn_id);
+                result.map_err(|e| format_err!("Failed to add sub: {}", e))
             }
-            .map_err(|e| format_err!("Failed to add sub: {}", e)),
             EventNotifierRequest::Unsub(sub_id) => self
                 .handle_unsub(sub_id)
                 .map_err(|e| format_err!("Failed to remove sub: {}", e)),
         }
     }
 
+    async fn handle_sub(
+        &mut self,
+        conn_id: u64,
+        request: EventSubscribeRequest,
+    ) -> Result<(), failure::Error> {
+        match request {
+            EventSubscribeRequest::Transaction {
+                hash,
+                action,
+                subscriber,
+            } => {
+                self.handle_transaction_sub(conn_id, hash, action, subscriber)
+                    .await
+            }
+            EventSubscribeRequest::PriorityOp {
+                serial_id,
+                action,
+                subscriber,
+            } => {
+                self.handle_priority_op_sub(conn_id, serial_id, action, subscriber)
+                    .await
+            }
+            EventSubscribeRequest::Account {
+                address,
+                action,
+                replay_from,
+                subscriber,
+            } => {
+                self.handle_account_update_sub(conn_id, address, action, replay_from, subscriber)
+                    .await
+            }
+            EventSubscribeRequest::Block {
+                block_number,
+                action,
+                subscriber,
+            } => {
+                self.handle_block_sub(conn_id, block_number, action, subscriber)
+                    .await
+            }
+            EventSubscribeRequest::AccountToken {
+                address,
+                token_id,
+                action,
+                replay_from,
+                subscriber,
+            } => {
+                self.handle_account_token_sub(
+                    conn_id,
+                    address,
+                    token_id,
+                    action,
+                    replay_from,
+                    subscriber,
+                )
+                .await
+            }
+        }
+    }
+
     async fn get_executed_priority_operation(
         &mut self,
         serial_id: u32,
@@ -249,6 +394,7 @@ impl OperationNotifier {
 
     async fn handle_priority_op_sub(
         &mut self,
+        conn_id: u64,
         serial_id: u64,
         action: ActionType,
         sub: Subscriber<ETHOpInfoResp>,
@@ -328,10 +474,12 @@ impl OperationNotifier {
             .remove(&(serial_id, action))
             .unwrap_or_default();
         if subs.len() < MAX_LISTENERS_PER_ENTITY {
-            let sink = sub
-                .assign_id(sub_id.clone())
-                .map_err(|_| format_err!("SubIdAssign"))?;
-            subs.push(SubscriptionSender { id: sub_id, sink });
+            let sink = self.assign_kept_id(conn_id, sub, sub_id.clone())?;
+            subs.push(SubscriptionSender {
+                id: sub_id,
+                conn_id,
+                sink,
+            });
         };
         self.prior_op_subs.insert((serial_id, action), subs);
         Ok(())
@@ -368,6 +516,7 @@ impl OperationNotifier {
 
     async fn handle_transaction_sub(
         &mut self,
+        conn_id: u64,
         hash: TxHash,
         action: ActionType,
         sub: Subscriber<TransactionInfoResp>,
@@ -438,10 +587,8 @@ impl OperationNotifier {
             .remove(&(hash.clone(), action))
             .unwrap_or_default();
         if subs.len() < MAX_LISTENERS_PER_ENTITY {
-            let sink = sub
-                .assign_id(id.clone())
-                .map_err(|_| format_err!("SubIdAssign"))?;
-            subs.push(SubscriptionSender { id, sink });
+            let sink = self.assign_kept_id(conn_id, sub, id.clone())?;
+            subs.push(SubscriptionSender { id, conn_id, sink });
             trace!("tx sub added: {}", hash.to_string());
         }
         self.tx_subs.insert((hash, action), subs);
@@ -450,8 +597,10 @@ impl OperationNotifier {
 
     async fn handle_account_update_sub(
         &mut self,
+        conn_id: u64,
         address: Address,
         action: ActionType,
+        replay_from: Option<u64>,
         sub: Subscriber<ResponseAccountState>,
     ) -> Result<(), failure::Error> {
         let mut storage = self.db_pool.access_storage_fragile().await?;
@@ -491,18 +640,252 @@ impl OperationNotifier {
             .remove(&(account_id, action))
             .unwrap_or_default();
         if subs.len() < MAX_LISTENERS_PER_ENTITY {
-            let sink = sub
-                .assign_id(sub_id.clone())
-                .map_err(|_| format_err!("SubIdAssign"))?;
+            let sink = self.assign_kept_id(conn_id, sub, sub_id.clone())?;
 
-            self.send_once(&sink, account_state);
-            subs.push(SubscriptionSender { id: sub_id, sink });
+            // Missed states are replayed first, so the current one is the last received.
+            let mut states = match replay_from {
+                Some(last_seen_seq) => self.account_events.events_after(
+                    &(account_id, action),
+                    &SubscriptionCheckpoint { last_seen_seq },
+                ),
+                None => Vec::new(),
+            };
+            states.push(account_state);
+            self.send_in_order(&sink, states);
+            subs.push(SubscriptionSender {
+                id: sub_id,
+                conn_id,
+                sink,
+            });
+            self.account_events.track((account_id, action));
         }
 
         self.account_subs.insert((account_id, action), subs);
         Ok(())
     }
 
+    async fn handle_account_token_sub(
+        &mut self,
+        conn_id: u64,
+        address: Address,
+        token_id: TokenId,
+        action: ActionType,
+        replay_from: Option<u64>,
+        sub: Subscriber<AccountTokenBalanceResp>,
+    ) -> Result<(), failure::Error> {
+        let mut storage = self.db_pool.access_storage_fragile().await?;
+        let account_state = storage
+            .chain()
+            .account_schema()
+            .account_state_by_address(&address)
+            .await?;
+
+        let account_id = if let Some(id) = account_state.committed.as_ref().map(|(id, _)| id) {
+            *id
+        } else {
+            bail!("AccountId is unkwown");
+        };
+
+        // Both account and token are encoded into the unique part, so the sub can be found on unsub.
+        let sub_id = SubscriptionId::String(format!(
+            "{}/{}:{}/{}/{}",
+            ACCOUNT_TOKEN_SUB_PREFIX,
+            account_id,
+            token_id,
+            action.to_string(),
+            crypto_exports::rand::random::<u64>()
+        ));
+
+        let mut subs = self
+            .account_token_subs
+            .remove(&(account_id, token_id, action))
+            .unwrap_or_default();
+        if subs.len() < MAX_LISTENERS_PER_ENTITY {
+            let sink = self.assign_kept_id(conn_id, sub, sub_id.clone())?;
+            if let Some(last_seen_seq) = replay_from {
+                let missed_events = self.account_token_events.events_after(
+                    &(account_id, token_id, action),
+                    &SubscriptionCheckpoint { last_seen_seq },
+                );
+                self.send_in_order(&sink, missed_events);
+            }
+            subs.push(SubscriptionSender {
+                id: sub_id,
+                conn_id,
+                sink,
+            });
+            self.account_token_events
+                .track((account_id, token_id, action));
+        }
+        self.account_token_subs
+            .insert((account_id, token_id, action), subs);
+        Ok(())
+    }
+
+    /// Notifies the account token subscribers about the balances changed in the block.
+    /// Unlike the account subscriptions, these ones are kept after the notification.
+    fn handle_account_token_updates(
+        &mut self,
+        updates: &AccountUpdates,
+        action: ActionType,
+        block_number: BlockNumber,
+    ) {
+        if self.account_token_subs.is_empty() && self.account_token_events.is_empty() {
+            return;
+        }
+
+        // Balance may be updated several times within the block, so the balance before
+        // the first update is compared with the balance after the last one.
+        let mut balance_changes: BTreeMap<(AccountId, TokenId), (BigUint, BigUint)> =
+            BTreeMap::new();
+        for (account_id, update) in updates {
+            if let AccountUpdate::UpdateBalance {
+                balance_update: (token_id, old_balance, new_balance),
+                ..
+            } = update
+            {
+                balance_changes
+                    .entry((*account_id, *token_id))
+                    .or_insert_with(|| (old_balance.clone(), new_balance.clone()))
+                    .1 = new_balance.clone();
+            }
+        }
+
+        for ((account_id, token_id), (old_balance, new_balance)) in balance_changes {
+            if old_balance == new_balance {
+                continue;
+            }
+            let key = (account_id, token_id, action);
+            let subs = self.account_token_subs.get(&key);
+            if subs.is_none() && !self.account_token_events.is_tracked(&key) {
+                continue;
+            }
+            let resp = AccountTokenBalanceResp {
+                token_id,
+                old_balance,
+                new_balance,
+                block_number: i64::from(block_number),
+            };
+            for sub in subs.into_iter().flatten() {
+                self.send_once(&sub.sink, resp.clone());
+            }
+            self.account_token_events
+                .push(&key, u64::from(block_number), resp);
+        }
+    }
+
+    async fn get_block_details(
+        &mut self,
+        block_number: u64,
+    ) -> Result<Option<BlockDetails>, failure::Error> {
+        let mut storage = self.db_pool.access_storage_fragile().await?;
+        let block_details = storage
+            .chain()
+            .block_schema()
+            .find_block_by_height_or_hash(block_number.to_string())
+            .await;
+        // Search by the query may return the block with a matching hash, so the number is checked.
+        Ok(block_details.filter(|details| details.block_number == block_number as i64))
+    }
+
+    async fn handle_block_sub(
+        &mut self,
+        conn_id: u64,
+        block_number: u64,
+        action: ActionType,
+        sub: Subscriber<BlockInfoResp>,
+    ) -> Result<(), failure::Error> {
+        let sub_id = SubscriptionId::String(format!(
+            "{}/{}/{}/{}",
+            BLOCK_SUB_PREFIX,
+            block_number,
+            action.to_string(),
+            crypto_exports::rand::random::<u64>()
+        ));
+
+        // Maybe block was committed or verified already.
+        if let Some(details) = self.get_block_details(block_number).await? {
+            let already_happened = match action {
+                ActionType::COMMIT => true,
+                ActionType::VERIFY => details.verified_at.is_some(),
+            };
+            if already_happened {
+                let sink = sub
+                    .assign_id(sub_id)
+                    .map_err(|_| format_err!("SubIdAssign"))?;
+                self.send_once(
+                    &sink,
+                    BlockInfoResp {
+                        block_number: details.block_number,
+                        new_root_hash: fe_from_bytes(&details.new_state_root)?,
+                        committed_at: details.committed_at,
+                        verified_at: details.verified_at,
+                    },
+                );
+                return Ok(());
+            }
+        }
+
+        let mut subs = self
+            .block_subs
+            .remove(&(block_number, action))
+            .unwrap_or_default();
+        if subs.len() < MAX_LISTENERS_PER_ENTITY {
+            let sink = self.assign_kept_id(conn_id, sub, sub_id.clone())?;
+            subs.push(SubscriptionSender {
+                id: sub_id,
+                conn_id,
+                sink,
+            });
+        }
+        self.block_subs.insert((block_number, action), subs);
+        Ok(())
+    }
+
+    async fn handle_block_event(
+        &mut self,
+        op: &Operation,
+        action: ActionType,
+    ) -> Result<(), failure::Error> {
+        let block_number = u64::from(op.block.block_number);
+        if !self.block_subs.contains_key(&(block_number, action)) {
+            return Ok(());
+        }
+
+        // Commit operation is notified before the database transaction is committed,
+        // so the time of the notification is used if the stored one is not available yet.
+        let now = Utc::now();
+        let details = self.get_block_details(block_number).await?;
+        let committed_at = details
+            .as_ref()
+            .map(|details| details.committed_at)
+            .unwrap_or(now);
+        let verified_at = match action {
+            ActionType::COMMIT => None,
+            ActionType::VERIFY => Some(
+                details
+                    .and_then(|details| details.verified_at)
+                    .unwrap_or(now),
+            ),
+        };
+
+        let resp = BlockInfoResp {
+            block_number: block_number as i64,
+            new_root_hash: op.block.new_root_hash,
+            committed_at,
+            verified_at,
+        };
+        let subs = self
+            .block_subs
+            .remove(&(block_number, action))
+            .unwrap_or_default();
+        self.release_notified(&subs);
+        for sub in subs {
+            self.send_once(&sub.sink, resp.clone());
+        }
+        Ok(())
+    }
+
     fn handle_executed_operations(
         &mut self,
         ops: Vec<ExecutedOperations>,
@@ -524,6 +907,7 @@ impl OperationNotifier {
                                 verified: action == ActionType::VERIFY,
                             }),
                         };
+                        self.release_notified(&subs);
                         for sub in subs {
                             self.send_once(&sub.sink, rec.clone());
                         }
@@ -540,6 +924,7 @@ impl OperationNotifier {
                                 verified: action == ActionType::VERIFY,
                             }),
                         };
+                        self.release_notified(&subs);
                         for sub in subs {
                             self.send_once(&sub.sink, rec.clone());
                         }
@@ -564,18 +949,30 @@ impl OperationNotifier {
     async fn handle_new_block(&mut self, op: Operation) -> Result<(), failure::Error> {
         let action = op.action.get_type();
 
+        self.handle_block_event(&op, action)
+            .await
+            .map_err(|e| warn!("Failed to notify block subscribers: {}", e))
+            .unwrap_or_default();
+
         self.handle_executed_operations(
             op.block.block_transactions,
             action,
             op.block.block_number,
         )?;
 
+        self.handle_account_token_updates(&op.accounts_updated, action, op.block.block_number);
+
         let mut storage = self.db_pool.access_storage_fragile().await?;
 
         let updated_accounts = op.accounts_updated.iter().map(|(id, _)| *id);
 
         for id in updated_accounts {
-            if let Some(subs) = self.account_subs.remove(&(id, action)) {
+            let subs = self.account_subs.remove(&(id, action));
+            if let Some(subs) = &subs {
+                self.release_notified(subs);
+            }
+            if subs.is_some() || self.account_events.is_tracked(&(id, action)) {
+                let subs = subs.unwrap_or_default();
                 let stored_account = match action {
                     ActionType::COMMIT => {
                         storage
@@ -616,6 +1013,8 @@ impl OperationNotifier {
                 for sub in &subs {
                     self.send_once(&sub.sink, account.clone());
                 }
+                self.account_events
+                    .push(&(id, action), u64::from(op.block.block_number), account);
             }
         }
 
@@ -627,9 +1026,11 @@ pub fn start_sub_notifier(
     db_pool: ConnectionPool,
     mut new_block_stream: mpsc::Receiver<Operation>,
     mut subscription_stream: mpsc::Receiver<EventNotifierRequest>,
+    connection_subs: Arc<ConnectionSubscriptions>,
     mut executed_tx_stream: mpsc::Receiver<ExecutedOpsNotify>,
     state_keeper_requests: mpsc::Sender<StateKeeperRequest>,
     api_requests_caches_size: usize,
+    replay_buffer_size: usize,
 ) -> tokio::task::JoinHandle<()> {
     let tokens_cache = TokenDBCache::new(db_pool.clone());
 
@@ -640,9 +1041,14 @@ pub fn start_sub_notifier(
         tokens_cache,
         db_pool,
         state_keeper_requests,
+        connection_subs,
         tx_subs: BTreeMap::new(),
         prior_op_subs: BTreeMap::new(),
         account_subs: BTreeMap::new(),
+        block_subs: BTreeMap::new(),
+        account_token_subs: BTreeMap::new(),
+        account_events: ReplayBuffer::new(api_requests_caches_size, replay_buffer_size),
+        account_token_events: ReplayBuffer::new(api_requests_caches_size, replay_buffer_size),
     };
 
     tokio::spawn(async move {
@@ -676,3 +1082,131 @@ pub fn start_sub_notifier(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{compat::Stream01CompatExt, Stream};
+    use models::node::{
+        Deposit, DepositOp, ExecutedPriorityOp, FranklinOp, FranklinPriorityOp, PriorityOp,
+    };
+    use std::num::NonZeroU32;
+
+    fn executed_deposit(serial_id: u64) -> ExecutedOperations {
+        let deposit = Deposit {
+            from: Address::zero(),
+            token: 0,
+            amount: 1u32.into(),
+            to: Address::zero(),
+        };
+        ExecutedOperations::PriorityOp(Box::new(ExecutedPriorityOp {
+            priority_op: PriorityOp {
+                serial_id,
+                data: FranklinPriorityOp::Deposit(deposit.clone()),
+                deadline_block: 0,
+                eth_hash: Vec::new(),
+                eth_block: 0,
+            },
+            op: FranklinOp::Deposit(Box::new(DepositOp {
+                priority_op: deposit,
+                account_id: 0,
+            })),
+            block_index: 0,
+            created_at: Utc::now(),
+        }))
+    }
+
+    /// Subscribes the connection to the `action` of the priority op,
+    /// returning the stream of the notifications.
+    fn subscribe_priority_op(
+        notifier: &mut OperationNotifier,
+        conn_id: u64,
+        serial_id: u64,
+        action: ActionType,
+    ) -> impl Stream<Item = Result<String, ()>> + Unpin {
+        let (subscriber, _, notifications) = Subscriber::new_test("ethop");
+        let id = SubscriptionId::String(format!("{}/{}", serial_id, action.to_string()));
+        notifier
+            .connection_subs
+            .acquire(conn_id)
+            .expect("subscription limit reached");
+        let sink = notifier
+            .assign_kept_id(conn_id, subscriber, id.clone())
+            .expect("failed to assign subscription id");
+        notifier.connection_subs.settle(conn_id);
+        notifier.prior_op_subs.insert(
+            (serial_id, action),
+            vec![SubscriptionSender { id, conn_id, sink }],
+        );
+        notifications.compat()
+    }
+
+    /// Block of the next notification of the priority op subscription.
+    async fn notified_block(
+        notifications: &mut (impl Stream<Item = Result<String, ()>> + Unpin),
+    ) -> serde_json::Value {
+        let notification = notifications
+            .next()
+            .await
+            .expect("subscription is closed")
+            .expect("failed to receive notification");
+        let notification: serde_json::Value =
+            serde_json::from_str(&notification).expect("invalid notification");
+        notification["params"]["result"]["block"].clone()
+    }
+
+    #[tokio::test]
+    #[cfg_attr(not(feature = "db_test"), ignore)]
+    async fn priority_op_subscribers_are_notified_on_commit_and_verify_separately() {
+        let db_pool = ConnectionPool::new(Some(1)).await;
+        let (state_keeper_requests, _) = mpsc::channel(1);
+        let mut notifier = OperationNotifier {
+            cache_of_executed_priority_operations: LruCache::new(16),
+            cache_of_transaction_receipts: LruCache::new(16),
+            cache_of_blocks_info: LruCache::new(16),
+            tokens_cache: TokenDBCache::new(db_pool.clone()),
+            db_pool,
+            state_keeper_requests,
+            connection_subs: Arc::new(ConnectionSubscriptions::new(
+                NonZeroU32::new(100).unwrap(),
+                10,
+            )),
+            tx_subs: BTreeMap::new(),
+            prior_op_subs: BTreeMap::new(),
+            account_subs: BTreeMap::new(),
+            block_subs: BTreeMap::new(),
+            account_token_subs: BTreeMap::new(),
+            account_events: ReplayBuffer::new(16, 0),
+            account_token_events: ReplayBuffer::new(16, 0),
+        };
+        let mut committed = subscribe_priority_op(&mut notifier, 1, 7, ActionType::COMMIT);
+        let mut verified = subscribe_priority_op(&mut notifier, 1, 7, ActionType::VERIFY);
+        assert_eq!(notifier.connection_subs.subscriptions(1), 2);
+
+        // Inclusion of the op into the pending block notifies the commit subscribers only.
+        notifier
+            .handle_new_executed_batch(ExecutedOpsNotify {
+                operations: vec![executed_deposit(7)],
+                block_number: 5,
+            })
+            .unwrap();
+        let pending: Vec<_> = notifier.prior_op_subs.keys().cloned().collect();
+        assert_eq!(pending, vec![(7, ActionType::VERIFY)]);
+        // Notified subscriptions are over, so the connection is no longer charged for them.
+        assert_eq!(notifier.connection_subs.subscriptions(1), 1);
+        assert_eq!(
+            notified_block(&mut committed).await,
+            serde_json::json!({"blockNumber": 5, "committed": true, "verified": false})
+        );
+
+        notifier
+            .handle_executed_operations(vec![executed_deposit(7)], ActionType::VERIFY, 5)
+            .unwrap();
+        assert!(notifier.prior_op_subs.is_empty());
+        assert_eq!(notifier.connection_subs.subscriptions(1), 0);
+        assert_eq!(
+            notified_block(&mut verified).await,
+            serde_json::json!({"blockNumber": 5, "committed": true, "verified": true})
+        );
+    }
+}
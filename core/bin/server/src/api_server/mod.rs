@@ -23,9 +23,12 @@ use crate::{
 };
 
 mod admin_server;
+mod connection_subscriptions;
 mod event_notify;
 mod loggers;
+pub mod openapi;
 mod ops_counter;
+mod replay_buffer;
 mod rest;
 pub mod rpc_server;
 mod rpc_subscriptions;
@@ -34,7 +37,7 @@ mod rpc_subscriptions;
 pub fn start_api_server(
     op_notify_receiver: mpsc::Receiver<Operation>,
     connection_pool: ConnectionPool,
-    panic_notify: mpsc::Sender<bool>,
+    panic_notify: mpsc::Sender<Option<String>>,
     mempool_request_sender: mpsc::Sender<MempoolRequest>,
     executed_tx_receiver: mpsc::Receiver<ExecutedOpsNotify>,
     state_keeper_request_sender: mpsc::Sender<StateKeeperRequest>,
@@ -0,0 +1,69 @@
+//! OpenAPI specification of the REST API server.
+//!
+//! The specification is committed to `core/bin/server/openapi.json` and regenerated with
+//! the `gen_openapi` binary, CI checks that the committed one is up to date.
+
+// External uses
+use utoipa::OpenApi;
+// Workspace uses
+use models::{node::Token, NetworkStatus};
+use storage::{
+    chain::{
+        block::records::{BlockDetails, BlockTransactionItem},
+        operations_ext::records::{
+            PriorityOpReceiptResponse, TransactionsHistoryItem, TxByHashResponse, TxReceiptResponse,
+        },
+    },
+    prover::records::ProverRun,
+};
+// Local uses
+use super::{
+    rest,
+    rpc_server::types::{BlockInfo, ETHOpInfoResp, ResponseAccountState, TransactionInfoResp},
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        rest::handle_get_testnet_config,
+        rest::handle_get_network_status,
+        rest::handle_get_tokens,
+        rest::handle_get_account_transactions_history,
+        rest::handle_get_account_transactions_history_older_than,
+        rest::handle_get_account_transactions_history_newer_than,
+        rest::handle_get_executed_transaction_by_hash,
+        rest::handle_get_tx_by_hash,
+        rest::handle_get_priority_op_receipt,
+        rest::handle_get_transaction_by_id,
+        rest::handle_get_block_transactions,
+        rest::handle_get_block_by_id,
+        rest::handle_get_blocks,
+        rest::handle_block_explorer_search,
+        rest::handle_get_withdrawal_processing_time,
+    ),
+    components(schemas(
+        rest::TestnetConfigResponse,
+        rest::WithdrawalProcessingTimeResponse,
+        NetworkStatus,
+        Token,
+        TransactionsHistoryItem,
+        TxReceiptResponse,
+        PriorityOpReceiptResponse,
+        TxByHashResponse,
+        ProverRun,
+        BlockDetails,
+        BlockTransactionItem,
+        ResponseAccountState,
+        BlockInfo,
+        TransactionInfoResp,
+        ETHOpInfoResp,
+    ))
+)]
+struct ApiDoc;
+
+/// Returns the OpenAPI specification of the REST API as pretty-printed JSON.
+pub fn openapi_json() -> String {
+    ApiDoc::openapi()
+        .to_pretty_json()
+        .expect("failed to serialize OpenAPI specification")
+}
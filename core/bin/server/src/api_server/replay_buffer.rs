@@ -0,0 +1,146 @@
+//! Buffer of the recent subscription events, replayed to the WebSocket clients which
+//! re-subscribe after reconnecting, so the events that occurred during the gap are not lost.
+
+// Built-in uses
+use std::collections::VecDeque;
+use std::hash::Hash;
+// External uses
+use lru_cache::LruCache;
+
+/// The last event of the subscription stream seen by the client.
+///
+/// Events are sequenced by the numbers of the blocks they belong to, so the client can
+/// re-subscribe with the number of the last block it was notified about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionCheckpoint {
+    pub last_seen_seq: u64,
+}
+
+/// Ring buffers with the latest events of the subscription streams.
+///
+/// A stream is buffered once somebody subscribes to it, and is kept buffered after
+/// the subscribers are gone, so the reconnected client can catch up.
+/// Amount of buffered streams is limited, the least recently used ones are evicted.
+pub struct ReplayBuffer<K: Eq + Hash, T> {
+    streams: LruCache<K, VecDeque<(u64, T)>>,
+    events_per_stream: usize,
+}
+
+impl<K: Eq + Hash, T: Clone> ReplayBuffer<K, T> {
+    /// Creates the buffer, which is disabled if `events_per_stream` is zero.
+    pub fn new(max_streams: usize, events_per_stream: usize) -> Self {
+        Self {
+            streams: LruCache::new(max_streams),
+            events_per_stream,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.events_per_stream > 0
+    }
+
+    /// Starts buffering the events of the stream, if they're not buffered yet.
+    pub fn track(&mut self, key: K) {
+        if self.is_enabled() && !self.streams.contains_key(&key) {
+            self.streams.insert(key, VecDeque::new());
+        }
+    }
+
+    /// Returns `true` if no stream is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Returns `true` if the events of the stream are buffered.
+    pub fn is_tracked(&mut self, key: &K) -> bool {
+        self.streams.contains_key(key)
+    }
+
+    /// Stores the event of the buffered stream, dropping the oldest one if the buffer is full.
+    /// Events of the streams that are not tracked are ignored.
+    pub fn push(&mut self, key: &K, seq: u64, event: T) {
+        let events_per_stream = self.events_per_stream;
+        if let Some(events) = self.streams.get_mut(key) {
+            if events.len() == events_per_stream {
+                events.pop_front();
+            }
+            events.push_back((seq, event));
+        }
+    }
+
+    /// Returns the buffered events of the stream that come after the checkpoint, oldest first.
+    pub fn events_after(&mut self, key: &K, checkpoint: &SubscriptionCheckpoint) -> Vec<T> {
+        self.streams
+            .get_mut(key)
+            .map(|events| {
+                events
+                    .iter()
+                    .filter(|(seq, _)| *seq > checkpoint.last_seen_seq)
+                    .map(|(_, event)| event.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(last_seen_seq: u64) -> SubscriptionCheckpoint {
+        SubscriptionCheckpoint { last_seen_seq }
+    }
+
+    #[test]
+    fn replays_events_after_checkpoint() {
+        let mut buffer = ReplayBuffer::new(10, 10);
+        buffer.track(1);
+        for seq in 1..=5 {
+            buffer.push(&1, seq, seq * 10);
+        }
+
+        assert_eq!(buffer.events_after(&1, &checkpoint(3)), vec![40, 50]);
+        assert_eq!(buffer.events_after(&1, &checkpoint(5)), Vec::<u64>::new());
+        assert_eq!(buffer.events_after(&2, &checkpoint(0)), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn keeps_only_latest_events() {
+        let mut buffer = ReplayBuffer::new(10, 2);
+        buffer.track(1);
+        for seq in 1..=5 {
+            buffer.push(&1, seq, seq);
+        }
+
+        assert_eq!(buffer.events_after(&1, &checkpoint(0)), vec![4, 5]);
+    }
+
+    #[test]
+    fn ignores_untracked_streams() {
+        let mut buffer = ReplayBuffer::new(10, 2);
+        buffer.push(&1, 1, 1);
+        assert!(!buffer.is_tracked(&1));
+
+        let mut disabled_buffer = ReplayBuffer::new(10, 0);
+        disabled_buffer.track(1);
+        disabled_buffer.push(&1, 1, 1);
+        assert!(!disabled_buffer.is_tracked(&1));
+        assert_eq!(
+            disabled_buffer.events_after(&1, &checkpoint(0)),
+            Vec::<u64>::new()
+        );
+    }
+
+    #[test]
+    fn evicts_least_recently_used_streams() {
+        let mut buffer = ReplayBuffer::new(2, 2);
+        buffer.track(1);
+        buffer.track(2);
+        buffer.push(&1, 1, 1);
+        buffer.track(3);
+
+        assert!(buffer.is_tracked(&1));
+        assert!(!buffer.is_tracked(&2));
+        assert!(buffer.is_tracked(&3));
+    }
+}
@@ -124,7 +124,7 @@ impl AppState {
     }
 
     // Spawns future updating SharedNetworkStatus in the current `actix::System`
-    fn spawn_network_status_updater(&self, panic_notify: mpsc::Sender<bool>) {
+    fn spawn_network_status_updater(&self, panic_notify: mpsc::Sender<Option<String>>) {
         let state = self.clone();
 
         std::thread::Builder::new()
@@ -352,28 +352,43 @@ impl AppState {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, utoipa::ToSchema)]
 #[serde(rename_all = "camelCase")]
-struct TestnetConfigResponse {
+pub(super) struct TestnetConfigResponse {
     contract_address: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/testnet_config",
+    responses((status = 200, description = "Address of the zkSync contract", body = TestnetConfigResponse))
+)]
 async fn handle_get_testnet_config(data: web::Data<AppState>) -> ActixResult<HttpResponse> {
     let contract_address = data.contract_address.clone();
     Ok(HttpResponse::Ok().json(TestnetConfigResponse { contract_address }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/status",
+    responses((status = 200, description = "Current state of the network", body = NetworkStatus))
+)]
 async fn handle_get_network_status(data: web::Data<AppState>) -> ActixResult<HttpResponse> {
     let network_status = data.network_status.read();
     Ok(HttpResponse::Ok().json(network_status))
 }
 
-#[derive(Debug, Serialize)]
-struct WithdrawalProcessingTimeResponse {
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub(super) struct WithdrawalProcessingTimeResponse {
     normal: u64,
     fast: u64,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/withdrawal_processing_time",
+    responses((status = 200, description = "Expected withdrawal processing time in seconds", body = WithdrawalProcessingTimeResponse))
+)]
 async fn handle_get_withdrawal_processing_time(
     data: web::Data<AppState>,
 ) -> ActixResult<HttpResponse> {
@@ -398,6 +413,11 @@ struct AccountStateResponse {
     verified: Account,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/tokens",
+    responses((status = 200, description = "Tokens supported by the network, ordered by ID", body = [Token]))
+)]
 async fn handle_get_tokens(data: web::Data<AppState>) -> ActixResult<HttpResponse> {
     let mut storage = data.access_storage().await?;
     let tokens = storage
@@ -556,6 +576,19 @@ fn priority_op_to_tx_history(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/account/{address}/history/{offset}/{limit}",
+    params(
+        ("address" = String, path, description = "Address of the account"),
+        ("offset" = u64, path, description = "Amount of the newest transactions to skip"),
+        ("limit" = u64, path, description = "Maximum amount of transactions, at most 100"),
+    ),
+    responses(
+        (status = 200, description = "Transactions of the account, oldest first", body = [TransactionsHistoryItem]),
+        (status = 400, description = "Limit is too large"),
+    )
+)]
 async fn handle_get_account_transactions_history(
     data: web::Data<AppState>,
     request_path: web::Path<(Address, u64, u64)>,
@@ -644,9 +677,12 @@ async fn handle_get_account_transactions_history(
     Ok(HttpResponse::Ok().json(transactions_history))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 struct TxHistoryQuery {
+    /// ID of the transaction (`<block>,<index>`) to start from, `-` for the latest one.
     tx_id: Option<String>,
+    /// Maximum amount of transactions, at most 100.
     limit: Option<u64>,
 }
 
@@ -679,6 +715,15 @@ async fn parse_tx_id(data: &str, storage: &mut StorageProcessor<'_>) -> ActixRes
     Ok((parts[0], parts[1]))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/account/{address}/history/older_than",
+    params(("address" = String, path, description = "Address of the account"), TxHistoryQuery),
+    responses(
+        (status = 200, description = "Transactions of the account older than the given one", body = [TransactionsHistoryItem]),
+        (status = 400, description = "Invalid transaction ID or limit"),
+    )
+)]
 async fn handle_get_account_transactions_history_older_than(
     data: web::Data<AppState>,
     request_path: web::Path<Address>,
@@ -726,6 +771,15 @@ async fn handle_get_account_transactions_history_older_than(
     Ok(HttpResponse::Ok().json(transactions_history))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/account/{address}/history/newer_than",
+    params(("address" = String, path, description = "Address of the account"), TxHistoryQuery),
+    responses(
+        (status = 200, description = "Transactions of the account newer than the given one", body = [TransactionsHistoryItem]),
+        (status = 400, description = "Invalid transaction ID or limit"),
+    )
+)]
 async fn handle_get_account_transactions_history_newer_than(
     data: web::Data<AppState>,
     request_path: web::Path<Address>,
@@ -826,6 +880,15 @@ async fn handle_get_account_transactions_history_newer_than(
     Ok(HttpResponse::Ok().json(transactions_history))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/transactions/{tx_hash}",
+    params(("tx_hash" = String, path, description = "0x-prefixed hash of the transaction")),
+    responses(
+        (status = 200, description = "Receipt of the executed transaction, `null` if it's not executed", body = TxReceiptResponse),
+        (status = 400, description = "Invalid transaction hash"),
+    )
+)]
 async fn handle_get_executed_transaction_by_hash(
     data: web::Data<AppState>,
     tx_hash_hex: web::Path<String>,
@@ -845,6 +908,15 @@ async fn handle_get_executed_transaction_by_hash(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/transactions_all/{tx_hash}",
+    params(("tx_hash" = String, path, description = "Hash of the transaction or priority operation")),
+    responses(
+        (status = 200, description = "Transaction or priority operation, `null` if it's not found", body = TxByHashResponse),
+        (status = 400, description = "Invalid hash"),
+    )
+)]
 async fn handle_get_tx_by_hash(
     data: web::Data<AppState>,
     hash_hex_with_prefix: web::Path<String>,
@@ -902,6 +974,12 @@ async fn handle_get_tx_by_hash(
     Ok(HttpResponse::Ok().json(res))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/priority_operations/{pq_id}/",
+    params(("pq_id" = u32, path, description = "Serial ID of the priority operation")),
+    responses((status = 200, description = "Receipt of the priority operation", body = PriorityOpReceiptResponse))
+)]
 async fn handle_get_priority_op_receipt(
     data: web::Data<AppState>,
     id: web::Path<u32>,
@@ -912,6 +990,18 @@ async fn handle_get_priority_op_receipt(
     Ok(HttpResponse::Ok().json(receipt))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/blocks/{block_id}/transactions/{tx_id}",
+    params(
+        ("block_id" = u32, path, description = "Number of the block"),
+        ("tx_id" = u32, path, description = "Index of the operation in the block"),
+    ),
+    responses(
+        (status = 200, description = "Operation executed in the block"),
+        (status = 404, description = "Operation is not found"),
+    )
+)]
 async fn handle_get_transaction_by_id(
     data: web::Data<AppState>,
     path: web::Path<(u32, u32)>,
@@ -927,12 +1017,24 @@ async fn handle_get_transaction_by_id(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 struct HandleBlocksQuery {
+    /// Number of the newest block to return.
     max_block: Option<u32>,
+    /// Maximum amount of blocks, at most 100.
     limit: Option<u32>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/blocks",
+    params(HandleBlocksQuery),
+    responses(
+        (status = 200, description = "Blocks, newest first", body = [BlockDetails]),
+        (status = 400, description = "Limit is too large"),
+    )
+)]
 async fn handle_get_blocks(
     data: web::Data<AppState>,
     query: web::Query<HandleBlocksQuery>,
@@ -961,6 +1063,15 @@ async fn handle_get_blocks(
     Ok(HttpResponse::Ok().json(resp))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/blocks/{block_id}",
+    params(("block_id" = u32, path, description = "Number of the block")),
+    responses(
+        (status = 200, description = "Block details", body = BlockDetails),
+        (status = 404, description = "Block is not found"),
+    )
+)]
 async fn handle_get_block_by_id(
     data: web::Data<AppState>,
     block_id: web::Path<u32>,
@@ -974,6 +1085,12 @@ async fn handle_get_block_by_id(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/blocks/{block_id}/transactions",
+    params(("block_id" = u32, path, description = "Number of the block")),
+    responses((status = 200, description = "Transactions executed in the block", body = [storage::chain::block::records::BlockTransactionItem]))
+)]
 async fn handle_get_block_transactions(
     data: web::Data<AppState>,
     path: web::Path<u32>,
@@ -995,11 +1112,22 @@ async fn handle_get_block_transactions(
     Ok(HttpResponse::Ok().json(txs))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 struct BlockExplorerSearchQuery {
+    /// Number of the block, its root hash, or hash of the commit or verify transaction.
     query: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v0.1/search",
+    params(BlockExplorerSearchQuery),
+    responses(
+        (status = 200, description = "Block with the given number, root hash or transaction hash", body = BlockDetails),
+        (status = 404, description = "Block is not found"),
+    )
+)]
 async fn handle_block_explorer_search(
     data: web::Data<AppState>,
     query: web::Query<BlockExplorerSearchQuery>,
@@ -1087,7 +1215,7 @@ pub(super) fn start_server_thread_detached(
     contract_address: H160,
     mempool_request_sender: mpsc::Sender<MempoolRequest>,
     eth_watcher_request_sender: mpsc::Sender<EthWatchRequest>,
-    panic_notify: mpsc::Sender<bool>,
+    panic_notify: mpsc::Sender<Option<String>>,
     config_options: ConfigurationOptions,
 ) {
     std::thread::Builder::new()
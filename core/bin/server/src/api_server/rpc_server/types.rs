@@ -1,14 +1,16 @@
 use std::collections::HashMap;
 // External uses
+use chrono::{DateTime, Utc};
 use jsonrpc_core::{Error, Result};
 use num::{BigUint, ToPrimitive};
 // Workspace uses
 use models::{
     node::{
-        tx::TxEthSignature, Account, AccountId, Address, FranklinPriorityOp, FranklinTx, Nonce,
-        PriorityOp, PubKeyHash,
+        tx::TxEthSignature, Account, AccountId, Address, Fr, FranklinPriorityOp, FranklinTx, Nonce,
+        PriorityOp, PubKeyHash, TokenId,
     },
     primitives::{BigUintSerdeAsRadix10Str, BigUintSerdeWrapper},
+    serialization::FrSerde,
 };
 // Local uses
 use crate::utils::token_db_cache::TokenDBCache;
@@ -20,11 +22,13 @@ pub struct TxWithSignature {
     pub signature: Option<TxEthSignature>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, utoipa::ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ResponseAccountState {
+    #[schema(value_type = HashMap<String, String>)]
     pub balances: HashMap<String, BigUintSerdeWrapper>,
     pub nonce: Nonce,
+    #[schema(value_type = String)]
     pub pub_key_hash: PubKeyHash,
 }
 
@@ -106,6 +110,18 @@ impl DepositingAccountBalances {
     }
 }
 
+/// Change of the account balance for a single token, sent to the account token subscribers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountTokenBalanceResp {
+    pub token_id: TokenId,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub old_balance: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub new_balance: BigUint,
+    pub block_number: i64,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountInfoResp {
@@ -116,7 +132,7 @@ pub struct AccountInfoResp {
     pub verified: ResponseAccountState,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockInfo {
     pub block_number: i64,
@@ -124,8 +140,20 @@ pub struct BlockInfo {
     pub verified: bool,
 }
 
+/// Information about the committed or verified block, sent to the block subscribers.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
+pub struct BlockInfoResp {
+    pub block_number: i64,
+    #[serde(with = "FrSerde")]
+    pub new_root_hash: Fr,
+    pub committed_at: DateTime<Utc>,
+    /// Time of the verify operation confirmation on Ethereum, `None` if the block is not verified yet.
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct TransactionInfoResp {
     pub executed: bool,
     pub success: Option<bool>,
@@ -133,7 +161,7 @@ pub struct TransactionInfoResp {
     pub block: Option<BlockInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ETHOpInfoResp {
     pub executed: bool,
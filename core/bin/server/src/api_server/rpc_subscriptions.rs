@@ -1,37 +1,123 @@
 #![allow(clippy::needless_return)]
 
 // Built-in deps
-use std::sync::Arc;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
 // External uses
 use crate::eth_watch::EthWatchRequest;
 use futures::channel::mpsc;
-use jsonrpc_core::{MetaIoHandler, Result};
+use jsonrpc_core::{Error, ErrorCode, MetaIoHandler, Metadata, Result};
 use jsonrpc_derive::rpc;
-use jsonrpc_pubsub::{typed::Subscriber, PubSubHandler, Session, SubscriptionId};
-use jsonrpc_ws_server::RequestContext;
+use jsonrpc_pubsub::{typed::Subscriber, PubSubHandler, PubSubMetadata, Session, SubscriptionId};
+use jsonrpc_ws_server::{
+    ws::{self, CloseCode},
+    RequestContext,
+};
 use web3::types::Address;
 // Workspace uses
 use models::{
     config_options::{ConfigurationOptions, ThreadPanicNotify},
-    node::tx::TxHash,
+    node::{tx::TxHash, TokenId},
     ActionType, Operation,
 };
 use storage::ConnectionPool;
 // Local uses
 use crate::fee_ticker::TickerRequest;
 use crate::{
+    api_server::connection_subscriptions::ConnectionSubscriptions,
     api_server::event_notify::{start_sub_notifier, EventNotifierRequest, EventSubscribeRequest},
-    api_server::rpc_server::types::{ETHOpInfoResp, ResponseAccountState, TransactionInfoResp},
+    api_server::rpc_server::types::{
+        AccountTokenBalanceResp, BlockInfoResp, ETHOpInfoResp, ResponseAccountState,
+        TransactionInfoResp,
+    },
     mempool::MempoolRequest,
     signature_checker::VerifyTxSignatureRequest,
     state_keeper::{ExecutedOpsNotify, StateKeeperRequest},
     utils::current_zksync_info::CurrentZksyncInfo,
 };
 
+/// JSON-RPC error code returned when the connection exceeds its subscription limits.
+const SUBSCRIPTION_LIMIT_ERROR_CODE: i64 = -32099;
+
+/// Metadata of the WebSocket connection.
+#[derive(Clone)]
+pub struct WsConnection {
+    session: Arc<Session>,
+    id: u64,
+}
+
+impl Metadata for WsConnection {}
+
+impl PubSubMetadata for WsConnection {
+    fn session(&self) -> Option<Arc<Session>> {
+        Some(self.session.clone())
+    }
+}
+
+/// Amount of open connections per client IP address.
+type ConnectionsPerIp = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+thread_local! {
+    /// Client IP address of the handshake that is being processed.
+    ///
+    /// Neither the TCP peer address nor the handshake headers are available to the meta
+    /// extractor, and `RequestContext` has no room for the data of the request middleware.
+    /// So the middleware stores the address here and the meta extractor takes it right after:
+    /// both are called one after another on the same WebSocket event loop thread.
+    static HANDSHAKE_CLIENT_IP: Cell<Option<IpAddr>> = Cell::new(None);
+}
+
+/// Extracts the client IP address appended to `X-Forwarded-For` by the reverse proxy.
+/// Only the last address is taken, the preceding ones are sent by the client and may be forged.
+fn forwarded_client_ip(request: &ws::Request) -> Option<IpAddr> {
+    let header = std::str::from_utf8(request.header("x-forwarded-for")?).ok()?;
+    let addr = header.rsplit(',').next()?.trim();
+    addr.parse::<IpAddr>()
+        .ok()
+        .or_else(|| addr.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+/// Accounts a new connection from `ip`, returns `false` if the per-IP limit is reached.
+fn acquire_ip_slot(connections_per_ip: &ConnectionsPerIp, ip: IpAddr, limit: usize) -> bool {
+    let mut connections = connections_per_ip
+        .lock()
+        .expect("connections lock poisoned");
+    let count = connections.entry(ip).or_insert(0);
+    if *count >= limit {
+        return false;
+    }
+    *count += 1;
+    true
+}
+
+fn release_ip_slot(connections_per_ip: &ConnectionsPerIp, ip: IpAddr) {
+    let mut connections = connections_per_ip
+        .lock()
+        .expect("connections lock poisoned");
+    if let Some(count) = connections.get_mut(&ip) {
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            connections.remove(&ip);
+        }
+    }
+}
+
+/// Every subscription accepts the optional `replay_from` parameter: the number of the last block
+/// the client was notified about before reconnecting. Events of the account streams that occurred
+/// after this block are replayed to the new subscription. Other subscriptions are notified right
+/// away if their event has already happened, so they ignore the parameter.
+///
+/// Unsubscribing returns `false` if the connection has no such subscription, e.g. it has already
+/// been notified and ended.
 #[rpc]
 pub trait RpcPubSub {
     type Metadata;
 
+    /// `replay_from` is ignored, the subscriber is notified right away if the transaction
+    /// has already been executed.
     #[pubsub(subscription = "tx", subscribe, name = "tx_subscribe", alias("tx_sub"))]
     fn subscribe_tx(
         &self,
@@ -39,6 +125,7 @@ pub trait RpcPubSub {
         subscriber: Subscriber<TransactionInfoResp>,
         hash: TxHash,
         action_type: ActionType,
+        replay_from: Option<u64>,
     );
     #[pubsub(subscription = "tx", unsubscribe, name = "tx_unsubscribe")]
     fn unsubscribe_tx(
@@ -47,6 +134,8 @@ pub trait RpcPubSub {
         subscription: SubscriptionId,
     ) -> Result<bool>;
 
+    /// `replay_from` is ignored, the subscriber is notified right away if the priority operation
+    /// has already been executed.
     #[pubsub(
         subscription = "eth_op",
         subscribe,
@@ -59,6 +148,7 @@ pub trait RpcPubSub {
         subscriber: Subscriber<ETHOpInfoResp>,
         serial_id: u64,
         action_type: ActionType,
+        replay_from: Option<u64>,
     );
     #[pubsub(subscription = "eth_op", unsubscribe, name = "ethop_unsubscribe")]
     fn unsubscribe_ethop(
@@ -79,6 +169,7 @@ pub trait RpcPubSub {
         subscriber: Subscriber<ResponseAccountState>,
         addr: Address,
         action_type: ActionType,
+        replay_from: Option<u64>,
     );
     #[pubsub(subscription = "account", unsubscribe, name = "account_unsubscribe")]
     fn unsubscribe_account(
@@ -86,98 +177,252 @@ pub trait RpcPubSub {
         meta: Option<Self::Metadata>,
         subscription: SubscriptionId,
     ) -> Result<bool>;
+
+    #[pubsub(
+        subscription = "account_token",
+        subscribe,
+        name = "account_token_subscribe",
+        alias("account_token_sub")
+    )]
+    fn subscribe_account_token(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<AccountTokenBalanceResp>,
+        addr: Address,
+        token_id: TokenId,
+        action_type: ActionType,
+        replay_from: Option<u64>,
+    );
+    #[pubsub(
+        subscription = "account_token",
+        unsubscribe,
+        name = "account_token_unsubscribe"
+    )]
+    fn unsubscribe_account_token(
+        &self,
+        meta: Option<Self::Metadata>,
+        subscription: SubscriptionId,
+    ) -> Result<bool>;
+
+    /// `replay_from` is ignored, the subscriber is notified right away if the block has already
+    /// been committed or verified.
+    #[pubsub(
+        subscription = "block",
+        subscribe,
+        name = "block_subscribe",
+        alias("block_sub")
+    )]
+    fn subscribe_block(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<BlockInfoResp>,
+        block_number: u64,
+        action_type: ActionType,
+        replay_from: Option<u64>,
+    );
+    #[pubsub(subscription = "block", unsubscribe, name = "block_unsubscribe")]
+    fn unsubscribe_block(
+        &self,
+        meta: Option<Self::Metadata>,
+        subscription: SubscriptionId,
+    ) -> Result<bool>;
 }
 
 impl RpcPubSub for RpcSubApp {
-    type Metadata = Arc<Session>;
+    type Metadata = WsConnection;
 
     // subscribe - sub id, sink
     // unsub - sub id
 
     fn subscribe_tx(
         &self,
-        _meta: Self::Metadata,
+        meta: Self::Metadata,
         subscriber: Subscriber<TransactionInfoResp>,
         hash: TxHash,
         action: ActionType,
+        _replay_from: Option<u64>,
     ) {
-        self.event_sub_sender
-            .clone()
-            .try_send(EventNotifierRequest::Sub(
-                EventSubscribeRequest::Transaction {
-                    hash,
-                    action,
-                    subscriber,
-                },
-            ))
-            .unwrap_or_default();
+        if let Err(err) = self.subscriptions.acquire(meta.id) {
+            subscriber
+                .reject(subscription_limit_error(err))
+                .unwrap_or_default();
+            return;
+        }
+        self.send_subscription(
+            meta.id,
+            EventSubscribeRequest::Transaction {
+                hash,
+                action,
+                subscriber,
+            },
+        );
     }
-    fn unsubscribe_tx(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
-        self.event_sub_sender
-            .clone()
-            .try_send(EventNotifierRequest::Unsub(id))
-            .unwrap_or_default();
-        Ok(true)
+    fn unsubscribe_tx(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(meta, id)
     }
 
     fn subscribe_ethop(
         &self,
-        _meta: Self::Metadata,
+        meta: Self::Metadata,
         subscriber: Subscriber<ETHOpInfoResp>,
         serial_id: u64,
         action: ActionType,
+        _replay_from: Option<u64>,
     ) {
-        self.event_sub_sender
-            .clone()
-            .try_send(EventNotifierRequest::Sub(
-                EventSubscribeRequest::PriorityOp {
-                    serial_id,
-                    action,
-                    subscriber,
-                },
-            ))
-            .unwrap_or_default();
+        if let Err(err) = self.subscriptions.acquire(meta.id) {
+            subscriber
+                .reject(subscription_limit_error(err))
+                .unwrap_or_default();
+            return;
+        }
+        self.send_subscription(
+            meta.id,
+            EventSubscribeRequest::PriorityOp {
+                serial_id,
+                action,
+                subscriber,
+            },
+        );
     }
-    fn unsubscribe_ethop(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
-        self.event_sub_sender
-            .clone()
-            .try_send(EventNotifierRequest::Unsub(id))
-            .unwrap_or_default();
-        Ok(true)
+    fn unsubscribe_ethop(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(meta, id)
     }
 
     fn subscribe_account(
         &self,
-        _meta: Self::Metadata,
+        meta: Self::Metadata,
         subscriber: Subscriber<ResponseAccountState>,
         address: Address,
         action: ActionType,
+        replay_from: Option<u64>,
     ) {
-        self.event_sub_sender
-            .clone()
-            .try_send(EventNotifierRequest::Sub(EventSubscribeRequest::Account {
+        if let Err(err) = self.subscriptions.acquire(meta.id) {
+            subscriber
+                .reject(subscription_limit_error(err))
+                .unwrap_or_default();
+            return;
+        }
+        self.send_subscription(
+            meta.id,
+            EventSubscribeRequest::Account {
                 address,
                 action,
+                replay_from,
                 subscriber,
-            }))
-            .unwrap_or_default();
+            },
+        );
     }
 
     fn unsubscribe_account(
         &self,
-        _meta: Option<Self::Metadata>,
+        meta: Option<Self::Metadata>,
         id: SubscriptionId,
     ) -> Result<bool> {
-        self.event_sub_sender
-            .clone()
-            .try_send(EventNotifierRequest::Unsub(id))
-            .unwrap_or_default();
-        Ok(true)
+        self.unsubscribe(meta, id)
+    }
+
+    fn subscribe_account_token(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<AccountTokenBalanceResp>,
+        address: Address,
+        token_id: TokenId,
+        action: ActionType,
+        replay_from: Option<u64>,
+    ) {
+        if let Err(err) = self.subscriptions.acquire(meta.id) {
+            subscriber
+                .reject(subscription_limit_error(err))
+                .unwrap_or_default();
+            return;
+        }
+        self.send_subscription(
+            meta.id,
+            EventSubscribeRequest::AccountToken {
+                address,
+                token_id,
+                action,
+                replay_from,
+                subscriber,
+            },
+        );
+    }
+
+    fn unsubscribe_account_token(
+        &self,
+        meta: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool> {
+        self.unsubscribe(meta, id)
+    }
+
+    fn subscribe_block(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<BlockInfoResp>,
+        block_number: u64,
+        action: ActionType,
+        _replay_from: Option<u64>,
+    ) {
+        if let Err(err) = self.subscriptions.acquire(meta.id) {
+            subscriber
+                .reject(subscription_limit_error(err))
+                .unwrap_or_default();
+            return;
+        }
+        self.send_subscription(
+            meta.id,
+            EventSubscribeRequest::Block {
+                block_number,
+                action,
+                subscriber,
+            },
+        );
+    }
+
+    fn unsubscribe_block(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        self.unsubscribe(meta, id)
     }
 }
 
 struct RpcSubApp {
     event_sub_sender: mpsc::Sender<EventNotifierRequest>,
+    /// Subscriptions of the open connections, shared with the event notifier.
+    subscriptions: Arc<ConnectionSubscriptions>,
+}
+
+impl RpcSubApp {
+    /// Passes the accounted subscription of the connection to the event notifier.
+    fn send_subscription(&self, conn_id: u64, request: EventSubscribeRequest) {
+        if self
+            .event_sub_sender
+            .clone()
+            .try_send(EventNotifierRequest::Sub { conn_id, request })
+            .is_err()
+        {
+            self.subscriptions.settle(conn_id);
+        }
+    }
+
+    /// Ends the subscription if the connection has it, otherwise returns `false`.
+    fn unsubscribe(&self, meta: Option<WsConnection>, id: SubscriptionId) -> Result<bool> {
+        let known = meta.map_or(false, |meta| self.subscriptions.release(meta.id, &id));
+        if known {
+            self.event_sub_sender
+                .clone()
+                .try_send(EventNotifierRequest::Unsub(id))
+                .unwrap_or_default();
+        }
+        Ok(known)
+    }
+}
+
+fn subscription_limit_error(message: String) -> Error {
+    Error {
+        code: ErrorCode::ServerError(SUBSCRIPTION_LIMIT_ERROR_CODE),
+        message,
+        data: None,
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -191,7 +436,7 @@ pub fn start_ws_server(
     sign_verify_request_sender: mpsc::Sender<VerifyTxSignatureRequest>,
     eth_watcher_request_sender: mpsc::Sender<EthWatchRequest>,
     ticker_request_sender: mpsc::Sender<TickerRequest>,
-    panic_notify: mpsc::Sender<bool>,
+    panic_notify: mpsc::Sender<Option<String>>,
     each_cache_size: usize,
     current_zksync_info: CurrentZksyncInfo,
 ) {
@@ -199,14 +444,21 @@ pub fn start_ws_server(
     let addr = config_options.json_rpc_ws_server_address;
 
     let (event_sub_sender, event_sub_receiver) = mpsc::channel(2048);
+    let subscriptions = Arc::new(ConnectionSubscriptions::new(
+        NonZeroU32::new(config_options.subscriptions_per_conn_per_sec)
+            .expect("subscriptions per connection per second must be positive"),
+        config_options.max_subscriptions_per_conn,
+    ));
 
     start_sub_notifier(
         db_pool.clone(),
         op_recv,
         event_sub_receiver,
+        subscriptions.clone(),
         executed_tx_receiver,
         state_keeper_request_sender.clone(),
         each_cache_size,
+        config_options.ws_replay_buffer_size,
     );
 
     std::thread::Builder::new()
@@ -235,7 +487,10 @@ pub fn start_ws_server(
             );
             req_rpc_app.extend(&mut io);
 
-            let rpc_sub_app = RpcSubApp { event_sub_sender };
+            let rpc_sub_app = RpcSubApp {
+                event_sub_sender,
+                subscriptions: subscriptions.clone(),
+            };
 
             io.extend_with(rpc_sub_app.to_delegate());
 
@@ -245,12 +500,52 @@ pub fn start_ws_server(
                 .build()
                 .expect("failed to build ws executor");
 
+            // Connections with unknown client address are only limited by the total amount.
+            let connections_per_ip: ConnectionsPerIp = Default::default();
+            let max_connections_per_ip = config_options.ws_max_connections_per_ip;
+            let behind_proxy = config_options.ws_behind_proxy;
+
             let server = jsonrpc_ws_server::ServerBuilder::with_meta_extractor(
                 io,
-                |context: &RequestContext| Arc::new(Session::new(context.sender())),
+                move |context: &RequestContext| {
+                    // Metadata is extracted once per connection, so limits are dropped along with it.
+                    let session = Arc::new(Session::new(context.sender()));
+                    let id = context.session_id as u64;
+                    let subscriptions = subscriptions.clone();
+
+                    let client_ip = HANDSHAKE_CLIENT_IP.with(Cell::take);
+                    let client_ip = client_ip.filter(|&ip| {
+                        if acquire_ip_slot(&connections_per_ip, ip, max_connections_per_ip) {
+                            return true;
+                        }
+                        log::debug!("Too many WebSocket connections from {}", ip);
+                        if let Err(e) = context.out.close(CloseCode::Again) {
+                            log::warn!("Failed to close WebSocket connection: {}", e);
+                        }
+                        false
+                    });
+
+                    let connections_per_ip = connections_per_ip.clone();
+                    session.on_drop(move || {
+                        subscriptions.remove_connection(id);
+                        if let Some(ip) = client_ip {
+                            release_ip_slot(&connections_per_ip, ip);
+                        }
+                    });
+                    WsConnection { session, id }
+                },
             )
-            .request_middleware(super::loggers::ws_rpc::request_middleware)
-            .max_connections(1000)
+            .request_middleware(move |request: &ws::Request| {
+                // Headers of the direct clients can't be trusted.
+                let client_ip = if behind_proxy {
+                    forwarded_client_ip(request)
+                } else {
+                    None
+                };
+                HANDSHAKE_CLIENT_IP.with(|cell| cell.set(client_ip));
+                super::loggers::ws_rpc::request_middleware(request)
+            })
+            .max_connections(config_options.ws_max_connections)
             .event_loop_executor(task_executor.executor())
             .start(&addr)
             .expect("Unable to start RPC ws server");
@@ -259,3 +554,40 @@ pub fn start_ws_server(
         })
         .expect("JSON RPC ws thread");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(headers: &str) -> ws::Request {
+        let request = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+             Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n{}\r\n",
+            headers
+        );
+        ws::Request::parse(request.as_bytes())
+            .expect("invalid handshake")
+            .expect("incomplete handshake")
+    }
+
+    #[test]
+    fn forwarded_client_ip_is_appended_by_proxy() {
+        let request = handshake("X-Forwarded-For: 10.0.0.1, 192.168.1.7\r\n");
+        assert_eq!(
+            forwarded_client_ip(&request),
+            Some("192.168.1.7".parse().unwrap())
+        );
+
+        let request = handshake("X-Forwarded-For: 192.168.1.7:4321\r\n");
+        assert_eq!(
+            forwarded_client_ip(&request),
+            Some("192.168.1.7".parse().unwrap())
+        );
+
+        assert_eq!(forwarded_client_ip(&handshake("")), None);
+        assert_eq!(
+            forwarded_client_ip(&handshake("X-Forwarded-For: 10.0.0.1, unknown\r\n")),
+            None
+        );
+    }
+}
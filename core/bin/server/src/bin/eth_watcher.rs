@@ -1,5 +1,7 @@
+use eth_client::{Web3FailoverTransport, Web3UrlSelector};
 use futures::{channel::mpsc, SinkExt};
 use log::*;
+use models::config_options::ConfigurationOptions;
 use server::eth_watch::{EthWatch, EthWatchRequest};
 use std::time::Duration;
 use tokio::{runtime::Runtime, time};
@@ -9,12 +11,10 @@ fn main() {
 
     env_logger::init();
     info!("ETH watcher started");
-    let web3_url = std::env::var("WEB3_URL").expect("WEB3_URL env var not found");
-    let contract_address = std::env::var("CONTRACT_ADDR").expect("CONTRACT_ADDR env var not found")
-        [2..]
-        .parse()
-        .expect("Failed to parse CONTRACT_ADDR");
-    let (web3_event_loop_handle, transport) = web3::transports::Http::new(&web3_url).unwrap();
+    let config_options = ConfigurationOptions::from_env();
+    let contract_address = config_options.contract_eth_addr;
+    let (web3_event_loop_handle, transport) =
+        Web3FailoverTransport::new(Web3UrlSelector::from_options(&config_options)).unwrap();
     let web3 = web3::Web3::new(transport);
 
     let (eth_req_sender, eth_req_receiver) = mpsc::channel(256);
@@ -0,0 +1,7 @@
+//! Prints the OpenAPI specification of the REST API server to stdout.
+//!
+//! Usage: `cargo run --bin gen_openapi > core/bin/server/openapi.json`
+
+fn main() {
+    println!("{}", server::api_server::openapi::openapi_json());
+}
@@ -4,11 +4,11 @@ use failure::ensure;
 use futures::compat::Future01CompatExt;
 use web3::contract::tokens::Tokenize;
 use web3::contract::Options;
-use web3::transports::{EventLoopHandle, Http};
+use web3::transports::EventLoopHandle;
 use web3::types::{TransactionReceipt, H256, U256};
 // Workspace uses
 use super::ExecutedTxStatus;
-use eth_client::{ETHClient, SignedCallResult};
+use eth_client::{ETHClient, SignedCallResult, Web3FailoverTransport, Web3UrlSelector};
 use models::abi::zksync_contract;
 use models::config_options::ConfigurationOptions;
 use std::time::Duration;
@@ -57,18 +57,19 @@ pub(super) trait EthereumInterface {
     ) -> Result<SignedCallResult, failure::Error>;
 }
 
-/// Wrapper over `ETHClient` using `Http` transport.
+/// Wrapper over `ETHClient` using `Http` transports to the configured nodes with the failover.
 /// Supposed to be an actual Ethereum intermediator for the `ETHSender`.
 #[derive(Debug)]
 pub struct EthereumHttpClient {
-    eth_client: ETHClient<Http>,
+    eth_client: ETHClient<Web3FailoverTransport>,
     // We have to prevent handle from drop, since it will cause event loop termination.
     _event_loop: EventLoopHandle,
 }
 
 impl EthereumHttpClient {
     pub fn new(options: &ConfigurationOptions) -> Result<Self, failure::Error> {
-        let (_event_loop, transport) = Http::new(&options.web3_url)?;
+        let (_event_loop, transport) =
+            Web3FailoverTransport::new(Web3UrlSelector::from_options(options))?;
 
         let eth_client = ETHClient::new(
             transport,
@@ -26,6 +26,7 @@ use web3::{
     Transport, Web3,
 };
 // Workspace deps
+use eth_client::{Web3FailoverTransport, Web3UrlSelector};
 use models::{
     abi::{eip1271_contract, zksync_contract},
     config_options::ConfigurationOptions,
@@ -533,7 +534,7 @@ pub fn start_eth_watch(
     eth_req_receiver: mpsc::Receiver<EthWatchRequest>,
 ) -> JoinHandle<()> {
     let (web3_event_loop_handle, transport) =
-        web3::transports::Http::new(&config_options.web3_url).unwrap();
+        Web3FailoverTransport::new(Web3UrlSelector::from_options(&config_options)).unwrap();
     let web3 = web3::Web3::new(transport);
 
     let eth_watch = EthWatch::new(
@@ -8,7 +8,7 @@ use tokio::{runtime::Builder, task::JoinHandle};
 use web3::types::H160;
 // Workspace uses
 use models::{
-    config_options::{AdminServerOptions, ConfigurationOptions, ProverOptions},
+    config_options::{install_panic_hook, AdminServerOptions, ConfigurationOptions, ProverOptions},
     node::{
         config::OBSERVER_MODE_PULL_INTERVAL,
         tokens::{get_genesis_token_list, Token},
@@ -28,13 +28,19 @@ use server::{
     leader_election,
     mempool::run_mempool_task,
     observer_mode,
-    prover_server::start_prover_server,
+    prover_server::{start_prover_server, RoundsIntervalScheduler},
     state_keeper::{start_state_keeper, PlasmaStateKeeper},
     utils::current_zksync_info::CurrentZksyncInfo,
 };
 
 fn main() {
     env_logger::init();
+    install_panic_hook();
+    // Export the tracing spans of the prover requests, if the collector is configured.
+    let _telemetry_guard = prover::telemetry::tracer_from_env("server").map(|(tracer, guard)| {
+        prover::telemetry::start_tracing(tracer);
+        guard
+    });
 
     let config_opts = ConfigurationOptions::from_env();
     let admin_server_opts = AdminServerOptions::from_env();
@@ -150,7 +156,7 @@ fn main() {
             let stop_signal_sender = RefCell::new(stop_signal_sender.clone());
             ctrlc::set_handler(move || {
                 let mut sender = stop_signal_sender.borrow_mut();
-                block_on(sender.send(true)).expect("crtlc signal send");
+                block_on(sender.send(None)).expect("crtlc signal send");
             })
             .expect("Error setting Ctrl-C handler");
         }
@@ -219,12 +225,37 @@ fn main() {
         );
 
         let prover_options = ProverOptions::from_env();
+        let jobs_progress = server::prover_server::JobsProgress::new();
+        let prover_registry = server::prover_server::ProverRegistry::new();
+        let rate_limiter = server::prover_server::WorkerRateLimiter::new(
+            config_opts.prover_rate_limit,
+            config_opts.prover_rate_limit_burst,
+        );
+        let rounds_interval = RoundsIntervalScheduler::new(
+            prover_options.prepare_data_interval,
+            prover_options.prepare_data_max_interval,
+        );
+        #[cfg(feature = "grpc")]
+        server::prover_server::start_grpc_prover_server(
+            connection_pool.clone(),
+            prover_options.gone_timeout,
+            rounds_interval.clone(),
+            stop_signal_sender.clone(),
+            models::config_options::parse_env("PROVER_SERVER_GRPC_BIND"),
+            config_opts.clone(),
+            jobs_progress.clone(),
+            prover_registry.clone(),
+            rate_limiter.clone(),
+        );
         start_prover_server(
             connection_pool.clone(),
             prover_options.gone_timeout,
-            prover_options.prepare_data_interval,
+            rounds_interval,
             stop_signal_sender,
             config_opts.clone(),
+            jobs_progress,
+            prover_registry,
+            rate_limiter,
         );
 
         let mempool_task = run_mempool_task(
@@ -282,8 +313,11 @@ fn main() {
         /// Waits for a message on a `stop_signal_receiver`. This receiver exists
         /// for threads that aren't using the tokio Runtime to run on, and thus
         /// cannot be handled the same way as the tokio tasks.
-        async fn wait_for_stop_signal(mut stop_signal_receiver: mpsc::Receiver<bool>) {
-            stop_signal_receiver.next().await;
+        /// Returns the description of the panic which caused the stop, if any.
+        async fn wait_for_stop_signal(
+            mut stop_signal_receiver: mpsc::Receiver<Option<String>>,
+        ) -> Option<String> {
+            stop_signal_receiver.next().await.flatten()
         }
 
         let task_future = wait_for_tasks(task_futures);
@@ -295,8 +329,13 @@ fn main() {
             _ = task_future => {
                 // Do nothing, task future always panic upon finishing.
             },
-            _ = signal_future => {
-                log::warn!("Stop signal received, shutting down");
+            panic_message = signal_future => match panic_message {
+                Some(panic_message) => {
+                    log::error!("{}, shutting down", panic_message);
+                }
+                None => {
+                    log::warn!("Stop signal received, shutting down");
+                }
             },
         }
     });
@@ -0,0 +1,516 @@
+//! gRPC front of the prover server, an alternative to the HTTP API for the provers.
+
+// Built-in
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+// External
+use failure::format_err;
+use futures::channel::{mpsc, oneshot};
+use futures::{future, stream};
+use log::{info, trace};
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::{metadata::MetadataValue, Request, Response, Status};
+// Workspace deps
+use models::{
+    config_options::{ConfigurationOptions, ThreadPanicNotify, TlsConfig},
+    node::BlockNumber,
+    prover_utils::{EncodedProofPlonk, ProvingStats},
+};
+use prover::client::proto::{
+    self,
+    prover_service_server::{ProverService, ProverServiceServer},
+};
+use storage::{prover::records::ProverMetadata, ConnectionPool};
+// Local deps
+use super::{
+    load_verified_block_witness, proof_verifier::ProofVerifier, publish_proof, rate_limiter,
+    JobsProgress, ProverRegistry, PublishProofError, RoundsIntervalScheduler, VerifiedWitnessError,
+    WorkerRateLimiter,
+};
+
+/// gRPC prover server. Handles the same requests as the HTTP prover server.
+#[derive(Debug, Clone)]
+pub struct GrpcProverServer {
+    connection_pool: ConnectionPool,
+    prover_timeout: Duration,
+    jobs_progress: JobsProgress,
+    /// Running provers, shared with the HTTP server serving them at `/provers`.
+    prover_registry: ProverRegistry,
+    /// Interval of the witness generators, adjusted by the responses of `block_to_prove`.
+    rounds_interval: RoundsIntervalScheduler,
+    /// Verifier of the published proofs, `None` if the proofs are stored unverified.
+    proof_verifier: Option<ProofVerifier>,
+    /// Block is not assigned to the prover which has failed or timed out on it this many times.
+    max_block_attempts: usize,
+    /// Limits the job requests of every worker, shared with the HTTP server.
+    rate_limiter: WorkerRateLimiter,
+}
+
+impl GrpcProverServer {
+    pub fn new(
+        connection_pool: ConnectionPool,
+        prover_timeout: Duration,
+        jobs_progress: JobsProgress,
+        prover_registry: ProverRegistry,
+        rounds_interval: RoundsIntervalScheduler,
+        proof_verifier: Option<ProofVerifier>,
+        max_block_attempts: usize,
+        rate_limiter: WorkerRateLimiter,
+    ) -> Self {
+        Self {
+            connection_pool,
+            prover_timeout,
+            jobs_progress,
+            prover_registry,
+            rounds_interval,
+            proof_verifier,
+            max_block_attempts,
+            rate_limiter,
+        }
+    }
+
+    async fn access_storage(&self) -> Result<storage::StorageProcessor<'_>, Status> {
+        self.connection_pool
+            .access_storage_fragile()
+            .await
+            .map_err(|e| {
+                vlog::warn!("Failed to access storage: {}", e);
+                Status::internal(e.to_string())
+            })
+    }
+
+    /// Rejects the job request of the worker exceeding its rate limit with `RESOURCE_EXHAUSTED`,
+    /// the seconds to wait before the next request are sent in the `retry-after` metadata.
+    fn check_rate_limit(&self, worker: &str) -> Result<(), Status> {
+        self.rate_limiter.check(worker).map_err(|retry_after| {
+            trace!(
+                "worker {} exceeded the rate limit of the job requests",
+                worker
+            );
+            let mut status = Status::resource_exhausted("rate limit exceeded");
+            status.metadata_mut().insert(
+                "retry-after",
+                MetadataValue::from(rate_limiter::retry_after_secs(retry_after)),
+            );
+            status
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl ProverService for GrpcProverServer {
+    async fn register(
+        &self,
+        request: Request<proto::RegisterRequest>,
+    ) -> Result<Response<proto::RegisterResponse>, Status> {
+        let r = request.into_inner();
+        info!(
+            "register request for prover with name: {}, block sizes: {:?}, host: {:?}, version: {:?}, cpu cores: {:?}",
+            r.name, r.block_sizes, r.hostname, r.version, r.cpu_cores
+        );
+        if r.name == "" {
+            return Err(Status::invalid_argument("empty name"));
+        }
+        if r.block_sizes.is_empty() {
+            return Err(Status::invalid_argument("empty block sizes"));
+        }
+        let block_sizes: Vec<usize> = r.block_sizes.iter().map(|size| *size as usize).collect();
+        // Unset fields of the message are empty.
+        let non_empty = |value: String| Some(value).filter(|value| !value.is_empty());
+        let metadata = ProverMetadata {
+            hostname: non_empty(r.hostname),
+            version: non_empty(r.version),
+            cpu_cores: Some(r.cpu_cores as i32).filter(|cpu_cores| *cpu_cores > 0),
+            vk_hash: non_empty(r.vk_hash),
+        };
+        let mut storage = self.access_storage().await?;
+        let prover_id = storage
+            .prover_schema()
+            .register_prover_with_metadata(&r.name, &block_sizes, &metadata)
+            .await
+            .map_err(|e| {
+                vlog::warn!("Failed to register prover in the db: {}", e);
+                Status::internal(e.to_string())
+            })?;
+        self.prover_registry.register(prover_id, &r.name);
+        Ok(Response::new(proto::RegisterResponse { prover_id }))
+    }
+
+    async fn block_to_prove(
+        &self,
+        request: Request<proto::ProverRequest>,
+    ) -> Result<Response<proto::BlockToProveResponse>, Status> {
+        let r = request.into_inner();
+        trace!("request block to prove from worker: {}", r.name);
+        if r.name == "" {
+            return Err(Status::invalid_argument("empty name"));
+        }
+        self.check_rate_limit(&r.name)?;
+        let mut storage = self.access_storage().await?;
+        let ret = storage
+            .prover_schema()
+            .prover_run_for_next_commit_with_max_attempts(
+                &r.name,
+                self.prover_timeout,
+                r.block_size as usize,
+                Some(self.max_block_attempts),
+            )
+            .await
+            .map_err(|e| {
+                vlog::warn!("could not get next unverified commit operation: {}", e);
+                Status::internal("storage layer error")
+            })?;
+        self.prover_registry
+            .job_requested(&r.name, ret.as_ref().map(|prover_run| prover_run.id));
+        self.rounds_interval.record_block_to_prove(ret.is_some());
+        let job = ret.map(|prover_run| {
+            info!(
+                "satisfied request block {} to prove from worker: {}",
+                prover_run.block_number, r.name
+            );
+            proto::Job {
+                prover_run_id: prover_run.id,
+                block: prover_run.block_number,
+            }
+        });
+        Ok(Response::new(proto::BlockToProveResponse { job }))
+    }
+
+    async fn working_on(
+        &self,
+        request: Request<proto::WorkingOnRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let r = request.into_inner();
+        trace!(
+            "Received heartbeat for prover_run with id: {}",
+            r.prover_run_id
+        );
+        let mut storage = self.access_storage().await?;
+        storage
+            .prover_schema()
+            .record_prover_is_working(r.prover_run_id)
+            .await
+            .map_err(|e| {
+                vlog::warn!("failed to record prover work in progress request: {}", e);
+                Status::internal("storage layer error")
+            })?;
+        self.prover_registry.heartbeat(r.prover_run_id);
+        if let Some(progress) = r.progress.as_ref().and_then(proto::JobProgress::decode) {
+            self.jobs_progress
+                .record(r.prover_run_id, progress, self.prover_timeout);
+        }
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn extend_lease(
+        &self,
+        request: Request<proto::ExtendLeaseRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let r = request.into_inner();
+        info!(
+            "Extending lease of prover_run with id: {} by {} seconds",
+            r.prover_run_id, r.extra_seconds
+        );
+        let mut storage = self.access_storage().await?;
+        storage
+            .prover_schema()
+            .extend_prover_run_lease(
+                r.prover_run_id,
+                Duration::from_secs(u64::from(r.extra_seconds)),
+            )
+            .await
+            .map_err(|e| {
+                vlog::warn!("failed to extend prover run lease: {}", e);
+                Status::internal("storage layer error")
+            })?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn prover_data(
+        &self,
+        request: Request<proto::ProverDataRequest>,
+    ) -> Result<Response<proto::ProverDataResponse>, Status> {
+        let block = request.into_inner().block as BlockNumber;
+        trace!("Got request for prover_data for block {}", block);
+        let mut storage = self.access_storage().await?;
+        let witness = storage
+            .prover_schema()
+            .get_witness(block)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if witness.is_some() {
+            info!("Sent prover_data for block {}", block);
+        } else {
+            // No witness, we should just wait
+            warn!("No witness for block {}", block);
+        }
+        let prover_data =
+            serde_json::to_vec(&witness).map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(proto::ProverDataResponse { prover_data }))
+    }
+
+    async fn verified_block_prover_data(
+        &self,
+        request: Request<proto::ProverDataRequest>,
+    ) -> Result<Response<proto::ProverDataResponse>, Status> {
+        let block = request.into_inner().block as BlockNumber;
+        trace!("Got request for prover_data for verified block {}", block);
+        let mut storage = self.access_storage().await?;
+        let witness = match load_verified_block_witness(&mut storage, block).await {
+            Ok(witness) => witness,
+            Err(VerifiedWitnessError::NotVerified) => {
+                return Err(Status::failed_precondition("block is not verified"))
+            }
+            Err(VerifiedWitnessError::Storage) => {
+                return Err(Status::internal("storage layer error"))
+            }
+        };
+        let prover_data =
+            serde_json::to_vec(&witness).map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(proto::ProverDataResponse { prover_data }))
+    }
+
+    async fn publish(
+        &self,
+        request: Request<proto::PublishRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let r = request.into_inner();
+        info!("Received a proof for block: {}", r.block);
+        let proof: EncodedProofPlonk = serde_json::from_slice(&r.proof)
+            .map_err(|e| Status::invalid_argument(format!("invalid proof: {}", e)))?;
+        // Older provers don't send the statistics.
+        let stats: ProvingStats = if r.stats.is_empty() {
+            ProvingStats::default()
+        } else {
+            serde_json::from_slice(&r.stats)
+                .map_err(|e| Status::invalid_argument(format!("invalid proving stats: {}", e)))?
+        };
+        let mut storage = self.access_storage().await?;
+        let proof_verifier = self.proof_verifier.as_ref();
+        let block = r.block as BlockNumber;
+        match publish_proof(proof_verifier, &mut storage, block, &proof, &stats).await {
+            Ok(()) => Ok(Response::new(proto::Empty {})),
+            Err(PublishProofError::Rejected(reason)) => Err(Status::invalid_argument(reason)),
+            Err(PublishProofError::Conflict) => Err(Status::already_exists("proof conflict")),
+            Err(PublishProofError::Internal(reason)) => Err(Status::internal(reason)),
+        }
+    }
+
+    async fn stopped(
+        &self,
+        request: Request<proto::StoppedRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let prover_id = request.into_inner().prover_id;
+        let mut storage = self.access_storage().await?;
+
+        let prover_description = storage
+            .prover_schema()
+            .prover_by_id(prover_id)
+            .await
+            .map_err(|_| {
+                vlog::warn!(
+                    "Received stop notification from an unknown prover with ID {}",
+                    prover_id
+                );
+                Status::invalid_argument("unknown prover ID")
+            })?;
+
+        info!(
+            "Prover instance '{}' with ID {} send a stopping notification",
+            prover_description.worker, prover_id
+        );
+
+        storage
+            .prover_schema()
+            .record_prover_stop(prover_id)
+            .await
+            .map_err(|e| {
+                vlog::warn!("failed to record prover stop: {}", e);
+                Status::internal("storage layer error")
+            })?;
+        self.prover_registry.stopped(prover_id);
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn record_failure(
+        &self,
+        request: Request<proto::RecordFailureRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let r = request.into_inner();
+        vlog::warn!(
+            "prover_run with id: {} failed: {}",
+            r.prover_run_id,
+            r.reason
+        );
+        let mut storage = self.access_storage().await?;
+        storage
+            .prover_schema()
+            .record_prover_job_failure(r.prover_run_id, &r.reason)
+            .await
+            .map_err(|e| {
+                vlog::warn!("failed to record prover job failure: {}", e);
+                Status::internal("storage layer error")
+            })?;
+        Ok(Response::new(proto::Empty {}))
+    }
+}
+
+/// Accepts the request if its bearer token is the shared secret of the provers,
+/// otherwise rejects it with `UNAUTHENTICATED`, as the HTTP server does.
+fn authenticate_prover(secret_auth: &str, request: Request<()>) -> Result<Request<()>, Status> {
+    let token = request
+        .metadata()
+        .get("authorization")
+        .and_then(|auth| auth.to_str().ok())
+        .and_then(|auth| auth.strip_prefix("Bearer "));
+    if token == Some(secret_auth) {
+        Ok(request)
+    } else {
+        Err(Status::unauthenticated("invalid prover secret auth"))
+    }
+}
+
+/// Loads the TLS config of the gRPC server from the same files as the HTTP server:
+/// `cert` and `key` identify the server, the provers must present the certificate signed by `ca`.
+fn load_tls_config(tls_config: &TlsConfig) -> Result<ServerTlsConfig, failure::Error> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| format_err!("failed to read {}: {}", path.display(), e))
+    };
+    let identity = Identity::from_pem(read(&tls_config.cert_pem)?, read(&tls_config.key_pem)?);
+    Ok(ServerTlsConfig::new()
+        .identity(identity)
+        .client_ca_root(Certificate::from_pem(read(&tls_config.ca_pem)?)))
+}
+
+/// Handle of the running gRPC prover server, returned by `start_grpc_prover_server`.
+/// Dropping the handle leaves the server running.
+#[derive(Debug)]
+pub struct GrpcServerHandle {
+    local_addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl GrpcServerHandle {
+    /// Address the server listens on, e.g. the actual port if it was bound to the port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops the server, waiting for the requests in progress to be processed.
+    pub fn stop(self) {
+        let _ = self.shutdown.send(());
+        self.thread
+            .join()
+            .expect("gRPC prover server thread panicked");
+    }
+}
+
+/// Starts the gRPC prover server in a separate thread.
+/// Witness generators are started by the `start_prover_server`, so it must be started as well,
+/// sharing the `rounds_interval` adjusted by the job requests of the provers.
+/// Progress of the jobs and the registry of the provers should be shared with the HTTP server,
+/// which serves them to the operators.
+/// Published proofs are verified the same way as by the HTTP server, see `ConfigurationOptions`.
+/// Rate limiter of the job requests should be shared with the HTTP server as well.
+/// Provers authenticate with the same shared secret and TLS config as to the HTTP server, if set.
+/// Returns once the server is bound to its address.
+#[allow(clippy::too_many_arguments)]
+pub fn start_grpc_prover_server(
+    connection_pool: ConnectionPool,
+    prover_timeout: Duration,
+    rounds_interval: RoundsIntervalScheduler,
+    panic_notify: mpsc::Sender<Option<String>>,
+    bind_address: SocketAddr,
+    config_options: ConfigurationOptions,
+    jobs_progress: JobsProgress,
+    prover_registry: ProverRegistry,
+    rate_limiter: WorkerRateLimiter,
+) -> GrpcServerHandle {
+    let listener = std::net::TcpListener::bind(bind_address)
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .unwrap_or_else(|e| {
+            panic!(
+                "failed to bind gRPC prover server to {}: {}",
+                bind_address, e
+            )
+        });
+    let local_addr = listener
+        .local_addr()
+        .expect("failed to get gRPC prover server address");
+    // Provers must present the certificate signed by the CA, if TLS is configured.
+    let tls_config = config_options.prover_tls.as_ref().map(|tls_config| {
+        load_tls_config(tls_config).expect("failed to load gRPC prover server TLS config")
+    });
+    let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+
+    let thread = thread::Builder::new()
+        .name("prover_grpc_server".to_string())
+        .spawn(move || {
+            let _panic_sentinel = ThreadPanicNotify(panic_notify);
+            let mut runtime = tokio::runtime::Builder::new()
+                .threaded_scheduler()
+                .enable_all()
+                .build()
+                .expect("failed to create gRPC prover server runtime");
+
+            let proof_verifier = if config_options.prover_verify_proofs {
+                Some(ProofVerifier::new())
+            } else {
+                None
+            };
+            let service = GrpcProverServer::new(
+                connection_pool,
+                prover_timeout,
+                jobs_progress,
+                prover_registry,
+                rounds_interval,
+                proof_verifier,
+                config_options.prover_max_block_attempts,
+                rate_limiter,
+            );
+            let service = match config_options.prover_secret_auth.clone() {
+                Some(secret_auth) => {
+                    ProverServiceServer::with_interceptor(service, move |request: Request<()>| {
+                        authenticate_prover(&secret_auth, request)
+                    })
+                }
+                None => ProverServiceServer::new(service),
+            };
+            runtime.block_on(async move {
+                info!("Starting gRPC prover server on {}", local_addr);
+                let listener = tokio::net::TcpListener::from_std(listener)
+                    .expect("failed to register gRPC prover server listener");
+                let incoming = stream::unfold(listener, |mut listener| async move {
+                    let stream = listener.accept().await.map(|(stream, _)| stream);
+                    Some((stream, listener))
+                });
+                // Dropped handle doesn't stop the server.
+                let shutdown = async move {
+                    if shutdown_receiver.await.is_err() {
+                        future::pending::<()>().await;
+                    }
+                };
+                let mut server = Server::builder();
+                if let Some(tls_config) = tls_config {
+                    server = server.tls_config(tls_config);
+                }
+                server
+                    .add_service(service)
+                    .serve_with_incoming_shutdown(incoming, shutdown)
+                    .await
+                    .expect("gRPC prover server failed");
+                info!("gRPC prover server on {} is stopped", local_addr);
+            });
+        })
+        .expect("failed to start gRPC prover server");
+
+    GrpcServerHandle {
+        local_addr,
+        shutdown,
+        thread,
+    }
+}
@@ -0,0 +1,128 @@
+//! Progress of the jobs, reported by the provers along with their heartbeats.
+
+// Built-in
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+// External
+use chrono::{DateTime, Utc};
+// Workspace deps
+use prover::{JobProgress, ProvingStage};
+
+/// Latest progress of the job (prover run) reported by the prover.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportedJobProgress {
+    pub prover_run_id: i32,
+    pub stage: ProvingStage,
+    /// Seconds the job spent in the stage at the moment of the report.
+    pub elapsed_secs: u64,
+    pub reported_at: DateTime<Utc>,
+}
+
+/// Latest progress of every job being proved, shared by the HTTP and gRPC prover servers.
+///
+/// Progress is kept in memory only: it is useful while the job is being proved, and the jobs
+/// without heartbeats are reassigned by the server anyway.
+#[derive(Debug, Clone, Default)]
+pub struct JobsProgress {
+    jobs: Arc<RwLock<HashMap<i32, ReportedJobProgress>>>,
+}
+
+impl JobsProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the progress of the job and forgets the jobs without reports for longer than
+    /// the `prover_timeout`, since they are considered abandoned.
+    pub fn record(&self, prover_run_id: i32, progress: JobProgress, prover_timeout: Duration) {
+        let now = Utc::now();
+        let prover_timeout = chrono::Duration::from_std(prover_timeout)
+            .unwrap_or_else(|_| chrono::Duration::max_value());
+
+        let mut jobs = self.jobs.write().expect("jobs progress lock is poisoned");
+        jobs.retain(|_, job| now.signed_duration_since(job.reported_at) <= prover_timeout);
+        jobs.insert(
+            prover_run_id,
+            ReportedJobProgress {
+                prover_run_id,
+                stage: progress.stage,
+                elapsed_secs: progress.elapsed_secs,
+                reported_at: now,
+            },
+        );
+    }
+
+    /// Returns the latest reported progress of the job, if any.
+    pub fn get(&self, prover_run_id: i32) -> Option<ReportedJobProgress> {
+        let jobs = self.jobs.read().expect("jobs progress lock is poisoned");
+        jobs.get(&prover_run_id).cloned()
+    }
+
+    /// Returns the latest reported progress of all the known jobs, ordered by the job ID.
+    pub fn all(&self) -> Vec<ReportedJobProgress> {
+        let jobs = self.jobs.read().expect("jobs progress lock is poisoned");
+        let mut jobs: Vec<_> = jobs.values().cloned().collect();
+        jobs.sort_by_key(|job| job.prover_run_id);
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(stage: ProvingStage) -> JobProgress {
+        JobProgress {
+            stage,
+            elapsed_secs: 1,
+        }
+    }
+
+    #[test]
+    fn keeps_latest_stage_per_job() {
+        let jobs_progress = JobsProgress::new();
+        let timeout = Duration::from_secs(60);
+
+        jobs_progress.record(1, progress(ProvingStage::FetchingData), timeout);
+        jobs_progress.record(2, progress(ProvingStage::Synthesis), timeout);
+        jobs_progress.record(1, progress(ProvingStage::ProofGeneration), timeout);
+
+        assert_eq!(
+            jobs_progress.get(1).map(|job| job.stage),
+            Some(ProvingStage::ProofGeneration)
+        );
+        let stages: Vec<_> = jobs_progress
+            .all()
+            .into_iter()
+            .map(|job| (job.prover_run_id, job.stage))
+            .collect();
+        assert_eq!(
+            stages,
+            vec![
+                (1, ProvingStage::ProofGeneration),
+                (2, ProvingStage::Synthesis)
+            ]
+        );
+    }
+
+    #[test]
+    fn forgets_jobs_without_reports() {
+        let jobs_progress = JobsProgress::new();
+
+        jobs_progress.record(
+            1,
+            progress(ProvingStage::Publishing),
+            Duration::from_secs(60),
+        );
+        std::thread::sleep(Duration::from_millis(20));
+        jobs_progress.record(
+            2,
+            progress(ProvingStage::Synthesis),
+            Duration::from_millis(10),
+        );
+
+        assert!(jobs_progress.get(1).is_none());
+        assert!(jobs_progress.get(2).is_some());
+    }
+}
@@ -1,27 +1,92 @@
 // Built-in
-use std::sync::{Arc, RwLock};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{mpsc as std_mpsc, Arc, RwLock};
 use std::thread;
 use std::time::{self, Duration};
 // External
-use actix_web::{web, App, HttpResponse, HttpServer};
-use futures::channel::mpsc;
+use actix_web::dev::{Server, Service, ServiceRequest};
+use actix_web::http::{header::RETRY_AFTER, HeaderName, HeaderValue};
+use actix_web::middleware::{Compress, Condition};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use actix_web_httpauth::extractors::{
+    bearer::{BearerAuth, Config},
+    AuthenticationError,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use futures::{channel::mpsc, Future};
 use log::{info, trace};
+use tokio::sync::broadcast;
+use tracing::Instrument;
 // Workspace deps
 use models::config_options::ConfigurationOptions;
-use models::{config_options::ThreadPanicNotify, node::BlockNumber};
-use prover::client;
-use storage::ConnectionPool;
+use models::{
+    config_options::ThreadPanicNotify,
+    node::BlockNumber,
+    prover_utils::{EncodedProofPlonk, ProvingStats},
+};
+use prover::{client, telemetry, tls};
+use storage::{prover::records::ProverMetadata, ConnectionPool, StorageProcessor};
 // Local deps
+use crate::prover_server::proof_verifier::{ProofVerificationError, ProofVerifier};
+use crate::prover_server::prover_data_parts::{ProverDataEncoding, ProverDataParts};
+use crate::prover_server::readiness::WitnessHeartbeat;
 use crate::prover_server::scaler::ScalerOracle;
 
+#[cfg(feature = "grpc")]
+mod grpc;
+mod jobs_progress;
+mod proof_verifier;
+mod prover_data_parts;
+mod provers_status;
+mod rate_limiter;
+mod readiness;
+mod registry;
+mod rounds_interval;
 mod scaler;
 mod witness_generator;
 
+#[cfg(feature = "grpc")]
+pub use self::grpc::{start_grpc_prover_server, GrpcProverServer, GrpcServerHandle};
+pub use self::jobs_progress::{JobsProgress, ReportedJobProgress};
+pub use self::provers_status::{JobAttempt, JobOutcome, ProverJob, ProverStatus};
+pub use self::rate_limiter::WorkerRateLimiter;
+pub use self::readiness::{Readiness, SubsystemFailure};
+pub use self::registry::{ProverInfo, ProverRegistry};
+pub use self::rounds_interval::RoundsIntervalScheduler;
+
+/// Idle connections of the provers are kept open for this long, so the provers polling
+/// the server don't establish a new connection every round. Exceeds the idle timeout
+/// of the prover client connections (see `client::ClientOptions`).
+const KEEP_ALIVE_SECS: usize = 75;
+/// Upper limit of the time `/block_to_prove_wait` holds the request, whatever the prover asks for.
+const MAX_BLOCK_TO_PROVE_WAIT: Duration = Duration::from_secs(60);
+/// Capacity of the channel of the new witnesses. Lagging receivers only need to know that
+/// there are new witnesses, so the missed blocks are not a problem.
+const NEW_WITNESS_CHANNEL_CAPACITY: usize = 16;
+
 #[derive(Debug)]
 struct AppState {
     connection_pool: storage::ConnectionPool,
     scaler_oracle: Arc<RwLock<ScalerOracle>>,
     prover_timeout: Duration,
+    jobs_progress: JobsProgress,
+    prover_registry: ProverRegistry,
+    prover_data_parts: ProverDataParts,
+    /// Notified by the witness generators once the witness of the block is stored.
+    new_witness: broadcast::Sender<BlockNumber>,
+    /// Interval of the witness generators, adjusted by the responses of `block_to_prove`.
+    rounds_interval: RoundsIntervalScheduler,
+    /// Expected hashes of the verification keys of the provers by block size.
+    vk_hashes: Arc<BTreeMap<usize, String>>,
+    /// Verifier of the published proofs, `None` if the proofs are stored unverified.
+    proof_verifier: Option<ProofVerifier>,
+    /// Block is not assigned to the prover which has failed or timed out on it this many times.
+    max_block_attempts: usize,
+    /// Updated by the witness generators every round, see `/readyz`.
+    witness_heartbeat: WitnessHeartbeat,
+    /// Limits the job requests of every worker, see `check_rate_limit`.
+    rate_limiter: WorkerRateLimiter,
 }
 
 impl AppState {
@@ -29,6 +94,16 @@ impl AppState {
         connection_pool: ConnectionPool,
         prover_timeout: Duration,
         idle_provers: u32,
+        jobs_progress: JobsProgress,
+        prover_registry: ProverRegistry,
+        prover_data_parts: ProverDataParts,
+        new_witness: broadcast::Sender<BlockNumber>,
+        rounds_interval: RoundsIntervalScheduler,
+        vk_hashes: Arc<BTreeMap<usize, String>>,
+        proof_verifier: Option<ProofVerifier>,
+        max_block_attempts: usize,
+        witness_heartbeat: WitnessHeartbeat,
+        rate_limiter: WorkerRateLimiter,
     ) -> Self {
         let scaler_oracle = Arc::new(RwLock::new(ScalerOracle::new(
             connection_pool.clone(),
@@ -39,6 +114,16 @@ impl AppState {
             connection_pool,
             scaler_oracle,
             prover_timeout,
+            jobs_progress,
+            prover_registry,
+            prover_data_parts,
+            new_witness,
+            rounds_interval,
+            vk_hashes,
+            proof_verifier,
+            max_block_attempts,
+            witness_heartbeat,
+            rate_limiter,
         }
     }
 
@@ -57,26 +142,138 @@ async fn status() -> actix_web::Result<String> {
     Ok("alive".into())
 }
 
+/// Reports whether the server is able to give out the jobs. Responds with `200 OK` even if
+/// the database is not connected, so the prover gets the reason of the failure.
+async fn health(data: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    let pending_jobs = match data.access_storage().await {
+        Ok(mut storage) => match storage.prover_schema().pending_jobs_count().await {
+            Ok(pending_jobs) => Some(pending_jobs),
+            Err(e) => {
+                vlog::warn!("Failed to count pending jobs: {}", e);
+                None
+            }
+        },
+        Err(_) => None,
+    };
+    Ok(HttpResponse::Ok().json(client::ServerHealth {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        db_connected: pending_jobs.is_some(),
+        pending_jobs: pending_jobs.unwrap_or_default() as usize,
+    }))
+}
+
+/// Liveness probe for the orchestrator, the process is able to respond.
+async fn healthz() -> actix_web::Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "alive": true })))
+}
+
+/// Readiness probe for the orchestrator: responds with `200 OK` if the database is reachable
+/// and the witness generators are running, `503 Service Unavailable` with the failing
+/// subsystems otherwise.
+async fn readyz(data: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    let db_check = match data.connection_pool.access_storage_fragile().await {
+        Ok(mut storage) => storage
+            .chain()
+            .block_schema()
+            .get_last_committed_block()
+            .await
+            .map(drop)
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let readiness = Readiness::new(
+        db_check,
+        data.witness_heartbeat.elapsed(),
+        data.rounds_interval.interval(),
+    );
+    if readiness.ready {
+        Ok(HttpResponse::Ok().json(readiness))
+    } else {
+        vlog::warn!("Prover server is not ready: {:?}", readiness.failing);
+        Ok(HttpResponse::ServiceUnavailable().json(readiness))
+    }
+}
+
+/// Compares the hashes of the verification keys of the prover with the expected ones,
+/// the prover refuses to run if any of them mismatches. Block sizes without the expected
+/// hash are not checked.
+async fn check_vk(
+    data: web::Data<AppState>,
+    r: web::Json<client::CheckVkReq>,
+) -> actix_web::Result<HttpResponse> {
+    let mismatched_block_sizes: Vec<usize> = r
+        .vk_hashes
+        .iter()
+        .filter(|vk_hash| {
+            data.vk_hashes
+                .get(&vk_hash.block_size)
+                .map_or(false, |expected| {
+                    !expected.eq_ignore_ascii_case(vk_hash.hash.trim_start_matches("0x"))
+                })
+        })
+        .map(|vk_hash| vk_hash.block_size)
+        .collect();
+    if !mismatched_block_sizes.is_empty() {
+        warn!(
+            "prover {} has the unexpected verification keys of block sizes {:?}",
+            r.name, mismatched_block_sizes
+        );
+    }
+    Ok(HttpResponse::Ok().json(client::CheckVkRes {
+        mismatched_block_sizes,
+    }))
+}
+
 async fn register(
     data: web::Data<AppState>,
-    r: web::Json<client::ProverReq>,
+    r: web::Json<client::RegisterReq>,
 ) -> actix_web::Result<String> {
-    info!("register request for prover with name: {}", r.name);
+    info!(
+        "register request for prover with name: {}, block sizes: {:?}, host: {:?}, version: {:?}, cpu cores: {:?}",
+        r.name, r.block_sizes, r.hostname, r.version, r.cpu_cores
+    );
     if r.name == "" {
         return Err(actix_web::error::ErrorBadRequest("empty name"));
     }
+    if r.block_sizes.is_empty() {
+        return Err(actix_web::error::ErrorBadRequest("empty block sizes"));
+    }
+    let metadata = ProverMetadata {
+        hostname: r.hostname.clone(),
+        version: r.version.clone(),
+        cpu_cores: r.cpu_cores.map(|cpu_cores| cpu_cores as i32),
+        vk_hash: r.vk_hash.clone(),
+    };
     let mut storage = data.access_storage().await?;
     let id = storage
         .prover_schema()
-        .register_prover(&r.name, r.block_size)
+        .register_prover_with_metadata(&r.name, &r.block_sizes, &metadata)
         .await
         .map_err(|e| {
             vlog::warn!("Failed to register prover in the db: {}", e);
             actix_web::error::ErrorInternalServerError(e)
         })?;
+    data.prover_registry.register(id, &r.name);
     Ok(id.to_string())
 }
 
+/// Rejects the job request of the worker exceeding its rate limit with `429 Too Many Requests`
+/// and the `Retry-After` header, so a prover polling in a tight loop doesn't exhaust
+/// the database connections of the server.
+fn check_rate_limit(data: &AppState, worker: &str) -> actix_web::Result<()> {
+    data.rate_limiter.check(worker).map_err(|retry_after| {
+        trace!(
+            "worker {} exceeded the rate limit of the job requests",
+            worker
+        );
+        let retry_after_secs = rate_limiter::retry_after_secs(retry_after);
+        let response = HttpResponse::TooManyRequests()
+            .header(RETRY_AFTER, retry_after_secs.to_string())
+            .body("rate limit exceeded");
+        actix_web::error::InternalError::from_response("rate limit exceeded", response).into()
+    })
+}
+
 async fn block_to_prove(
     data: web::Data<AppState>,
     r: web::Json<client::ProverReq>,
@@ -85,30 +282,83 @@ async fn block_to_prove(
     if r.name == "" {
         return Err(actix_web::error::ErrorBadRequest("empty name"));
     }
+    check_rate_limit(&data, &r.name)?;
+    let response = next_block_to_prove(&data, &r.name, r.block_size).await?;
+    data.rounds_interval.record_block_to_prove(response.is_some());
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Long-poll version of `block_to_prove`: if there is no job, the request is held until
+/// the witness generators store a new witness or the wait requested by the prover expires.
+/// Jobs becoming available for the other reasons (e.g. timed out prover runs) are returned
+/// by the next request of the prover. Only the final response is recorded by the rounds interval.
+async fn block_to_prove_wait(
+    data: web::Data<AppState>,
+    r: web::Json<client::ProverWaitReq>,
+) -> actix_web::Result<HttpResponse> {
+    trace!(
+        "request block to prove from worker: {}, waiting up to {}ms",
+        r.name,
+        r.max_wait_ms
+    );
+    if r.name == "" {
+        return Err(actix_web::error::ErrorBadRequest("empty name"));
+    }
+    check_rate_limit(&data, &r.name)?;
+    let max_wait = Duration::from_millis(r.max_wait_ms).min(MAX_BLOCK_TO_PROVE_WAIT);
+    let deadline = time::Instant::now() + max_wait;
+    // Subscribe before checking the jobs, so the witness stored in between isn't missed.
+    let mut new_witness = data.new_witness.subscribe();
+    loop {
+        let response = next_block_to_prove(&data, &r.name, r.block_size).await?;
+        if response.is_some() {
+            data.rounds_interval.record_block_to_prove(true);
+            return Ok(HttpResponse::Ok().json(response));
+        }
+        let wait = deadline.saturating_duration_since(time::Instant::now());
+        match tokio::time::timeout(wait, new_witness.recv()).await {
+            // Lagged receiver still knows there are new witnesses.
+            Ok(Ok(_)) | Ok(Err(broadcast::RecvError::Lagged(_))) => {}
+            Ok(Err(broadcast::RecvError::Closed)) | Err(_) => {
+                data.rounds_interval.record_block_to_prove(false);
+                return Ok(HttpResponse::Ok().json(None::<client::BlockToProveRes>));
+            }
+        }
+    }
+}
+
+/// Assigns the next block of the given size to the prover, if there is any.
+async fn next_block_to_prove(
+    data: &AppState,
+    worker: &str,
+    block_size: usize,
+) -> actix_web::Result<Option<client::BlockToProveRes>> {
     let mut storage = data.access_storage().await?;
     let ret = storage
         .prover_schema()
-        .prover_run_for_next_commit(&r.name, data.prover_timeout, r.block_size)
+        .prover_run_for_next_commit_with_max_attempts(
+            worker,
+            data.prover_timeout,
+            block_size,
+            Some(data.max_block_attempts),
+        )
         .await
         .map_err(|e| {
             vlog::warn!("could not get next unverified commit operation: {}", e);
             actix_web::error::ErrorInternalServerError("storage layer error")
         })?;
-    if let Some(prover_run) = ret {
+    data.prover_registry
+        .job_requested(worker, ret.as_ref().map(|prover_run| prover_run.id));
+    Ok(ret.map(|prover_run| {
         info!(
             "satisfied request block {} to prove from worker: {}",
-            prover_run.block_number, r.name
+            prover_run.block_number, worker
         );
-        Ok(HttpResponse::Ok().json(client::BlockToProveRes {
+        client::BlockToProveRes {
             prover_run_id: prover_run.id,
             block: prover_run.block_number,
-        }))
-    } else {
-        Ok(HttpResponse::Ok().json(client::BlockToProveRes {
-            prover_run_id: 0,
-            block: 0,
-        }))
-    }
+        }
+    }))
 }
 
 async fn prover_data(
@@ -133,6 +383,130 @@ async fn prover_data(
     Ok(HttpResponse::Ok().json(witness))
 }
 
+/// Serves the part of the encoded prover data along with the total amount of parts
+/// (see `client::PROVER_DATA_PARTS_HEADER`), so the large prover data can be downloaded
+/// piece by piece. Data is encoded in binary for the provers accepting it, and in JSON otherwise.
+/// Absent witness is encoded in a single part.
+async fn prover_data_part(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<(BlockNumber, usize)>,
+) -> actix_web::Result<HttpResponse> {
+    let (block, part) = path.into_inner();
+    let encoding = ProverDataEncoding::accepted_by(&req);
+    trace!(
+        "Got request for prover_data part {} for block {}",
+        part,
+        block
+    );
+    let encoded = match data.prover_data_parts.get(block, encoding) {
+        Some(encoded) => encoded,
+        None => {
+            let mut storage = data.access_storage().await?;
+            let witness = storage
+                .prover_schema()
+                .get_witness(block)
+                .await
+                .map_err(|e| {
+                    vlog::warn!("failed to load witness: {}", e);
+                    actix_web::error::ErrorInternalServerError("storage layer error")
+                })?;
+            let encoded = Arc::new(encoding.encode(&witness).map_err(|e| {
+                vlog::warn!("failed to encode witness of block {}: {}", block, e);
+                actix_web::error::ErrorInternalServerError("failed to encode witness")
+            })?);
+            // Absent witness is going to be generated, so it's not cached.
+            if witness.is_some() {
+                data.prover_data_parts
+                    .insert(block, encoding, Arc::clone(&encoded));
+            } else {
+                warn!("No witness for block {}", block);
+            }
+            encoded
+        }
+    };
+    let (part_data, parts) = prover_data_parts::data_part(&encoded, part)
+        .ok_or_else(|| actix_web::error::ErrorNotFound("no such part of prover data"))?;
+    if part + 1 == parts {
+        info!("Sent prover_data for block {} in {} part(s)", block, parts);
+    }
+    Ok(HttpResponse::Ok()
+        .header(client::PROVER_DATA_PARTS_HEADER, parts.to_string())
+        .content_type("application/octet-stream")
+        .body(part_data.to_vec()))
+}
+
+/// Reason the witness of the verified block was not served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VerifiedWitnessError {
+    /// Block is not verified yet, so it must be proved through the job queue.
+    NotVerified,
+    Storage,
+}
+
+/// Loads the witness of the already verified block, bypassing the job queue,
+/// so the historical blocks can be re-proved.
+async fn load_verified_block_witness(
+    storage: &mut StorageProcessor<'_>,
+    block: BlockNumber,
+) -> Result<Option<serde_json::Value>, VerifiedWitnessError> {
+    let last_verified_block = storage
+        .chain()
+        .block_schema()
+        .get_last_verified_block()
+        .await
+        .map_err(|e| {
+            vlog::warn!("failed to load last verified block: {}", e);
+            VerifiedWitnessError::Storage
+        })?;
+    if block > last_verified_block {
+        return Err(VerifiedWitnessError::NotVerified);
+    }
+    let witness = storage
+        .prover_schema()
+        .get_witness(block)
+        .await
+        .map_err(|e| {
+            vlog::warn!("failed to load witness: {}", e);
+            VerifiedWitnessError::Storage
+        })?;
+    if witness.is_some() {
+        info!("Sent prover_data for verified block {}", block);
+    } else {
+        warn!("No witness for verified block {}", block);
+    }
+    Ok(witness)
+}
+
+async fn verified_block_prover_data(
+    data: web::Data<AppState>,
+    req: HttpRequest,
+    block: web::Json<BlockNumber>,
+) -> actix_web::Result<HttpResponse> {
+    trace!("Got request for prover_data for verified block {}", *block);
+    let mut storage = data.access_storage().await?;
+    match load_verified_block_witness(&mut storage, block.0).await {
+        Ok(witness) => {
+            let encoding = ProverDataEncoding::accepted_by(&req);
+            let encoded = encoding.encode(&witness).map_err(|e| {
+                vlog::warn!("failed to encode witness of block {}: {}", *block, e);
+                actix_web::error::ErrorInternalServerError("failed to encode witness")
+            })?;
+            let content_type = match encoding {
+                ProverDataEncoding::Json => "application/json",
+                ProverDataEncoding::Binary => prover::prover_data::BINARY_CONTENT_TYPE,
+            };
+            Ok(HttpResponse::Ok().content_type(content_type).body(encoded))
+        }
+        Err(VerifiedWitnessError::NotVerified) => {
+            Err(actix_web::error::ErrorBadRequest("block is not verified"))
+        }
+        Err(VerifiedWitnessError::Storage) => Err(actix_web::error::ErrorInternalServerError(
+            "storage layer error",
+        )),
+    }
+}
+
 async fn working_on(
     data: web::Data<AppState>,
     r: web::Json<client::WorkingOnReq>,
@@ -155,10 +529,181 @@ async fn working_on(
             vlog::warn!("failed to record prover work in progress request: {}", e);
             actix_web::error::ErrorInternalServerError("storage layer error")
         })?;
+    data.prover_registry.heartbeat(r.prover_run_id);
+    if let Some(progress) = r.progress {
+        data.jobs_progress
+            .record(r.prover_run_id, progress, data.prover_timeout);
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn extend_lease(
+    data: web::Data<AppState>,
+    r: web::Json<client::ExtendLeaseReq>,
+) -> actix_web::Result<HttpResponse> {
+    info!(
+        "Extending lease of prover_run with id: {} by {} seconds",
+        r.prover_run_id, r.extra_seconds
+    );
+    let mut storage = data
+        .access_storage()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    storage
+        .prover_schema()
+        .extend_prover_run_lease(
+            r.prover_run_id,
+            Duration::from_secs(u64::from(r.extra_seconds)),
+        )
+        .await
+        .map_err(|e| {
+            vlog::warn!("failed to extend prover run lease: {}", e);
+            actix_web::error::ErrorInternalServerError("storage layer error")
+        })?;
 
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Reason the published proof was not accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PublishProofError {
+    /// Proof is invalid, the reason is sent back to the prover.
+    Rejected(String),
+    /// Different proof is already stored for the block.
+    Conflict,
+    /// Proof can't be verified or stored.
+    Internal(String),
+}
+
+/// Invalid proof is rejected with `400 Bad Request` and the reason in the body.
+impl From<PublishProofError> for actix_web::Error {
+    fn from(e: PublishProofError) -> Self {
+        match e {
+            PublishProofError::Rejected(reason) => actix_web::error::ErrorBadRequest(reason),
+            PublishProofError::Conflict => actix_web::error::ErrorConflict("proof conflict"),
+            PublishProofError::Internal(reason) => {
+                actix_web::error::ErrorInternalServerError(reason)
+            }
+        }
+    }
+}
+
+/// Verifies the published proof of the block, unless the verification is disabled.
+async fn verify_published_proof(
+    proof_verifier: Option<&ProofVerifier>,
+    storage: &mut StorageProcessor<'_>,
+    block: BlockNumber,
+    proof: &EncodedProofPlonk,
+) -> Result<(), PublishProofError> {
+    let proof_verifier = match proof_verifier {
+        Some(proof_verifier) => proof_verifier,
+        None => return Ok(()),
+    };
+    proof_verifier
+        .verify(storage, block, proof)
+        .await
+        .map_err(|e| match e {
+            ProofVerificationError::Rejected(reason) => {
+                vlog::warn!("rejected the proof of block {}: {}", block, reason);
+                PublishProofError::Rejected(reason)
+            }
+            ProofVerificationError::Internal(reason) => {
+                vlog::error!("failed to verify the proof of block {}: {}", block, reason);
+                PublishProofError::Internal(reason)
+            }
+        })
+}
+
+/// Verifies the published proof and stores it, see `store_published_proof`.
+/// Shared by the HTTP and gRPC servers, so neither of them stores the unverified proofs.
+async fn publish_proof(
+    proof_verifier: Option<&ProofVerifier>,
+    storage: &mut StorageProcessor<'_>,
+    block: BlockNumber,
+    proof: &EncodedProofPlonk,
+    stats: &ProvingStats,
+) -> Result<(), PublishProofError> {
+    verify_published_proof(proof_verifier, storage, block, proof).await?;
+    store_published_proof(storage, block, proof, stats)
+        .await
+        .map_err(|e| match e {
+            StoreProofError::Conflict => PublishProofError::Conflict,
+            StoreProofError::Storage => {
+                PublishProofError::Internal("storage layer error".to_string())
+            }
+        })
+}
+
+/// Reason the published proof was not stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StoreProofError {
+    /// Different proof is already stored for the block.
+    Conflict,
+    Storage,
+}
+
+/// Stores the published proof. Publishing the same proof again (e.g. when the prover didn't get
+/// the response for the previous attempt) is accepted, while a different proof for the already
+/// proven block is a conflict. Statistics of the proof generation are stored along with the proof.
+async fn store_published_proof(
+    storage: &mut StorageProcessor<'_>,
+    block: BlockNumber,
+    proof: &EncodedProofPlonk,
+    stats: &ProvingStats,
+) -> Result<(), StoreProofError> {
+    if let Some(stored) = load_stored_proof(storage, block).await? {
+        return check_stored_proof(block, &stored, proof);
+    }
+
+    if let Err(e) = storage
+        .prover_schema()
+        .store_proof_with_stats(block, proof, stats)
+        .await
+    {
+        // Proof may be stored concurrently by the request sent before.
+        if e.to_string().contains("duplicate key") {
+            if let Some(stored) = load_stored_proof(storage, block).await? {
+                return check_stored_proof(block, &stored, proof);
+            }
+        }
+        vlog::error!("failed to store received proof: {}", e);
+        return Err(StoreProofError::Storage);
+    }
+    Ok(())
+}
+
+async fn load_stored_proof(
+    storage: &mut StorageProcessor<'_>,
+    block: BlockNumber,
+) -> Result<Option<EncodedProofPlonk>, StoreProofError> {
+    storage
+        .prover_schema()
+        .load_proof(block)
+        .await
+        .map_err(|e| {
+            vlog::error!("failed to load stored proof: {}", e);
+            StoreProofError::Storage
+        })
+}
+
+fn check_stored_proof(
+    block: BlockNumber,
+    stored: &EncodedProofPlonk,
+    proof: &EncodedProofPlonk,
+) -> Result<(), StoreProofError> {
+    if stored == proof {
+        info!("Proof for block {} is already stored", block);
+        Ok(())
+    } else {
+        vlog::warn!(
+            "Received a different proof for already proven block {}",
+            block
+        );
+        Err(StoreProofError::Conflict)
+    }
+}
+
 async fn publish(
     data: web::Data<AppState>,
     r: web::Json<client::PublishReq>,
@@ -168,17 +713,84 @@ async fn publish(
         .access_storage()
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
-    if let Err(e) = storage.prover_schema().store_proof(r.block, &r.proof).await {
-        vlog::error!("failed to store received proof: {}", e);
-        let message = if e.to_string().contains("duplicate key") {
-            "duplicate key"
-        } else {
-            "storage layer error"
-        };
-        return Err(actix_web::error::ErrorInternalServerError(message));
+    let proof_verifier = data.proof_verifier.as_ref();
+    match publish_proof(proof_verifier, &mut storage, r.block, &r.proof, &r.stats).await {
+        Ok(()) => Ok(HttpResponse::Ok().finish()),
+        Err(PublishProofError::Conflict) => {
+            Ok(HttpResponse::Conflict().json(client::PublishConflictRes {
+                already_published: true,
+            }))
+        }
+        Err(e) => Err(e.into()),
     }
+}
 
-    Ok(HttpResponse::Ok().finish())
+/// Stores the proofs of several blocks in a single transaction, so either all of them
+/// are stored or none. Blocks of the batch must be strictly increasing.
+/// Responds with the outcome for every proof of the batch.
+async fn publish_batch(
+    data: web::Data<AppState>,
+    r: web::Json<Vec<client::PublishReq>>,
+) -> actix_web::Result<HttpResponse> {
+    let batch = r.into_inner();
+    info!(
+        "Received a batch of proofs for blocks: {:?}",
+        batch.iter().map(|item| item.block).collect::<Vec<_>>()
+    );
+    if batch
+        .windows(2)
+        .any(|items| items[0].block >= items[1].block)
+    {
+        return Err(actix_web::error::ErrorBadRequest(
+            "blocks of the batch must be strictly increasing",
+        ));
+    }
+
+    let mut storage = data
+        .access_storage()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    // Batch with an invalid proof is rejected as a whole, like the malformed one.
+    for item in &batch {
+        verify_published_proof(
+            data.proof_verifier.as_ref(),
+            &mut storage,
+            item.block,
+            &item.proof,
+        )
+        .await?;
+    }
+    let mut transaction = storage.start_transaction().await.map_err(|e| {
+        vlog::warn!("failed to start transaction: {}", e);
+        actix_web::error::ErrorInternalServerError("storage layer error")
+    })?;
+    let mut results = Vec::with_capacity(batch.len());
+    for item in &batch {
+        match store_published_proof(&mut transaction, item.block, &item.proof, &item.stats).await {
+            Ok(()) => results.push(client::PublishResult::Stored),
+            Err(StoreProofError::Conflict) => results.push(client::PublishResult::Conflict),
+            Err(StoreProofError::Storage) => {
+                return Err(actix_web::error::ErrorInternalServerError(
+                    "storage layer error",
+                ))
+            }
+        }
+    }
+
+    if results.contains(&client::PublishResult::Conflict) {
+        // Transaction is rolled back once dropped, so the valid proofs are not stored either.
+        for result in &mut results {
+            if *result == client::PublishResult::Stored {
+                *result = client::PublishResult::NotCommitted;
+            }
+        }
+    } else {
+        transaction.commit().await.map_err(|e| {
+            vlog::warn!("failed to commit published proofs: {}", e);
+            actix_web::error::ErrorInternalServerError("storage layer error")
+        })?;
+    }
+    Ok(HttpResponse::Ok().json(results))
 }
 
 async fn stopped(
@@ -217,6 +829,32 @@ async fn stopped(
             vlog::warn!("failed to record prover stop: {}", e);
             actix_web::error::ErrorInternalServerError("storage layer error")
         })?;
+    data.prover_registry.stopped(prover_id);
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn record_failure(
+    data: web::Data<AppState>,
+    r: web::Json<client::RecordFailureReq>,
+) -> actix_web::Result<HttpResponse> {
+    vlog::warn!(
+        "prover_run with id: {} failed: {}",
+        r.prover_run_id,
+        r.reason
+    );
+    let mut storage = data
+        .access_storage()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    storage
+        .prover_schema()
+        .record_prover_job_failure(r.prover_run_id, &r.reason)
+        .await
+        .map_err(|e| {
+            vlog::warn!("failed to record prover job failure: {}", e);
+            actix_web::error::ErrorInternalServerError("storage layer error")
+        })?;
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -252,14 +890,239 @@ async fn required_replicas(
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Returns the latest reported progress of the jobs being proved.
+async fn jobs_progress(data: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(data.jobs_progress.all()))
+}
+
+/// Lists the registered provers along with the jobs they are working on and their attempts
+/// to prove the blocks which are not proved yet.
+/// Protected by the shared secret of the provers, as the rest of the server.
+async fn admin_provers(data: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    let mut storage = data.access_storage().await?;
+    let provers = storage.prover_schema().load_provers().await.map_err(|e| {
+        vlog::warn!("Failed to load provers: {}", e);
+        actix_web::error::ErrorInternalServerError(e)
+    })?;
+    let ongoing_runs = storage
+        .prover_schema()
+        .ongoing_prover_runs(data.prover_timeout)
+        .await
+        .map_err(|e| {
+            vlog::warn!("Failed to load ongoing prover runs: {}", e);
+            actix_web::error::ErrorInternalServerError(e)
+        })?;
+    let unproved_runs = storage
+        .prover_schema()
+        .unproved_prover_runs()
+        .await
+        .map_err(|e| {
+            vlog::warn!("Failed to load prover runs: {}", e);
+            actix_web::error::ErrorInternalServerError(e)
+        })?;
+    let failures = storage
+        .prover_schema()
+        .unproved_prover_job_failures()
+        .await
+        .map_err(|e| {
+            vlog::warn!("Failed to load prover job failures: {}", e);
+            actix_web::error::ErrorInternalServerError(e)
+        })?;
+    let jobs_history = provers_status::JobsHistory {
+        ongoing_runs,
+        unproved_runs,
+        failures,
+        max_block_attempts: data.max_block_attempts,
+    };
+    Ok(HttpResponse::Ok().json(provers_status::provers_status(
+        provers,
+        jobs_history,
+        &data.jobs_progress,
+    )))
+}
+
+/// Lists the blocks awaiting for proof in the order they're assigned to the provers,
+/// i.e. the blocks with fast withdrawals first, then the oldest ones.
+async fn admin_queue(data: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    let mut storage = data.access_storage().await?;
+    let queue = storage.prover_schema().proving_queue().await.map_err(|e| {
+        vlog::warn!("Failed to load proving queue: {}", e);
+        actix_web::error::ErrorInternalServerError("storage layer error")
+    })?;
+    Ok(HttpResponse::Ok().json(queue))
+}
+
+/// Frees the block of the stuck job, e.g. of the dead prover, so it's assigned to the next prover
+/// asking for a job without waiting for the prover timeout. Responds with `404 Not Found`
+/// if the block has no job and `409 Conflict` if the block is proved already.
+async fn admin_requeue(
+    data: web::Data<AppState>,
+    block: web::Path<BlockNumber>,
+) -> actix_web::Result<HttpResponse> {
+    let block = block.into_inner();
+    let mut storage = data.access_storage().await?;
+    let proof = storage
+        .prover_schema()
+        .load_proof(block)
+        .await
+        .map_err(|e| {
+            vlog::warn!("Failed to load proof: {}", e);
+            actix_web::error::ErrorInternalServerError("storage layer error")
+        })?;
+    if proof.is_some() {
+        return Err(actix_web::error::ErrorConflict("block is proved already"));
+    }
+    let removed_jobs = storage
+        .prover_schema()
+        .requeue_block(block)
+        .await
+        .map_err(|e| {
+            vlog::warn!("Failed to requeue block: {}", e);
+            actix_web::error::ErrorInternalServerError("storage layer error")
+        })?;
+    if removed_jobs == 0 {
+        return Err(actix_web::error::ErrorNotFound("block has no pending job"));
+    }
+    info!(
+        "Block {} is requeued by the operator, {} job(s) removed",
+        block, removed_jobs
+    );
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Reports whether the proof of the block is stored, so the operators can wait for it
+/// before the further actions with the block.
+async fn block_proof_status(
+    data: web::Data<AppState>,
+    block: web::Path<BlockNumber>,
+) -> actix_web::Result<HttpResponse> {
+    let block = block.into_inner();
+    let mut storage = data.access_storage().await?;
+    let proof_created_at = storage
+        .prover_schema()
+        .load_proof_created_at(block)
+        .await
+        .map_err(|e| {
+            vlog::warn!("Failed to load proof: {}", e);
+            actix_web::error::ErrorInternalServerError("storage layer error")
+        })?;
+    let status = if proof_created_at.is_some() {
+        client::ProofState::Proved
+    } else {
+        let last_committed_block = storage
+            .chain()
+            .block_schema()
+            .get_last_committed_block()
+            .await
+            .map_err(|e| {
+                vlog::warn!("Failed to load last committed block: {}", e);
+                actix_web::error::ErrorInternalServerError("storage layer error")
+            })?;
+        if block > 0 && block <= last_committed_block {
+            client::ProofState::Pending
+        } else {
+            client::ProofState::NotFound
+        }
+    };
+    Ok(HttpResponse::Ok().json(client::ProofStatus {
+        block: i64::from(block),
+        status,
+        proof_created_at,
+    }))
+}
+
+/// Lists the running provers known to the server, without querying the database.
+async fn provers(data: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    data.prover_registry.evict_gone(data.prover_timeout);
+    Ok(HttpResponse::Ok().json(data.prover_registry.all()))
+}
+
+/// Periodically forgets the provers gone without stopping, e.g. killed ones.
+async fn evict_gone_provers(prover_registry: ProverRegistry, prover_timeout: Duration) {
+    let mut timer = tokio::time::interval(prover_timeout);
+    loop {
+        timer.tick().await;
+        for id in prover_registry.evict_gone(prover_timeout) {
+            info!("Prover with ID {} is gone, removed from the registry", id);
+        }
+    }
+}
+
+/// Accepts the request if its bearer token is the shared secret of the provers,
+/// otherwise responds with `401 Unauthorized`.
+fn authenticate_prover(
+    secret_auth: &str,
+    req: ServiceRequest,
+    credentials: &BearerAuth,
+) -> Result<ServiceRequest, actix_web::Error> {
+    if credentials.token() == secret_auth {
+        Ok(req)
+    } else {
+        let config = req.app_data::<Config>().cloned().unwrap_or_default();
+        Err(AuthenticationError::from(config).into())
+    }
+}
+
+/// Paths of the prover requests recorded as the tracing spans, see `request_span`.
+const TRACED_PATHS: &[&str] = &["/block_to_prove", "/prover_data", "/publish"];
+/// Response header with the ID of the trace the request is recorded to.
+const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// Span of the prover request, continuing the trace of the prover if it sent the `traceparent` header.
+/// Requests to the paths other than `TRACED_PATHS` are not recorded.
+fn request_span(req: &ServiceRequest) -> tracing::Span {
+    if !TRACED_PATHS.contains(&req.path()) {
+        return tracing::Span::none();
+    }
+    let span = tracing::info_span!("prover_server_request", otel.name = req.path());
+    let headers = req
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    telemetry::set_parent_from_headers(&span, &headers);
+    span
+}
+
+/// Handle of the running HTTP prover server, returned by `start_prover_server`.
+#[derive(Clone)]
+pub struct ServerHandle {
+    server: Server,
+    local_addr: SocketAddr,
+}
+
+impl ServerHandle {
+    /// Address the server listens on, e.g. the actual port if it was bound to the port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops the server. Graceful stop waits for the requests in progress to be processed.
+    /// Witness generators keep running.
+    pub fn stop(&self, graceful: bool) -> impl Future<Output = ()> {
+        self.server.stop(graceful)
+    }
+}
+
+/// Starts the HTTP prover server and the witness generators.
+/// Witness generators wait `rounds_interval` between the rounds without the new blocks.
+/// Progress of the jobs reported by the provers is recorded to `jobs_progress`,
+/// and the running provers to `prover_registry`.
+/// Job requests of the workers are limited by `rate_limiter`, which should be shared
+/// with the gRPC server, so the provers can't bypass the limit by using both APIs.
+/// Returns once the server is bound to its address.
 #[allow(clippy::too_many_arguments)]
 pub fn start_prover_server(
     connection_pool: storage::ConnectionPool,
     prover_timeout: time::Duration,
-    rounds_interval: time::Duration,
-    panic_notify: mpsc::Sender<bool>,
+    rounds_interval: RoundsIntervalScheduler,
+    panic_notify: mpsc::Sender<Option<String>>,
     config_options: ConfigurationOptions,
-) {
+    jobs_progress: JobsProgress,
+    prover_registry: ProverRegistry,
+    rate_limiter: WorkerRateLimiter,
+) -> ServerHandle {
+    let (handle_sender, handle_receiver) = std_mpsc::channel();
     thread::Builder::new()
         .name("prover_server".to_string())
         .spawn(move || {
@@ -282,51 +1145,163 @@ pub fn start_prover_server(
                         as usize
                 };
 
-                // Start pool maintainer threads.
-                for offset in 0..config_options.witness_generators {
-                    let start_block = (last_verified_block + offset + 1) as u32;
-                    let block_step = config_options.witness_generators as u32;
-                    info!(
-                        "Starting witness generator ({},{})",
-                        start_block, block_step
-                    );
+                // Start pool maintainer threads, waking up the provers waiting for the new jobs.
+                let (new_witness, _) = broadcast::channel(NEW_WITNESS_CHANNEL_CAPACITY);
+                let prover_data_parts = ProverDataParts::new();
+                let witness_heartbeat = WitnessHeartbeat::new();
+                let witness_queue =
+                    witness_generator::WitnessQueue::new((last_verified_block + 1) as u32);
+                info!(
+                    "Starting {} witness generators from block {}",
+                    config_options.witness_generators,
+                    last_verified_block + 1
+                );
+                for worker in 0..config_options.witness_generators {
                     let pool_maintainer = witness_generator::WitnessGenerator::new(
                         connection_pool.clone(),
-                        rounds_interval,
-                        start_block,
-                        block_step,
+                        rounds_interval.clone(),
+                        worker,
+                        witness_queue.clone(),
+                        new_witness.clone(),
+                        prover_data_parts.clone(),
+                        witness_heartbeat.clone(),
                     );
                     pool_maintainer.start(panic_notify.clone());
                 }
 
                 // Start HTTP server.
                 let idle_provers = config_options.idle_provers;
-                HttpServer::new(move || {
-                    let app_state =
-                        AppState::new(connection_pool.clone(), prover_timeout, idle_provers);
+                actix_rt::spawn(evict_gone_provers(prover_registry.clone(), prover_timeout));
+                let secret_auth = config_options.prover_secret_auth.clone();
+                let vk_hashes = Arc::new(config_options.prover_vk_hashes.clone());
+                if vk_hashes.is_empty() {
+                    warn!(
+                        "PROVER_VK_HASHES is not set, verification keys of provers are not checked"
+                    );
+                }
+                if secret_auth.is_none() {
+                    warn!("PROVER_SECRET_AUTH is not set, provers are not authenticated");
+                }
+                let proof_verifier = if config_options.prover_verify_proofs {
+                    Some(ProofVerifier::new())
+                } else {
+                    warn!("PROVER_SERVER_VERIFY_PROOFS is disabled, published proofs are not verified");
+                    None
+                };
+                let server = HttpServer::new(move || {
+                    let app_state = AppState::new(
+                        connection_pool.clone(),
+                        prover_timeout,
+                        idle_provers,
+                        jobs_progress.clone(),
+                        prover_registry.clone(),
+                        prover_data_parts.clone(),
+                        new_witness.clone(),
+                        rounds_interval.clone(),
+                        vk_hashes.clone(),
+                        proof_verifier.clone(),
+                        config_options.prover_max_block_attempts,
+                        witness_heartbeat.clone(),
+                        rate_limiter.clone(),
+                    );
+
+                    // Provers authenticate with the shared secret, if it's set.
+                    let secret = secret_auth.clone().unwrap_or_default();
+                    let auth = HttpAuthentication::bearer(move |req, credentials| {
+                        let result = authenticate_prover(&secret, req, &credentials);
+                        async move { result }
+                    });
 
                     // By calling `register_data` instead of `data` we're avoiding double
                     // `Arc` wrapping of the object.
                     App::new()
                         .wrap(actix_web::middleware::Logger::default())
+                        // Prover data is large and repetitive, so it's compressed for the provers
+                        // accepting the compressed response.
+                        .wrap(Compress::default())
+                        .wrap_fn(|req, srv| {
+                            let span = request_span(&req);
+                            let response = srv.call(req).instrument(span.clone());
+                            async move {
+                                let mut response = response.await?;
+                                if let Some(trace_id) = telemetry::trace_id(&span) {
+                                    response.headers_mut().insert(
+                                        HeaderName::from_static(TRACE_ID_HEADER),
+                                        HeaderValue::from_str(&trace_id)
+                                            .expect("trace ID is not a valid header"),
+                                    );
+                                }
+                                Ok(response)
+                            }
+                        })
                         .app_data(web::Data::new(app_state))
+                        // Probes of the orchestrator don't know the secret of the provers.
                         .route("/status", web::get().to(status))
-                        .route("/register", web::post().to(register))
-                        .route("/block_to_prove", web::get().to(block_to_prove))
-                        .route("/working_on", web::post().to(working_on))
-                        .route("/prover_data", web::get().to(prover_data))
-                        .route("/publish", web::post().to(publish))
-                        .route("/stopped", web::post().to(stopped))
-                        .route(
-                            "/api/internal/prover/replicas",
-                            web::post().to(required_replicas),
+                        .route("/healthz", web::get().to(healthz))
+                        .route("/readyz", web::get().to(readyz))
+                        .service(
+                            web::scope("")
+                                .wrap(Condition::new(secret_auth.is_some(), auth))
+                                .route("/health", web::get().to(health))
+                                .route("/register", web::post().to(register))
+                                .route("/check_vk", web::post().to(check_vk))
+                                .route("/block_to_prove", web::get().to(block_to_prove))
+                                .route("/block_to_prove_wait", web::get().to(block_to_prove_wait))
+                                .route("/working_on", web::post().to(working_on))
+                                .route("/extend_lease", web::post().to(extend_lease))
+                                .route("/prover_data", web::get().to(prover_data))
+                                .route(
+                                    "/prover_data/{block}/part/{part}",
+                                    web::get().to(prover_data_part),
+                                )
+                                .route(
+                                    "/verified_block_prover_data",
+                                    web::get().to(verified_block_prover_data),
+                                )
+                                .route("/publish", web::post().to(publish))
+                                .route("/publish_batch", web::post().to(publish_batch))
+                                .route("/block/{block}/proof", web::get().to(block_proof_status))
+                                .route("/stopped", web::post().to(stopped))
+                                .route("/record_failure", web::post().to(record_failure))
+                                .route(
+                                    "/api/internal/prover/replicas",
+                                    web::post().to(required_replicas),
+                                )
+                                .route(
+                                    "/api/internal/prover/jobs_progress",
+                                    web::get().to(jobs_progress),
+                                )
+                                .route("/provers", web::get().to(provers))
+                                .route("/admin/provers", web::get().to(admin_provers))
+                                .route("/admin/queue", web::get().to(admin_queue))
+                                .route("/admin/requeue/{block}", web::post().to(admin_requeue))
                         )
                 })
-                .bind(&config_options.prover_server_address)
-                .expect("failed to bind")
-                .run()
-                .await
+                .keep_alive(KEEP_ALIVE_SECS);
+                // Provers must present the certificate signed by the CA, if TLS is configured.
+                let server = match &config_options.prover_tls {
+                    Some(tls_config) => {
+                        let tls_config = tls::load_server_config(tls_config)
+                            .expect("failed to load prover server TLS config");
+                        server.bind_rustls(&config_options.prover_server_address, tls_config)
+                    }
+                    None => server.bind(&config_options.prover_server_address),
+                }
+                .expect("failed to bind");
+                let local_addr = server.addrs()[0];
+                let server = server.run();
+
+                // Receiver is gone if the caller has panicked.
+                let _ = handle_sender.send(ServerHandle {
+                    server: server.clone(),
+                    local_addr,
+                });
+                server.await
             })
         })
         .expect("failed to start prover server");
+
+    handle_receiver
+        .recv()
+        .expect("prover server thread has stopped before binding")
 }
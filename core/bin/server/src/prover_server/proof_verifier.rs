@@ -0,0 +1,129 @@
+//! Verification of the published proofs, so the invalid ones are rejected by the server
+//! rather than by the contract, after the Ethereum transaction is paid for.
+
+// Built-in
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+// External
+use actix_web::error::BlockingError;
+use actix_web::web;
+use failure::Fail;
+// Workspace deps
+use models::node::BlockNumber;
+use models::primitives::serialize_fe_for_ethereum;
+use models::prover_utils::{EncodedProofPlonk, PlonkVerificationKey};
+use prover::prover_data::ProverData;
+use storage::StorageProcessor;
+
+/// Reason of the published proof not being accepted.
+#[derive(Debug, Fail)]
+pub enum ProofVerificationError {
+    /// Proof is invalid, the prover should not publish it again.
+    #[fail(display = "{}", _0)]
+    Rejected(String),
+    /// Proof can't be checked, e.g. the verification key is missing.
+    #[fail(display = "{}", _0)]
+    Internal(String),
+}
+
+/// Verifies the published proofs with the verification keys of the block sizes, which are loaded
+/// on the first use. Cloned verifiers share the loaded keys.
+#[derive(Clone, Default)]
+pub struct ProofVerifier {
+    keys: Arc<Mutex<HashMap<usize, Arc<PlonkVerificationKey>>>>,
+}
+
+impl fmt::Debug for ProofVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut block_sizes: Vec<_> = self.keys.lock().unwrap().keys().cloned().collect();
+        block_sizes.sort();
+        f.debug_struct("ProofVerifier")
+            .field("loaded_block_sizes", &block_sizes)
+            .finish()
+    }
+}
+
+impl ProofVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(&self, block_size: usize) -> Result<Arc<PlonkVerificationKey>, failure::Error> {
+        let mut keys = self.keys.lock().unwrap();
+        if let Some(key) = keys.get(&block_size) {
+            return Ok(Arc::clone(key));
+        }
+        let key =
+            Arc::new(PlonkVerificationKey::read_verification_key_for_main_circuit(block_size)?);
+        keys.insert(block_size, Arc::clone(&key));
+        Ok(key)
+    }
+
+    /// Checks that the proof is valid and proves the public data of the block,
+    /// which is taken from the stored prover data.
+    pub async fn verify(
+        &self,
+        storage: &mut StorageProcessor<'_>,
+        block: BlockNumber,
+        proof: &EncodedProofPlonk,
+    ) -> Result<(), ProofVerificationError> {
+        let witness = storage
+            .prover_schema()
+            .get_witness(block)
+            .await
+            .map_err(|e| {
+                vlog::warn!("failed to load witness of block {}: {}", block, e);
+                ProofVerificationError::Internal("storage layer error".to_string())
+            })?
+            .ok_or_else(|| {
+                ProofVerificationError::Rejected(format!("block {} is not being proven", block))
+            })?;
+        let prover_data: ProverData = serde_json::from_value(witness).map_err(|e| {
+            ProofVerificationError::Internal(format!(
+                "stored prover data of block {} is malformed: {}",
+                block, e
+            ))
+        })?;
+        let commitment = serialize_fe_for_ethereum(&prover_data.public_data_commitment);
+        if proof.inputs.first() != Some(&commitment) {
+            return Err(ProofVerificationError::Rejected(format!(
+                "proof is not for the public data commitment of block {}",
+                block
+            )));
+        }
+
+        let block_size = prover_data.operations.len();
+        let verifier = self.clone();
+        let proof = proof.clone();
+        // Verification takes a while, so it doesn't block the server workers.
+        let valid = web::block(move || {
+            let key = verifier.key(block_size).map_err(|e| {
+                ProofVerificationError::Internal(format!(
+                    "failed to load verification key for block size {}: {}",
+                    block_size, e
+                ))
+            })?;
+            key.verify_encoded_proof(&proof).map_err(|e| {
+                ProofVerificationError::Rejected(format!(
+                    "malformed proof of block {}: {}",
+                    block, e
+                ))
+            })
+        })
+        .await
+        .map_err(|e| match e {
+            BlockingError::Error(e) => e,
+            BlockingError::Canceled => {
+                ProofVerificationError::Internal("proof verification is canceled".to_string())
+            }
+        })?;
+        if !valid {
+            return Err(ProofVerificationError::Rejected(format!(
+                "proof of block {} is invalid",
+                block
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//! Prover data served in parts, so the provers can download the large blocks piece by piece.
+
+// Built-in
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+// External
+use actix_web::http::header::ACCEPT;
+use actix_web::HttpRequest;
+// Workspace deps
+use models::node::BlockNumber;
+use prover::prover_data::{self, ProverData};
+
+/// Size of every part of the encoded prover data, except for the last one.
+pub const PROVER_DATA_PART_SIZE: usize = 1 << 20;
+/// Amount of the blocks whose encoded prover data is kept in memory.
+const CACHED_BLOCKS: usize = 4;
+
+/// Encoding of the served prover data. Binary encoding is used for the provers accepting it,
+/// the others get JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProverDataEncoding {
+    Json,
+    Binary,
+}
+
+impl ProverDataEncoding {
+    /// Picks the encoding accepted by the request.
+    pub fn accepted_by(req: &HttpRequest) -> Self {
+        let accepts_binary = req
+            .headers()
+            .get_all(ACCEPT)
+            .filter_map(|accept| accept.to_str().ok())
+            .flat_map(|accept| accept.split(','))
+            .any(|media_type| {
+                media_type.split(';').next().unwrap_or("").trim()
+                    == prover_data::BINARY_CONTENT_TYPE
+            });
+        if accepts_binary {
+            ProverDataEncoding::Binary
+        } else {
+            ProverDataEncoding::Json
+        }
+    }
+
+    /// Encodes the witness as stored by the witness generator, `None` if it's absent.
+    pub fn encode(self, witness: &Option<serde_json::Value>) -> Result<Vec<u8>, failure::Error> {
+        match self {
+            ProverDataEncoding::Json => Ok(serde_json::to_vec(witness)?),
+            ProverDataEncoding::Binary => {
+                let prover_data: Option<ProverData> =
+                    witness.clone().map(serde_json::from_value).transpose()?;
+                prover_data::encode_binary(prover_data.as_ref())
+            }
+        }
+    }
+}
+
+/// Recently requested encoded prover data, shared by the workers of the HTTP prover server,
+/// so the witness is loaded from the storage once per block rather than once per part.
+#[derive(Debug, Clone, Default)]
+pub struct ProverDataParts {
+    blocks: Arc<Mutex<VecDeque<(BlockNumber, ProverDataEncoding, Arc<Vec<u8>>)>>>,
+}
+
+impl ProverDataParts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the encoded prover data of the block, if it's cached.
+    pub fn get(&self, block: BlockNumber, encoding: ProverDataEncoding) -> Option<Arc<Vec<u8>>> {
+        let blocks = self
+            .blocks
+            .lock()
+            .expect("prover data parts lock is poisoned");
+        blocks
+            .iter()
+            .find(|(cached_block, cached_encoding, _)| {
+                *cached_block == block && *cached_encoding == encoding
+            })
+            .map(|(_, _, data)| Arc::clone(data))
+    }
+
+    /// Caches the encoded prover data of the block, evicting the oldest entry if needed.
+    /// Every encoding of the block takes its own entry.
+    pub fn insert(&self, block: BlockNumber, encoding: ProverDataEncoding, data: Arc<Vec<u8>>) {
+        let mut blocks = self
+            .blocks
+            .lock()
+            .expect("prover data parts lock is poisoned");
+        blocks.retain(|(cached_block, cached_encoding, _)| {
+            *cached_block != block || *cached_encoding != encoding
+        });
+        if blocks.len() >= CACHED_BLOCKS {
+            blocks.pop_front();
+        }
+        blocks.push_back((block, encoding, data));
+    }
+
+    /// Forgets the encoded prover data of the block, e.g. once its witness is regenerated.
+    pub fn remove(&self, block: BlockNumber) {
+        let mut blocks = self
+            .blocks
+            .lock()
+            .expect("prover data parts lock is poisoned");
+        blocks.retain(|(cached_block, _, _)| *cached_block != block);
+    }
+}
+
+/// Returns the part of the encoded prover data along with the total amount of parts,
+/// or `None` if there is no such part. Data is never split into zero parts, even if empty.
+pub fn data_part(data: &[u8], part: usize) -> Option<(&[u8], usize)> {
+    let parts = std::cmp::max(
+        1,
+        (data.len() + PROVER_DATA_PART_SIZE - 1) / PROVER_DATA_PART_SIZE,
+    );
+    if part >= parts {
+        return None;
+    }
+    let start = part * PROVER_DATA_PART_SIZE;
+    let end = std::cmp::min(start + PROVER_DATA_PART_SIZE, data.len());
+    Some((&data[start..end], parts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_is_split_into_parts() {
+        let data: Vec<u8> = (0..PROVER_DATA_PART_SIZE * 2 + 10)
+            .map(|i| i as u8)
+            .collect();
+        let parts: Vec<_> = (0..3)
+            .map(|part| data_part(&data, part).expect("part is missing"))
+            .collect();
+        assert!(data_part(&data, 3).is_none());
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|(_, total)| *total == 3));
+        assert_eq!(parts[2].0.len(), 10);
+        let joined: Vec<u8> = parts
+            .iter()
+            .flat_map(|(part, _)| part.iter().copied())
+            .collect();
+        assert_eq!(joined, data);
+
+        // Absent witness is encoded as `null`, which fits into a single part.
+        assert_eq!(data_part(b"null", 0), Some((&b"null"[..], 1)));
+        assert_eq!(data_part(b"null", 1), None);
+        assert_eq!(data_part(b"", 0), Some((&b""[..], 1)));
+    }
+
+    #[test]
+    fn oldest_block_is_evicted() {
+        let parts = ProverDataParts::new();
+        for block in 0..=CACHED_BLOCKS as BlockNumber {
+            parts.insert(block, ProverDataEncoding::Json, Arc::new(vec![block as u8]));
+        }
+        assert!(parts.get(0, ProverDataEncoding::Json).is_none());
+        assert_eq!(
+            parts
+                .get(CACHED_BLOCKS as BlockNumber, ProverDataEncoding::Json)
+                .as_deref(),
+            Some(&vec![CACHED_BLOCKS as u8])
+        );
+
+        parts.remove(1);
+        assert!(parts.get(1, ProverDataEncoding::Json).is_none());
+        assert!(parts.get(2, ProverDataEncoding::Json).is_some());
+    }
+
+    #[test]
+    fn encodings_are_cached_separately() {
+        let parts = ProverDataParts::new();
+        parts.insert(1, ProverDataEncoding::Json, Arc::new(b"null".to_vec()));
+        assert!(parts.get(1, ProverDataEncoding::Binary).is_none());
+
+        let binary = ProverDataEncoding::Binary.encode(&None).unwrap();
+        parts.insert(1, ProverDataEncoding::Binary, Arc::new(binary.clone()));
+        assert_eq!(
+            parts.get(1, ProverDataEncoding::Json).as_deref(),
+            Some(&b"null".to_vec())
+        );
+        assert_eq!(
+            parts.get(1, ProverDataEncoding::Binary).as_deref(),
+            Some(&binary)
+        );
+        assert!(prover_data::decode(&binary).unwrap().is_none());
+
+        parts.remove(1);
+        assert!(parts.get(1, ProverDataEncoding::Json).is_none());
+        assert!(parts.get(1, ProverDataEncoding::Binary).is_none());
+    }
+}
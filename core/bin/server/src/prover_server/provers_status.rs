@@ -0,0 +1,325 @@
+//! Status of the registered provers and their current jobs, for the operators.
+
+// Built-in
+use std::collections::{BTreeMap, HashMap, HashSet};
+// External
+use chrono::{DateTime, Utc};
+// Workspace deps
+use prover::ProvingStage;
+use storage::prover::records::{ActiveProver, ProverJobFailure, ProverRun};
+// Local deps
+use super::JobsProgress;
+
+/// Registered prover along with the job it's working on, see `/admin/provers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProverStatus {
+    pub id: i32,
+    pub worker: String,
+    pub registered_at: DateTime<Utc>,
+    /// Moment the prover was deregistered, `None` if it's still running.
+    pub stopped_at: Option<DateTime<Utc>>,
+    /// Latest heartbeat of the current job, `None` if the prover has no job.
+    pub last_heartbeat: Option<DateTime<Utc>>,
+    pub current_job: Option<ProverJob>,
+    /// Attempts of the prover to prove the blocks which are not proved yet, oldest first.
+    /// Listed for the latest registration of the worker only.
+    pub job_history: Vec<JobAttempt>,
+    /// Blocks not assigned to the prover anymore, since it has failed or timed out on them
+    /// the max amount of times.
+    pub excluded_blocks: Vec<i64>,
+}
+
+/// Job the prover is working on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProverJob {
+    pub job_id: i32,
+    pub block: i64,
+    /// Stage reported with the latest heartbeat, `None` if the prover doesn't report the progress.
+    pub stage: Option<ProvingStage>,
+}
+
+/// Attempt of the prover to prove the block, see `ProverStatus::job_history`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobAttempt {
+    pub job_id: i32,
+    pub block: i64,
+    /// Moment the job was assigned to the prover, `None` for the failures recorded before
+    /// it was tracked.
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub outcome: JobOutcome,
+}
+
+/// Outcome of the `JobAttempt`, failed and timed out attempts are counted towards
+/// `ConfigurationOptions::prover_max_block_attempts`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobOutcome {
+    InProgress,
+    /// Prover stopped sending the heartbeats.
+    TimedOut,
+    /// Prover reported the failure.
+    Failed {
+        reason: String,
+    },
+}
+
+/// Jobs of the blocks which are not proved yet, matched with the provers by `provers_status`.
+#[derive(Debug, Default)]
+pub struct JobsHistory {
+    /// Jobs with the recent heartbeats, see `ProverSchema::ongoing_prover_runs`.
+    pub ongoing_runs: Vec<ProverRun>,
+    /// Both ongoing and timed out jobs.
+    pub unproved_runs: Vec<ProverRun>,
+    pub failures: Vec<ProverJobFailure>,
+    /// See `ConfigurationOptions::prover_max_block_attempts`.
+    pub max_block_attempts: usize,
+}
+
+impl JobsHistory {
+    /// Attempts of every worker, ordered by the job ID.
+    fn attempts_by_worker(&self) -> HashMap<&str, Vec<JobAttempt>> {
+        let ongoing_jobs: HashSet<i32> = self.ongoing_runs.iter().map(|run| run.id).collect();
+        let mut attempts: HashMap<&str, Vec<JobAttempt>> = HashMap::new();
+        for run in &self.unproved_runs {
+            if let Some(worker) = &run.worker {
+                attempts
+                    .entry(worker.as_str())
+                    .or_default()
+                    .push(JobAttempt {
+                        job_id: run.id,
+                        block: run.block_number,
+                        claimed_at: Some(run.created_at),
+                        outcome: if ongoing_jobs.contains(&run.id) {
+                            JobOutcome::InProgress
+                        } else {
+                            JobOutcome::TimedOut
+                        },
+                    });
+            }
+        }
+        for failure in &self.failures {
+            if let Some(worker) = &failure.worker {
+                attempts
+                    .entry(worker.as_str())
+                    .or_default()
+                    .push(JobAttempt {
+                        job_id: failure.prover_run_id,
+                        block: failure.block_number,
+                        claimed_at: failure.claimed_at,
+                        outcome: JobOutcome::Failed {
+                            reason: failure.reason.clone(),
+                        },
+                    });
+            }
+        }
+        for worker_attempts in attempts.values_mut() {
+            worker_attempts.sort_by_key(|attempt| attempt.job_id);
+        }
+        attempts
+    }
+
+    /// Blocks the prover has failed or timed out on the max amount of times, in ascending order.
+    fn excluded_blocks(&self, attempts: &[JobAttempt]) -> Vec<i64> {
+        let mut unsuccessful_attempts = BTreeMap::new();
+        for attempt in attempts {
+            if attempt.outcome != JobOutcome::InProgress {
+                *unsuccessful_attempts.entry(attempt.block).or_insert(0) += 1;
+            }
+        }
+        unsuccessful_attempts
+            .into_iter()
+            .filter(|&(_, count)| count >= self.max_block_attempts)
+            .map(|(block, _)| block)
+            .collect()
+    }
+}
+
+/// Matches the ongoing jobs with the provers which took them. Jobs are assigned by the worker name,
+/// so the job of the worker registered several times belongs to its latest running registration.
+pub fn provers_status(
+    provers: Vec<ActiveProver>,
+    jobs_history: JobsHistory,
+    jobs_progress: &JobsProgress,
+) -> Vec<ProverStatus> {
+    let mut latest_registrations = HashMap::new();
+    for prover in provers.iter().filter(|prover| prover.stopped_at.is_none()) {
+        let latest = latest_registrations
+            .entry(prover.worker.as_str())
+            .or_insert(prover.id);
+        *latest = (*latest).max(prover.id);
+    }
+    let mut latest_any_registrations = HashMap::new();
+    for prover in &provers {
+        let latest = latest_any_registrations
+            .entry(prover.worker.as_str())
+            .or_insert(prover.id);
+        *latest = (*latest).max(prover.id);
+    }
+    // Runs are ordered by ID, so the latest job of the worker wins.
+    let mut current_runs = HashMap::new();
+    for run in &jobs_history.ongoing_runs {
+        if let Some(worker) = &run.worker {
+            current_runs.insert(worker.as_str(), run);
+        }
+    }
+
+    let attempts = jobs_history.attempts_by_worker();
+
+    provers
+        .iter()
+        .map(|prover| {
+            let run = latest_registrations
+                .get(prover.worker.as_str())
+                .filter(|&&id| id == prover.id)
+                .and_then(|_| current_runs.get(prover.worker.as_str()));
+            let progress = run.and_then(|run| jobs_progress.get(run.id));
+            let job_history = if latest_any_registrations[prover.worker.as_str()] == prover.id {
+                attempts
+                    .get(prover.worker.as_str())
+                    .cloned()
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            ProverStatus {
+                id: prover.id,
+                worker: prover.worker.clone(),
+                registered_at: prover.created_at,
+                stopped_at: prover.stopped_at,
+                last_heartbeat: run.map(|run| {
+                    progress.as_ref().map_or(run.updated_at, |progress| {
+                        progress.reported_at.max(run.updated_at)
+                    })
+                }),
+                current_job: run.map(|run| ProverJob {
+                    job_id: run.id,
+                    block: run.block_number,
+                    stage: progress.as_ref().map(|progress| progress.stage),
+                }),
+                excluded_blocks: jobs_history.excluded_blocks(&job_history),
+                job_history,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prover::JobProgress;
+    use std::time::Duration;
+
+    fn prover(id: i32, worker: &str, stopped: bool) -> ActiveProver {
+        ActiveProver {
+            id,
+            worker: worker.to_string(),
+            created_at: Utc::now(),
+            stopped_at: if stopped { Some(Utc::now()) } else { None },
+            block_sizes: vec![6],
+            hostname: None,
+            version: None,
+            cpu_cores: None,
+            vk_hash: None,
+        }
+    }
+
+    fn run(id: i32, block_number: i64, worker: &str) -> ProverRun {
+        ProverRun {
+            id,
+            block_number,
+            worker: Some(worker.to_string()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            extended_deadline: None,
+        }
+    }
+
+    #[test]
+    fn jobs_are_matched_with_latest_running_registration() {
+        let provers = vec![
+            prover(1, "alice", false),
+            prover(2, "alice", false),
+            prover(3, "bob", true),
+            prover(4, "carol", false),
+        ];
+        let runs = vec![run(10, 5, "alice"), run(11, 6, "bob")];
+        let jobs_progress = JobsProgress::new();
+        let progress = JobProgress {
+            stage: ProvingStage::ProofGeneration,
+            elapsed_secs: 1,
+        };
+        jobs_progress.record(10, progress, Duration::from_secs(60));
+
+        let jobs_history = JobsHistory {
+            ongoing_runs: runs,
+            ..JobsHistory::default()
+        };
+        let status = provers_status(provers, jobs_history, &jobs_progress);
+        let jobs: Vec<_> = status
+            .iter()
+            .map(|prover| prover.current_job.as_ref().map(|job| job.block))
+            .collect();
+        // Stale registration of alice and stopped bob have no jobs.
+        assert_eq!(jobs, vec![None, Some(5), None, None]);
+        assert_eq!(
+            status[1].current_job.as_ref().unwrap().stage,
+            Some(ProvingStage::ProofGeneration)
+        );
+        assert!(status[1].last_heartbeat.is_some());
+        assert!(status[3].last_heartbeat.is_none());
+        assert!(status[2].stopped_at.is_some());
+    }
+
+    fn failure(prover_run_id: i32, block_number: i64, worker: &str) -> ProverJobFailure {
+        ProverJobFailure {
+            id: prover_run_id,
+            prover_run_id,
+            block_number,
+            worker: Some(worker.to_string()),
+            reason: "failed".to_string(),
+            created_at: Utc::now(),
+            claimed_at: Some(Utc::now()),
+        }
+    }
+
+    #[test]
+    fn job_history_lists_attempts_and_excluded_blocks() {
+        let provers = vec![
+            prover(1, "alice", true),
+            prover(2, "alice", false),
+            prover(3, "bob", false),
+        ];
+        let ongoing = run(14, 6, "alice");
+        let jobs_history = JobsHistory {
+            ongoing_runs: vec![ongoing.clone()],
+            unproved_runs: vec![run(10, 5, "alice"), run(12, 5, "bob"), ongoing],
+            failures: vec![failure(11, 5, "alice"), failure(13, 6, "alice")],
+            max_block_attempts: 2,
+        };
+
+        let status = provers_status(provers, jobs_history, &JobsProgress::new());
+        // History is listed for the latest registration only.
+        assert!(status[0].job_history.is_empty());
+        let outcomes: Vec<_> = status[1]
+            .job_history
+            .iter()
+            .map(|attempt| (attempt.job_id, attempt.block, attempt.outcome.clone()))
+            .collect();
+        let failed = JobOutcome::Failed {
+            reason: "failed".to_string(),
+        };
+        assert_eq!(
+            outcomes,
+            vec![
+                (10, 5, JobOutcome::TimedOut),
+                (11, 5, failed.clone()),
+                (13, 6, failed),
+                (14, 6, JobOutcome::InProgress),
+            ]
+        );
+        // Ongoing attempt is not counted.
+        assert_eq!(status[1].excluded_blocks, vec![5]);
+        assert_eq!(status[2].job_history.len(), 1);
+        assert!(status[2].excluded_blocks.is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! Rate limit of the job requests of the prover workers.
+
+// Built-in
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Token bucket of a single worker.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Limits the rate of the requests of every worker to `rate` per second, allowing the bursts
+/// of up to `burst` requests. Workers don't share the limits, so a misconfigured prover polling
+/// in a tight loop doesn't starve the others. Cloned handles share the buckets.
+///
+/// Buckets of the workers idle for long enough to be refilled are dropped, as they don't differ
+/// from the new ones, so the buckets of the gone workers don't pile up.
+#[derive(Debug, Clone)]
+pub struct WorkerRateLimiter {
+    rate: u32,
+    burst: u32,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl WorkerRateLimiter {
+    /// Creates the limiter, zero `rate` disables the limit.
+    pub fn new(rate: u32, burst: u32) -> Self {
+        Self {
+            rate,
+            burst,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Takes a token of the worker for the request. If there is none left, returns the time
+    /// after which the next request of the worker is allowed.
+    pub fn check(&self, worker: &str) -> Result<(), Duration> {
+        if self.rate == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let refill_time = Duration::from_secs_f64(f64::from(self.burst) / f64::from(self.rate));
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.retain(|_, bucket| now.duration_since(bucket.refilled_at) < refill_time);
+        let bucket = buckets.entry(worker.to_string()).or_insert(Bucket {
+            tokens: f64::from(self.burst),
+            refilled_at: now,
+        });
+
+        let refilled = now.duration_since(bucket.refilled_at).as_secs_f64() * f64::from(self.rate);
+        bucket.tokens = (bucket.tokens + refilled).min(f64::from(self.burst));
+        bucket.refilled_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / f64::from(self.rate),
+            ))
+        }
+    }
+}
+
+/// Rounds the delay returned by `WorkerRateLimiter::check` up to the whole seconds,
+/// the precision of the retry hints, so the client doesn't retry too early.
+pub fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_limited_per_worker() {
+        let limiter = WorkerRateLimiter::new(1, 3);
+        for _ in 0..3 {
+            assert!(limiter.check("prover_1").is_ok());
+        }
+        let retry_after = limiter
+            .check("prover_1")
+            .expect_err("request over the burst is allowed");
+        assert!(retry_after > Duration::from_millis(0));
+        assert!(retry_after <= Duration::from_secs(1));
+
+        // Other workers are not limited.
+        assert!(limiter.clone().check("prover_2").is_ok());
+    }
+
+    #[test]
+    fn tokens_are_refilled_over_time() {
+        let limiter = WorkerRateLimiter::new(20, 1);
+        assert!(limiter.check("prover_1").is_ok());
+        let retry_after = limiter
+            .check("prover_1")
+            .expect_err("request over the burst is allowed");
+        std::thread::sleep(retry_after);
+        assert!(limiter.check("prover_1").is_ok());
+    }
+
+    #[test]
+    fn idle_buckets_are_dropped() {
+        let limiter = WorkerRateLimiter::new(20, 1);
+        assert!(limiter.check("prover_1").is_ok());
+        // Bucket is full again after `burst / rate` seconds.
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check("prover_2").is_ok());
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(!buckets.contains_key("prover_1"));
+        assert!(buckets.contains_key("prover_2"));
+    }
+
+    #[test]
+    fn retry_after_is_rounded_up_to_seconds() {
+        assert_eq!(retry_after_secs(Duration::from_millis(1)), 1);
+        assert_eq!(retry_after_secs(Duration::from_secs(2)), 2);
+        assert_eq!(retry_after_secs(Duration::from_millis(2001)), 3);
+    }
+
+    #[test]
+    fn zero_rate_disables_limit() {
+        let limiter = WorkerRateLimiter::new(0, 0);
+        for _ in 0..100 {
+            assert!(limiter.check("prover_1").is_ok());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Readiness of the prover server to give out the jobs, reported to the orchestrator.
+
+// Built-in
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Witness generators are considered stuck if they have not finished a round
+/// for this many rounds intervals.
+pub const MAX_MISSED_ROUNDS: u32 = 10;
+
+/// Moment the witness generators have last finished a round, shared with the HTTP handlers.
+/// Cloned handles share the moment.
+#[derive(Debug, Clone)]
+pub struct WitnessHeartbeat {
+    last_beat: Arc<Mutex<Instant>>,
+}
+
+impl WitnessHeartbeat {
+    /// Creates the heartbeat, as if the round was just finished, so the server is not reported
+    /// unready while the generators are starting.
+    pub fn new() -> Self {
+        Self {
+            last_beat: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Records the finished round of a witness generator.
+    pub fn beat(&self) {
+        *self.last_beat.lock().unwrap() = Instant::now();
+    }
+
+    /// Time passed since the last finished round.
+    pub fn elapsed(&self) -> Duration {
+        self.last_beat.lock().unwrap().elapsed()
+    }
+}
+
+impl Default for WitnessHeartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Subsystem of the server which is not able to serve the jobs, with the reason.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubsystemFailure {
+    pub subsystem: String,
+    pub reason: String,
+}
+
+/// Body of the `/readyz` response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub failing: Vec<SubsystemFailure>,
+}
+
+impl Readiness {
+    /// Combines the result of the database check with the state of the witness generators,
+    /// which are stuck if their last round was more than `MAX_MISSED_ROUNDS` of `rounds_interval` ago.
+    pub fn new(
+        db_check: Result<(), String>,
+        heartbeat_elapsed: Duration,
+        rounds_interval: Duration,
+    ) -> Self {
+        let mut failing = Vec::new();
+        if let Err(reason) = db_check {
+            failing.push(SubsystemFailure {
+                subsystem: "database".to_string(),
+                reason,
+            });
+        }
+        if heartbeat_elapsed > rounds_interval * MAX_MISSED_ROUNDS {
+            failing.push(SubsystemFailure {
+                subsystem: "witness_generator".to_string(),
+                reason: format!("no rounds finished for {}s", heartbeat_elapsed.as_secs()),
+            });
+        }
+
+        Self {
+            ready: failing.is_empty(),
+            failing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_with_database_and_recent_heartbeat() {
+        let readiness = Readiness::new(Ok(()), Duration::from_secs(1), Duration::from_secs(1));
+        assert!(readiness.ready);
+        assert!(readiness.failing.is_empty());
+    }
+
+    #[test]
+    fn unready_subsystems_are_reported() {
+        let readiness = Readiness::new(
+            Err("connection refused".to_string()),
+            Duration::from_secs(11),
+            Duration::from_secs(1),
+        );
+        assert!(!readiness.ready);
+        let subsystems: Vec<_> = readiness
+            .failing
+            .iter()
+            .map(|failure| failure.subsystem.as_str())
+            .collect();
+        assert_eq!(subsystems, vec!["database", "witness_generator"]);
+        assert_eq!(readiness.failing[0].reason, "connection refused");
+
+        let readiness = Readiness::new(Ok(()), Duration::from_secs(11), Duration::from_secs(1));
+        assert!(!readiness.ready);
+        assert_eq!(readiness.failing.len(), 1);
+    }
+
+    #[test]
+    fn heartbeat_is_shared_by_handles() {
+        let heartbeat = WitnessHeartbeat::new();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(heartbeat.elapsed() >= Duration::from_millis(20));
+        heartbeat.clone().beat();
+        assert!(heartbeat.elapsed() < Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,159 @@
+//! In-memory registry of the running provers, for the fast lookup without the database.
+
+// Built-in
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+// External
+use chrono::{DateTime, Utc};
+
+/// Registered prover as seen by the server, see `/provers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProverInfo {
+    pub name: String,
+    pub registered_at: DateTime<Utc>,
+    /// Latest request of the prover: registration, job request or heartbeat.
+    pub last_heartbeat: DateTime<Utc>,
+    /// ID of the job (prover run) the prover is working on, if any.
+    pub current_job: Option<i32>,
+}
+
+/// Running provers by their ID. Provers are added on registration and removed once stopped
+/// or silent for longer than the prover timeout, as their jobs are reassigned by then.
+///
+/// Registry is kept in memory only, so it's empty until the provers register after
+/// the server restart. The database remains the source of truth for the jobs.
+#[derive(Debug, Clone, Default)]
+pub struct ProverRegistry {
+    provers: Arc<Mutex<HashMap<i32, ProverInfo>>>,
+}
+
+impl ProverRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<i32, ProverInfo>> {
+        self.provers
+            .lock()
+            .expect("prover registry lock is poisoned")
+    }
+
+    pub fn register(&self, id: i32, name: &str) {
+        let now = Utc::now();
+        let mut provers = self.lock();
+        provers.insert(
+            id,
+            ProverInfo {
+                name: name.to_string(),
+                registered_at: now,
+                last_heartbeat: now,
+                current_job: None,
+            },
+        );
+    }
+
+    /// Records the job request of the prover named `name`, along with the job given to it.
+    /// Jobs are requested by the name, so the latest registration with the name is updated.
+    pub fn job_requested(&self, name: &str, job: Option<i32>) {
+        let mut provers = self.lock();
+        let latest = provers
+            .iter_mut()
+            .filter(|(_, prover)| prover.name == name)
+            .max_by_key(|(&id, _)| id);
+        if let Some((_, prover)) = latest {
+            prover.last_heartbeat = Utc::now();
+            prover.current_job = job;
+        }
+    }
+
+    /// Records the heartbeat of the prover working on the job.
+    pub fn heartbeat(&self, job: i32) {
+        let mut provers = self.lock();
+        if let Some(prover) = provers
+            .values_mut()
+            .find(|prover| prover.current_job == Some(job))
+        {
+            prover.last_heartbeat = Utc::now();
+        }
+    }
+
+    pub fn stopped(&self, id: i32) {
+        let mut provers = self.lock();
+        provers.remove(&id);
+    }
+
+    /// Forgets the provers without requests for longer than the `prover_timeout`,
+    /// returning the IDs of the evicted ones.
+    pub fn evict_gone(&self, prover_timeout: Duration) -> Vec<i32> {
+        let now = Utc::now();
+        let prover_timeout = chrono::Duration::from_std(prover_timeout)
+            .unwrap_or_else(|_| chrono::Duration::max_value());
+
+        let mut provers = self.lock();
+        let mut gone: Vec<i32> = provers
+            .iter()
+            .filter(|(_, prover)| now.signed_duration_since(prover.last_heartbeat) > prover_timeout)
+            .map(|(&id, _)| id)
+            .collect();
+        gone.sort();
+        for id in &gone {
+            provers.remove(id);
+        }
+        gone
+    }
+
+    pub fn get(&self, id: i32) -> Option<ProverInfo> {
+        let provers = self.lock();
+        provers.get(&id).cloned()
+    }
+
+    /// Returns all the known provers, ordered by ID.
+    pub fn all(&self) -> BTreeMap<i32, ProverInfo> {
+        let provers = self.lock();
+        provers
+            .iter()
+            .map(|(&id, prover)| (id, prover.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_are_tracked_for_latest_registration() {
+        let registry = ProverRegistry::new();
+        registry.register(1, "alice");
+        registry.register(2, "alice");
+        registry.register(3, "bob");
+
+        registry.job_requested("alice", Some(10));
+        registry.heartbeat(10);
+        assert_eq!(registry.get(1).unwrap().current_job, None);
+        assert_eq!(registry.get(2).unwrap().current_job, Some(10));
+
+        // Prover asking for the next job is done with the previous one.
+        registry.job_requested("alice", None);
+        assert_eq!(registry.get(2).unwrap().current_job, None);
+
+        registry.stopped(3);
+        let ids: Vec<_> = registry.all().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn silent_provers_are_evicted() {
+        let registry = ProverRegistry::new();
+        registry.register(1, "alice");
+        registry.register(2, "bob");
+        registry.job_requested("bob", Some(10));
+        std::thread::sleep(Duration::from_millis(20));
+        registry.heartbeat(10);
+
+        assert_eq!(registry.evict_gone(Duration::from_millis(10)), vec![1]);
+        assert!(registry.get(1).is_none());
+        assert!(registry.get(2).is_some());
+    }
+}
@@ -0,0 +1,89 @@
+//! Interval between the rounds of the witness generators, adjusted to the demand of the provers.
+
+// Built-in
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Interval between the rounds of the witness generators looking for the new blocks.
+///
+/// Interval starts at `min_interval` and is doubled (up to `max_interval`) every time
+/// `block_to_prove` has no job for the prover, so the idle server doesn't poll the database
+/// more often than needed. The first job given out resets it to `min_interval`, so the pending
+/// blocks are picked up quickly. Cloned handles share the interval.
+#[derive(Debug, Clone)]
+pub struct RoundsIntervalScheduler {
+    min_interval: Duration,
+    max_interval: Duration,
+    current: Arc<Mutex<Duration>>,
+}
+
+impl RoundsIntervalScheduler {
+    /// Creates the scheduler, `max_interval` lower than `min_interval` is raised to it,
+    /// so the interval doesn't change.
+    pub fn new(min_interval: Duration, max_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            max_interval: max_interval.max(min_interval),
+            current: Arc::new(Mutex::new(min_interval)),
+        }
+    }
+
+    /// Current interval between the rounds.
+    pub fn interval(&self) -> Duration {
+        *self.current.lock().unwrap()
+    }
+
+    /// Records the response of `block_to_prove`: the interval grows while there are no jobs
+    /// and is reset once there is one.
+    pub fn record_block_to_prove(&self, has_job: bool) {
+        let mut current = self.current.lock().unwrap();
+        *current = if has_job {
+            self.min_interval
+        } else {
+            (*current * 2).min(self.max_interval)
+        };
+    }
+}
+
+/// Scheduler with the fixed interval.
+impl From<Duration> for RoundsIntervalScheduler {
+    fn from(interval: Duration) -> Self {
+        Self::new(interval, interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_grows_without_jobs_and_resets_on_job() {
+        let scheduler =
+            RoundsIntervalScheduler::new(Duration::from_millis(100), Duration::from_millis(500));
+        assert_eq!(scheduler.interval(), Duration::from_millis(100));
+
+        let intervals: Vec<_> = (0..4)
+            .map(|_| {
+                scheduler.record_block_to_prove(false);
+                scheduler.interval().as_millis()
+            })
+            .collect();
+        assert_eq!(intervals, vec![200, 400, 500, 500]);
+
+        // Handles share the interval.
+        scheduler.clone().record_block_to_prove(true);
+        assert_eq!(scheduler.interval(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn fixed_interval_does_not_change() {
+        let scheduler = RoundsIntervalScheduler::from(Duration::from_millis(100));
+        scheduler.record_block_to_prove(false);
+        assert_eq!(scheduler.interval(), Duration::from_millis(100));
+
+        let scheduler =
+            RoundsIntervalScheduler::new(Duration::from_millis(100), Duration::from_millis(10));
+        scheduler.record_block_to_prove(false);
+        assert_eq!(scheduler.interval(), Duration::from_millis(100));
+    }
+}
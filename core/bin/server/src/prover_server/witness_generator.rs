@@ -1,10 +1,13 @@
 // Built-in
-use std::{thread, time};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
 // External
 use crate::franklin_crypto::bellman::pairing::ff::PrimeField;
 use failure::format_err;
 use futures::channel::mpsc;
 use log::info;
+use tokio::sync::broadcast;
 // Workspace deps
 use circuit::witness::{
     utils::{SigDataInput, WitnessBuilder},
@@ -16,26 +19,84 @@ use models::params::{account_tree_depth, CHUNK_BIT_WIDTH};
 use models::{
     circuit::CircuitAccountTree,
     config_options::ThreadPanicNotify,
+    fe_from_hex,
     node::{BlockNumber, Fr, FranklinOp},
 };
 use plasma::state::CollectedFee;
 use prover::prover_data::ProverData;
 use std::time::Instant;
 use storage::StorageProcessor;
+// Local deps
+use crate::prover_server::prover_data_parts::ProverDataParts;
+use crate::prover_server::readiness::WitnessHeartbeat;
+use crate::prover_server::rounds_interval::RoundsIntervalScheduler;
+
+#[derive(Debug)]
+struct QueueState {
+    /// Lowest block never claimed so far.
+    next_block: BlockNumber,
+    /// Claimed blocks given back to the queue, to be claimed again before the `next_block`.
+    released: BTreeSet<BlockNumber>,
+}
+
+/// Blocks to generate the witnesses for, shared by the witness generators.
+/// Every block is claimed by a single generator, so the generators never work on the same block.
+/// Cloned handles share the state.
+#[derive(Debug, Clone)]
+pub struct WitnessQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl WitnessQueue {
+    /// Creates the queue starting with the block `start_block`.
+    pub fn new(start_block: BlockNumber) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(QueueState {
+                next_block: start_block,
+                released: BTreeSet::new(),
+            })),
+        }
+    }
+
+    /// Claims the lowest block that is neither claimed by other generator nor done.
+    pub fn claim(&self) -> BlockNumber {
+        let mut state = self.state.lock().expect("witness queue lock is poisoned");
+        if let Some(&block) = state.released.iter().next() {
+            state.released.remove(&block);
+            return block;
+        }
+        let block = state.next_block;
+        state.next_block += 1;
+        block
+    }
+
+    /// Gives the claimed block back, e.g. if it isn't committed yet or the witness
+    /// generation failed, so the block is claimed again.
+    pub fn release(&self, block: BlockNumber) {
+        let mut state = self.state.lock().expect("witness queue lock is poisoned");
+        state.released.insert(block);
+    }
+}
 
 /// The essential part of this structure is `maintain` function
 /// which runs forever and adds data to the database.
 ///
-/// This will generate and store in db witnesses for blocks with indexes
-/// start_block, start_block + block_step, start_block + 2*block_step, ...
+/// Witness generators started with the same `WitnessQueue` work in parallel,
+/// each one generating and storing in db the witness of the next unclaimed block.
 pub struct WitnessGenerator {
     /// Connection to the database.
     conn_pool: storage::ConnectionPool,
     /// Routine refresh interval.
-    rounds_interval: time::Duration,
-
-    start_block: BlockNumber,
-    block_step: BlockNumber,
+    rounds_interval: RoundsIntervalScheduler,
+    /// Worker index, for the logs.
+    worker: usize,
+    queue: WitnessQueue,
+    /// Notified once the witness of the block is stored, so the waiting provers take it right away.
+    new_witness: broadcast::Sender<BlockNumber>,
+    /// Served prover data, invalidated once the witness of the block is regenerated.
+    prover_data_parts: ProverDataParts,
+    /// Beats every round, so the server is reported unready if the generators are stuck.
+    heartbeat: WitnessHeartbeat,
 }
 
 enum BlockInfo {
@@ -48,20 +109,26 @@ impl WitnessGenerator {
     /// Creates a new `WitnessGenerator` object.
     pub fn new(
         conn_pool: storage::ConnectionPool,
-        rounds_interval: time::Duration,
-        start_block: BlockNumber,
-        block_step: BlockNumber,
+        rounds_interval: RoundsIntervalScheduler,
+        worker: usize,
+        queue: WitnessQueue,
+        new_witness: broadcast::Sender<BlockNumber>,
+        prover_data_parts: ProverDataParts,
+        heartbeat: WitnessHeartbeat,
     ) -> Self {
         Self {
             conn_pool,
             rounds_interval,
-            start_block,
-            block_step,
+            worker,
+            queue,
+            new_witness,
+            prover_data_parts,
+            heartbeat,
         }
     }
 
     /// Starts the thread running `maintain` method.
-    pub fn start(self, panic_notify: mpsc::Sender<bool>) {
+    pub fn start(self, panic_notify: mpsc::Sender<Option<String>>) {
         thread::Builder::new()
             .name("prover_server_pool".to_string())
             .spawn(move || {
@@ -96,10 +163,21 @@ impl WitnessGenerator {
                 .prover_schema()
                 .get_witness(block_number)
                 .await?;
-            if witness.is_none() {
-                BlockInfo::NoWitness(block)
-            } else {
-                BlockInfo::WithWitness
+            match witness {
+                None => BlockInfo::NoWitness(block),
+                Some(witness) if is_stale_witness(&witness, &block) => {
+                    // Block was committed again after the witness was generated.
+                    log::warn!(
+                        "witness for block {} doesn't match its root, regenerating it",
+                        block_number
+                    );
+                    transaction
+                        .prover_schema()
+                        .remove_witness(block_number)
+                        .await?;
+                    BlockInfo::NoWitness(block)
+                }
+                Some(_) => BlockInfo::WithWitness,
             }
         } else {
             BlockInfo::NotReadyBlock
@@ -218,9 +296,10 @@ impl WitnessGenerator {
 
         transaction
             .prover_schema()
-            .store_witness(
+            .store_witness_with_priority(
                 block.block_number,
                 serde_json::to_value(witness).expect("Witness serialize to json"),
+                block.fast_processing_required(),
             )
             .await?;
 
@@ -229,52 +308,62 @@ impl WitnessGenerator {
         Ok(())
     }
 
-    /// Returns next block for generating witness
-    fn next_witness_block(
-        current_block: BlockNumber,
-        block_step: BlockNumber,
-        block_info: &BlockInfo,
-    ) -> BlockNumber {
-        match block_info {
-            BlockInfo::NotReadyBlock => current_block, // Keep waiting
-            BlockInfo::WithWitness | BlockInfo::NoWitness(_) => current_block + block_step, // Go to the next block
-        }
-    }
-
-    /// Updates witness data in database in an infinite loop,
-    /// awaiting `rounds_interval` time between updates.
+    /// Updates witness data in database in an infinite loop. Generator waits
+    /// `rounds_interval` time only once there's no block to generate the witness for.
     async fn maintain(self) {
-        info!(
-            "preparing prover data routine started with start_block({}), block_step({})",
-            self.start_block, self.block_step
-        );
-        let mut current_block = self.start_block;
+        info!("preparing prover data routine {} started", self.worker);
         loop {
-            std::thread::sleep(self.rounds_interval);
-            let should_work = match self.should_work_on_block(current_block).await {
+            self.heartbeat.beat();
+            let block_number = self.queue.claim();
+            let should_work = match self.should_work_on_block(block_number).await {
                 Ok(should_work) => should_work,
                 Err(err) => {
-                    log::warn!("witness for block {} check failed: {}", current_block, err);
+                    log::warn!("witness for block {} check failed: {}", block_number, err);
+                    self.queue.release(block_number);
+                    std::thread::sleep(self.rounds_interval.interval());
                     continue;
                 }
             };
 
-            let next_block = Self::next_witness_block(current_block, self.block_step, &should_work);
-            if let BlockInfo::NoWitness(block) = should_work {
-                let block_number = block.block_number;
-                if let Err(err) = self.prepare_witness_and_save_it(block).await {
-                    log::warn!("Witness generator ({},{}) failed to prepare witness for block: {}, err: {}",
-                        self.start_block, self.block_step, block_number, err);
-                    continue; // Retry the same block on the next iteration.
+            match should_work {
+                BlockInfo::NotReadyBlock => {
+                    // Keep waiting for the block to be committed.
+                    self.queue.release(block_number);
+                    std::thread::sleep(self.rounds_interval.interval());
+                }
+                BlockInfo::WithWitness => {}
+                BlockInfo::NoWitness(block) => {
+                    if let Err(err) = self.prepare_witness_and_save_it(block).await {
+                        log::warn!(
+                            "Witness generator {} failed to prepare witness for block: {}, err: {}",
+                            self.worker,
+                            block_number,
+                            err
+                        );
+                        // Retry the block on the next iteration.
+                        self.queue.release(block_number);
+                        std::thread::sleep(self.rounds_interval.interval());
+                        continue;
+                    }
+                    self.prover_data_parts.remove(block_number);
+                    // There may be no provers waiting for the job.
+                    let _ = self.new_witness.send(block_number);
                 }
             }
-
-            // Update current block.
-            current_block = next_block;
         }
     }
 }
 
+/// Whether the stored witness was generated for the block with another root. Witness
+/// with the unreadable root is kept, as there's nothing to compare with.
+fn is_stale_witness(witness: &serde_json::Value, block: &Block) -> bool {
+    witness
+        .get("new_root")
+        .and_then(|root| root.as_str())
+        .and_then(|root| fe_from_hex::<Fr>(root).ok())
+        .map_or(false, |root| root != block.new_root_hash)
+}
+
 async fn build_prover_block_data(
     account_tree: &mut CircuitAccountTree,
     transaction: &mut storage::StorageProcessor<'_>,
@@ -399,7 +488,7 @@ async fn build_prover_block_data(
     assert_eq!(witness_accum.pubdata.len(), CHUNK_BIT_WIDTH * block_size);
     assert_eq!(witness_accum.operations.len(), block_size);
 
-    witness_accum.collect_fees(&fees);
+    witness_accum.try_collect_fees(&fees)?;
     assert_eq!(
         witness_accum
             .root_after_fees
@@ -424,32 +513,38 @@ async fn build_prover_block_data(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use models::node::Fr;
-    use web3::types::U256;
+    use std::collections::HashSet;
 
     #[test]
-    fn test_next_witness_block() {
-        assert_eq!(
-            WitnessGenerator::next_witness_block(3, 4, &BlockInfo::NotReadyBlock),
-            3
-        );
-        assert_eq!(
-            WitnessGenerator::next_witness_block(3, 4, &BlockInfo::WithWitness),
-            7
-        );
-        let empty_block = Block::new(
-            0,
-            Fr::default(),
-            0,
-            vec![],
-            (0, 0),
-            0,
-            U256::default(),
-            U256::default(),
-        );
-        assert_eq!(
-            WitnessGenerator::next_witness_block(3, 4, &BlockInfo::NoWitness(empty_block)),
-            7
-        );
+    fn released_blocks_are_claimed_first() {
+        let queue = WitnessQueue::new(3);
+        assert_eq!(queue.claim(), 3);
+        assert_eq!(queue.claim(), 4);
+        assert_eq!(queue.claim(), 5);
+
+        queue.release(5);
+        queue.release(4);
+        assert_eq!(queue.claim(), 4);
+        assert_eq!(queue.claim(), 5);
+        assert_eq!(queue.claim(), 6);
+    }
+
+    #[test]
+    fn workers_never_claim_the_same_block() {
+        let queue = WitnessQueue::new(1);
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let queue = queue.clone();
+                thread::spawn(move || (0..100).map(|_| queue.claim()).collect::<Vec<_>>())
+            })
+            .collect();
+
+        let claimed: Vec<_> = workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect();
+        let unique: HashSet<_> = claimed.iter().collect();
+        assert_eq!(unique.len(), claimed.len());
+        assert_eq!(queue.claim(), 201);
     }
 }
@@ -161,7 +161,7 @@ pub struct VerifyTxSignatureRequest {
 pub fn start_sign_checker_detached(
     input: mpsc::Receiver<VerifyTxSignatureRequest>,
     eth_watch_req: mpsc::Sender<EthWatchRequest>,
-    panic_notify: mpsc::Sender<bool>,
+    panic_notify: mpsc::Sender<Option<String>>,
 ) {
     /// Main signature check requests handler.
     /// Basically it receives the requests through the channel and verifies signatures,
@@ -0,0 +1,666 @@
+//! Tests of the gRPC prover server, mirroring the HTTP prover server tests.
+#![cfg(feature = "grpc")]
+
+// Built-in deps
+use std::{collections::BTreeMap, thread, time, time::Duration};
+// External deps
+use futures::channel::mpsc;
+use tokio::runtime::Runtime;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint};
+// Workspace deps
+use models::{config_options::ConfigurationOptions, prover_utils::EncodedProofPlonk};
+use prover::client::{self, proto, proto::prover_service_client::ProverServiceClient};
+use prover::ApiClient;
+// Local deps
+use server::prover_server::{self, GrpcServerHandle, RoundsIntervalScheduler, ServerHandle};
+use utils::{
+    connect_to_db, store_deposit_block_witness, test_operation_and_wanted_prover_data,
+    test_tls_config, tls_server_url,
+};
+
+mod utils;
+
+/// Running HTTP prover server (which runs the witness generators) and the gRPC one.
+struct TestServers {
+    http: ServerHandle,
+    grpc: GrpcServerHandle,
+}
+
+impl TestServers {
+    fn stop(self, runtime: &mut Runtime) {
+        self.grpc.stop();
+        runtime.block_on(self.http.stop(true));
+    }
+}
+
+/// Spawns both servers on the free ports, so the tests running in parallel don't interfere.
+/// Returns the URL of the gRPC server and the handles of the servers, which should be stopped
+/// by the end of the test.
+fn spawn_server(
+    runtime: &mut Runtime,
+    prover_timeout: time::Duration,
+    rounds_interval: time::Duration,
+) -> (String, TestServers) {
+    spawn_server_with_config(runtime, prover_timeout, rounds_interval, |_| {})
+}
+
+/// Same as `spawn_server`, with the configuration options of both servers adjusted by `configure`.
+/// Rounds interval is either fixed or adjusted by the given scheduler.
+fn spawn_server_with_config(
+    runtime: &mut Runtime,
+    prover_timeout: time::Duration,
+    rounds_interval: impl Into<RoundsIntervalScheduler>,
+    configure: impl FnOnce(&mut ConfigurationOptions),
+) -> (String, TestServers) {
+    let rounds_interval = rounds_interval.into();
+    let mut config_opt = ConfigurationOptions::from_env();
+    config_opt.prover_server_address = "127.0.0.1:0".parse().unwrap();
+    config_opt.prover_secret_auth = None;
+    config_opt.prover_tls = None;
+    // Most of the tests publish the dummy proofs.
+    config_opt.prover_verify_proofs = false;
+    // Tests poll the server for the jobs in a tight loop.
+    config_opt.prover_rate_limit = 0;
+    configure(&mut config_opt);
+
+    let conn_pool = runtime.block_on(connect_to_db());
+    let (tx, _rx) = mpsc::channel(1);
+    let jobs_progress = prover_server::JobsProgress::new();
+    let prover_registry = prover_server::ProverRegistry::new();
+    let rate_limiter = prover_server::WorkerRateLimiter::new(
+        config_opt.prover_rate_limit,
+        config_opt.prover_rate_limit_burst,
+    );
+
+    let grpc = prover_server::start_grpc_prover_server(
+        conn_pool.clone(),
+        prover_timeout,
+        rounds_interval.clone(),
+        tx.clone(),
+        "127.0.0.1:0".parse().unwrap(),
+        config_opt.clone(),
+        jobs_progress.clone(),
+        prover_registry.clone(),
+        rate_limiter.clone(),
+    );
+    let http = prover_server::start_prover_server(
+        conn_pool,
+        prover_timeout,
+        rounds_interval,
+        tx,
+        config_opt,
+        jobs_progress,
+        prover_registry,
+        rate_limiter,
+    );
+    (
+        format!("http://{}", grpc.local_addr()),
+        TestServers { http, grpc },
+    )
+}
+
+/// Connects to the gRPC server without the `GrpcApiClient` retries,
+/// so the tests can check the status of the rejected requests.
+fn connect_raw_client(runtime: &mut Runtime, addr: &str) -> ProverServiceClient<Channel> {
+    runtime
+        .block_on(ProverServiceClient::connect(addr.to_string()))
+        .expect("failed to connect to gRPC server")
+}
+
+#[test]
+#[should_panic]
+fn grpc_client_with_empty_worker_name_panics() {
+    client::GrpcApiClient::new("http://example.com", "", Duration::from_secs(1));
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_client_register_start_and_stop_of_prover() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, servers) = spawn_server(
+        &mut runtime,
+        time::Duration::from_secs(1),
+        time::Duration::from_secs(1),
+    );
+    let client = client::GrpcApiClient::new(&addr, "foo", Duration::from_secs(1));
+    let id = client
+        .register_prover(&[block_size_chunks])
+        .expect("failed to register");
+
+    let db_connection = runtime.block_on(connect_to_db());
+    runtime.block_on(async {
+        let mut storage = db_connection
+            .access_storage()
+            .await
+            .expect("Failed to connect to db");
+        storage
+            .prover_schema()
+            .prover_by_id(id)
+            .await
+            .expect("failed to select registered prover");
+    });
+
+    client.prover_stopped(id).expect("unexpected error");
+
+    runtime.block_on(async {
+        let mut storage = db_connection
+            .access_storage()
+            .await
+            .expect("Failed to connect to db");
+        let prover = storage
+            .prover_schema()
+            .prover_by_id(id)
+            .await
+            .expect("failed to select registered prover");
+        prover.stopped_at.expect("expected not empty");
+    });
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_stores_prover_metadata_on_registration() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, servers) = spawn_server(
+        &mut runtime,
+        time::Duration::from_secs(1),
+        time::Duration::from_secs(10),
+    );
+    let client = client::GrpcApiClient::new(&addr, "grpc_metadata_prover", Duration::from_secs(1));
+    let prover_id = client
+        .register_prover(&[block_size_chunks])
+        .expect("failed to register");
+
+    // Provers of the older versions register without the metadata.
+    let mut raw_client = connect_raw_client(&mut runtime, &addr);
+    let old_prover_id = runtime
+        .block_on(raw_client.register(proto::RegisterRequest {
+            name: "grpc_old_prover".to_string(),
+            block_sizes: vec![block_size_chunks as u64],
+            ..Default::default()
+        }))
+        .expect("failed to register")
+        .into_inner()
+        .prover_id;
+
+    let db_connection = runtime.block_on(connect_to_db());
+    let (prover, old_prover) = runtime.block_on(async {
+        let mut storage = db_connection
+            .access_storage()
+            .await
+            .expect("Failed to connect to db");
+        let prover = storage
+            .prover_schema()
+            .prover_by_id(prover_id)
+            .await
+            .expect("failed to load prover");
+        let old_prover = storage
+            .prover_schema()
+            .prover_by_id(old_prover_id)
+            .await
+            .expect("failed to load prover");
+        (prover, old_prover)
+    });
+    let expected = client::RegisterReq::new("grpc_metadata_prover", &[block_size_chunks]);
+    assert_eq!(prover.worker, "grpc_metadata_prover");
+    assert_eq!(prover.block_sizes, vec![block_size_chunks as i64]);
+    assert_eq!(prover.hostname, expected.hostname);
+    assert_eq!(prover.version, expected.version);
+    assert_eq!(
+        prover.cpu_cores,
+        expected.cpu_cores.map(|cores| cores as i32)
+    );
+    assert_eq!(prover.vk_hash, expected.vk_hash);
+
+    assert_eq!(old_prover.hostname, None);
+    assert_eq!(old_prover.version, None);
+    assert_eq!(old_prover.cpu_cores, None);
+    assert_eq!(old_prover.vk_hash, None);
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_tracks_running_provers_in_registry() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, servers) = spawn_server(
+        &mut runtime,
+        time::Duration::from_secs(10),
+        time::Duration::from_secs(10),
+    );
+
+    let client = client::GrpcApiClient::new(&addr, "grpc_registry_prover", Duration::from_secs(1));
+    let prover_id = client
+        .register_prover(&[block_size_chunks])
+        .expect("failed to register");
+    let job = client
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove")
+        .map(|(_, job)| job);
+
+    // Provers registered over gRPC are served by the HTTP server.
+    let provers_url = format!("http://{}/provers", servers.http.local_addr());
+    let mut list_provers = || -> BTreeMap<i32, prover_server::ProverInfo> {
+        runtime.block_on(async {
+            reqwest::get(&provers_url)
+                .await
+                .expect("failed to request provers")
+                .json()
+                .await
+                .expect("failed to parse provers")
+        })
+    };
+
+    let provers = list_provers();
+    let prover = provers
+        .get(&prover_id)
+        .expect("registered prover is not listed");
+    assert_eq!(prover.name, "grpc_registry_prover");
+    assert_eq!(prover.current_job, job);
+
+    client.prover_stopped(prover_id).expect("unexpected error");
+    assert!(!list_provers().contains_key(&prover_id));
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_rejects_provers_with_wrong_or_missing_secret_auth() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, servers) = spawn_server_with_config(
+        &mut runtime,
+        time::Duration::from_secs(1),
+        time::Duration::from_secs(10),
+        |config_opt| config_opt.prover_secret_auth = Some("grpc_secret".to_string()),
+    );
+
+    let mut raw_client = connect_raw_client(&mut runtime, &addr);
+    for token in &[None, Some("Bearer wrong_secret"), Some("grpc_secret")] {
+        let mut request = tonic::Request::new(proto::ProverRequest {
+            name: "foo".to_string(),
+            block_size: block_size_chunks as u64,
+        });
+        if let Some(token) = token {
+            request
+                .metadata_mut()
+                .insert("authorization", token.parse().unwrap());
+        }
+        let status = runtime
+            .block_on(raw_client.block_to_prove(request))
+            .expect_err("request without the valid secret is accepted");
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    client::GrpcApiClient::new(&addr, "foo", Duration::from_secs(1))
+        .with_secret_auth("grpc_secret")
+        .block_to_prove(block_size_chunks)
+        .expect("request with the secret is rejected");
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_with_tls_accepts_only_provers_with_client_certificate() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (_, servers) = spawn_server_with_config(
+        &mut runtime,
+        time::Duration::from_secs(1),
+        time::Duration::from_secs(10),
+        |config_opt| config_opt.prover_tls = Some(test_tls_config("server")),
+    );
+    let url = tls_server_url(&servers.grpc.local_addr().to_string());
+
+    client::GrpcApiClient::new(&url, "foo", Duration::from_secs(1))
+        .with_tls(&test_tls_config("prover"))
+        .expect("failed to load prover TLS config")
+        .register_prover(&[block_size_chunks])
+        .expect("prover with certificate is rejected");
+
+    // Server certificate is trusted, but the prover doesn't present its own.
+    let ca_cert = std::fs::read(test_tls_config("prover").ca_pem).expect("failed to read CA");
+    let without_certificate = Endpoint::from_shared(url)
+        .unwrap()
+        .tls_config(ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca_cert)));
+    // Plain HTTP/2 is not served at all.
+    let plain = Endpoint::from_shared(format!("http://{}", servers.grpc.local_addr())).unwrap();
+    for endpoint in &[without_certificate, plain] {
+        let channel = runtime
+            .enter(|| endpoint.connect_lazy())
+            .expect("failed to create gRPC channel");
+        let request = proto::ProverRequest {
+            name: "foo".to_string(),
+            block_size: block_size_chunks as u64,
+        };
+        runtime
+            .block_on(ProverServiceClient::new(channel).block_to_prove(request))
+            .expect_err("prover without certificate got the response");
+    }
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_client_simple_simulation() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let prover_timeout = time::Duration::from_secs(1);
+    let rounds_interval = time::Duration::from_secs(10);
+
+    let (addr, servers) = spawn_server(&mut runtime, prover_timeout, rounds_interval);
+
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let client = client::GrpcApiClient::new(&addr, "foo", time::Duration::from_secs(1));
+
+    // call block_to_prove and check its none
+    let to_prove = client
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove");
+    assert!(to_prove.is_none());
+
+    let (op, wanted_prover_data) = runtime
+        .block_on(test_operation_and_wanted_prover_data(block_size_chunks))
+        .expect("failed to prepare test operation");
+
+    println!("inserting test operation");
+    // write test commit operation to db
+    let db_connection = runtime.block_on(connect_to_db());
+    runtime.block_on(async {
+        let mut storage = db_connection
+            .access_storage()
+            .await
+            .expect("Failed to connect to db");
+        storage
+            .chain()
+            .block_schema()
+            .execute_operation(op)
+            .await
+            .expect("failed to mock commit operation");
+    });
+
+    thread::sleep(time::Duration::from_secs(10));
+
+    // should return block
+    let to_prove = client
+        .block_to_prove(block_size_chunks)
+        .expect("failed to bet block to prove");
+    assert!(to_prove.is_some());
+
+    // block is taken unless no heartbeat from prover within prover_timeout period
+    // should return None at this moment
+    let to_prove = client
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove");
+    assert!(to_prove.is_none());
+
+    // make block available
+    thread::sleep(prover_timeout * 10);
+
+    let to_prove = client
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove");
+    assert!(to_prove.is_some());
+
+    let (block, job) = to_prove.unwrap();
+    // sleep for prover_timeout and send heartbeat
+    thread::sleep(prover_timeout * 2);
+    client.working_on(job, None).unwrap();
+
+    let to_prove = client
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove");
+    assert!(to_prove.is_none());
+
+    let prover_data = client
+        .prover_data(block)
+        .expect("failed to get prover data");
+    assert_eq!(prover_data.old_root, Some(wanted_prover_data.old_root));
+    assert_eq!(
+        prover_data.pub_data_commitment,
+        Some(wanted_prover_data.public_data_commitment),
+    );
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_excludes_block_from_prover_timing_out_on_it() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_timeout = time::Duration::from_secs(1);
+    let (addr, servers) = spawn_server_with_config(
+        &mut runtime,
+        prover_timeout,
+        time::Duration::from_secs(10),
+        |config_opt| config_opt.prover_max_block_attempts = 2,
+    );
+
+    let (op, _) = runtime
+        .block_on(test_operation_and_wanted_prover_data(block_size_chunks))
+        .expect("failed to prepare test operation");
+    let db_connection = runtime.block_on(connect_to_db());
+    runtime.block_on(async {
+        let mut storage = db_connection
+            .access_storage()
+            .await
+            .expect("Failed to connect to db");
+        storage
+            .chain()
+            .block_schema()
+            .execute_operation(op)
+            .await
+            .expect("failed to mock commit operation");
+    });
+    thread::sleep(time::Duration::from_secs(10));
+
+    // Prover claims the block and times out on it, until it's not assigned the block anymore.
+    let stuck_prover = client::GrpcApiClient::new(&addr, "stuck_prover", prover_timeout);
+    stuck_prover
+        .register_prover(&[block_size_chunks])
+        .expect("failed to register");
+    let (block, _) = stuck_prover
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
+    thread::sleep(prover_timeout * 2);
+    let (reassigned_block, _) = stuck_prover
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove")
+        .expect("timed out block is not reassigned");
+    assert_eq!(reassigned_block, block);
+    thread::sleep(prover_timeout * 2);
+    let next_job = stuck_prover
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove");
+    assert_ne!(next_job.map(|(next_block, _)| next_block), Some(block));
+
+    // Other provers still get the block.
+    let (other_block, _) = client::GrpcApiClient::new(&addr, "healthy_prover", prover_timeout)
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
+    assert_eq!(other_block, block);
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_rate_limits_job_requests_of_worker() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, servers) = spawn_server_with_config(
+        &mut runtime,
+        time::Duration::from_secs(1),
+        time::Duration::from_secs(10),
+        |config_opt| {
+            config_opt.prover_rate_limit = 1;
+            config_opt.prover_rate_limit_burst = 2;
+        },
+    );
+    let mut raw_client = connect_raw_client(&mut runtime, &addr);
+    let request = proto::ProverRequest {
+        name: "hasty_prover".to_string(),
+        block_size: block_size_chunks as u64,
+    };
+
+    for _ in 0..2 {
+        runtime
+            .block_on(raw_client.block_to_prove(request.clone()))
+            .expect("request within the burst failed");
+    }
+    let status = runtime
+        .block_on(raw_client.block_to_prove(request))
+        .expect_err("request over the burst is not limited");
+    assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    let retry_after = status.metadata().get("retry-after");
+    assert_eq!(retry_after.and_then(|value| value.to_str().ok()), Some("1"));
+
+    // Limit is shared with the HTTP server.
+    let http_url = format!("http://{}/block_to_prove", servers.http.local_addr());
+    let res = runtime
+        .block_on(
+            reqwest::Client::new()
+                .get(&http_url)
+                .json(&client::ProverReq {
+                    name: "hasty_prover".to_string(),
+                    block_size: block_size_chunks,
+                })
+                .send(),
+        )
+        .expect("failed to request block");
+    assert_eq!(res.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_adjusts_rounds_interval_to_jobs() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let rounds_interval =
+        RoundsIntervalScheduler::new(Duration::from_millis(100), Duration::from_millis(500));
+    let (addr, servers) = spawn_server_with_config(
+        &mut runtime,
+        Duration::from_secs(10),
+        rounds_interval.clone(),
+        |_| {},
+    );
+    let client = client::GrpcApiClient::new(&addr, "foo", Duration::from_secs(1));
+
+    // Interval grows while there are no jobs.
+    for _ in 0..2 {
+        let to_prove = client
+            .block_to_prove(block_size_chunks)
+            .expect("failed to get block to prove");
+        assert!(to_prove.is_none());
+    }
+    assert_eq!(rounds_interval.interval(), Duration::from_millis(400));
+
+    let (op, _) = runtime
+        .block_on(test_operation_and_wanted_prover_data(block_size_chunks))
+        .expect("failed to prepare test operation");
+    let db_connection = runtime.block_on(connect_to_db());
+    runtime.block_on(async {
+        let mut storage = db_connection
+            .access_storage()
+            .await
+            .expect("Failed to connect to db");
+        storage
+            .chain()
+            .block_schema()
+            .execute_operation(op)
+            .await
+            .expect("failed to mock commit operation");
+    });
+
+    // Witness is still generated with the grown interval, and the first job resets it.
+    let started = time::Instant::now();
+    while client
+        .block_to_prove(block_size_chunks)
+        .expect("failed to get block to prove")
+        .is_none()
+    {
+        assert_eq!(rounds_interval.interval(), Duration::from_millis(500));
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "block to prove is not assigned"
+        );
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(rounds_interval.interval(), Duration::from_millis(100));
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_publish_dummy() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let prover_timeout = time::Duration::from_secs(1);
+    let rounds_interval = time::Duration::from_secs(10);
+    let (addr, servers) = spawn_server(&mut runtime, prover_timeout, rounds_interval);
+
+    let client = client::GrpcApiClient::new(&addr, "foo", time::Duration::from_secs(1));
+    client
+        .publish(1, EncodedProofPlonk::default())
+        .expect("failed to publish proof");
+
+    servers.stop(&mut runtime);
+}
+
+#[test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+fn grpc_server_rejects_invalid_proof_when_verification_is_on() {
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let (addr, servers) = spawn_server_with_config(
+        &mut runtime,
+        time::Duration::from_secs(10),
+        time::Duration::from_secs(10),
+        |config_opt| config_opt.prover_verify_proofs = true,
+    );
+
+    let block = 403;
+    runtime.block_on(store_deposit_block_witness(block));
+    let mut raw_client = connect_raw_client(&mut runtime, &addr);
+    let request = proto::PublishRequest {
+        block: i64::from(block),
+        proof: serde_json::to_vec(&EncodedProofPlonk::default()).expect("failed to serialize"),
+        stats: Vec::new(),
+    };
+    let status = runtime
+        .block_on(raw_client.publish(request))
+        .expect_err("invalid proof is accepted");
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    assert!(
+        status.message().contains("public data commitment"),
+        "{}",
+        status.message()
+    );
+
+    let db_connection = runtime.block_on(connect_to_db());
+    let stored = runtime.block_on(async {
+        let mut storage = db_connection
+            .access_storage()
+            .await
+            .expect("Failed to connect to db");
+        storage
+            .prover_schema()
+            .load_proof(block)
+            .await
+            .expect("failed to load proof")
+    });
+    assert!(stored.is_none());
+
+    servers.stop(&mut runtime);
+}
@@ -1,71 +1,187 @@
 // Built-in deps
-use std::{net, str::FromStr, thread, time, time::Duration};
+use std::{collections::BTreeMap, thread, time, time::Duration};
 // External deps
-use crypto_exports::pairing::ff::{Field, PrimeField};
 use futures::channel::mpsc;
 // Workspace deps
-use circuit::witness::{deposit::DepositWitness, Witness};
 use models::{
     config_options::ConfigurationOptions,
-    node::{block::Block, Address},
-    params::total_tokens,
-    prover_utils::EncodedProofPlonk,
+    prover_utils::{
+        EncodedProofPlonk, PlonkVerificationKey, ProvingStats, SetupForStepByStepProver,
+    },
 };
-use num::BigUint;
-use prover::{client, ApiClient};
+use prover::{client, AsyncApiClient, JobProgress, ProvingStage};
 // Local deps
-use circuit::witness::utils::get_used_subtree_root_hash;
-use server::prover_server;
+use server::prover_server::{self, ReportedJobProgress, RoundsIntervalScheduler, ServerHandle};
+use utils::{
+    connect_to_db, empty_block_operation, store_deposit_block_witness,
+    test_operation_and_wanted_prover_data, test_tls_config, tls_server_url,
+};
+
+mod utils;
+
+/// Spawns the server on a free port, so the tests running in parallel don't interfere.
+/// Returns the address of the server and its handle, the server should be stopped by the end of the test.
+async fn spawn_server(
+    prover_timeout: time::Duration,
+    rounds_interval: time::Duration,
+) -> (String, ServerHandle) {
+    spawn_server_with_secret_auth(prover_timeout, rounds_interval, None).await
+}
+
+/// Same as `spawn_server`, but the provers must authenticate with `secret_auth`, if it's set.
+async fn spawn_server_with_secret_auth(
+    prover_timeout: time::Duration,
+    rounds_interval: time::Duration,
+    secret_auth: Option<&str>,
+) -> (String, ServerHandle) {
+    let secret_auth = secret_auth.map(str::to_string);
+    spawn_server_with_config(prover_timeout, rounds_interval, |config_opt| {
+        config_opt.prover_secret_auth = secret_auth;
+    })
+    .await
+}
+
+/// Same as `spawn_server`, but the provers must connect over the mutual TLS,
+/// presenting the certificate signed by the test CA.
+async fn spawn_server_with_tls(
+    prover_timeout: time::Duration,
+    rounds_interval: time::Duration,
+) -> (String, ServerHandle) {
+    spawn_server_with_config(prover_timeout, rounds_interval, |config_opt| {
+        config_opt.prover_tls = Some(test_tls_config("server"));
+    })
+    .await
+}
 
-async fn connect_to_db() -> storage::ConnectionPool {
-    storage::ConnectionPool::new(Some(1)).await
+/// Same as `spawn_server`, with the configuration options adjusted by `configure`.
+/// Rounds interval is either fixed or adjusted by the given scheduler.
+async fn spawn_server_with_config(
+    prover_timeout: time::Duration,
+    rounds_interval: impl Into<RoundsIntervalScheduler>,
+    configure: impl FnOnce(&mut ConfigurationOptions),
+) -> (String, ServerHandle) {
+    spawn_server_with_pool(
+        connect_to_db().await,
+        prover_timeout,
+        rounds_interval,
+        configure,
+    )
+    .await
 }
 
-async fn spawn_server(prover_timeout: time::Duration, rounds_interval: time::Duration) -> String {
-    // TODO: make single server spawn for all tests
-    let bind_to = "127.0.0.1:8088";
+/// Same as `spawn_server_with_config`, but the server uses the given connection pool.
+async fn spawn_server_with_pool(
+    conn_pool: storage::ConnectionPool,
+    prover_timeout: time::Duration,
+    rounds_interval: impl Into<RoundsIntervalScheduler>,
+    configure: impl FnOnce(&mut ConfigurationOptions),
+) -> (String, ServerHandle) {
+    let rounds_interval = rounds_interval.into();
     let mut config_opt = ConfigurationOptions::from_env();
-    config_opt.prover_server_address = net::SocketAddr::from_str(bind_to).unwrap();
+    config_opt.prover_server_address = "127.0.0.1:0".parse().unwrap();
+    config_opt.prover_secret_auth = None;
+    config_opt.prover_tls = None;
+    // Most of the tests publish the dummy proofs.
+    config_opt.prover_verify_proofs = false;
+    // Tests poll the server for the jobs in a tight loop.
+    config_opt.prover_rate_limit = 0;
+    configure(&mut config_opt);
 
-    let conn_pool = connect_to_db().await;
     let (tx, _rx) = mpsc::channel(1);
+    let rate_limiter = prover_server::WorkerRateLimiter::new(
+        config_opt.prover_rate_limit,
+        config_opt.prover_rate_limit_burst,
+    );
 
-    thread::spawn(move || {
+    // Server startup blocks until it's bound, while the connection pool is driven by the test runtime.
+    let server = tokio::task::spawn_blocking(move || {
         prover_server::start_prover_server(
             conn_pool,
             prover_timeout,
             rounds_interval,
             tx,
             config_opt,
-        );
-    });
-    bind_to.to_string()
+            prover_server::JobsProgress::new(),
+            prover_server::ProverRegistry::new(),
+            rate_limiter,
+        )
+    })
+    .await
+    .expect("failed to start server");
+    (server.local_addr().to_string(), server)
+}
+
+/// Requests the server health, which is available to the provers only, with the given bearer token.
+async fn health_with_token(addr: &str, token: Option<&str>) -> reqwest::StatusCode {
+    let mut request = reqwest::Client::new().get(&format!("http://{}/health", addr));
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    request
+        .send()
+        .await
+        .expect("failed to request server health")
+        .status()
+}
+
+#[test]
+fn client_with_empty_worker_name_is_rejected() {
+    let err = client::ApiClient::new("http://example.com", "", Duration::from_secs(1))
+        .err()
+        .expect("client with empty worker name is created");
+    assert!(matches!(err, client::ClientError::Config { .. }));
 }
 
 #[test]
 #[should_panic]
-fn client_with_empty_worker_name_panics() {
-    client::ApiClient::new(
+fn async_client_with_empty_worker_name_panics() {
+    client::AsyncApiClient::new(
         &"http:://example.com".parse().unwrap(),
         "",
         Duration::from_secs(1),
     );
 }
 
-#[tokio::test]
+#[tokio::test(threaded_scheduler)]
 #[cfg_attr(not(feature = "db_test"), ignore)]
 async fn api_client_register_start_and_stop_of_prover() {
     let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
-    let addr = spawn_server(time::Duration::from_secs(1), time::Duration::from_secs(1)).await;
-    let client = client::ApiClient::new(
+    let (addr, server) =
+        spawn_server(time::Duration::from_secs(1), time::Duration::from_secs(1)).await;
+    let client =
+        client::ApiClient::new(&format!("http://{}", &addr), "foo", Duration::from_secs(1))
+            .expect("failed to create client");
+    let id = tokio::task::block_in_place(|| client.register_prover(&[block_size_chunks]))
+        .expect("failed to register");
+
+    check_prover_registered_and_stopped(&client, id).await;
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn async_api_client_register_start_and_stop_of_prover() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) =
+        spawn_server(time::Duration::from_secs(1), time::Duration::from_secs(1)).await;
+    let client = client::AsyncApiClient::new(
         &format!("http://{}", &addr).parse().unwrap(),
         "foo",
         Duration::from_secs(1),
     );
     let id = client
-        .register_prover(block_size_chunks)
+        .register_prover(&[block_size_chunks])
+        .await
         .expect("failed to register");
 
+    check_prover_registered_and_stopped(&client, id).await;
+
+    server.stop(true).await;
+}
+
+/// Checks that the registered prover is stored and gets stopped by the client.
+async fn check_prover_registered_and_stopped<C: AsyncApiClient>(client: &C, id: i32) {
     let db_connection = connect_to_db().await;
     let mut storage = db_connection
         .access_storage()
@@ -77,7 +193,7 @@ async fn api_client_register_start_and_stop_of_prover() {
         .prover_by_id(id)
         .await
         .expect("failed to select registered prover");
-    client.prover_stopped(id).expect("unexpected error");
+    client.prover_stopped(id).await.expect("unexpected error");
     let prover = storage
         .prover_schema()
         .prover_by_id(id)
@@ -86,24 +202,123 @@ async fn api_client_register_start_and_stop_of_prover() {
     prover.stopped_at.expect("expected not empty");
 }
 
-#[tokio::test]
+#[tokio::test(threaded_scheduler)]
 #[cfg_attr(not(feature = "db_test"), ignore)]
 async fn api_client_simple_simulation() {
     let prover_timeout = time::Duration::from_secs(1);
     let rounds_interval = time::Duration::from_secs(10);
 
-    let addr = spawn_server(prover_timeout, rounds_interval).await;
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
 
-    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
     let client = client::ApiClient::new(
+        &format!("http://{}", &addr),
+        "foo",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+    simple_simulation(&client, prover_timeout).await;
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_client_simple_simulation_over_tls() {
+    let prover_timeout = time::Duration::from_secs(1);
+    let rounds_interval = time::Duration::from_secs(10);
+
+    let (addr, server) = spawn_server_with_tls(prover_timeout, rounds_interval).await;
+
+    let client = client::ApiClientBuilder::new(&tls_server_url(&addr), "foo")
+        .with_request_timeout(time::Duration::from_secs(1))
+        .with_tls(test_tls_config("prover"))
+        .build()
+        .expect("failed to create client");
+    simple_simulation(&client, prover_timeout).await;
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_with_tls_rejects_provers_without_client_certificate() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) =
+        spawn_server_with_tls(time::Duration::from_secs(1), time::Duration::from_secs(10)).await;
+
+    // Server certificate is trusted, but the prover doesn't present its own.
+    let without_certificate = client::ApiClientBuilder::new(&tls_server_url(&addr), "foo")
+        .with_ca_cert(test_tls_config("prover").ca_pem)
+        .with_retries(0, time::Duration::from_millis(100))
+        .build()
+        .expect("failed to create client");
+    without_certificate
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect_err("prover without certificate got the response");
+
+    // Plain HTTP is not served at all.
+    let plain_http = client::ApiClientBuilder::new(&format!("http://{}", addr), "foo")
+        .with_retries(0, time::Duration::from_millis(100))
+        .build()
+        .expect("failed to create client");
+    plain_http
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect_err("prover over plain HTTP got the response");
+
+    server.stop(true).await;
+}
+
+#[test]
+fn client_with_tls_requires_https_server_url() {
+    let err = client::ApiClientBuilder::new("http://localhost:8088", "foo")
+        .with_tls(test_tls_config("prover"))
+        .build()
+        .err()
+        .expect("TLS client with the http server URL is created");
+    assert!(matches!(err, client::ClientError::Config { .. }));
+}
+
+#[test]
+fn server_tls_config_is_loaded_from_test_certificates() {
+    let tls = test_tls_config("server");
+    prover::tls::load_tls_config(&tls.cert_pem, &tls.key_pem, &tls.ca_pem)
+        .expect("failed to load TLS config");
+
+    // Private key is not a certificate.
+    prover::tls::load_tls_config(&tls.key_pem, &tls.key_pem, &tls.ca_pem)
+        .err()
+        .expect("TLS config without certificate is loaded");
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn async_api_client_simple_simulation() {
+    let prover_timeout = time::Duration::from_secs(1);
+    let rounds_interval = time::Duration::from_secs(10);
+
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
+
+    let client = client::AsyncApiClient::new(
         &format!("http://{}", &addr).parse().unwrap(),
         "foo",
         time::Duration::from_secs(1),
     );
+    simple_simulation(&client, prover_timeout).await;
+
+    server.stop(true).await;
+}
+
+/// Goes through the job lifecycle: takes the block to prove, loses it because of the missing
+/// heartbeats, takes it again and keeps it with the heartbeat.
+async fn simple_simulation<C: AsyncApiClient>(client: &C, prover_timeout: time::Duration) {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
 
     // call block_to_prove and check its none
     let to_prove = client
         .block_to_prove(block_size_chunks)
+        .await
         .expect("failed to get block to prove");
     assert!(to_prove.is_none());
 
@@ -113,7 +328,9 @@ async fn api_client_simple_simulation() {
         .await
         .expect("Failed to connect to db");
 
-    let (op, wanted_prover_data) = test_operation_and_wanted_prover_data(block_size_chunks).await;
+    let (op, wanted_prover_data) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
 
     println!("inserting test operation");
     // write test commit operation to db
@@ -129,6 +346,7 @@ async fn api_client_simple_simulation() {
     // should return block
     let to_prove = client
         .block_to_prove(block_size_chunks)
+        .await
         .expect("failed to bet block to prove");
     assert!(to_prove.is_some());
 
@@ -136,6 +354,7 @@ async fn api_client_simple_simulation() {
     // should return None at this moment
     let to_prove = client
         .block_to_prove(block_size_chunks)
+        .await
         .expect("failed to get block to prove");
     assert!(to_prove.is_none());
 
@@ -144,21 +363,24 @@ async fn api_client_simple_simulation() {
 
     let to_prove = client
         .block_to_prove(block_size_chunks)
+        .await
         .expect("failed to get block to prove");
     assert!(to_prove.is_some());
 
     let (block, job) = to_prove.unwrap();
     // sleep for prover_timeout and send heartbeat
     thread::sleep(prover_timeout * 2);
-    client.working_on(job).unwrap();
+    client.working_on(job, None).await.unwrap();
 
     let to_prove = client
         .block_to_prove(block_size_chunks)
+        .await
         .expect("failed to get block to prove");
     assert!(to_prove.is_none());
 
     let prover_data = client
         .prover_data(block)
+        .await
         .expect("failed to get prover data");
     assert_eq!(prover_data.old_root, Some(wanted_prover_data.old_root));
     assert_eq!(
@@ -167,197 +389,1690 @@ async fn api_client_simple_simulation() {
     );
 }
 
-pub async fn test_operation_and_wanted_prover_data(
-    block_size_chunks: usize,
-) -> (models::Operation, prover::prover_data::ProverData) {
-    let mut circuit_tree =
-        models::circuit::CircuitAccountTree::new(models::params::account_tree_depth());
-    // insert account and its balance
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_records_reported_job_progress() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_timeout = time::Duration::from_secs(10);
+    let rounds_interval = time::Duration::from_secs(10);
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
 
-    let db_connection = connect_to_db().await;
-    let mut storage = db_connection
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "foo",
+        time::Duration::from_secs(1),
+    );
+
+    let mut storage = connect_to_db()
+        .await
         .access_storage()
         .await
         .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+    thread::sleep(time::Duration::from_secs(10));
 
-    // Fee account
-    let mut accounts = models::node::AccountMap::default();
-    let validator_account = models::node::Account::default_with_address(&Address::random());
-    let validator_account_id: u32 = 0;
-    accounts.insert(validator_account_id, validator_account.clone());
-
-    let mut state = plasma::state::PlasmaState::from_acc_map(accounts, 1);
-    println!(
-        "acc_number 0, acc {:?}",
-        models::circuit::account::CircuitAccount::from(validator_account.clone()).pub_key_hash,
-    );
-    circuit_tree.insert(
-        0,
-        models::circuit::account::CircuitAccount::from(validator_account.clone()),
-    );
-    let initial_root = circuit_tree.root_hash();
-    let initial_root2 = circuit_tree.root_hash();
-    let initial_used_subtree_root = get_used_subtree_root_hash(&circuit_tree);
-    let deposit_priority_op = models::node::FranklinPriorityOp::Deposit(models::node::Deposit {
-        from: validator_account.address,
-        token: 0,
-        amount: BigUint::from(10u32),
-        to: validator_account.address,
-    });
-    let mut op_success = state.execute_priority_op(deposit_priority_op.clone());
-    let mut fees = Vec::new();
-    let mut ops = Vec::new();
-    let mut accounts_updated = Vec::new();
+    let (_, job) = client
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
 
-    if let Some(fee) = op_success.fee {
-        fees.push(fee);
+    // Mocked prover goes through the stages, reporting them with heartbeats.
+    // Old provers send heartbeats without progress, which must be accepted as well.
+    client.working_on(job, None).await.unwrap();
+    for &stage in &[ProvingStage::FetchingData, ProvingStage::ProofGeneration] {
+        let progress = JobProgress {
+            stage,
+            elapsed_secs: 1,
+        };
+        client.working_on(job, Some(progress)).await.unwrap();
     }
 
-    accounts_updated.append(&mut op_success.updates);
+    let jobs_progress: Vec<ReportedJobProgress> = reqwest::Client::new()
+        .get(&format!(
+            "http://{}/api/internal/prover/jobs_progress",
+            &addr
+        ))
+        .send()
+        .await
+        .expect("failed to request jobs progress")
+        .json()
+        .await
+        .expect("failed to parse jobs progress");
+    let job_progress = jobs_progress
+        .into_iter()
+        .find(|progress| progress.prover_run_id == job)
+        .expect("progress of the job is not recorded");
+    assert_eq!(job_progress.stage, ProvingStage::ProofGeneration);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_lists_provers_and_their_jobs() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_timeout = time::Duration::from_secs(10);
+    let rounds_interval = time::Duration::from_secs(10);
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
+
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "listed_prover",
+        time::Duration::from_secs(1),
+    );
+    let prover_id = client
+        .register_prover(&[block_size_chunks])
+        .await
+        .expect("failed to register");
 
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
     storage
         .chain()
-        .state_schema()
-        .commit_state_update(
-            0,
-            &[(
-                0,
-                models::node::AccountUpdate::Create {
-                    address: validator_account.address,
-                    nonce: validator_account.nonce,
-                },
-            )],
-        )
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+    thread::sleep(time::Duration::from_secs(10));
+
+    let (block, job) = client
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
+    let progress = JobProgress {
+        stage: ProvingStage::ProofGeneration,
+        elapsed_secs: 1,
+    };
+    client.working_on(job, Some(progress)).await.unwrap();
+
+    let list_provers = || async {
+        let provers: Vec<prover_server::ProverStatus> = reqwest::Client::new()
+            .get(&format!("http://{}/admin/provers", &addr))
+            .send()
+            .await
+            .expect("failed to request provers")
+            .json()
+            .await
+            .expect("failed to parse provers");
+        provers
+            .into_iter()
+            .find(|prover| prover.id == prover_id)
+            .expect("registered prover is not listed")
+    };
+
+    let prover = list_provers().await;
+    assert_eq!(prover.worker, "listed_prover");
+    assert_eq!(prover.stopped_at, None);
+    assert!(prover.last_heartbeat.is_some());
+    assert_eq!(
+        prover.current_job,
+        Some(prover_server::ProverJob {
+            job_id: job,
+            block,
+            stage: Some(ProvingStage::ProofGeneration),
+        })
+    );
+
+    client.prover_stopped(prover_id).await.unwrap();
+    let prover = list_provers().await;
+    assert!(prover.stopped_at.is_some());
+    assert_eq!(prover.current_job, None);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_tracks_running_provers_in_registry() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_secs(10)).await;
+
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "registry_prover",
+        time::Duration::from_secs(1),
+    );
+    let prover_id = client
+        .register_prover(&[block_size_chunks])
+        .await
+        .expect("failed to register");
+    let job = client
+        .block_to_prove(block_size_chunks)
         .await
-        .unwrap();
+        .expect("failed to get block to prove")
+        .map(|(_, job)| job);
+
+    let list_provers = || async {
+        let provers: BTreeMap<i32, prover_server::ProverInfo> = reqwest::Client::new()
+            .get(&format!("http://{}/provers", &addr))
+            .send()
+            .await
+            .expect("failed to request provers")
+            .json()
+            .await
+            .expect("failed to parse provers");
+        provers
+    };
+
+    let provers = list_provers().await;
+    let prover = provers
+        .get(&prover_id)
+        .expect("registered prover is not listed");
+    assert_eq!(prover.name, "registry_prover");
+    assert_eq!(prover.current_job, job);
+    assert!(prover.last_heartbeat >= prover.registered_at);
+
+    client.prover_stopped(prover_id).await.unwrap();
+    assert!(!list_provers().await.contains_key(&prover_id));
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_extends_job_lease() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_timeout = time::Duration::from_secs(10);
+    let rounds_interval = time::Duration::from_secs(10);
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
+
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "foo",
+        time::Duration::from_secs(1),
+    );
+
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
     storage
         .chain()
-        .state_schema()
-        .apply_state_update(0)
-        .await
-        .unwrap();
-
-    ops.push(models::node::ExecutedOperations::PriorityOp(Box::new(
-        models::node::ExecutedPriorityOp {
-            op: op_success.executed_op,
-            priority_op: models::node::PriorityOp {
-                serial_id: 0,
-                data: deposit_priority_op.clone(),
-                deadline_block: 2,
-                eth_hash: vec![0; 8],
-                eth_block: 10,
-            },
-            block_index: 0,
-            created_at: chrono::Utc::now(),
-        },
-    )));
-
-    let fee_updates = state.collect_fee(&fees, validator_account_id);
-    accounts_updated.extend(fee_updates.into_iter());
-
-    let block = Block::new_from_availabe_block_sizes(
-        state.block_number,
-        state.root_hash(),
-        validator_account_id,
-        ops,
-        (0, 1),
-        &ConfigurationOptions::from_env().available_block_chunk_sizes,
-        1_000_000.into(),
-        1_500_000.into(),
-    );
-
-    let mut pub_data = vec![];
-    let mut operations = vec![];
-
-    if let models::node::FranklinPriorityOp::Deposit(deposit_op) = deposit_priority_op {
-        let deposit_witness = DepositWitness::apply_tx(
-            &mut circuit_tree,
-            &models::node::operations::DepositOp {
-                priority_op: deposit_op,
-                account_id: 0,
-            },
-        );
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+    thread::sleep(time::Duration::from_secs(10));
 
-        let deposit_operations = deposit_witness.calculate_operations(());
-        operations.extend(deposit_operations);
-        pub_data.extend(deposit_witness.get_pubdata());
-    }
+    let (block, job) = client
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
+    client
+        .extend_lease(job, 600)
+        .await
+        .expect("failed to extend lease");
 
-    for _ in 0..block_size_chunks - operations.len() {
-        operations.push(circuit::witness::noop::noop_operation(
-            &circuit_tree,
-            block.fee_account,
-        ));
-        pub_data.extend(vec![false; 64]);
-    }
-    assert_eq!(pub_data.len(), 64 * block_size_chunks);
-    assert_eq!(operations.len(), block_size_chunks);
-
-    let validator_acc = circuit_tree
-        .get(block.fee_account as u32)
-        .expect("fee_account is not empty");
-    let mut validator_balances = vec![];
-    for i in 0..total_tokens() {
-        let balance_value = match validator_acc.subtree.get(i as u32) {
-            None => models::node::Fr::zero(),
-            Some(bal) => bal.value,
-        };
-        validator_balances.push(Some(balance_value));
-    }
-    let _: models::node::Fr = circuit_tree.root_hash();
-    let (root_after_fee, validator_account_witness) =
-        circuit::witness::utils::apply_fee(&mut circuit_tree, block.fee_account, 0, 0);
-
-    assert_eq!(root_after_fee, block.new_root_hash);
-    let (validator_audit_path, _) =
-        circuit::witness::utils::get_audits(&circuit_tree, block.fee_account as u32, 0);
-    let public_data_commitment =
-        circuit::witness::utils::public_data_commitment::<models::node::Engine>(
-            &pub_data,
-            Some(initial_root),
-            Some(root_after_fee),
-            Some(models::node::Fr::from_str(&block.fee_account.to_string()).unwrap()),
-            Some(models::node::Fr::from_str(&(block.block_number).to_string()).unwrap()),
-        );
+    let prover_run = storage
+        .prover_schema()
+        .get_existing_prover_run(block as u32)
+        .await
+        .expect("failed to load prover run")
+        .expect("prover run is not stored");
+    let extended_deadline = prover_run.extended_deadline.expect("lease is not extended");
+    assert!(extended_deadline > prover_run.updated_at);
 
-    (
-        models::Operation {
-            id: None,
-            action: models::Action::Commit,
-            block: block.clone(),
-            accounts_updated,
-        },
-        prover::prover_data::ProverData {
-            public_data_commitment,
-            old_root: initial_root2,
-            initial_used_subtree_root,
-            new_root: block.new_root_hash,
-            validator_address: models::node::Fr::from_str(&block.fee_account.to_string()).unwrap(),
-            operations,
-            validator_balances,
-            validator_audit_path,
-            validator_account: validator_account_witness,
-        },
-    )
+    server.stop(true).await;
 }
 
 #[tokio::test]
 #[cfg_attr(not(feature = "db_test"), ignore)]
-async fn api_server_publish_dummy() {
-    let prover_timeout = time::Duration::from_secs(1);
+async fn api_server_serves_verified_block_prover_data_for_verified_blocks_only() {
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_secs(10)).await;
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "foo",
+        time::Duration::from_secs(1),
+    );
+
+    // Blocks which are not verified yet are proved through the job queue only.
+    let err = client
+        .prover_data_for_block(1_000_000)
+        .await
+        .expect_err("prover data of the unverified block is served");
+    assert!(err.to_string().contains("400"), "unexpected error: {}", err);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_assigns_blocks_according_to_prover_block_sizes() {
+    let block_sizes = ConfigurationOptions::from_env().available_block_chunk_sizes;
+    assert!(
+        block_sizes.len() >= 2,
+        "at least two block sizes are required for the test"
+    );
+    let (block_size, other_block_size) = (block_sizes[0], block_sizes[1]);
+    let prover_timeout = time::Duration::from_secs(10);
     let rounds_interval = time::Duration::from_secs(10);
-    let addr = spawn_server(prover_timeout, rounds_interval).await;
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
 
-    let client = reqwest::Client::new();
-    let res = client
-        .post(&format!("http://{}/publish", &addr))
-        .json(&client::PublishReq {
-            block: 1,
-            proof: EncodedProofPlonk::default(),
-        })
-        .send()
+    // Provers with the disjoint block sizes.
+    let new_client = |worker: &str| {
+        client::AsyncApiClient::new(
+            &format!("http://{}", &addr).parse().unwrap(),
+            worker,
+            time::Duration::from_secs(1),
+        )
+    };
+    let client = new_client("prover_of_small_blocks");
+    let other_client = new_client("prover_of_large_blocks");
+    client
+        .register_prover(&[block_size])
         .await
-        .expect("failed to send publish request");
+        .expect("failed to register");
+    other_client
+        .register_prover(&[other_block_size])
+        .await
+        .expect("failed to register");
 
-    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size)
+        .await
+        .expect("failed to prepare test operation");
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+    thread::sleep(time::Duration::from_secs(10));
+
+    // Block is not assigned to the prover which doesn't support its size, even if asked for.
+    let to_prove = other_client
+        .block_to_prove(block_size)
+        .await
+        .expect("failed to get block to prove");
+    assert!(to_prove.is_none());
+    let to_prove = other_client
+        .block_to_prove(other_block_size)
+        .await
+        .expect("failed to get block to prove");
+    assert!(to_prove.is_none());
+
+    let to_prove = client
+        .block_to_prove(block_size)
+        .await
+        .expect("failed to get block to prove");
+    assert!(to_prove.is_some());
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_stores_prover_registration_metadata() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) = spawn_server(Duration::from_secs(1), Duration::from_secs(10)).await;
+    let client = client::ApiClient::new(
+        &format!("http://{}", &addr),
+        "metadata_prover",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+    let prover_id = tokio::task::block_in_place(|| client.register_prover(&[block_size_chunks]))
+        .expect("failed to register");
+
+    // Provers of the older versions register without the metadata.
+    let res = reqwest::Client::new()
+        .post(&format!("http://{}/register", &addr))
+        .json(&serde_json::json!({
+            "name": "old_prover",
+            "block_sizes": [block_size_chunks],
+        }))
+        .send()
+        .await
+        .expect("failed to send register request");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let old_prover_id: i32 = res
+        .text()
+        .await
+        .expect("failed to read register response")
+        .parse()
+        .expect("prover ID is not a number");
+
+    let db_connection = connect_to_db().await;
+    let mut storage = db_connection
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let prover = storage
+        .prover_schema()
+        .prover_by_id(prover_id)
+        .await
+        .expect("failed to load prover");
+    let expected = client::RegisterReq::new("metadata_prover", &[block_size_chunks]);
+    assert_eq!(prover.worker, "metadata_prover");
+    assert_eq!(prover.block_sizes, vec![block_size_chunks as i64]);
+    assert_eq!(prover.hostname, expected.hostname);
+    assert_eq!(prover.version, expected.version);
+    assert_eq!(
+        prover.cpu_cores,
+        expected.cpu_cores.map(|cores| cores as i32)
+    );
+    assert_eq!(prover.vk_hash, expected.vk_hash);
+
+    let old_prover = storage
+        .prover_schema()
+        .prover_by_id(old_prover_id)
+        .await
+        .expect("failed to load prover");
+    assert_eq!(old_prover.hostname, None);
+    assert_eq!(old_prover.version, None);
+    assert_eq!(old_prover.cpu_cores, None);
+    assert_eq!(old_prover.vk_hash, None);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_rejects_prover_without_block_sizes() {
+    let prover_timeout = time::Duration::from_secs(1);
+    let rounds_interval = time::Duration::from_secs(10);
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
+
+    let res = reqwest::Client::new()
+        .post(&format!("http://{}/register", &addr))
+        .json(&client::RegisterReq::new("foo", &[]))
+        .send()
+        .await
+        .expect("failed to send register request");
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_publish_dummy() {
+    let prover_timeout = time::Duration::from_secs(1);
+    let rounds_interval = time::Duration::from_secs(10);
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post(&format!("http://{}/publish", &addr))
+        .json(&client::PublishReq {
+            block: 1,
+            proof: EncodedProofPlonk::default(),
+            stats: ProvingStats::default(),
+        })
+        .send()
+        .await
+        .expect("failed to send publish request");
+
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    server.stop(true).await;
+}
+
+async fn publish_proof(addr: &str, block: u32, proof: EncodedProofPlonk) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(&format!("http://{}/publish", addr))
+        .json(&client::PublishReq {
+            block,
+            proof,
+            stats: ProvingStats::default(),
+        })
+        .send()
+        .await
+        .expect("failed to send publish request")
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_rejects_invalid_proof_when_verification_is_on() {
+    let (addr, server) = spawn_server_with_config(
+        Duration::from_secs(10),
+        Duration::from_secs(10),
+        |config_opt| config_opt.prover_verify_proofs = true,
+    )
+    .await;
+
+    let block = 401;
+    store_deposit_block_witness(block).await;
+    let res = publish_proof(&addr, block, EncodedProofPlonk::default()).await;
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let reason = res.text().await.expect("failed to read response body");
+    assert!(reason.contains("public data commitment"), "{}", reason);
+
+    // Proof of the block which is not being proven is rejected as well.
+    let res = publish_proof(&addr, block + 1, EncodedProofPlonk::default()).await;
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let stored = storage
+        .prover_schema()
+        .load_proof(block)
+        .await
+        .expect("failed to load proof");
+    assert!(stored.is_none());
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+#[cfg_attr(not(feature = "keys-required"), ignore)]
+async fn api_server_accepts_valid_proof_when_verification_is_on() {
+    let (addr, server) = spawn_server_with_config(
+        Duration::from_secs(10),
+        Duration::from_secs(10),
+        |config_opt| config_opt.prover_verify_proofs = true,
+    )
+    .await;
+
+    let block = 402;
+    let prover_data = store_deposit_block_witness(block).await;
+    let block_size_chunks = prover_data.operations.len();
+    // Proof is generated the same way as by the real prover.
+    let proof = tokio::task::block_in_place(|| {
+        let circuit = prover_data.into_circuit(block as i64);
+        let setup =
+            SetupForStepByStepProver::prepare_setup_for_step_by_step_prover(circuit.clone(), false)
+                .expect("failed to prepare setup");
+        let vk = PlonkVerificationKey::read_verification_key_for_main_circuit(block_size_chunks)
+            .expect("failed to read verification key");
+        setup
+            .gen_step_by_step_proof_using_prepared_setup(circuit, &vk)
+            .expect("failed to generate proof")
+    });
+
+    let res = publish_proof(&addr, block, proof).await;
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_publish_is_idempotent_and_rejects_conflicting_proof() {
+    let prover_timeout = time::Duration::from_secs(1);
+    let rounds_interval = time::Duration::from_secs(10);
+    let (addr, server) = spawn_server(prover_timeout, rounds_interval).await;
+
+    let block = 101;
+    let proof = EncodedProofPlonk::default();
+    let mut other_proof = EncodedProofPlonk::default();
+    other_proof.inputs.push(1u64.into());
+
+    let client = reqwest::Client::new();
+    let publish = |proof: EncodedProofPlonk| {
+        client
+            .post(&format!("http://{}/publish", &addr))
+            .json(&client::PublishReq {
+                block,
+                proof,
+                stats: ProvingStats::default(),
+            })
+            .send()
+    };
+
+    let res = publish(proof.clone())
+        .await
+        .expect("failed to send publish request");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // Same proof is published again, e.g. after the response was lost.
+    let res = publish(proof)
+        .await
+        .expect("failed to send publish request");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // Proof of another prover for the same block is rejected with a clean error.
+    let res = publish(other_proof)
+        .await
+        .expect("failed to send publish request");
+    assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+    let conflict: client::PublishConflictRes =
+        res.json().await.expect("failed to parse conflict response");
+    assert!(conflict.already_published);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_stores_proving_stats_published_with_proof() {
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_secs(10)).await;
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "stats_prover",
+        time::Duration::from_secs(1),
+    );
+
+    let stats = ProvingStats {
+        proving_time_secs: Some(42.5),
+        peak_memory_bytes: Some(16 << 30),
+        worker_name: None,
+        prover_version: Some(prover::PROVER_VERSION.to_string()),
+    };
+    client
+        .publish_with_stats(201, EncodedProofPlonk::default(), stats.clone())
+        .await
+        .expect("failed to publish proof");
+
+    // Older provers don't send the statistics.
+    let res = reqwest::Client::new()
+        .post(&format!("http://{}/publish", &addr))
+        .json(&serde_json::json!({
+            "block": 202,
+            "proof": EncodedProofPlonk::default(),
+        }))
+        .send()
+        .await
+        .expect("failed to send publish request");
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let stored = storage
+        .prover_schema()
+        .load_proof_stats(201)
+        .await
+        .expect("failed to load proving stats")
+        .expect("proof is not stored");
+    // Worker name is filled by the client.
+    assert_eq!(
+        stored,
+        ProvingStats {
+            worker_name: Some("stats_prover".to_string()),
+            ..stats
+        }
+    );
+    let stored = storage
+        .prover_schema()
+        .load_proof_stats(202)
+        .await
+        .expect("failed to load proving stats")
+        .expect("proof is not stored");
+    assert_eq!(stored, ProvingStats::default());
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_accepts_provers_with_correct_secret_auth() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) = spawn_server_with_secret_auth(
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+        Some("prover-secret"),
+    )
+    .await;
+    assert_eq!(
+        health_with_token(&addr, Some("prover-secret")).await,
+        reqwest::StatusCode::OK
+    );
+
+    let client = client::ApiClient::new_with_options(
+        &format!("http://{}", &addr),
+        "foo",
+        client::ClientOptions {
+            secret_auth: Some("prover-secret".to_string()),
+            ..Default::default()
+        },
+    )
+    .expect("failed to create client");
+    let id = tokio::task::block_in_place(|| client.register_prover(&[block_size_chunks]))
+        .expect("failed to register");
+
+    check_prover_registered_and_stopped(&client, id).await;
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_requeues_stuck_job_on_admin_request() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) = spawn_server_with_secret_auth(
+        Duration::from_secs(60),
+        Duration::from_secs(10),
+        Some("prover-secret"),
+    )
+    .await;
+    let new_client = |name: &str| {
+        client::ApiClient::new_with_options(
+            &format!("http://{}", &addr),
+            name,
+            client::ClientOptions {
+                secret_auth: Some("prover-secret".to_string()),
+                // Provers ask for the requeued job right after being told there is none.
+                none_cache_ttl: Duration::from_secs(0),
+                ..Default::default()
+            },
+        )
+        .expect("failed to create client")
+    };
+    let requeue = |block: i64, token: Option<&'static str>| {
+        let request =
+            reqwest::Client::new().post(&format!("http://{}/admin/requeue/{}", &addr, block));
+        let request = match token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        async move {
+            request
+                .send()
+                .await
+                .expect("failed to send requeue request")
+                .status()
+        }
+    };
+
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+    thread::sleep(time::Duration::from_secs(10));
+
+    // Prover takes the job and dies, the job isn't reassigned until the prover timeout.
+    let dead_prover = new_client("dead_prover");
+    let (block, _) = dead_prover
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
+    let other_prover = new_client("other_prover");
+    let next_job = other_prover
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove");
+    assert_ne!(next_job.map(|(next_block, _)| next_block), Some(block));
+
+    assert_eq!(
+        requeue(block, None).await,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        requeue(block, Some("prover-secret")).await,
+        reqwest::StatusCode::OK
+    );
+    let (requeued_block, _) = other_prover
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("requeued block is not assigned");
+    assert_eq!(requeued_block, block);
+
+    other_prover
+        .publish(block, EncodedProofPlonk::default())
+        .await
+        .expect("failed to publish proof");
+    assert_eq!(
+        requeue(block, Some("prover-secret")).await,
+        reqwest::StatusCode::CONFLICT
+    );
+    assert_eq!(
+        requeue(i64::from(u32::max_value()), Some("prover-secret")).await,
+        reqwest::StatusCode::NOT_FOUND
+    );
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_excludes_block_from_prover_timing_out_on_it() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_timeout = Duration::from_secs(1);
+    let (addr, server) =
+        spawn_server_with_config(prover_timeout, Duration::from_secs(10), |config_opt| {
+            config_opt.prover_max_block_attempts = 2;
+        })
+        .await;
+    let new_client = |name: &str| {
+        client::ApiClient::new_with_options(
+            &format!("http://{}", &addr),
+            name,
+            client::ClientOptions {
+                // Provers ask for the timed out job right after being told there is none.
+                none_cache_ttl: Duration::from_secs(0),
+                ..Default::default()
+            },
+        )
+        .expect("failed to create client")
+    };
+
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+    thread::sleep(time::Duration::from_secs(10));
+
+    // Prover claims the block and times out on it, until it's not assigned the block anymore.
+    let stuck_prover = new_client("stuck_prover");
+    tokio::task::block_in_place(|| stuck_prover.register_prover(&[block_size_chunks]))
+        .expect("failed to register");
+    let (block, _) = stuck_prover
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
+    thread::sleep(prover_timeout * 2);
+    let (reassigned_block, _) = stuck_prover
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("timed out block is not reassigned");
+    assert_eq!(reassigned_block, block);
+    thread::sleep(prover_timeout * 2);
+    let next_job = stuck_prover
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove");
+    assert_ne!(next_job.map(|(next_block, _)| next_block), Some(block));
+
+    // Other provers still get the block.
+    let (other_block, _) = new_client("healthy_prover")
+        .block_to_prove(block_size_chunks)
+        .await
+        .expect("failed to get block to prove")
+        .expect("block to prove is not assigned");
+    assert_eq!(other_block, block);
+
+    // Attempts of the stuck prover are listed for the operators.
+    let provers: Vec<prover_server::ProverStatus> = reqwest::Client::new()
+        .get(&format!("http://{}/admin/provers", &addr))
+        .send()
+        .await
+        .expect("failed to request provers")
+        .json()
+        .await
+        .expect("failed to parse provers");
+    let listed_prover = provers
+        .iter()
+        .rev()
+        .find(|prover| prover.worker == "stuck_prover")
+        .expect("stuck prover is not listed");
+    let attempts: Vec<_> = listed_prover
+        .job_history
+        .iter()
+        .filter(|attempt| attempt.block == block)
+        .map(|attempt| attempt.outcome.clone())
+        .collect();
+    assert_eq!(attempts, vec![prover_server::JobOutcome::TimedOut; 2]);
+    assert!(listed_prover.excluded_blocks.contains(&block));
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_rejects_provers_with_wrong_or_missing_secret_auth() {
+    let (addr, server) = spawn_server_with_secret_auth(
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+        Some("prover-secret"),
+    )
+    .await;
+
+    assert_eq!(
+        health_with_token(&addr, Some("wrong-secret")).await,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        health_with_token(&addr, None).await,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_probes_do_not_require_secret_auth() {
+    let (addr, server) = spawn_server_with_secret_auth(
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+        Some("prover-secret"),
+    )
+    .await;
+
+    for path in &["/status", "/healthz", "/readyz"] {
+        let response = reqwest::get(&format!("http://{}{}", addr, path))
+            .await
+            .unwrap_or_else(|e| panic!("failed to request {}: {}", path, e));
+        assert_eq!(response.status(), reqwest::StatusCode::OK, "{}", path);
+    }
+    // Prover endpoints are still authenticated.
+    assert_eq!(
+        health_with_token(&addr, None).await,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_without_secret_auth_accepts_any_provers() {
+    let (addr, server) =
+        spawn_server_with_secret_auth(Duration::from_secs(1), Duration::from_secs(1), None).await;
+
+    assert_eq!(
+        health_with_token(&addr, None).await,
+        reqwest::StatusCode::OK
+    );
+    assert_eq!(
+        health_with_token(&addr, Some("any-secret")).await,
+        reqwest::StatusCode::OK
+    );
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_checks_verification_keys_of_provers() {
+    let block_sizes = ConfigurationOptions::from_env().available_block_chunk_sizes;
+    let expected_hash = "ab".repeat(32);
+    let (addr, server) = {
+        let expected = (block_sizes[0], expected_hash.clone());
+        spawn_server_with_config(
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+            |config_opt| {
+                config_opt.prover_vk_hashes = vec![expected].into_iter().collect();
+            },
+        )
+        .await
+    };
+    let client = client::ApiClient::new(
+        &format!("http://{}", &addr),
+        "vk_checking_prover",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+    let vk_hash = |block_size: usize, hash: &str| client::VkHash {
+        block_size,
+        hash: hash.to_string(),
+    };
+
+    // Matching hash proceeds, hash of the block size without the expected one is not checked.
+    let mut vk_hashes = vec![vk_hash(block_sizes[0], &format!("0x{}", expected_hash))];
+    vk_hashes.extend(
+        block_sizes[1..]
+            .iter()
+            .map(|&size| vk_hash(size, &"cd".repeat(32))),
+    );
+    tokio::task::block_in_place(|| {
+        prover::check_verification_keys(&client, &vk_hashes)
+            .expect("matching verification keys are refused")
+    });
+
+    let vk_hashes = vec![vk_hash(block_sizes[0], &"cd".repeat(32))];
+    let res = tokio::task::block_in_place(|| prover::ApiClient::check_vk(&client, &vk_hashes))
+        .expect("failed to check verification keys");
+    assert_eq!(res.mismatched_block_sizes, vec![block_sizes[0]]);
+    let err = tokio::task::block_in_place(|| {
+        prover::check_verification_keys(&client, &vk_hashes)
+            .err()
+            .expect("mismatching verification keys are accepted")
+    });
+    assert!(
+        matches!(err, prover::BabyProverError::Internal { .. }),
+        "{}",
+        err
+    );
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_reports_its_health() {
+    let (addr, server) = spawn_server(Duration::from_secs(1), Duration::from_secs(10)).await;
+    let client = client::ApiClient::new(
+        &format!("http://{}", &addr),
+        "health_checking_prover",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+
+    let health = tokio::task::block_in_place(|| prover::check_server_health(&client))
+        .expect("server is not healthy");
+    assert!(health.db_connected);
+    assert!(!health.version.is_empty());
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_rate_limits_job_requests_of_worker() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) = spawn_server_with_config(
+        Duration::from_secs(1),
+        Duration::from_secs(10),
+        |config_opt| {
+            config_opt.prover_rate_limit = 1;
+            config_opt.prover_rate_limit_burst = 2;
+        },
+    )
+    .await;
+    let block_to_prove = |worker: &str| {
+        reqwest::Client::new()
+            .get(&format!("http://{}/block_to_prove", addr))
+            .json(&client::ProverReq {
+                name: worker.to_string(),
+                block_size: block_size_chunks,
+            })
+            .send()
+    };
+
+    // Burst of the requests over the limit is rejected.
+    let mut statuses = Vec::new();
+    for _ in 0..5 {
+        let res = block_to_prove("hasty_prover")
+            .await
+            .expect("failed to request block");
+        if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            assert_eq!(res.headers()["retry-after"], "1");
+        }
+        statuses.push(res.status().as_u16());
+    }
+    assert_eq!(statuses, vec![200, 200, 429, 429, 429]);
+
+    // Other workers and the requests without the worker are not limited.
+    let res = block_to_prove("other_prover")
+        .await
+        .expect("failed to request block");
+    assert!(res.status().is_success());
+    assert_eq!(
+        health_with_token(&addr, None).await,
+        reqwest::StatusCode::OK
+    );
+
+    // Client waits for the time the server asked for before the next request.
+    let client = client::ApiClientBuilder::new(&format!("http://{}", addr), "patient_prover")
+        .with_none_cache_ttl(Duration::from_secs(0))
+        .build()
+        .expect("failed to create client");
+    tokio::task::block_in_place(|| {
+        for _ in 0..2 {
+            prover::ApiClient::block_to_prove(&client, block_size_chunks)
+                .expect("request within the burst failed");
+        }
+        let err = prover::ApiClient::block_to_prove(&client, block_size_chunks)
+            .expect_err("request over the burst is not limited");
+        assert!(matches!(
+            err.downcast_ref::<client::ClientError>(),
+            Some(client::ClientError::Http { status, .. })
+                if *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(!client::is_fatal(&err));
+
+        let started = time::Instant::now();
+        prover::ApiClient::block_to_prove(&client, block_size_chunks)
+            .expect("client didn't wait for the rate limit");
+        assert!(started.elapsed() >= Duration::from_millis(500));
+    });
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_reports_liveness_and_readiness() {
+    let conn_pool = connect_to_db().await;
+    let (addr, server) = spawn_server_with_pool(
+        conn_pool.clone(),
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+        |_| {},
+    )
+    .await;
+    let probe = |path: &'static str| {
+        let url = format!("http://{}{}", addr, path);
+        async move {
+            reqwest::get(&url)
+                .await
+                .unwrap_or_else(|e| panic!("failed to request {}: {}", path, e))
+        }
+    };
+
+    let response = probe("/healthz").await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let response = probe("/readyz").await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let readiness: prover_server::Readiness =
+        response.json().await.expect("failed to decode readiness");
+    assert!(readiness.ready);
+    assert!(readiness.failing.is_empty());
+
+    // Server is alive, but not ready without the database.
+    conn_pool.close().await;
+    let response = probe("/healthz").await;
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let response = probe("/readyz").await;
+    assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let readiness: prover_server::Readiness =
+        response.json().await.expect("failed to decode readiness");
+    assert!(!readiness.ready);
+    let subsystems: Vec<_> = readiness
+        .failing
+        .iter()
+        .map(|failure| failure.subsystem.as_str())
+        .collect();
+    assert_eq!(subsystems, vec!["database"]);
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_is_stopped_by_its_handle() {
+    let (addr, server) = spawn_server(Duration::from_secs(1), Duration::from_secs(1)).await;
+    assert_eq!(
+        health_with_token(&addr, None).await,
+        reqwest::StatusCode::OK
+    );
+
+    server.stop(true).await;
+    reqwest::Client::new()
+        .get(&format!("http://{}/status", addr))
+        .send()
+        .await
+        .expect_err("stopped server is still serving requests");
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_long_poll_returns_once_witness_is_generated() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let rounds_interval = Duration::from_secs(1);
+    let (addr, server) = spawn_server(Duration::from_secs(10), rounds_interval).await;
+    let client = client::ApiClient::new(
+        &format!("http://{}", &addr),
+        "long_poll_prover",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+
+    // No job of the size nobody proves, the wait expires.
+    let started = time::Instant::now();
+    let to_prove = tokio::task::block_in_place(|| {
+        client.block_to_prove_wait(block_size_chunks + 1, Duration::from_millis(500))
+    })
+    .expect("failed to wait for block to prove");
+    assert!(to_prove.is_none());
+    assert!(started.elapsed() >= Duration::from_millis(500));
+
+    // Long-poll is started before the operation is inserted.
+    let max_wait = Duration::from_secs(50);
+    let waiting_client = client.clone();
+    let waiting = thread::spawn(move || {
+        let to_prove = waiting_client.block_to_prove_wait(block_size_chunks, max_wait);
+        (to_prove, time::Instant::now())
+    });
+    thread::sleep(Duration::from_millis(200));
+
+    let db_connection = connect_to_db().await;
+    let mut storage = db_connection
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    let inserted = time::Instant::now();
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+
+    // Prover gets the block right after the witness is generated, instead of waiting
+    // for the wait to expire and then polling again.
+    let (to_prove, returned) = tokio::task::block_in_place(|| waiting.join().unwrap());
+    let to_prove = to_prove.expect("failed to wait for block to prove");
+    assert!(to_prove.is_some());
+    assert!(
+        returned - inserted < max_wait / 2,
+        "long-poll returned after {:?}",
+        returned - inserted
+    );
+
+    server.stop(true).await;
+}
+
+lazy_static::lazy_static! {
+    /// Spans are recorded by the tracer without exporter, so they get the trace IDs.
+    /// Provider is kept alive, as the tracer records nothing once it's dropped.
+    static ref TEST_TRACER_PROVIDER: opentelemetry::sdk::trace::TracerProvider =
+        opentelemetry::sdk::trace::TracerProvider::builder().build();
+}
+
+fn start_test_tracing() {
+    use opentelemetry::trace::TracerProvider;
+
+    static START: std::sync::Once = std::sync::Once::new();
+    START.call_once(|| {
+        prover::telemetry::start_tracing(
+            TEST_TRACER_PROVIDER.get_tracer("prover_server_tests", None),
+        )
+    });
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_returns_trace_id_of_prover_request() {
+    start_test_tracing();
+    let (addr, server) = spawn_server(Duration::from_secs(1), Duration::from_secs(1)).await;
+
+    // Prover propagates the trace of its current span.
+    let span = tracing::info_span!("prover_round");
+    let trace_id = prover::telemetry::trace_id(&span).expect("span is not recorded");
+    let headers = span.in_scope(prover::telemetry::trace_context_headers);
+    let traceparent = headers.get("traceparent").expect("no traceparent header");
+    assert!(traceparent.contains(&trace_id));
+
+    let block_to_prove = |traceparent: Option<&str>| {
+        let mut request = reqwest::Client::new()
+            .get(&format!("http://{}/block_to_prove", addr))
+            .json(&client::ProverReq {
+                name: "traced_prover".to_string(),
+                block_size: ConfigurationOptions::from_env().available_block_chunk_sizes[0],
+            });
+        if let Some(traceparent) = traceparent {
+            request = request.header("traceparent", traceparent);
+        }
+        request.send()
+    };
+
+    // Server records the request to the trace of the prover.
+    let res = block_to_prove(Some(traceparent))
+        .await
+        .expect("failed to request block");
+    assert!(res.status().is_success());
+    assert_eq!(res.headers()["x-trace-id"], trace_id.as_str());
+
+    // Request without the trace context starts the new trace.
+    let res = block_to_prove(None).await.expect("failed to request block");
+    let new_trace_id = res.headers()["x-trace-id"].to_str().unwrap();
+    assert_ne!(new_trace_id, trace_id);
+
+    // Untraced requests have no trace ID.
+    let res = reqwest::Client::new()
+        .get(&format!("http://{}/status", addr))
+        .send()
+        .await
+        .expect("failed to request status");
+    assert!(res.headers().get("x-trace-id").is_none());
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_publish_batch_stores_all_proofs_or_none() {
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_secs(10)).await;
+    let client = client::ApiClient::new(
+        &format!("http://{}", &addr),
+        "batch_prover",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+    let proof = EncodedProofPlonk::default();
+    let mut other_proof = EncodedProofPlonk::default();
+    other_proof.inputs.push(1u64.into());
+
+    let results = tokio::task::block_in_place(|| {
+        prover::ApiClient::publish_batch(&client, vec![(201, proof.clone()), (202, proof.clone())])
+    })
+    .expect("failed to publish batch");
+    assert_eq!(
+        results,
+        vec![client::PublishResult::Stored, client::PublishResult::Stored]
+    );
+
+    // Conflicting proof rejects the whole batch.
+    let results = tokio::task::block_in_place(|| {
+        prover::ApiClient::publish_batch(&client, vec![(202, other_proof), (203, proof)])
+    })
+    .expect("failed to publish batch");
+    assert_eq!(
+        results,
+        vec![
+            client::PublishResult::Conflict,
+            client::PublishResult::NotCommitted
+        ]
+    );
+
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    for &(block, stored) in &[(201, true), (202, true), (203, false)] {
+        let proof = storage
+            .prover_schema()
+            .load_proof(block)
+            .await
+            .expect("failed to load proof");
+        assert_eq!(proof.is_some(), stored, "block {}", block);
+    }
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_rejects_publish_batch_with_unordered_blocks() {
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_secs(10)).await;
+
+    let batch: Vec<_> = [205, 204]
+        .iter()
+        .map(|&block| client::PublishReq {
+            block,
+            proof: EncodedProofPlonk::default(),
+            stats: ProvingStats::default(),
+        })
+        .collect();
+    let res = reqwest::Client::new()
+        .post(&format!("http://{}/publish_batch", &addr))
+        .json(&batch)
+        .send()
+        .await
+        .expect("failed to send publish batch request");
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    server.stop(true).await;
+}
+
+#[tokio::test(threaded_scheduler)]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_rejects_proof_already_published_by_another_prover() {
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_secs(10)).await;
+    let new_client = |worker: &str| {
+        client::ApiClient::new(
+            &format!("http://{}", &addr),
+            worker,
+            time::Duration::from_secs(1),
+        )
+        .expect("failed to create client")
+    };
+    let (first_prover, second_prover) = (new_client("first_prover"), new_client("second_prover"));
+    let block = 301;
+    let first_proof = EncodedProofPlonk::default();
+    let mut second_proof = EncodedProofPlonk::default();
+    second_proof.inputs.push(1u64.into());
+
+    tokio::task::block_in_place(|| prover::ApiClient::publish(&first_prover, block, first_proof))
+        .expect("failed to publish proof");
+    let err = tokio::task::block_in_place(|| {
+        prover::ApiClient::publish(&second_prover, block, second_proof)
+    })
+    .expect_err("second proof for the block was published");
+    assert!(matches!(
+        prover::BabyProverError::from_publish(block, err),
+        prover::BabyProverError::AlreadyPublished { block: 301 }
+    ));
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_reports_proof_status_of_block() {
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_secs(10)).await;
+    let client = client::ApiClient::new(
+        &format!("http://{}", &addr),
+        "proof_status_prover",
+        time::Duration::from_secs(1),
+    )
+    .expect("failed to create client");
+    let block = 311;
+
+    let status = tokio::task::block_in_place(|| prover::ApiClient::proof_status(&client, 1 << 30))
+        .expect("failed to get proof status");
+    assert_eq!(status.block, 1 << 30);
+    assert_eq!(status.status, client::ProofState::NotFound);
+    assert_eq!(status.proof_created_at, None);
+
+    tokio::task::block_in_place(|| {
+        prover::ApiClient::publish(&client, block, EncodedProofPlonk::default())
+    })
+    .expect("failed to publish proof");
+    let status = tokio::task::block_in_place(|| prover::ApiClient::proof_status(&client, block))
+        .expect("failed to get proof status");
+    assert_eq!(status.block, block);
+    assert_eq!(status.status, client::ProofState::Proved);
+    assert!(status.proof_created_at.is_some());
+
+    // Status is served as the plain JSON for the scripts.
+    let res: serde_json::Value = reqwest::get(&format!("http://{}/block/{}/proof", &addr, block))
+        .await
+        .expect("failed to send proof status request")
+        .json()
+        .await
+        .expect("failed to parse proof status");
+    assert_eq!(res["status"], "proved");
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_generates_witnesses_with_several_workers() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (_, server) = spawn_server_with_config(
+        Duration::from_secs(10),
+        Duration::from_millis(100),
+        |config_opt| config_opt.witness_generators = 2,
+    )
+    .await;
+
+    let db_connection = connect_to_db().await;
+    let mut storage = db_connection
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    let root_hash = op.block.new_root_hash;
+    let mut ops = vec![op];
+    ops.push(empty_block_operation(2, root_hash));
+    ops.push(empty_block_operation(3, root_hash));
+    for op in ops {
+        storage
+            .chain()
+            .block_schema()
+            .execute_operation(op)
+            .await
+            .expect("failed to mock commit operation");
+    }
+
+    let started = time::Instant::now();
+    for block in 1..=3 {
+        while storage
+            .prover_schema()
+            .get_witness(block)
+            .await
+            .expect("failed to load witness")
+            .is_none()
+        {
+            assert!(
+                started.elapsed() < Duration::from_secs(60),
+                "witness of block {} is not generated",
+                block
+            );
+            tokio::time::delay_for(Duration::from_millis(100)).await;
+        }
+    }
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_regenerates_stale_witness_and_serves_stored_one() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let db_connection = connect_to_db().await;
+    let mut storage = db_connection
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, wanted_prover_data) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    let wanted_root = models::fe_to_hex(&op.block.new_root_hash);
+
+    // Witness generated before the block was committed again with another root.
+    storage
+        .prover_schema()
+        .store_witness(1, serde_json::json!({ "new_root": "00".repeat(32) }))
+        .await
+        .expect("failed to store witness");
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_millis(100)).await;
+    let started = time::Instant::now();
+    let witness = loop {
+        let witness = storage
+            .prover_schema()
+            .get_witness(1)
+            .await
+            .expect("failed to load witness");
+        if let Some(witness) = witness.filter(|witness| witness["new_root"] == wanted_root) {
+            break witness;
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "stale witness is not regenerated"
+        );
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+    };
+
+    // Witness is generated once and served from the storage afterwards.
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "foo",
+        time::Duration::from_secs(1),
+    );
+    for _ in 0..2 {
+        let prover_data = client
+            .prover_data(1)
+            .await
+            .expect("failed to get prover data");
+        assert_eq!(prover_data.old_root, Some(wanted_prover_data.old_root));
+    }
+    let served_witness = storage
+        .prover_schema()
+        .get_witness(1)
+        .await
+        .expect("failed to load witness");
+    assert_eq!(served_witness, Some(witness));
+
+    server.stop(true).await;
+}
+
+/// Downloads all the parts of the prover data of the block, accepting the `accept` media type.
+async fn download_prover_data_parts(addr: &str, block: u32, accept: &str) -> Vec<u8> {
+    let http_client = reqwest::Client::new();
+    let mut data = Vec::new();
+    let mut part = 0;
+    loop {
+        let res = http_client
+            .get(&format!(
+                "http://{}/prover_data/{}/part/{}",
+                addr, block, part
+            ))
+            .header(reqwest::header::ACCEPT, accept)
+            .send()
+            .await
+            .expect("failed to request prover data part");
+        let parts: usize = res.headers()[client::PROVER_DATA_PARTS_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        data.extend_from_slice(&res.bytes().await.expect("failed to read prover data part"));
+        part += 1;
+        if part == parts {
+            return data;
+        }
+    }
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_serves_binary_prover_data_to_provers_accepting_it() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let (addr, server) = spawn_server(Duration::from_secs(10), Duration::from_millis(100)).await;
+
+    let db_connection = connect_to_db().await;
+    let mut storage = db_connection
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+    let started = time::Instant::now();
+    while storage
+        .prover_schema()
+        .get_witness(1)
+        .await
+        .expect("failed to load witness")
+        .is_none()
+    {
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "witness is not generated"
+        );
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+    }
+
+    // Old provers don't accept the binary encoding.
+    let json = download_prover_data_parts(&addr, 1, "*/*").await;
+    let binary =
+        download_prover_data_parts(&addr, 1, prover::prover_data::BINARY_CONTENT_TYPE).await;
+    assert!(
+        binary.len() < json.len(),
+        "binary encoding takes {} bytes, JSON {}",
+        binary.len(),
+        json.len()
+    );
+    assert_eq!(binary[0], prover::prover_data::BINARY_ENCODING_VERSION);
+    let decode = |data: &[u8]| {
+        let prover_data = prover::prover_data::decode(data)
+            .expect("failed to decode prover data")
+            .expect("prover data is absent");
+        serde_json::to_value(prover_data).unwrap()
+    };
+    assert_eq!(decode(&binary), decode(&json));
+
+    server.stop(true).await;
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "db_test"), ignore)]
+async fn api_server_adjusts_rounds_interval_to_jobs() {
+    let block_size_chunks = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let rounds_interval =
+        RoundsIntervalScheduler::new(Duration::from_millis(100), Duration::from_millis(500));
+    let (addr, server) =
+        spawn_server_with_config(Duration::from_secs(10), rounds_interval.clone(), |_| {}).await;
+    let client = client::AsyncApiClient::new(
+        &format!("http://{}", &addr).parse().unwrap(),
+        "foo",
+        time::Duration::from_secs(1),
+    );
+
+    // Interval grows while there are no jobs.
+    for _ in 0..2 {
+        let to_prove = client
+            .block_to_prove(block_size_chunks)
+            .await
+            .expect("failed to get block to prove");
+        assert!(to_prove.is_none());
+    }
+    assert_eq!(rounds_interval.interval(), Duration::from_millis(400));
+
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    let (op, _) = test_operation_and_wanted_prover_data(block_size_chunks)
+        .await
+        .expect("failed to prepare test operation");
+    storage
+        .chain()
+        .block_schema()
+        .execute_operation(op)
+        .await
+        .expect("failed to mock commit operation");
+
+    // Witness is still generated with the grown interval, and the first job resets it.
+    let started = time::Instant::now();
+    loop {
+        let to_prove = client
+            .block_to_prove(block_size_chunks)
+            .await
+            .expect("failed to get block to prove");
+        if to_prove.is_some() {
+            break;
+        }
+        assert_eq!(rounds_interval.interval(), Duration::from_millis(500));
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "block to prove is not assigned"
+        );
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+    }
+    assert_eq!(rounds_interval.interval(), Duration::from_millis(100));
+
+    server.stop(true).await;
 }
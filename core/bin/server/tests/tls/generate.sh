@@ -0,0 +1,37 @@
+#!/bin/bash
+# Generates the certificates of the mutual TLS tests of the prover server:
+# the CA, the server certificate for `localhost` and the prover certificate, signed by the CA.
+set -e
+cd "$(dirname "$0")"
+
+DAYS=36500
+
+key() {
+    openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out "$1"
+}
+
+key ca.key.pem
+openssl req -x509 -new -key ca.key.pem -sha256 -days $DAYS -subj "/CN=zksync test CA" \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign" \
+    -out ca.pem
+
+sign() {
+    local name=$1 subject=$2 extensions=$3
+    key "$name.key.pem"
+    openssl req -new -key "$name.key.pem" -subj "$subject" -out "$name.csr"
+    openssl x509 -req -in "$name.csr" -CA ca.pem -CAkey ca.key.pem -CAcreateserial -sha256 -days $DAYS \
+        -extfile <(printf "%s" "$extensions") -out "$name.pem"
+    rm "$name.csr"
+}
+
+sign server "/CN=localhost" \
+    "basicConstraints=CA:FALSE
+keyUsage=critical,digitalSignature
+extendedKeyUsage=serverAuth
+subjectAltName=DNS:localhost"
+sign prover "/CN=prover" \
+    "basicConstraints=CA:FALSE
+keyUsage=critical,digitalSignature
+extendedKeyUsage=clientAuth"
+
+rm -f ca.srl ca.key.pem
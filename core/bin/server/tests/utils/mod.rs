@@ -0,0 +1,259 @@
+//! Utilities shared by the prover server tests.
+
+// Built-in deps
+use std::path::PathBuf;
+// External deps
+use crypto_exports::pairing::ff::{Field, PrimeField};
+use num::BigUint;
+// Workspace deps
+use circuit::witness::{
+    deposit::DepositWitness,
+    utils::{get_used_subtree_root_hash, WitnessError},
+    Witness,
+};
+use models::{
+    config_options::{ConfigurationOptions, TlsConfig},
+    node::{block::Block, Address},
+    params::total_tokens,
+    Action, OperationBuilder,
+};
+
+pub async fn connect_to_db() -> storage::ConnectionPool {
+    storage::ConnectionPool::new(Some(1)).await
+}
+
+pub async fn test_operation_and_wanted_prover_data(
+    block_size_chunks: usize,
+) -> Result<(models::Operation, prover::prover_data::ProverData), WitnessError> {
+    let mut circuit_tree =
+        models::circuit::CircuitAccountTree::new(models::params::account_tree_depth());
+    // insert account and its balance
+
+    let db_connection = connect_to_db().await;
+    let mut storage = db_connection
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+
+    // Fee account
+    let mut accounts = models::node::AccountMap::default();
+    let validator_account = models::node::Account::default_with_address(&Address::random());
+    let validator_account_id: u32 = 0;
+    accounts.insert(validator_account_id, validator_account.clone());
+
+    let mut state = plasma::state::PlasmaState::from_acc_map(accounts, 1);
+    println!(
+        "acc_number 0, acc {:?}",
+        models::circuit::account::CircuitAccount::from(validator_account.clone()).pub_key_hash,
+    );
+    circuit_tree.insert(
+        0,
+        models::circuit::account::CircuitAccount::from(validator_account.clone()),
+    );
+    let initial_root = circuit_tree.root_hash();
+    let initial_root2 = circuit_tree.root_hash();
+    let initial_used_subtree_root = get_used_subtree_root_hash(&circuit_tree);
+    let deposit_priority_op = models::node::FranklinPriorityOp::Deposit(models::node::Deposit {
+        from: validator_account.address,
+        token: 0,
+        amount: BigUint::from(10u32),
+        to: validator_account.address,
+    });
+    let mut op_success = state.execute_priority_op(deposit_priority_op.clone());
+    let mut fees = Vec::new();
+    let mut ops = Vec::new();
+    let mut accounts_updated = Vec::new();
+
+    if let Some(fee) = op_success.fee {
+        fees.push(fee);
+    }
+
+    accounts_updated.append(&mut op_success.updates);
+
+    storage
+        .chain()
+        .state_schema()
+        .commit_state_update(
+            0,
+            &[(
+                0,
+                models::node::AccountUpdate::Create {
+                    address: validator_account.address,
+                    nonce: validator_account.nonce,
+                },
+            )],
+        )
+        .await
+        .unwrap();
+    storage
+        .chain()
+        .state_schema()
+        .apply_state_update(0)
+        .await
+        .unwrap();
+
+    ops.push(models::node::ExecutedOperations::PriorityOp(Box::new(
+        models::node::ExecutedPriorityOp {
+            op: op_success.executed_op,
+            priority_op: models::node::PriorityOp {
+                serial_id: 0,
+                data: deposit_priority_op.clone(),
+                deadline_block: 2,
+                eth_hash: vec![0; 8],
+                eth_block: 10,
+            },
+            block_index: 0,
+            created_at: chrono::Utc::now(),
+        },
+    )));
+
+    let fee_updates = state.collect_fee(&fees, validator_account_id);
+    accounts_updated.extend(fee_updates.into_iter());
+
+    let block = Block::new_from_availabe_block_sizes(
+        state.block_number,
+        state.root_hash(),
+        validator_account_id,
+        ops,
+        (0, 1),
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+        1_000_000.into(),
+        1_500_000.into(),
+    );
+
+    let mut pub_data = vec![];
+    let mut operations = vec![];
+
+    if let models::node::FranklinPriorityOp::Deposit(deposit_op) = deposit_priority_op {
+        let deposit_witness = DepositWitness::apply_tx(
+            &mut circuit_tree,
+            &models::node::operations::DepositOp {
+                priority_op: deposit_op,
+                account_id: 0,
+            },
+        );
+
+        let deposit_operations = deposit_witness.calculate_operations(());
+        operations.extend(deposit_operations);
+        pub_data.extend(deposit_witness.get_pubdata());
+    }
+
+    for _ in 0..block_size_chunks - operations.len() {
+        operations.push(circuit::witness::noop::noop_operation(
+            &circuit_tree,
+            block.fee_account,
+        ));
+        pub_data.extend(vec![false; 64]);
+    }
+    assert_eq!(pub_data.len(), 64 * block_size_chunks);
+    assert_eq!(operations.len(), block_size_chunks);
+
+    let validator_acc = circuit_tree
+        .get(block.fee_account as u32)
+        .expect("fee_account is not empty");
+    let mut validator_balances = vec![];
+    for i in 0..total_tokens() {
+        let balance_value = match validator_acc.subtree.get(i as u32) {
+            None => models::node::Fr::zero(),
+            Some(bal) => bal.value,
+        };
+        validator_balances.push(Some(balance_value));
+    }
+    let _: models::node::Fr = circuit_tree.root_hash();
+    let (root_after_fee, validator_account_witness) =
+        circuit::witness::utils::try_apply_fee(&mut circuit_tree, block.fee_account, 0, 0)?;
+
+    assert_eq!(root_after_fee, block.new_root_hash);
+    let (validator_audit_path, _) =
+        circuit::witness::utils::get_audits(&circuit_tree, block.fee_account as u32, 0);
+    let public_data_commitment =
+        circuit::witness::utils::public_data_commitment::<models::node::Engine>(
+            &pub_data,
+            Some(initial_root),
+            Some(root_after_fee),
+            Some(models::node::Fr::from_str(&block.fee_account.to_string()).unwrap()),
+            Some(models::node::Fr::from_str(&(block.block_number).to_string()).unwrap()),
+        );
+
+    Ok((
+        OperationBuilder::new()
+            .block(block.clone())
+            .action(Action::Commit)
+            .accounts_updated(accounts_updated)
+            .build(),
+        prover::prover_data::ProverData {
+            public_data_commitment,
+            old_root: initial_root2,
+            initial_used_subtree_root,
+            new_root: block.new_root_hash,
+            validator_address: models::node::Fr::from_str(&block.fee_account.to_string()).unwrap(),
+            operations,
+            validator_balances,
+            validator_audit_path,
+            validator_account: validator_account_witness,
+        },
+    ))
+}
+
+/// Commit operation of the block `block_number` without transactions, following the block
+/// with the root hash `root_hash`, e.g. the one of `test_operation_and_wanted_prover_data`.
+pub fn empty_block_operation(
+    block_number: models::node::BlockNumber,
+    root_hash: models::node::Fr,
+) -> models::Operation {
+    let block = Block::new_from_availabe_block_sizes(
+        block_number,
+        root_hash,
+        0,
+        vec![],
+        (1, 1),
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+        1_000_000.into(),
+        1_500_000.into(),
+    );
+    OperationBuilder::new()
+        .block(block)
+        .action(Action::Commit)
+        .build()
+}
+
+/// Stores the prover data of the block with a single deposit as the witness of `block`.
+pub async fn store_deposit_block_witness(block: u32) -> prover::prover_data::ProverData {
+    let block_size_chunks = prover::testing::smallest_deposit_block_size(
+        &ConfigurationOptions::from_env().available_block_chunk_sizes,
+    );
+    let prover_data = prover::testing::deposit_block_prover_data(block_size_chunks);
+    let mut storage = connect_to_db()
+        .await
+        .access_storage()
+        .await
+        .expect("Failed to connect to db");
+    storage
+        .prover_schema()
+        .store_witness(
+            block,
+            serde_json::to_value(&prover_data).expect("failed to serialize prover data"),
+        )
+        .await
+        .expect("failed to store witness");
+    prover_data
+}
+
+/// TLS files of the `side` ("server" or "prover") generated by `tests/tls/generate.sh`.
+pub fn test_tls_config(side: &str) -> TlsConfig {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/tls");
+    TlsConfig {
+        cert_pem: dir.join(format!("{}.pem", side)),
+        key_pem: dir.join(format!("{}.key.pem", side)),
+        ca_pem: dir.join("ca.pem"),
+    }
+}
+
+/// URL of the server bound to `addr` over TLS. Test server certificate is issued for `localhost`.
+pub fn tls_server_url(addr: &str) -> String {
+    let port = addr
+        .parse::<std::net::SocketAddr>()
+        .expect("invalid server address")
+        .port();
+    format!("https://localhost:{}", port)
+}
@@ -0,0 +1,67 @@
+//! Serde remote definitions of the circuit witness types, which don't implement `Serialize`
+//! and `Deserialize` themselves. Used with `#[serde(with = "...Def")]`.
+
+// External
+use serde::{Deserialize, Serialize};
+// Workspace
+use models::node::{Engine, Fr};
+use models::serialization::{OptionalFrSerde, VecOptionalFrSerde};
+// Local
+use crate::account::AccountWitness;
+use crate::operation::{OperationArguments, OperationBranch, OperationBranchWitness};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "AccountWitness::<Engine>")]
+pub struct AccountWitnessDef {
+    #[serde(with = "OptionalFrSerde")]
+    pub nonce: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub pub_key_hash: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub address: Option<Fr>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "OperationArguments::<Engine>")]
+pub struct OperationArgumentsDef {
+    #[serde(with = "OptionalFrSerde")]
+    pub a: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub b: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub amount_packed: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub full_amount: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub fee: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub new_pub_key_hash: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub eth_address: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub pub_nonce: Option<Fr>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "OperationBranch::<Engine>")]
+pub struct OperationBranchDef {
+    #[serde(with = "OptionalFrSerde")]
+    pub address: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    pub token: Option<Fr>,
+    #[serde(with = "OperationBranchWitnessDef")]
+    pub witness: OperationBranchWitness<Engine>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "OperationBranchWitness::<Engine>")]
+pub struct OperationBranchWitnessDef {
+    #[serde(with = "AccountWitnessDef")]
+    pub account_witness: AccountWitness<Engine>,
+    #[serde(with = "VecOptionalFrSerde")]
+    pub account_path: Vec<Option<Fr>>,
+    #[serde(with = "OptionalFrSerde")]
+    pub balance_value: Option<Fr>,
+    #[serde(with = "VecOptionalFrSerde")]
+    pub balance_subtree_path: Vec<Option<Fr>>,
+}
@@ -1,3 +1,5 @@
+// Built-in deps
+use std::io::{Read, Write};
 // External deps
 use crypto_exports::franklin_crypto::{
     bellman::pairing::{
@@ -6,6 +8,7 @@ use crypto_exports::franklin_crypto::{
     },
     rescue::RescueEngine,
 };
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 // Workspace deps
 use models::{
     circuit::{
@@ -18,12 +21,14 @@ use models::{
         ETH_ADDRESS_BIT_WIDTH, NEW_PUBKEY_HASH_WIDTH, NONCE_BIT_WIDTH, TOKEN_BIT_WIDTH,
         TX_TYPE_BIT_WIDTH,
     },
+    serialization::OptionalFrSerde,
 };
 // Local deps
 use crate::{
     operation::{
         Operation, OperationArguments, OperationBranch, OperationBranchWitness, SignatureData,
     },
+    serialization::{OperationArgumentsDef, OperationBranchDef},
     utils::resize_grow_only,
     witness::{
         utils::{apply_leaf_operation, get_audits},
@@ -47,6 +52,35 @@ pub struct DepositWitness<E: RescueEngine> {
     pub tx_type: Option<E::Fr>,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "DepositWitness::<Bn256>")]
+struct DepositWitnessDef {
+    #[serde(with = "OperationBranchDef")]
+    before: OperationBranch<Bn256>,
+    #[serde(with = "OperationBranchDef")]
+    after: OperationBranch<Bn256>,
+    #[serde(with = "OperationArgumentsDef")]
+    args: OperationArguments<Bn256>,
+    #[serde(with = "OptionalFrSerde")]
+    before_root: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    after_root: Option<Fr>,
+    #[serde(with = "OptionalFrSerde")]
+    tx_type: Option<Fr>,
+}
+
+impl Serialize for DepositWitness<Bn256> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DepositWitnessDef::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DepositWitness<Bn256> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        DepositWitnessDef::deserialize(deserializer)
+    }
+}
+
 impl Witness for DepositWitness<Bn256> {
     type OperationType = DepositOp;
     type CalculateOpsInput = ();
@@ -176,6 +210,16 @@ impl<E: RescueEngine> DepositWitness<E> {
 }
 
 impl DepositWitness<Bn256> {
+    /// Writes the witness as JSON.
+    pub fn save(&self, w: impl Write) -> Result<(), serde_json::Error> {
+        serde_json::to_writer_pretty(w, self)
+    }
+
+    /// Reads the witness written by `save`.
+    pub fn load(r: impl Read) -> Result<Self, serde_json::Error> {
+        serde_json::from_reader(r)
+    }
+
     fn apply_data(tree: &mut CircuitAccountTree, deposit: &DepositData) -> Self {
         //preparing data and base witness
         let before_root = tree.root_hash();
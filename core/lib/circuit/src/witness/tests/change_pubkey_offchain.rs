@@ -6,14 +6,16 @@ use plasma::state::CollectedFee;
 // Local deps
 use crate::witness::{
     change_pubkey_offchain::ChangePubkeyOffChainWitness,
-    tests::test_utils::{generic_test_scenario, incorrect_op_test_scenario, WitnessTestAccount},
+    tests::test_utils::{
+        generic_test_scenario, incorrect_op_test_scenario, WitnessError, WitnessTestAccount,
+    },
 };
 
 /// Basic check for execution of `ChangePubKeyOp` in circuit.
 /// Here we generate an empty account and change its public key.
 #[test]
 #[ignore]
-fn test_change_pubkey_offchain_success() {
+fn test_change_pubkey_offchain_success() -> Result<(), WitnessError> {
     // Input data.
     let accounts = vec![WitnessTestAccount::new_empty(0xc1)];
     let account = &accounts[0];
@@ -36,7 +38,9 @@ fn test_change_pubkey_offchain_success() {
 
             vec![fee]
         },
-    );
+    )?;
+
+    Ok(())
 }
 
 /// Checks that executing a change pubkey operation with incorrect
@@ -72,5 +76,6 @@ fn test_incorrect_change_pubkey_account() {
                 amount: 0u32.into(),
             }]
         },
-    );
+    )
+    .expect("failed to collect fees");
 }
@@ -1,19 +1,101 @@
+// Built-in deps
+use std::env;
+use std::fs::File;
+use std::time::{SystemTime, UNIX_EPOCH};
 // External deps
 use crypto_exports::franklin_crypto::bellman::pairing::bn256::Bn256;
 use num::BigUint;
+use serde::{Deserialize, Serialize};
 // Workspace deps
-use models::node::{operations::DepositOp, Deposit};
+use models::circuit::{account::CircuitAccount, CircuitAccountTree};
+use models::node::{operations::DepositOp, Account, AccountId, BlockNumber, Deposit};
+use models::params::account_tree_depth;
 // Local deps
 use crate::witness::{
     deposit::DepositWitness,
-    tests::test_utils::{generic_test_scenario, WitnessTestAccount},
+    tests::test_utils::{
+        check_circuit, generic_test_scenario, test_scenario_with_failure_hook,
+        PlasmaStateGenerator, WitnessBuilder, WitnessError, WitnessTestAccount, FEE_ACCOUNT_ID,
+    },
+    Witness,
 };
 
+/// Environment variable with the path of the state saved by a failed deposit test,
+/// which is replayed by `replay_deposit_witness`.
+const REPLAY_ENV_VAR: &str = "DEPOSIT_WITNESS_REPLAY";
+
+/// State of a failed deposit test, which is enough to re-run the proving of its witness.
+/// The account tree is saved as the accounts it is built from.
+#[derive(Serialize, Deserialize)]
+struct FailedDepositTest<W> {
+    accounts: Vec<(AccountId, Account)>,
+    fee_account_id: AccountId,
+    block_number: BlockNumber,
+    deposit_op: DepositOp,
+    witness: W,
+}
+
+/// Runs the `generic_test_scenario` for the deposit, saving the state of the test
+/// to a temporary file if the checks fail.
+fn deposit_test_scenario(
+    accounts: &[WitnessTestAccount],
+    deposit_op: DepositOp,
+) -> Result<(), WitnessError> {
+    let failed_op = deposit_op.clone();
+    test_scenario_with_failure_hook::<DepositWitness<Bn256>, _, _>(
+        accounts,
+        deposit_op,
+        (),
+        |plasma_state, op| {
+            plasma_state.apply_deposit_op(op);
+            vec![]
+        },
+        |witness| save_failed_test(accounts, failed_op, witness),
+    )
+}
+
+fn save_failed_test(
+    accounts: &[WitnessTestAccount],
+    deposit_op: DepositOp,
+    witness: &DepositWitness<Bn256>,
+) {
+    // The test scenario builds the same account tree and witness accumulator.
+    let (plasma_state, _) = PlasmaStateGenerator::generate(accounts);
+    let failed_test = FailedDepositTest {
+        accounts: plasma_state.get_accounts(),
+        fee_account_id: FEE_ACCOUNT_ID,
+        block_number: 1,
+        deposit_op,
+        witness,
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the epoch")
+        .as_nanos();
+    let path = env::temp_dir().join(format!("deposit_witness_{}.json", timestamp));
+    let saved = File::create(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|file| {
+            serde_json::to_writer_pretty(file, &failed_test).map_err(|e| e.to_string())
+        });
+    match saved {
+        Ok(()) => eprintln!(
+            "State of the failed deposit test is saved to {}, replay it with \
+             `{}={} cargo test -p circuit replay_deposit_witness -- --ignored`",
+            path.display(),
+            REPLAY_ENV_VAR,
+            path.display()
+        ),
+        Err(e) => eprintln!("Failed to save the state of the failed deposit test: {}", e),
+    }
+}
+
 /// Checks that deposit can be applied to a new account.
 /// Here we generate an empty PlasmaState (with no accounts), and make a deposit to a new account.
 #[test]
 #[ignore]
-fn test_deposit_in_empty_leaf() {
+fn test_deposit_in_empty_leaf() -> Result<(), WitnessError> {
     // Input data.
     let accounts = &[];
     let account = WitnessTestAccount::new_empty(1); // Will not be included into PlasmaState
@@ -27,22 +109,16 @@ fn test_deposit_in_empty_leaf() {
         account_id: account.id,
     };
 
-    generic_test_scenario::<DepositWitness<Bn256>, _>(
-        accounts,
-        deposit_op,
-        (),
-        |plasma_state, op| {
-            plasma_state.apply_deposit_op(op);
-            vec![]
-        },
-    );
+    deposit_test_scenario(accounts, deposit_op)?;
+
+    Ok(())
 }
 
 /// Checks that deposit can be applied to an existing account.
 /// Here we generate a PlasmaState with one account, and make a deposit to this account.
 #[test]
 #[ignore]
-fn test_deposit_existing_account() {
+fn test_deposit_existing_account() -> Result<(), WitnessError> {
     // Data for building a test vector: tuples of (token_id, amount).
     let test_vector = vec![
         (0, 1),             // Small deposit in ETH.
@@ -65,16 +141,10 @@ fn test_deposit_existing_account() {
             account_id: account.id,
         };
 
-        generic_test_scenario::<DepositWitness<Bn256>, _>(
-            &accounts,
-            deposit_op,
-            (),
-            |plasma_state, op| {
-                plasma_state.apply_deposit_op(op);
-                vec![]
-            },
-        );
+        deposit_test_scenario(&accounts, deposit_op)?;
     }
+
+    Ok(())
 }
 
 /// Checks that executing a deposit operation with incorrect
@@ -109,5 +179,91 @@ fn test_incorrect_deposit_address() {
             plasma_state.apply_deposit_op(op);
             vec![]
         },
+    )
+    .expect("failed to collect fees");
+}
+
+/// Checks that the deposit witness is the same after it's saved and loaded.
+#[test]
+fn test_deposit_witness_save_load() {
+    let accounts = vec![WitnessTestAccount::new_empty(1)];
+    let account = &accounts[0];
+    let deposit_op = DepositOp {
+        priority_op: Deposit {
+            from: account.account.address,
+            token: 0,
+            amount: BigUint::from(1u32),
+            to: account.account.address,
+        },
+        account_id: account.id,
+    };
+
+    let (_, mut circuit_account_tree) = PlasmaStateGenerator::generate(&accounts);
+    let witness = DepositWitness::<Bn256>::apply_tx(&mut circuit_account_tree, &deposit_op);
+
+    let mut saved = Vec::new();
+    witness
+        .save(&mut saved)
+        .expect("failed to save the witness");
+    let loaded =
+        DepositWitness::<Bn256>::load(saved.as_slice()).expect("failed to load the witness");
+
+    assert_eq!(loaded.before_root, witness.before_root);
+    assert_eq!(loaded.after_root, witness.after_root);
+    assert_eq!(loaded.get_pubdata(), witness.get_pubdata());
+    assert_eq!(
+        serde_json::to_value(&loaded).unwrap(),
+        serde_json::to_value(&witness).unwrap()
     );
 }
+
+/// Replays the state saved by a failed deposit test, its path is taken from
+/// the `DEPOSIT_WITNESS_REPLAY` environment variable.
+/// The account tree and the witness accumulator are restored, and the circuit
+/// is built from the saved witness and checked for unsatisfied constraints.
+#[test]
+#[ignore]
+fn replay_deposit_witness() -> Result<(), WitnessError> {
+    let path = env::var(REPLAY_ENV_VAR).unwrap_or_else(|_| {
+        panic!(
+            "{} must be set to the path of the failed deposit test to replay",
+            REPLAY_ENV_VAR
+        )
+    });
+    let file = File::open(&path).expect("failed to open the saved deposit test");
+    let failed_test: FailedDepositTest<DepositWitness<Bn256>> =
+        serde_json::from_reader(file).expect("failed to load the saved deposit test");
+    let witness = failed_test.witness;
+
+    let mut circuit_account_tree = CircuitAccountTree::new(account_tree_depth());
+    for (id, account) in failed_test.accounts {
+        circuit_account_tree.insert(id, CircuitAccount::from(account));
+    }
+    assert_eq!(
+        Some(circuit_account_tree.root_hash()),
+        witness.before_root,
+        "account tree root mismatches the witness"
+    );
+
+    let mut witness_accum = WitnessBuilder::new(
+        &mut circuit_account_tree,
+        failed_test.fee_account_id,
+        failed_test.block_number,
+    );
+    // Fees are collected from the account tree after the deposit.
+    let applied_witness =
+        DepositWitness::<Bn256>::apply_tx(&mut witness_accum.account_tree, &failed_test.deposit_op);
+    assert_eq!(
+        applied_witness.after_root, witness.after_root,
+        "account tree root after the deposit mismatches the witness"
+    );
+
+    witness_accum
+        .add_operation_with_pubdata(witness.calculate_operations(()), witness.get_pubdata());
+    witness_accum.try_collect_fees(&[])?;
+    witness_accum.calculate_pubdata_commitment();
+
+    check_circuit(witness_accum.into_circuit_instance());
+
+    Ok(())
+}
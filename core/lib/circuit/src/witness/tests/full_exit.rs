@@ -6,7 +6,9 @@ use models::node::{operations::FullExitOp, FullExit};
 // Local deps
 use crate::witness::{
     full_exit::FullExitWitness,
-    tests::test_utils::{generic_test_scenario, incorrect_op_test_scenario, WitnessTestAccount},
+    tests::test_utils::{
+        generic_test_scenario, incorrect_op_test_scenario, WitnessError, WitnessTestAccount,
+    },
 };
 
 /// Checks that `FullExit` can be applied to an existing account.
@@ -14,7 +16,7 @@ use crate::witness::{
 /// apply a `FullExit` to this account.
 #[test]
 #[ignore]
-fn test_full_exit_success() {
+fn test_full_exit_success() -> Result<(), WitnessError> {
     // Input data.
     let accounts = vec![WitnessTestAccount::new(1, 10)];
     let account = &accounts[0];
@@ -36,12 +38,14 @@ fn test_full_exit_success() {
             plasma_state.apply_full_exit_op(&op.0);
             vec![]
         },
-    );
+    )?;
+
+    Ok(())
 }
 
 #[test]
 #[ignore]
-fn test_full_exit_failure_no_account_in_tree() {
+fn test_full_exit_failure_no_account_in_tree() -> Result<(), WitnessError> {
     // Input data.
     let accounts = &[];
     let account = WitnessTestAccount::new_empty(1); // Will not be included into PlasmaState
@@ -63,12 +67,14 @@ fn test_full_exit_failure_no_account_in_tree() {
             plasma_state.apply_full_exit_op(&op.0);
             vec![]
         },
-    );
+    )?;
+
+    Ok(())
 }
 
 #[test]
 #[ignore]
-fn test_full_exit_initialted_from_wrong_account_owner() {
+fn test_full_exit_initialted_from_wrong_account_owner() -> Result<(), WitnessError> {
     // Input data.
     let accounts = vec![WitnessTestAccount::new(1, 10)];
     let invalid_account = WitnessTestAccount::new(2, 10);
@@ -93,14 +99,16 @@ fn test_full_exit_initialted_from_wrong_account_owner() {
             // this operation should change nothing
             vec![]
         },
-    );
+    )?;
+
+    Ok(())
 }
 
 /// Checks that executing a withdraw operation with incorrect
 /// withdraw amount results in an error.
 #[test]
 #[ignore]
-fn test_incorrect_full_exit_withdraw_amount() {
+fn test_incorrect_full_exit_withdraw_amount() -> Result<(), WitnessError> {
     // Test vector of (initial_balance, withdraw_amount, success).
     // Transactions are expected to fail with any value of provided `success` flag.
     let test_vector = vec![
@@ -133,6 +141,8 @@ fn test_incorrect_full_exit_withdraw_amount() {
             (),
             ERR_MSG,
             || vec![],
-        );
+        )?;
     }
+
+    Ok(())
 }
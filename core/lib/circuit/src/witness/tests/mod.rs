@@ -22,7 +22,7 @@ use crate::{
             check_circuit, check_circuit_non_panicking, PlasmaStateGenerator, WitnessTestAccount,
             FEE_ACCOUNT_ID,
         },
-        utils::{SigDataInput, WitnessBuilder},
+        utils::{SigDataInput, WitnessBuilder, WitnessError},
         DepositWitness, FullExitWitness, TransferToNewWitness, TransferWitness, WithdrawWitness,
         Witness,
     },
@@ -46,7 +46,7 @@ mod withdraw;
 ///
 /// Returns the resulting `WitnessBuilder` and the hash obtained
 /// from `PlasmaState` for further correctness checks.
-fn apply_many_ops() -> FranklinCircuit<'static, Bn256> {
+fn apply_many_ops() -> Result<FranklinCircuit<'static, Bn256>, WitnessError> {
     const ETH_TOKEN: u16 = 0;
     const NNM_TOKEN: u16 = 2;
 
@@ -217,7 +217,7 @@ fn apply_many_ops() -> FranklinCircuit<'static, Bn256> {
 
     // Collect fees.
     plasma_state.collect_fee(&fees, FEE_ACCOUNT_ID);
-    witness_accum.collect_fees(&fees);
+    witness_accum.try_collect_fees(&fees)?;
     witness_accum.calculate_pubdata_commitment();
 
     // Check that root hashes match
@@ -229,7 +229,7 @@ fn apply_many_ops() -> FranklinCircuit<'static, Bn256> {
         "root hash in state keeper and witness generation code mismatch"
     );
 
-    witness_accum.into_circuit_instance()
+    Ok(witness_accum.into_circuit_instance())
 }
 
 /// Composite test combines all the witness types applied together within one block:
@@ -243,21 +243,23 @@ fn apply_many_ops() -> FranklinCircuit<'static, Bn256> {
 /// All the actions are performed within one block.
 #[test]
 #[ignore]
-fn composite_test() {
+fn composite_test() -> Result<(), WitnessError> {
     // Perform some operations
-    let circuit = apply_many_ops();
+    let circuit = apply_many_ops()?;
 
     // Verify that there are no unsatisfied constraints
     check_circuit(circuit);
+
+    Ok(())
 }
 
 /// Checks that corrupted list of operations in block leads to predictable errors.
 /// Check for chunk in the end of the operations list.
 #[test]
 #[ignore]
-fn corrupted_last_operation() {
+fn corrupted_last_operation() -> Result<(), WitnessError> {
     // Perform some operations
-    let mut circuit = apply_many_ops();
+    let mut circuit = apply_many_ops()?;
 
     // Try to cut off an operation at end.
     circuit.operations.pop();
@@ -277,15 +279,17 @@ fn corrupted_last_operation() {
         error,
         expected_msg
     );
+
+    Ok(())
 }
 
 /// Checks that corrupted list of operations in block leads to predictable errors.
 /// Check for chunk in the beginning of the operations list.
 #[test]
 #[ignore]
-fn corrupted_first_operation() {
+fn corrupted_first_operation() -> Result<(), WitnessError> {
     // Perform some operations
-    let mut circuit = apply_many_ops();
+    let mut circuit = apply_many_ops()?;
 
     // Now try to cut off an operation at the beginning.
     circuit.operations.remove(0);
@@ -303,15 +307,17 @@ fn corrupted_first_operation() {
         error,
         expected_msg
     );
+
+    Ok(())
 }
 
 /// Checks that corrupted list of operations in block leads to predictable errors.
 /// Check for chunk in the middle of the operations list.
 #[test]
 #[ignore]
-fn corrupted_intermediate_operation() {
+fn corrupted_intermediate_operation() -> Result<(), WitnessError> {
     // Perform some operations
-    let mut circuit = apply_many_ops();
+    let mut circuit = apply_many_ops()?;
 
     // Now replace the operation in the middle with incorrect operation.
     let corrupted_op_chunk = circuit.operations.len() / 2;
@@ -333,15 +339,17 @@ fn corrupted_intermediate_operation() {
         error,
         expected_msg
     );
+
+    Ok(())
 }
 
 /// Checks that corrupted validator merkle proof in block leads to predictable errors.
 /// Check for chunk in the end of the operations list.
 #[test]
 #[ignore]
-fn corrupted_validator_audit_path() {
+fn corrupted_validator_audit_path() -> Result<(), WitnessError> {
     // Perform some operations
-    let mut circuit = apply_many_ops();
+    let mut circuit = apply_many_ops()?;
 
     // Corrupt merkle proof.
     circuit.validator_audit_path[0] = Some(Default::default());
@@ -359,4 +367,6 @@ fn corrupted_validator_audit_path() {
         error,
         expected_msg
     );
+
+    Ok(())
 }
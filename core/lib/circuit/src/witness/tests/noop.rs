@@ -26,7 +26,10 @@ use crate::{
     witness::{
         noop::noop_operation,
         tests::test_utils::{check_circuit, check_circuit_non_panicking},
-        utils::{apply_fee, get_audits, get_used_subtree_root_hash, public_data_commitment},
+        utils::{
+            get_audits, get_used_subtree_root_hash, public_data_commitment, try_apply_fee,
+            WitnessError,
+        },
         WitnessBuilder,
     },
 };
@@ -118,18 +121,20 @@ fn insert_sender(
 /// After that, we check that circuit doesn't contain any unsatisfied constraints.
 #[test]
 #[ignore]
-fn test_noop() {
+fn test_noop() -> Result<(), WitnessError> {
     let mut circuit_account_tree = CircuitAccountTree::new(account_tree_depth());
     circuit_account_tree.insert(0, CircuitAccount::default());
 
     let mut witness_accum = WitnessBuilder::new(&mut circuit_account_tree, 0, 1);
     witness_accum.extend_pubdata_with_noops(1);
-    witness_accum.collect_fees(&[]);
+    witness_accum.try_collect_fees(&[])?;
     witness_accum.calculate_pubdata_commitment();
 
     let circuit_instance = witness_accum.into_circuit_instance();
     // Check that there are no unsatisfied constraints.
     check_circuit(circuit_instance);
+
+    Ok(())
 }
 
 /// Test for the incorrect values being fed to the circuit via the provided
@@ -147,7 +152,7 @@ fn test_noop() {
 /// circuit initialization.
 #[test]
 #[ignore]
-fn incorrect_circuit_pubdata() {
+fn incorrect_circuit_pubdata() -> Result<(), WitnessError> {
     // ----------
     // Test setup
     // ----------
@@ -174,7 +179,7 @@ fn incorrect_circuit_pubdata() {
 
     // Perform the `noop` operation and collect the data required for circuit instance creation.
     let operation = noop_operation(&tree, validator_address_number);
-    let (_, validator_account_witness) = apply_fee(&mut tree, validator_address_number, 0, 0);
+    let (_, validator_account_witness) = try_apply_fee(&mut tree, validator_address_number, 0, 0)?;
     let (validator_audit_path, _) = get_audits(&tree, validator_address_number, 0);
 
     let correct_hash = tree.root_hash();
@@ -330,4 +335,39 @@ fn incorrect_circuit_pubdata() {
         error,
         expected_msg
     );
+
+    Ok(())
+}
+
+/// Checks that the fee is not applied to the tree if the validator account is missing,
+/// the token is not supported or the balance overflows.
+#[test]
+fn apply_fee_errors() -> Result<(), WitnessError> {
+    let mut tree = CircuitAccountTree::new(account_tree_depth());
+    tree.insert(0, CircuitAccount::default());
+    let root_hash = tree.root_hash();
+
+    assert_eq!(
+        try_apply_fee(&mut tree, 1, 0, 0).err(),
+        Some(WitnessError::AccountNotFound(1))
+    );
+    let unknown_token = params::total_tokens() as u32;
+    assert_eq!(
+        try_apply_fee(&mut tree, 0, unknown_token, 0).err(),
+        Some(WitnessError::TokenNotFound(unknown_token))
+    );
+    assert_eq!(tree.root_hash(), root_hash);
+
+    let (root_after_fee, _) = try_apply_fee(&mut tree, 0, 0, u128::max_value())?;
+    assert_ne!(root_after_fee, root_hash);
+    assert_eq!(
+        try_apply_fee(&mut tree, 0, 0, 1).err(),
+        Some(WitnessError::BalanceOverflow {
+            account: 0,
+            token: 0
+        })
+    );
+    assert_eq!(tree.root_hash(), root_after_fee);
+
+    Ok(())
 }
@@ -1,3 +1,5 @@
+// Built-in deps
+use std::panic::{self, AssertUnwindSafe};
 // External deps
 use crypto_exports::franklin_crypto::{
     bellman::{pairing::ff::PrimeField, Circuit},
@@ -15,7 +17,7 @@ use testkit::zksync_account::ZksyncAccount;
 use crate::{circuit::FranklinCircuit, witness::Witness};
 
 // Public re-exports
-pub use crate::witness::utils::WitnessBuilder;
+pub use crate::witness::utils::{WitnessBuilder, WitnessError};
 
 pub const FEE_ACCOUNT_ID: u32 = 0;
 
@@ -124,14 +126,35 @@ impl WitnessTestAccount {
 /// - Applies the provided operation on circuit
 /// - Verifies that root hashes in plasma and circuit match
 /// - Verifies that there are no unsatisfied constraints in the circuit.
+///
+/// Returns an error if the fees can't be collected in the circuit.
 pub fn generic_test_scenario<W, F>(
     accounts: &[WitnessTestAccount],
     op: W::OperationType,
     input: W::CalculateOpsInput,
     apply_op_on_plasma: F,
-) where
+) -> Result<(), WitnessError>
+where
     W: Witness,
     F: FnOnce(&mut PlasmaState, &W::OperationType) -> Vec<CollectedFee>,
+{
+    test_scenario_with_failure_hook::<W, F, _>(accounts, op, input, apply_op_on_plasma, |_| {})
+}
+
+/// Does the same operations as the `generic_test_scenario`, but if the checks of
+/// the witness fail, passes it to `on_failure` before propagating the panic or the error,
+/// e.g. to save the witness for the replay.
+pub fn test_scenario_with_failure_hook<W, F, H>(
+    accounts: &[WitnessTestAccount],
+    op: W::OperationType,
+    input: W::CalculateOpsInput,
+    apply_op_on_plasma: F,
+    on_failure: H,
+) -> Result<(), WitnessError>
+where
+    W: Witness,
+    F: FnOnce(&mut PlasmaState, &W::OperationType) -> Vec<CollectedFee>,
+    H: FnOnce(&W),
 {
     // Initialize Plasma and WitnessBuilder.
     let (mut plasma_state, mut circuit_account_tree) = PlasmaStateGenerator::generate(&accounts);
@@ -143,25 +166,41 @@ pub fn generic_test_scenario<W, F>(
 
     // Apply op on circuit
     let witness = W::apply_tx(&mut witness_accum.account_tree, &op);
-    let circuit_operations = witness.calculate_operations(input);
-    let pub_data_from_witness = witness.get_pubdata();
+    let checks = panic::catch_unwind(AssertUnwindSafe(|| -> Result<(), WitnessError> {
+        let circuit_operations = witness.calculate_operations(input);
+        let pub_data_from_witness = witness.get_pubdata();
+
+        // Prepare circuit
+        witness_accum.add_operation_with_pubdata(circuit_operations, pub_data_from_witness);
+        witness_accum.try_collect_fees(&fees)?;
+        witness_accum.calculate_pubdata_commitment();
+
+        // Check that root hashes match
+        assert_eq!(
+            plasma_state.root_hash(),
+            witness_accum
+                .root_after_fees
+                .expect("witness accum after root hash empty"),
+            "root hash in state keeper and witness generation code mismatch"
+        );
+
+        // Verify that there are no unsatisfied constraints
+        check_circuit(witness_accum.into_circuit_instance());
 
-    // Prepare circuit
-    witness_accum.add_operation_with_pubdata(circuit_operations, pub_data_from_witness);
-    witness_accum.collect_fees(&fees);
-    witness_accum.calculate_pubdata_commitment();
+        Ok(())
+    }));
 
-    // Check that root hashes match
-    assert_eq!(
-        plasma_state.root_hash(),
-        witness_accum
-            .root_after_fees
-            .expect("witness accum after root hash empty"),
-        "root hash in state keeper and witness generation code mismatch"
-    );
-
-    // Verify that there are no unsatisfied constraints
-    check_circuit(witness_accum.into_circuit_instance());
+    match checks {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => {
+            on_failure(&witness);
+            Err(e)
+        }
+        Err(panic) => {
+            on_failure(&witness);
+            panic::resume_unwind(panic);
+        }
+    }
 }
 
 /// Does the same operations as the `generic_test_scenario`, but assumes
@@ -173,7 +212,8 @@ pub fn corrupted_input_test_scenario<W, F>(
     input: W::CalculateOpsInput,
     expected_msg: &str,
     apply_op_on_plasma: F,
-) where
+) -> Result<(), WitnessError>
+where
     W: Witness,
     W::CalculateOpsInput: Clone + std::fmt::Debug,
     F: FnOnce(&mut PlasmaState, &W::OperationType) -> Vec<CollectedFee>,
@@ -193,7 +233,7 @@ pub fn corrupted_input_test_scenario<W, F>(
 
     // Prepare circuit
     witness_accum.add_operation_with_pubdata(circuit_operations, pub_data_from_witness);
-    witness_accum.collect_fees(&fees);
+    witness_accum.try_collect_fees(&fees)?;
     witness_accum.calculate_pubdata_commitment();
 
     let result = check_circuit_non_panicking(witness_accum.into_circuit_instance());
@@ -216,6 +256,8 @@ pub fn corrupted_input_test_scenario<W, F>(
             );
         }
     }
+
+    Ok(())
 }
 
 /// Performs the operation on the circuit, but not on the plasma,
@@ -227,7 +269,8 @@ pub fn incorrect_op_test_scenario<W, F>(
     input: W::CalculateOpsInput,
     expected_msg: &str,
     collect_fees: F,
-) where
+) -> Result<(), WitnessError>
+where
     W: Witness,
     W::CalculateOpsInput: Clone + std::fmt::Debug,
     F: FnOnce() -> Vec<CollectedFee>,
@@ -246,7 +289,7 @@ pub fn incorrect_op_test_scenario<W, F>(
 
     // Prepare circuit
     witness_accum.add_operation_with_pubdata(circuit_operations, pub_data_from_witness);
-    witness_accum.collect_fees(&fees);
+    witness_accum.try_collect_fees(&fees)?;
     witness_accum.calculate_pubdata_commitment();
 
     let result = check_circuit_non_panicking(witness_accum.into_circuit_instance());
@@ -269,4 +312,6 @@ pub fn incorrect_op_test_scenario<W, F>(
             );
         }
     }
+
+    Ok(())
 }
@@ -8,7 +8,7 @@ use plasma::state::CollectedFee;
 use crate::witness::{
     tests::test_utils::{
         corrupted_input_test_scenario, generic_test_scenario, incorrect_op_test_scenario,
-        WitnessTestAccount,
+        WitnessError, WitnessTestAccount,
     },
     transfer_to_new::TransferToNewWitness,
     utils::SigDataInput,
@@ -18,7 +18,7 @@ use crate::witness::{
 /// Here we create one account and perform a transfer to a new account.
 #[test]
 #[ignore]
-fn test_transfer_to_new_success() {
+fn test_transfer_to_new_success() -> Result<(), WitnessError> {
     // Test vector of (initial_balance, transfer_amount, fee_amount).
     let test_vector = vec![
         (10u64, 7u64, 3u64),       // Basic transfer
@@ -64,14 +64,16 @@ fn test_transfer_to_new_success() {
                     .expect("transfer should be success");
                 vec![fee]
             },
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 /// Checks that corrupted signature data leads to unsatisfied constraints in circuit.
 #[test]
 #[ignore]
-fn corrupted_ops_input() {
+fn corrupted_ops_input() -> Result<(), WitnessError> {
     // Incorrect signature data will lead to `op_valid` constraint failure.
     // See `circuit.rs` for details.
     const EXPECTED_PANIC_MSG: &str = "op_valid is true";
@@ -116,15 +118,17 @@ fn corrupted_ops_input() {
                     .expect("transfer should be success");
                 vec![fee]
             },
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 /// Checks that executing a transfer operation with incorrect
 /// data (account `from` ID) results in an error.
 #[test]
 #[ignore]
-fn test_incorrect_transfer_account_from() {
+fn test_incorrect_transfer_account_from() -> Result<(), WitnessError> {
     const TOKEN_ID: u16 = 0;
     const INITIAL_BALANCE: u64 = 10;
     const TOKEN_AMOUNT: u64 = 7;
@@ -171,7 +175,9 @@ fn test_incorrect_transfer_account_from() {
                 amount: FEE_AMOUNT.into(),
             }]
         },
-    );
+    )?;
+
+    Ok(())
 }
 
 /// Checks that executing a transfer operation with incorrect
@@ -226,14 +232,15 @@ fn test_incorrect_transfer_account_to() {
                 amount: FEE_AMOUNT.into(),
             }]
         },
-    );
+    )
+    .expect("failed to collect fees");
 }
 
 /// Checks that executing a transfer operation with incorrect
 /// data (insufficient funds) results in an error.
 #[test]
 #[ignore]
-fn test_incorrect_transfer_amount() {
+fn test_incorrect_transfer_amount() -> Result<(), WitnessError> {
     const TOKEN_ID: u16 = 0;
     // Balance check should fail.
     // "balance-fee bits" is message for subtraction check in circuit.
@@ -284,15 +291,17 @@ fn test_incorrect_transfer_amount() {
                     amount: fee_amount.into(),
                 }]
             },
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 /// Checks that even if there are two accounts with the same keys in the state,
 /// one account cannot authorize the transfer from its duplicate.
 #[test]
 #[ignore]
-fn test_transfer_replay() {
+fn test_transfer_replay() -> Result<(), WitnessError> {
     const TOKEN_ID: u16 = 0;
     const INITIAL_BALANCE: u64 = 10;
     const TOKEN_AMOUNT: u64 = 7;
@@ -348,5 +357,7 @@ fn test_transfer_replay() {
                 amount: FEE_AMOUNT.into(),
             }]
         },
-    );
+    )?;
+
+    Ok(())
 }
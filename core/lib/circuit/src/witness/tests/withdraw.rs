@@ -8,7 +8,7 @@ use plasma::state::CollectedFee;
 use crate::witness::{
     tests::test_utils::{
         corrupted_input_test_scenario, generic_test_scenario, incorrect_op_test_scenario,
-        WitnessTestAccount,
+        WitnessError, WitnessTestAccount,
     },
     utils::SigDataInput,
     withdraw::WithdrawWitness,
@@ -16,7 +16,7 @@ use crate::witness::{
 
 #[test]
 #[ignore]
-fn test_withdraw() {
+fn test_withdraw() -> Result<(), WitnessError> {
     // Test vector of (initial_balance, transfer_amount, fee_amount).
     let test_vector = vec![
         (10u64, 7u64, 3u64),       // Basic transfer
@@ -60,14 +60,16 @@ fn test_withdraw() {
                     .expect("transfer should be success");
                 vec![fee]
             },
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 /// Checks that corrupted signature data leads to unsatisfied constraints in circuit.
 #[test]
 #[ignore]
-fn corrupted_ops_input() {
+fn corrupted_ops_input() -> Result<(), WitnessError> {
     // Incorrect signature data will lead to `op_valid` constraint failure.
     // See `circuit.rs` for details.
     const EXPECTED_PANIC_MSG: &str = "op_valid is true";
@@ -109,15 +111,17 @@ fn corrupted_ops_input() {
                     .expect("transfer should be success");
                 vec![fee]
             },
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 /// Checks that executing a withdraw operation with incorrect
 /// data (account `from` ID) results in an error.
 #[test]
 #[ignore]
-fn test_incorrect_withdraw_account_from() {
+fn test_incorrect_withdraw_account_from() -> Result<(), WitnessError> {
     const TOKEN_ID: u16 = 0;
     const INITIAL_BALANCE: u64 = 10;
     const TOKEN_AMOUNT: u64 = 7;
@@ -161,14 +165,16 @@ fn test_incorrect_withdraw_account_from() {
                 amount: FEE_AMOUNT.into(),
             }]
         },
-    );
+    )?;
+
+    Ok(())
 }
 
 /// Checks that executing a withdraw operation with incorrect
 /// data (insufficient funds) results in an error.
 #[test]
 #[ignore]
-fn test_incorrect_withdraw_amount() {
+fn test_incorrect_withdraw_amount() -> Result<(), WitnessError> {
     const TOKEN_ID: u16 = 0;
     // Balance check should fail.
     // "balance-fee bits" is message for subtraction check in circuit.
@@ -216,15 +222,17 @@ fn test_incorrect_withdraw_amount() {
                     amount: fee_amount.into(),
                 }]
             },
-        );
+        )?;
     }
+
+    Ok(())
 }
 
 /// Checks that even if there are two accounts with the same keys in the state,
 /// one account cannot authorize the withdraw from its duplicate.
 #[test]
 #[ignore]
-fn test_withdraw_replay() {
+fn test_withdraw_replay() -> Result<(), WitnessError> {
     const TOKEN_ID: u16 = 0;
     const INITIAL_BALANCE: u64 = 10;
     const TOKEN_AMOUNT: u64 = 7;
@@ -278,5 +286,7 @@ fn test_withdraw_replay() {
                 amount: FEE_AMOUNT.into(),
             }]
         },
-    );
+    )?;
+
+    Ok(())
 }
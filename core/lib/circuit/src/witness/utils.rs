@@ -1,3 +1,5 @@
+// Built-in deps
+use std::fmt;
 // External deps
 use crypto::{digest::Digest, sha2::Sha256};
 use crypto_exports::franklin_crypto::{
@@ -25,7 +27,8 @@ use models::{
         AccountId, BlockNumber, Engine,
     },
     params::{
-        total_tokens, used_account_subtree_depth, CHUNK_BIT_WIDTH, MAX_CIRCUIT_MSG_HASH_BITS,
+        total_tokens, used_account_subtree_depth, BALANCE_BIT_WIDTH, CHUNK_BIT_WIDTH,
+        MAX_CIRCUIT_MSG_HASH_BITS,
     },
     primitives::GetBits,
 };
@@ -104,13 +107,23 @@ impl<'a> WitnessBuilder<'a> {
     }
 
     /// After operations are added, collect fees.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fees can't be applied, see `try_collect_fees`.
     pub fn collect_fees(&mut self, fees: &[CollectedFee]) {
+        self.try_collect_fees(fees).expect("failed to collect fees");
+    }
+
+    /// Same as `collect_fees`, but returns an error if the fee account is not in the tree
+    /// or its balance overflows.
+    pub fn try_collect_fees(&mut self, fees: &[CollectedFee]) -> Result<(), WitnessError> {
         self.root_before_fees = Some(self.account_tree.root_hash());
 
         let fee_circuit_account = self
             .account_tree
             .get(self.fee_account_id)
-            .expect("fee account is not in the tree");
+            .ok_or(WitnessError::AccountNotFound(self.fee_account_id))?;
         let mut fee_circuit_account_balances = Vec::with_capacity(total_tokens());
         for i in 0u32..(total_tokens() as u32) {
             let balance_value = fee_circuit_account
@@ -123,20 +136,22 @@ impl<'a> WitnessBuilder<'a> {
         self.fee_account_balances = Some(fee_circuit_account_balances);
 
         let (mut root_after_fee, mut fee_account_witness) =
-            crate::witness::utils::apply_fee(&mut self.account_tree, self.fee_account_id, 0, 0);
+            try_apply_fee(&mut self.account_tree, self.fee_account_id, 0, 0)?;
         for CollectedFee { token, amount } in fees {
-            let (root, acc_witness) = crate::witness::utils::apply_fee(
-                &mut self.account_tree,
-                self.fee_account_id,
-                u32::from(*token),
-                amount.to_u128().unwrap(),
-            );
+            let token = u32::from(*token);
+            let amount = amount.to_u128().ok_or(WitnessError::BalanceOverflow {
+                account: self.fee_account_id,
+                token,
+            })?;
+            let (root, acc_witness) =
+                try_apply_fee(&mut self.account_tree, self.fee_account_id, token, amount)?;
             root_after_fee = root;
             fee_account_witness = acc_witness;
         }
 
         self.root_after_fees = Some(root_after_fee);
         self.fee_account_witness = Some(fee_account_witness);
+        Ok(())
     }
 
     /// After fees collected creates public data commitment
@@ -436,26 +451,89 @@ pub fn apply_leaf_operation<Fa: Fn(&mut CircuitAccount<Bn256>), Fb: Fn(&mut Bala
     )
 }
 
+/// Error of the witness generation caused by the inconsistent input data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessError {
+    /// Account with the given ID is not in the tree.
+    AccountNotFound(u32),
+    /// Token ID exceeds the number of the supported tokens.
+    TokenNotFound(u32),
+    /// Balance of the account doesn't fit into `BALANCE_BIT_WIDTH` bits.
+    BalanceOverflow { account: u32, token: u32 },
+}
+
+impl fmt::Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::AccountNotFound(account) => {
+                write!(f, "account {} is not in the tree", account)
+            }
+            WitnessError::TokenNotFound(token) => write!(f, "token {} is not supported", token),
+            WitnessError::BalanceOverflow { account, token } => write!(
+                f,
+                "balance of the account {} in the token {} overflows",
+                account, token
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+/// Adds the fee to the balance of the validator account.
+/// Returns the root hash of the tree after the fee and the witness of the validator account
+/// before it.
+///
+/// # Panics
+///
+/// Panics if the fee can't be applied, see `try_apply_fee`.
 pub fn apply_fee(
     tree: &mut CircuitAccountTree,
     validator_address: u32,
     token: u32,
     fee: u128,
 ) -> (Fr, AccountWitness<Bn256>) {
-    let fee_fe = Fr::from_str(&fee.to_string()).unwrap();
+    try_apply_fee(tree, validator_address, token, fee).expect("failed to apply fee")
+}
+
+/// Same as `apply_fee`, but returns an error if the validator account is not in the tree,
+/// the token is not supported or the balance overflows. The tree is not changed on error.
+pub fn try_apply_fee(
+    tree: &mut CircuitAccountTree,
+    validator_address: u32,
+    token: u32,
+    fee: u128,
+) -> Result<(Fr, AccountWitness<Bn256>), WitnessError> {
+    if token as usize >= total_tokens() {
+        return Err(WitnessError::TokenNotFound(token));
+    }
+    let validator_leaf = tree
+        .get(validator_address)
+        .ok_or(WitnessError::AccountNotFound(validator_address))?;
+
+    let fee_fe = Fr::from_str(&fee.to_string()).expect("u128 fits into the field element");
+    let mut balance = validator_leaf
+        .subtree
+        .get(token)
+        .cloned()
+        .unwrap_or_default();
+    balance.value.add_assign(&fee_fe);
+    if balance.value.into_repr().num_bits() as usize > BALANCE_BIT_WIDTH {
+        return Err(WitnessError::BalanceOverflow {
+            account: validator_address,
+            token,
+        });
+    }
+
     let mut validator_leaf = tree
         .remove(validator_address)
-        .expect("validator_leaf is empty");
+        .expect("validator account is checked above");
     let validator_account_witness = AccountWitness::from_circuit_account(&validator_leaf);
-
-    let mut balance = validator_leaf.subtree.remove(token).unwrap_or_default();
-    balance.value.add_assign(&fee_fe);
     validator_leaf.subtree.insert(token, balance);
-
     tree.insert(validator_address, validator_leaf);
 
     let root_after_fee = tree.root_hash();
-    (root_after_fee, validator_account_witness)
+    Ok((root_after_fee, validator_account_witness))
 }
 
 pub fn fr_from_bytes(bytes: Vec<u8>) -> Fr {
@@ -13,6 +13,9 @@ use web3::types::{H160, H256, U256, U64};
 use web3::{Error, Transport, Web3};
 
 pub mod signer;
+mod web3_failover;
+
+pub use web3_failover::{Web3FailoverTransport, Web3UrlSelector};
 
 /// Gas limit value to be used in transaction if for some reason
 /// gas limit was not set for it.
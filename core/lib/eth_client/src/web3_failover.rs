@@ -0,0 +1,282 @@
+//! Failover between several Ethereum nodes.
+//!
+//! Requests are spread over the nodes round-robin. The node failing the configured amount
+//! of consecutive requests is quarantined and isn't used until the quarantine is over,
+//! so an outage of a single node doesn't stop the interaction with Ethereum.
+
+// Built-in deps
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+// External uses
+use futures::compat::{Compat, Future01CompatExt};
+use futures::future::BoxFuture;
+use web3::transports::{EventLoopHandle, Http};
+use web3::{rpc, Error, RequestId, Transport};
+// Workspace uses
+use models::config_options::ConfigurationOptions;
+
+/// Max amount of simultaneous requests to a single node, same as the default of `Http`.
+const MAX_PARALLEL_REQUESTS: usize = 64;
+
+#[derive(Debug)]
+struct NodeState {
+    url: String,
+    consecutive_failures: u32,
+    quarantined_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct SelectorState {
+    nodes: Vec<NodeState>,
+    next: usize,
+}
+
+/// Handle choosing the Ethereum node for the next request. Cloned handles share the state.
+#[derive(Debug, Clone)]
+pub struct Web3UrlSelector {
+    state: Arc<Mutex<SelectorState>>,
+    failures_threshold: u32,
+    quarantine: Duration,
+}
+
+impl Web3UrlSelector {
+    /// Creates the selector over `urls`. The node failing `failures_threshold` consecutive
+    /// requests is quarantined for `quarantine`.
+    pub fn new(urls: Vec<String>, failures_threshold: u32, quarantine: Duration) -> Self {
+        assert!(!urls.is_empty(), "at least one web3 url is required");
+        let nodes = urls
+            .into_iter()
+            .map(|url| NodeState {
+                url,
+                consecutive_failures: 0,
+                quarantined_until: None,
+            })
+            .collect();
+        Self {
+            state: Arc::new(Mutex::new(SelectorState { nodes, next: 0 })),
+            failures_threshold,
+            quarantine,
+        }
+    }
+
+    pub fn from_options(options: &ConfigurationOptions) -> Self {
+        Self::new(
+            options.web3_urls.clone(),
+            options.web3_url_failures_threshold,
+            options.web3_url_quarantine,
+        )
+    }
+
+    /// All the URLs of the selector, in the configured order.
+    pub fn urls(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.nodes.iter().map(|node| node.url.clone()).collect()
+    }
+
+    /// Picks the next node round-robin, skipping the quarantined ones. Nodes whose quarantine
+    /// is over are retried. If all the nodes are quarantined, the one released first is picked.
+    pub fn next_url(&self) -> String {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let count = state.nodes.len();
+        let start = state.next;
+        let available = (0..count)
+            .map(|offset| (start + offset) % count)
+            .find(|&idx| match state.nodes[idx].quarantined_until {
+                Some(until) => until <= now,
+                None => true,
+            });
+        let idx = available.unwrap_or_else(|| {
+            (0..count)
+                .min_by_key(|&idx| state.nodes[idx].quarantined_until)
+                .unwrap()
+        });
+        state.next = (idx + 1) % count;
+        state.nodes[idx].url.clone()
+    }
+
+    /// Records the successful request to the node, releasing it from the quarantine.
+    pub fn report_success(&self, url: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(node) = state.nodes.iter_mut().find(|node| node.url == url) {
+            if node.quarantined_until.is_some() {
+                log::info!("Web3 node {} is back, released from the quarantine", url);
+            }
+            node.consecutive_failures = 0;
+            node.quarantined_until = None;
+        }
+    }
+
+    /// Records the failed request to the node, quarantining it once it fails
+    /// the threshold amount of consecutive requests.
+    pub fn report_failure(&self, url: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(node) = state.nodes.iter_mut().find(|node| node.url == url) {
+            node.consecutive_failures += 1;
+            if node.consecutive_failures >= self.failures_threshold {
+                log::warn!(
+                    "Web3 node {} failed {} consecutive requests, quarantined for {:?}",
+                    url,
+                    node.consecutive_failures,
+                    self.quarantine
+                );
+                node.consecutive_failures = 0;
+                node.quarantined_until = Some(Instant::now() + self.quarantine);
+            }
+        }
+    }
+
+    /// URLs of the currently quarantined nodes.
+    pub fn quarantined(&self) -> Vec<String> {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        state
+            .nodes
+            .iter()
+            .filter(|node| node.quarantined_until.map_or(false, |until| until > now))
+            .map(|node| node.url.clone())
+            .collect()
+    }
+}
+
+/// Whether the error means the node is unavailable, rather than the node rejecting the request.
+fn is_node_failure(error: &Error) -> bool {
+    match error {
+        Error::Unreachable | Error::Transport(_) | Error::Io(_) | Error::InvalidResponse(_) => true,
+        _ => false,
+    }
+}
+
+/// Web3 transport sending the requests to the node chosen by the `Web3UrlSelector`.
+/// Request failed by the node is retried with the other nodes.
+#[derive(Debug, Clone)]
+pub struct Web3FailoverTransport {
+    selector: Web3UrlSelector,
+    transports: Arc<Vec<(String, Http)>>,
+}
+
+impl Web3FailoverTransport {
+    /// Creates the transport to the nodes of `selector`. Returns the handle of the event loop
+    /// serving the requests, which must be kept alive as long as the transport is used.
+    pub fn new(selector: Web3UrlSelector) -> Result<(EventLoopHandle, Self), Error> {
+        let urls = selector.urls();
+        let (event_loop, transports) = EventLoopHandle::spawn(move |handle| {
+            urls.into_iter()
+                .map(|url| {
+                    let transport = Http::with_event_loop(&url, handle, MAX_PARALLEL_REQUESTS)?;
+                    Ok((url, transport))
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        })?;
+        Ok((
+            event_loop,
+            Self {
+                selector,
+                transports: Arc::new(transports),
+            },
+        ))
+    }
+
+    /// Handle of the selector used by the transport.
+    pub fn selector(&self) -> &Web3UrlSelector {
+        &self.selector
+    }
+
+    fn transport(&self, url: &str) -> Http {
+        self.transports
+            .iter()
+            .find(|(transport_url, _)| transport_url == url)
+            .map(|(_, transport)| transport.clone())
+            .expect("selector returned unknown url")
+    }
+
+    async fn send_with_failover(
+        self,
+        id: RequestId,
+        request: rpc::Call,
+    ) -> Result<rpc::Value, Error> {
+        let mut last_error = Error::Unreachable;
+        for _ in 0..self.transports.len() {
+            let url = self.selector.next_url();
+            match self
+                .transport(&url)
+                .send(id, request.clone())
+                .compat()
+                .await
+            {
+                Err(e) if is_node_failure(&e) => {
+                    log::warn!("Request to web3 node {} failed: {}", url, e);
+                    self.selector.report_failure(&url);
+                    last_error = e;
+                }
+                res => {
+                    self.selector.report_success(&url);
+                    return res;
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+impl Transport for Web3FailoverTransport {
+    type Out = Compat<BoxFuture<'static, Result<rpc::Value, Error>>>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        // Request IDs only have to be unique, so any of the transports prepares the request.
+        self.transports[0].1.prepare(method, params)
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        Compat::new(Box::pin(self.clone().send_with_failover(id, request)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn selector(quarantine: Duration) -> Web3UrlSelector {
+        let urls = vec!["http://a".to_string(), "http://b".to_string()];
+        Web3UrlSelector::new(urls, 2, quarantine)
+    }
+
+    #[test]
+    fn urls_are_used_round_robin() {
+        let selector = selector(Duration::from_secs(60));
+        let urls: Vec<_> = (0..4).map(|_| selector.next_url()).collect();
+        assert_eq!(urls, vec!["http://a", "http://b", "http://a", "http://b"]);
+    }
+
+    #[test]
+    fn failing_url_is_quarantined_and_retried_later() {
+        let selector = selector(Duration::from_millis(100));
+
+        // Failure interrupted by the success doesn't count.
+        selector.report_failure("http://a");
+        selector.report_success("http://a");
+        selector.report_failure("http://a");
+        assert!(selector.quarantined().is_empty());
+
+        selector.report_failure("http://a");
+        assert_eq!(selector.quarantined(), vec!["http://a"]);
+        let urls: Vec<_> = (0..3).map(|_| selector.next_url()).collect();
+        assert_eq!(urls, vec!["http://b", "http://b", "http://b"]);
+
+        thread::sleep(Duration::from_millis(150));
+        assert!(selector.quarantined().is_empty());
+        let urls: Vec<_> = (0..2).map(|_| selector.next_url()).collect();
+        assert_eq!(urls, vec!["http://a", "http://b"]);
+    }
+
+    #[test]
+    fn url_released_first_is_used_if_all_are_quarantined() {
+        let selector = selector(Duration::from_secs(60));
+        for url in &["http://b", "http://b", "http://a", "http://a"] {
+            selector.report_failure(url);
+        }
+        assert_eq!(selector.quarantined().len(), 2);
+        assert_eq!(selector.next_url(), "http://b");
+    }
+}
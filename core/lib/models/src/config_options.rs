@@ -1,23 +1,59 @@
 // Built-in deps
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::env;
+use std::fs;
 use std::net::SocketAddr;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 // External uses
+use failure::Fail;
 use futures::{channel::mpsc, executor::block_on, SinkExt};
 use web3::types::{H160, H256};
 // Local uses
 use crate::params::block_chunk_sizes;
 use url::Url;
 
-/// If its placed inside thread::spawn closure it will notify channel when this thread panics.
-pub struct ThreadPanicNotify(pub mpsc::Sender<bool>);
+thread_local! {
+    /// Description of the panic the current thread is unwinding from, see `install_panic_hook`.
+    static PANIC_MESSAGE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Installs the panic hook remembering the description of the panic (its message and location),
+/// so `ThreadPanicNotify` can report it. Previously installed hook is still called.
+/// Should be called once at startup, before spawning any threads.
+pub fn install_panic_hook() {
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        PANIC_MESSAGE.with(|message| *message.borrow_mut() = Some(info.to_string()));
+        previous_hook(info);
+    }));
+}
+
+/// If its placed inside thread::spawn closure it will notify channel when this thread finishes.
+/// Panic is reported with the name of the thread and the description of the panic,
+/// while normal termination of the thread is reported as `None`.
+pub struct ThreadPanicNotify(pub mpsc::Sender<Option<String>>);
 
 impl Drop for ThreadPanicNotify {
     fn drop(&mut self) {
-        if std::thread::panicking() {
-            block_on(self.0.send(true)).unwrap();
-        }
+        let notification = if std::thread::panicking() {
+            let thread = std::thread::current();
+            let panic_message = PANIC_MESSAGE
+                .with(|message| message.borrow_mut().take())
+                .unwrap_or_else(|| "panicked, see the log for details".to_string());
+            Some(format!(
+                "thread '{}' {}",
+                thread.name().unwrap_or("<unnamed>"),
+                panic_message
+            ))
+        } else {
+            None
+        };
+        // Receiver is gone if the application is already shutting down.
+        let _ = block_on(self.0.send(notification));
     }
 }
 
@@ -53,6 +89,229 @@ where
         .unwrap_or_else(|e| panic!("Failed to parse environment variable {}: {:?}", name, e))
 }
 
+/// Similar to `parse_env`, but returns `None` if there is no environment variable with provided name set.
+/// Panics if the value cannot be parsed.
+pub fn parse_env_opt<F>(name: &str) -> Option<F>
+where
+    F: FromStr,
+    F::Err: std::fmt::Debug,
+{
+    env::var(name).ok().map(|_| parse_env(name))
+}
+
+/// Similar to `parse_env`, but returns the provided default value if there is no environment variable
+/// with provided name set. Panics if the value cannot be parsed.
+pub fn parse_env_or<F>(name: &str, default: F) -> F
+where
+    F: FromStr,
+    F::Err: std::fmt::Debug,
+{
+    parse_env_opt(name).unwrap_or(default)
+}
+
+/// Similar to `parse_env_with`, but returns the provided default value if there is no environment
+/// variable with provided name set.
+pub fn parse_env_with_or<T, F>(name: &str, f: F, default: T) -> T
+where
+    T: FromStr,
+    T::Err: std::fmt::Debug,
+    F: FnOnce(&str) -> &str,
+{
+    if env::var(name).is_ok() {
+        parse_env_with(name, f)
+    } else {
+        default
+    }
+}
+
+/// Certificates of the mutual TLS between the provers and the prover server.
+/// Certificate and key identify the side itself, CA certificate verifies the other side.
+/// Parsed from `PROVER_TLS_CERT`, `PROVER_TLS_KEY` and `PROVER_TLS_CA` by both sides.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    /// PEM certificate chain presented to the other side.
+    pub cert_pem: PathBuf,
+    /// PEM private key of the certificate, PKCS#8 or RSA.
+    pub key_pem: PathBuf,
+    /// PEM certificate of the CA the certificate of the other side must be signed by.
+    pub ca_pem: PathBuf,
+}
+
+impl TlsConfig {
+    /// Parses the TLS files from the environment variables, `None` if none of them is set.
+    /// Panics if only some of them are set.
+    pub fn from_env() -> Option<Self> {
+        let var = |name| env::var(name).ok();
+        Self::from_paths(
+            var("PROVER_TLS_CERT"),
+            var("PROVER_TLS_KEY"),
+            var("PROVER_TLS_CA"),
+        )
+        .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Combines the paths, which must be either all set or all unset. Empty paths are unset.
+    fn from_paths(
+        cert: Option<String>,
+        key: Option<String>,
+        ca: Option<String>,
+    ) -> Result<Option<Self>, String> {
+        let non_empty = |path: Option<String>| path.filter(|path| !path.is_empty());
+        match (non_empty(cert), non_empty(key), non_empty(ca)) {
+            (None, None, None) => Ok(None),
+            (Some(cert), Some(key), Some(ca)) => Ok(Some(Self {
+                cert_pem: cert.into(),
+                key_pem: key.into(),
+                ca_pem: ca.into(),
+            })),
+            _ => Err(
+                "PROVER_TLS_CERT, PROVER_TLS_KEY and PROVER_TLS_CA must be either all set or all unset"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// Error of loading the configuration options from a file.
+#[derive(Debug, Fail)]
+pub enum ConfigError {
+    #[fail(display = "failed to read config file: {}", _0)]
+    Io(String),
+    #[fail(display = "failed to parse config file: {}", _0)]
+    Parse(String),
+    #[fail(display = "config key {} is missing", _0)]
+    MissingKey(String),
+    #[fail(
+        display = "failed to parse config key {} with value {:?}: {}",
+        key, value, reason
+    )]
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+    #[fail(display = "invalid configuration options: {:?}", _0)]
+    Invalid(Vec<String>),
+}
+
+/// Values of the TOML configuration file.
+/// Keys are the names of the corresponding environment variables in snake_case.
+struct TomlValues(toml::value::Table);
+
+impl TomlValues {
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+        let table = contents
+            .parse::<toml::Value>()
+            .map_err(|e| ConfigError::Parse(e.to_string()))?;
+        match table {
+            toml::Value::Table(table) => Ok(Self(table)),
+            _ => Err(ConfigError::Parse(
+                "config file must be a TOML table".to_string(),
+            )),
+        }
+    }
+
+    /// Converts value to the string in the same format as the environment variable.
+    /// Arrays are represented as comma-separated lists.
+    fn value_to_string(value: &toml::Value) -> String {
+        match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Array(values) => values
+                .iter()
+                .map(Self::value_to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+            value => value.to_string(),
+        }
+    }
+
+    /// Similar to `env::var`, takes the environment variable name.
+    fn get_opt(&self, name: &str) -> Option<String> {
+        self.0.get(&name.to_lowercase()).map(Self::value_to_string)
+    }
+
+    /// Similar to `get_env`, but returns an error rather than panicking.
+    fn get(&self, name: &str) -> Result<String, ConfigError> {
+        self.get_opt(name)
+            .ok_or_else(|| ConfigError::MissingKey(name.to_lowercase()))
+    }
+
+    /// Similar to `parse_env`, but returns an error rather than panicking.
+    fn parse<T>(&self, name: &str) -> Result<T, ConfigError>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Debug,
+    {
+        self.parse_with(name, |s| s)
+    }
+
+    /// Similar to `parse_env_with`, but returns an error rather than panicking.
+    fn parse_with<T, F>(&self, name: &str, f: F) -> Result<T, ConfigError>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Debug,
+        F: FnOnce(&str) -> &str,
+    {
+        let value = self.get(name)?;
+        f(&value).parse().map_err(|e| ConfigError::InvalidValue {
+            key: name.to_lowercase(),
+            value: value.clone(),
+            reason: format!("{:?}", e),
+        })
+    }
+
+    /// Similar to `parse_env_opt`, but returns an error rather than panicking.
+    fn parse_opt<T>(&self, name: &str) -> Result<Option<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Debug,
+    {
+        match self.get_opt(name) {
+            Some(_) => self.parse(name).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Similar to `parse_env_or`, but returns an error rather than panicking.
+    fn parse_or<T>(&self, name: &str, default: T) -> Result<T, ConfigError>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Debug,
+    {
+        Ok(self.parse_opt(name)?.unwrap_or(default))
+    }
+
+    /// Parses the comma-separated list of values.
+    fn parse_list<T>(&self, name: &str) -> Result<Vec<T>, ConfigError>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Debug,
+    {
+        let value = self.get(name)?;
+        value
+            .split(',')
+            .map(|item| {
+                item.trim().parse().map_err(|e| ConfigError::InvalidValue {
+                    key: name.to_lowercase(),
+                    value: value.clone(),
+                    reason: format!("{:?}", e),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Strips the `0x` prefix of the hex value, if any.
+fn strip_hex_prefix(s: &str) -> &str {
+    if s.starts_with("0x") {
+        &s[2..]
+    } else {
+        s
+    }
+}
+
 /// Configuration options for `eth_sender`.
 #[derive(Debug, Clone)]
 pub struct EthSenderOptions {
@@ -69,22 +328,102 @@ impl EthSenderOptions {
     pub fn from_env() -> Self {
         let tx_poll_period_secs: u64 = parse_env("ETH_TX_POLL_PERIOD");
 
-        Self {
+        let options = Self {
             expected_wait_time_block: parse_env("ETH_EXPECTED_WAIT_TIME_BLOCK"),
             tx_poll_period: Duration::new(tx_poll_period_secs, 0),
             wait_confirmations: parse_env("ETH_WAIT_CONFIRMATIONS"),
             max_txs_in_flight: parse_env("ETH_MAX_TXS_IN_FLIGHT"),
             is_enabled: parse_env("ETH_IS_ENABLED"),
+        };
+        options
+            .validate()
+            .unwrap_or_else(|e| panic!("Invalid ETH sender config: {}", e));
+        options
+    }
+
+    /// Checks the semantic constraints of the options values.
+    /// Returns the first violated constraint, if any.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.expected_wait_time_block == 0 {
+            return Err("expected_wait_time_block must be positive".to_string());
+        }
+        if self.tx_poll_period == Duration::from_secs(0) {
+            return Err("tx_poll_period must be positive".to_string());
+        }
+        if self.max_txs_in_flight == 0 {
+            return Err("max_txs_in_flight must be positive".to_string());
+        }
+        // Transaction with no confirmations would be finalized before it's included in a block.
+        if self.wait_confirmations == 0 {
+            return Err("wait_confirmations must be positive".to_string());
         }
+        if self.expected_wait_time_block < self.wait_confirmations {
+            return Err(format!(
+                "expected_wait_time_block ({}) must be at least wait_confirmations ({})",
+                self.expected_wait_time_block, self.wait_confirmations
+            ));
+        }
+        Ok(())
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ProverOptions {
     pub prepare_data_interval: Duration,
+    /// Max interval of the prover server witness generators, the interval grows up to it while
+    /// the provers get no jobs. Equals to `prepare_data_interval` by default, i.e. it doesn't grow.
+    pub prepare_data_max_interval: Duration,
     pub heartbeat_interval: Duration,
     pub cycle_wait: Duration,
     pub gone_timeout: Duration,
+    pub retry_initial_delay: Duration,
+    pub retry_max_delay: Duration,
+    /// Amount of jobs proved concurrently by a single prover.
+    pub workers: usize,
+    /// Amount of consecutive rounds without a job after which the wait between rounds starts to grow.
+    pub idle_backoff_after: u32,
+    /// Max wait between rounds without a job. Equals to `cycle_wait` by default, i.e. wait doesn't grow.
+    pub idle_backoff_max: Duration,
+    /// Whether the prover publishes the proofs without verifying them first.
+    /// Saves the verification time, but the proofs created with a broken setup are detected
+    /// by the contract only.
+    pub skip_self_verify: bool,
+    /// Loading of the circuit parameters taking longer than this is reported with a warning.
+    pub params_load_warn_threshold: Option<Duration>,
+    /// Job is abandoned if its proof generation takes longer than this. No limit by default.
+    pub max_proof_time: Option<Duration>,
+    /// Blocks the prover is pinned to, e.g. to re-prove the historical blocks for debugging.
+    /// If set, the prover proves these blocks only, bypassing the job queue of the server.
+    pub block_range: Option<Range<i64>>,
+    /// Max time to establish the connection to the prover server.
+    pub connect_timeout: Duration,
+    /// Max time of a single request to the prover server, including the connection.
+    pub request_timeout: Duration,
+    /// Amount of extra attempts of the idempotent requests which failed to connect to the server.
+    pub request_retries: u32,
+    /// Delay before the first retry of the request, doubled after every failed attempt.
+    pub request_retry_backoff: Duration,
+    /// Amount of provers run by a single process, see `prover::pool::ProverPool`.
+    pub pool_size: usize,
+    /// Failed prover of the pool is restarted at most this many times per hour,
+    /// after that the whole pool is stopped. Failed prover is not restarted by default.
+    pub max_restarts_per_hour: u32,
+    /// Shared secret sent to the prover server as a bearer token, see `ConfigurationOptions::prover_secret_auth`.
+    pub secret_auth: Option<String>,
+    /// PEM certificate trusted in addition to the system ones when connecting to the prover server
+    /// over HTTPS, e.g. the self-signed certificate of the deployment.
+    pub server_ca_cert: Option<PathBuf>,
+    /// Whether the prover checks the health of the server on start and exits if the server
+    /// is not able to give out the jobs, see `prover::check_server_health`.
+    pub health_check_on_start: bool,
+    /// Mutual TLS with the prover server, plain HTTP is used if unset.
+    pub tls: Option<TlsConfig>,
+    /// Max size of the prover data of a block in bytes. Download of the larger data fails
+    /// right away instead of running out of memory on a corrupted server response.
+    pub max_prover_data_size: usize,
+    /// Server responding that there is no job is not asked for the job of the same size again
+    /// for this long, zero disables the caching.
+    pub none_cache_ttl: Duration,
 }
 
 impl ProverOptions {
@@ -93,19 +432,142 @@ impl ProverOptions {
     pub fn from_env() -> Self {
         let prepare_data_interval =
             Duration::from_millis(parse_env("PROVER_PREPARE_DATA_INTERVAL"));
+        let prepare_data_max_interval = parse_env_opt("PROVER_PREPARE_DATA_MAX_INTERVAL")
+            .map(Duration::from_millis)
+            .unwrap_or(prepare_data_interval);
         let heartbeat_interval = Duration::from_millis(parse_env("PROVER_HEARTBEAT_INTERVAL"));
         let cycle_wait = Duration::from_millis(parse_env("PROVER_CYCLE_WAIT"));
         let gone_timeout = Duration::from_millis(parse_env("PROVER_GONE_TIMEOUT"));
+        let retry_initial_delay =
+            Duration::from_millis(parse_env_or("PROVER_RETRY_INITIAL_DELAY_MS", 1000));
+        let retry_max_delay =
+            Duration::from_millis(parse_env_or("PROVER_RETRY_MAX_DELAY_MS", 300_000));
+        let workers = parse_env_or("PROVER_WORKERS", 1);
+        let idle_backoff_after = parse_env_or("PROVER_IDLE_BACKOFF_AFTER", 0);
+        let idle_backoff_max = parse_env_opt("PROVER_IDLE_BACKOFF_MAX")
+            .map(Duration::from_millis)
+            .unwrap_or(cycle_wait);
+        let skip_self_verify = parse_env_or("PROVER_SKIP_SELF_VERIFY", false);
+        let params_load_warn_threshold =
+            parse_env_opt("PROVER_PARAMS_LOAD_WARN_SECS").map(Duration::from_secs);
+        let max_proof_time = parse_env_opt("PROVER_MAX_PROOF_TIME").map(Duration::from_millis);
+        let block_range = env::var("PROVER_BLOCK_RANGE").ok().map(|range| {
+            parse_block_range(&range).unwrap_or_else(|e| {
+                panic!(
+                    "Failed to parse environment variable PROVER_BLOCK_RANGE: {}",
+                    e
+                )
+            })
+        });
+        let connect_timeout =
+            Duration::from_millis(parse_env_or("PROVER_CONNECT_TIMEOUT_MS", 5000));
+        let request_timeout = Duration::from_secs(parse_env_or("REQ_SERVER_TIMEOUT", 10));
+        let request_retries = parse_env_or("PROVER_REQUEST_RETRIES", 3);
+        let request_retry_backoff =
+            Duration::from_millis(parse_env_or("PROVER_REQUEST_RETRY_BACKOFF_MS", 1000));
+        let pool_size = parse_env_or("PROVER_POOL_SIZE", 1);
+        let max_restarts_per_hour = parse_env_or("PROVER_POOL_MAX_RESTARTS_PER_HOUR", 0);
+        let secret_auth = env::var("PROVER_SECRET_AUTH")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+        let server_ca_cert = env::var_os("PROVER_SERVER_CA_CERT").map(PathBuf::from);
+        let health_check_on_start = parse_env_or("PROVER_HEALTH_CHECK_ON_START", false);
+        let tls = TlsConfig::from_env();
+        let max_prover_data_size = parse_env_or("PROVER_MAX_PROVER_DATA_MB", 512) * 1024 * 1024;
+        let none_cache_ttl = Duration::from_millis(parse_env_or("PROVER_NONE_CACHE_TTL_MS", 500));
 
         Self {
             prepare_data_interval,
+            prepare_data_max_interval,
             heartbeat_interval,
             cycle_wait,
             gone_timeout,
+            retry_initial_delay,
+            retry_max_delay,
+            workers,
+            idle_backoff_after,
+            idle_backoff_max,
+            skip_self_verify,
+            params_load_warn_threshold,
+            max_proof_time,
+            block_range,
+            connect_timeout,
+            request_timeout,
+            request_retries,
+            request_retry_backoff,
+            pool_size,
+            max_restarts_per_hour,
+            secret_auth,
+            server_ca_cert,
+            health_check_on_start,
+            tls,
+            max_prover_data_size,
+            none_cache_ttl,
         }
     }
 }
 
+/// Parses the non-empty block range in the `start..end` form, the end is excluded.
+pub fn parse_block_range(range: &str) -> Result<Range<i64>, String> {
+    let mut bounds = range.splitn(2, "..");
+    let start = bounds.next().unwrap_or_default();
+    let end = bounds
+        .next()
+        .ok_or_else(|| format!("block range {} must be in the start..end form", range))?;
+    let parse_bound = |bound: &str| {
+        bound
+            .trim()
+            .parse::<i64>()
+            .map_err(|e| format!("invalid bound {} of block range {}: {}", bound, range, e))
+    };
+    let range = parse_bound(start)?..parse_bound(end)?;
+    if range.start >= range.end {
+        return Err(format!("block range {:?} is empty", range));
+    }
+    Ok(range)
+}
+
+/// Parses the expected hashes of the verification keys in the `chunks:hash,chunks:hash` form,
+/// e.g. `6:0x12ab..,30:0x34cd..`. Hashes are the hex-encoded SHA-256 digests, returned
+/// in lowercase without the `0x` prefix.
+pub fn parse_vk_hashes(hashes: &str) -> Result<BTreeMap<usize, String>, String> {
+    let mut parsed = BTreeMap::new();
+    for entry in hashes
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let mut parts = entry.splitn(2, ':');
+        let block_size = parts.next().unwrap_or_default();
+        let hash = parts.next().ok_or_else(|| {
+            format!(
+                "verification key hash {} must be in the chunks:hash form",
+                entry
+            )
+        })?;
+        let block_size = block_size.trim().parse::<usize>().map_err(|e| {
+            format!(
+                "invalid block size of verification key hash {}: {}",
+                entry, e
+            )
+        })?;
+        let hash = hash.trim().trim_start_matches("0x").to_lowercase();
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "verification key hash of block size {} must be 32 hex-encoded bytes",
+                block_size
+            ));
+        }
+        if parsed.insert(block_size, hash).is_some() {
+            return Err(format!(
+                "verification key hash of block size {} is set twice",
+                block_size
+            ));
+        }
+    }
+    Ok(parsed)
+}
+
 /// Configuration options for `admin server`.
 #[derive(Debug, Clone)]
 pub struct AdminServerOptions {
@@ -144,6 +606,23 @@ impl TokenPriceSource {
             source => panic!("Unknown token price source: {}", source),
         }
     }
+
+    fn from_toml_values(values: &TomlValues) -> Result<Self, ConfigError> {
+        let source = values.get("TOKEN_PRICE_SOURCE")?;
+        match source.to_lowercase().as_str() {
+            "coinmarketcap" => Ok(Self::CoinMarketCap {
+                base_url: values.parse("COINMARKETCAP_BASE_URL")?,
+            }),
+            "coingecko" => Ok(Self::CoinGecko {
+                base_url: values.parse("COINGECKO_BASE_URL")?,
+            }),
+            _ => Err(ConfigError::InvalidValue {
+                key: "token_price_source".to_string(),
+                value: source,
+                reason: "unknown token price source".to_string(),
+            }),
+        }
+    }
 }
 
 /// Configuration options related to generating blocks by state keeper.
@@ -161,28 +640,75 @@ pub struct MiniblockTimings {
 
 impl MiniblockTimings {
     pub fn from_env() -> Self {
-        let fast_miniblock_iterations = if env::var("FAST_BLOCK_MINIBLOCKS_ITERATIONS").is_ok() {
-            parse_env("FAST_BLOCK_MINIBLOCKS_ITERATIONS")
-        } else {
-            parse_env("MINIBLOCKS_ITERATIONS")
-        };
+        let max_miniblock_iterations = parse_env("MINIBLOCKS_ITERATIONS");
+        let fast_miniblock_iterations =
+            parse_env_or("FAST_BLOCK_MINIBLOCKS_ITERATIONS", max_miniblock_iterations);
 
-        Self {
+        let timings = Self {
             miniblock_iteration_interval: Duration::from_millis(parse_env::<u64>(
                 "MINIBLOCK_ITERATION_INTERVAL",
             )),
-            max_miniblock_iterations: parse_env("MINIBLOCKS_ITERATIONS"),
+            max_miniblock_iterations,
             fast_miniblock_iterations,
+        };
+        timings
+            .validate()
+            .unwrap_or_else(|e| panic!("Invalid miniblock timings: {}", e));
+        timings
+    }
+
+    /// Checks the semantic constraints of the timings values.
+    /// Returns the first violated constraint, if any.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.miniblock_iteration_interval == Duration::from_secs(0) {
+            return Err("miniblock_iteration_interval must be positive".to_string());
+        }
+        if self.max_miniblock_iterations == 0 {
+            return Err("max_miniblock_iterations must be positive".to_string());
+        }
+        if self.fast_miniblock_iterations == 0 {
+            return Err("fast_miniblock_iterations must be positive".to_string());
         }
+        if self.fast_miniblock_iterations > self.max_miniblock_iterations {
+            return Err(format!(
+                "fast_miniblock_iterations ({}) must not exceed max_miniblock_iterations ({})",
+                self.fast_miniblock_iterations, self.max_miniblock_iterations
+            ));
+        }
+        Ok(())
+    }
+
+    fn from_toml_values(values: &TomlValues) -> Result<Self, ConfigError> {
+        let max_miniblock_iterations = values.parse("MINIBLOCKS_ITERATIONS")?;
+        let fast_miniblock_iterations =
+            values.parse_or("FAST_BLOCK_MINIBLOCKS_ITERATIONS", max_miniblock_iterations)?;
+
+        Ok(Self {
+            miniblock_iteration_interval: Duration::from_millis(
+                values.parse("MINIBLOCK_ITERATION_INTERVAL")?,
+            ),
+            max_miniblock_iterations,
+            fast_miniblock_iterations,
+        })
     }
 }
 
+/// Default amount of consecutive failed requests after which the Ethereum node is quarantined.
+const DEFAULT_WEB3_URL_FAILURES_THRESHOLD: u32 = 3;
+/// Default interval after which the quarantined Ethereum node is retried.
+const DEFAULT_WEB3_URL_QUARANTINE_SECS: u64 = 60;
+
 #[derive(Debug, Clone)]
 pub struct ConfigurationOptions {
     pub rest_api_server_address: SocketAddr,
     pub json_rpc_http_server_address: SocketAddr,
     pub json_rpc_ws_server_address: SocketAddr,
-    pub web3_url: String,
+    /// Ethereum nodes, used in turn with the failover, see `eth_client::Web3UrlSelector`.
+    pub web3_urls: Vec<String>,
+    /// Ethereum node failing this many consecutive requests is quarantined.
+    pub web3_url_failures_threshold: u32,
+    /// Quarantined Ethereum node is retried after this interval.
+    pub web3_url_quarantine: Duration,
     pub genesis_tx_hash: H256,
     pub contract_eth_addr: H160,
     pub governance_eth_addr: H160,
@@ -205,6 +731,43 @@ pub struct ConfigurationOptions {
     pub witness_generators: usize,
     /// Fee increase coefficient for fast processing of withdrawal.
     pub ticker_fast_processing_coeff: f64,
+    /// Max amount of subscription requests per second over a single WebSocket connection.
+    pub subscriptions_per_conn_per_sec: u32,
+    /// Max amount of active subscriptions of a single WebSocket connection.
+    pub max_subscriptions_per_conn: usize,
+    /// Max amount of simultaneously open WebSocket connections.
+    pub ws_max_connections: usize,
+    /// Max amount of simultaneously open WebSocket connections from a single IP address.
+    pub ws_max_connections_per_ip: usize,
+    /// Whether the WebSocket server is reachable only through the reverse proxy appending the client
+    /// address to `X-Forwarded-For`. The per-IP limit is applied only behind the proxy: the server
+    /// doesn't see the TCP peer addresses, and the headers sent by the clients directly are forged
+    /// at will.
+    pub ws_behind_proxy: bool,
+    /// Amount of the latest events per subscription kept to be replayed to the re-subscribing
+    /// WebSocket clients. Zero disables the replay.
+    pub ws_replay_buffer_size: usize,
+    /// Shared secret the provers must present as a bearer token to the prover server.
+    /// Prover server accepts unauthenticated requests if unset.
+    pub prover_secret_auth: Option<String>,
+    /// Mutual TLS with the provers: prover server accepts the provers presenting the certificate
+    /// signed by the CA only. Prover server accepts plain HTTP if unset.
+    pub prover_tls: Option<TlsConfig>,
+    /// Expected hashes of the verification keys of the provers by block size, see `parse_vk_hashes`.
+    /// Provers with the other keys are refused on start. Keys are not checked if empty.
+    pub prover_vk_hashes: BTreeMap<usize, String>,
+    /// Whether the prover server verifies the published proofs before storing them, so the invalid
+    /// proofs are rejected instead of failing on-chain. Disabled for the dummy provers.
+    pub prover_verify_proofs: bool,
+    /// Amount of the failed or timed out attempts of a prover to prove a block,
+    /// after which the block is not assigned to this prover anymore.
+    pub prover_max_block_attempts: usize,
+    /// Max rate of the job requests of a single prover worker per second, the excess requests
+    /// are rejected with `429 Too Many Requests`. Zero disables the limit.
+    pub prover_rate_limit: u32,
+    /// Amount of the job requests a prover worker may send at once before it's limited
+    /// to `prover_rate_limit`.
+    pub prover_rate_limit_burst: u32,
 }
 
 impl ConfigurationOptions {
@@ -214,21 +777,28 @@ impl ConfigurationOptions {
         let mut available_block_chunk_sizes = block_chunk_sizes().to_vec();
         available_block_chunk_sizes.sort();
 
-        Self {
+        let options = Self {
             rest_api_server_address: parse_env("REST_API_BIND"),
             json_rpc_http_server_address: parse_env("HTTP_RPC_API_BIND"),
             json_rpc_ws_server_address: parse_env("WS_API_BIND"),
-            web3_url: get_env("WEB3_URL"),
+            web3_urls: get_env("WEB3_URL")
+                .split(',')
+                .map(|url| url.trim().to_string())
+                .collect(),
+            web3_url_failures_threshold: parse_env_or(
+                "WEB3_URL_FAILURES_THRESHOLD",
+                DEFAULT_WEB3_URL_FAILURES_THRESHOLD,
+            ),
+            web3_url_quarantine: Duration::from_secs(parse_env_or(
+                "WEB3_URL_QUARANTINE_SECS",
+                DEFAULT_WEB3_URL_QUARANTINE_SECS,
+            )),
             genesis_tx_hash: parse_env_with("GENESIS_TX_HASH", |s| &s[2..]),
             contract_eth_addr: parse_env_with("CONTRACT_ADDR", |s| &s[2..]),
             governance_eth_addr: parse_env_with("GOVERNANCE_ADDR", |s| &s[2..]),
             operator_commit_eth_addr: parse_env_with("OPERATOR_COMMIT_ETH_ADDRESS", |s| &s[2..]),
             operator_fee_eth_addr: parse_env_with("OPERATOR_FEE_ETH_ADDRESS", |s| &s[2..]),
-            operator_private_key: if env::var("OPERATOR_PRIVATE_KEY").is_ok() {
-                Some(parse_env("OPERATOR_PRIVATE_KEY"))
-            } else {
-                None
-            },
+            operator_private_key: parse_env_opt("OPERATOR_PRIVATE_KEY"),
             chain_id: parse_env("CHAIN_ID"),
             gas_price_factor: parse_env("GAS_PRICE_FACTOR"),
             prover_server_address: parse_env("PROVER_SERVER_BIND"),
@@ -246,6 +816,242 @@ impl ConfigurationOptions {
             token_price_source: TokenPriceSource::from_env(),
             witness_generators: parse_env("WITNESS_GENERATORS"),
             ticker_fast_processing_coeff: parse_env("TICKER_FAST_PROCESSING_COEFF"),
+            subscriptions_per_conn_per_sec: parse_env("SUBSCRIPTIONS_PER_CONN_PER_SEC"),
+            max_subscriptions_per_conn: parse_env("MAX_SUBSCRIPTIONS_PER_CONN"),
+            ws_max_connections: parse_env("WS_MAX_CONNECTIONS"),
+            ws_max_connections_per_ip: parse_env("WS_MAX_CONNECTIONS_PER_IP"),
+            ws_behind_proxy: parse_env_or("WS_BEHIND_PROXY", false),
+            ws_replay_buffer_size: parse_env("WS_REPLAY_BUFFER_SIZE"),
+            prover_secret_auth: env::var("PROVER_SECRET_AUTH")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            prover_tls: TlsConfig::from_env(),
+            prover_vk_hashes: env::var("PROVER_VK_HASHES")
+                .map(|hashes| {
+                    parse_vk_hashes(&hashes).unwrap_or_else(|e| {
+                        panic!(
+                            "Failed to parse environment variable PROVER_VK_HASHES: {}",
+                            e
+                        )
+                    })
+                })
+                .unwrap_or_default(),
+            prover_verify_proofs: parse_env_or("PROVER_SERVER_VERIFY_PROOFS", true),
+            prover_max_block_attempts: parse_env_or("PROVER_SERVER_MAX_BLOCK_ATTEMPTS", 3),
+            prover_rate_limit: parse_env_or("PROVER_SERVER_RATE_LIMIT", 10),
+            prover_rate_limit_burst: parse_env_or("PROVER_SERVER_RATE_LIMIT_BURST", 20),
+        };
+        if let Err(violations) = options.validate() {
+            panic!("Invalid configuration options:\n{}", violations.join("\n"));
+        }
+        options
+    }
+
+    /// URL of the primary Ethereum node, for the tools talking to a single node.
+    pub fn web3_url(&self) -> &str {
+        &self.web3_urls[0]
+    }
+
+    /// Loads the configuration options from the TOML file.
+    /// Keys of the file are the names of the environment variables used by `from_env` in snake_case,
+    /// e.g. `rest_api_bind` or `block_chunk_sizes`. Lists may be either TOML arrays or
+    /// comma-separated strings.
+    pub fn from_toml(path: &Path) -> Result<Self, ConfigError> {
+        let values = TomlValues::from_file(path)?;
+
+        let mut available_block_chunk_sizes: Vec<usize> = values.parse_list("BLOCK_CHUNK_SIZES")?;
+        available_block_chunk_sizes.sort();
+
+        let options = Self {
+            rest_api_server_address: values.parse("REST_API_BIND")?,
+            json_rpc_http_server_address: values.parse("HTTP_RPC_API_BIND")?,
+            json_rpc_ws_server_address: values.parse("WS_API_BIND")?,
+            web3_urls: values.parse_list("WEB3_URL")?,
+            web3_url_failures_threshold: values.parse_or(
+                "WEB3_URL_FAILURES_THRESHOLD",
+                DEFAULT_WEB3_URL_FAILURES_THRESHOLD,
+            )?,
+            web3_url_quarantine: Duration::from_secs(
+                values.parse_or("WEB3_URL_QUARANTINE_SECS", DEFAULT_WEB3_URL_QUARANTINE_SECS)?,
+            ),
+            genesis_tx_hash: values.parse_with("GENESIS_TX_HASH", strip_hex_prefix)?,
+            contract_eth_addr: values.parse_with("CONTRACT_ADDR", strip_hex_prefix)?,
+            governance_eth_addr: values.parse_with("GOVERNANCE_ADDR", strip_hex_prefix)?,
+            operator_commit_eth_addr: values
+                .parse_with("OPERATOR_COMMIT_ETH_ADDRESS", strip_hex_prefix)?,
+            operator_fee_eth_addr: values
+                .parse_with("OPERATOR_FEE_ETH_ADDRESS", strip_hex_prefix)?,
+            operator_private_key: values.parse_opt("OPERATOR_PRIVATE_KEY")?,
+            chain_id: values.parse("CHAIN_ID")?,
+            gas_price_factor: values.parse("GAS_PRICE_FACTOR")?,
+            prover_server_address: values.parse("PROVER_SERVER_BIND")?,
+            confirmations_for_eth_event: values.parse("CONFIRMATIONS_FOR_ETH_EVENT")?,
+            api_requests_caches_size: values.parse("API_REQUESTS_CACHES_SIZE")?,
+            available_block_chunk_sizes,
+            max_number_of_withdrawals_per_block: values
+                .parse("MAX_NUMBER_OF_WITHDRAWALS_PER_BLOCK")?,
+            eth_watch_poll_interval: Duration::from_millis(
+                values.parse("ETH_WATCH_POLL_INTERVAL")?,
+            ),
+            eth_network: values.parse("ETH_NETWORK")?,
+            idle_provers: values.parse("IDLE_PROVERS")?,
+            miniblock_timings: MiniblockTimings::from_toml_values(&values)?,
+            prometheus_export_port: values.parse("PROMETHEUS_EXPORT_PORT")?,
+            token_price_source: TokenPriceSource::from_toml_values(&values)?,
+            witness_generators: values.parse("WITNESS_GENERATORS")?,
+            ticker_fast_processing_coeff: values.parse("TICKER_FAST_PROCESSING_COEFF")?,
+            subscriptions_per_conn_per_sec: values.parse("SUBSCRIPTIONS_PER_CONN_PER_SEC")?,
+            max_subscriptions_per_conn: values.parse("MAX_SUBSCRIPTIONS_PER_CONN")?,
+            ws_max_connections: values.parse("WS_MAX_CONNECTIONS")?,
+            ws_max_connections_per_ip: values.parse("WS_MAX_CONNECTIONS_PER_IP")?,
+            ws_behind_proxy: values.parse_or("WS_BEHIND_PROXY", false)?,
+            ws_replay_buffer_size: values.parse("WS_REPLAY_BUFFER_SIZE")?,
+            prover_secret_auth: values
+                .get_opt("PROVER_SECRET_AUTH")
+                .filter(|secret| !secret.is_empty()),
+            prover_tls: TlsConfig::from_paths(
+                values.get_opt("PROVER_TLS_CERT"),
+                values.get_opt("PROVER_TLS_KEY"),
+                values.get_opt("PROVER_TLS_CA"),
+            )
+            .map_err(|e| ConfigError::Invalid(vec![e]))?,
+            prover_vk_hashes: values
+                .get_opt("PROVER_VK_HASHES")
+                .map(|hashes| parse_vk_hashes(&hashes))
+                .transpose()
+                .map_err(|e| ConfigError::Invalid(vec![e]))?
+                .unwrap_or_default(),
+            prover_verify_proofs: values.parse_or("PROVER_SERVER_VERIFY_PROOFS", true)?,
+            prover_max_block_attempts: values.parse_or("PROVER_SERVER_MAX_BLOCK_ATTEMPTS", 3)?,
+            prover_rate_limit: values.parse_or("PROVER_SERVER_RATE_LIMIT", 10)?,
+            prover_rate_limit_burst: values.parse_or("PROVER_SERVER_RATE_LIMIT_BURST", 20)?,
+        };
+        options.validate().map_err(ConfigError::Invalid)?;
+        Ok(options)
+    }
+
+    /// Checks the semantic constraints of the options values.
+    /// Returns the list of all the violated constraints, if any.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+
+        if self.available_block_chunk_sizes.is_empty() {
+            violations.push("available_block_chunk_sizes must not be empty".to_string());
+        }
+        if self.available_block_chunk_sizes.contains(&0) {
+            violations.push("available_block_chunk_sizes must not contain zero".to_string());
+        }
+        if !(self.gas_price_factor.is_finite() && self.gas_price_factor > 0.0) {
+            violations.push(format!(
+                "gas_price_factor must be positive, got {}",
+                self.gas_price_factor
+            ));
+        }
+        if self.max_number_of_withdrawals_per_block == 0 {
+            violations.push("max_number_of_withdrawals_per_block must be positive".to_string());
+        }
+        if self.api_requests_caches_size == 0 {
+            violations.push("api_requests_caches_size must be positive".to_string());
+        }
+        if self.eth_watch_poll_interval == Duration::from_secs(0) {
+            violations.push("eth_watch_poll_interval must be positive".to_string());
+        }
+        if self.eth_network.is_empty() {
+            violations.push("eth_network must not be empty".to_string());
+        }
+        if self.web3_urls.is_empty() {
+            violations.push("web3_urls must not be empty".to_string());
+        }
+        for url in &self.web3_urls {
+            if let Err(e) = Url::parse(url) {
+                violations.push(format!("web3_url {} is not a valid url: {}", url, e));
+            }
+        }
+        if self.web3_url_failures_threshold == 0 {
+            violations.push("web3_url_failures_threshold must be positive".to_string());
+        }
+        if self.web3_url_quarantine == Duration::from_secs(0) {
+            violations.push("web3_url_quarantine must be positive".to_string());
+        }
+        if self.witness_generators == 0 {
+            violations.push("witness_generators must be positive".to_string());
+        }
+        if !(self.ticker_fast_processing_coeff.is_finite()
+            && self.ticker_fast_processing_coeff > 0.0)
+        {
+            violations.push(format!(
+                "ticker_fast_processing_coeff must be positive, got {}",
+                self.ticker_fast_processing_coeff
+            ));
+        }
+
+        if self.subscriptions_per_conn_per_sec == 0 {
+            violations.push("subscriptions_per_conn_per_sec must be positive".to_string());
+        }
+        if self.max_subscriptions_per_conn == 0 {
+            violations.push("max_subscriptions_per_conn must be positive".to_string());
+        }
+        if self.ws_max_connections == 0 {
+            violations.push("ws_max_connections must be positive".to_string());
+        }
+        if self.ws_max_connections_per_ip == 0 {
+            violations.push("ws_max_connections_per_ip must be positive".to_string());
+        } else if self.ws_max_connections_per_ip > self.ws_max_connections {
+            violations.push(format!(
+                "ws_max_connections_per_ip ({}) must not exceed ws_max_connections ({})",
+                self.ws_max_connections_per_ip, self.ws_max_connections
+            ));
+        }
+
+        if self.prover_max_block_attempts == 0 {
+            violations.push("prover_max_block_attempts must be positive".to_string());
+        }
+        if self.prover_rate_limit > 0 && self.prover_rate_limit_burst == 0 {
+            violations.push(
+                "prover_rate_limit_burst must be positive if prover_rate_limit is set".to_string(),
+            );
+        }
+
+        if let Err(e) = self.miniblock_timings.validate() {
+            violations.push(e);
+        }
+        for block_size in self.prover_vk_hashes.keys() {
+            if !self.available_block_chunk_sizes.contains(block_size) {
+                violations.push(format!(
+                    "prover_vk_hashes has the hash of unavailable block size {}",
+                    block_size
+                ));
+            }
+        }
+
+        // Servers can't listen on the same address.
+        let server_addresses = [
+            ("rest_api_server_address", self.rest_api_server_address),
+            (
+                "json_rpc_http_server_address",
+                self.json_rpc_http_server_address,
+            ),
+            (
+                "json_rpc_ws_server_address",
+                self.json_rpc_ws_server_address,
+            ),
+            ("prover_server_address", self.prover_server_address),
+        ];
+        for (idx, (name, address)) in server_addresses.iter().enumerate() {
+            for (other_name, other_address) in &server_addresses[idx + 1..] {
+                if address == other_address && address.port() != 0 {
+                    violations.push(format!(
+                        "{} and {} must differ, both are {}",
+                        name, other_name, address
+                    ));
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
         }
     }
 }
@@ -276,5 +1082,416 @@ impl AvailableBlockSizesConfig {
             "block sized and setup powers should have same length, check config file"
         );
         result
+            .validate()
+            .expect("setup powers are too small for the block sizes, check config file");
+        result
+    }
+
+    /// Checks that the setup of every block size is large enough to fit its chunks,
+    /// i.e. `2^blocks_setup_power2[i] >= blocks_chunks[i]`.
+    /// Returns the list of all the under-powered block sizes, if any.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let violations: Vec<String> = self
+            .blocks_chunks
+            .iter()
+            .zip(&self.blocks_setup_power2)
+            .filter_map(|(&chunks, &power)| {
+                let min_power = chunks.next_power_of_two().trailing_zeros();
+                if power < min_power {
+                    Some(format!(
+                        "setup power {} is too small for block of {} chunks, at least {} is required",
+                        power, chunks, min_power
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_options() -> ConfigurationOptions {
+        ConfigurationOptions {
+            rest_api_server_address: "127.0.0.1:3001".parse().unwrap(),
+            json_rpc_http_server_address: "127.0.0.1:3030".parse().unwrap(),
+            json_rpc_ws_server_address: "127.0.0.1:3031".parse().unwrap(),
+            web3_urls: vec!["http://127.0.0.1:8545".to_string()],
+            web3_url_failures_threshold: 3,
+            web3_url_quarantine: Duration::from_secs(60),
+            genesis_tx_hash: H256::zero(),
+            contract_eth_addr: H160::zero(),
+            governance_eth_addr: H160::zero(),
+            operator_fee_eth_addr: H160::zero(),
+            operator_commit_eth_addr: H160::zero(),
+            operator_private_key: None,
+            chain_id: 9,
+            gas_price_factor: 1.0,
+            prover_server_address: "127.0.0.1:8088".parse().unwrap(),
+            confirmations_for_eth_event: 0,
+            api_requests_caches_size: 10_000,
+            available_block_chunk_sizes: vec![6, 30],
+            max_number_of_withdrawals_per_block: 10,
+            eth_watch_poll_interval: Duration::from_millis(300),
+            eth_network: "localhost".to_string(),
+            idle_provers: 1,
+            miniblock_timings: MiniblockTimings {
+                miniblock_iteration_interval: Duration::from_millis(200),
+                max_miniblock_iterations: 10,
+                fast_miniblock_iterations: 5,
+            },
+            prometheus_export_port: 3312,
+            token_price_source: TokenPriceSource::CoinGecko {
+                base_url: "http://127.0.0.1:9876".parse().unwrap(),
+            },
+            witness_generators: 2,
+            ticker_fast_processing_coeff: 10.0,
+            subscriptions_per_conn_per_sec: 10,
+            max_subscriptions_per_conn: 100,
+            ws_max_connections: 1000,
+            ws_max_connections_per_ip: 50,
+            ws_behind_proxy: false,
+            ws_replay_buffer_size: 100,
+            prover_secret_auth: None,
+            prover_tls: None,
+            prover_vk_hashes: BTreeMap::new(),
+            prover_verify_proofs: true,
+            prover_max_block_attempts: 3,
+            prover_rate_limit: 10,
+            prover_rate_limit_burst: 20,
+        }
+    }
+
+    /// Checks that the options modified by `f` are invalid and returns the violations.
+    fn violations(f: impl FnOnce(&mut ConfigurationOptions)) -> Vec<String> {
+        let mut options = valid_options();
+        f(&mut options);
+        options
+            .validate()
+            .expect_err("invalid options passed the validation")
+    }
+
+    #[test]
+    fn valid_options_pass_validation() {
+        assert_eq!(valid_options().validate(), Ok(()));
+    }
+
+    #[test]
+    fn tls_config_requires_all_files() {
+        let path = |path: &str| Some(path.to_string());
+        assert_eq!(TlsConfig::from_paths(None, None, None), Ok(None));
+        assert_eq!(TlsConfig::from_paths(path(""), None, path("")), Ok(None));
+        assert_eq!(
+            TlsConfig::from_paths(path("cert.pem"), path("key.pem"), path("ca.pem")),
+            Ok(Some(TlsConfig {
+                cert_pem: "cert.pem".into(),
+                key_pem: "key.pem".into(),
+                ca_pem: "ca.pem".into(),
+            }))
+        );
+        assert!(TlsConfig::from_paths(path("cert.pem"), path("key.pem"), None).is_err());
+        assert!(TlsConfig::from_paths(None, None, path("ca.pem")).is_err());
+    }
+
+    #[test]
+    fn block_range_parsing() {
+        assert_eq!(parse_block_range("1200..1210"), Ok(1200..1210));
+        assert_eq!(parse_block_range(" 5 .. 6 "), Ok(5..6));
+        assert!(parse_block_range("1200").is_err());
+        assert!(parse_block_range("1210..1200").is_err());
+        assert!(parse_block_range("5..5").is_err());
+        assert!(parse_block_range("a..b").is_err());
+    }
+
+    #[test]
+    fn vk_hashes_parsing() {
+        let hash = "ab".repeat(32);
+        let parsed = parse_vk_hashes(&format!("6:0x{}, 30:{}", hash.to_uppercase(), hash)).unwrap();
+        assert_eq!(
+            parsed.into_iter().collect::<Vec<_>>(),
+            vec![(6, hash.clone()), (30, hash.clone())]
+        );
+        assert_eq!(parse_vk_hashes(""), Ok(BTreeMap::new()));
+        assert!(parse_vk_hashes(&hash).is_err());
+        assert!(parse_vk_hashes("6:abcd").is_err());
+        assert!(parse_vk_hashes(&format!("x:{}", hash)).is_err());
+        assert!(parse_vk_hashes(&format!("6:{},6:{}", hash, hash)).is_err());
+    }
+
+    #[test]
+    fn vk_hash_of_unavailable_block_size() {
+        violations(|o| {
+            let block_size = o.available_block_chunk_sizes.iter().max().unwrap() + 1;
+            o.prover_vk_hashes.insert(block_size, "ab".repeat(32));
+        });
+    }
+
+    #[test]
+    fn zero_prover_max_block_attempts() {
+        violations(|o| o.prover_max_block_attempts = 0);
+    }
+
+    #[test]
+    fn zero_prover_rate_limit_burst() {
+        violations(|o| o.prover_rate_limit_burst = 0);
+    }
+
+    #[test]
+    fn empty_block_chunk_sizes() {
+        violations(|o| o.available_block_chunk_sizes.clear());
+    }
+
+    #[test]
+    fn zero_block_chunk_size() {
+        violations(|o| o.available_block_chunk_sizes.push(0));
+    }
+
+    #[test]
+    fn non_positive_gas_price_factor() {
+        violations(|o| o.gas_price_factor = 0.0);
+        violations(|o| o.gas_price_factor = -1.5);
+        violations(|o| o.gas_price_factor = f64::NAN);
+    }
+
+    #[test]
+    fn zero_withdrawals_per_block() {
+        violations(|o| o.max_number_of_withdrawals_per_block = 0);
+    }
+
+    #[test]
+    fn zero_api_requests_caches_size() {
+        violations(|o| o.api_requests_caches_size = 0);
+    }
+
+    #[test]
+    fn zero_eth_watch_poll_interval() {
+        violations(|o| o.eth_watch_poll_interval = Duration::from_secs(0));
+    }
+
+    #[test]
+    fn empty_eth_network() {
+        violations(|o| o.eth_network.clear());
+    }
+
+    #[test]
+    fn invalid_web3_url() {
+        violations(|o| o.web3_urls = vec!["not a url".to_string()]);
+        violations(|o| o.web3_urls.push("not a url".to_string()));
+    }
+
+    #[test]
+    fn empty_web3_urls() {
+        violations(|o| o.web3_urls.clear());
+    }
+
+    #[test]
+    fn zero_web3_url_failover_settings() {
+        violations(|o| o.web3_url_failures_threshold = 0);
+        violations(|o| o.web3_url_quarantine = Duration::from_secs(0));
+    }
+
+    #[test]
+    fn zero_witness_generators() {
+        violations(|o| o.witness_generators = 0);
+    }
+
+    #[test]
+    fn non_positive_fast_processing_coeff() {
+        violations(|o| o.ticker_fast_processing_coeff = 0.0);
+        violations(|o| o.ticker_fast_processing_coeff = f64::INFINITY);
+    }
+
+    #[test]
+    fn zero_subscription_limits() {
+        violations(|o| o.subscriptions_per_conn_per_sec = 0);
+        violations(|o| o.max_subscriptions_per_conn = 0);
+    }
+
+    #[test]
+    fn invalid_ws_connection_limits() {
+        violations(|o| o.ws_max_connections = 0);
+        violations(|o| o.ws_max_connections_per_ip = 0);
+        violations(|o| o.ws_max_connections_per_ip = o.ws_max_connections + 1);
+    }
+
+    #[test]
+    fn invalid_miniblock_timings() {
+        violations(|o| o.miniblock_timings.miniblock_iteration_interval = Duration::from_secs(0));
+        violations(|o| o.miniblock_timings.max_miniblock_iterations = 0);
+        violations(|o| o.miniblock_timings.fast_miniblock_iterations = 0);
+        violations(|o| o.miniblock_timings.fast_miniblock_iterations = 11);
+    }
+
+    #[test]
+    fn miniblock_timings_validation() {
+        let valid = valid_options().miniblock_timings;
+        assert_eq!(valid.validate(), Ok(()));
+
+        // Fast blocks may take as many iterations as the regular ones.
+        let mut timings = valid.clone();
+        timings.fast_miniblock_iterations = timings.max_miniblock_iterations;
+        assert_eq!(timings.validate(), Ok(()));
+
+        let mut timings = valid.clone();
+        timings.fast_miniblock_iterations = timings.max_miniblock_iterations + 1;
+        assert!(timings.validate().is_err());
+
+        let mut timings = valid.clone();
+        timings.max_miniblock_iterations = 0;
+        timings.fast_miniblock_iterations = 0;
+        assert!(timings.validate().is_err());
+
+        let mut timings = valid;
+        timings.miniblock_iteration_interval = Duration::from_secs(0);
+        assert!(timings.validate().is_err());
+    }
+
+    #[test]
+    fn eth_sender_options_validation() {
+        let valid = EthSenderOptions {
+            expected_wait_time_block: 30,
+            tx_poll_period: Duration::from_secs(3),
+            wait_confirmations: 1,
+            max_txs_in_flight: 3,
+            is_enabled: true,
+        };
+        assert_eq!(valid.validate(), Ok(()));
+
+        let mut options = valid.clone();
+        options.wait_confirmations = 0;
+        assert!(options.validate().is_err());
+
+        // Expected wait time may be as short as the confirmations, but not shorter.
+        let mut options = valid.clone();
+        options.wait_confirmations = 30;
+        assert_eq!(options.validate(), Ok(()));
+        options.wait_confirmations = 31;
+        assert!(options.validate().is_err());
+
+        let mut options = valid.clone();
+        options.max_txs_in_flight = 1;
+        assert_eq!(options.validate(), Ok(()));
+
+        let mut options = valid.clone();
+        options.expected_wait_time_block = 0;
+        assert!(options.validate().is_err());
+
+        let mut options = valid.clone();
+        options.tx_poll_period = Duration::from_secs(0);
+        assert!(options.validate().is_err());
+
+        let mut options = valid;
+        options.max_txs_in_flight = 0;
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn servers_on_the_same_address() {
+        violations(|o| o.json_rpc_ws_server_address = o.json_rpc_http_server_address);
+        violations(|o| o.prover_server_address = o.rest_api_server_address);
+    }
+
+    #[test]
+    fn all_violations_are_collected() {
+        let violations = violations(|o| {
+            o.available_block_chunk_sizes.clear();
+            o.gas_price_factor = 0.0;
+            o.max_number_of_withdrawals_per_block = 0;
+        });
+        assert_eq!(violations.len(), 3, "{:?}", violations);
+    }
+
+    #[test]
+    fn under_powered_block_setup() {
+        let config = AvailableBlockSizesConfig {
+            blocks_chunks: vec![6, 512, 30],
+            blocks_setup_power2: vec![21, 8, 4],
+        };
+        assert_eq!(config.validate().unwrap_err().len(), 2);
+
+        // Setup of exactly `2^power` chunks is enough.
+        let config = AvailableBlockSizesConfig {
+            blocks_chunks: vec![6, 512],
+            blocks_setup_power2: vec![3, 9],
+        };
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn thread_panic_notify_reports_panic_description() {
+        use futures::StreamExt;
+
+        install_panic_hook();
+        let (notify_tx, mut notify_rx) = mpsc::channel(1);
+
+        let panic_notify = notify_tx.clone();
+        let handle = std::thread::Builder::new()
+            .name("panicking-thread".to_string())
+            .spawn(move || {
+                let _panic_sentinel = ThreadPanicNotify(panic_notify);
+                panic!("something went wrong");
+            })
+            .unwrap();
+        assert!(handle.join().is_err());
+        let description = block_on(notify_rx.next())
+            .unwrap()
+            .expect("panic is not reported");
+        assert!(
+            description.contains("panicking-thread")
+                && description.contains("something went wrong"),
+            "unexpected panic description: {}",
+            description
+        );
+
+        // Normal termination is reported without the description.
+        std::thread::spawn(move || {
+            let _panic_sentinel = ThreadPanicNotify(notify_tx);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(block_on(notify_rx.next()), Some(None));
+    }
+
+    #[test]
+    fn parse_env_or_uses_default_for_absent_variable() {
+        env::remove_var("CONFIG_OPTIONS_TEST_ABSENT");
+        assert_eq!(parse_env_or("CONFIG_OPTIONS_TEST_ABSENT", 42u32), 42);
+        assert_eq!(parse_env_opt::<u32>("CONFIG_OPTIONS_TEST_ABSENT"), None);
+        assert_eq!(
+            parse_env_with_or("CONFIG_OPTIONS_TEST_ABSENT", |s| &s[2..], H160::zero()),
+            H160::zero()
+        );
+    }
+
+    #[test]
+    fn parse_env_or_parses_present_variable() {
+        env::set_var(
+            "CONFIG_OPTIONS_TEST_PRESENT",
+            "0x0000000000000000000000000000000000000001",
+        );
+        assert_eq!(
+            parse_env_with_or("CONFIG_OPTIONS_TEST_PRESENT", |s| &s[2..], H160::zero()),
+            H160::from_low_u64_be(1)
+        );
+
+        env::set_var("CONFIG_OPTIONS_TEST_PRESENT", "7");
+        assert_eq!(parse_env_or("CONFIG_OPTIONS_TEST_PRESENT", 42u32), 7);
+        assert_eq!(parse_env_opt("CONFIG_OPTIONS_TEST_PRESENT"), Some(7u32));
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_env_or_panics_on_invalid_value() {
+        env::set_var("CONFIG_OPTIONS_TEST_INVALID", "not a number");
+        parse_env_or("CONFIG_OPTIONS_TEST_INVALID", 42u32);
     }
 }
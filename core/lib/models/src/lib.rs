@@ -89,7 +89,7 @@ pub struct TxMeta {
     pub nonce: u32,
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct NetworkStatus {
     pub next_block_at_max: Option<u64>,
     pub last_committed: BlockNumber,
@@ -143,6 +143,83 @@ pub struct Operation {
     pub accounts_updated: AccountUpdates,
 }
 
+/// Builder of the `Operation`, mostly for the tests.
+///
+/// The block and the action are required, so `build` is only available once
+/// both of them are set:
+///
+/// ```ignore
+/// let operation = OperationBuilder::new()
+///     .block(block)
+///     .action(Action::Commit)
+///     .build();
+/// ```
+#[derive(Debug)]
+pub struct OperationBuilder<B, A> {
+    id: Option<i64>,
+    block: B,
+    action: A,
+    accounts_updated: AccountUpdates,
+}
+
+impl OperationBuilder<(), ()> {
+    pub fn new() -> Self {
+        Self {
+            id: None,
+            block: (),
+            action: (),
+            accounts_updated: Vec::new(),
+        }
+    }
+}
+
+impl Default for OperationBuilder<(), ()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B, A> OperationBuilder<B, A> {
+    pub fn block(self, block: Block) -> OperationBuilder<Block, A> {
+        OperationBuilder {
+            id: self.id,
+            block,
+            action: self.action,
+            accounts_updated: self.accounts_updated,
+        }
+    }
+
+    pub fn action(self, action: Action) -> OperationBuilder<B, Action> {
+        OperationBuilder {
+            id: self.id,
+            block: self.block,
+            action,
+            accounts_updated: self.accounts_updated,
+        }
+    }
+
+    pub fn id(mut self, id: i64) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn accounts_updated(mut self, accounts_updated: AccountUpdates) -> Self {
+        self.accounts_updated = accounts_updated;
+        self
+    }
+}
+
+impl OperationBuilder<Block, Action> {
+    pub fn build(self) -> Operation {
+        Operation {
+            id: self.id,
+            action: self.action,
+            block: self.block,
+            accounts_updated: self.accounts_updated,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum CommitRequest {
     PendingBlock(PendingBlock, oneshot::Sender<()>),
@@ -158,7 +235,7 @@ pub struct BlockCommitRequest {
 pub const ACTION_COMMIT: &str = "COMMIT";
 pub const ACTION_VERIFY: &str = "VERIFY";
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum ActionType {
     COMMIT,
     VERIFY,
@@ -2,7 +2,7 @@ use super::FranklinOp;
 use super::PriorityOp;
 use super::{AccountId, BlockNumber, Fr};
 use crate::franklin_crypto::bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
-use crate::node::SignedFranklinTx;
+use crate::node::{FranklinTx, SignedFranklinTx};
 use crate::params::CHUNK_BIT_WIDTH;
 use crate::serialization::*;
 use chrono::DateTime;
@@ -210,6 +210,20 @@ impl Block {
 
         withdrawals_data
     }
+
+    /// Returns `true` if the block contains fast withdrawals, so it should be proved
+    /// before the rest of the blocks awaiting for proof.
+    pub fn fast_processing_required(&self) -> bool {
+        self.block_transactions.iter().any(|block_tx| {
+            block_tx
+                .get_executed_tx()
+                .filter(|exec_tx| exec_tx.success)
+                .map_or(false, |exec_tx| match &exec_tx.signed_tx.tx {
+                    FranklinTx::Withdraw(tx) => tx.fast,
+                    _ => false,
+                })
+        })
+    }
 }
 
 // Get smallest block size given
@@ -34,11 +34,12 @@ impl From<&str> for TokenLike {
 }
 
 /// Token supported in zkSync protocol
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, utoipa::ToSchema)]
 pub struct Token {
     /// id is used for tx signature and serialization
     pub id: TokenId,
     /// Contract address of ERC20 token or Address::zero() for "ETH"
+    #[schema(value_type = String)]
     pub address: Address,
     /// Token symbol (e.g. "ETH" or "USDC")
     pub symbol: String,
@@ -7,7 +7,7 @@ use crate::franklin_crypto::bellman::pairing::ff::ScalarEngine;
 use crate::franklin_crypto::bellman::pairing::ff::{
     BitIterator, Field, PrimeField, PrimeFieldRepr,
 };
-use crate::franklin_crypto::bellman::pairing::{CurveAffine, Engine};
+use crate::franklin_crypto::bellman::pairing::{CurveAffine, EncodedPoint, Engine};
 use crate::franklin_crypto::jubjub::{edwards, JubjubEngine, Unknown};
 use bigdecimal::BigDecimal;
 use failure::bail;
@@ -156,6 +156,30 @@ pub fn serialize_fe_for_ethereum(field_element: &<Bn256 as ScalarEngine>::Fr) ->
     U256::from_big_endian(&be_bytes[..])
 }
 
+/// Inverse of `serialize_g1_for_ethereum`, fails if the coordinates are not a point of the curve.
+pub fn deserialize_g1_for_ethereum(
+    (x, y): (U256, U256),
+) -> Result<<Bn256 as Engine>::G1Affine, failure::Error> {
+    if x.is_zero() && y.is_zero() {
+        return Ok(<Bn256 as Engine>::G1Affine::zero());
+    }
+    let mut uncompressed = <<Bn256 as Engine>::G1Affine as CurveAffine>::Uncompressed::empty();
+    x.to_big_endian(&mut uncompressed.as_mut()[0..32]);
+    y.to_big_endian(&mut uncompressed.as_mut()[32..64]);
+    Ok(uncompressed.into_affine()?)
+}
+
+/// Inverse of `serialize_fe_for_ethereum`, fails if the value exceeds the field modulus.
+pub fn deserialize_fe_for_ethereum(
+    value: U256,
+) -> Result<<Bn256 as ScalarEngine>::Fr, failure::Error> {
+    let mut be_bytes = [0u8; 32];
+    value.to_big_endian(&mut be_bytes);
+    let mut repr = <<Bn256 as ScalarEngine>::Fr as PrimeField>::Repr::default();
+    repr.read_be(&be_bytes[..])?;
+    Ok(<Bn256 as ScalarEngine>::Fr::from_repr(repr)?)
+}
+
 pub fn unpack_edwards_point<E: JubjubEngine>(
     serialized: [u8; 32],
     params: &E::Params,
@@ -585,4 +609,22 @@ mod test {
         let out: Vec<bool> = BitIteratorLe::new(&test_vector).collect();
         assert_eq!(reference, out);
     }
+
+    #[test]
+    fn ethereum_serialization_round_trip() {
+        let fe = <<Bn256 as ScalarEngine>::Fr as PrimeField>::from_str("42").unwrap();
+        let encoded = serialize_fe_for_ethereum(&fe);
+        assert_eq!(deserialize_fe_for_ethereum(encoded).unwrap(), fe);
+        assert!(deserialize_fe_for_ethereum(U256::max_value()).is_err());
+
+        let point = <Bn256 as Engine>::G1Affine::one();
+        let encoded = serialize_g1_for_ethereum(&point);
+        assert_eq!(deserialize_g1_for_ethereum(encoded).unwrap(), point);
+        let zero = <Bn256 as Engine>::G1Affine::zero();
+        assert_eq!(
+            deserialize_g1_for_ethereum(serialize_g1_for_ethereum(&zero)).unwrap(),
+            zero
+        );
+        assert!(deserialize_g1_for_ethereum((U256::one(), U256::one())).is_err());
+    }
 }
@@ -1,7 +1,11 @@
+use crate::franklin_crypto::bellman::pairing::Engine as EngineTrait;
 use crate::franklin_crypto::bellman::Circuit;
 use crate::node::U256;
 use crate::node::{Engine, Fr};
-use crate::primitives::{serialize_fe_for_ethereum, serialize_g1_for_ethereum};
+use crate::primitives::{
+    deserialize_fe_for_ethereum, deserialize_g1_for_ethereum, serialize_fe_for_ethereum,
+    serialize_g1_for_ethereum,
+};
 use crate::prover_utils::fs_utils::{
     get_block_verification_key_path, get_exodus_verification_key_path,
 };
@@ -22,6 +26,8 @@ pub mod network_utils;
 
 pub const SETUP_MIN_POW2: u32 = 20;
 pub const SETUP_MAX_POW2: u32 = 26;
+/// Amount of the wires of the main circuit, determines the layout of `EncodedProofPlonk`.
+const STATE_WIDTH: usize = 4;
 
 pub struct PlonkVerificationKey(VerificationKey<Engine, PlonkCsWidth4WithNextStepParams>);
 
@@ -39,6 +45,13 @@ impl PlonkVerificationKey {
             VerificationKey::read(File::open(get_exodus_verification_key_path())?)?;
         Ok(Self(verification_key))
     }
+
+    /// Verifies the proof encoded for the contract. Returns `false` for the invalid proof
+    /// and fails if the proof can't be decoded, e.g. it's of the other circuit.
+    pub fn verify_encoded_proof(&self, proof: &EncodedProofPlonk) -> Result<bool, failure::Error> {
+        let proof = deserialize_proof(proof, self.0.n, self.0.num_inputs)?;
+        Ok(verify::<_, RollingKeccakTranscript<Fr>>(&proof, &self.0)?)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -47,6 +60,23 @@ pub struct EncodedProofPlonk {
     pub proof: Vec<U256>,
 }
 
+impl EncodedProofPlonk {
+    /// ABI encoding of the proof as the `uint256[] _proof` argument of the `verifyBlock` method
+    /// of the contract. Inputs are not encoded, since the contract derives them from the commitment
+    /// of the block.
+    pub fn to_abi_bytes(&self) -> Vec<u8> {
+        // Dynamic array is encoded as the offset of its data, its length and the elements.
+        let head = [U256::from(32), U256::from(self.proof.len())];
+        let mut encoded = Vec::with_capacity(32 * (head.len() + self.proof.len()));
+        let mut word = [0u8; 32];
+        for value in head.iter().chain(&self.proof) {
+            value.to_big_endian(&mut word);
+            encoded.extend_from_slice(&word);
+        }
+        encoded
+    }
+}
+
 impl Default for EncodedProofPlonk {
     fn default() -> Self {
         Self {
@@ -56,6 +86,19 @@ impl Default for EncodedProofPlonk {
     }
 }
 
+/// Statistics of the proof generation, reported by the prover along with the proof
+/// to tune the job scheduling. Every field is optional, since older provers don't report them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProvingStats {
+    /// Time spent on the proof generation, in seconds.
+    pub proving_time_secs: Option<f64>,
+    /// Peak resident memory of the prover process, in bytes.
+    pub peak_memory_bytes: Option<u64>,
+    pub worker_name: Option<String>,
+    pub prover_version: Option<String>,
+}
+
 pub struct SetupForStepByStepProver {
     setup_polynomials: SetupPolynomials<Engine, PlonkCsWidth4WithNextStepParams>,
     hints: Vec<(usize, TranspilationVariant)>,
@@ -84,12 +127,11 @@ impl SetupForStepByStepProver {
         })
     }
 
-    pub fn gen_step_by_step_proof_using_prepared_setup<C: Circuit<Engine> + Clone>(
+    fn gen_proof<C: Circuit<Engine> + Clone>(
         &self,
         circuit: C,
-        vk: &PlonkVerificationKey,
-    ) -> Result<EncodedProofPlonk, failure::Error> {
-        let proof = prove_by_steps::<_, _, RollingKeccakTranscript<Fr>>(
+    ) -> Result<Proof<Engine, PlonkCsWidth4WithNextStepParams>, failure::Error> {
+        Ok(prove_by_steps::<_, _, RollingKeccakTranscript<Fr>>(
             circuit,
             &self.hints,
             &self.setup_polynomials,
@@ -97,12 +139,30 @@ impl SetupForStepByStepProver {
             self.key_monomial_form
                 .as_ref()
                 .expect("Setup should have universal setup struct"),
-        )?;
+        )?)
+    }
+
+    pub fn gen_step_by_step_proof_using_prepared_setup<C: Circuit<Engine> + Clone>(
+        &self,
+        circuit: C,
+        vk: &PlonkVerificationKey,
+    ) -> Result<EncodedProofPlonk, failure::Error> {
+        let proof = self.gen_proof(circuit)?;
 
         let valid = verify::<_, RollingKeccakTranscript<Fr>>(&proof, &vk.0)?;
         failure::ensure!(valid, "proof for block is invalid");
         Ok(serialize_proof(&proof))
     }
+
+    /// Same as `gen_step_by_step_proof_using_prepared_setup`, but doesn't verify the generated proof.
+    /// Invalid proof is rejected by the contract only, so this should be used with the trusted setup.
+    pub fn gen_unverified_step_by_step_proof_using_prepared_setup<C: Circuit<Engine> + Clone>(
+        &self,
+        circuit: C,
+    ) -> Result<EncodedProofPlonk, failure::Error> {
+        let proof = self.gen_proof(circuit)?;
+        Ok(serialize_proof(&proof))
+    }
 }
 
 impl Drop for SetupForStepByStepProver {
@@ -203,6 +263,71 @@ pub fn serialize_proof(
     }
 }
 
+/// Inverse of `serialize_proof` for the proof of the circuit of the size `n` with `num_inputs` inputs.
+fn deserialize_proof(
+    encoded: &EncodedProofPlonk,
+    n: usize,
+    num_inputs: usize,
+) -> Result<Proof<Engine, PlonkCsWidth4WithNextStepParams>, failure::Error> {
+    // Commitments and openings take two values, evaluations take one.
+    let expected_len = 2 * (2 * STATE_WIDTH + 3) + 2 * STATE_WIDTH + 3;
+    failure::ensure!(
+        encoded.inputs.len() == num_inputs,
+        "proof has {} inputs, expected {}",
+        encoded.inputs.len(),
+        num_inputs
+    );
+    failure::ensure!(
+        encoded.proof.len() == expected_len,
+        "proof has {} values, expected {}",
+        encoded.proof.len(),
+        expected_len
+    );
+
+    // Length is checked, so the values are never exhausted.
+    fn next_g1(
+        values: &mut impl Iterator<Item = U256>,
+    ) -> Result<<Engine as EngineTrait>::G1Affine, failure::Error> {
+        let x = values.next().unwrap();
+        let y = values.next().unwrap();
+        deserialize_g1_for_ethereum((x, y))
+    }
+    fn next_fe(values: &mut impl Iterator<Item = U256>) -> Result<Fr, failure::Error> {
+        deserialize_fe_for_ethereum(values.next().unwrap())
+    }
+
+    let values = &mut encoded.proof.iter().cloned();
+    let mut proof = Proof::empty();
+    proof.n = n;
+    proof.num_inputs = num_inputs;
+    proof.input_values = encoded
+        .inputs
+        .iter()
+        .cloned()
+        .map(deserialize_fe_for_ethereum)
+        .collect::<Result<_, _>>()?;
+    proof.wire_commitments = (0..STATE_WIDTH)
+        .map(|_| next_g1(values))
+        .collect::<Result<_, _>>()?;
+    proof.grand_product_commitment = next_g1(values)?;
+    proof.quotient_poly_commitments = (0..STATE_WIDTH)
+        .map(|_| next_g1(values))
+        .collect::<Result<_, _>>()?;
+    proof.wire_values_at_z = (0..STATE_WIDTH)
+        .map(|_| next_fe(values))
+        .collect::<Result<_, _>>()?;
+    proof.wire_values_at_z_omega = vec![next_fe(values)?];
+    proof.grand_product_at_z_omega = next_fe(values)?;
+    proof.quotient_polynomial_at_z = next_fe(values)?;
+    proof.linearization_polynomial_at_z = next_fe(values)?;
+    proof.permutation_polynomials_at_z = (0..STATE_WIDTH - 1)
+        .map(|_| next_fe(values))
+        .collect::<Result<_, _>>()?;
+    proof.opening_at_z_proof = next_g1(values)?;
+    proof.opening_at_z_omega_proof = next_g1(values)?;
+    Ok(proof)
+}
+
 /// Reads universal setup from disk or downloads from network.
 pub fn get_universal_setup_monomial_form(
     power_of_two: u32,
@@ -251,3 +376,42 @@ impl UniversalSetupCache {
 lazy_static! {
     static ref UNIVERSAL_SETUP_CACHE: UniversalSetupCache = UniversalSetupCache::new();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_proof_round_trip() {
+        let encoded = EncodedProofPlonk::default();
+        let proof = deserialize_proof(&encoded, 1, 1).expect("failed to decode proof");
+        assert_eq!(serialize_proof(&proof), encoded);
+
+        // Proof of the other circuit can't be decoded.
+        assert!(deserialize_proof(&encoded, 1, 2).is_err());
+        let truncated = EncodedProofPlonk {
+            proof: encoded.proof[1..].to_vec(),
+            ..encoded.clone()
+        };
+        assert!(deserialize_proof(&truncated, 1, 1).is_err());
+    }
+
+    #[test]
+    fn encoded_proof_abi_encoding() {
+        let mut proof = EncodedProofPlonk::default();
+        proof.proof[0] = 1.into();
+        proof.proof[32] = U256::max_value();
+        let tokens = proof.proof.iter().cloned().map(ethabi::Token::Uint);
+        let reference = ethabi::encode(&[ethabi::Token::Array(tokens.collect())]);
+        assert_eq!(proof.to_abi_bytes(), reference);
+
+        let empty = EncodedProofPlonk {
+            inputs: Vec::new(),
+            proof: Vec::new(),
+        };
+        assert_eq!(
+            empty.to_abi_bytes(),
+            ethabi::encode(&[ethabi::Token::Array(Vec::new())])
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Tests of loading the configuration options from the TOML file.
+
+// Built-in deps
+use std::{env, fs, path::PathBuf};
+// Workspace deps
+use models::config_options::{ConfigError, ConfigurationOptions};
+
+/// Environment variables used by `ConfigurationOptions::from_env`.
+const CONFIG_ENV_VARS: &[&str] = &[
+    "REST_API_BIND",
+    "HTTP_RPC_API_BIND",
+    "WS_API_BIND",
+    "WEB3_URL",
+    "WEB3_URL_FAILURES_THRESHOLD",
+    "WEB3_URL_QUARANTINE_SECS",
+    "GENESIS_TX_HASH",
+    "CONTRACT_ADDR",
+    "GOVERNANCE_ADDR",
+    "OPERATOR_COMMIT_ETH_ADDRESS",
+    "OPERATOR_FEE_ETH_ADDRESS",
+    "OPERATOR_PRIVATE_KEY",
+    "CHAIN_ID",
+    "GAS_PRICE_FACTOR",
+    "PROVER_SERVER_BIND",
+    "CONFIRMATIONS_FOR_ETH_EVENT",
+    "API_REQUESTS_CACHES_SIZE",
+    "BLOCK_CHUNK_SIZES",
+    "MAX_NUMBER_OF_WITHDRAWALS_PER_BLOCK",
+    "ETH_WATCH_POLL_INTERVAL",
+    "ETH_NETWORK",
+    "IDLE_PROVERS",
+    "MINIBLOCK_ITERATION_INTERVAL",
+    "MINIBLOCKS_ITERATIONS",
+    "FAST_BLOCK_MINIBLOCKS_ITERATIONS",
+    "PROMETHEUS_EXPORT_PORT",
+    "TOKEN_PRICE_SOURCE",
+    "COINMARKETCAP_BASE_URL",
+    "COINGECKO_BASE_URL",
+    "WITNESS_GENERATORS",
+    "TICKER_FAST_PROCESSING_COEFF",
+    "SUBSCRIPTIONS_PER_CONN_PER_SEC",
+    "MAX_SUBSCRIPTIONS_PER_CONN",
+    "WS_MAX_CONNECTIONS",
+    "WS_MAX_CONNECTIONS_PER_IP",
+    "WS_BEHIND_PROXY",
+    "WS_REPLAY_BUFFER_SIZE",
+    "PROVER_SERVER_VERIFY_PROOFS",
+    "PROVER_SERVER_MAX_BLOCK_ATTEMPTS",
+    "PROVER_SERVER_RATE_LIMIT",
+    "PROVER_SERVER_RATE_LIMIT_BURST",
+];
+
+/// Builds the TOML document with the values of the currently set environment variables.
+fn toml_from_env() -> toml::value::Table {
+    let mut table = toml::value::Table::new();
+    for name in CONFIG_ENV_VARS {
+        if let Ok(value) = env::var(name) {
+            table.insert(name.to_lowercase(), toml::Value::String(value));
+        }
+    }
+    table
+}
+
+fn write_config(name: &str, table: &toml::value::Table) -> PathBuf {
+    let path = env::temp_dir().join(format!("{}_{}.toml", name, std::process::id()));
+    let contents = toml::to_string(table).expect("failed to serialize config");
+    fs::write(&path, contents).expect("failed to write config");
+    path
+}
+
+/// `ConfigurationOptions` doesn't implement `PartialEq`, so the debug representations are compared.
+fn assert_same_options(lhs: &ConfigurationOptions, rhs: &ConfigurationOptions) {
+    assert_eq!(format!("{:?}", lhs), format!("{:?}", rhs));
+}
+
+#[test]
+fn toml_config_matches_env_config() {
+    let path = write_config("config_from_env", &toml_from_env());
+    let from_toml = ConfigurationOptions::from_toml(&path).expect("failed to load config");
+    fs::remove_file(path).unwrap();
+
+    assert_same_options(&from_toml, &ConfigurationOptions::from_env());
+}
+
+#[test]
+fn toml_config_accepts_native_types() {
+    let mut table = toml_from_env();
+    let block_chunk_sizes = env::var("BLOCK_CHUNK_SIZES")
+        .unwrap()
+        .split(',')
+        .map(|size| toml::Value::Integer(size.parse().unwrap()))
+        .collect();
+    table.insert(
+        "block_chunk_sizes".to_string(),
+        toml::Value::Array(block_chunk_sizes),
+    );
+    let chain_id = env::var("CHAIN_ID").unwrap().parse().unwrap();
+    table.insert("chain_id".to_string(), toml::Value::Integer(chain_id));
+
+    let path = write_config("config_native_types", &table);
+    let from_toml = ConfigurationOptions::from_toml(&path).expect("failed to load config");
+    fs::remove_file(path).unwrap();
+
+    assert_same_options(&from_toml, &ConfigurationOptions::from_env());
+}
+
+#[test]
+fn toml_config_accepts_several_web3_urls() {
+    let urls = vec!["http://127.0.0.1:8545", "http://127.0.0.1:8546"];
+    let mut table = toml_from_env();
+    table.insert(
+        "web3_url".to_string(),
+        toml::Value::Array(urls.iter().map(|&url| url.into()).collect()),
+    );
+
+    let path = write_config("config_web3_urls", &table);
+    let from_toml = ConfigurationOptions::from_toml(&path).expect("failed to load config");
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(from_toml.web3_urls, urls);
+    assert_eq!(from_toml.web3_url(), urls[0]);
+}
+
+#[test]
+fn toml_config_reports_missing_key() {
+    let mut table = toml_from_env();
+    table.remove("web3_url");
+
+    let path = write_config("config_missing_key", &table);
+    let err = ConfigurationOptions::from_toml(&path).expect_err("config must be invalid");
+    fs::remove_file(path).unwrap();
+
+    match err {
+        ConfigError::MissingKey(key) => assert_eq!(key, "web3_url"),
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn toml_config_reports_invalid_value() {
+    let mut table = toml_from_env();
+    table.insert(
+        "chain_id".to_string(),
+        toml::Value::String("not a number".to_string()),
+    );
+
+    let path = write_config("config_invalid_value", &table);
+    let err = ConfigurationOptions::from_toml(&path).expect_err("config must be invalid");
+    fs::remove_file(path).unwrap();
+
+    match err {
+        ConfigError::InvalidValue { key, value, .. } => {
+            assert_eq!(key, "chain_id");
+            assert_eq!(value, "not a number");
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
@@ -0,0 +1,2 @@
+ALTER TABLE active_provers DROP COLUMN block_sizes;
+ALTER TABLE active_provers ADD COLUMN block_size BIGINT NOT NULL DEFAULT 0;
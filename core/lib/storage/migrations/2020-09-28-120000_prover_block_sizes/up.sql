@@ -0,0 +1,4 @@
+-- Block sizes the prover is able to prove, reported on registration.
+-- Replaces the single block size, which was never actually used.
+ALTER TABLE active_provers DROP COLUMN block_size;
+ALTER TABLE active_provers ADD COLUMN block_sizes BIGINT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1 @@
+ALTER TABLE prover_runs DROP COLUMN extended_deadline;
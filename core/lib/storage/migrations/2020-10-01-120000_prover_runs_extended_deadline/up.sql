@@ -0,0 +1,3 @@
+-- Deadline until which the job is not reassigned to another prover, even without heartbeats.
+-- Extended by the provers whose proof generation takes longer than the prover timeout.
+ALTER TABLE prover_runs ADD COLUMN extended_deadline TIMESTAMP with time zone;
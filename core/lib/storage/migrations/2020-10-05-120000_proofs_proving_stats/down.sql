@@ -0,0 +1,4 @@
+ALTER TABLE proofs DROP COLUMN prover_version;
+ALTER TABLE proofs DROP COLUMN worker;
+ALTER TABLE proofs DROP COLUMN peak_memory_bytes;
+ALTER TABLE proofs DROP COLUMN proving_time_secs;
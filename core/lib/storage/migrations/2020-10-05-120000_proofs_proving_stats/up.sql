@@ -0,0 +1,6 @@
+-- Statistics of the proof generation reported by the prover, used to tune the job scheduling.
+-- Nullable, since older provers don't report them.
+ALTER TABLE proofs ADD COLUMN proving_time_secs DOUBLE PRECISION;
+ALTER TABLE proofs ADD COLUMN peak_memory_bytes BIGINT;
+ALTER TABLE proofs ADD COLUMN worker TEXT;
+ALTER TABLE proofs ADD COLUMN prover_version TEXT;
@@ -0,0 +1,10 @@
+-- Failures of the block proving jobs reported by the provers.
+-- Failed job is removed from `prover_runs`, so its block is reassigned right away.
+CREATE TABLE prover_job_failures (
+    id serial PRIMARY KEY,
+    prover_run_id INTEGER NOT NULL,
+    block_number BIGINT NOT NULL,
+    worker TEXT,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMP with time zone NOT NULL DEFAULT now()
+);
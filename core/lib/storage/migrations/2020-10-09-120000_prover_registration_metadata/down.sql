@@ -0,0 +1,3 @@
+ALTER TABLE active_provers DROP COLUMN cpu_cores;
+ALTER TABLE active_provers DROP COLUMN version;
+ALTER TABLE active_provers DROP COLUMN hostname;
@@ -0,0 +1,5 @@
+-- Metadata reported by the prover on registration, to tell which machine and build holds the jobs.
+-- Provers of the older versions don't report it, so the columns are nullable.
+ALTER TABLE active_provers ADD COLUMN hostname TEXT;
+ALTER TABLE active_provers ADD COLUMN version TEXT;
+ALTER TABLE active_provers ADD COLUMN cpu_cores INTEGER;
@@ -0,0 +1,3 @@
+-- Hash of the verification keys the prover is registered with, to tell the provers with the stale keys.
+-- Provers of the older versions don't report it, so the column is nullable.
+ALTER TABLE active_provers ADD COLUMN vk_hash TEXT;
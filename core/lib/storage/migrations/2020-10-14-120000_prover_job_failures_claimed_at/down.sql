@@ -0,0 +1 @@
+ALTER TABLE prover_job_failures DROP COLUMN claimed_at;
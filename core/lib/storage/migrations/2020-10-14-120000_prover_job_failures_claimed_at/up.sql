@@ -0,0 +1,3 @@
+-- Moment the failed job was assigned to the prover, to show the history of the block proving attempts.
+-- Failures recorded before don't have it, so the column is nullable.
+ALTER TABLE prover_job_failures ADD COLUMN claimed_at TIMESTAMP with time zone;
@@ -0,0 +1,2 @@
+-- Blocks with fast withdrawals are proved before the rest of the blocks awaiting for proof.
+ALTER TABLE block_witness ADD COLUMN fast_processing BOOLEAN NOT NULL DEFAULT false;
@@ -27,19 +27,22 @@ pub struct StoragePendingBlock {
     pub pending_block_iteration: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, FromRow, PartialEq, Clone, utoipa::ToSchema)]
 pub struct BlockDetails {
     pub block_number: i64,
 
     #[serde(with = "BytesToHexSerde::<SyncBlockPrefix>")]
+    #[schema(value_type = String)]
     pub new_state_root: Vec<u8>,
 
     pub block_size: i64,
 
     #[serde(with = "OptionBytesToHexSerde::<ZeroxPrefix>")]
+    #[schema(value_type = Option<String>)]
     pub commit_tx_hash: Option<Vec<u8>>,
 
     #[serde(with = "OptionBytesToHexSerde::<ZeroxPrefix>")]
+    #[schema(value_type = Option<String>)]
     pub verify_tx_hash: Option<Vec<u8>>,
 
     pub committed_at: DateTime<Utc>,
@@ -47,10 +50,11 @@ pub struct BlockDetails {
     pub verified_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Serialize, Deserialize, FromRow, utoipa::ToSchema)]
 pub struct BlockTransactionItem {
     pub tx_hash: String,
     pub block_number: i64,
+    #[schema(value_type = Object)]
     pub op: Value,
     pub created_at: DateTime<Utc>,
 }
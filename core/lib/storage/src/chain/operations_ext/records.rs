@@ -17,12 +17,13 @@ pub struct AccountTransaction {
     pub verified: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, FromRow, PartialEq, utoipa::ToSchema)]
 pub struct TransactionsHistoryItem {
     pub tx_id: String,
     pub hash: Option<String>,
     pub eth_block: Option<i64>,
     pub pq_id: Option<i64>,
+    #[schema(value_type = Object)]
     pub tx: Value,
     pub success: Option<bool>,
     pub fail_reason: Option<String>,
@@ -31,7 +32,7 @@ pub struct TransactionsHistoryItem {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct TxReceiptResponse {
     pub tx_hash: String,
     pub block_number: i64,
@@ -42,14 +43,14 @@ pub struct TxReceiptResponse {
 }
 
 // TODO: jazzandrock add more info(?)
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, utoipa::ToSchema)]
 pub struct PriorityOpReceiptResponse {
     pub committed: bool,
     pub verified: bool,
     pub prover_run: Option<ProverRun>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct TxByHashResponse {
     pub tx_type: String, // all
     pub from: String,    // transfer(from) | deposit(our contract) | withdraw(sender)
@@ -61,5 +62,6 @@ pub struct TxByHashResponse {
     pub nonce: i64,          // all txs
     pub created_at: String,
     pub fail_reason: Option<String>,
+    #[schema(value_type = Object)]
     pub tx: Value,
 }
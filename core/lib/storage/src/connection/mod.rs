@@ -73,6 +73,12 @@ impl ConnectionPool {
         self.access_storage().await
     }
 
+    /// Closes the pool: the new connections are refused and the active ones are closed
+    /// once returned to the pool, e.g. to check the handling of the database outages.
+    pub async fn close(&self) {
+        self.pool.close().await
+    }
+
     /// Obtains the database URL from the environment variable.
     fn get_database_url() -> String {
         env::var("DATABASE_URL").expect("DATABASE_URL must be set")
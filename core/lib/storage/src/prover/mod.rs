@@ -1,12 +1,15 @@
 // Built-in deps
 use std::time;
 // External imports
+use chrono::{DateTime, Utc};
 use sqlx::Done;
 // Workspace imports
 use models::node::BlockNumber;
-use models::prover_utils::EncodedProofPlonk;
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
 // Local imports
-use self::records::{ActiveProver, ProverRun, StoredProof};
+use self::records::{
+    ActiveProver, ProverJobFailure, ProverMetadata, ProverRun, ProvingQueueEntry, StoredProof,
+};
 use crate::prover::records::StorageBlockWitness;
 use crate::{chain::block::BlockSchema, QueryResult, StorageProcessor};
 
@@ -99,17 +102,42 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
     }
 
     /// Given the block size, chooses the next block to prove for the certain prover.
-    /// Returns `None` if either there are no blocks of given size to prove, or
-    /// there is already an ongoing job for non-proved block.
+    /// Returns `None` if either there are no blocks of given size to prove,
+    /// there is already an ongoing job for non-proved block, or the prover
+    /// hasn't reported the block size as supported on registration.
+    /// Provers registered without the block sizes can prove the blocks of any size.
     pub async fn prover_run_for_next_commit(
         &mut self,
         worker_: &str,
-        _prover_timeout: time::Duration,
+        prover_timeout: time::Duration,
         block_size: usize,
+    ) -> QueryResult<Option<ProverRun>> {
+        self.prover_run_for_next_commit_with_max_attempts(worker_, prover_timeout, block_size, None)
+            .await
+    }
+
+    /// Same as `prover_run_for_next_commit`, but the block is not assigned to the prover
+    /// which has already failed or timed out on it `max_attempts` times, so the prover
+    /// stuck on the block doesn't keep the other provers from proving it.
+    pub async fn prover_run_for_next_commit_with_max_attempts(
+        &mut self,
+        worker_: &str,
+        prover_timeout: time::Duration,
+        block_size: usize,
+        max_attempts: Option<usize>,
     ) -> QueryResult<Option<ProverRun>> {
         // Select the block to prove.
         let mut transaction = self.0.start_transaction().await?;
 
+        let supported_block_sizes = ProverSchema(&mut transaction)
+            .prover_block_sizes(worker_)
+            .await?
+            .unwrap_or_default();
+        if !supported_block_sizes.is_empty() && !supported_block_sizes.contains(&block_size) {
+            transaction.commit().await?;
+            return Ok(None);
+        }
+
         sqlx::query!("LOCK TABLE prover_runs IN EXCLUSIVE MODE")
             .execute(transaction.conn())
             .await?;
@@ -117,10 +145,14 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         // Find the block that satisfies the following criteria:
         // - Block number is greater than the index of last verified block.
         // - There is no proof for block.
-        // - Either there is no ongoing job for the block, or the job exceeded the timeout.
-        // Return the index of such a block.
-
-        // TODO: Prover gone interval is hard-coded. Is it critical?
+        // - Either there is no ongoing job for the block, or the job exceeded the timeout
+        //   and its lease is not extended.
+        // - The prover has failed or timed out on the block less than `max_attempts` times.
+        //   Failed jobs are moved to `prover_job_failures`, and the rest of the jobs
+        //   of the block are timed out, since there is no ongoing job.
+        // Return the index of such a block, preferring the ones marked for the fast processing
+        // and the oldest one otherwise.
+        let max_attempts = max_attempts.map_or(i64::MAX, |attempts| attempts as i64);
         let job = sqlx::query!(
             r#"
                 WITH unsized_blocks AS (
@@ -132,17 +164,29 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
                             (SELECT * FROM proofs WHERE block_number = o.block_number)
                         AND NOT EXISTS
                             (SELECT * FROM prover_runs
-                                WHERE block_number = o.block_number AND (now() - updated_at) < interval '120 seconds')
+                                WHERE block_number = o.block_number
+                                    AND (updated_at > now() - make_interval(secs => $2) OR extended_deadline > now()))
+                        AND (SELECT count(*) FROM prover_runs
+                                WHERE block_number = o.block_number AND worker = $3)
+                            + (SELECT count(*) FROM prover_job_failures
+                                WHERE block_number = o.block_number AND worker = $3) < $4
                 )
-                SELECT min(block_number) FROM unsized_blocks
+                SELECT unsized_blocks.block_number AS "block_number!" FROM unsized_blocks
                 INNER JOIN blocks
                     ON unsized_blocks.block_number = blocks.number AND blocks.block_size = $1
+                LEFT JOIN block_witness
+                    ON unsized_blocks.block_number = block_witness.block
+                ORDER BY COALESCE(block_witness.fast_processing, false) DESC, unsized_blocks.block_number
+                LIMIT 1
             "#,
-            block_size as i64
+            block_size as i64,
+            prover_timeout.as_secs_f64(),
+            worker_,
+            max_attempts
             )
-            .fetch_one(transaction.conn())
+            .fetch_optional(transaction.conn())
             .await?
-            .min;
+            .map(|row| row.block_number);
 
         // If there is a block to prove, create a job and store it
         // in the `prover_runs` table; otherwise do nothing and return `None`.
@@ -192,14 +236,54 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(())
     }
 
-    /// Adds a prover to the database.
-    pub async fn register_prover(&mut self, worker_: &str, block_size_: usize) -> QueryResult<i32> {
+    /// Extends the lease of the ongoing prover job, so it's not reassigned to another prover
+    /// for the `extra` time even if it exceeds the timeout.
+    pub async fn extend_prover_run_lease(
+        &mut self,
+        job_id: i32,
+        extra: time::Duration,
+    ) -> QueryResult<()> {
+        sqlx::query!(
+            "UPDATE prover_runs
+            SET updated_at = now(), extended_deadline = now() + make_interval(secs => $2)
+            WHERE id = $1",
+            job_id,
+            extra.as_secs_f64()
+        )
+        .execute(self.0.conn())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Adds a prover able to prove the blocks of the given sizes to the database.
+    pub async fn register_prover(
+        &mut self,
+        worker_: &str,
+        block_sizes: &[usize],
+    ) -> QueryResult<i32> {
+        self.register_prover_with_metadata(worker_, block_sizes, &ProverMetadata::default())
+            .await
+    }
+
+    /// Same as `register_prover`, but also stores the metadata reported by the prover.
+    pub async fn register_prover_with_metadata(
+        &mut self,
+        worker_: &str,
+        block_sizes: &[usize],
+        metadata: &ProverMetadata,
+    ) -> QueryResult<i32> {
+        let block_sizes: Vec<i64> = block_sizes.iter().map(|size| *size as i64).collect();
         let inserted_id = sqlx::query!(
-            "INSERT INTO active_provers (worker, block_size)
-            VALUES ($1, $2)
+            "INSERT INTO active_provers (worker, block_sizes, hostname, version, cpu_cores, vk_hash)
+            VALUES ($1, $2, $3, $4, $5, $6)
             RETURNING id",
             worker_.to_string(),
-            block_size_ as i64
+            &block_sizes,
+            metadata.hostname,
+            metadata.version,
+            metadata.cpu_cores,
+            metadata.vk_hash
         )
         .fetch_one(self.0.conn())
         .await?
@@ -208,6 +292,29 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(inserted_id)
     }
 
+    /// Returns the block sizes supported by the latest running prover with the given worker name,
+    /// or `None` if there is no such prover.
+    pub async fn prover_block_sizes(&mut self, worker_: &str) -> QueryResult<Option<Vec<usize>>> {
+        let block_sizes = sqlx::query!(
+            "SELECT block_sizes FROM active_provers
+            WHERE worker = $1 AND stopped_at IS NULL
+            ORDER BY id DESC
+            LIMIT 1",
+            worker_
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|prover| {
+            prover
+                .block_sizes
+                .into_iter()
+                .map(|size| size as usize)
+                .collect()
+        });
+
+        Ok(block_sizes)
+    }
+
     /// Gets a prover descriptor by its numeric ID.
     pub async fn prover_by_id(&mut self, prover_id: i32) -> QueryResult<ActiveProver> {
         let prover = sqlx::query_as!(
@@ -221,6 +328,35 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(prover)
     }
 
+    /// Loads all the registered provers, including the stopped ones, ordered by ID.
+    pub async fn load_provers(&mut self) -> QueryResult<Vec<ActiveProver>> {
+        let provers = sqlx::query_as!(ActiveProver, "SELECT * FROM active_provers ORDER BY id")
+            .fetch_all(self.0.conn())
+            .await?;
+
+        Ok(provers)
+    }
+
+    /// Loads the jobs being proved: the jobs without the proof which got the heartbeat within
+    /// the `prover_timeout` or have the extended lease. Ordered by ID.
+    pub async fn ongoing_prover_runs(
+        &mut self,
+        prover_timeout: time::Duration,
+    ) -> QueryResult<Vec<ProverRun>> {
+        let runs = sqlx::query_as!(
+            ProverRun,
+            "SELECT * FROM prover_runs
+            WHERE NOT EXISTS (SELECT * FROM proofs WHERE block_number = prover_runs.block_number)
+                AND (updated_at > now() - make_interval(secs => $1) OR extended_deadline > now())
+            ORDER BY id",
+            prover_timeout.as_secs_f64()
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        Ok(runs)
+    }
+
     /// Marks the prover as stopped.
     pub async fn record_prover_stop(&mut self, prover_id: i32) -> QueryResult<()> {
         // FIXME(popzxc): It seems that it isn't actually checked if the prover has been stopped
@@ -238,17 +374,152 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(())
     }
 
+    /// Records the failure of the prover job and removes the job, so its block is assigned
+    /// to another prover right away instead of waiting for the prover timeout.
+    /// Failure of the already removed job is ignored.
+    pub async fn record_prover_job_failure(
+        &mut self,
+        job_id: i32,
+        reason: &str,
+    ) -> QueryResult<()> {
+        let mut transaction = self.0.start_transaction().await?;
+
+        let removed_run = sqlx::query!(
+            "DELETE FROM prover_runs WHERE id = $1
+            RETURNING block_number, worker, created_at",
+            job_id
+        )
+        .fetch_optional(transaction.conn())
+        .await?;
+        if let Some(run) = removed_run {
+            sqlx::query!(
+                "INSERT INTO prover_job_failures (prover_run_id, block_number, worker, reason, claimed_at)
+                VALUES ($1, $2, $3, $4, $5)",
+                job_id,
+                run.block_number,
+                run.worker,
+                reason,
+                run.created_at
+            )
+            .execute(transaction.conn())
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Removes the jobs of the block, so it's assigned to the next prover asking for a job
+    /// instead of waiting for the prover timeout. Returns the amount of removed jobs.
+    pub async fn requeue_block(&mut self, block_number: BlockNumber) -> QueryResult<u64> {
+        let removed_runs = sqlx::query!(
+            "DELETE FROM prover_runs WHERE block_number = $1",
+            i64::from(block_number),
+        )
+        .execute(self.0.conn())
+        .await?
+        .rows_affected();
+
+        Ok(removed_runs)
+    }
+
+    /// Gets the failures of the prover jobs reported for a block, oldest first.
+    pub async fn load_prover_job_failures(
+        &mut self,
+        block_number: BlockNumber,
+    ) -> QueryResult<Vec<ProverJobFailure>> {
+        let failures = sqlx::query_as!(
+            ProverJobFailure,
+            "SELECT * FROM prover_job_failures WHERE block_number = $1 ORDER BY id",
+            i64::from(block_number),
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        Ok(failures)
+    }
+
+    /// Loads the jobs of the blocks which are not proved yet, both ongoing and timed out,
+    /// ordered by ID. Failed jobs are not included, see `unproved_prover_job_failures`.
+    pub async fn unproved_prover_runs(&mut self) -> QueryResult<Vec<ProverRun>> {
+        let runs = sqlx::query_as!(
+            ProverRun,
+            "SELECT * FROM prover_runs
+            WHERE NOT EXISTS (SELECT * FROM proofs WHERE block_number = prover_runs.block_number)
+            ORDER BY id"
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        Ok(runs)
+    }
+
+    /// Loads the failures of the jobs reported for the blocks which are not proved yet,
+    /// ordered by ID.
+    pub async fn unproved_prover_job_failures(&mut self) -> QueryResult<Vec<ProverJobFailure>> {
+        let failures = sqlx::query_as!(
+            ProverJobFailure,
+            "SELECT * FROM prover_job_failures
+            WHERE NOT EXISTS (SELECT * FROM proofs WHERE block_number = prover_job_failures.block_number)
+            ORDER BY id"
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        Ok(failures)
+    }
+
+    /// Loads the blocks awaiting for proof in the order they're assigned to the provers:
+    /// blocks marked for the fast processing first, then the oldest ones.
+    /// Blocks with ongoing jobs are included as well.
+    pub async fn proving_queue(&mut self) -> QueryResult<Vec<ProvingQueueEntry>> {
+        let queue = sqlx::query_as!(
+            ProvingQueueEntry,
+            r#"
+                SELECT o.block_number, COALESCE(block_witness.fast_processing, false) AS "fast_processing!"
+                FROM operations o
+                LEFT JOIN block_witness ON o.block_number = block_witness.block
+                WHERE o.action_type = 'COMMIT'
+                    AND o.block_number >
+                        (SELECT COALESCE(max(block_number),0) FROM operations WHERE action_type = 'VERIFY')
+                    AND NOT EXISTS
+                        (SELECT * FROM proofs WHERE block_number = o.block_number)
+                ORDER BY COALESCE(block_witness.fast_processing, false) DESC, o.block_number
+            "#
+        )
+        .fetch_all(self.0.conn())
+        .await?;
+
+        Ok(queue)
+    }
+
     /// Stores the proof for a block.
     pub async fn store_proof(
         &mut self,
         block_number: BlockNumber,
         proof: &EncodedProofPlonk,
+    ) -> QueryResult<usize> {
+        self.store_proof_with_stats(block_number, proof, &ProvingStats::default())
+            .await
+    }
+
+    /// Stores the proof for a block along with the statistics of its generation.
+    pub async fn store_proof_with_stats(
+        &mut self,
+        block_number: BlockNumber,
+        proof: &EncodedProofPlonk,
+        stats: &ProvingStats,
     ) -> QueryResult<usize> {
         let updated_rows = sqlx::query!(
-            "INSERT INTO proofs (block_number, proof)
-            VALUES ($1, $2)",
+            "INSERT INTO proofs (block_number, proof, proving_time_secs, peak_memory_bytes, worker, prover_version)
+            VALUES ($1, $2, $3, $4, $5, $6)",
             i64::from(block_number),
-            serde_json::to_value(proof).unwrap()
+            serde_json::to_value(proof).unwrap(),
+            stats.proving_time_secs,
+            stats.peak_memory_bytes.map(|bytes| bytes as i64),
+            stats.worker_name,
+            stats.prover_version,
         )
         .execute(self.0.conn())
         .await?
@@ -274,20 +545,73 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(proof)
     }
 
+    /// Gets the statistics of the proof generation reported for a block.
+    /// Returns `None` if there is no proof for the block.
+    pub async fn load_proof_stats(
+        &mut self,
+        block_number: BlockNumber,
+    ) -> QueryResult<Option<ProvingStats>> {
+        let stats = sqlx::query_as!(
+            StoredProof,
+            "SELECT * FROM proofs WHERE block_number = $1",
+            i64::from(block_number),
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|stored| ProvingStats {
+            proving_time_secs: stored.proving_time_secs,
+            peak_memory_bytes: stored.peak_memory_bytes.map(|bytes| bytes as u64),
+            worker_name: stored.worker,
+            prover_version: stored.prover_version,
+        });
+
+        Ok(stats)
+    }
+
+    /// Gets the time the proof of a block was stored at.
+    /// Returns `None` if there is no proof for the block.
+    pub async fn load_proof_created_at(
+        &mut self,
+        block_number: BlockNumber,
+    ) -> QueryResult<Option<DateTime<Utc>>> {
+        let created_at = sqlx::query_as!(
+            StoredProof,
+            "SELECT * FROM proofs WHERE block_number = $1",
+            i64::from(block_number),
+        )
+        .fetch_optional(self.0.conn())
+        .await?
+        .map(|stored| stored.created_at);
+
+        Ok(created_at)
+    }
+
     /// Stores witness for a block
     pub async fn store_witness(
         &mut self,
         block: BlockNumber,
         witness: serde_json::Value,
+    ) -> QueryResult<()> {
+        self.store_witness_with_priority(block, witness, false).await
+    }
+
+    /// Same as `store_witness`, but also marks whether the block should be proved
+    /// before the rest of the blocks, see `Block::fast_processing_required`.
+    pub async fn store_witness_with_priority(
+        &mut self,
+        block: BlockNumber,
+        witness: serde_json::Value,
+        fast_processing: bool,
     ) -> QueryResult<()> {
         let witness_str = serde_json::to_string(&witness).expect("Failed to serialize witness");
         sqlx::query!(
-            "INSERT INTO block_witness (block, witness)
-            VALUES ($1, $2)
+            "INSERT INTO block_witness (block, witness, fast_processing)
+            VALUES ($1, $2, $3)
             ON CONFLICT (block)
             DO NOTHING",
             i64::from(block),
-            witness_str
+            witness_str,
+            fast_processing
         )
         .execute(self.0.conn())
         .await?;
@@ -311,4 +635,17 @@ impl<'a, 'c> ProverSchema<'a, 'c> {
         Ok(block_witness
             .map(|w| serde_json::from_str(&w.witness).expect("Failed to deserialize witness")))
     }
+
+    /// Removes the stored witness for a block, e.g. the one generated for the block
+    /// which was committed again with a different root.
+    pub async fn remove_witness(&mut self, block: BlockNumber) -> QueryResult<()> {
+        sqlx::query!(
+            "DELETE FROM block_witness WHERE block = $1",
+            i64::from(block)
+        )
+        .execute(self.0.conn())
+        .await?;
+
+        Ok(())
+    }
 }
@@ -11,7 +11,25 @@ pub struct ActiveProver {
     pub worker: String,
     pub created_at: DateTime<Utc>,
     pub stopped_at: Option<DateTime<Utc>>,
-    pub block_size: i64,
+    /// Block sizes the prover is able to prove.
+    pub block_sizes: Vec<i64>,
+    /// Host the prover runs on, `None` if it's not reported by the prover.
+    pub hostname: Option<String>,
+    /// Version of the prover build, `None` if it's not reported by the prover.
+    pub version: Option<String>,
+    /// Amount of CPU cores of the prover machine, `None` if it's not reported by the prover.
+    pub cpu_cores: Option<i32>,
+    /// Hex-encoded hash of the verification keys of the prover, `None` if it's not reported by the prover.
+    pub vk_hash: Option<String>,
+}
+
+/// Metadata reported by the prover on registration, all of it is optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProverMetadata {
+    pub hostname: Option<String>,
+    pub version: Option<String>,
+    pub cpu_cores: Option<i32>,
+    pub vk_hash: Option<String>,
 }
 
 #[derive(Debug, FromRow)]
@@ -25,16 +43,37 @@ pub struct StoredProof {
     pub block_number: i64,
     pub proof: serde_json::Value,
     pub created_at: DateTime<Utc>,
+    /// Statistics of the proof generation, see `models::prover_utils::ProvingStats`.
+    pub proving_time_secs: Option<f64>,
+    pub peak_memory_bytes: Option<i64>,
+    pub worker: Option<String>,
+    pub prover_version: Option<String>,
+}
+
+/// Failure of the prover job reported by the prover, see `ProverSchema::record_prover_job_failure`.
+#[derive(Debug, Clone, FromRow)]
+pub struct ProverJobFailure {
+    pub id: i32,
+    pub prover_run_id: i32,
+    pub block_number: i64,
+    pub worker: Option<String>,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+    /// Moment the failed job was assigned to the prover, `None` for the failures recorded before
+    /// it was tracked.
+    pub claimed_at: Option<DateTime<Utc>>,
 }
 
 // Every time before a prover worker starts generating the proof, a prover run is recorded for monitoring purposes
-#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ProverRun {
     pub id: i32,
     pub block_number: i64,
     pub worker: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// The job is not reassigned to another prover until this moment, see `extend_prover_run_lease`.
+    pub extended_deadline: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, FromRow)]
@@ -46,4 +85,13 @@ pub struct IntegerNumber {
 pub struct StorageBlockWitness {
     pub block: i64,
     pub witness: String,
+    /// Whether the block contains fast withdrawals and should be proved out of turn.
+    pub fast_processing: bool,
+}
+
+/// Block awaiting for proof, as it's ordered in the proving queue.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ProvingQueueEntry {
+    pub block_number: i64,
+    pub fast_processing: bool,
 }
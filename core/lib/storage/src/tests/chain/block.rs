@@ -3,7 +3,7 @@ use web3::types::H256;
 // Workspace imports
 use crypto_exports::{ff::PrimeField, rand::XorShiftRng};
 use models::node::{apply_updates, block::Block, AccountMap, AccountUpdate, BlockNumber, Fr};
-use models::{ethereum::OperationType, fe_to_bytes, Action, Operation};
+use models::{ethereum::OperationType, fe_to_bytes, Action, Operation, OperationBuilder};
 // Local imports
 use super::utils::{acc_create_random_updates, get_operation, get_operation_with_txs};
 use crate::tests::{create_rng, db_test};
@@ -163,10 +163,8 @@ fn get_unique_operation(
     action: Action,
     accounts_updated: Vec<(u32, AccountUpdate)>,
 ) -> Operation {
-    Operation {
-        id: None,
-        action,
-        block: Block::new(
+    OperationBuilder::new()
+        .block(Block::new(
             block_number,
             root_hash_for_block(block_number),
             0,
@@ -175,9 +173,10 @@ fn get_unique_operation(
             100,
             1_000_000.into(),
             1_500_000.into(),
-        ),
-        accounts_updated,
-    }
+        ))
+        .action(action)
+        .accounts_updated(accounts_updated)
+        .build()
 }
 
 /// Checks that `find_block_by_height_or_hash` method allows
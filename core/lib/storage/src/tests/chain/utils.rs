@@ -10,7 +10,7 @@ use models::{
         block::{Block, ExecutedOperations},
         AccountUpdate, BlockNumber, Fr, PubKeyHash,
     },
-    Action, Operation,
+    Action, Operation, OperationBuilder,
 };
 use num::BigUint;
 use std::ops::Deref;
@@ -68,10 +68,8 @@ pub fn get_operation(
     accounts_updated: Vec<(u32, AccountUpdate)>,
     block_size: usize,
 ) -> Operation {
-    Operation {
-        id: None,
-        action,
-        block: Block::new(
+    OperationBuilder::new()
+        .block(Block::new(
             block_number,
             Fr::default(),
             0,
@@ -80,9 +78,10 @@ pub fn get_operation(
             block_size,
             1_000_000.into(),
             1_500_000.into(),
-        ),
-        accounts_updated,
-    }
+        ))
+        .action(action)
+        .accounts_updated(accounts_updated)
+        .build()
 }
 
 pub fn get_operation_with_txs(
@@ -92,10 +91,8 @@ pub fn get_operation_with_txs(
     block_size: usize,
     txs: Vec<ExecutedOperations>,
 ) -> Operation {
-    Operation {
-        id: None,
-        action,
-        block: Block::new(
+    OperationBuilder::new()
+        .block(Block::new(
             block_number,
             Fr::default(),
             0,
@@ -104,9 +101,10 @@ pub fn get_operation_with_txs(
             block_size,
             1_000_000.into(),
             1_500_000.into(),
-        ),
-        accounts_updated,
-    }
+        ))
+        .action(action)
+        .accounts_updated(accounts_updated)
+        .build()
 }
 
 /// Generates EthSignData for testing (not a valid signature)
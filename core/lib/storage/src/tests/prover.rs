@@ -5,9 +5,13 @@ use std::time::Duration;
 use models::{node::block::PendingBlock, Action};
 // Local imports
 use crate::tests::{chain::utils::get_operation, db_test};
-use crate::{chain::block::BlockSchema, prover::ProverSchema, QueryResult, StorageProcessor};
+use crate::{
+    chain::block::BlockSchema,
+    prover::{records::ProverMetadata, ProverSchema},
+    QueryResult, StorageProcessor,
+};
 use models::config_options::ConfigurationOptions;
-use models::prover_utils::EncodedProofPlonk;
+use models::prover_utils::{EncodedProofPlonk, ProvingStats};
 
 /// Checks that the proof can be stored and loaded.
 #[db_test]
@@ -29,6 +33,44 @@ async fn test_store_proof(mut storage: StorageProcessor<'_>) -> QueryResult<()>
     // Now load it.
     let loaded = ProverSchema(&mut storage).load_proof(1).await?;
     assert_eq!(loaded, Some(proof));
+    assert!(ProverSchema(&mut storage)
+        .load_proof_created_at(1)
+        .await?
+        .is_some());
+    assert!(ProverSchema(&mut storage)
+        .load_proof_created_at(2)
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+/// Checks that the statistics of the proof generation are stored along with the proof.
+#[db_test]
+async fn test_store_proof_with_stats(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let stats = ProvingStats {
+        proving_time_secs: Some(12.5),
+        peak_memory_bytes: Some(8 << 30),
+        worker_name: Some("prover_1".to_string()),
+        prover_version: Some("1.0.0".to_string()),
+    };
+    ProverSchema(&mut storage)
+        .store_proof_with_stats(1, &EncodedProofPlonk::default(), &stats)
+        .await?;
+    assert_eq!(
+        ProverSchema(&mut storage).load_proof_stats(1).await?,
+        Some(stats)
+    );
+
+    // Proofs stored without the statistics have them empty.
+    ProverSchema(&mut storage)
+        .store_proof(2, &EncodedProofPlonk::default())
+        .await?;
+    assert_eq!(
+        ProverSchema(&mut storage).load_proof_stats(2).await?,
+        Some(ProvingStats::default())
+    );
+    assert_eq!(ProverSchema(&mut storage).load_proof_stats(3).await?, None);
 
     Ok(())
 }
@@ -42,9 +84,9 @@ async fn prover_registration(mut storage: StorageProcessor<'_>) -> QueryResult<(
 
     // Add the prover.
     let prover_name = "prover_10";
-    let block_size = 10;
+    let block_sizes = vec![10, 32];
     let prover_id = ProverSchema(&mut storage)
-        .register_prover(prover_name, block_size)
+        .register_prover(prover_name, &block_sizes)
         .await?;
 
     // Check that prover is added to the database.
@@ -52,8 +94,14 @@ async fn prover_registration(mut storage: StorageProcessor<'_>) -> QueryResult<(
 
     assert_eq!(prover.id, prover_id);
     assert_eq!(prover.worker, prover_name);
-    assert_eq!(prover.block_size, block_size as i64);
+    assert_eq!(prover.block_sizes, vec![10, 32]);
     assert_eq!(prover.stopped_at, None);
+    assert_eq!(
+        ProverSchema(&mut storage)
+            .prover_block_sizes(prover_name)
+            .await?,
+        Some(block_sizes)
+    );
 
     // Stop the prover.
     ProverSchema(&mut storage)
@@ -63,6 +111,44 @@ async fn prover_registration(mut storage: StorageProcessor<'_>) -> QueryResult<(
     // Check that it has been marked as stopped.
     let prover = ProverSchema(&mut storage).prover_by_id(prover_id).await?;
     assert!(prover.stopped_at.is_some());
+    // Block sizes of the stopped provers are not reported.
+    assert_eq!(
+        ProverSchema(&mut storage)
+            .prover_block_sizes(prover_name)
+            .await?,
+        None
+    );
+
+    Ok(())
+}
+
+/// Checks that the metadata reported by the prover on registration is stored,
+/// and the provers registered without it have none.
+#[db_test]
+async fn prover_registration_metadata(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let metadata = ProverMetadata {
+        hostname: Some("prover-host-1".to_string()),
+        version: Some("0.0.1".to_string()),
+        cpu_cores: Some(16),
+        vk_hash: Some("ab".repeat(32)),
+    };
+    let prover_id = ProverSchema(&mut storage)
+        .register_prover_with_metadata("prover_11", &[10], &metadata)
+        .await?;
+    let prover = ProverSchema(&mut storage).prover_by_id(prover_id).await?;
+    assert_eq!(prover.hostname, metadata.hostname);
+    assert_eq!(prover.version, metadata.version);
+    assert_eq!(prover.cpu_cores, metadata.cpu_cores);
+    assert_eq!(prover.vk_hash, metadata.vk_hash);
+
+    let prover_id = ProverSchema(&mut storage)
+        .register_prover("prover_12", &[10])
+        .await?;
+    let prover = ProverSchema(&mut storage).prover_by_id(prover_id).await?;
+    assert_eq!(prover.hostname, None);
+    assert_eq!(prover.version, None);
+    assert_eq!(prover.cpu_cores, None);
+    assert_eq!(prover.vk_hash, None);
 
     Ok(())
 }
@@ -81,7 +167,7 @@ async fn prover_run(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
     let prover_name = "prover_10";
     let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0]; //smallest block size
     let _prover_id = ProverSchema(&mut storage)
-        .register_prover(prover_name, block_size)
+        .register_prover(prover_name, &[block_size])
         .await?;
 
     // Create a block.
@@ -143,6 +229,359 @@ async fn prover_run(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
     Ok(())
 }
 
+/// Checks that the job with the extended lease is not reassigned to another prover
+/// after the timeout, until the extended deadline passes.
+#[db_test]
+async fn prover_run_lease_extension(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let prover_name = "prover_10";
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    ProverSchema(&mut storage)
+        .register_prover(prover_name, &[block_size])
+        .await?;
+    BlockSchema(&mut storage)
+        .execute_operation(get_operation(1, Action::Commit, Vec::new(), block_size))
+        .await?;
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Can't get a prover run with a block committed");
+    assert_eq!(run.extended_deadline, None);
+
+    ProverSchema(&mut storage)
+        .extend_prover_run_lease(run.id, Duration::from_secs(3600))
+        .await?;
+    let extended = ProverSchema(&mut storage)
+        .get_existing_prover_run(1)
+        .await?
+        .expect("Prover run is not stored");
+    assert!(extended.extended_deadline.expect("Lease is not extended") > extended.updated_at);
+
+    // Heartbeats stop coming, but the lease is still valid.
+    sqlx::query("UPDATE prover_runs SET updated_at = now() - interval '1 hour' WHERE id = $1")
+        .bind(run.id)
+        .execute(storage.conn())
+        .await?;
+    let maybe_run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?;
+    assert!(
+        maybe_run.is_none(),
+        "Job with the extended lease should not be reassigned"
+    );
+
+    // Once the extended deadline passes, the job is reassigned.
+    sqlx::query(
+        "UPDATE prover_runs SET extended_deadline = now() - interval '1 second' WHERE id = $1",
+    )
+    .bind(run.id)
+    .execute(storage.conn())
+    .await?;
+    let maybe_run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?;
+    assert_eq!(maybe_run.map(|run| run.block_number), Some(1));
+
+    Ok(())
+}
+
+/// Checks that all the registered provers are listed, and only the jobs with the recent heartbeats
+/// and without the proof are considered ongoing.
+#[db_test]
+async fn provers_and_ongoing_prover_runs(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let prover_name = "prover_10";
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    let prover_id = ProverSchema(&mut storage)
+        .register_prover(prover_name, &[block_size])
+        .await?;
+    let stopped_prover_id = ProverSchema(&mut storage)
+        .register_prover("prover_11", &[block_size])
+        .await?;
+    ProverSchema(&mut storage)
+        .record_prover_stop(stopped_prover_id)
+        .await?;
+
+    let provers = ProverSchema(&mut storage).load_provers().await?;
+    let listed = |id| provers.iter().find(|prover| prover.id == id);
+    assert_eq!(
+        listed(prover_id).map(|prover| prover.stopped_at),
+        Some(None)
+    );
+    assert!(listed(stopped_prover_id)
+        .and_then(|prover| prover.stopped_at)
+        .is_some());
+
+    for block in 1..=2 {
+        BlockSchema(&mut storage)
+            .execute_operation(get_operation(block, Action::Commit, Vec::new(), block_size))
+            .await?;
+    }
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Can't get a prover run with a block committed");
+    let ongoing = ProverSchema(&mut storage)
+        .ongoing_prover_runs(Duration::from_secs(60))
+        .await?;
+    assert_eq!(
+        ongoing.iter().map(|run| run.id).collect::<Vec<_>>(),
+        vec![run.id]
+    );
+
+    // Job without the heartbeats is abandoned.
+    sqlx::query("UPDATE prover_runs SET updated_at = now() - interval '1 hour' WHERE id = $1")
+        .bind(run.id)
+        .execute(storage.conn())
+        .await?;
+    assert!(ProverSchema(&mut storage)
+        .ongoing_prover_runs(Duration::from_secs(60))
+        .await?
+        .is_empty());
+
+    // Proved job is not ongoing anymore.
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Can't get a prover run with a block committed");
+    ProverSchema(&mut storage)
+        .store_proof(run.block_number as u32, &EncodedProofPlonk::default())
+        .await?;
+    assert!(ProverSchema(&mut storage)
+        .ongoing_prover_runs(Duration::from_secs(60))
+        .await?
+        .iter()
+        .all(|ongoing| ongoing.id != run.id));
+
+    Ok(())
+}
+
+/// Checks that the failed job is recorded and its block is reassigned right away.
+#[db_test]
+async fn prover_job_failure(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let prover_name = "prover_10";
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    ProverSchema(&mut storage)
+        .register_prover(prover_name, &[block_size])
+        .await?;
+    BlockSchema(&mut storage)
+        .execute_operation(get_operation(1, Action::Commit, Vec::new(), block_size))
+        .await?;
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Can't get a prover run with a block committed");
+
+    ProverSchema(&mut storage)
+        .record_prover_job_failure(run.id, "proof verification failed")
+        .await?;
+    let failures = ProverSchema(&mut storage)
+        .load_prover_job_failures(1)
+        .await?;
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].prover_run_id, run.id);
+    assert_eq!(failures[0].worker.as_deref(), Some(prover_name));
+    assert_eq!(failures[0].reason, "proof verification failed");
+    assert_eq!(failures[0].claimed_at, Some(run.created_at));
+
+    // Block is reassigned without waiting for the prover timeout.
+    let new_run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Block of the failed job is not reassigned");
+    assert_eq!(new_run.block_number, 1);
+
+    // Failure of the already removed job is ignored.
+    ProverSchema(&mut storage)
+        .record_prover_job_failure(run.id, "proof verification failed")
+        .await?;
+    assert_eq!(
+        ProverSchema(&mut storage)
+            .load_prover_job_failures(1)
+            .await?
+            .len(),
+        1
+    );
+
+    Ok(())
+}
+
+/// Checks that the block is not assigned to the prover which has failed or timed out on it
+/// the max amount of times, while the other provers still get it.
+#[db_test]
+async fn prover_run_max_attempts(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let (stuck_prover, other_prover) = ("prover_12", "prover_13");
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    for prover_name in &[stuck_prover, other_prover] {
+        ProverSchema(&mut storage)
+            .register_prover(prover_name, &[block_size])
+            .await?;
+    }
+    BlockSchema(&mut storage)
+        .execute_operation(get_operation(1, Action::Commit, Vec::new(), block_size))
+        .await?;
+    let max_attempts = Some(2);
+
+    // The first attempt times out.
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit_with_max_attempts(
+            stuck_prover,
+            Duration::from_secs(1),
+            block_size,
+            max_attempts,
+        )
+        .await?
+        .expect("Can't get a prover run with a block committed");
+    sqlx::query("UPDATE prover_runs SET updated_at = now() - interval '1 hour' WHERE id = $1")
+        .bind(run.id)
+        .execute(storage.conn())
+        .await?;
+
+    // The second attempt fails.
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit_with_max_attempts(
+            stuck_prover,
+            Duration::from_secs(1),
+            block_size,
+            max_attempts,
+        )
+        .await?
+        .expect("Block of the timed out job is not reassigned");
+    ProverSchema(&mut storage)
+        .record_prover_job_failure(run.id, "proof verification failed")
+        .await?;
+    assert_eq!(
+        ProverSchema(&mut storage)
+            .unproved_prover_runs()
+            .await?
+            .len(),
+        1
+    );
+    assert_eq!(
+        ProverSchema(&mut storage)
+            .unproved_prover_job_failures()
+            .await?
+            .len(),
+        1
+    );
+
+    // The block is not assigned to the stuck prover anymore.
+    assert!(ProverSchema(&mut storage)
+        .prover_run_for_next_commit_with_max_attempts(
+            stuck_prover,
+            Duration::from_secs(1),
+            block_size,
+            max_attempts,
+        )
+        .await?
+        .is_none());
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit_with_max_attempts(
+            other_prover,
+            Duration::from_secs(1),
+            block_size,
+            max_attempts,
+        )
+        .await?
+        .expect("Block is not assigned to another prover");
+    assert_eq!(run.block_number, 1);
+
+    ProverSchema(&mut storage)
+        .store_proof(1, &EncodedProofPlonk::default())
+        .await?;
+    assert!(ProverSchema(&mut storage)
+        .unproved_prover_runs()
+        .await?
+        .is_empty());
+    assert!(ProverSchema(&mut storage)
+        .unproved_prover_job_failures()
+        .await?
+        .is_empty());
+
+    Ok(())
+}
+
+/// Checks that the block with fast withdrawals is assigned before the older blocks.
+#[db_test]
+async fn fast_processing_block_priority(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let prover_name = "prover_14";
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    ProverSchema(&mut storage)
+        .register_prover(prover_name, &[block_size])
+        .await?;
+    for block in 1..=2 {
+        BlockSchema(&mut storage)
+            .execute_operation(get_operation(block, Action::Commit, Vec::new(), block_size))
+            .await?;
+    }
+    ProverSchema(&mut storage)
+        .store_witness(1, serde_json::json!(null))
+        .await?;
+    ProverSchema(&mut storage)
+        .store_witness_with_priority(2, serde_json::json!(null), true)
+        .await?;
+
+    let queue: Vec<_> = ProverSchema(&mut storage)
+        .proving_queue()
+        .await?
+        .into_iter()
+        .map(|entry| (entry.block_number, entry.fast_processing))
+        .collect();
+    assert_eq!(queue, vec![(2, true), (1, false)]);
+
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Can't get a prover run with blocks committed");
+    assert_eq!(run.block_number, 2);
+    let run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Older block is not assigned after the fast one");
+    assert_eq!(run.block_number, 1);
+
+    // Proved blocks leave the queue.
+    ProverSchema(&mut storage)
+        .store_proof(2, &EncodedProofPlonk::default())
+        .await?;
+    let queue = ProverSchema(&mut storage).proving_queue().await?;
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue[0].block_number, 1);
+
+    Ok(())
+}
+
+/// Checks that the requeued block is reassigned right away.
+#[db_test]
+async fn requeued_block(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let prover_name = "prover_11";
+    let block_size = ConfigurationOptions::from_env().available_block_chunk_sizes[0];
+    ProverSchema(&mut storage)
+        .register_prover(prover_name, &[block_size])
+        .await?;
+    BlockSchema(&mut storage)
+        .execute_operation(get_operation(1, Action::Commit, Vec::new(), block_size))
+        .await?;
+    ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Can't get a prover run with a block committed");
+    assert!(ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .is_none());
+
+    assert_eq!(ProverSchema(&mut storage).requeue_block(1).await?, 1);
+    let new_run = ProverSchema(&mut storage)
+        .prover_run_for_next_commit(prover_name, Duration::from_secs(1), block_size)
+        .await?
+        .expect("Requeued block is not reassigned");
+    assert_eq!(new_run.block_number, 1);
+
+    // Block without jobs has nothing to requeue.
+    assert_eq!(ProverSchema(&mut storage).requeue_block(2).await?, 0);
+
+    Ok(())
+}
+
 /// Checks that `unstarted_jobs_count` method of schema returns the amount
 /// of blocks for which proof is not generating (or generated) yet.
 #[db_test]
@@ -248,3 +687,33 @@ async fn unstarted_prover_jobs_count(mut storage: StorageProcessor<'_>) -> Query
 
     Ok(())
 }
+
+/// Checks that the removed witness can be stored again.
+#[db_test]
+async fn removed_witness(mut storage: StorageProcessor<'_>) -> QueryResult<()> {
+    let stale = serde_json::json!({ "new_root": "stale" });
+    let fresh = serde_json::json!({ "new_root": "fresh" });
+    ProverSchema(&mut storage)
+        .store_witness(1, stale.clone())
+        .await?;
+    // Stored witness is not overwritten.
+    ProverSchema(&mut storage)
+        .store_witness(1, fresh.clone())
+        .await?;
+    assert_eq!(
+        ProverSchema(&mut storage).get_witness(1).await?,
+        Some(stale)
+    );
+
+    ProverSchema(&mut storage).remove_witness(1).await?;
+    assert!(ProverSchema(&mut storage).get_witness(1).await?.is_none());
+    ProverSchema(&mut storage)
+        .store_witness(1, fresh.clone())
+        .await?;
+    assert_eq!(
+        ProverSchema(&mut storage).get_witness(1).await?,
+        Some(fresh)
+    );
+
+    Ok(())
+}
@@ -134,7 +134,7 @@ impl ScenarioExecutor {
 
         // Create a transport for Ethereum account.
         let (_event_loop_handle, transport) =
-            Http::new(&ctx.options.web3_url).expect("http transport start");
+            Http::new(ctx.options.web3_url()).expect("http transport start");
 
         // Create main account to deposit money from and to return money back later.
         let main_account = TestAccount::from_info(&config.input_account, &transport, &ctx.options);
@@ -548,7 +548,7 @@ pub fn get_testkit_config_from_env() -> TestkitConfig {
     TestkitConfig {
         chain_id: env_config.chain_id,
         gas_price_factor: env_config.gas_price_factor,
-        web3_url: env_config.web3_url,
+        web3_url: env_config.web3_url().to_string(),
     }
 }
 